@@ -1,7 +1,10 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::ffi::OsStr;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -13,15 +16,45 @@ use sha2::{Digest, Sha256};
 use tauri::{Emitter, Manager};
 use tokio::sync::{mpsc, oneshot, Mutex};
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+use tokio_util::sync::CancellationToken;
 
+use crate::acp_core::{self, spawn_agent_process, AgentLaunch, StopReason, TokenUsage};
 use crate::app_config;
+use crate::app_paths;
+use crate::audit_log;
+use crate::context;
+use crate::diff::{self, TextDiff};
+use crate::edit_snapshots;
+use crate::error::{ErrorCode, NeoaiError};
+use crate::folder_state;
+use crate::mentions::{self, ResolvedMention};
+use crate::notifications::{self, NotificationKind};
 use crate::nvim_bridge::{nvim_read_file_for_terminal, nvim_write_file_for_terminal};
+use crate::tmux_control;
 use crate::tmux_runtime;
 
 const CODEX_ACP_VERSION: &str = "0.9.2";
 const CODEX_RELEASES_URL: &str = "https://github.com/zed-industries/codex-acp/releases";
-const DEFAULT_AGENT_PATH: &str = "codex-acp";
+/// Prefix of every pinned asset URL; `agent_download_base_url` replaces it.
+const CODEX_DOWNLOAD_BASE_URL: &str =
+    "https://github.com/zed-industries/codex-acp/releases/download";
+pub(crate) const DEFAULT_AGENT_PATH: &str = "codex-acp";
 const DEFAULT_AGENT_PATH_WINDOWS: &str = "codex-acp.exe";
+const AGENT_RESTART_DELAY: Duration = Duration::from_secs(2);
+const MAX_AGENT_AUTO_RESTARTS: u32 = 3;
+/// An agent that stayed up this long gets a fresh auto-restart budget.
+const AGENT_RESTART_RESET_AFTER: Duration = Duration::from_secs(60);
+/// Entries kept in the in-memory ACP trace.
+const ACP_TRACE_CAPACITY: usize = 200;
+/// Extension request sent to check that the agent still answers; any reply,
+/// including method-not-found, counts.
+const HEALTH_PING_METHOD: &str = "_neoai/ping";
+const HEALTH_PING_INTERVAL: Duration = Duration::from_secs(15);
+/// A ping unanswered for this long marks the agent `Unresponsive`.
+const HEALTH_PING_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time a cancelled session's commands get to exit after Ctrl-C before their
+/// panes are killed.
+const COMMAND_TERMINATE_GRACE: Duration = Duration::from_secs(3);
 
 static CODEX_INSTALL_LOCK: std::sync::OnceLock<tokio::sync::Mutex<()>> = std::sync::OnceLock::new();
 
@@ -47,6 +80,9 @@ pub enum AgentStatus {
     Stopped,
     Starting,
     Running,
+    /// The process is alive but stopped answering health pings. Goes back to
+    /// `Running` once it replies again.
+    Unresponsive,
     Error(String),
 }
 
@@ -65,10 +101,305 @@ pub enum AcpEvent {
         id: String,
         status: String,
     },
+    /// A file edit proposed or applied by a tool call, diffed on the backend.
+    ToolCallDiff {
+        id: String,
+        path: String,
+        diff: TextDiff,
+    },
     Done {
-        stop_reason: String,
+        turn: TurnMetadata,
+        /// How much of the turn's context was new versus already sent.
+        context: ContextReuseStats,
     },
-    Error(String),
+    /// A prompt failed for good. `retryable` errors (rate limits, overload,
+    /// timeouts) were retried `attempts - 1` times before giving up.
+    Error {
+        /// What went wrong, for picking a recovery action.
+        kind: AcpErrorKind,
+        message: String,
+        retryable: bool,
+        attempts: u32,
+        /// Metadata of the failed turn; its stop reason is `Error`.
+        turn: TurnMetadata,
+    },
+    /// A permission request approved without asking because its tool kind
+    /// is listed in `auto_approve_kinds`.
+    PermissionAutoApproved {
+        id: String,
+        title: String,
+        kind: String,
+    },
+}
+
+/// Cause of a failed prompt, serialized as `{ code, ... }`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "code", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AcpErrorKind {
+    /// The agent process or its connection is gone; restarting it helps.
+    AgentUnavailable,
+    /// The agent rejected the request as malformed or unsupported.
+    ProtocolError,
+    /// A tool call the turn depended on failed.
+    ToolCallFailed {
+        id: String,
+    },
+    /// The agent or its model provider wants (new) credentials.
+    PermissionDenied,
+    Timeout,
+    /// Anything else, including rate limits (see `retryable`).
+    Other,
+}
+
+/// How a prompt turn ended, stored with the assistant message it produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TurnMetadata {
+    pub stop_reason: StopReason,
+    /// Wall time from sending the prompt to its response, retries included.
+    pub duration_ms: u64,
+    #[serde(flatten)]
+    pub usage: TokenUsage,
+    /// Tool calls the agent started during the turn.
+    pub tool_calls: u32,
+}
+
+/// Payload of `acp-retrying`: a prompt failed with a retryable error and is
+/// sent again after `delay_ms`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcpRetryingEvent {
+    pub session_id: String,
+    pub terminal_id: Option<String>,
+    /// 1-based number of the retry about to be made.
+    pub attempt: u32,
+    pub max_retries: u32,
+    pub delay_ms: u64,
+    pub error: String,
+}
+
+/// Payload of `acp-command-queue`: a session's commands waiting for a slot
+/// under `agent.max_concurrent_commands`. Sent when a command starts waiting
+/// and when one gets its slot; `drained` is set once none are left waiting.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcpCommandQueueEvent {
+    pub session_id: String,
+    pub terminal_id: String,
+    pub queued: usize,
+    pub running: usize,
+    pub limit: usize,
+    pub drained: bool,
+}
+
+/// Payload of `acp-context-usage`: a session's estimated conversation size
+/// crossed `threshold` percent of `agent.context_window_tokens`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcpContextUsageEvent {
+    pub session_id: String,
+    pub terminal_id: Option<String>,
+    pub tokens: usize,
+    pub limit: usize,
+    pub threshold: usize,
+}
+
+/// Shares of the context window, in percent, that raise `acp-context-usage`.
+const CONTEXT_USAGE_THRESHOLDS: [usize; 3] = [50, 75, 90];
+
+/// Estimated tokens a session's conversation holds: prompts and replies
+/// add up until the agent reports real usage, which replaces the estimate.
+#[derive(Debug, Default)]
+struct ContextWindowUsage {
+    tokens: usize,
+    /// Highest threshold already reported, so each is reported once.
+    reported: usize,
+}
+
+impl ContextWindowUsage {
+    fn add(&mut self, tokens: usize, limit: usize) -> Option<usize> {
+        self.tokens += tokens;
+        self.crossed(limit)
+    }
+
+    /// A turn's reported input covers the whole history sent to the model.
+    fn set_reported(&mut self, usage: TokenUsage, limit: usize) -> Option<usize> {
+        if let Some(input) = usage.input_tokens {
+            self.tokens = (input + usage.output_tokens.unwrap_or(0)) as usize;
+        }
+        self.crossed(limit)
+    }
+
+    /// The threshold newly reached, if any. Dropping back below one (the
+    /// agent compacted its history) lets it be reported again.
+    fn crossed(&mut self, limit: usize) -> Option<usize> {
+        let percent = self.tokens.saturating_mul(100) / limit.max(1);
+        let reached = CONTEXT_USAGE_THRESHOLDS
+            .iter()
+            .copied()
+            .filter(|threshold| percent >= *threshold)
+            .max()
+            .unwrap_or(0);
+        let newly = (reached > self.reported).then_some(reached);
+        self.reported = reached;
+        newly
+    }
+}
+
+type SessionContextUsage = Rc<RefCell<std::collections::HashMap<String, ContextWindowUsage>>>;
+
+/// Applies `update` to `session_id`'s usage and emits `acp-context-usage`
+/// when it crosses a threshold. Does nothing with the window disabled.
+fn track_context_usage(
+    app_handle: &tauri::AppHandle,
+    usage: &SessionContextUsage,
+    scope: &AcpEventScope,
+    update: impl FnOnce(&mut ContextWindowUsage, usize) -> Option<usize>,
+) {
+    let Some(session_id) = scope.session_id.clone() else {
+        return;
+    };
+    let Some(limit) = app_handle
+        .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+        .and_then(|state| {
+            state
+                .lock()
+                .ok()
+                .and_then(|state| state.context_window_tokens())
+        })
+    else {
+        return;
+    };
+    let (threshold, tokens) = {
+        let mut usage = usage.borrow_mut();
+        let entry = usage.entry(session_id.clone()).or_default();
+        (update(entry, limit), entry.tokens)
+    };
+    if let Some(threshold) = threshold {
+        let _ = app_handle.emit(
+            "acp-context-usage",
+            &AcpContextUsageEvent {
+                session_id,
+                terminal_id: scope.terminal_id.clone(),
+                tokens,
+                limit,
+                threshold,
+            },
+        );
+    }
+}
+
+/// Per-turn accounting of context blocks (context sections and mentions).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextReuseStats {
+    pub sent_blocks: usize,
+    pub reused_blocks: usize,
+    /// Estimated tokens of the blocks sent this turn.
+    pub sent_tokens: usize,
+    /// Estimated tokens saved by leaving out blocks the agent already has.
+    pub reused_tokens: usize,
+}
+
+impl ContextReuseStats {
+    fn add(&mut self, other: &ContextReuseStats) {
+        self.sent_blocks += other.sent_blocks;
+        self.reused_blocks += other.reused_blocks;
+        self.sent_tokens += other.sent_tokens;
+        self.reused_tokens += other.reused_tokens;
+    }
+}
+
+/// Hashes of the context blocks each session has already received, so a
+/// block sent unchanged on an earlier turn can be left out of the next one.
+#[derive(Default)]
+struct ContextLedger {
+    sent: std::collections::HashMap<String, std::collections::HashSet<u64>>,
+}
+
+/// Blocks to include this turn, with the hashes to record once the prompt
+/// goes through.
+struct PlannedContext<T> {
+    blocks: Vec<T>,
+    hashes: Vec<u64>,
+    stats: ContextReuseStats,
+}
+
+impl ContextLedger {
+    fn block_hash(text: &str) -> u64 {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        text.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Picks which of `blocks` (keyed by their text) to send. With `reuse`
+    /// off every block is sent.
+    fn plan<T>(
+        &self,
+        session_id: &str,
+        blocks: Vec<(String, T)>,
+        reuse: bool,
+    ) -> PlannedContext<T> {
+        let sent = self.sent.get(session_id);
+        let mut planned = PlannedContext {
+            blocks: Vec::new(),
+            hashes: Vec::new(),
+            stats: ContextReuseStats::default(),
+        };
+        for (key, block) in blocks {
+            let hash = Self::block_hash(&key);
+            let tokens = context::estimate_tokens(&key);
+            let already_sent =
+                sent.is_some_and(|sent| sent.contains(&hash)) || planned.hashes.contains(&hash);
+            if reuse && already_sent {
+                planned.stats.reused_blocks += 1;
+                planned.stats.reused_tokens += tokens;
+                continue;
+            }
+            planned.stats.sent_blocks += 1;
+            planned.stats.sent_tokens += tokens;
+            planned.hashes.push(hash);
+            planned.blocks.push(block);
+        }
+        planned
+    }
+
+    fn record(&mut self, session_id: &str, hashes: Vec<u64>) {
+        self.sent
+            .entry(session_id.to_string())
+            .or_default()
+            .extend(hashes);
+    }
+}
+
+/// The conversation an event belongs to. Both are `None` only when the
+/// session is unknown to NeoAI.
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AcpEventScope {
+    pub session_id: Option<String>,
+    pub terminal_id: Option<String>,
+}
+
+/// What `acp-event` and `acp-event-batch` carry: the event's `type`/`data`
+/// alongside its `sessionId` and `terminalId`.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScopedAcpEvent {
+    #[serde(flatten)]
+    pub scope: AcpEventScope,
+    #[serde(flatten)]
+    pub event: AcpEvent,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AcpTraceEntry {
+    pub timestamp_ms: u64,
+    /// `in` (from the agent), `out` (to the agent) or `local`.
+    pub direction: String,
+    pub kind: String,
+    pub detail: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -77,6 +408,9 @@ pub struct AcpInstallStatusEvent {
     pub phase: String,
     pub message: String,
     pub version: Option<String>,
+    /// Where the archive is fetched from, on `downloading` and `verifying`;
+    /// shows whether a configured mirror was used.
+    pub download_url: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -97,16 +431,69 @@ pub struct AcpPermissionRequestEvent {
     pub title: Option<String>,
     pub kind: Option<String>,
     pub options: Vec<AcpPermissionOption>,
+    /// Shell command the tool call would run, as one line.
+    pub command: Option<String>,
+    /// Directory the command would run in.
+    pub cwd: Option<String>,
+    /// Files the tool call reads or changes.
+    pub paths: Vec<String>,
+    /// Proposed file changes, diffed like `ToolCallDiff` events.
+    pub diffs: Vec<AcpPermissionDiff>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AcpPermissionDiff {
+    pub path: String,
+    pub diff: TextDiff,
 }
 
-type PendingPermissionRequests =
-    Arc<Mutex<std::collections::HashMap<String, oneshot::Sender<acp::RequestPermissionOutcome>>>>;
+/// A permission request waiting for the user's choice.
+struct PendingPermission {
+    /// Session the request came from, so cancelling its turn can answer it.
+    session_id: String,
+    reply: oneshot::Sender<acp::RequestPermissionOutcome>,
+}
+
+type PendingPermissionRequests = Arc<Mutex<std::collections::HashMap<String, PendingPermission>>>;
 type SessionTerminalBindings = Arc<Mutex<std::collections::HashMap<String, String>>>;
+/// Reply text of sessions NeoAI prompts for itself (e.g. conversation
+/// summaries). Their chunks are collected here instead of reaching the chat.
+type CapturedOutput = Arc<Mutex<std::collections::HashMap<String, String>>>;
+/// Terminals whose events are also emitted on `acp-event/<terminalId>` and
+/// `acp-event-batch/<terminalId>`.
+type TerminalChannels = Arc<std::sync::Mutex<std::collections::HashSet<String>>>;
+/// Stream options sessions set with `acp_set_stream_options`; sessions
+/// missing here follow `[agent] stream_thoughts`.
+type SessionStreamOptions = Rc<RefCell<std::collections::HashMap<String, StreamOptions>>>;
+/// Held by a session's running prompt turn, so the session's next prompt
+/// waits for it while other sessions' prompts go ahead.
+type SessionTurnLocks = Rc<RefCell<std::collections::HashMap<String, Rc<Mutex<()>>>>>;
+/// Cancelled by `AcpCommand::Cancel` to end a session's running turn, even
+/// while it waits to retry.
+type SessionTurnCancellations = Rc<RefCell<std::collections::HashMap<String, CancellationToken>>>;
+
+/// What a session streams to the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamOptions {
+    /// Forward the agent's reasoning as `thought_chunk` events.
+    pub thoughts: bool,
+}
 
 // -- Channel-based communication with the !Send ACP connection --
 
 enum AcpCommand {
     CreateSession {
+        working_dir: PathBuf,
+        terminal_id: String,
+        /// Model to switch the new session to.
+        model: Option<String>,
+        reply: oneshot::Sender<Result<String, String>>,
+    },
+    /// Reopens a session from an earlier run, if the agent supports it.
+    LoadSession {
+        session_id: String,
         working_dir: PathBuf,
         terminal_id: String,
         reply: oneshot::Sender<Result<String, String>>,
@@ -114,17 +501,577 @@ enum AcpCommand {
     Prompt {
         session_id: String,
         messages: Vec<String>,
-        context: Option<String>,
+        /// Context sections, each a block the agent may already have.
+        context: Vec<String>,
+        /// `@file` and `#symbol` mentions expanded from `messages`.
+        mentions: Vec<ResolvedMention>,
+        reply: oneshot::Sender<Result<String, String>>,
+    },
+    /// One-off prompt in a fresh session whose reply text is returned
+    /// rather than streamed to the UI.
+    CapturedPrompt {
+        working_dir: PathBuf,
+        prompt: String,
         reply: oneshot::Sender<Result<String, String>>,
     },
+    SetStreamOptions {
+        session_id: String,
+        options: StreamOptions,
+    },
+    /// Asks the agent to stop the session's current turn.
+    Cancel {
+        session_id: String,
+    },
     Shutdown,
 }
 
+/// Coalesces streamed chunks into one `acp-event-batch` emission per frame so
+/// fast generations don't flood IPC. Any other event flushes the pending
+/// chunks first, keeping the order the agent sent them in.
+struct AcpEventBus {
+    app_handle: tauri::AppHandle,
+    /// `None` emits every chunk as its own `acp-event`.
+    frame: Option<Duration>,
+    pending: RefCell<Vec<ScopedAcpEvent>>,
+    terminal_channels: TerminalChannels,
+    /// Tool calls started per session since the last [`Self::take_tool_calls`].
+    tool_calls: RefCell<std::collections::HashMap<String, u32>>,
+    /// Last tool call per session reported as failed, until the turn ends.
+    failed_tool_calls: RefCell<std::collections::HashMap<String, String>>,
+    /// Sessions that streamed content or started a tool call since the last
+    /// [`Self::take_turn_output`]; their failed turns are not sent again.
+    turn_output: RefCell<std::collections::HashSet<String>>,
+}
+
+impl AcpEventBus {
+    fn emit(self: &Rc<Self>, scope: AcpEventScope, event: AcpEvent) {
+        if let (
+            AcpEvent::ContentChunk(_)
+            | AcpEvent::ThoughtChunk(_)
+            | AcpEvent::ToolCallStarted { .. },
+            Some(session_id),
+        ) = (&event, &scope.session_id)
+        {
+            self.turn_output.borrow_mut().insert(session_id.clone());
+        }
+        if let (AcpEvent::ToolCallStarted { .. }, Some(session_id)) = (&event, &scope.session_id) {
+            *self
+                .tool_calls
+                .borrow_mut()
+                .entry(session_id.clone())
+                .or_default() += 1;
+        }
+        if let (AcpEvent::ToolCallUpdated { id, status }, Some(session_id)) =
+            (&event, &scope.session_id)
+        {
+            if status == "Failed" {
+                self.failed_tool_calls
+                    .borrow_mut()
+                    .insert(session_id.clone(), id.clone());
+            }
+        }
+        let event = ScopedAcpEvent { scope, event };
+        let Some(frame) = self.frame else {
+            self.send(&event);
+            return;
+        };
+        if !matches!(
+            event.event,
+            AcpEvent::ContentChunk(_) | AcpEvent::ThoughtChunk(_)
+        ) {
+            self.flush();
+            self.send(&event);
+            return;
+        }
+
+        let schedule_flush = {
+            let mut pending = self.pending.borrow_mut();
+            let was_empty = pending.is_empty();
+            push_coalesced(&mut pending, event);
+            was_empty
+        };
+        if schedule_flush {
+            let bus = self.clone();
+            tokio::task::spawn_local(async move {
+                tokio::time::sleep(frame).await;
+                bus.flush();
+            });
+        }
+    }
+
+    /// Whether the session streamed anything since the last call.
+    fn take_turn_output(&self, session_id: &str) -> bool {
+        self.turn_output.borrow_mut().remove(session_id)
+    }
+
+    fn take_failed_tool_call(&self, session_id: &str) -> Option<String> {
+        self.failed_tool_calls.borrow_mut().remove(session_id)
+    }
+
+    fn take_tool_calls(&self, session_id: &str) -> u32 {
+        self.tool_calls
+            .borrow_mut()
+            .remove(session_id)
+            .unwrap_or_default()
+    }
+
+    fn send(&self, event: &ScopedAcpEvent) {
+        trace_acp_event(&event.event);
+        let _ = self.app_handle.emit("acp-event", event);
+        if let Some(terminal_id) = self.channel_for(&event.scope) {
+            let _ = self
+                .app_handle
+                .emit(&format!("acp-event/{terminal_id}"), event);
+        }
+    }
+
+    fn flush(&self) {
+        let events = std::mem::take(&mut *self.pending.borrow_mut());
+        if events.is_empty() {
+            return;
+        }
+        events
+            .iter()
+            .for_each(|event| trace_acp_event(&event.event));
+        let _ = self.app_handle.emit("acp-event-batch", &events);
+
+        let mut by_terminal: std::collections::BTreeMap<&str, Vec<&ScopedAcpEvent>> =
+            Default::default();
+        for event in &events {
+            if let Some(terminal_id) = self.channel_for(&event.scope) {
+                by_terminal.entry(terminal_id).or_default().push(event);
+            }
+        }
+        for (terminal_id, events) in by_terminal {
+            let _ = self
+                .app_handle
+                .emit(&format!("acp-event-batch/{terminal_id}"), &events);
+        }
+    }
+
+    /// The scope's terminal, when something subscribed to its own channel.
+    fn channel_for<'a>(&self, scope: &'a AcpEventScope) -> Option<&'a str> {
+        let terminal_id = scope.terminal_id.as_deref()?;
+        let channels = self.terminal_channels.lock().ok()?;
+        channels.contains(terminal_id).then_some(terminal_id)
+    }
+}
+
+/// Appends `event` to a batch, merging it into the last entry when both are
+/// chunks of the same kind for the same session.
+fn push_coalesced(pending: &mut Vec<ScopedAcpEvent>, event: ScopedAcpEvent) {
+    if let Some(last) = pending.last_mut().filter(|last| last.scope == event.scope) {
+        match (&mut last.event, event.event) {
+            (AcpEvent::ContentChunk(text), AcpEvent::ContentChunk(more))
+            | (AcpEvent::ThoughtChunk(text), AcpEvent::ThoughtChunk(more)) => {
+                text.push_str(&more);
+            }
+            (_, other) => pending.push(ScopedAcpEvent {
+                scope: event.scope,
+                event: other,
+            }),
+        }
+        return;
+    }
+    pending.push(event);
+}
+
+/// Extension method that lets the agent read the chat terminal's screen when
+/// `agent.allow_terminal_snapshot` is on and the user approves.
+const TERMINAL_SNAPSHOT_METHOD: &str = "_neoai/terminal_snapshot";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TerminalSnapshotParams {
+    session_id: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TerminalSnapshot {
+    terminal_id: String,
+    text: String,
+}
+
 struct AcpClientHandler {
     app_handle: tauri::AppHandle,
+    events: Rc<AcpEventBus>,
+    terminal_snapshot_enabled: bool,
     pending_permission_requests: PendingPermissionRequests,
     permission_request_counter: Arc<AtomicU64>,
     session_terminal_bindings: SessionTerminalBindings,
+    captured_output: CapturedOutput,
+    stream_options: SessionStreamOptions,
+    context_usage: SessionContextUsage,
+}
+
+impl AcpClientHandler {
+    /// Whether `session_id`'s thought chunks reach the UI.
+    fn streams_thoughts(&self, session_id: &str) -> bool {
+        if let Some(options) = self.stream_options.borrow().get(session_id) {
+            return options.thoughts;
+        }
+        self.app_handle
+            .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+            .and_then(|state| state.lock().ok().map(|state| state.stream_thoughts()))
+            .unwrap_or(true)
+    }
+
+    /// Shows `permission_event` in the UI and waits up to five minutes for the
+    /// user's choice; a timeout counts as cancelled.
+    async fn ask_permission(
+        &self,
+        permission_event: AcpPermissionRequestEvent,
+    ) -> acp::Result<acp::RequestPermissionOutcome> {
+        let request_id = permission_event.request_id.clone();
+        record_trace(
+            "in",
+            "permission_request",
+            format!(
+                "{} ({})",
+                permission_event.title.as_deref().unwrap_or("untitled"),
+                permission_event.request_id
+            ),
+        );
+        let (decision_tx, decision_rx) = oneshot::channel::<acp::RequestPermissionOutcome>();
+        self.pending_permission_requests.lock().await.insert(
+            request_id.clone(),
+            PendingPermission {
+                session_id: permission_event.session_id.clone(),
+                reply: decision_tx,
+            },
+        );
+
+        if let Err(err) = self
+            .app_handle
+            .emit("acp-permission-request", &permission_event)
+        {
+            self.pending_permission_requests
+                .lock()
+                .await
+                .remove(&request_id);
+            return Err(acp::Error::internal_error().data(err.to_string()));
+        }
+        notifications::notify(
+            &self.app_handle,
+            NotificationKind::PermissionRequest,
+            "Permission requested",
+            permission_event
+                .title
+                .as_deref()
+                .unwrap_or("The agent is waiting for your approval."),
+        );
+
+        let outcome = match tokio::time::timeout(Duration::from_secs(300), decision_rx).await {
+            Ok(Ok(outcome)) => outcome,
+            _ => {
+                self.pending_permission_requests
+                    .lock()
+                    .await
+                    .remove(&request_id);
+                acp::RequestPermissionOutcome::Cancelled
+            }
+        };
+        Ok(outcome)
+    }
+
+    /// Shows an auto-approved permission request in the chat and records it
+    /// in the audit log, so approving without asking never goes unseen.
+    async fn note_auto_approval(&self, permission_event: &AcpPermissionRequestEvent) {
+        let kind = permission_event.kind.clone().unwrap_or_default();
+        let title = permission_event
+            .title
+            .clone()
+            .or_else(|| permission_event.command.clone())
+            .unwrap_or_else(|| "untitled".to_string());
+        log::info!(
+            "Auto-approved {} permission request in session '{}': {}",
+            kind,
+            permission_event.session_id,
+            title
+        );
+        audit_log::record_auto_approval(
+            &self.app_handle,
+            permission_event.terminal_id.as_deref().unwrap_or_default(),
+            &permission_event.session_id,
+            format!(
+                "{kind}: {}",
+                permission_event.command.as_deref().unwrap_or(&title)
+            ),
+            permission_event.cwd.clone(),
+        );
+        let scope = event_scope(
+            &self.session_terminal_bindings,
+            &permission_event.session_id,
+        )
+        .await;
+        self.events.emit(
+            scope,
+            AcpEvent::PermissionAutoApproved {
+                id: permission_event.tool_call_id.clone(),
+                title,
+                kind,
+            },
+        );
+    }
+
+    /// Answers `_neoai/terminal_snapshot` once the user approves: the visible
+    /// screen of the tmux session hosted by the chat's terminal.
+    async fn terminal_snapshot(&self, session_id: String) -> acp::Result<TerminalSnapshot> {
+        let terminal_id = {
+            let bindings = self.session_terminal_bindings.lock().await;
+            bindings.get(&session_id).cloned()
+        }
+        .ok_or_else(|| {
+            acp::Error::invalid_params().data(serde_json::json!({
+                "reason": "session is not bound to a terminal",
+                "sessionId": session_id
+            }))
+        })?;
+
+        let permission_event = AcpPermissionRequestEvent {
+            request_id: self.next_permission_request_id(),
+            session_id,
+            terminal_id: Some(terminal_id.clone()),
+            tool_call_id: TERMINAL_SNAPSHOT_METHOD.to_string(),
+            title: Some("Read the contents of your terminal".to_string()),
+            kind: Some("Read".to_string()),
+            options: vec![
+                AcpPermissionOption {
+                    option_id: "allow".to_string(),
+                    name: "Allow".to_string(),
+                    kind: "AllowOnce".to_string(),
+                },
+                AcpPermissionOption {
+                    option_id: "reject".to_string(),
+                    name: "Reject".to_string(),
+                    kind: "RejectOnce".to_string(),
+                },
+            ],
+            command: None,
+            cwd: None,
+            paths: Vec::new(),
+            diffs: Vec::new(),
+        };
+        let approved = matches!(
+            self.ask_permission(permission_event).await?,
+            acp::RequestPermissionOutcome::Selected(selected) if selected.option_id.to_string() == "allow"
+        );
+        if !approved {
+            return Err(
+                acp::Error::invalid_request().data("The user declined the terminal snapshot")
+            );
+        }
+
+        let session_name = self
+            .app_handle
+            .state::<Mutex<tmux_runtime::TmuxRuntimeState>>()
+            .lock()
+            .await
+            .session_name(&terminal_id)
+            .ok_or_else(|| {
+                acp::Error::internal_error()
+                    .data("The terminal is not running tmux, so its screen can't be read")
+            })?;
+        let text = tmux_runtime::pane_output(&session_name)
+            .await
+            .map_err(|e| acp::Error::internal_error().data(e))?;
+        record_trace(
+            "out",
+            "terminal_snapshot",
+            format!("{terminal_id}: {} bytes", text.len()),
+        );
+        Ok(TerminalSnapshot { terminal_id, text })
+    }
+
+    /// Directory an agent command runs in. A requested `cwd`, relative to the
+    /// session's directory, must resolve inside the session's project folder
+    /// or a directory from `agent.command_cwd_allow`; without one the command
+    /// runs in the session's directory.
+    async fn command_cwd(
+        &self,
+        session_id: &str,
+        requested: Option<PathBuf>,
+        allowed_dirs: Vec<PathBuf>,
+    ) -> acp::Result<Option<PathBuf>> {
+        let session_cwd = self
+            .app_handle
+            .state::<Mutex<AcpClientState>>()
+            .lock()
+            .await
+            .session_cwds
+            .get(session_id)
+            .cloned();
+        let Some(requested) = requested else {
+            return Ok(session_cwd);
+        };
+        let reject = |reason: String, roots: &[PathBuf]| {
+            acp::Error::invalid_params().data(serde_json::json!({
+                "reason": reason,
+                "cwd": requested,
+                "allowedRoots": roots,
+                "sessionId": session_id
+            }))
+        };
+        let path = resolve_command_cwd(&requested, session_cwd.as_deref())
+            .map_err(|reason| reject(reason, &[]))?;
+
+        let mut roots: Vec<PathBuf> = allowed_dirs
+            .into_iter()
+            .map(|dir| fs::canonicalize(&dir).unwrap_or(dir))
+            .collect();
+        // Without a session directory any registered folder is a project.
+        let anchor = session_cwd.as_deref().unwrap_or(&path);
+        let project_root = self
+            .app_handle
+            .state::<std::sync::Mutex<folder_state::FolderStateStore>>()
+            .lock()
+            .ok()
+            .and_then(|folders| {
+                let folder_id = folders.folder_containing(anchor)?;
+                folders.folder_path(&folder_id)
+            })
+            .map(|root| fs::canonicalize(&root).unwrap_or(root))
+            .or(session_cwd);
+        roots.extend(project_root);
+
+        if !roots.iter().any(|root| path.starts_with(root)) {
+            log::warn!(
+                "Rejected agent command cwd '{}' for session {}: outside {:?}",
+                path.display(),
+                session_id,
+                roots
+            );
+            return Err(reject(
+                format!("cwd '{}' is outside the project", path.display()),
+                &roots,
+            ));
+        }
+        Ok(Some(path))
+    }
+
+    /// Saves a command's full output under `command-logs/` once it passes
+    /// `agent.command_output_spill_bytes`, returning the file's path.
+    fn spill_command_output(
+        &self,
+        command_id: &str,
+        command: &tmux_runtime::ManagedTmuxCommand,
+        output: &str,
+    ) -> Option<PathBuf> {
+        let threshold = self
+            .app_handle
+            .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+            .and_then(|state| {
+                state
+                    .lock()
+                    .ok()
+                    .map(|state| state.agent_config().command_output_spill_bytes)
+            })
+            .unwrap_or(256 * 1024);
+        if threshold == 0 || (output.len() as u64) <= threshold {
+            return None;
+        }
+        let spilled = app_paths::app_root_dir(&self.app_handle).and_then(|root| {
+            let path = tmux_runtime::command_log_path(
+                &root.join(tmux_runtime::COMMAND_LOG_DIR_NAME),
+                command_id,
+                command.started_at_ms,
+            );
+            tmux_runtime::spill_output(&path, output).map(|()| path)
+        });
+        match spilled {
+            Ok(path) => Some(path),
+            Err(err) => {
+                log::warn!("Failed to save output of {}: {}", command_id, err);
+                None
+            }
+        }
+    }
+
+    /// Takes one of the session's command slots, waiting for one to free up
+    /// when `agent.queue_excess_commands` is on and rejecting the command
+    /// otherwise.
+    async fn reserve_command_slot(
+        &self,
+        session_id: &str,
+        host_terminal_id: &str,
+    ) -> acp::Result<()> {
+        let (limit, queue) = self
+            .app_handle
+            .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+            .and_then(|state| {
+                state.lock().ok().map(|state| {
+                    let agent = state.agent_config();
+                    (
+                        agent.max_concurrent_commands as usize,
+                        agent.queue_excess_commands,
+                    )
+                })
+            })
+            .unwrap_or((4, true));
+        let tmux_state = self
+            .app_handle
+            .state::<Mutex<tmux_runtime::TmuxRuntimeState>>();
+        let emit_queue = |queued: usize, running: usize| {
+            let _ = self.app_handle.emit(
+                "acp-command-queue",
+                &AcpCommandQueueEvent {
+                    session_id: session_id.to_string(),
+                    terminal_id: host_terminal_id.to_string(),
+                    queued,
+                    running,
+                    limit,
+                    drained: queued == 0,
+                },
+            );
+        };
+
+        let slot_freed = tmux_state.lock().await.command_slot_freed();
+        let mut waiting = false;
+        loop {
+            // Registered before the slots are counted, so a slot freed in
+            // between still wakes this wait.
+            let notified = slot_freed.notified();
+            {
+                let mut state = tmux_state.lock().await;
+                if state.try_reserve_command_slot(session_id, limit) {
+                    if waiting {
+                        let queued = state.dequeue_command(session_id);
+                        emit_queue(queued, state.active_commands(session_id));
+                    }
+                    return Ok(());
+                }
+                let running = state.active_commands(session_id);
+                if !queue {
+                    return Err(acp::Error::invalid_request().data(serde_json::json!({
+                        "reason": "concurrent command limit reached",
+                        "limit": limit,
+                        "running": running,
+                        "sessionId": session_id
+                    })));
+                }
+                if !waiting {
+                    waiting = true;
+                    let queued = state.enqueue_command(session_id);
+                    log::info!(
+                        "Queueing command for session {} ({} running, limit {})",
+                        session_id,
+                        running,
+                        limit
+                    );
+                    emit_queue(queued, running);
+                }
+            }
+            notified.await;
+        }
+    }
+
+    fn next_permission_request_id(&self) -> String {
+        let request_number = self
+            .permission_request_counter
+            .fetch_add(1, Ordering::Relaxed);
+        format!("perm-{}", request_number)
+    }
 }
 
 #[async_trait::async_trait(?Send)]
@@ -133,23 +1080,55 @@ impl acp::Client for AcpClientHandler {
         &self,
         args: acp::RequestPermissionRequest,
     ) -> acp::Result<acp::RequestPermissionResponse> {
-        let request_number = self
-            .permission_request_counter
-            .fetch_add(1, Ordering::Relaxed);
-        let request_id = format!("perm-{}", request_number);
+        let request_id = self.next_permission_request_id();
         let session_id = args.session_id.to_string();
         let terminal_id = {
             let bindings = self.session_terminal_bindings.lock().await;
             bindings.get(&session_id).cloned()
         };
 
+        let fields = &args.tool_call.fields;
+        let raw_input = fields.raw_input.as_ref();
+        let mut paths: Vec<String> = fields
+            .locations
+            .iter()
+            .flatten()
+            .map(|location| location.path.to_string_lossy().into_owned())
+            .chain(raw_input.map(permission_paths).unwrap_or_default())
+            .collect();
+        let mut diffs = Vec::new();
+        for item in fields.content.iter().flatten() {
+            let acp::ToolCallContent::Diff(diff) = item else {
+                continue;
+            };
+            let path = diff.path.to_string_lossy().into_owned();
+            let original = diff.old_text.clone().unwrap_or_default();
+            let modified = diff.new_text.clone();
+            match tauri::async_runtime::spawn_blocking(move || {
+                diff::compute_diff(&original, &modified)
+            })
+            .await
+            {
+                Ok(text_diff) => {
+                    paths.push(path.clone());
+                    diffs.push(AcpPermissionDiff {
+                        path,
+                        diff: text_diff,
+                    });
+                }
+                Err(err) => log::warn!("Failed to diff permission request {}: {}", path, err),
+            }
+        }
+        let mut seen = std::collections::HashSet::new();
+        paths.retain(|path| seen.insert(path.clone()));
+
         let permission_event = AcpPermissionRequestEvent {
-            request_id: request_id.clone(),
+            request_id,
             session_id,
             terminal_id,
             tool_call_id: args.tool_call.tool_call_id.to_string(),
-            title: args.tool_call.fields.title.clone(),
-            kind: args.tool_call.fields.kind.map(|kind| format!("{:?}", kind)),
+            title: fields.title.clone(),
+            kind: fields.kind.map(|kind| format!("{:?}", kind)),
             options: args
                 .options
                 .iter()
@@ -159,68 +1138,116 @@ impl acp::Client for AcpClientHandler {
                     kind: format!("{:?}", option.kind),
                 })
                 .collect(),
+            command: raw_input.and_then(permission_command),
+            cwd: raw_input.and_then(|input| {
+                ["cwd", "workdir"]
+                    .iter()
+                    .find_map(|key| input.get(key).and_then(|value| value.as_str()))
+                    .map(str::to_string)
+            }),
+            paths,
+            diffs,
         };
 
-        let (decision_tx, decision_rx) = oneshot::channel::<acp::RequestPermissionOutcome>();
-        self.pending_permission_requests
-            .lock()
-            .await
-            .insert(request_id.clone(), decision_tx);
-
-        if let Err(err) = self
+        let auto_approve_kinds = self
             .app_handle
-            .emit("acp-permission-request", &permission_event)
+            .state::<std::sync::Mutex<app_config::AppConfigState>>()
+            .lock()
+            .map(|state| state.auto_approve_kinds())
+            .unwrap_or_default();
+        if let Some(option_id) =
+            auto_approve_option(fields.kind, &args.options, &auto_approve_kinds)
         {
-            self.pending_permission_requests
-                .lock()
-                .await
-                .remove(&request_id);
-            return Err(acp::Error::internal_error().data(err.to_string()));
+            self.note_auto_approval(&permission_event).await;
+            return Ok(acp::RequestPermissionResponse::new(
+                acp::RequestPermissionOutcome::Selected(acp::SelectedPermissionOutcome::new(
+                    option_id,
+                )),
+            ));
         }
 
-        let outcome = match tokio::time::timeout(Duration::from_secs(300), decision_rx).await {
-            Ok(Ok(outcome)) => outcome,
-            _ => {
-                self.pending_permission_requests
-                    .lock()
-                    .await
-                    .remove(&request_id);
-                acp::RequestPermissionOutcome::Cancelled
-            }
-        };
-
+        let outcome = self.ask_permission(permission_event).await?;
         Ok(acp::RequestPermissionResponse::new(outcome))
     }
 
     async fn session_notification(&self, args: acp::SessionNotification) -> acp::Result<()> {
+        {
+            let mut captured = self.captured_output.lock().await;
+            if let Some(text) = captured.get_mut(&args.session_id.to_string()) {
+                if let acp::SessionUpdate::AgentMessageChunk(chunk) = args.update {
+                    if let acp::ContentBlock::Text(chunk_text) = chunk.content {
+                        text.push_str(&chunk_text.text);
+                    }
+                }
+                return Ok(());
+            }
+        }
+
+        let scope = event_scope(
+            &self.session_terminal_bindings,
+            &args.session_id.to_string(),
+        )
+        .await;
         let event = match args.update {
             acp::SessionUpdate::AgentMessageChunk(chunk) => {
                 if let acp::ContentBlock::Text(text) = chunk.content {
+                    let tokens = context::estimate_tokens(&text.text);
+                    track_context_usage(
+                        &self.app_handle,
+                        &self.context_usage,
+                        &scope,
+                        |usage, limit| usage.add(tokens, limit),
+                    );
                     AcpEvent::ContentChunk(text.text)
                 } else {
                     return Ok(());
                 }
             }
             acp::SessionUpdate::AgentThoughtChunk(chunk) => {
+                if !self.streams_thoughts(&args.session_id.to_string()) {
+                    return Ok(());
+                }
                 if let acp::ContentBlock::Text(text) = chunk.content {
                     AcpEvent::ThoughtChunk(text.text)
                 } else {
                     return Ok(());
                 }
             }
-            acp::SessionUpdate::ToolCall(tool_call) => AcpEvent::ToolCallStarted {
-                id: tool_call.tool_call_id.to_string(),
-                title: tool_call.title,
-                kind: format!("{:?}", tool_call.kind),
-            },
-            acp::SessionUpdate::ToolCallUpdate(update) => AcpEvent::ToolCallUpdated {
-                id: update.tool_call_id.to_string(),
-                status: "updated".to_string(),
-            },
+            acp::SessionUpdate::ToolCall(tool_call) => {
+                let id = tool_call.tool_call_id.to_string();
+                self.events.emit(
+                    scope.clone(),
+                    AcpEvent::ToolCallStarted {
+                        id: id.clone(),
+                        title: tool_call.title,
+                        kind: format!("{:?}", tool_call.kind),
+                    },
+                );
+                emit_tool_call_diffs(&self.events, &scope, &id, &tool_call.content).await;
+                return Ok(());
+            }
+            acp::SessionUpdate::ToolCallUpdate(update) => {
+                let id = update.tool_call_id.to_string();
+                self.events.emit(
+                    scope.clone(),
+                    AcpEvent::ToolCallUpdated {
+                        id: id.clone(),
+                        status: update
+                            .fields
+                            .status
+                            .map(|status| format!("{:?}", status))
+                            .unwrap_or_else(|| "updated".to_string()),
+                    },
+                );
+                if let Some(content) = &update.fields.content {
+                    emit_tool_call_diffs(&self.events, &scope, &id, content).await;
+                }
+                return Ok(());
+            }
             _ => return Ok(()),
         };
 
-        let _ = self.app_handle.emit("acp-event", &event);
+        self.events.emit(scope, event);
         Ok(())
     }
 
@@ -281,6 +1308,13 @@ impl acp::Client for AcpClientHandler {
             }))
         })?;
 
+        edit_snapshots::snapshot_before_write(
+            &self.app_handle,
+            &session_id,
+            "write_text_file",
+            vec![args.path.clone()],
+        )
+        .await;
         nvim_write_file_for_terminal(&self.app_handle, &terminal_id, &args.path, &args.content)
             .await
             .map_err(|e| acp::Error::internal_error().data(e))?;
@@ -341,14 +1375,21 @@ impl acp::Client for AcpClientHandler {
         }
 
         let requested_mode = requested_tmux_mode(meta.as_ref());
-        let (command_mode, command_mode_source) = {
+        let (command_mode, command_mode_source, command_shell, env_rules, cwd_allow) = {
             let config_state = self
                 .app_handle
                 .state::<std::sync::Mutex<app_config::AppConfigState>>();
             let state = config_state
                 .lock()
                 .map_err(|_| acp::Error::internal_error().data("App config lock poisoned"))?;
-            state.resolve_tmux_command_mode(requested_mode)
+            let (mode, source) = state.resolve_tmux_command_mode(requested_mode);
+            (
+                mode,
+                source,
+                state.command_shell(),
+                state.command_env_rules(),
+                state.command_cwd_allow(),
+            )
         };
         log::info!(
             "ACP tmux mode resolved: terminal='{}' requested='{}' applied='{}' source='{}'",
@@ -360,40 +1401,101 @@ impl acp::Client for AcpClientHandler {
             command_mode_source
         );
 
-        let session_name = if let Some(name) = assigned_session_name {
-            name
-        } else {
-            let base_name = tmux_runtime::session_base_name(cwd.as_deref(), &host_terminal_id);
-            let chosen = tmux_runtime::find_available_session_name(&base_name, &assigned_names)
+        let rejected_env = tmux_runtime::rejected_env_names(&env, &env_rules);
+        if !rejected_env.is_empty() {
+            log::warn!(
+                "Not passing environment variables to agent command in terminal '{}': {}",
+                host_terminal_id,
+                rejected_env.join(", ")
+            );
+        }
+
+        let cwd = self.command_cwd(&session_id, cwd, cwd_allow).await?;
+
+        self.reserve_command_slot(&session_id, &host_terminal_id)
+            .await?;
+
+        let title = tmux_runtime::command_title(&command, &command_args);
+        let created = async {
+            let session_name = if let Some(name) = assigned_session_name {
+                name
+            } else {
+                let base_name = tmux_runtime::session_base_name(cwd.as_deref(), &host_terminal_id);
+                let chosen = tmux_runtime::find_available_session_name(&base_name, &assigned_names)
+                    .await
+                    .map_err(|e| acp::Error::internal_error().data(e))?;
+                let mut state = tmux_state.lock().await;
+                state.set_session_name(&host_terminal_id, chosen.clone());
+                chosen
+            };
+
+            let cwd_ref = cwd.as_deref();
+            tmux_runtime::ensure_session_exists(&session_name, cwd_ref)
                 .await
                 .map_err(|e| acp::Error::internal_error().data(e))?;
-            let mut state = tmux_state.lock().await;
-            state.set_session_name(&host_terminal_id, chosen.clone());
-            chosen
-        };
 
-        let cwd_ref = cwd.as_deref();
-        tmux_runtime::ensure_session_exists(&session_name, cwd_ref)
+            let pane_command = tmux_runtime::PaneCommand {
+                command: &command,
+                args: &command_args,
+                env: &env,
+                env_rules: &env_rules,
+                shell: command_shell.as_deref(),
+            };
+            tmux_runtime::create_command_pane(
+                &session_name,
+                command_mode,
+                &title,
+                &pane_command,
+                cwd_ref,
+            )
             .await
-            .map_err(|e| acp::Error::internal_error().data(e))?;
-
-        let pane_id = tmux_runtime::create_command_pane(
-            &session_name,
-            command_mode,
-            &command,
-            &command_args,
-            &env,
-            cwd_ref,
-        )
-        .await
-        .map_err(|e| acp::Error::internal_error().data(e))?;
+            .map_err(|e| acp::Error::internal_error().data(e))
+        }
+        .await;
 
+        let label = std::iter::once(command.as_str())
+            .chain(command_args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if created.is_ok() {
+            audit_log::record_agent_command(
+                &self.app_handle,
+                &host_terminal_id,
+                &session_id,
+                label.clone(),
+                cwd.as_ref().map(|cwd| cwd.display().to_string()),
+            );
+        }
         let terminal_handle = {
             let mut state = tmux_state.lock().await;
-            state.register_command(&host_terminal_id, pane_id, output_byte_limit)
+            // The slot passes from "starting" to the registered command.
+            let registered = created.map(|pane_id| {
+                state.register_command(
+                    &host_terminal_id,
+                    &session_id,
+                    pane_id,
+                    tmux_runtime::CommandRegistration {
+                        output_byte_limit,
+                        label,
+                        title,
+                        cwd,
+                    },
+                )
+            });
+            state.end_command_start(&session_id);
+            registered?
         };
 
-        Ok(acp::CreateTerminalResponse::new(terminal_handle))
+        let mut response = acp::CreateTerminalResponse::new(terminal_handle);
+        if !rejected_env.is_empty() {
+            let mut meta = acp::Meta::new();
+            meta.insert(
+                "neoai_rejected_env".to_string(),
+                serde_json::Value::from(rejected_env),
+            );
+            response = response.meta(meta);
+        }
+        Ok(response)
     }
 
     async fn terminal_output(
@@ -415,16 +1517,26 @@ impl acp::Client for AcpClientHandler {
             }))
         })?;
 
-        let output = tmux_runtime::pane_output(&command.pane_id)
+        let output = tmux_runtime::pane_history(&command.pane_id)
             .await
             .map_err(|e| acp::Error::internal_error().data(e))?;
         let pane_state = tmux_runtime::pane_state(&command.pane_id)
             .await
             .map_err(|e| acp::Error::internal_error().data(e))?;
 
+        let saved_to = self.spill_command_output(&command_id, &command, &output);
         let (output, truncated) = tmux_runtime::truncate_output(output, command.output_byte_limit);
         let mut response = acp::TerminalOutputResponse::new(output, truncated);
+        if let Some(path) = saved_to {
+            let mut meta = acp::Meta::new();
+            meta.insert(
+                "savedTo".to_string(),
+                serde_json::Value::from(path.to_string_lossy().into_owned()),
+            );
+            response = response.meta(meta);
+        }
         if pane_state.dead {
+            notify_command_exit(&self.app_handle, &command_id, pane_state.exit_code).await;
             response = response
                 .exit_status(acp::TerminalExitStatus::new().exit_code(pane_state.exit_code));
         }
@@ -456,11 +1568,12 @@ impl acp::Client for AcpClientHandler {
                 .map_err(|e| acp::Error::internal_error().data(e))?;
 
             if pane_state.dead {
+                notify_command_exit(&self.app_handle, &command_id, pane_state.exit_code).await;
                 let exit_status = acp::TerminalExitStatus::new().exit_code(pane_state.exit_code);
                 return Ok(acp::WaitForTerminalExitResponse::new(exit_status));
             }
 
-            tokio::time::sleep(Duration::from_millis(200)).await;
+            tmux_control::wait_for_pane_change().await;
         }
     }
 
@@ -520,6 +1633,136 @@ impl acp::Client for AcpClientHandler {
 
         Ok(acp::ReleaseTerminalResponse::new())
     }
+
+    async fn ext_method(&self, args: acp::ExtRequest) -> acp::Result<acp::ExtResponse> {
+        if &*args.method != TERMINAL_SNAPSHOT_METHOD || !self.terminal_snapshot_enabled {
+            return Err(acp::Error::method_not_found());
+        }
+        let params: TerminalSnapshotParams = serde_json::from_str(args.params.get())
+            .map_err(|e| acp::Error::invalid_params().data(e.to_string()))?;
+        let snapshot = self.terminal_snapshot(params.session_id).await?;
+        let raw = serde_json::value::to_raw_value(&snapshot)
+            .map_err(|e| acp::Error::internal_error().data(e.to_string()))?;
+        Ok(acp::ExtResponse::new(raw.into()))
+    }
+}
+
+/// Records that a command's pane died and notifies about it, once.
+pub(crate) async fn notify_command_exit(
+    app_handle: &tauri::AppHandle,
+    command_id: &str,
+    exit_code: Option<u32>,
+) {
+    let command = {
+        let tmux_state = app_handle.state::<Mutex<tmux_runtime::TmuxRuntimeState>>();
+        let mut state = tmux_state.lock().await;
+        state.mark_exited(command_id, exit_code)
+    };
+    let Some(command) = command else {
+        return;
+    };
+    let title = match exit_code {
+        Some(0) => "Command finished".to_string(),
+        Some(code) => format!("Command failed (exit {code})"),
+        None => "Command exited".to_string(),
+    };
+    notifications::notify(
+        app_handle,
+        NotificationKind::CommandFinished {
+            elapsed: command.started_at.elapsed(),
+        },
+        &title,
+        &command.label,
+    );
+}
+
+/// Payload of the `tmux-command-terminated` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandTerminatedEvent {
+    pub command_id: String,
+    pub session_id: String,
+    pub terminal_id: String,
+    /// Still running after Ctrl-C and the grace period, so its pane was
+    /// killed.
+    pub killed: bool,
+    pub exit_code: Option<u32>,
+}
+
+/// Stops the running commands of `session_id`, or of every session when
+/// `None`: each gets Ctrl-C, and panes still running after
+/// [`COMMAND_TERMINATE_GRACE`] are killed. Emits `tmux-command-terminated`
+/// per command.
+pub(crate) async fn terminate_session_commands(
+    app_handle: tauri::AppHandle,
+    session_id: Option<String>,
+) {
+    let Some(tmux_state) = app_handle.try_state::<Mutex<tmux_runtime::TmuxRuntimeState>>() else {
+        return;
+    };
+    let mut running = tmux_state
+        .lock()
+        .await
+        .running_commands_for_session(session_id.as_deref());
+    if running.is_empty() {
+        return;
+    }
+    log::info!("Stopping {} agent command(s)", running.len());
+    for (_, command) in &running {
+        if let Err(err) = tmux_runtime::interrupt_pane(&command.pane_id).await {
+            log::debug!("Failed to interrupt pane {}: {}", command.pane_id, err);
+        }
+    }
+
+    let deadline = tokio::time::Instant::now() + COMMAND_TERMINATE_GRACE;
+    loop {
+        let mut still_running = Vec::new();
+        for (command_id, command) in running {
+            match tmux_runtime::pane_state(&command.pane_id).await {
+                Ok(pane) if !pane.dead => still_running.push((command_id, command)),
+                // A pane that can't be inspected is gone.
+                pane => {
+                    let exit_code = pane.ok().and_then(|pane| pane.exit_code);
+                    command_terminated(&app_handle, &command_id, &command, false, exit_code).await;
+                }
+            }
+        }
+        running = still_running;
+        if running.is_empty() || tokio::time::Instant::now() >= deadline {
+            break;
+        }
+        let _ = tokio::time::timeout_at(deadline, tmux_control::wait_for_pane_change()).await;
+    }
+
+    for (command_id, command) in running {
+        if let Err(err) = tmux_runtime::kill_pane(&command.pane_id).await {
+            log::warn!("Failed to kill pane {}: {}", command.pane_id, err);
+        }
+        command_terminated(&app_handle, &command_id, &command, true, None).await;
+    }
+}
+
+async fn command_terminated(
+    app_handle: &tauri::AppHandle,
+    command_id: &str,
+    command: &tmux_runtime::ManagedTmuxCommand,
+    killed: bool,
+    exit_code: Option<u32>,
+) {
+    {
+        let tmux_state = app_handle.state::<Mutex<tmux_runtime::TmuxRuntimeState>>();
+        tmux_state.lock().await.mark_exited(command_id, exit_code);
+    }
+    let _ = app_handle.emit(
+        "tmux-command-terminated",
+        &CommandTerminatedEvent {
+            command_id: command_id.to_string(),
+            session_id: command.session_id.clone(),
+            terminal_id: command.host_terminal_id.clone(),
+            killed,
+            exit_code,
+        },
+    );
 }
 
 fn requested_tmux_mode(meta: Option<&acp::Meta>) -> Option<tmux_runtime::TmuxCommandMode> {
@@ -564,7 +1807,7 @@ fn codex_binary_name_current() -> &'static str {
     codex_binary_name_for_os(std::env::consts::OS)
 }
 
-fn is_default_agent_path(agent_path: &str) -> bool {
+pub(crate) fn is_default_agent_path(agent_path: &str) -> bool {
     let path = agent_path.trim();
     path == DEFAULT_AGENT_PATH || path == DEFAULT_AGENT_PATH_WINDOWS
 }
@@ -677,51 +1920,378 @@ fn resolve_current_codex_asset() -> Result<CodexAsset, String> {
     })
 }
 
+fn acp_trace() -> &'static std::sync::Mutex<VecDeque<AcpTraceEntry>> {
+    static ACP_TRACE: std::sync::OnceLock<std::sync::Mutex<VecDeque<AcpTraceEntry>>> =
+        std::sync::OnceLock::new();
+    ACP_TRACE.get_or_init(|| std::sync::Mutex::new(VecDeque::with_capacity(ACP_TRACE_CAPACITY)))
+}
+
+/// Appends to the in-memory ACP trace included in diagnostic bundles.
+/// Message content is summarized, never recorded verbatim.
+fn record_trace(direction: &str, kind: &str, detail: impl Into<String>) {
+    let Ok(mut trace) = acp_trace().lock() else {
+        return;
+    };
+    if trace.len() == ACP_TRACE_CAPACITY {
+        trace.pop_front();
+    }
+    trace.push_back(AcpTraceEntry {
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+        direction: direction.to_string(),
+        kind: kind.to_string(),
+        detail: detail.into(),
+    });
+}
+
+pub fn recent_trace() -> Vec<AcpTraceEntry> {
+    acp_trace()
+        .lock()
+        .map(|trace| trace.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+async fn event_scope(bindings: &SessionTerminalBindings, session_id: &str) -> AcpEventScope {
+    AcpEventScope {
+        session_id: Some(session_id.to_string()),
+        terminal_id: bindings.lock().await.get(session_id).cloned(),
+    }
+}
+
+fn trace_acp_event(event: &AcpEvent) {
+    let (kind, detail) = match event {
+        AcpEvent::ContentChunk(text) => ("content_chunk", format!("{} chars", text.len())),
+        AcpEvent::ThoughtChunk(text) => ("thought_chunk", format!("{} chars", text.len())),
+        AcpEvent::ToolCallStarted { id, title, kind } => {
+            ("tool_call_started", format!("{id} {kind}: {title}"))
+        }
+        AcpEvent::ToolCallUpdated { id, status } => ("tool_call_updated", format!("{id} {status}")),
+        AcpEvent::ToolCallDiff { id, path, diff } => (
+            "tool_call_diff",
+            format!("{id} {path} +{} -{}", diff.additions, diff.deletions),
+        ),
+        AcpEvent::Done { turn, context } => (
+            "done",
+            format!(
+                "{} in {}ms, {} tool call(s) (context: {} sent, {} reused)",
+                turn.stop_reason,
+                turn.duration_ms,
+                turn.tool_calls,
+                context.sent_blocks,
+                context.reused_blocks
+            ),
+        ),
+        AcpEvent::Error {
+            kind,
+            message,
+            retryable,
+            attempts,
+            ..
+        } => (
+            "error",
+            format!("{kind:?}: {message} (retryable: {retryable}, attempts: {attempts})"),
+        ),
+        AcpEvent::PermissionAutoApproved { id, title, kind } => {
+            ("permission_auto_approved", format!("{id} {kind}: {title}"))
+        }
+    };
+    record_trace("in", kind, detail);
+}
+
+/// Option that approves a request for a tool call of `kind` when the kind is
+/// listed in `auto_approve_kinds`: allow once, or allow always when that is
+/// all the agent offers. `None` leaves the request to the user.
+fn auto_approve_option(
+    kind: Option<acp::ToolKind>,
+    options: &[acp::PermissionOption],
+    auto_approve_kinds: &[String],
+) -> Option<String> {
+    let kind = tool_kind_name(kind?)?;
+    if !auto_approve_kinds.contains(&kind) {
+        return None;
+    }
+    [
+        acp::PermissionOptionKind::AllowOnce,
+        acp::PermissionOptionKind::AllowAlways,
+    ]
+    .iter()
+    .find_map(|wanted| {
+        options
+            .iter()
+            .find(|option| option.kind == *wanted)
+            .map(|option| option.option_id.to_string())
+    })
+}
+
+/// Protocol name of a tool kind (`read`, `switch_mode`, ...), as written in
+/// `auto_approve_kinds`.
+fn tool_kind_name(kind: acp::ToolKind) -> Option<String> {
+    serde_json::to_value(kind)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+}
+
+/// Whether a failed prompt is worth sending again: rate limits, overloaded
+/// or unavailable upstreams, timeouts and dropped connections. Agents report
+/// these as internal errors, so the message text is all there is to go on.
+fn is_retryable_prompt_error(message: &str) -> bool {
+    const MARKERS: &[&str] = &[
+        "rate limit",
+        "rate_limit",
+        "ratelimit",
+        "too many requests",
+        "overloaded",
+        "temporarily unavailable",
+        "service unavailable",
+        "bad gateway",
+        "gateway timeout",
+        "timed out",
+        "connection reset",
+        "connection closed",
+    ];
+    let message = message.to_lowercase();
+    MARKERS.iter().any(|marker| message.contains(marker))
+        || [429, 502, 503, 504]
+            .iter()
+            .any(|code| has_http_status(&message, *code))
+}
+
+/// Whether lowercased `message` reports HTTP status `code` where a status
+/// goes: `"status":429`, `status code 503`, `HTTP 502`. A bare number may be
+/// a path, a line or tool output, so it doesn't count.
+fn has_http_status(message: &str, code: u16) -> bool {
+    const KEYS: &[&str] = &[
+        "status",
+        "status_code",
+        "statuscode",
+        "status code",
+        "http",
+        "http/1.0",
+        "http/1.1",
+        "http/2",
+    ];
+    let code = code.to_string();
+    message.match_indices(&code).any(|(start, _)| {
+        let end = start + code.len();
+        if message[end..].starts_with(|c: char| c.is_ascii_digit()) {
+            return false;
+        }
+        let key = message[..start].trim_end_matches([' ', ':', '=', '"']);
+        KEYS.iter().any(|candidate| {
+            key.strip_suffix(candidate).is_some_and(|before| {
+                !before.ends_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+            })
+        })
+    })
+}
+
+/// Sorts a failed prompt by its JSON-RPC error `code` and message. Auth and
+/// protocol failures have their own codes; the rest only show in the text.
+/// `failed_tool_call` is the turn's last tool call that failed, if any.
+fn classify_prompt_error(
+    code: Option<i64>,
+    message: &str,
+    failed_tool_call: Option<String>,
+) -> AcpErrorKind {
+    const AUTH: &[&str] = &[
+        "authenticat",
+        "authoriz",
+        "auth required",
+        "auth_required",
+        "forbidden",
+        "api key",
+        "api_key",
+    ];
+    // Matched as whole words, so `loginctl` or `credential_helper.rs` in a
+    // path don't count.
+    const AUTH_WORDS: &[&str] = &["login", "credential", "credentials"];
+    const TIMEOUT: &[&str] = &["timed out", "timeout", "deadline exceeded"];
+    const UNAVAILABLE: &[&str] = &[
+        "connection closed",
+        "connection reset",
+        "broken pipe",
+        "channel closed",
+        "server shut down",
+        "agent exited",
+        "not running",
+    ];
+    let message = message.to_lowercase();
+    let mentions = |markers: &[&str]| markers.iter().any(|marker| message.contains(marker));
+    match code {
+        // ACP's `auth_required`.
+        Some(-32000) => return AcpErrorKind::PermissionDenied,
+        // Parse error, invalid request, method not found, invalid params.
+        Some(-32700 | -32600 | -32601 | -32602) => return AcpErrorKind::ProtocolError,
+        _ => {}
+    }
+    let has_word = |words: &[&str]| {
+        message
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|word| words.contains(&word))
+    };
+    if mentions(AUTH)
+        || has_word(AUTH_WORDS)
+        || has_http_status(&message, 401)
+        || has_http_status(&message, 403)
+    {
+        AcpErrorKind::PermissionDenied
+    } else if mentions(TIMEOUT) || has_http_status(&message, 504) {
+        AcpErrorKind::Timeout
+    } else if mentions(UNAVAILABLE) {
+        AcpErrorKind::AgentUnavailable
+    } else if let Some(id) = failed_tool_call {
+        AcpErrorKind::ToolCallFailed { id }
+    } else {
+        AcpErrorKind::Other
+    }
+}
+
+/// A fresh random number for retry jitter, without pulling in a RNG crate.
+fn jitter_seed() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish()
+}
+
+/// The command line in a tool call's raw input. Agents send `command` as a
+/// string (optionally with `args`) or as an argv array; `sh -c <script>`
+/// wrappers are shown as just the script.
+fn permission_command(raw_input: &serde_json::Value) -> Option<String> {
+    let command = raw_input.get("command").or_else(|| raw_input.get("cmd"))?;
+    let argv: Vec<&str> = match command {
+        serde_json::Value::String(program) => std::iter::once(program.as_str())
+            .chain(
+                raw_input
+                    .get("args")
+                    .and_then(|args| args.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|arg| arg.as_str()),
+            )
+            .collect(),
+        serde_json::Value::Array(args) => args.iter().filter_map(|arg| arg.as_str()).collect(),
+        _ => return None,
+    };
+    if let [shell, "-c" | "-lc", script] = argv.as_slice() {
+        let shell = Path::new(shell)
+            .file_name()
+            .and_then(OsStr::to_str)
+            .unwrap_or(shell);
+        if matches!(shell, "sh" | "bash" | "zsh") {
+            return Some(script.to_string());
+        }
+    }
+    if raw_input.get("args").is_none() {
+        if let [program] = argv.as_slice() {
+            // A lone string is already a command line.
+            return Some(program.to_string());
+        }
+    }
+    (!argv.is_empty()).then(|| {
+        argv.iter()
+            .map(|arg| crate::tmux_runtime::shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    })
+}
+
+/// File paths named in a tool call's raw input.
+fn permission_paths(raw_input: &serde_json::Value) -> Vec<String> {
+    let mut paths = Vec::new();
+    for key in ["path", "file_path", "filePath", "paths"] {
+        match raw_input.get(key) {
+            Some(serde_json::Value::String(path)) => paths.push(path.clone()),
+            Some(serde_json::Value::Array(items)) => paths.extend(
+                items
+                    .iter()
+                    .filter_map(|item| item.as_str())
+                    .map(str::to_string),
+            ),
+            _ => {}
+        }
+    }
+    paths
+}
+
+/// Emits a `ToolCallDiff` for every diff in a tool call's content. Diffing
+/// runs off the async runtime since agents may send whole files.
+async fn emit_tool_call_diffs(
+    events: &Rc<AcpEventBus>,
+    scope: &AcpEventScope,
+    id: &str,
+    content: &[acp::ToolCallContent],
+) {
+    for item in content {
+        let acp::ToolCallContent::Diff(diff) = item else {
+            continue;
+        };
+        let original = diff.old_text.clone().unwrap_or_default();
+        let modified = diff.new_text.clone();
+        let text_diff = match tauri::async_runtime::spawn_blocking(move || {
+            diff::compute_diff(&original, &modified)
+        })
+        .await
+        {
+            Ok(text_diff) => text_diff,
+            Err(err) => {
+                log::warn!("Failed to diff tool call {}: {}", id, err);
+                continue;
+            }
+        };
+        events.emit(
+            scope.clone(),
+            AcpEvent::ToolCallDiff {
+                id: id.to_string(),
+                path: diff.path.to_string_lossy().into_owned(),
+                diff: text_diff,
+            },
+        );
+    }
+}
+
 fn emit_install_status(app_handle: &tauri::AppHandle, phase: &str, message: impl Into<String>) {
+    emit_download_status(app_handle, phase, message, None);
+}
+
+fn emit_download_status(
+    app_handle: &tauri::AppHandle,
+    phase: &str,
+    message: impl Into<String>,
+    download_url: Option<&str>,
+) {
     let _ = app_handle.emit(
         "acp-install-status",
         &AcpInstallStatusEvent {
             phase: phase.to_string(),
             message: message.into(),
             version: Some(CODEX_ACP_VERSION.to_string()),
+            download_url: download_url.map(str::to_string),
         },
     );
 }
 
-fn spawn_agent_process(agent_path: &str) -> Result<tokio::process::Child, std::io::Error> {
-    tokio::process::Command::new(agent_path)
-        .stdin(std::process::Stdio::piped())
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .kill_on_drop(true)
-        .spawn()
-}
-
-fn codex_vendor_root_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
-    #[cfg(target_os = "macos")]
-    {
-        let home = app_handle
-            .path()
-            .home_dir()
-            .map_err(|e| format!("Failed to resolve home directory: {e}"))?;
-        return Ok(home.join(".neoai"));
-    }
-
-    #[cfg(not(target_os = "macos"))]
-    {
-        app_handle
-            .path()
-            .app_local_data_dir()
-            .map_err(|e| format!("Failed to resolve app local data directory: {e}"))
+/// Points a pinned release URL at `mirror` (a base URL without trailing
+/// slash) by swapping the GitHub download prefix for it.
+fn asset_download_url(url: &str, mirror: Option<&str>) -> String {
+    match (mirror, url.strip_prefix(CODEX_DOWNLOAD_BASE_URL)) {
+        (Some(mirror), Some(rest)) => format!("{mirror}{rest}"),
+        _ => url.to_string(),
     }
 }
 
 fn codex_install_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
-    Ok(codex_vendor_root_dir(app_handle)?
-        .join("agents")
+    Ok(managed_codex_path(&app_paths::app_root_dir(app_handle)?))
+}
+
+/// Where the managed codex-acp lives under the app root directory.
+pub(crate) fn managed_codex_path(root: &Path) -> PathBuf {
+    root.join("agents")
         .join("codex-acp")
         .join(CODEX_ACP_VERSION)
-        .join(codex_binary_name_current()))
+        .join(codex_binary_name_current())
 }
 
 async fn download_release_asset(url: &str) -> Result<Vec<u8>, String> {
@@ -886,18 +2456,39 @@ async fn ensure_vendored_codex_acp(app_handle: &tauri::AppHandle) -> Result<Path
     fs::create_dir_all(parent)
         .map_err(|e| format!("Failed to create installation directory: {e}"))?;
 
-    emit_install_status(
+    let mirror = app_handle
+        .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+        .and_then(|state| {
+            state
+                .lock()
+                .ok()
+                .and_then(|state| state.agent_download_base_url())
+        });
+    let download_url = asset_download_url(asset.url, mirror.as_deref());
+    emit_download_status(
         app_handle,
         "downloading",
-        format!(
-            "Downloading codex-acp {} ({})...",
-            CODEX_ACP_VERSION, asset.target
-        ),
+        match &mirror {
+            Some(mirror) => format!(
+                "Downloading codex-acp {} ({}) from {}...",
+                CODEX_ACP_VERSION, asset.target, mirror
+            ),
+            None => format!(
+                "Downloading codex-acp {} ({})...",
+                CODEX_ACP_VERSION, asset.target
+            ),
+        },
+        Some(&download_url),
     );
 
-    let archive_bytes = download_release_asset(asset.url).await?;
+    let archive_bytes = download_release_asset(&download_url).await?;
 
-    emit_install_status(app_handle, "verifying", "Verifying download integrity...");
+    emit_download_status(
+        app_handle,
+        "verifying",
+        "Verifying download integrity...",
+        Some(&download_url),
+    );
     verify_sha256(&archive_bytes, asset.sha256)?;
 
     emit_install_status(app_handle, "extracting", "Extracting codex-acp binary...");
@@ -935,31 +2526,34 @@ async fn ensure_vendored_codex_acp(app_handle: &tauri::AppHandle) -> Result<Path
 }
 
 /// Runs on a dedicated thread with a LocalSet. Owns the !Send ACP connection
-/// and processes commands from the Send world via channels.
+/// and processes commands from the Send world via channels. Returns `true`
+/// when the agent process exited on its own rather than being shut down.
 async fn acp_worker(
     app_handle: tauri::AppHandle,
-    agent_path: String,
+    launch: AgentLaunch,
     pending_permission_requests: PendingPermissionRequests,
     permission_request_counter: Arc<AtomicU64>,
     session_terminal_bindings: SessionTerminalBindings,
+    terminal_channels: TerminalChannels,
     mut cmd_rx: mpsc::Receiver<AcpCommand>,
-    ready_tx: oneshot::Sender<Result<(), String>>,
-) {
+    ready_tx: oneshot::Sender<Result<Option<u32>, String>>,
+) -> bool {
     let local = tokio::task::LocalSet::new();
     local
         .run_until(async move {
             emit_install_status(&app_handle, "starting", "Starting AI agent...");
 
-            let mut child = match spawn_agent_process(&agent_path) {
+            let agent_path = launch.path.as_str();
+            let mut child = match spawn_agent_process(agent_path, &launch) {
                 Ok(child) => child,
                 Err(spawn_err)
                     if spawn_err.kind() == std::io::ErrorKind::NotFound
-                        && is_default_agent_path(&agent_path) =>
+                        && is_default_agent_path(agent_path) =>
                 {
                     match ensure_vendored_codex_acp(&app_handle).await {
                         Ok(vendored_path) => {
                             let vendored_path_str = vendored_path.to_string_lossy().to_string();
-                            match spawn_agent_process(&vendored_path_str) {
+                            match spawn_agent_process(&vendored_path_str, &launch) {
                                 Ok(child) => child,
                                 Err(e) => {
                                     let err_msg = format!(
@@ -970,7 +2564,7 @@ async fn acp_worker(
                                     );
                                     emit_install_status(&app_handle, "error", err_msg.clone());
                                     let _ = ready_tx.send(Err(err_msg));
-                                    return;
+                                    return false;
                                 }
                             }
                         }
@@ -981,13 +2575,13 @@ async fn acp_worker(
                             );
                             emit_install_status(&app_handle, "error", err_msg.clone());
                             let _ = ready_tx.send(Err(err_msg));
-                            return;
+                            return false;
                         }
                     }
                 }
                 Err(e) => {
                     let _ = ready_tx.send(Err(format!("Failed to spawn agent '{}': {}", agent_path, e)));
-                    return;
+                    return false;
                 }
             };
 
@@ -997,7 +2591,7 @@ async fn acp_worker(
                     let err_msg = "Failed to take agent stdin".to_string();
                     emit_install_status(&app_handle, "error", err_msg.clone());
                     let _ = ready_tx.send(Err(err_msg));
-                    return;
+                    return false;
                 }
             };
             let agent_stdout = match child.stdout.take() {
@@ -1006,15 +2600,44 @@ async fn acp_worker(
                     let err_msg = "Failed to take agent stdout".to_string();
                     emit_install_status(&app_handle, "error", err_msg.clone());
                     let _ = ready_tx.send(Err(err_msg));
-                    return;
+                    return false;
                 }
             };
 
+            let captured_output: CapturedOutput = Arc::new(Mutex::new(Default::default()));
+            let stream_options: SessionStreamOptions = Rc::new(RefCell::new(Default::default()));
+            let context_usage: SessionContextUsage = Rc::new(RefCell::new(Default::default()));
+            let events = Rc::new(AcpEventBus {
+                app_handle: app_handle.clone(),
+                frame: app_handle
+                    .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+                    .and_then(|state| state.lock().ok().map(|state| state.event_batch_interval()))
+                    .unwrap_or(Some(Duration::from_millis(16))),
+                pending: RefCell::new(Vec::new()),
+                terminal_channels,
+                tool_calls: RefCell::new(std::collections::HashMap::new()),
+                failed_tool_calls: RefCell::new(std::collections::HashMap::new()),
+                turn_output: RefCell::new(std::collections::HashSet::new()),
+            });
+            let terminal_snapshot_enabled = app_handle
+                .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+                .and_then(|state| {
+                    state
+                        .lock()
+                        .ok()
+                        .map(|state| state.agent_config().allow_terminal_snapshot)
+                })
+                .unwrap_or(false);
             let handler = AcpClientHandler {
                 app_handle: app_handle.clone(),
+                events: events.clone(),
+                terminal_snapshot_enabled,
                 pending_permission_requests: pending_permission_requests.clone(),
                 permission_request_counter: permission_request_counter.clone(),
                 session_terminal_bindings: session_terminal_bindings.clone(),
+                captured_output: captured_output.clone(),
+                stream_options: stream_options.clone(),
+                context_usage: context_usage.clone(),
             };
 
             let (conn, io_future) = acp::ClientSideConnection::new(
@@ -1025,6 +2648,7 @@ async fn acp_worker(
                     tokio::task::spawn_local(fut);
                 },
             );
+            let conn = Rc::new(conn);
 
             // Drive I/O in background
             tokio::task::spawn_local(io_future);
@@ -1036,6 +2660,10 @@ async fn acp_worker(
                 "terminal_output".to_string(),
                 serde_json::Value::Bool(tmux_available),
             );
+            capability_meta.insert(
+                "terminal_snapshot".to_string(),
+                serde_json::Value::Bool(terminal_snapshot_enabled && tmux_available),
+            );
             let init_result = conn
                 .initialize(
                     acp::InitializeRequest::new(acp::ProtocolVersion::V1)
@@ -1053,29 +2681,49 @@ async fn acp_worker(
                 )
                 .await;
 
-            match init_result {
+            let (supports_load_session, supports_embedded_context) = match init_result {
                 Ok(resp) => {
                     log::info!(
                         "ACP agent initialized: {:?}",
                         resp.agent_info.as_ref().map(|i| &i.name)
                     );
                     emit_install_status(&app_handle, "done", "AI agent is ready.");
-                    let _ = ready_tx.send(Ok(()));
+                    let _ = ready_tx.send(Ok(child.id()));
+                    (
+                        resp.agent_capabilities.load_session,
+                        resp.agent_capabilities.prompt_capabilities.embedded_context,
+                    )
                 }
                 Err(e) => {
                     let err_msg = format!("ACP initialize failed: {}", e);
                     emit_install_status(&app_handle, "error", err_msg.clone());
                     let _ = ready_tx.send(Err(err_msg));
-                    return;
+                    return false;
                 }
-            }
+            };
 
-            // Process commands from the Send world
-            while let Some(cmd) = cmd_rx.recv().await {
+            let health_checks =
+                tokio::task::spawn_local(run_health_checks(conn.clone(), app_handle.clone()));
+            let context_ledger = Rc::new(RefCell::new(ContextLedger::default()));
+            let turn_locks: SessionTurnLocks = Default::default();
+            let turn_cancellations: SessionTurnCancellations = Default::default();
+            // Process commands from the Send world until shutdown or the agent exits
+            let exited_unexpectedly = loop {
+                let cmd = tokio::select! {
+                    cmd = cmd_rx.recv() => match cmd {
+                        Some(cmd) => cmd,
+                        None => break false,
+                    },
+                    status = child.wait() => {
+                        log::warn!("ACP agent exited unexpectedly: {:?}", status);
+                        break true;
+                    }
+                };
                 match cmd {
                     AcpCommand::CreateSession {
                         working_dir,
                         terminal_id,
+                        model,
                         reply,
                     } => {
                         let result = conn
@@ -1084,6 +2732,23 @@ async fn acp_worker(
                         match result {
                             Ok(resp) => {
                                 let sid = resp.session_id.to_string();
+                                // Agents without model selection keep their default.
+                                if let Some(model) = model {
+                                    if let Err(e) = conn
+                                        .set_session_model(acp::SetSessionModelRequest::new(
+                                            resp.session_id.clone(),
+                                            model.clone(),
+                                        ))
+                                        .await
+                                    {
+                                        log::warn!(
+                                            "Failed to select model '{}' for ACP session {}: {}",
+                                            model,
+                                            sid,
+                                            e
+                                        );
+                                    }
+                                }
                                 session_terminal_bindings
                                     .lock()
                                     .await
@@ -1094,58 +2759,474 @@ async fn acp_worker(
                                 let _ =
                                     reply.send(Err(format!("Failed to create session: {}", e)));
                             }
-                        }
+                        }
+                    }
+                    AcpCommand::LoadSession {
+                        session_id,
+                        working_dir,
+                        terminal_id,
+                        reply,
+                    } => {
+                        if !supports_load_session {
+                            let _ = reply.send(Err(
+                                "The agent does not support loading sessions".to_string(),
+                            ));
+                            continue;
+                        }
+                        // The agent replays the conversation while loading; the
+                        // chat already has it, so collect the replay and drop it.
+                        captured_output
+                            .lock()
+                            .await
+                            .insert(session_id.clone(), String::new());
+                        let result = conn
+                            .load_session(acp::LoadSessionRequest::new(
+                                session_id.clone(),
+                                working_dir,
+                            ))
+                            .await;
+                        captured_output.lock().await.remove(&session_id);
+                        match result {
+                            Ok(_) => {
+                                session_terminal_bindings
+                                    .lock()
+                                    .await
+                                    .insert(session_id.clone(), terminal_id);
+                                let _ = reply.send(Ok(session_id));
+                            }
+                            Err(e) => {
+                                let _ = reply.send(Err(format!("Failed to load session: {}", e)));
+                            }
+                        }
+                    }
+                    AcpCommand::Prompt {
+                        session_id,
+                        messages,
+                        context,
+                        mentions,
+                        reply,
+                    } => {
+                        // Each turn runs as its own task so prompts to different
+                        // sessions proceed side by side; a session's own turns
+                        // still run one at a time, in order.
+                        let turn_lock = turn_locks
+                            .borrow_mut()
+                            .entry(session_id.clone())
+                            .or_default()
+                            .clone();
+                        let conn = conn.clone();
+                        let app_handle = app_handle.clone();
+                        let events = events.clone();
+                        let session_terminal_bindings = session_terminal_bindings.clone();
+                        let context_usage = context_usage.clone();
+                        let context_ledger = context_ledger.clone();
+                        let turn_cancellations = turn_cancellations.clone();
+                        tokio::task::spawn_local(async move {
+                            let _turn = turn_lock.lock().await;
+                            let cancel = CancellationToken::new();
+                            turn_cancellations
+                                .borrow_mut()
+                                .insert(session_id.clone(), cancel.clone());
+                            let context_reuse = app_handle
+                                .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+                                .and_then(|state| {
+                                    state.lock().ok().map(|state| state.context_config().context_reuse)
+                                })
+                                .unwrap_or(true);
+                            let context_plan = context_ledger.borrow_mut().plan(
+                                &session_id,
+                                context
+                                    .into_iter()
+                                    .map(|text| (text.clone(), acp::ContentBlock::from(text)))
+                                    .collect(),
+                                context_reuse,
+                            );
+                            let mention_plan = context_ledger.borrow_mut().plan(
+                                &session_id,
+                                mentions
+                                    .iter()
+                                    .map(|mention| {
+                                        (
+                                            format!("{}\n{}", mention.uri(), mention.content),
+                                            mention.to_content_block(supports_embedded_context),
+                                        )
+                                    })
+                                    .collect(),
+                                context_reuse,
+                            );
+                            let mut stats = context_plan.stats;
+                            stats.add(&mention_plan.stats);
+                            let prompt_tokens = stats.sent_tokens
+                                + messages
+                                    .iter()
+                                    .map(|msg| context::estimate_tokens(msg))
+                                    .sum::<usize>();
+
+                            let mut prompt_blocks: Vec<acp::ContentBlock> = Vec::new();
+                            if stats.reused_blocks > 0 {
+                                prompt_blocks.push(
+                                    format!(
+                                        "({} context block(s) sent earlier in this conversation are unchanged and not repeated.)",
+                                        stats.reused_blocks
+                                    )
+                                    .into(),
+                                );
+                            }
+                            prompt_blocks.extend(context_plan.blocks);
+                            for msg in messages {
+                                prompt_blocks.push(msg.into());
+                            }
+                            prompt_blocks.extend(mention_plan.blocks);
+                            let sent_hashes: Vec<u64> = context_plan
+                                .hashes
+                                .into_iter()
+                                .chain(mention_plan.hashes)
+                                .collect();
+
+                            let scope = event_scope(&session_terminal_bindings, &session_id).await;
+                            track_context_usage(&app_handle, &context_usage, &scope, |usage, limit| {
+                                usage.add(prompt_tokens, limit)
+                            });
+                            let retry_policy = app_handle
+                                .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+                                .and_then(|state| state.lock().ok().map(|state| state.retry_policy()))
+                                .unwrap_or_else(|| app_config::AppConfigState::default().retry_policy());
+                            let mut retries = 0;
+                            events.take_tool_calls(&session_id);
+                            events.take_failed_tool_call(&session_id);
+                            events.take_turn_output(&session_id);
+                            let started = std::time::Instant::now();
+                            let result = loop {
+                                let result = conn
+                                    .prompt(acp::PromptRequest::new(
+                                        session_id.clone(),
+                                        prompt_blocks.clone(),
+                                    ))
+                                    .await;
+                                // A turn that already streamed output or ran tools is
+                                // not sent again: the user would see it twice and its
+                                // edits and commands could run twice.
+                                let error = match result {
+                                    Err(e)
+                                        if retries < retry_policy.max_retries
+                                            && is_retryable_prompt_error(&e.to_string())
+                                            && !events.take_turn_output(&session_id) =>
+                                    {
+                                        e.to_string()
+                                    }
+                                    result => break result,
+                                };
+                                if cancel.is_cancelled() {
+                                    break Ok(acp::PromptResponse::new(acp::StopReason::Cancelled));
+                                }
+                                retries += 1;
+                                let delay = retry_policy.delay(retries, jitter_seed());
+                                record_trace(
+                                    "in",
+                                    "prompt_retry",
+                                    format!("{retries}/{} in {delay:?}: {error}", retry_policy.max_retries),
+                                );
+                                let _ = app_handle.emit(
+                                    "acp-retrying",
+                                    &AcpRetryingEvent {
+                                        session_id: session_id.clone(),
+                                        terminal_id: scope.terminal_id.clone(),
+                                        attempt: retries,
+                                        max_retries: retry_policy.max_retries,
+                                        delay_ms: delay.as_millis() as u64,
+                                        error,
+                                    },
+                                );
+                                tokio::select! {
+                                    _ = tokio::time::sleep(delay) => {}
+                                    _ = cancel.cancelled() => {
+                                        record_trace("in", "prompt_retry", "cancelled".to_string());
+                                        break Ok(acp::PromptResponse::new(acp::StopReason::Cancelled));
+                                    }
+                                }
+                            };
+                            turn_cancellations.borrow_mut().remove(&session_id);
+                            let mut turn = TurnMetadata {
+                                stop_reason: StopReason::Error,
+                                duration_ms: started.elapsed().as_millis() as u64,
+                                usage: TokenUsage::default(),
+                                tool_calls: events.take_tool_calls(&session_id),
+                            };
+                            match result {
+                                Ok(resp) => {
+                                    // A turn cancelled while waiting to retry may
+                                    // never have reached the agent.
+                                    if !cancel.is_cancelled() {
+                                        context_ledger.borrow_mut().record(&session_id, sent_hashes);
+                                    }
+                                    let stop_reason = StopReason::from(resp.stop_reason);
+                                    turn.stop_reason = stop_reason;
+                                    turn.usage = serde_json::to_value(&resp)
+                                        .map(|resp| TokenUsage::from_response(&resp))
+                                        .unwrap_or_default();
+                                    track_context_usage(
+                                        &app_handle,
+                                        &context_usage,
+                                        &scope,
+                                        |usage, limit| usage.set_reported(turn.usage, limit),
+                                    );
+                                    events.emit(
+                                        scope,
+                                        AcpEvent::Done {
+                                            turn,
+                                            context: stats,
+                                        },
+                                    );
+                                    if let Some(body) = notifications::turn_finished_body(stop_reason) {
+                                        notifications::notify(
+                                            &app_handle,
+                                            NotificationKind::TurnFinished,
+                                            "Agent finished",
+                                            &body,
+                                        );
+                                    }
+                                    let _ = reply.send(Ok(stop_reason.to_string()));
+                                }
+                                Err(e) => {
+                                    record_trace("in", "prompt_error", e.to_string());
+                                    let code = serde_json::to_value(&e)
+                                        .ok()
+                                        .and_then(|e| e.get("code").and_then(|code| code.as_i64()));
+                                    let kind = classify_prompt_error(
+                                        code,
+                                        &e.to_string(),
+                                        events.take_failed_tool_call(&session_id),
+                                    );
+                                    events.emit(
+                                        scope,
+                                        AcpEvent::Error {
+                                            kind,
+                                            message: e.to_string(),
+                                            retryable: is_retryable_prompt_error(&e.to_string()),
+                                            attempts: retries + 1,
+                                            turn,
+                                        },
+                                    );
+                                    let _ = reply.send(Err(format!("Prompt failed: {}", e)));
+                                }
+                            }
+                        });
                     }
-                    AcpCommand::Prompt {
-                        session_id,
-                        messages,
-                        context,
+                    AcpCommand::CapturedPrompt {
+                        working_dir,
+                        prompt,
                         reply,
                     } => {
-                        let mut prompt_blocks: Vec<acp::ContentBlock> = Vec::new();
-                        if let Some(ctx) = context {
-                            prompt_blocks.push(ctx.into());
-                        }
-                        for msg in messages {
-                            prompt_blocks.push(msg.into());
+                        // Like a prompt turn, this runs as its own task so a long
+                        // summary doesn't hold up other sessions' commands.
+                        let conn = conn.clone();
+                        let captured_output = captured_output.clone();
+                        tokio::task::spawn_local(async move {
+                            let session = match conn
+                                .new_session(acp::NewSessionRequest::new(working_dir))
+                                .await
+                            {
+                                Ok(resp) => resp.session_id.to_string(),
+                                Err(e) => {
+                                    let _ = reply
+                                        .send(Err(format!("Failed to create session: {}", e)));
+                                    return;
+                                }
+                            };
+                            captured_output
+                                .lock()
+                                .await
+                                .insert(session.clone(), String::new());
+                            let result = conn
+                                .prompt(acp::PromptRequest::new(
+                                    session.clone(),
+                                    vec![prompt.into()],
+                                ))
+                                .await;
+                            let text = captured_output
+                                .lock()
+                                .await
+                                .remove(&session)
+                                .unwrap_or_default();
+                            let _ = reply.send(
+                                result
+                                    .map(|_| text)
+                                    .map_err(|e| format!("Prompt failed: {}", e)),
+                            );
+                        });
+                    }
+                    AcpCommand::SetStreamOptions {
+                        session_id,
+                        options,
+                    } => {
+                        stream_options.borrow_mut().insert(session_id, options);
+                    }
+                    AcpCommand::Cancel { session_id } => {
+                        record_trace("out", "cancel", session_id.clone());
+                        // Wakes a turn waiting to retry, so it isn't sent again.
+                        if let Some(cancel) = turn_cancellations.borrow_mut().remove(&session_id) {
+                            cancel.cancel();
                         }
-
-                        let result = conn
-                            .prompt(acp::PromptRequest::new(session_id, prompt_blocks))
-                            .await;
-                        match result {
-                            Ok(resp) => {
-                                let stop_reason = format!("{:?}", resp.stop_reason);
-                                let _ = app_handle.emit(
-                                    "acp-event",
-                                    &AcpEvent::Done {
-                                        stop_reason: stop_reason.clone(),
-                                    },
-                                );
-                                let _ = reply.send(Ok(stop_reason));
-                            }
-                            Err(e) => {
-                                let _ = reply.send(Err(format!("Prompt failed: {}", e)));
-                            }
+                        // The agent answers by ending the turn with a
+                        // `cancelled` stop reason.
+                        if let Err(err) = conn
+                            .cancel(acp::CancelNotification::new(session_id.clone()))
+                            .await
+                        {
+                            log::warn!("Failed to cancel ACP prompt: {}", err);
                         }
+                        // The protocol has the client answer the turn's
+                        // outstanding permission requests with `cancelled`.
+                        cancel_pending_permission_requests(
+                            &pending_permission_requests,
+                            Some(&session_id),
+                        )
+                        .await;
                     }
                     AcpCommand::Shutdown => {
-                        break;
+                        break false;
                     }
                 }
-            }
+            };
+            health_checks.abort();
 
-            let mut pending = pending_permission_requests.lock().await;
-            for (_, tx) in pending.drain() {
-                let _ = tx.send(acp::RequestPermissionOutcome::Cancelled);
-            }
-            drop(pending);
+            cancel_pending_permission_requests(&pending_permission_requests, None).await;
             session_terminal_bindings.lock().await.clear();
+            // The sessions are gone with the agent; so is any reason to keep
+            // their commands running.
+            tauri::async_runtime::spawn(terminate_session_commands(app_handle.clone(), None));
 
             // Clean up
             let _ = child.kill().await;
+            exited_unexpectedly
         })
-        .await;
+        .await
+}
+
+/// Agent binaries worth probing for diagnostics: the configured launch path
+/// and, for the default agent, the managed install.
+pub fn agent_binary_candidates(app_handle: &tauri::AppHandle) -> Vec<String> {
+    let launch = resolve_agent_launch(app_handle, None);
+    let mut candidates = vec![launch.path.clone()];
+    if is_default_agent_path(&launch.path) {
+        if let Ok(path) = codex_install_path(app_handle) {
+            candidates.push(path.to_string_lossy().into_owned());
+        }
+    }
+    candidates
+}
+
+/// An explicit `agent_path` wins over `[agent] path` in config.toml, which
+/// wins over the bundled codex-acp default. Args and env always come from config.
+fn resolve_agent_launch(app_handle: &tauri::AppHandle, agent_path: Option<String>) -> AgentLaunch {
+    let agent = app_handle
+        .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+        .and_then(|state| state.lock().ok().map(|state| state.agent_config()))
+        .unwrap_or_default();
+    acp_core::agent_launch(agent, agent_path)
+}
+
+fn emit_agent_status(app_handle: &tauri::AppHandle, status: &AgentStatus) {
+    record_trace("local", "agent_status", format!("{status:?}"));
+    let _ = app_handle.emit("acp-agent-status", status);
+}
+
+/// Pings the agent every [`HEALTH_PING_INTERVAL`] for as long as the worker
+/// runs, flipping the status between `Running` and `Unresponsive`. Runs beside
+/// the command loop so a prompt in flight doesn't hold the pings up.
+async fn run_health_checks(conn: Rc<acp::ClientSideConnection>, app_handle: tauri::AppHandle) {
+    let Ok(params) = serde_json::value::RawValue::from_string("{}".to_string()) else {
+        return;
+    };
+    let params: Arc<serde_json::value::RawValue> = params.into();
+    let mut responsive = true;
+    loop {
+        tokio::time::sleep(HEALTH_PING_INTERVAL).await;
+        let ping = conn.ext_method(acp::ExtRequest::new(HEALTH_PING_METHOD, params.clone()));
+        let answered = tokio::time::timeout(HEALTH_PING_TIMEOUT, ping)
+            .await
+            .is_ok();
+        if answered != responsive {
+            responsive = answered;
+            set_agent_responsive(&app_handle, answered).await;
+        }
+    }
+}
+
+async fn set_agent_responsive(app_handle: &tauri::AppHandle, responsive: bool) {
+    let state = app_handle.state::<Mutex<AcpClientState>>();
+    let mut acp_state = state.lock().await;
+    let status = match (&acp_state.status, responsive) {
+        (AgentStatus::Running, false) => {
+            log::warn!(
+                "ACP agent did not answer a health ping within {:?}",
+                HEALTH_PING_TIMEOUT
+            );
+            AgentStatus::Unresponsive
+        }
+        (AgentStatus::Unresponsive, true) => {
+            log::info!("ACP agent is responding again");
+            AgentStatus::Running
+        }
+        _ => return,
+    };
+    acp_state.status = status;
+    emit_agent_status(app_handle, &acp_state.status);
+}
+
+/// Called from the worker thread once the agent process has died on its own.
+/// Clears the stale handles and, when `[agent] auto_restart` is set, starts it again.
+fn handle_agent_exit(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let state = app_handle.state::<Mutex<AcpClientState>>();
+        let (agent_path, restart) = {
+            let mut acp_state = state.lock().await;
+            // A stop or restart may already have replaced this worker.
+            if !acp_state.cmd_tx.as_ref().is_some_and(|tx| tx.is_closed()) {
+                return;
+            }
+            acp_state.cmd_tx = None;
+            acp_state.worker_handle = None;
+            acp_state.agent_pid = None;
+            let status = AgentStatus::Error("Agent process exited unexpectedly".to_string());
+            acp_state.status = status.clone();
+            emit_agent_status(&app_handle, &status);
+
+            let ran_long_enough = acp_state
+                .started_at
+                .is_some_and(|started| started.elapsed() >= AGENT_RESTART_RESET_AFTER);
+            if ran_long_enough {
+                acp_state.auto_restarts = 0;
+            }
+            let auto_restart = resolve_auto_restart(&app_handle);
+            let restart = auto_restart && acp_state.auto_restarts < MAX_AGENT_AUTO_RESTARTS;
+            if restart {
+                acp_state.auto_restarts += 1;
+            }
+            (acp_state.agent_path.take(), restart)
+        };
+
+        if !restart {
+            return;
+        }
+        tokio::time::sleep(AGENT_RESTART_DELAY).await;
+        log::info!("Restarting ACP agent after unexpected exit");
+        if let Err(err) = start_agent(&app_handle, agent_path).await {
+            log::warn!("Failed to restart ACP agent: {}", err);
+        }
+    });
+}
+
+fn resolve_auto_restart(app_handle: &tauri::AppHandle) -> bool {
+    app_handle
+        .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+        .and_then(|state| {
+            state
+                .lock()
+                .ok()
+                .map(|state| state.agent_config().auto_restart)
+        })
+        .unwrap_or(false)
 }
 
 // -- Managed state --
@@ -1154,9 +3235,21 @@ pub struct AcpClientState {
     cmd_tx: Option<mpsc::Sender<AcpCommand>>,
     worker_handle: Option<std::thread::JoinHandle<()>>,
     status: AgentStatus,
+    agent_path: Option<String>,
+    started_at: Option<std::time::Instant>,
+    /// OS process id of the running agent.
+    agent_pid: Option<u32>,
+    auto_restarts: u32,
     pending_permission_requests: PendingPermissionRequests,
     permission_request_counter: Arc<AtomicU64>,
     session_terminal_bindings: SessionTerminalBindings,
+    terminal_channels: TerminalChannels,
+    /// Validated working directory of each session, set at creation and by
+    /// `acp_set_session_cwd`.
+    session_cwds: std::collections::HashMap<String, PathBuf>,
+    /// Context blocks queued by `queue_session_context`, sent ahead of the
+    /// session's next prompt.
+    session_seeds: std::collections::HashMap<String, Vec<String>>,
 }
 
 impl AcpClientState {
@@ -1165,30 +3258,105 @@ impl AcpClientState {
             cmd_tx: None,
             worker_handle: None,
             status: AgentStatus::Stopped,
+            agent_path: None,
+            started_at: None,
+            agent_pid: None,
+            auto_restarts: 0,
             pending_permission_requests: Arc::new(Mutex::new(std::collections::HashMap::new())),
             permission_request_counter: Arc::new(AtomicU64::new(1)),
             session_terminal_bindings: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            terminal_channels: Arc::new(std::sync::Mutex::new(Default::default())),
+            session_cwds: std::collections::HashMap::new(),
+            session_seeds: std::collections::HashMap::new(),
+        }
+    }
+
+    pub fn agent_pid(&self) -> Option<u32> {
+        self.agent_pid
+    }
+}
+
+/// Stops the agent while the app exits. Unlike `acp_stop_agent` this doesn't
+/// wait on an in-flight prompt: the agent process is killed after `timeout`.
+pub async fn shutdown_agent(app_handle: &tauri::AppHandle, timeout: Duration) {
+    let state = app_handle.state::<Mutex<AcpClientState>>();
+    let (pending_permission_requests, tx, handle, agent_pid) = {
+        let mut acp_state = state.lock().await;
+        acp_state.session_cwds.clear();
+        acp_state.session_seeds.clear();
+        (
+            acp_state.pending_permission_requests.clone(),
+            acp_state.cmd_tx.take(),
+            acp_state.worker_handle.take(),
+            acp_state.agent_pid.take(),
+        )
+    };
+    cancel_pending_permission_requests(&pending_permission_requests, None).await;
+
+    let Some(tx) = tx else {
+        return;
+    };
+    let _ = tx.try_send(AcpCommand::Shutdown);
+    let joined = match handle {
+        Some(handle) => tokio::time::timeout(
+            timeout,
+            tauri::async_runtime::spawn_blocking(move || handle.join()),
+        )
+        .await
+        .is_ok(),
+        None => true,
+    };
+    if !joined {
+        if let Some(pid) = agent_pid {
+            log::warn!("ACP agent did not shut down in time; killing pid {}", pid);
+            kill_agent_process(pid);
         }
     }
 }
 
+#[cfg(unix)]
+fn kill_agent_process(pid: u32) {
+    unsafe {
+        libc::kill(pid as i32, libc::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+fn kill_agent_process(pid: u32) {
+    let _ = std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .output();
+}
+
 // -- Tauri IPC commands --
 
+/// Answers pending permission requests with `cancelled`: those of
+/// `session_id`, or all of them.
 async fn cancel_pending_permission_requests(
     pending_permission_requests: &PendingPermissionRequests,
+    session_id: Option<&str>,
 ) {
     let mut pending = pending_permission_requests.lock().await;
-    for (_, tx) in pending.drain() {
-        let _ = tx.send(acp::RequestPermissionOutcome::Cancelled);
+    let request_ids: Vec<String> = pending
+        .iter()
+        .filter(|(_, request)| session_id.is_none() || session_id == Some(&request.session_id))
+        .map(|(request_id, _)| request_id.clone())
+        .collect();
+    for request_id in request_ids {
+        if let Some(request) = pending.remove(&request_id) {
+            let _ = request.reply.send(acp::RequestPermissionOutcome::Cancelled);
+        }
     }
 }
 
-#[tauri::command]
-pub async fn acp_start_agent(
-    state: tauri::State<'_, Mutex<AcpClientState>>,
-    app_handle: tauri::AppHandle,
-    agent_path: String,
+/// Starts the agent described by `agent_path` and the `[agent]` config section.
+/// Shared by `acp_start_agent`, auto-start on launch and auto-restart.
+pub async fn start_agent(
+    app_handle: &tauri::AppHandle,
+    agent_path: Option<String>,
 ) -> Result<(), String> {
+    let launch = resolve_agent_launch(app_handle, agent_path);
+    let state = app_handle.state::<Mutex<AcpClientState>>();
     let mut acp_state = state.lock().await;
 
     if acp_state.cmd_tx.is_some() {
@@ -1196,18 +3364,22 @@ pub async fn acp_start_agent(
     }
 
     acp_state.status = AgentStatus::Starting;
+    emit_agent_status(app_handle, &acp_state.status);
 
     acp_state.session_terminal_bindings.lock().await.clear();
-    cancel_pending_permission_requests(&acp_state.pending_permission_requests).await;
+    acp_state.session_cwds.clear();
+    acp_state.session_seeds.clear();
+    cancel_pending_permission_requests(&acp_state.pending_permission_requests, None).await;
 
     let (cmd_tx, cmd_rx) = mpsc::channel::<AcpCommand>(32);
     let (ready_tx, ready_rx) = oneshot::channel();
 
     let handle = app_handle.clone();
-    let path = agent_path.clone();
+    let agent_path = launch.path.clone();
     let pending_permission_requests = acp_state.pending_permission_requests.clone();
     let permission_request_counter = acp_state.permission_request_counter.clone();
     let session_terminal_bindings = acp_state.session_terminal_bindings.clone();
+    let terminal_channels = acp_state.terminal_channels.clone();
 
     // Spawn a dedicated thread with its own tokio runtime + LocalSet
     let worker_handle = std::thread::spawn(move || {
@@ -1216,15 +3388,19 @@ pub async fn acp_start_agent(
             .build()
             .expect("Failed to create ACP worker runtime");
 
-        rt.block_on(acp_worker(
-            handle,
-            path,
+        let exited_unexpectedly = rt.block_on(acp_worker(
+            handle.clone(),
+            launch,
             pending_permission_requests,
             permission_request_counter,
             session_terminal_bindings,
+            terminal_channels,
             cmd_rx,
             ready_tx,
         ));
+        if exited_unexpectedly {
+            handle_agent_exit(handle);
+        }
     });
 
     // Wait for initialization to complete
@@ -1233,21 +3409,51 @@ pub async fn acp_start_agent(
         .map_err(|_| "Worker thread died".to_string())?;
 
     match init_result {
-        Ok(()) => {
+        Ok(agent_pid) => {
             acp_state.cmd_tx = Some(cmd_tx);
             acp_state.worker_handle = Some(worker_handle);
+            acp_state.agent_pid = agent_pid;
             acp_state.status = AgentStatus::Running;
+            acp_state.agent_path = Some(agent_path);
+            acp_state.started_at = Some(std::time::Instant::now());
+            emit_agent_status(app_handle, &acp_state.status);
             Ok(())
         }
         Err(e) => {
             acp_state.status = AgentStatus::Error(e.clone());
+            emit_agent_status(app_handle, &acp_state.status);
             Err(e)
         }
     }
 }
 
 #[tauri::command]
-pub async fn acp_stop_agent(state: tauri::State<'_, Mutex<AcpClientState>>) -> Result<(), String> {
+pub async fn acp_start_agent(
+    state: tauri::State<'_, Mutex<AcpClientState>>,
+    app_handle: tauri::AppHandle,
+    agent_path: Option<String>,
+) -> Result<(), NeoaiError> {
+    {
+        let mut acp_state = state.lock().await;
+        if acp_state.cmd_tx.is_some() {
+            return Err(NeoaiError::new(
+                ErrorCode::AgentAlreadyRunning,
+                "Agent already running. Stop it first.",
+            ));
+        }
+        // A manual start gets a fresh auto-restart budget.
+        acp_state.auto_restarts = 0;
+    }
+    start_agent(&app_handle, agent_path)
+        .await
+        .map_err(|e| NeoaiError::new(ErrorCode::AgentStartFailed, e))
+}
+
+#[tauri::command]
+pub async fn acp_stop_agent(
+    state: tauri::State<'_, Mutex<AcpClientState>>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), NeoaiError> {
     let (pending_permission_requests, session_terminal_bindings, tx, handle) = {
         let mut acp_state = state.lock().await;
         (
@@ -1258,36 +3464,476 @@ pub async fn acp_stop_agent(state: tauri::State<'_, Mutex<AcpClientState>>) -> R
         )
     };
 
-    cancel_pending_permission_requests(&pending_permission_requests).await;
-    session_terminal_bindings.lock().await.clear();
+    cancel_pending_permission_requests(&pending_permission_requests, None).await;
+    session_terminal_bindings.lock().await.clear();
+
+    if let Some(tx) = tx {
+        let _ = tx.send(AcpCommand::Shutdown).await;
+    }
+
+    // The worker thread will exit after processing Shutdown
+    if let Some(handle) = handle {
+        let _ = handle.join();
+    }
+
+    let mut acp_state = state.lock().await;
+    acp_state.status = AgentStatus::Stopped;
+    acp_state.agent_path = None;
+    acp_state.started_at = None;
+    acp_state.agent_pid = None;
+    emit_agent_status(&app_handle, &acp_state.status);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn acp_agent_status(
+    state: tauri::State<'_, Mutex<AcpClientState>>,
+) -> Result<AgentStatus, NeoaiError> {
+    let acp_state = state.lock().await;
+    Ok(acp_state.status.clone())
+}
+
+/// Also emits `terminal_id`'s events on `acp-event/<terminalId>` and
+/// `acp-event-batch/<terminalId>`, for views that only follow one chat.
+#[tauri::command]
+pub async fn acp_subscribe_terminal_events(
+    state: tauri::State<'_, Mutex<AcpClientState>>,
+    terminal_id: String,
+) -> Result<(), NeoaiError> {
+    let channels = state.lock().await.terminal_channels.clone();
+    channels
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(terminal_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn acp_unsubscribe_terminal_events(
+    state: tauri::State<'_, Mutex<AcpClientState>>,
+    terminal_id: String,
+) -> Result<(), NeoaiError> {
+    let channels = state.lock().await.terminal_channels.clone();
+    channels
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&terminal_id);
+    Ok(())
+}
+
+/// Canonical form of the `cwd` an agent asked a command to run in, joined to
+/// the session's directory when relative. Symlinks and `..` are resolved, so
+/// the result can be checked against the allowed roots.
+fn resolve_command_cwd(requested: &Path, session_cwd: Option<&Path>) -> Result<PathBuf, String> {
+    let joined = match session_cwd {
+        _ if requested.is_absolute() => requested.to_path_buf(),
+        Some(base) => base.join(requested),
+        None => {
+            return Err(format!(
+                "relative cwd '{}' needs a session directory",
+                requested.display()
+            ))
+        }
+    };
+    let path = fs::canonicalize(&joined)
+        .map_err(|_| format!("cwd '{}' does not exist", joined.display()))?;
+    if !path.is_dir() {
+        return Err(format!("cwd '{}' is not a directory", path.display()));
+    }
+    Ok(path)
+}
+
+/// Canonicalizes `dir` and checks it is an existing directory inside a
+/// folder registered by the frontend, so agents can't be pointed at
+/// arbitrary paths.
+fn validate_working_dir(app_handle: &tauri::AppHandle, dir: &str) -> Result<PathBuf, NeoaiError> {
+    let invalid = |message: String| NeoaiError::new(ErrorCode::InvalidWorkingDir, message);
+    if dir.trim().is_empty() {
+        return Err(invalid("No working directory given".to_string()));
+    }
+    let path = std::fs::canonicalize(dir)
+        .map_err(|e| invalid(format!("Working directory '{dir}' does not exist")).with_detail(e))?;
+    if !path.is_dir() {
+        return Err(invalid(format!("'{}' is not a directory", path.display())));
+    }
+    let registered = app_handle
+        .state::<std::sync::Mutex<folder_state::FolderStateStore>>()
+        .lock()
+        .map_err(|e| e.to_string())?
+        .folder_containing(&path)
+        .is_some();
+    if !registered {
+        return Err(invalid(format!(
+            "'{}' is not inside a registered folder",
+            path.display()
+        )));
+    }
+    Ok(path)
+}
+
+/// Agent and model a new session should use.
+struct SessionPreferences {
+    /// `None` runs whatever `[agent] path` resolves to.
+    agent_path: Option<String>,
+    model: Option<String>,
+}
+
+/// The preferences of the folder owning `terminal_id`, falling back to the
+/// `[agent] model` default.
+fn session_preferences(app_handle: &tauri::AppHandle, terminal_id: &str) -> SessionPreferences {
+    let (agent_path, model) = folder_state::preferences_for_terminal(app_handle, terminal_id);
+    let model = model.or_else(|| {
+        app_handle
+            .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+            .and_then(|state| {
+                state
+                    .lock()
+                    .ok()
+                    .and_then(|state| state.agent_config().model)
+            })
+    });
+    SessionPreferences { agent_path, model }
+}
+
+/// Restarts the running agent as `agent_path` when a folder prefers a
+/// different one. Only one agent runs at a time, so it is kept instead while
+/// other terminals still have sessions on it.
+async fn switch_to_preferred_agent(
+    app_handle: &tauri::AppHandle,
+    terminal_id: &str,
+    agent_path: Option<String>,
+) -> Result<(), String> {
+    let Some(agent_path) = agent_path else {
+        return Ok(());
+    };
+    let preferred = resolve_agent_launch(app_handle, Some(agent_path.clone())).path;
+    let state = app_handle.state::<Mutex<AcpClientState>>();
+    let (running, bindings) = {
+        let acp_state = state.lock().await;
+        (
+            acp_state.agent_path.clone(),
+            acp_state.session_terminal_bindings.clone(),
+        )
+    };
+    if running.as_deref() == Some(preferred.as_str()) {
+        return Ok(());
+    }
+    let in_use = bindings
+        .lock()
+        .await
+        .values()
+        .any(|bound| bound != terminal_id);
+    if in_use {
+        log::info!(
+            "Keeping agent {:?} for {}: other terminals have sessions on it (folder prefers '{}')",
+            running,
+            terminal_id,
+            preferred
+        );
+        return Ok(());
+    }
+
+    log::info!("Switching ACP agent to '{}' for {}", preferred, terminal_id);
+    acp_stop_agent(app_handle.state(), app_handle.clone())
+        .await
+        .map_err(String::from)?;
+    start_agent(app_handle, Some(agent_path)).await
+}
+
+#[tauri::command]
+pub async fn acp_create_session(
+    state: tauri::State<'_, Mutex<AcpClientState>>,
+    app_handle: tauri::AppHandle,
+    working_dir: String,
+    terminal_id: String,
+) -> Result<String, NeoaiError> {
+    let working_dir = validate_working_dir(&app_handle, &working_dir)?;
+    let preferences = session_preferences(&app_handle, &terminal_id);
+    if state.lock().await.cmd_tx.is_none() {
+        return Err(NeoaiError::agent_not_running());
+    }
+    switch_to_preferred_agent(&app_handle, &terminal_id, preferences.agent_path)
+        .await
+        .map_err(|e| NeoaiError::new(ErrorCode::AgentStartFailed, e))?;
+    let (tx, agent_path) = {
+        let acp_state = state.lock().await;
+        (
+            acp_state
+                .cmd_tx
+                .as_ref()
+                .cloned()
+                .ok_or_else(NeoaiError::agent_not_running)?,
+            acp_state.agent_path.clone(),
+        )
+    };
+
+    let (reply_tx, reply_rx) = oneshot::channel();
+
+    tx.send(AcpCommand::CreateSession {
+        working_dir: working_dir.clone(),
+        terminal_id: terminal_id.clone(),
+        model: preferences.model,
+        reply: reply_tx,
+    })
+    .await
+    .map_err(|_| NeoaiError::agent_worker_died())?;
+
+    let session_id = reply_rx
+        .await
+        .map_err(|_| NeoaiError::agent_worker_died())?
+        .map_err(|e| NeoaiError::new(ErrorCode::SessionFailed, e))?;
+    state
+        .lock()
+        .await
+        .session_cwds
+        .insert(session_id.clone(), working_dir);
+
+    folder_state::remember_for_terminal(
+        &app_handle,
+        &terminal_id,
+        folder_state::FolderSessionUpdate {
+            acp_session_id: Some(session_id.clone()),
+            agent_name: agent_path,
+            ..Default::default()
+        },
+    );
+
+    Ok(session_id)
+}
+
+#[tauri::command]
+pub async fn acp_send_prompt(
+    state: tauri::State<'_, Mutex<AcpClientState>>,
+    app_handle: tauri::AppHandle,
+    session_id: String,
+    messages: Vec<String>,
+    context: Option<String>,
+    context_blocks: Option<Vec<String>>,
+) -> Result<String, NeoaiError> {
+    // Separate blocks let unchanged sections be skipped on later turns.
+    let context = context_blocks.unwrap_or_else(|| context.into_iter().collect());
+    send_prompt(&state, &app_handle, session_id, messages, context).await
+}
+
+/// Outcome of one session's turn in `acp_send_prompt_multi`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcpMultiPromptResult {
+    pub session_id: String,
+    /// Set when the turn finished.
+    pub stop_reason: Option<String>,
+    /// Set when the turn failed.
+    pub error: Option<NeoaiError>,
+}
+
+/// Sends the same prompt to several sessions at once, e.g. to compare their
+/// answers side by side. Each session streams as usual, its events tagged
+/// with its `sessionId`; one session failing doesn't stop the others.
+#[tauri::command]
+pub async fn acp_send_prompt_multi(
+    app_handle: tauri::AppHandle,
+    session_ids: Vec<String>,
+    messages: Vec<String>,
+    context: Option<String>,
+    context_blocks: Option<Vec<String>>,
+) -> Result<Vec<AcpMultiPromptResult>, NeoaiError> {
+    let mut session_ids = session_ids;
+    let mut seen = std::collections::HashSet::new();
+    session_ids.retain(|id| seen.insert(id.clone()));
+    if session_ids.is_empty() {
+        return Err(NeoaiError::new(
+            ErrorCode::PromptFailed,
+            "No sessions to send the prompt to",
+        ));
+    }
+    let context = context_blocks.unwrap_or_else(|| context.into_iter().collect());
+
+    let turns: Vec<_> = session_ids
+        .into_iter()
+        .map(|session_id| {
+            let app_handle = app_handle.clone();
+            let messages = messages.clone();
+            let context = context.clone();
+            let turn = tauri::async_runtime::spawn(async move {
+                let state = app_handle.state::<Mutex<AcpClientState>>();
+                send_prompt(&state, &app_handle, session_id, messages, context).await
+            });
+            (session_id, turn)
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(turns.len());
+    for (session_id, turn) in turns {
+        let result = turn
+            .await
+            .unwrap_or_else(|e| Err(NeoaiError::new(ErrorCode::Internal, e.to_string())));
+        let (stop_reason, error) = match result {
+            Ok(stop_reason) => (Some(stop_reason), None),
+            Err(err) => (None, Some(err)),
+        };
+        results.push(AcpMultiPromptResult {
+            session_id,
+            stop_reason,
+            error,
+        });
+    }
+    Ok(results)
+}
+
+async fn send_prompt(
+    state: &Mutex<AcpClientState>,
+    app_handle: &tauri::AppHandle,
+    session_id: String,
+    messages: Vec<String>,
+    context: Vec<String>,
+) -> Result<String, NeoaiError> {
+    let (tx, session_terminal_bindings, session_cwd, seeds) = {
+        let acp_state = state.lock().await;
+        (
+            acp_state
+                .cmd_tx
+                .as_ref()
+                .cloned()
+                .ok_or_else(NeoaiError::agent_not_running)?,
+            acp_state.session_terminal_bindings.clone(),
+            acp_state.session_cwds.get(&session_id).cloned(),
+            acp_state.session_seeds.get(&session_id).cloned(),
+        )
+    };
+
+    let terminal_id = session_terminal_bindings
+        .lock()
+        .await
+        .get(&session_id)
+        .cloned();
+    let cwd = session_cwd.or_else(|| {
+        terminal_id
+            .as_deref()
+            .and_then(folder_state::folder_id_for_terminal)
+            .and_then(|folder_id| folder_state::resolve_folder_path(app_handle, folder_id).ok())
+    });
+    let mentions = mentions::resolve_mentions(
+        app_handle,
+        terminal_id.as_deref(),
+        cwd.as_deref(),
+        &messages,
+    )
+    .await;
 
-    if let Some(tx) = tx {
-        let _ = tx.send(AcpCommand::Shutdown).await;
+    let (reply_tx, reply_rx) = oneshot::channel();
+
+    record_trace(
+        "out",
+        "prompt",
+        format!(
+            "{session_id}: {} message(s), {} mention(s)",
+            messages.len(),
+            mentions.len()
+        ),
+    );
+    let seeded = seeds.is_some();
+    let context: Vec<String> = seeds
+        .unwrap_or_default()
+        .into_iter()
+        .chain(context)
+        .filter(|block| !block.trim().is_empty())
+        .collect();
+    check_prompt_fits(app_handle, &messages, &context, &mentions)?;
+    tx.send(AcpCommand::Prompt {
+        session_id: session_id.clone(),
+        messages,
+        context,
+        mentions,
+        reply: reply_tx,
+    })
+    .await
+    .map_err(|_| NeoaiError::agent_worker_died())?;
+    if seeded {
+        state.lock().await.session_seeds.remove(&session_id);
+    }
+    if let Some(terminal_id) = terminal_id.as_deref() {
+        folder_state::clear_draft_for_terminal(app_handle, terminal_id);
+        folder_state::note_terminal_activity(app_handle, terminal_id);
     }
 
-    // The worker thread will exit after processing Shutdown
-    if let Some(handle) = handle {
-        let _ = handle.join();
+    reply_rx
+        .await
+        .map_err(|_| NeoaiError::agent_worker_died())?
+        .map_err(|e| NeoaiError::new(ErrorCode::PromptFailed, e))
+}
+
+/// Rejects a prompt whose estimated size alone exceeds the context window,
+/// which the agent would fail on with a less helpful error.
+fn check_prompt_fits(
+    app_handle: &tauri::AppHandle,
+    messages: &[String],
+    context: &[String],
+    mentions: &[ResolvedMention],
+) -> Result<(), NeoaiError> {
+    let Some(limit) = app_handle
+        .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+        .and_then(|state| {
+            state
+                .lock()
+                .ok()
+                .and_then(|state| state.context_window_tokens())
+        })
+    else {
+        return Ok(());
+    };
+    let tokens = prompt_tokens(messages, context, mentions);
+    if tokens <= limit {
+        return Ok(());
     }
+    Err(NeoaiError::new(
+        ErrorCode::ContextWindowExceeded,
+        format!("This prompt is about {tokens} tokens, more than the {limit}-token context window"),
+    )
+    .with_detail(
+        "Attach less context or fewer @mentions, or split the request; \
+         agent.context_window_tokens sets the window size",
+    ))
+}
 
-    let mut acp_state = state.lock().await;
-    acp_state.status = AgentStatus::Stopped;
-    Ok(())
+/// Estimated tokens of everything a prompt sends.
+fn prompt_tokens(messages: &[String], blocks: &[String], mentions: &[ResolvedMention]) -> usize {
+    let text = messages
+        .iter()
+        .chain(blocks)
+        .map(|text| context::estimate_tokens(text));
+    let mentions = mentions
+        .iter()
+        .map(|mention| context::estimate_tokens(&mention.content));
+    text.chain(mentions).sum()
 }
 
-#[tauri::command]
-pub async fn acp_agent_status(
-    state: tauri::State<'_, Mutex<AcpClientState>>,
-) -> Result<AgentStatus, String> {
-    let acp_state = state.lock().await;
-    Ok(acp_state.status.clone())
+/// Queues `blocks` to be sent as context ahead of the next prompt to
+/// `session_id`, replacing anything queued before.
+pub async fn queue_session_context(
+    state: &Mutex<AcpClientState>,
+    session_id: &str,
+    blocks: Vec<String>,
+) {
+    state
+        .lock()
+        .await
+        .session_seeds
+        .insert(session_id.to_string(), blocks);
 }
 
-#[tauri::command]
-pub async fn acp_create_session(
-    state: tauri::State<'_, Mutex<AcpClientState>>,
-    working_dir: String,
-    terminal_id: String,
+/// Terminal whose chat `session_id` belongs to.
+pub async fn session_terminal(state: &Mutex<AcpClientState>, session_id: &str) -> Option<String> {
+    let bindings = state.lock().await.session_terminal_bindings.clone();
+    let terminal_id = bindings.lock().await.get(session_id).cloned();
+    terminal_id
+}
+
+/// Sends `prompt` to the running agent in a throwaway session and returns its
+/// reply text. Nothing is streamed to the chat.
+pub async fn prompt_captured(
+    state: &Mutex<AcpClientState>,
+    working_dir: PathBuf,
+    prompt: String,
 ) -> Result<String, String> {
     let tx = {
         let acp_state = state.lock().await;
@@ -1299,10 +3945,10 @@ pub async fn acp_create_session(
     };
 
     let (reply_tx, reply_rx) = oneshot::channel();
-
-    tx.send(AcpCommand::CreateSession {
-        working_dir: PathBuf::from(&working_dir),
-        terminal_id,
+    record_trace("out", "captured_prompt", format!("{} chars", prompt.len()));
+    tx.send(AcpCommand::CapturedPrompt {
+        working_dir,
+        prompt,
         reply: reply_tx,
     })
     .await
@@ -1313,37 +3959,237 @@ pub async fn acp_create_session(
         .map_err(|_| "Agent worker died".to_string())?
 }
 
+/// Starts the agent if needed and reopens `session_id` for `terminal_id`,
+/// falling back to a new session when there is none or it can't be loaded.
+/// Returns the session id and whether the previous session was resumed.
+pub async fn resume_session(
+    app_handle: &tauri::AppHandle,
+    working_dir: PathBuf,
+    terminal_id: &str,
+    session_id: Option<String>,
+) -> Result<(String, bool), String> {
+    let state = app_handle.state::<Mutex<AcpClientState>>();
+    let preferences = session_preferences(app_handle, terminal_id);
+    let running = state.lock().await.cmd_tx.is_some();
+    if running {
+        switch_to_preferred_agent(app_handle, terminal_id, preferences.agent_path).await?;
+    } else {
+        start_agent(app_handle, preferences.agent_path).await?;
+    }
+    let (tx, agent_path) = {
+        let acp_state = state.lock().await;
+        (
+            acp_state
+                .cmd_tx
+                .as_ref()
+                .cloned()
+                .ok_or("No agent running")?,
+            acp_state.agent_path.clone(),
+        )
+    };
+
+    let mut resumed = None;
+    if let Some(session_id) = session_id {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        tx.send(AcpCommand::LoadSession {
+            session_id: session_id.clone(),
+            working_dir: working_dir.clone(),
+            terminal_id: terminal_id.to_string(),
+            reply: reply_tx,
+        })
+        .await
+        .map_err(|_| "Agent worker died".to_string())?;
+        match reply_rx
+            .await
+            .map_err(|_| "Agent worker died".to_string())?
+        {
+            Ok(session_id) => resumed = Some(session_id),
+            Err(err) => log::info!(
+                "Could not resume ACP session {}; starting a new one: {}",
+                session_id,
+                err
+            ),
+        }
+    }
+
+    let (session_id, was_resumed) = match resumed {
+        Some(session_id) => (session_id, true),
+        None => {
+            let (reply_tx, reply_rx) = oneshot::channel();
+            tx.send(AcpCommand::CreateSession {
+                working_dir: working_dir.clone(),
+                terminal_id: terminal_id.to_string(),
+                model: preferences.model,
+                reply: reply_tx,
+            })
+            .await
+            .map_err(|_| "Agent worker died".to_string())?;
+            let session_id = reply_rx
+                .await
+                .map_err(|_| "Agent worker died".to_string())??;
+            (session_id, false)
+        }
+    };
+    state
+        .lock()
+        .await
+        .session_cwds
+        .insert(session_id.clone(), working_dir);
+
+    folder_state::remember_for_terminal(
+        app_handle,
+        terminal_id,
+        folder_state::FolderSessionUpdate {
+            acp_session_id: Some(session_id.clone()),
+            agent_name: agent_path,
+            ..Default::default()
+        },
+    );
+    Ok((session_id, was_resumed))
+}
+
+/// Moves a session to another directory, e.g. after the user switches
+/// worktrees mid-conversation. The agent reloads the session with the new
+/// cwd, so it needs `loadSession` support. Returns the canonical directory.
 #[tauri::command]
-pub async fn acp_send_prompt(
+pub async fn acp_set_session_cwd(
     state: tauri::State<'_, Mutex<AcpClientState>>,
-    _app_handle: tauri::AppHandle,
+    app_handle: tauri::AppHandle,
     session_id: String,
-    messages: Vec<String>,
-    context: Option<String>,
-) -> Result<String, String> {
-    let tx = {
+    dir: String,
+) -> Result<String, NeoaiError> {
+    let working_dir = validate_working_dir(&app_handle, &dir)?;
+    let (tx, session_terminal_bindings) = {
         let acp_state = state.lock().await;
-        acp_state
-            .cmd_tx
-            .as_ref()
-            .cloned()
-            .ok_or("No agent running")?
+        (
+            acp_state
+                .cmd_tx
+                .as_ref()
+                .cloned()
+                .ok_or_else(NeoaiError::agent_not_running)?,
+            acp_state.session_terminal_bindings.clone(),
+        )
     };
+    let terminal_id = session_terminal_bindings
+        .lock()
+        .await
+        .get(&session_id)
+        .cloned()
+        .ok_or_else(|| {
+            NeoaiError::new(
+                ErrorCode::SessionFailed,
+                format!("Unknown session: {session_id}"),
+            )
+        })?;
 
     let (reply_tx, reply_rx) = oneshot::channel();
+    tx.send(AcpCommand::LoadSession {
+        session_id: session_id.clone(),
+        working_dir: working_dir.clone(),
+        terminal_id,
+        reply: reply_tx,
+    })
+    .await
+    .map_err(|_| NeoaiError::agent_worker_died())?;
+    reply_rx
+        .await
+        .map_err(|_| NeoaiError::agent_worker_died())?
+        .map_err(|e| {
+            NeoaiError::new(
+                ErrorCode::SessionFailed,
+                "Failed to move the session to the new directory",
+            )
+            .with_detail(e)
+        })?;
 
-    tx.send(AcpCommand::Prompt {
+    log::info!(
+        "Moved ACP session {} to {}",
         session_id,
-        messages,
-        context,
-        reply: reply_tx,
+        working_dir.display()
+    );
+    let cwd = working_dir.to_string_lossy().into_owned();
+    state
+        .lock()
+        .await
+        .session_cwds
+        .insert(session_id, working_dir);
+    Ok(cwd)
+}
+
+/// Chooses what `session_id` streams to the UI. Thought chunks turned off
+/// here are dropped in the worker and never cross IPC.
+#[tauri::command]
+pub async fn acp_set_stream_options(
+    state: tauri::State<'_, Mutex<AcpClientState>>,
+    session_id: String,
+    options: StreamOptions,
+) -> Result<(), NeoaiError> {
+    let (tx, session_terminal_bindings) = {
+        let acp_state = state.lock().await;
+        (
+            acp_state
+                .cmd_tx
+                .as_ref()
+                .cloned()
+                .ok_or_else(NeoaiError::agent_not_running)?,
+            acp_state.session_terminal_bindings.clone(),
+        )
+    };
+    if !session_terminal_bindings
+        .lock()
+        .await
+        .contains_key(&session_id)
+    {
+        return Err(NeoaiError::new(
+            ErrorCode::SessionFailed,
+            format!("Unknown session: {session_id}"),
+        ));
+    }
+
+    tx.send(AcpCommand::SetStreamOptions {
+        session_id,
+        options,
     })
     .await
-    .map_err(|_| "Agent worker died".to_string())?;
+    .map_err(|_| NeoaiError::agent_worker_died())
+}
 
-    reply_rx
+/// Cancels the session's current turn and stops the commands it started.
+#[tauri::command]
+pub async fn acp_cancel_prompt(
+    state: tauri::State<'_, Mutex<AcpClientState>>,
+    app_handle: tauri::AppHandle,
+    session_id: String,
+) -> Result<(), NeoaiError> {
+    let (tx, session_terminal_bindings) = {
+        let acp_state = state.lock().await;
+        (
+            acp_state
+                .cmd_tx
+                .as_ref()
+                .cloned()
+                .ok_or_else(NeoaiError::agent_not_running)?,
+            acp_state.session_terminal_bindings.clone(),
+        )
+    };
+    if !session_terminal_bindings
+        .lock()
         .await
-        .map_err(|_| "Agent worker died".to_string())?
+        .contains_key(&session_id)
+    {
+        return Err(NeoaiError::new(
+            ErrorCode::SessionFailed,
+            format!("Unknown session: {session_id}"),
+        ));
+    }
+
+    tx.send(AcpCommand::Cancel {
+        session_id: session_id.clone(),
+    })
+    .await
+    .map_err(|_| NeoaiError::agent_worker_died())?;
+    tauri::async_runtime::spawn(terminate_session_commands(app_handle, Some(session_id)));
+    Ok(())
 }
 
 #[tauri::command]
@@ -1351,16 +4197,21 @@ pub async fn acp_respond_permission_request(
     state: tauri::State<'_, Mutex<AcpClientState>>,
     request_id: String,
     option_id: Option<String>,
-) -> Result<(), String> {
+) -> Result<(), NeoaiError> {
     let acp_state = state.lock().await;
     let pending_permission_requests = acp_state.pending_permission_requests.clone();
     drop(acp_state);
 
-    let tx = pending_permission_requests
+    let request = pending_permission_requests
         .lock()
         .await
         .remove(&request_id)
-        .ok_or_else(|| format!("Unknown permission request: {}", request_id))?;
+        .ok_or_else(|| {
+            NeoaiError::new(
+                ErrorCode::PermissionRequestNotFound,
+                format!("Unknown permission request: {}", request_id),
+            )
+        })?;
 
     let outcome = match option_id {
         Some(option_id) => {
@@ -1369,8 +4220,12 @@ pub async fn acp_respond_permission_request(
         None => acp::RequestPermissionOutcome::Cancelled,
     };
 
-    tx.send(outcome)
-        .map_err(|_| "Permission request is no longer active".to_string())?;
+    request.reply.send(outcome).map_err(|_| {
+        NeoaiError::new(
+            ErrorCode::PermissionRequestNotFound,
+            "Permission request is no longer active",
+        )
+    })?;
 
     Ok(())
 }
@@ -1379,6 +4234,213 @@ pub async fn acp_respond_permission_request(
 mod tests {
     use super::*;
 
+    #[test]
+    fn auto_approves_listed_tool_kinds_with_an_allow_option() {
+        assert_eq!(
+            tool_kind_name(acp::ToolKind::SwitchMode).as_deref(),
+            Some("switch_mode")
+        );
+        let options = [
+            acp::PermissionOption::new(
+                "always",
+                "Always allow",
+                acp::PermissionOptionKind::AllowAlways,
+            ),
+            acp::PermissionOption::new("once", "Allow", acp::PermissionOptionKind::AllowOnce),
+            acp::PermissionOption::new("no", "Reject", acp::PermissionOptionKind::RejectOnce),
+        ];
+        let kinds = vec!["read".to_string(), "fetch".to_string()];
+        assert_eq!(
+            auto_approve_option(Some(acp::ToolKind::Read), &options, &kinds).as_deref(),
+            Some("once")
+        );
+        assert_eq!(
+            auto_approve_option(Some(acp::ToolKind::Fetch), &options[..1], &kinds).as_deref(),
+            Some("always")
+        );
+        assert_eq!(
+            auto_approve_option(Some(acp::ToolKind::Execute), &options, &kinds),
+            None
+        );
+        assert_eq!(auto_approve_option(None, &options, &kinds), None);
+        assert_eq!(
+            auto_approve_option(Some(acp::ToolKind::Read), &options[2..], &kinds),
+            None
+        );
+    }
+
+    #[test]
+    fn reports_each_context_usage_threshold_once() {
+        let mut usage = ContextWindowUsage::default();
+        assert_eq!(usage.add(400, 1000), None);
+        assert_eq!(usage.add(100, 1000), Some(50));
+        assert_eq!(usage.add(100, 1000), None);
+        // Jumping past several thresholds reports the highest.
+        assert_eq!(usage.add(350, 1000), Some(90));
+        assert_eq!(usage.add(10, 1000), None);
+
+        // Reported usage replaces the estimate; after the agent compacts,
+        // thresholds can be reached again.
+        let reported = TokenUsage {
+            input_tokens: Some(200),
+            output_tokens: Some(50),
+        };
+        assert_eq!(usage.set_reported(reported, 1000), None);
+        assert_eq!(usage.tokens, 250);
+        assert_eq!(usage.add(300, 1000), Some(50));
+        assert_eq!(usage.set_reported(TokenUsage::default(), 1000), None);
+        assert_eq!(usage.tokens, 550);
+    }
+
+    #[test]
+    fn classifies_rate_limits_and_transient_failures_as_retryable() {
+        assert!(is_retryable_prompt_error(
+            "Internal error: {\"status\":429,\"message\":\"Rate limit reached\"}"
+        ));
+        assert!(is_retryable_prompt_error(
+            "Internal error: upstream Overloaded"
+        ));
+        assert!(is_retryable_prompt_error("stream error: request timed out"));
+        assert!(is_retryable_prompt_error("upstream returned HTTP 502"));
+        assert!(is_retryable_prompt_error(
+            "Internal error: {\"data\":{\"status_code\": 503}}"
+        ));
+        assert!(!is_retryable_prompt_error(
+            "Invalid params: unknown session"
+        ));
+        assert!(!is_retryable_prompt_error(
+            "Internal error: authentication required"
+        ));
+    }
+
+    #[test]
+    fn ignores_status_numbers_outside_a_status_position() {
+        assert!(!is_retryable_prompt_error(
+            "Internal error: failed to parse src/errors/503.rs at line 429"
+        ));
+        assert!(!is_retryable_prompt_error(
+            "Internal error: tool exited with code 1 after 504 files; status 5040"
+        ));
+        assert!(!is_retryable_prompt_error(
+            "Invalid params: timeout must be positive, try again"
+        ));
+    }
+
+    #[test]
+    fn classifies_prompt_errors_for_recovery() {
+        assert_eq!(
+            classify_prompt_error(Some(-32000), "Authentication required", None),
+            AcpErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            classify_prompt_error(Some(-32603), "Internal error: 401 Unauthorized", None),
+            AcpErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            classify_prompt_error(Some(-32602), "Invalid params: unknown session", None),
+            AcpErrorKind::ProtocolError
+        );
+        assert_eq!(
+            classify_prompt_error(None, "stream error: request timed out", None),
+            AcpErrorKind::Timeout
+        );
+        assert_eq!(
+            classify_prompt_error(None, "server shut down unexpectedly", None),
+            AcpErrorKind::AgentUnavailable
+        );
+        assert_eq!(
+            classify_prompt_error(Some(-32603), "Internal error", Some("call-3".to_string())),
+            AcpErrorKind::ToolCallFailed {
+                id: "call-3".to_string()
+            }
+        );
+        assert_eq!(
+            classify_prompt_error(Some(-32603), "Rate limit reached", None),
+            AcpErrorKind::Other
+        );
+        assert_eq!(
+            classify_prompt_error(Some(-32603), "Internal error: {\"status\": 403}", None),
+            AcpErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            classify_prompt_error(None, "upstream returned HTTP 504", None),
+            AcpErrorKind::Timeout
+        );
+        assert_eq!(
+            classify_prompt_error(None, "Please login again", None),
+            AcpErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            classify_prompt_error(Some(-32603), "Internal error: failed at main.rs:4012", None),
+            AcpErrorKind::Other
+        );
+        assert_eq!(
+            classify_prompt_error(Some(-32603), "Internal error: listening on :5040", None),
+            AcpErrorKind::Other
+        );
+        assert_eq!(
+            classify_prompt_error(Some(-32603), "Internal error: loginctl exited with 1", None),
+            AcpErrorKind::Other
+        );
+
+        let json = serde_json::to_value(AcpErrorKind::ToolCallFailed {
+            id: "call-3".to_string(),
+        })
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "code": "TOOL_CALL_FAILED", "id": "call-3" })
+        );
+    }
+
+    #[test]
+    fn resolves_command_cwd_against_the_session_directory() {
+        let root = std::env::temp_dir().join(format!("neoai-cwd-test-{}", std::process::id()));
+        let project = root.join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::create_dir_all(root.join("other")).unwrap();
+        let project = fs::canonicalize(&project).unwrap();
+
+        let inside = resolve_command_cwd(Path::new("src"), Some(&project)).unwrap();
+        assert_eq!(inside, project.join("src"));
+        assert!(inside.starts_with(&project));
+
+        let escaped = resolve_command_cwd(Path::new("../other"), Some(&project)).unwrap();
+        assert!(!escaped.starts_with(&project));
+
+        assert!(resolve_command_cwd(Path::new("missing"), Some(&project)).is_err());
+        assert!(resolve_command_cwd(Path::new("src"), None).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn extracts_permission_command_lines_and_paths() {
+        let command = |input: serde_json::Value| permission_command(&input);
+        assert_eq!(
+            command(serde_json::json!({ "command": ["bash", "-lc", "rm -rf build/"] })).as_deref(),
+            Some("rm -rf build/")
+        );
+        assert_eq!(
+            command(serde_json::json!({ "command": ["git", "commit", "-m", "fix it"] })).as_deref(),
+            Some("'git' 'commit' '-m' 'fix it'")
+        );
+        assert_eq!(
+            command(serde_json::json!({ "command": "npm", "args": ["run", "it's"] })).as_deref(),
+            Some("'npm' 'run' 'it'\"'\"'s'")
+        );
+        assert_eq!(
+            command(serde_json::json!({ "command": "cargo test --workspace" })).as_deref(),
+            Some("cargo test --workspace")
+        );
+        assert_eq!(command(serde_json::json!({ "path": "a.rs" })), None);
+
+        assert_eq!(
+            permission_paths(&serde_json::json!({ "path": "a.rs", "paths": ["b.rs", 3] })),
+            vec!["a.rs".to_string(), "b.rs".to_string()]
+        );
+    }
+
     #[test]
     fn recognizes_default_agent_paths() {
         assert!(is_default_agent_path("codex-acp"));
@@ -1386,6 +4448,114 @@ mod tests {
         assert!(!is_default_agent_path("/usr/local/bin/codex-acp"));
     }
 
+    #[test]
+    fn coalesces_adjacent_chunks_of_the_same_kind_and_session() {
+        let scope = |session: &str| AcpEventScope {
+            session_id: Some(session.to_string()),
+            terminal_id: Some(format!("terminal-{session}")),
+        };
+        let mut pending = Vec::new();
+        for (session, event) in [
+            ("a", AcpEvent::ContentChunk("Hel".to_string())),
+            ("a", AcpEvent::ContentChunk("lo".to_string())),
+            ("b", AcpEvent::ContentChunk("other".to_string())),
+            ("b", AcpEvent::ThoughtChunk("hmm".to_string())),
+            ("b", AcpEvent::ContentChunk("!".to_string())),
+        ] {
+            push_coalesced(
+                &mut pending,
+                ScopedAcpEvent {
+                    scope: scope(session),
+                    event,
+                },
+            );
+        }
+        let chunks: Vec<String> = pending
+            .iter()
+            .map(|scoped| {
+                let session = scoped.scope.session_id.as_deref().unwrap_or_default();
+                match &scoped.event {
+                    AcpEvent::ContentChunk(text) => format!("{session} content:{text}"),
+                    AcpEvent::ThoughtChunk(text) => format!("{session} thought:{text}"),
+                    other => panic!("unexpected event {other:?}"),
+                }
+            })
+            .collect();
+        assert_eq!(
+            chunks,
+            [
+                "a content:Hello",
+                "b content:other",
+                "b thought:hmm",
+                "b content:!"
+            ]
+        );
+
+        let json = serde_json::to_value(&pending[0]).unwrap();
+        assert_eq!(json["sessionId"], "a");
+        assert_eq!(json["terminalId"], "terminal-a");
+        assert_eq!(json["type"], "contentChunk");
+        assert_eq!(json["data"], "Hello");
+    }
+
+    #[test]
+    fn skips_context_blocks_already_sent_in_the_session() {
+        let blocks = |texts: &[&str]| -> Vec<(String, String)> {
+            texts
+                .iter()
+                .map(|text| (text.to_string(), text.to_string()))
+                .collect()
+        };
+        let mut ledger = ContextLedger::default();
+        let first = ledger.plan("s1", blocks(&["cursor v1", "diagnostics"]), true);
+        assert_eq!(first.blocks, vec!["cursor v1", "diagnostics"]);
+        ledger.record("s1", first.hashes);
+
+        let second = ledger.plan("s1", blocks(&["cursor v2", "diagnostics"]), true);
+        assert_eq!(second.blocks, vec!["cursor v2"]);
+        assert_eq!(second.stats.sent_blocks, 1);
+        assert_eq!(second.stats.reused_blocks, 1);
+        assert_eq!(
+            second.stats.reused_tokens,
+            context::estimate_tokens("diagnostics")
+        );
+
+        // Other sessions and disabled reuse get everything.
+        assert_eq!(
+            ledger
+                .plan("s2", blocks(&["diagnostics"]), true)
+                .blocks
+                .len(),
+            1
+        );
+        assert_eq!(
+            ledger
+                .plan("s1", blocks(&["diagnostics"]), false)
+                .blocks
+                .len(),
+            1
+        );
+
+        let json = serde_json::to_value(AcpEvent::Done {
+            turn: TurnMetadata {
+                stop_reason: StopReason::EndTurn,
+                duration_ms: 1500,
+                usage: TokenUsage {
+                    input_tokens: Some(900),
+                    output_tokens: None,
+                },
+                tool_calls: 2,
+            },
+            context: second.stats,
+        })
+        .unwrap();
+        assert_eq!(json["data"]["turn"]["stopReason"], "EndTurn");
+        assert_eq!(json["data"]["turn"]["inputTokens"], 900);
+        assert!(json["data"]["turn"]["outputTokens"].is_null());
+        assert_eq!(json["data"]["turn"]["toolCalls"], 2);
+        assert_eq!(json["data"]["context"]["reusedBlocks"], 1);
+    }
+
     #[test]
     fn resolves_release_assets_for_known_targets() {
         let mac = resolve_codex_asset_for("macos", "aarch64", None).expect("missing mac asset");
@@ -1403,10 +4573,49 @@ mod tests {
         assert_eq!(windows.archive, ArchiveFormat::Zip);
     }
 
+    #[test]
+    fn rewrites_asset_urls_to_a_mirror() {
+        let asset = resolve_codex_asset_for("linux", "x86_64", Some("gnu")).unwrap();
+        assert_eq!(asset_download_url(asset.url, None), asset.url);
+        assert_eq!(
+            asset_download_url(asset.url, Some("https://mirror.corp/codex-acp")),
+            "https://mirror.corp/codex-acp/v0.9.2/codex-acp-0.9.2-x86_64-unknown-linux-gnu.tar.gz"
+        );
+    }
+
     #[test]
     fn checksum_verification_detects_mismatch() {
         let abc_sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
         assert!(verify_sha256(b"abc", abc_sha256).is_ok());
         assert!(verify_sha256(b"abc", "deadbeef").is_err());
     }
+
+    #[test]
+    fn cancelling_a_session_answers_only_its_permission_requests() {
+        let pending: PendingPermissionRequests = Default::default();
+        let request = |request_id: &str, session_id: &str| {
+            let (reply, rx) = oneshot::channel();
+            pending.try_lock().unwrap().insert(
+                request_id.to_string(),
+                PendingPermission {
+                    session_id: session_id.to_string(),
+                    reply,
+                },
+            );
+            rx
+        };
+        let mut cancelled = request("r1", "s1");
+        let mut other = request("r2", "s2");
+
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(cancel_pending_permission_requests(&pending, Some("s1")));
+        assert!(matches!(
+            cancelled.try_recv(),
+            Ok(acp::RequestPermissionOutcome::Cancelled)
+        ));
+        assert!(other.try_recv().is_err());
+        assert!(pending.try_lock().unwrap().contains_key("r2"));
+    }
 }
@@ -0,0 +1,409 @@
+//! Agent plumbing shared by the app and `neoai-cli`: how the agent process is
+//! launched, and a client that drives one prompt without a window, Neovim or
+//! tmux behind it.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use acp::Agent as _;
+use agent_client_protocol as acp;
+use serde::{Deserialize, Serialize};
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+use crate::acp_client::{is_default_agent_path, managed_codex_path, DEFAULT_AGENT_PATH};
+use crate::app_config::{self, AgentConfig};
+use crate::app_paths;
+use crate::diff;
+use crate::edit_snapshots;
+use crate::nvim_bridge::apply_line_window;
+
+pub use crate::diff::TextDiff;
+
+/// Program, arguments and environment used to launch the agent.
+pub struct AgentLaunch {
+    pub path: String,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+/// Resolves the launch from `[agent]` settings; a non-empty `agent_path`
+/// overrides the configured program.
+pub fn agent_launch(agent: AgentConfig, agent_path: Option<String>) -> AgentLaunch {
+    let path = agent_path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .or(agent.path)
+        .unwrap_or_else(|| DEFAULT_AGENT_PATH.to_string());
+
+    AgentLaunch {
+        path,
+        args: agent.args,
+        env: agent.env,
+    }
+}
+
+pub fn spawn_agent_process(
+    agent_path: &str,
+    launch: &AgentLaunch,
+) -> Result<tokio::process::Child, std::io::Error> {
+    tokio::process::Command::new(agent_path)
+        .args(&launch.args)
+        .envs(crate::secrets::resolve_env(&launch.env))
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+}
+
+/// Why a prompt turn ended. `Error` covers turns that failed instead of
+/// stopping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopReason {
+    EndTurn,
+    MaxTokens,
+    MaxTurnRequests,
+    Refusal,
+    Cancelled,
+    Error,
+}
+
+impl From<acp::StopReason> for StopReason {
+    fn from(reason: acp::StopReason) -> Self {
+        match reason {
+            acp::StopReason::EndTurn => Self::EndTurn,
+            acp::StopReason::MaxTokens => Self::MaxTokens,
+            acp::StopReason::MaxTurnRequests => Self::MaxTurnRequests,
+            acp::StopReason::Refusal => Self::Refusal,
+            acp::StopReason::Cancelled => Self::Cancelled,
+            #[allow(unreachable_patterns)]
+            _ => Self::EndTurn,
+        }
+    }
+}
+
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Tokens a turn used, when the agent reports them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+}
+
+impl TokenUsage {
+    /// Reads usage from a serialized prompt response. ACP has no standard
+    /// field yet, so this accepts `usage` at the top level or under `_meta`,
+    /// with camelCase or snake_case counters.
+    pub fn from_response(response: &serde_json::Value) -> Self {
+        let usage = response
+            .get("usage")
+            .or_else(|| response.get("_meta").and_then(|meta| meta.get("usage")));
+        let Some(usage) = usage else {
+            return Self::default();
+        };
+        let count = |camel: &str, snake: &str| {
+            usage
+                .get(camel)
+                .or_else(|| usage.get(snake))
+                .and_then(serde_json::Value::as_u64)
+        };
+        Self {
+            input_tokens: count("inputTokens", "input_tokens"),
+            output_tokens: count("outputTokens", "output_tokens"),
+        }
+    }
+}
+
+/// Where a headless prompt's output goes. Called on the prompt's thread.
+pub trait HeadlessOutput {
+    fn message_chunk(&self, text: &str);
+    fn thought_chunk(&self, text: &str);
+    fn tool_call(&self, id: &str, title: &str, kind: &str);
+    fn tool_call_updated(&self, id: &str, status: &str);
+    fn diff(&self, id: &str, path: &str, diff: &TextDiff);
+    /// Picks one of `options` by id, or `None` to cancel the tool call.
+    fn choose_permission(
+        &self,
+        title: Option<&str>,
+        options: &[acp::PermissionOption],
+    ) -> Option<String>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HeadlessOptions {
+    /// Working directory for the session; must be absolute.
+    pub cwd: PathBuf,
+    /// Overrides `agent.path` from `config.toml`.
+    pub agent_path: Option<String>,
+}
+
+/// Outcome of a headless prompt.
+#[derive(Debug, Clone)]
+pub struct HeadlessRun {
+    pub session_id: String,
+    pub stop_reason: StopReason,
+    /// Snapshots taken before the agent's writes, for `rollback_edit`.
+    pub snapshot_ids: Vec<String>,
+}
+
+struct HeadlessClient {
+    output: Rc<dyn HeadlessOutput>,
+    snapshots_root: PathBuf,
+    snapshot_ids: Rc<std::cell::RefCell<Vec<String>>>,
+}
+
+fn require_absolute(path: &Path) -> acp::Result<()> {
+    if path.is_absolute() {
+        Ok(())
+    } else {
+        Err(acp::Error::invalid_params()
+            .data(serde_json::json!({ "reason": "path must be absolute", "path": path })))
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl acp::Client for HeadlessClient {
+    async fn request_permission(
+        &self,
+        args: acp::RequestPermissionRequest,
+    ) -> acp::Result<acp::RequestPermissionResponse> {
+        let choice = self
+            .output
+            .choose_permission(args.tool_call.fields.title.as_deref(), &args.options);
+        let outcome = match choice {
+            Some(option_id) => acp::RequestPermissionOutcome::Selected(
+                acp::SelectedPermissionOutcome::new(option_id),
+            ),
+            None => acp::RequestPermissionOutcome::Cancelled,
+        };
+        Ok(acp::RequestPermissionResponse::new(outcome))
+    }
+
+    async fn session_notification(&self, args: acp::SessionNotification) -> acp::Result<()> {
+        match args.update {
+            acp::SessionUpdate::AgentMessageChunk(chunk) => {
+                if let acp::ContentBlock::Text(text) = chunk.content {
+                    self.output.message_chunk(&text.text);
+                }
+            }
+            acp::SessionUpdate::AgentThoughtChunk(chunk) => {
+                if let acp::ContentBlock::Text(text) = chunk.content {
+                    self.output.thought_chunk(&text.text);
+                }
+            }
+            acp::SessionUpdate::ToolCall(tool_call) => {
+                let id = tool_call.tool_call_id.to_string();
+                self.output
+                    .tool_call(&id, &tool_call.title, &format!("{:?}", tool_call.kind));
+                self.report_diffs(&id, &tool_call.content);
+            }
+            acp::SessionUpdate::ToolCallUpdate(update) => {
+                let id = update.tool_call_id.to_string();
+                let status = update
+                    .fields
+                    .status
+                    .map(|status| format!("{:?}", status))
+                    .unwrap_or_else(|| "updated".to_string());
+                self.output.tool_call_updated(&id, &status);
+                if let Some(content) = &update.fields.content {
+                    self.report_diffs(&id, content);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn read_text_file(
+        &self,
+        args: acp::ReadTextFileRequest,
+    ) -> acp::Result<acp::ReadTextFileResponse> {
+        require_absolute(&args.path)?;
+        let content = std::fs::read_to_string(&args.path).map_err(|e| {
+            acp::Error::internal_error()
+                .data(format!("Failed to read '{}': {e}", args.path.display()))
+        })?;
+        Ok(acp::ReadTextFileResponse::new(apply_line_window(
+            &content, args.line, args.limit,
+        )))
+    }
+
+    async fn write_text_file(
+        &self,
+        args: acp::WriteTextFileRequest,
+    ) -> acp::Result<acp::WriteTextFileResponse> {
+        require_absolute(&args.path)?;
+        match edit_snapshots::take_snapshot(
+            &self.snapshots_root,
+            &args.session_id.to_string(),
+            "write_text_file",
+            &[args.path.clone()],
+        ) {
+            Ok(snapshot) => self.snapshot_ids.borrow_mut().push(snapshot.id),
+            Err(err) => log::warn!("Failed to snapshot before write: {}", err),
+        }
+        if let Some(parent) = args.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| acp::Error::internal_error().data(e.to_string()))?;
+        }
+        std::fs::write(&args.path, &args.content).map_err(|e| {
+            acp::Error::internal_error()
+                .data(format!("Failed to write '{}': {e}", args.path.display()))
+        })?;
+        Ok(acp::WriteTextFileResponse::new())
+    }
+}
+
+impl HeadlessClient {
+    fn report_diffs(&self, id: &str, content: &[acp::ToolCallContent]) {
+        for item in content {
+            if let acp::ToolCallContent::Diff(diff) = item {
+                let original = diff.old_text.as_deref().unwrap_or_default();
+                let text_diff = diff::compute_diff(original, &diff.new_text);
+                self.output
+                    .diff(id, &diff.path.to_string_lossy(), &text_diff);
+            }
+        }
+    }
+}
+
+/// Spawns the configured agent, falling back to an already-installed managed
+/// codex-acp when the default agent is not on `PATH`. Unlike the app, this
+/// never downloads one.
+fn spawn_headless_agent(
+    root: &Path,
+    launch: &AgentLaunch,
+) -> Result<tokio::process::Child, String> {
+    match spawn_agent_process(&launch.path, launch) {
+        Ok(child) => Ok(child),
+        Err(err)
+            if err.kind() == std::io::ErrorKind::NotFound
+                && is_default_agent_path(&launch.path) =>
+        {
+            let managed = managed_codex_path(root);
+            spawn_agent_process(&managed.to_string_lossy(), launch).map_err(|_| {
+                format!(
+                    "Agent '{}' was not found on PATH and no managed codex-acp is installed at '{}'. Start NeoAI once to install it, or pass --agent",
+                    launch.path,
+                    managed.display()
+                )
+            })
+        }
+        Err(err) => Err(format!("Failed to spawn agent '{}': {}", launch.path, err)),
+    }
+}
+
+/// Runs one prompt to completion against a fresh agent session, streaming
+/// updates to `output`. Must be polled inside a `tokio::task::LocalSet`
+/// because the ACP connection is `!Send`.
+pub async fn run_prompt(
+    options: HeadlessOptions,
+    prompt: String,
+    output: Rc<dyn HeadlessOutput>,
+) -> Result<HeadlessRun, String> {
+    if !options.cwd.is_absolute() {
+        return Err(format!(
+            "Working directory '{}' must be absolute",
+            options.cwd.display()
+        ));
+    }
+    let root = app_paths::standalone_root_dir()?;
+    let launch = agent_launch(app_config::read_agent_config(&root), options.agent_path);
+    let mut child = spawn_headless_agent(&root, &launch)?;
+    let agent_stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| "Failed to take agent stdin".to_string())?;
+    let agent_stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| "Failed to take agent stdout".to_string())?;
+
+    let snapshot_ids = Rc::new(std::cell::RefCell::new(Vec::new()));
+    let client = HeadlessClient {
+        output,
+        snapshots_root: edit_snapshots::snapshots_dir(&root),
+        snapshot_ids: snapshot_ids.clone(),
+    };
+    let (conn, io_future) = acp::ClientSideConnection::new(
+        client,
+        agent_stdin.compat_write(),
+        agent_stdout.compat(),
+        |fut| {
+            tokio::task::spawn_local(fut);
+        },
+    );
+    tokio::task::spawn_local(io_future);
+
+    conn.initialize(
+        acp::InitializeRequest::new(acp::ProtocolVersion::V1)
+            .client_capabilities(
+                acp::ClientCapabilities::new()
+                    .fs(acp::FileSystemCapability::new()
+                        .read_text_file(true)
+                        .write_text_file(true))
+                    .terminal(false),
+            )
+            .client_info(acp::Implementation::new(
+                "neoai-cli",
+                env!("CARGO_PKG_VERSION"),
+            )),
+    )
+    .await
+    .map_err(|e| format!("Agent initialization failed: {e}"))?;
+
+    let session = conn
+        .new_session(acp::NewSessionRequest::new(options.cwd))
+        .await
+        .map_err(|e| format!("Failed to create session: {e}"))?;
+    let session_id = session.session_id.to_string();
+
+    let response = conn
+        .prompt(acp::PromptRequest::new(
+            session.session_id,
+            vec![prompt.into()],
+        ))
+        .await
+        .map_err(|e| format!("Prompt failed: {e}"))?;
+
+    let _ = child.start_kill();
+    let snapshot_ids = snapshot_ids.borrow().clone();
+    Ok(HeadlessRun {
+        session_id,
+        stop_reason: response.stop_reason.into(),
+        snapshot_ids,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_token_usage_from_the_response_or_its_meta() {
+        let usage = TokenUsage::from_response(&serde_json::json!({
+            "stopReason": "end_turn",
+            "usage": { "inputTokens": 1200, "outputTokens": 340 }
+        }));
+        assert_eq!(usage.input_tokens, Some(1200));
+        assert_eq!(usage.output_tokens, Some(340));
+
+        let usage = TokenUsage::from_response(&serde_json::json!({
+            "_meta": { "usage": { "input_tokens": 5 } }
+        }));
+        assert_eq!(usage.input_tokens, Some(5));
+        assert_eq!(usage.output_tokens, None);
+
+        assert_eq!(
+            TokenUsage::from_response(&serde_json::json!({ "stopReason": "cancelled" })),
+            TokenUsage::default()
+        );
+    }
+}
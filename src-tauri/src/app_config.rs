@@ -1,10 +1,27 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
-use serde::Deserialize;
-use tauri::Manager;
+use log::LevelFilter;
 
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{Emitter, Manager};
+
+use crate::app_paths;
+use crate::global_shortcut;
+use crate::logging;
+use crate::secrets;
+use crate::socket_manager::{self, SocketManager};
+use crate::tmux_control;
 use crate::tmux_runtime::TmuxCommandMode;
 
+/// Quiet period after the last file event before `config.toml` is re-read;
+/// editors often save through several writes and renames.
+const CONFIG_RELOAD_DEBOUNCE: Duration = Duration::from_millis(250);
+
 const DEFAULT_CONFIG_TEMPLATE: &str = r#"# NeoAI configuration
 # How ACP command terminals are placed in tmux: split | window | hidden
 tmux_command_mode = "split"
@@ -14,13 +31,365 @@ allow_agent_tmux_override = true
 
 # Accepted values for agent-requested mode overrides.
 agent_tmux_override_whitelist = ["split", "window", "hidden"]
+
+# Shell that runs agent commands in tmux panes; it is `exec`ed in each new pane. By default
+# commands are typed into the pane's own shell, quoted for it (POSIX shells, fish or PowerShell).
+# command_shell = "/bin/sh"
+
+# Leave the tmux sessions hosting Neovim running when NeoAI quits, so folders
+# reattach to them on the next launch. Agent command panes are always closed.
+keep_nvim_sessions_on_exit = true
+
+# Talk to tmux over one persistent control-mode (`tmux -C`) connection instead of
+# running a tmux process per call; it also reports pane exits and window renames as
+# they happen. Turn off if a tmux build misbehaves in control mode.
+# tmux_control_mode = true
+
+# Directory for Neovim RPC sockets (absolute path).
+# Defaults to $XDG_RUNTIME_DIR/neoai, or a per-user directory under the system temp dir.
+# socket_dir = "/run/user/1000/neoai"
+
+# Log verbosity for files under <app folder>/logs: off | error | warn | info | debug | trace
+# log_level = "info"
+
+# Look for a newer NeoAI release on GitHub shortly after launch.
+# check_for_updates = true
+
+# System-wide shortcut that shows/hides the NeoAI window and focuses the last used terminal.
+# global_shortcut = "CommandOrControl+Shift+Space"
+
+# Mirror for the managed codex-acp download, laid out like GitHub's release downloads
+# (<base>/v<version>/<asset>). Checksums are verified against the pinned values either way.
+# agent_download_base_url = "https://mirror.example.com/codex-acp/releases/download"
+
+# ACP agent launched by NeoAI. `path` defaults to codex-acp.
+# [agent]
+# path = "codex-acp"
+# args = []
+# Model new sessions ask the agent for; a folder's preferred model (folder_session_update) wins.
+# Unset keeps the agent's own default.
+# model = "gpt-5"
+# env = { RUST_LOG = "info" }
+# Credentials belong in the OS keychain: store them with `secret_set` and
+# reference them by name, e.g. env = { OPENAI_API_KEY = "secret:openai_api_key" }
+# auto_start_on_launch = false
+# auto_restart = false
+# Streamed reply chunks are coalesced per frame of this many milliseconds; 0 sends each one.
+# event_batch_ms = 16
+# Stream the agent's reasoning to the chat; acp_set_stream_options overrides it per session.
+# stream_thoughts = true
+# Let the agent ask (with a permission prompt) to read the screen of the terminal its chat belongs to.
+# allow_terminal_snapshot = false
+# Tool call kinds approved without asking, e.g. ["read", "search", "fetch"]. Kinds are read, edit,
+# delete, move, search, execute, think, fetch, switch_mode and other. Each approval is still
+# written to the audit log and shown in the chat.
+# auto_approve_kinds = []
+# Prompts failing with a rate-limit or transient error are retried this many times, backing off
+# exponentially from prompt_retry_base_ms up to prompt_retry_max_ms; 0 disables retries.
+# prompt_retries = 3
+# prompt_retry_base_ms = 1000
+# prompt_retry_max_ms = 30000
+# Randomize each delay between half and all of its backoff so sessions don't retry in lockstep.
+# prompt_retry_jitter = true
+# Commands (tmux panes) one agent session may run at once; 0 removes the limit. Further
+# commands wait for a slot, or are rejected when queue_excess_commands = false.
+# max_concurrent_commands = 4
+# queue_excess_commands = true
+# Environment variables agents may set on their commands; a trailing `*` matches a prefix and
+# names compare case-insensitively. An empty allow list permits every name that isn't denied.
+# command_env_allow = []
+# command_env_deny = ["PATH", "LD_*", "DYLD_*"]
+# Commands run inside the session's project folder; list absolute directories to allow outside it.
+# command_cwd_allow = ["/tmp"]
+# Once a command's captured output exceeds this many bytes, the full output is also saved under
+# command-logs/ in the app folder and its path returned to the agent; 0 never saves it.
+# command_output_spill_bytes = 262144
+# Context window of the agent's model, in tokens. NeoAI estimates how much of it each session has
+# used, warns at 50%, 75% and 90%, and refuses prompts larger than the whole window; 0 disables.
+# context_window_tokens = 200000
+
+# Defaults for embedded terminals when the UI does not set them.
+# [terminal]
+# font_size = 13
+# theme = "Builtin Dark"
+# padding = 8
+# corner_radius = 6
+# scrollback_limit = 10000000
+# Hidden terminals kept with a shell already started, so new ones open instantly (at most 4).
+# prewarm = 0
+# Treat the macOS Option key as Alt (Meta) instead of using it to type special characters.
+# option_as_alt = false
+
+# OS notifications shown while the NeoAI window is unfocused.
+# [notifications]
+# turn_finished = true
+# permission_request = true
+# command_finished = true
+# command_min_duration_secs = 10
+
+# Prompt context assembled from Neovim, diagnostics, recent files and git diff.
+# [context]
+# token_budget = 8000
+# recent_files = 10
+# Skip context sections the agent already received earlier in the session.
+# context_reuse = true
+
+# Neovim keymaps NeoAI adds (<leader>mf, <leader>mi, <leader>me, <leader>ma) when
+# one of them is already mapped: "skip" keeps your mapping, "relocate" binds
+# NeoAI's under relocate_prefix instead, "override" replaces yours.
+# [keymaps]
+# on_conflict = "skip"
+# relocate_prefix = "<leader>M"
 "#;
 
+/// `[agent]` section: how the ACP agent process is launched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentConfig {
+    pub path: Option<String>,
+    pub args: Vec<String>,
+    /// Model requested for new sessions, unless their folder prefers another.
+    pub model: Option<String>,
+    pub env: BTreeMap<String, String>,
+    pub auto_start_on_launch: bool,
+    pub auto_restart: bool,
+    /// Frame over which streamed chunks are batched into one UI event; 0 disables.
+    pub event_batch_ms: u64,
+    /// Forward thought chunks to the UI unless a session turned them off.
+    pub stream_thoughts: bool,
+    /// Offer the `_neoai/terminal_snapshot` extension method to the agent.
+    pub allow_terminal_snapshot: bool,
+    /// Tool call kinds whose permission requests are approved without asking.
+    pub auto_approve_kinds: Vec<String>,
+    /// Retries after a rate-limited or transient prompt failure; 0 disables.
+    pub prompt_retries: u32,
+    pub prompt_retry_base_ms: u64,
+    pub prompt_retry_max_ms: u64,
+    pub prompt_retry_jitter: bool,
+    /// Commands a session may run at once; 0 is unlimited.
+    pub max_concurrent_commands: u32,
+    /// Wait for a free slot instead of rejecting commands over the limit.
+    pub queue_excess_commands: bool,
+    /// Env var patterns agents may set on commands; empty allows all.
+    pub command_env_allow: Vec<String>,
+    pub command_env_deny: Vec<String>,
+    /// Directories outside the project where agent commands may run.
+    pub command_cwd_allow: Vec<PathBuf>,
+    /// Output size above which it is also written to `command-logs/`; 0 disables.
+    pub command_output_spill_bytes: u64,
+    /// Model context window used for usage warnings; 0 disables them.
+    pub context_window_tokens: u64,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            args: Vec::new(),
+            model: None,
+            env: BTreeMap::new(),
+            auto_start_on_launch: false,
+            auto_restart: false,
+            event_batch_ms: 16,
+            stream_thoughts: true,
+            allow_terminal_snapshot: false,
+            auto_approve_kinds: Vec::new(),
+            prompt_retries: 3,
+            prompt_retry_base_ms: 1000,
+            prompt_retry_max_ms: 30_000,
+            prompt_retry_jitter: true,
+            max_concurrent_commands: 4,
+            queue_excess_commands: true,
+            command_env_allow: Vec::new(),
+            command_env_deny: default_command_env_deny(),
+            command_cwd_allow: Vec::new(),
+            command_output_spill_bytes: 256 * 1024,
+            context_window_tokens: 200_000,
+        }
+    }
+}
+
+fn default_command_env_deny() -> Vec<String> {
+    ["PATH", "LD_*", "DYLD_*"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+/// Which environment variables an agent may set on the commands it runs,
+/// from `[agent]`. The default value permits everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvRules {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl EnvRules {
+    /// Whether `name` may be set: it matches no deny pattern and, when there
+    /// is an allow list, some allow pattern.
+    pub fn permits(&self, name: &str) -> bool {
+        let matches = |pattern: &String| match pattern.strip_suffix('*') {
+            Some(prefix) => name
+                .get(..prefix.len())
+                .is_some_and(|head| head.eq_ignore_ascii_case(prefix)),
+            None => name.eq_ignore_ascii_case(pattern),
+        };
+        !self.deny.iter().any(matches) && (self.allow.is_empty() || self.allow.iter().any(matches))
+    }
+}
+
+/// When and how often a failed prompt is retried, from `[agent]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (1-based): the base delay doubled per
+    /// earlier retry and capped at the maximum. With jitter, `seed` picks a
+    /// point between half and all of that delay.
+    pub fn delay(&self, retry: u32, seed: u64) -> Duration {
+        let factor = 1u32
+            .checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        let backoff = self
+            .base_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        if !self.jitter {
+            return backoff;
+        }
+        let half = backoff.as_millis() as u64 / 2;
+        Duration::from_millis(half + seed % (half + 1))
+    }
+}
+
+/// `[terminal]` section: defaults for embedded Ghostty terminals.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TerminalConfig {
+    pub font_size: Option<f32>,
+    pub theme: Option<String>,
+    /// Uniform inset in points between the terminal host element and its content.
+    pub padding: Option<f64>,
+    pub corner_radius: Option<f64>,
+    /// Ghostty `scrollback-limit`, in bytes.
+    pub scrollback_limit: Option<u64>,
+    /// Hidden terminals to keep ready for adoption; 0 disables the pool.
+    pub prewarm: usize,
+    /// macOS Option sends Alt rather than composing characters.
+    pub option_as_alt: bool,
+}
+
+/// `[notifications]` section: which events raise an OS notification while
+/// the window is unfocused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotificationConfig {
+    pub turn_finished: bool,
+    pub permission_request: bool,
+    pub command_finished: bool,
+    /// Agent commands that finish sooner than this don't notify.
+    pub command_min_duration: Duration,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            turn_finished: true,
+            permission_request: true,
+            command_finished: true,
+            command_min_duration: Duration::from_secs(10),
+        }
+    }
+}
+
+/// `[context]` section: limits for prompt context built by the backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextConfig {
+    /// Estimated tokens the assembled context may use.
+    pub token_budget: usize,
+    /// Recently used buffers listed alongside the current file.
+    pub recent_files: usize,
+    /// Leave out context blocks already sent unchanged in the same session.
+    pub context_reuse: bool,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            token_budget: 8_000,
+            recent_files: 10,
+            context_reuse: true,
+        }
+    }
+}
+
+/// What keymap injection does with a `<leader>m*` key the user already mapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeymapConflictPolicy {
+    Skip,
+    Relocate,
+    Override,
+}
+
+impl KeymapConflictPolicy {
+    pub fn from_config_str(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "skip" => Some(Self::Skip),
+            "relocate" => Some(Self::Relocate),
+            "override" => Some(Self::Override),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Relocate => "relocate",
+            Self::Override => "override",
+        }
+    }
+}
+
+/// `[keymaps]` section: how NeoAI's Neovim keymaps treat existing mappings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeymapConfig {
+    pub on_conflict: KeymapConflictPolicy,
+    /// Replaces `<leader>m` for relocated keymaps.
+    pub relocate_prefix: String,
+}
+
+impl Default for KeymapConfig {
+    fn default() -> Self {
+        Self {
+            on_conflict: KeymapConflictPolicy::Skip,
+            relocate_prefix: "<leader>M".to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AppConfig {
     pub tmux_command_mode: TmuxCommandMode,
     pub allow_agent_tmux_override: bool,
     pub agent_tmux_override_whitelist: Vec<TmuxCommandMode>,
+    pub keep_nvim_sessions_on_exit: bool,
+    pub tmux_control_mode: bool,
+    /// Shell `exec`ed in command panes instead of the user's default shell.
+    pub command_shell: Option<String>,
+    pub socket_dir: Option<PathBuf>,
+    pub log_level: LevelFilter,
+    pub global_shortcut: Option<String>,
+    pub check_for_updates: bool,
+    /// Replaces the GitHub release download URL of the managed agent.
+    pub agent_download_base_url: Option<String>,
+    pub agent: AgentConfig,
+    pub terminal: TerminalConfig,
+    pub notifications: NotificationConfig,
+    pub context: ContextConfig,
+    pub keymaps: KeymapConfig,
 }
 
 impl AppConfig {
@@ -33,15 +402,485 @@ impl AppConfig {
                 TmuxCommandMode::Window,
                 TmuxCommandMode::Hidden,
             ],
+            keep_nvim_sessions_on_exit: true,
+            tmux_control_mode: true,
+            command_shell: None,
+            socket_dir: None,
+            log_level: LevelFilter::Info,
+            global_shortcut: None,
+            check_for_updates: true,
+            agent_download_base_url: None,
+            agent: AgentConfig::default(),
+            terminal: TerminalConfig::default(),
+            notifications: NotificationConfig::default(),
+            context: ContextConfig::default(),
+            keymaps: KeymapConfig::default(),
         }
     }
+
+    /// Flattened `key -> value` view used to describe what changed on reload.
+    fn entries(&self) -> Vec<(&'static str, Value)> {
+        vec![
+            (
+                "tmux_command_mode",
+                Value::from(self.tmux_command_mode.as_str()),
+            ),
+            (
+                "allow_agent_tmux_override",
+                Value::from(self.allow_agent_tmux_override),
+            ),
+            (
+                "agent_tmux_override_whitelist",
+                Value::from(
+                    self.agent_tmux_override_whitelist
+                        .iter()
+                        .map(|mode| mode.as_str())
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+            (
+                "keep_nvim_sessions_on_exit",
+                Value::from(self.keep_nvim_sessions_on_exit),
+            ),
+            ("tmux_control_mode", Value::from(self.tmux_control_mode)),
+            (
+                "command_shell",
+                self.command_shell
+                    .clone()
+                    .map(Value::from)
+                    .unwrap_or(Value::Null),
+            ),
+            (
+                "socket_dir",
+                self.socket_dir
+                    .as_ref()
+                    .map(|dir| Value::from(dir.to_string_lossy().into_owned()))
+                    .unwrap_or(Value::Null),
+            ),
+            (
+                "log_level",
+                Value::from(self.log_level.as_str().to_lowercase()),
+            ),
+            (
+                "global_shortcut",
+                self.global_shortcut
+                    .clone()
+                    .map(Value::from)
+                    .unwrap_or(Value::Null),
+            ),
+            ("check_for_updates", Value::from(self.check_for_updates)),
+            (
+                "agent_download_base_url",
+                self.agent_download_base_url
+                    .clone()
+                    .map(Value::from)
+                    .unwrap_or(Value::Null),
+            ),
+            (
+                "agent.path",
+                self.agent
+                    .path
+                    .clone()
+                    .map(Value::from)
+                    .unwrap_or(Value::Null),
+            ),
+            ("agent.args", Value::from(self.agent.args.clone())),
+            (
+                "agent.model",
+                self.agent
+                    .model
+                    .clone()
+                    .map(Value::from)
+                    .unwrap_or(Value::Null),
+            ),
+            (
+                "agent.env",
+                Value::Object(
+                    self.agent
+                        .env
+                        .iter()
+                        .map(|(key, value)| (key.clone(), Value::from(value.clone())))
+                        .collect(),
+                ),
+            ),
+            (
+                "agent.auto_start_on_launch",
+                Value::from(self.agent.auto_start_on_launch),
+            ),
+            ("agent.auto_restart", Value::from(self.agent.auto_restart)),
+            (
+                "agent.event_batch_ms",
+                Value::from(self.agent.event_batch_ms),
+            ),
+            (
+                "agent.stream_thoughts",
+                Value::from(self.agent.stream_thoughts),
+            ),
+            (
+                "agent.allow_terminal_snapshot",
+                Value::from(self.agent.allow_terminal_snapshot),
+            ),
+            (
+                "agent.auto_approve_kinds",
+                Value::from(self.agent.auto_approve_kinds.clone()),
+            ),
+            (
+                "agent.prompt_retries",
+                Value::from(self.agent.prompt_retries),
+            ),
+            (
+                "agent.prompt_retry_base_ms",
+                Value::from(self.agent.prompt_retry_base_ms),
+            ),
+            (
+                "agent.prompt_retry_max_ms",
+                Value::from(self.agent.prompt_retry_max_ms),
+            ),
+            (
+                "agent.prompt_retry_jitter",
+                Value::from(self.agent.prompt_retry_jitter),
+            ),
+            (
+                "agent.max_concurrent_commands",
+                Value::from(self.agent.max_concurrent_commands),
+            ),
+            (
+                "agent.queue_excess_commands",
+                Value::from(self.agent.queue_excess_commands),
+            ),
+            (
+                "agent.command_env_allow",
+                Value::from(self.agent.command_env_allow.clone()),
+            ),
+            (
+                "agent.command_env_deny",
+                Value::from(self.agent.command_env_deny.clone()),
+            ),
+            (
+                "agent.command_cwd_allow",
+                Value::from(
+                    self.agent
+                        .command_cwd_allow
+                        .iter()
+                        .map(|dir| dir.to_string_lossy().into_owned())
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+            (
+                "agent.command_output_spill_bytes",
+                Value::from(self.agent.command_output_spill_bytes),
+            ),
+            (
+                "agent.context_window_tokens",
+                Value::from(self.agent.context_window_tokens),
+            ),
+            (
+                "terminal.font_size",
+                self.terminal
+                    .font_size
+                    .map(Value::from)
+                    .unwrap_or(Value::Null),
+            ),
+            (
+                "terminal.theme",
+                self.terminal
+                    .theme
+                    .clone()
+                    .map(Value::from)
+                    .unwrap_or(Value::Null),
+            ),
+            (
+                "terminal.padding",
+                self.terminal
+                    .padding
+                    .map(Value::from)
+                    .unwrap_or(Value::Null),
+            ),
+            (
+                "terminal.corner_radius",
+                self.terminal
+                    .corner_radius
+                    .map(Value::from)
+                    .unwrap_or(Value::Null),
+            ),
+            (
+                "terminal.scrollback_limit",
+                self.terminal
+                    .scrollback_limit
+                    .map(Value::from)
+                    .unwrap_or(Value::Null),
+            ),
+            ("terminal.prewarm", Value::from(self.terminal.prewarm)),
+            (
+                "terminal.option_as_alt",
+                Value::from(self.terminal.option_as_alt),
+            ),
+            (
+                "notifications.turn_finished",
+                Value::from(self.notifications.turn_finished),
+            ),
+            (
+                "notifications.permission_request",
+                Value::from(self.notifications.permission_request),
+            ),
+            (
+                "notifications.command_finished",
+                Value::from(self.notifications.command_finished),
+            ),
+            (
+                "notifications.command_min_duration_secs",
+                Value::from(self.notifications.command_min_duration.as_secs()),
+            ),
+            (
+                "context.token_budget",
+                Value::from(self.context.token_budget),
+            ),
+            (
+                "context.recent_files",
+                Value::from(self.context.recent_files),
+            ),
+            (
+                "context.context_reuse",
+                Value::from(self.context.context_reuse),
+            ),
+            (
+                "keymaps.on_conflict",
+                Value::from(self.keymaps.on_conflict.as_str()),
+            ),
+            (
+                "keymaps.relocate_prefix",
+                Value::from(self.keymaps.relocate_prefix.clone()),
+            ),
+        ]
+    }
+
+    pub fn diff(&self, next: &AppConfig) -> Vec<ConfigChange> {
+        self.entries()
+            .into_iter()
+            .zip(next.entries())
+            .filter(|((_, previous), (_, current))| previous != current)
+            .map(|((key, previous), (_, current))| ConfigChange {
+                key: key.to_string(),
+                previous,
+                current,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigChange {
+    pub key: String,
+    pub previous: Value,
+    pub current: Value,
 }
 
+/// Payload of the `setting-changed` event, emitted once per changed key.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingChangedEvent {
+    pub key: String,
+    pub value: Value,
+}
+
+/// Payload of the `config-changed` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigChangedEvent {
+    pub path: String,
+    pub changes: Vec<ConfigChange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticSeverity {
+    /// The value is ignored and its default is used instead.
+    Warning,
+    /// The file cannot be applied at all; every setting falls back to defaults.
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigDiagnostic {
+    pub severity: DiagnosticSeverity,
+    pub key: Option<String>,
+    /// 1-based line in `config.toml`, when it can be located.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigValidation {
+    pub path: String,
+    pub valid: bool,
+    pub diagnostics: Vec<ConfigDiagnostic>,
+}
+
+const KNOWN_KEYS: &[&str] = &[
+    "tmux_command_mode",
+    "allow_agent_tmux_override",
+    "agent_tmux_override_whitelist",
+    "keep_nvim_sessions_on_exit",
+    "tmux_control_mode",
+    "command_shell",
+    "socket_dir",
+    "log_level",
+    "global_shortcut",
+    "check_for_updates",
+    "agent_download_base_url",
+    "agent",
+    "terminal",
+    "notifications",
+    "context",
+    "keymaps",
+];
+
+/// ACP tool call kinds, as agents send them.
+const TOOL_KINDS: &[&str] = &[
+    "read",
+    "edit",
+    "delete",
+    "move",
+    "search",
+    "execute",
+    "think",
+    "fetch",
+    "switch_mode",
+    "other",
+];
+
+const KNOWN_AGENT_KEYS: &[&str] = &[
+    "path",
+    "args",
+    "model",
+    "env",
+    "auto_start_on_launch",
+    "auto_restart",
+    "event_batch_ms",
+    "stream_thoughts",
+    "allow_terminal_snapshot",
+    "auto_approve_kinds",
+    "prompt_retries",
+    "prompt_retry_base_ms",
+    "prompt_retry_max_ms",
+    "prompt_retry_jitter",
+    "max_concurrent_commands",
+    "queue_excess_commands",
+    "command_env_allow",
+    "command_env_deny",
+    "command_cwd_allow",
+    "command_output_spill_bytes",
+    "context_window_tokens",
+];
+
+const KNOWN_TERMINAL_KEYS: &[&str] = &[
+    "font_size",
+    "theme",
+    "padding",
+    "corner_radius",
+    "scrollback_limit",
+    "prewarm",
+    "option_as_alt",
+];
+
+const KNOWN_NOTIFICATION_KEYS: &[&str] = &[
+    "turn_finished",
+    "permission_request",
+    "command_finished",
+    "command_min_duration_secs",
+];
+
+const KNOWN_CONTEXT_KEYS: &[&str] = &["token_budget", "recent_files", "context_reuse"];
+
+const KNOWN_KEYMAP_KEYS: &[&str] = &["on_conflict", "relocate_prefix"];
+
 #[derive(Debug, Default, Deserialize)]
 struct RawAppConfig {
     tmux_command_mode: Option<String>,
     allow_agent_tmux_override: Option<bool>,
     agent_tmux_override_whitelist: Option<Vec<String>>,
+    keep_nvim_sessions_on_exit: Option<bool>,
+    tmux_control_mode: Option<bool>,
+    command_shell: Option<String>,
+    socket_dir: Option<String>,
+    log_level: Option<String>,
+    global_shortcut: Option<String>,
+    check_for_updates: Option<bool>,
+    agent_download_base_url: Option<String>,
+    agent: Option<RawAgentConfig>,
+    terminal: Option<RawTerminalConfig>,
+    notifications: Option<RawNotificationConfig>,
+    context: Option<RawContextConfig>,
+    keymaps: Option<RawKeymapConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawAgentConfig {
+    path: Option<String>,
+    args: Option<Vec<String>>,
+    model: Option<String>,
+    env: Option<BTreeMap<String, String>>,
+    auto_start_on_launch: Option<bool>,
+    auto_restart: Option<bool>,
+    event_batch_ms: Option<u64>,
+    stream_thoughts: Option<bool>,
+    allow_terminal_snapshot: Option<bool>,
+    auto_approve_kinds: Option<Vec<String>>,
+    prompt_retries: Option<u32>,
+    prompt_retry_base_ms: Option<u64>,
+    prompt_retry_max_ms: Option<u64>,
+    prompt_retry_jitter: Option<bool>,
+    max_concurrent_commands: Option<u32>,
+    queue_excess_commands: Option<bool>,
+    command_env_allow: Option<Vec<String>>,
+    command_env_deny: Option<Vec<String>>,
+    command_cwd_allow: Option<Vec<String>>,
+    command_output_spill_bytes: Option<u64>,
+    context_window_tokens: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawTerminalConfig {
+    font_size: Option<f64>,
+    theme: Option<String>,
+    padding: Option<f64>,
+    corner_radius: Option<f64>,
+    scrollback_limit: Option<u64>,
+    prewarm: Option<usize>,
+    option_as_alt: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawNotificationConfig {
+    turn_finished: Option<bool>,
+    permission_request: Option<bool>,
+    command_finished: Option<bool>,
+    command_min_duration_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawContextConfig {
+    token_budget: Option<usize>,
+    recent_files: Option<usize>,
+    context_reuse: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawKeymapConfig {
+    on_conflict: Option<String>,
+    relocate_prefix: Option<String>,
 }
 
 #[derive(Debug)]
@@ -61,7 +900,7 @@ impl Default for AppConfigState {
 
 impl AppConfigState {
     pub fn initialize(&mut self, app_handle: &tauri::AppHandle) -> Result<(), String> {
-        let root = app_root_dir(app_handle)?;
+        let root = app_paths::app_root_dir(app_handle)?;
         std::fs::create_dir_all(&root).map_err(|e| {
             format!(
                 "Failed to create app config directory '{}': {e}",
@@ -82,12 +921,30 @@ impl AppConfigState {
         let contents = std::fs::read_to_string(&path)
             .map_err(|e| format!("Failed to read config file '{}': {e}", path.display()))?;
 
+        log_config_diagnostics(&path, &validate_config_contents(&contents));
         let parsed = parse_config_contents(&contents);
         self.config = parsed;
         self.config_path = Some(path);
         Ok(())
     }
 
+    /// Re-reads `config.toml` and swaps in the new configuration. Unlike startup,
+    /// a file that fails to parse keeps the current configuration in place.
+    pub fn reload(&mut self) -> Result<Vec<ConfigChange>, String> {
+        let path = self
+            .config_path
+            .clone()
+            .ok_or_else(|| "Config file has not been initialized".to_string())?;
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read config file '{}': {e}", path.display()))?;
+        let next = try_parse_config_contents(&contents)?;
+        log_config_diagnostics(&path, &validate_config_contents(&contents));
+
+        let changes = self.config.diff(&next);
+        self.config = next;
+        Ok(changes)
+    }
+
     pub fn resolve_tmux_command_mode(
         &self,
         requested: Option<TmuxCommandMode>,
@@ -111,18 +968,126 @@ impl AppConfigState {
     pub fn config_path(&self) -> Option<PathBuf> {
         self.config_path.clone()
     }
+
+    pub fn keep_nvim_sessions_on_exit(&self) -> bool {
+        self.config.keep_nvim_sessions_on_exit
+    }
+
+    pub fn tmux_control_mode(&self) -> bool {
+        self.config.tmux_control_mode
+    }
+
+    pub fn command_shell(&self) -> Option<String> {
+        self.config.command_shell.clone()
+    }
+
+    pub fn check_for_updates(&self) -> bool {
+        self.config.check_for_updates
+    }
+
+    pub fn socket_dir(&self) -> Option<PathBuf> {
+        self.config.socket_dir.clone()
+    }
+
+    pub fn log_level(&self) -> LevelFilter {
+        self.config.log_level
+    }
+
+    pub fn global_shortcut(&self) -> Option<String> {
+        self.config.global_shortcut.clone()
+    }
+
+    pub fn agent_download_base_url(&self) -> Option<String> {
+        self.config.agent_download_base_url.clone()
+    }
+
+    pub fn agent_config(&self) -> AgentConfig {
+        self.config.agent.clone()
+    }
+
+    pub fn stream_thoughts(&self) -> bool {
+        self.config.agent.stream_thoughts
+    }
+
+    /// Frame for batching streamed ACP chunks, or `None` to emit each one.
+    pub fn event_batch_interval(&self) -> Option<Duration> {
+        let ms = self.config.agent.event_batch_ms;
+        (ms > 0).then(|| Duration::from_millis(ms))
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let agent = &self.config.agent;
+        RetryPolicy {
+            max_retries: agent.prompt_retries,
+            base_delay: Duration::from_millis(agent.prompt_retry_base_ms),
+            max_delay: Duration::from_millis(
+                agent.prompt_retry_max_ms.max(agent.prompt_retry_base_ms),
+            ),
+            jitter: agent.prompt_retry_jitter,
+        }
+    }
+
+    pub fn command_env_rules(&self) -> EnvRules {
+        EnvRules {
+            allow: self.config.agent.command_env_allow.clone(),
+            deny: self.config.agent.command_env_deny.clone(),
+        }
+    }
+
+    /// Tool call kinds whose permission requests are approved without asking.
+    pub fn auto_approve_kinds(&self) -> Vec<String> {
+        self.config.agent.auto_approve_kinds.clone()
+    }
+
+    /// Extra directories agent commands may run in besides the project.
+    pub fn command_cwd_allow(&self) -> Vec<PathBuf> {
+        self.config.agent.command_cwd_allow.clone()
+    }
+
+    /// Context window to track sessions against, or `None` when disabled.
+    pub fn context_window_tokens(&self) -> Option<usize> {
+        let tokens = self.config.agent.context_window_tokens as usize;
+        (tokens > 0).then_some(tokens)
+    }
+
+    pub fn terminal_config(&self) -> TerminalConfig {
+        self.config.terminal.clone()
+    }
+
+    pub fn notification_config(&self) -> NotificationConfig {
+        self.config.notifications.clone()
+    }
+
+    pub fn context_config(&self) -> ContextConfig {
+        self.config.context.clone()
+    }
+
+    pub fn keymap_config(&self) -> KeymapConfig {
+        self.config.keymaps.clone()
+    }
+}
+
+/// Agent settings from `config.toml` under `root`, for callers outside the
+/// app (e.g. `neoai-cli`). A missing or unreadable file yields the defaults.
+pub fn read_agent_config(root: &Path) -> AgentConfig {
+    match std::fs::read_to_string(root.join("config.toml")) {
+        Ok(contents) => parse_config_contents(&contents).agent,
+        Err(_) => AppConfig::runtime_default().agent,
+    }
 }
 
 fn parse_config_contents(contents: &str) -> AppConfig {
+    try_parse_config_contents(contents).unwrap_or_else(|err| {
+        log::warn!("{err}. Falling back to defaults");
+        AppConfig::runtime_default()
+    })
+}
+
+fn try_parse_config_contents(contents: &str) -> Result<AppConfig, String> {
     let mut config = AppConfig::runtime_default();
 
-    let raw = match toml::from_str::<RawAppConfig>(contents) {
-        Ok(raw) => raw,
-        Err(err) => {
-            log::warn!("Failed to parse NeoAI config.toml. Falling back to defaults: {err}");
-            return config;
-        }
-    };
+    let raw = toml::from_str::<RawAppConfig>(contents)
+        .map_err(|err| format!("Failed to parse NeoAI config.toml: {err}"))?;
 
     if let Some(mode) = raw
         .tmux_command_mode
@@ -143,27 +1108,1018 @@ fn parse_config_contents(contents: &str) -> AppConfig {
             config.agent_tmux_override_whitelist = parsed;
         }
     }
+    if let Some(keep) = raw.keep_nvim_sessions_on_exit {
+        config.keep_nvim_sessions_on_exit = keep;
+    }
+    if let Some(control) = raw.tmux_control_mode {
+        config.tmux_control_mode = control;
+    }
+    config.command_shell = raw
+        .command_shell
+        .map(|shell| shell.trim().to_string())
+        .filter(|shell| !shell.is_empty());
+    if let Some(check) = raw.check_for_updates {
+        config.check_for_updates = check;
+    }
+    if let Some(dir) = raw.socket_dir.as_deref().map(str::trim) {
+        let path = PathBuf::from(dir);
+        if path.is_absolute() {
+            config.socket_dir = Some(path);
+        } else if !dir.is_empty() {
+            log::warn!("Ignoring socket_dir '{dir}' in config.toml: path must be absolute");
+        }
+    }
+    if let Some(level) = raw.log_level.as_deref().map(str::trim) {
+        match LevelFilter::from_str(level) {
+            Ok(level) => config.log_level = level,
+            Err(_) => log::warn!("Ignoring log_level '{level}' in config.toml"),
+        }
+    }
+    config.global_shortcut = raw
+        .global_shortcut
+        .map(|shortcut| shortcut.trim().to_string())
+        .filter(|shortcut| !shortcut.is_empty());
+    if let Some(url) = raw.agent_download_base_url.as_deref().map(str::trim) {
+        if is_http_url(url) {
+            config.agent_download_base_url = Some(url.trim_end_matches('/').to_string());
+        } else if !url.is_empty() {
+            log::warn!(
+                "Ignoring agent_download_base_url '{url}' in config.toml: expected an http(s) URL"
+            );
+        }
+    }
+    if let Some(agent) = raw.agent {
+        config.agent.path = agent
+            .path
+            .map(|path| path.trim().to_string())
+            .filter(|path| !path.is_empty());
+        config.agent.args = agent.args.unwrap_or_default();
+        config.agent.model = agent
+            .model
+            .map(|model| model.trim().to_string())
+            .filter(|model| !model.is_empty());
+        config.agent.env = agent.env.unwrap_or_default();
+        config.agent.auto_start_on_launch = agent.auto_start_on_launch.unwrap_or(false);
+        config.agent.auto_restart = agent.auto_restart.unwrap_or(false);
+        if let Some(ms) = agent.event_batch_ms {
+            config.agent.event_batch_ms = ms;
+        }
+        config.agent.stream_thoughts = agent.stream_thoughts.unwrap_or(true);
+        config.agent.allow_terminal_snapshot = agent.allow_terminal_snapshot.unwrap_or(false);
+        config.agent.auto_approve_kinds = agent
+            .auto_approve_kinds
+            .unwrap_or_default()
+            .iter()
+            .map(|kind| kind.trim().to_ascii_lowercase())
+            .filter(|kind| !kind.is_empty())
+            .filter(|kind| {
+                let known = TOOL_KINDS.contains(&kind.as_str());
+                if !known {
+                    log::warn!(
+                        "Ignoring agent.auto_approve_kinds entry '{kind}' in config.toml: unknown tool kind"
+                    );
+                }
+                known
+            })
+            .collect();
+        if let Some(retries) = agent.prompt_retries {
+            config.agent.prompt_retries = retries;
+        }
+        if let Some(ms) = agent.prompt_retry_base_ms {
+            config.agent.prompt_retry_base_ms = ms;
+        }
+        if let Some(ms) = agent.prompt_retry_max_ms {
+            config.agent.prompt_retry_max_ms = ms;
+        }
+        config.agent.prompt_retry_jitter = agent.prompt_retry_jitter.unwrap_or(true);
+        if let Some(limit) = agent.max_concurrent_commands {
+            config.agent.max_concurrent_commands = limit;
+        }
+        config.agent.queue_excess_commands = agent.queue_excess_commands.unwrap_or(true);
+        let patterns = |patterns: Vec<String>| -> Vec<String> {
+            patterns
+                .into_iter()
+                .map(|pattern| pattern.trim().to_string())
+                .filter(|pattern| !pattern.is_empty())
+                .collect()
+        };
+        config.agent.command_env_allow = agent.command_env_allow.map(patterns).unwrap_or_default();
+        if let Some(deny) = agent.command_env_deny {
+            config.agent.command_env_deny = patterns(deny);
+        }
+        config.agent.command_cwd_allow = agent
+            .command_cwd_allow
+            .unwrap_or_default()
+            .iter()
+            .map(|dir| dir.trim())
+            .filter(|dir| !dir.is_empty())
+            .filter_map(|dir| {
+                let path = PathBuf::from(dir);
+                if path.is_absolute() {
+                    Some(path)
+                } else {
+                    log::warn!(
+                        "Ignoring agent.command_cwd_allow entry '{dir}' in config.toml: path must be absolute"
+                    );
+                    None
+                }
+            })
+            .collect();
+        if let Some(bytes) = agent.command_output_spill_bytes {
+            config.agent.command_output_spill_bytes = bytes;
+        }
+        if let Some(tokens) = agent.context_window_tokens {
+            config.agent.context_window_tokens = tokens;
+        }
+    }
+    if let Some(terminal) = raw.terminal {
+        let non_negative = |value: f64| (value.is_finite() && value >= 0.0).then_some(value);
+        config.terminal.font_size = terminal
+            .font_size
+            .filter(|size| size.is_finite() && *size > 0.0)
+            .map(|size| size as f32);
+        config.terminal.theme = terminal
+            .theme
+            .map(|theme| theme.trim().to_string())
+            .filter(|theme| !theme.is_empty());
+        config.terminal.padding = terminal.padding.and_then(non_negative);
+        config.terminal.corner_radius = terminal.corner_radius.and_then(non_negative);
+        config.terminal.scrollback_limit = terminal.scrollback_limit;
+        config.terminal.prewarm = terminal.prewarm.unwrap_or(0);
+        config.terminal.option_as_alt = terminal.option_as_alt.unwrap_or(false);
+    }
+    if let Some(notifications) = raw.notifications {
+        let defaults = NotificationConfig::default();
+        config.notifications = NotificationConfig {
+            turn_finished: notifications
+                .turn_finished
+                .unwrap_or(defaults.turn_finished),
+            permission_request: notifications
+                .permission_request
+                .unwrap_or(defaults.permission_request),
+            command_finished: notifications
+                .command_finished
+                .unwrap_or(defaults.command_finished),
+            command_min_duration: notifications
+                .command_min_duration_secs
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.command_min_duration),
+        };
+    }
+    if let Some(context) = raw.context {
+        let defaults = ContextConfig::default();
+        config.context = ContextConfig {
+            token_budget: context
+                .token_budget
+                .filter(|budget| *budget > 0)
+                .unwrap_or(defaults.token_budget),
+            recent_files: context.recent_files.unwrap_or(defaults.recent_files),
+            context_reuse: context.context_reuse.unwrap_or(defaults.context_reuse),
+        };
+    }
+    if let Some(keymaps) = raw.keymaps {
+        let defaults = KeymapConfig::default();
+        config.keymaps = KeymapConfig {
+            on_conflict: keymaps
+                .on_conflict
+                .as_deref()
+                .and_then(KeymapConflictPolicy::from_config_str)
+                .unwrap_or(defaults.on_conflict),
+            relocate_prefix: keymaps
+                .relocate_prefix
+                .map(|prefix| prefix.trim().to_string())
+                .filter(|prefix| !prefix.is_empty())
+                .unwrap_or(defaults.relocate_prefix),
+        };
+    }
 
-    config
+    Ok(config)
 }
 
-fn app_root_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
-    #[cfg(target_os = "macos")]
+/// Checks `config.toml` contents for problems that parsing would otherwise
+/// paper over with defaults: syntax errors, unknown keys and invalid values.
+pub fn validate_config_contents(contents: &str) -> Vec<ConfigDiagnostic> {
+    let table = match contents.parse::<toml::Table>() {
+        Ok(table) => table,
+        Err(err) => {
+            return vec![ConfigDiagnostic {
+                severity: DiagnosticSeverity::Error,
+                key: None,
+                line: err.span().map(|span| line_for_offset(contents, span.start)),
+                message: format!("Invalid TOML: {}", err.message()),
+            }];
+        }
+    };
+
+    let mut diagnostics = Vec::new();
+    for (key, value) in &table {
+        let mut report = |severity: DiagnosticSeverity, message: String| {
+            diagnostics.push(ConfigDiagnostic {
+                severity,
+                key: Some(key.clone()),
+                line: key_line(contents, None, key),
+                message,
+            });
+        };
+
+        match key.as_str() {
+            "tmux_command_mode" => match value.as_str() {
+                Some(mode) if TmuxCommandMode::from_config_str(mode).is_some() => {}
+                Some(mode) => report(
+                    DiagnosticSeverity::Warning,
+                    format!(
+                        "Invalid tmux_command_mode '{mode}' (expected split, window or hidden); using the default"
+                    ),
+                ),
+                None => report(
+                    DiagnosticSeverity::Error,
+                    "tmux_command_mode must be a string".to_string(),
+                ),
+            },
+            "allow_agent_tmux_override"
+            | "keep_nvim_sessions_on_exit"
+            | "tmux_control_mode"
+            | "check_for_updates" => {
+                if !value.is_bool() {
+                    report(
+                        DiagnosticSeverity::Error,
+                        format!("{key} must be true or false"),
+                    );
+                }
+            }
+            "agent_tmux_override_whitelist" => {
+                let Some(entries) = value.as_array() else {
+                    report(
+                        DiagnosticSeverity::Error,
+                        "agent_tmux_override_whitelist must be an array of strings".to_string(),
+                    );
+                    continue;
+                };
+                let Some(entries) = entries
+                    .iter()
+                    .map(|entry| entry.as_str())
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    report(
+                        DiagnosticSeverity::Error,
+                        "agent_tmux_override_whitelist must be an array of strings".to_string(),
+                    );
+                    continue;
+                };
+                let mut valid_entries = 0;
+                for entry in entries {
+                    if TmuxCommandMode::from_config_str(entry).is_some() {
+                        valid_entries += 1;
+                    } else {
+                        report(
+                            DiagnosticSeverity::Warning,
+                            format!(
+                                "Ignoring agent_tmux_override_whitelist entry '{entry}' (expected split, window or hidden)"
+                            ),
+                        );
+                    }
+                }
+                if valid_entries == 0 {
+                    report(
+                        DiagnosticSeverity::Warning,
+                        "agent_tmux_override_whitelist has no valid entries; using the default"
+                            .to_string(),
+                    );
+                }
+            }
+            "socket_dir" => match value.as_str().map(str::trim) {
+                Some(dir) if dir.is_empty() || Path::new(dir).is_absolute() => {}
+                Some(dir) => report(
+                    DiagnosticSeverity::Warning,
+                    format!("socket_dir '{dir}' must be an absolute path; using the default"),
+                ),
+                None => report(
+                    DiagnosticSeverity::Error,
+                    "socket_dir must be a string".to_string(),
+                ),
+            },
+            "log_level" => match value.as_str().map(str::trim) {
+                Some(level) if LevelFilter::from_str(level).is_ok() => {}
+                Some(level) => report(
+                    DiagnosticSeverity::Warning,
+                    format!(
+                        "Invalid log_level '{level}' (expected off, error, warn, info, debug or trace); using the default"
+                    ),
+                ),
+                None => report(
+                    DiagnosticSeverity::Error,
+                    "log_level must be a string".to_string(),
+                ),
+            },
+            "command_shell" => {
+                if !value.is_str() {
+                    report(
+                        DiagnosticSeverity::Error,
+                        "command_shell must be a string".to_string(),
+                    );
+                }
+            }
+            "global_shortcut" => match value.as_str().map(str::trim) {
+                Some("") => {}
+                Some(shortcut) => {
+                    if let Err(err) = global_shortcut::parse_shortcut(shortcut) {
+                        report(
+                            DiagnosticSeverity::Warning,
+                            format!("{err}; no shortcut is registered"),
+                        );
+                    }
+                }
+                None => report(
+                    DiagnosticSeverity::Error,
+                    "global_shortcut must be a string".to_string(),
+                ),
+            },
+            "agent_download_base_url" => match value.as_str().map(str::trim) {
+                Some(url) if url.is_empty() || is_http_url(url) => {}
+                Some(url) => report(
+                    DiagnosticSeverity::Warning,
+                    format!(
+                        "agent_download_base_url '{url}' must be an http(s) URL; downloading from GitHub"
+                    ),
+                ),
+                None => report(
+                    DiagnosticSeverity::Error,
+                    "agent_download_base_url must be a string".to_string(),
+                ),
+            },
+            "agent" => match value.as_table() {
+                Some(agent) => validate_agent_section(contents, agent, &mut diagnostics),
+                None => report(
+                    DiagnosticSeverity::Error,
+                    "agent must be a table ([agent])".to_string(),
+                ),
+            },
+            "terminal" => match value.as_table() {
+                Some(terminal) => validate_terminal_section(contents, terminal, &mut diagnostics),
+                None => report(
+                    DiagnosticSeverity::Error,
+                    "terminal must be a table ([terminal])".to_string(),
+                ),
+            },
+            "notifications" => match value.as_table() {
+                Some(notifications) => {
+                    validate_notifications_section(contents, notifications, &mut diagnostics)
+                }
+                None => report(
+                    DiagnosticSeverity::Error,
+                    "notifications must be a table ([notifications])".to_string(),
+                ),
+            },
+            "context" => match value.as_table() {
+                Some(context) => validate_context_section(contents, context, &mut diagnostics),
+                None => report(
+                    DiagnosticSeverity::Error,
+                    "context must be a table ([context])".to_string(),
+                ),
+            },
+            "keymaps" => match value.as_table() {
+                Some(keymaps) => validate_keymaps_section(contents, keymaps, &mut diagnostics),
+                None => report(
+                    DiagnosticSeverity::Error,
+                    "keymaps must be a table ([keymaps])".to_string(),
+                ),
+            },
+            _ => report(
+                DiagnosticSeverity::Warning,
+                format!(
+                    "Unknown key '{key}' (known keys: {})",
+                    KNOWN_KEYS.join(", ")
+                ),
+            ),
+        }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    diagnostics
+}
+
+fn validate_agent_section(
+    contents: &str,
+    agent: &toml::Table,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) {
+    for (key, value) in agent {
+        let type_error = match key.as_str() {
+            "path" | "model" => (!value.is_str()).then_some("a string"),
+            "args" | "command_env_allow" | "command_env_deny" | "command_cwd_allow"
+            | "auto_approve_kinds" => (!value
+                .as_array()
+                .is_some_and(|args| args.iter().all(toml::Value::is_str)))
+            .then_some("an array of strings"),
+            "env" => (!value
+                .as_table()
+                .is_some_and(|env| env.values().all(toml::Value::is_str)))
+            .then_some("a table of string values"),
+            "auto_start_on_launch"
+            | "auto_restart"
+            | "stream_thoughts"
+            | "allow_terminal_snapshot"
+            | "prompt_retry_jitter"
+            | "queue_excess_commands" => (!value.is_bool()).then_some("true or false"),
+            "event_batch_ms"
+            | "prompt_retries"
+            | "prompt_retry_base_ms"
+            | "prompt_retry_max_ms"
+            | "max_concurrent_commands"
+            | "command_output_spill_bytes"
+            | "context_window_tokens" => {
+                (!value.as_integer().is_some_and(|ms| ms >= 0)).then_some("a non-negative integer")
+            }
+            _ => {
+                diagnostics.push(ConfigDiagnostic {
+                    severity: DiagnosticSeverity::Warning,
+                    key: Some(format!("agent.{key}")),
+                    line: key_line(contents, Some("agent"), key),
+                    message: format!(
+                        "Unknown key 'agent.{key}' (known keys: {})",
+                        KNOWN_AGENT_KEYS.join(", ")
+                    ),
+                });
+                continue;
+            }
+        };
+        if let Some(expected) = type_error {
+            diagnostics.push(ConfigDiagnostic {
+                severity: DiagnosticSeverity::Error,
+                key: Some(format!("agent.{key}")),
+                line: key_line(contents, Some("agent"), key),
+                message: format!("agent.{key} must be {expected}"),
+            });
+        }
+    }
+
+    let kinds = agent
+        .get("auto_approve_kinds")
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(toml::Value::as_str);
+    for kind in kinds {
+        if !TOOL_KINDS.contains(&kind.trim().to_ascii_lowercase().as_str()) {
+            diagnostics.push(ConfigDiagnostic {
+                severity: DiagnosticSeverity::Warning,
+                key: Some("agent.auto_approve_kinds".to_string()),
+                line: key_line(contents, Some("agent"), "auto_approve_kinds"),
+                message: format!(
+                    "Unknown tool kind '{kind}' in agent.auto_approve_kinds (known kinds: {})",
+                    TOOL_KINDS.join(", ")
+                ),
+            });
+        }
+    }
+
+    let Some(env) = agent.get("env").and_then(toml::Value::as_table) else {
+        return;
+    };
+    for (name, value) in env {
+        let Some(value) = value.as_str() else {
+            continue;
+        };
+        if secrets::looks_sensitive(name) && secrets::secret_ref(value).is_none() {
+            diagnostics.push(ConfigDiagnostic {
+                severity: DiagnosticSeverity::Warning,
+                key: Some(format!("agent.env.{name}")),
+                line: key_line(contents, Some("agent"), "env"),
+                message: format!(
+                    "agent.env.{name} looks like a credential stored in plain text; save it with secret_set and use \"{}{}\" instead",
+                    secrets::SECRET_REF_PREFIX,
+                    name.to_ascii_lowercase()
+                ),
+            });
+        }
+    }
+}
+
+fn validate_terminal_section(
+    contents: &str,
+    terminal: &toml::Table,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) {
+    for (key, value) in terminal {
+        let number = value
+            .as_float()
+            .or_else(|| value.as_integer().map(|n| n as f64));
+        let (severity, message) = match key.as_str() {
+            "theme" if !value.is_str() => (
+                DiagnosticSeverity::Error,
+                "terminal.theme must be a string".to_string(),
+            ),
+            "theme" => continue,
+            "font_size" | "padding" | "corner_radius" => match number {
+                None => (
+                    DiagnosticSeverity::Error,
+                    format!("terminal.{key} must be a number"),
+                ),
+                Some(n) if key == "font_size" && n <= 0.0 => (
+                    DiagnosticSeverity::Warning,
+                    "terminal.font_size must be greater than zero; using the default".to_string(),
+                ),
+                Some(n) if n < 0.0 => (
+                    DiagnosticSeverity::Warning,
+                    format!("terminal.{key} must not be negative; using the default"),
+                ),
+                Some(_) => continue,
+            },
+            "scrollback_limit" => match value.as_integer() {
+                Some(n) if n >= 0 => continue,
+                _ => (
+                    DiagnosticSeverity::Error,
+                    "terminal.scrollback_limit must be a non-negative integer (bytes)".to_string(),
+                ),
+            },
+            "prewarm" => match value.as_integer() {
+                Some(n) if n >= 0 => continue,
+                _ => (
+                    DiagnosticSeverity::Error,
+                    "terminal.prewarm must be a non-negative integer".to_string(),
+                ),
+            },
+            "option_as_alt" if !value.is_bool() => (
+                DiagnosticSeverity::Error,
+                "terminal.option_as_alt must be a boolean".to_string(),
+            ),
+            "option_as_alt" => continue,
+            _ => (
+                DiagnosticSeverity::Warning,
+                format!(
+                    "Unknown key 'terminal.{key}' (known keys: {})",
+                    KNOWN_TERMINAL_KEYS.join(", ")
+                ),
+            ),
+        };
+        diagnostics.push(ConfigDiagnostic {
+            severity,
+            key: Some(format!("terminal.{key}")),
+            line: key_line(contents, Some("terminal"), key),
+            message,
+        });
+    }
+}
+
+fn validate_notifications_section(
+    contents: &str,
+    notifications: &toml::Table,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) {
+    for (key, value) in notifications {
+        let (severity, message) = match key.as_str() {
+            "turn_finished" | "permission_request" | "command_finished" => {
+                if value.is_bool() {
+                    continue;
+                }
+                (
+                    DiagnosticSeverity::Error,
+                    format!("notifications.{key} must be true or false"),
+                )
+            }
+            "command_min_duration_secs" => match value.as_integer() {
+                Some(n) if n >= 0 => continue,
+                _ => (
+                    DiagnosticSeverity::Error,
+                    "notifications.command_min_duration_secs must be a non-negative integer"
+                        .to_string(),
+                ),
+            },
+            _ => (
+                DiagnosticSeverity::Warning,
+                format!(
+                    "Unknown key 'notifications.{key}' (known keys: {})",
+                    KNOWN_NOTIFICATION_KEYS.join(", ")
+                ),
+            ),
+        };
+        diagnostics.push(ConfigDiagnostic {
+            severity,
+            key: Some(format!("notifications.{key}")),
+            line: key_line(contents, Some("notifications"), key),
+            message,
+        });
+    }
+}
+
+fn validate_context_section(
+    contents: &str,
+    context: &toml::Table,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) {
+    for (key, value) in context {
+        let (severity, message) = match (key.as_str(), value.as_integer()) {
+            ("token_budget", Some(0)) => (
+                DiagnosticSeverity::Warning,
+                "context.token_budget must be greater than zero; using the default".to_string(),
+            ),
+            ("token_budget" | "recent_files", Some(n)) if n >= 0 => continue,
+            ("context_reuse", _) if value.is_bool() => continue,
+            ("context_reuse", _) => (
+                DiagnosticSeverity::Error,
+                "context.context_reuse must be true or false".to_string(),
+            ),
+            ("token_budget" | "recent_files", _) => (
+                DiagnosticSeverity::Error,
+                format!("context.{key} must be a non-negative integer"),
+            ),
+            _ => (
+                DiagnosticSeverity::Warning,
+                format!(
+                    "Unknown key 'context.{key}' (known keys: {})",
+                    KNOWN_CONTEXT_KEYS.join(", ")
+                ),
+            ),
+        };
+        diagnostics.push(ConfigDiagnostic {
+            severity,
+            key: Some(format!("context.{key}")),
+            line: key_line(contents, Some("context"), key),
+            message,
+        });
+    }
+}
+
+fn validate_keymaps_section(
+    contents: &str,
+    keymaps: &toml::Table,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) {
+    for (key, value) in keymaps {
+        let (severity, message) = match (key.as_str(), value.as_str()) {
+            ("on_conflict", Some(policy))
+                if KeymapConflictPolicy::from_config_str(policy).is_some() =>
+            {
+                continue
+            }
+            ("on_conflict", Some(policy)) => (
+                DiagnosticSeverity::Warning,
+                format!(
+                    "Invalid keymaps.on_conflict '{policy}' (expected skip, relocate or override); using skip"
+                ),
+            ),
+            ("relocate_prefix", Some(prefix)) if !prefix.trim().is_empty() => continue,
+            ("relocate_prefix", Some(_)) => (
+                DiagnosticSeverity::Warning,
+                "keymaps.relocate_prefix is empty; using the default".to_string(),
+            ),
+            ("on_conflict" | "relocate_prefix", None) => (
+                DiagnosticSeverity::Error,
+                format!("keymaps.{key} must be a string"),
+            ),
+            _ => (
+                DiagnosticSeverity::Warning,
+                format!(
+                    "Unknown key 'keymaps.{key}' (known keys: {})",
+                    KNOWN_KEYMAP_KEYS.join(", ")
+                ),
+            ),
+        };
+        diagnostics.push(ConfigDiagnostic {
+            severity,
+            key: Some(format!("keymaps.{key}")),
+            line: key_line(contents, Some("keymaps"), key),
+            message,
+        });
+    }
+}
+
+fn log_config_diagnostics(path: &Path, diagnostics: &[ConfigDiagnostic]) {
+    if diagnostics.is_empty() {
+        return;
+    }
+    log::warn!(
+        "Found {} issue(s) in NeoAI config '{}'",
+        diagnostics.len(),
+        path.display()
+    );
+    for diagnostic in diagnostics {
+        log::warn!("  {diagnostic}");
+    }
+}
+
+fn is_http_url(value: &str) -> bool {
+    ["https://", "http://"]
+        .iter()
+        .any(|scheme| value.len() > scheme.len() && value.starts_with(scheme))
+}
+
+fn line_for_offset(contents: &str, offset: usize) -> usize {
+    let offset = offset.min(contents.len());
+    contents.as_bytes()[..offset]
+        .iter()
+        .filter(|byte| **byte == b'\n')
+        .count()
+        + 1
+}
+
+/// Line of a `key = ...` assignment at the top level (`section` = `None`) or
+/// inside a `[section]` table.
+fn key_line(contents: &str, section: Option<&str>, key: &str) -> Option<usize> {
+    let mut current: Option<&str> = None;
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            current = Some(header.split(']').next().unwrap_or_default().trim());
+            continue;
+        }
+        if current != section {
+            continue;
+        }
+        let Some((name, _)) = line.split_once('=') else {
+            continue;
+        };
+        if name.trim().trim_matches(|c| c == '"' || c == '\'') == key {
+            return Some(index + 1);
+        }
+    }
+    None
+}
+
+/// Number of lines the assignment starting on 1-based `line` spans: more
+/// than one for multi-line arrays, inline tables and strings. 1 when the
+/// value never parses, so a broken line is still replaced alone.
+fn assignment_lines(lines: &[String], line: usize) -> usize {
+    let mut text = String::new();
+    for (count, next) in lines[line - 1..].iter().enumerate() {
+        text.push_str(next);
+        text.push('\n');
+        if toml::from_str::<toml::Table>(&text).is_ok() {
+            return count + 1;
+        }
+    }
+    1
+}
+
+/// Returns `contents` with `key` (dotted for sections, e.g.
+/// `context.token_budget`) set to `value`, or removed when `value` is null.
+/// Only the key's assignment changes, so comments and layout are kept. Fails
+/// for unknown keys and values that would make the file invalid.
+pub fn set_config_value(contents: &str, key: &str, value: &Value) -> Result<String, String> {
+    let known = AppConfig::runtime_default()
+        .entries()
+        .into_iter()
+        .any(|(known, _)| known == key);
+    if !known {
+        return Err(format!("Unknown setting '{key}'"));
+    }
+    let (section, name) = match key.split_once('.') {
+        Some((section, name)) => (Some(section), name),
+        None => (None, key),
+    };
+    let assignment = if value.is_null() {
+        None
+    } else {
+        let value = toml::Value::try_from(value)
+            .map_err(|e| format!("Can't store {value} in config.toml: {e}"))?;
+        Some(format!("{name} = {value}"))
+    };
+
+    let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+    match (key_line(contents, section, name), assignment) {
+        (Some(line), assignment) => {
+            let span = line - 1..line - 1 + assignment_lines(&lines, line);
+            lines.splice(span, assignment);
+        }
+        (None, None) => return Ok(contents.to_string()),
+        (None, Some(assignment)) => {
+            match section {
+                // Top-level keys must come before the first table.
+                None => {
+                    let mut at = lines
+                        .iter()
+                        .position(|line| table_header(line).is_some())
+                        .unwrap_or(lines.len());
+                    while at > 0 && lines[at - 1].trim().is_empty() {
+                        at -= 1;
+                    }
+                    lines.insert(at, assignment);
+                }
+                Some(section) => match lines
+                    .iter()
+                    .position(|line| table_header(line) == Some(section))
+                {
+                    Some(at) => lines.insert(at + 1, assignment),
+                    None => {
+                        if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                            lines.push(String::new());
+                        }
+                        lines.push(format!("[{section}]"));
+                        lines.push(assignment);
+                    }
+                },
+            }
+        }
+    }
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+
+    let before: Vec<String> = validate_config_contents(contents)
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect();
+    if let Some(problem) = validate_config_contents(&updated)
+        .into_iter()
+        .find(|diagnostic| !before.contains(&diagnostic.message))
     {
-        let home = app_handle
-            .path()
-            .home_dir()
-            .map_err(|e| format!("Failed to resolve home directory: {e}"))?;
-        return Ok(home.join(".neoai"));
+        return Err(format!("Can't set '{key}': {}", problem.message));
+    }
+    Ok(updated)
+}
+
+/// Name of the table a `[name]` line opens.
+fn table_header(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix('[')
+        .and_then(|rest| rest.split(']').next())
+        .map(str::trim)
+}
+
+/// Watches `config.toml` for changes, reloading the managed [`AppConfigState`]
+/// and emitting `config-changed` with the keys that changed.
+pub fn watch_config(app_handle: tauri::AppHandle) -> Result<(), String> {
+    let path = {
+        let state = app_handle.state::<std::sync::Mutex<AppConfigState>>();
+        let state = state.lock().map_err(|e| e.to_string())?;
+        state.config_path()
     }
+    .ok_or_else(|| "Config file has not been initialized".to_string())?;
+    let dir = path
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| format!("Config file '{}' has no parent directory", path.display()))?;
 
-    #[cfg(not(target_os = "macos"))]
+    // Watch the directory rather than the file so saves that replace the file
+    // via rename keep being observed.
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| format!("Failed to create config watcher: {e}"))?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch config directory '{}': {e}", dir.display()))?;
+
+    std::thread::Builder::new()
+        .name("neoai-config-watch".to_string())
+        .spawn(move || {
+            let _watcher = watcher;
+            while let Ok(event) = rx.recv() {
+                let touches_config = matches!(
+                    event,
+                    Ok(notify::Event { ref kind, ref paths, .. })
+                        if !matches!(kind, EventKind::Access(_))
+                            && paths.iter().any(|p| p.file_name() == path.file_name())
+                );
+                if !touches_config {
+                    continue;
+                }
+                while rx.recv_timeout(CONFIG_RELOAD_DEBOUNCE).is_ok() {}
+                reload_and_emit(&app_handle, &path);
+            }
+        })
+        .map_err(|e| format!("Failed to spawn config watcher thread: {e}"))?;
+
+    Ok(())
+}
+
+fn reload_and_emit(app_handle: &tauri::AppHandle, path: &Path) {
+    let state = app_handle.state::<std::sync::Mutex<AppConfigState>>();
+    let result = match state.lock() {
+        Ok(mut state) => state.reload().map(|changes| {
+            (
+                changes,
+                state.socket_dir(),
+                state.log_level(),
+                state.global_shortcut(),
+                state.tmux_control_mode(),
+            )
+        }),
+        Err(_) => {
+            log::warn!("Failed to lock NeoAI app config state");
+            return;
+        }
+    };
+
+    let (changes, socket_dir, log_level, shortcut, tmux_control_mode) = match result {
+        Ok(result) => result,
+        Err(err) => {
+            log::warn!("Keeping previous NeoAI configuration: {err}");
+            return;
+        }
+    };
+    if changes.is_empty() {
+        return;
+    }
+    log::info!(
+        "Reloaded NeoAI configuration from '{}' ({} change(s))",
+        path.display(),
+        changes.len()
+    );
+
+    if changes.iter().any(|change| change.key == "socket_dir") {
+        if let Some(sockets) = app_handle.try_state::<std::sync::Mutex<SocketManager>>() {
+            if let Ok(mut mgr) = sockets.lock() {
+                mgr.set_socket_dir(socket_dir.unwrap_or_else(socket_manager::default_socket_dir));
+            }
+        }
+    }
+
+    if changes.iter().any(|change| change.key == "log_level") {
+        logging::set_level(log_level);
+    }
+
+    if changes.iter().any(|change| change.key == "global_shortcut") {
+        if let Err(err) = global_shortcut::apply(app_handle, shortcut.as_deref()) {
+            log::warn!("{err}");
+        }
+    }
+
+    if changes
+        .iter()
+        .any(|change| change.key == "tmux_control_mode")
     {
-        app_handle
-            .path()
-            .app_local_data_dir()
-            .map_err(|e| format!("Failed to resolve app local data directory: {e}"))
+        tmux_control::set_enabled(tmux_control_mode);
+    }
+
+    for change in &changes {
+        let _ = app_handle.emit(
+            "setting-changed",
+            &SettingChangedEvent {
+                key: change.key.clone(),
+                value: change.current.clone(),
+            },
+        );
+    }
+    let _ = app_handle.emit(
+        "config-changed",
+        &ConfigChangedEvent {
+            path: path.to_string_lossy().into_owned(),
+            changes,
+        },
+    );
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn config_validate(
+    state: tauri::State<'_, std::sync::Mutex<AppConfigState>>,
+) -> Result<ConfigValidation, String> {
+    let path = state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .config_path()
+        .ok_or_else(|| "Config file has not been initialized".to_string())?;
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read config file '{}': {e}", path.display()))?;
+
+    let diagnostics = validate_config_contents(&contents);
+    Ok(ConfigValidation {
+        path: path.to_string_lossy().into_owned(),
+        valid: !diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == DiagnosticSeverity::Error),
+        diagnostics,
+    })
+}
+
+/// Changes one setting in `config.toml` (see [`set_config_value`]) and
+/// applies it right away, emitting `setting-changed` and `config-changed`
+/// like an edit to the file would. A null `value` removes the key.
+#[tauri::command]
+pub async fn config_set(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, std::sync::Mutex<AppConfigState>>,
+    key: String,
+    value: Value,
+) -> Result<(), String> {
+    let path = state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .config_path()
+        .ok_or_else(|| "Config file has not been initialized".to_string())?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => {
+            return Err(format!(
+                "Failed to read config file '{}': {err}",
+                path.display()
+            ))
+        }
+    };
+    let updated = set_config_value(&contents, &key, &value)?;
+    if updated == contents {
+        return Ok(());
     }
+
+    let temp_path = path.with_extension("toml.tmp");
+    std::fs::write(&temp_path, updated)
+        .map_err(|e| format!("Failed to write config file '{}': {e}", temp_path.display()))?;
+    std::fs::rename(&temp_path, &path)
+        .map_err(|e| format!("Failed to replace config file '{}': {e}", path.display()))?;
+    // The watcher sees the write too, but finds nothing left to apply.
+    reload_and_emit(&app_handle, &path);
+    Ok(())
 }
 
 #[cfg(test)]
@@ -190,4 +2146,489 @@ agent_tmux_override_whitelist = ["split","hidden"]
             vec![TmuxCommandMode::Split, TmuxCommandMode::Hidden]
         );
     }
+
+    #[test]
+    fn diff_reports_changed_keys_only() {
+        let previous = parse_config_contents(r#"tmux_command_mode = "split""#);
+        let next = parse_config_contents(
+            r#"
+tmux_command_mode = "hidden"
+socket_dir = "/tmp/neoai"
+"#,
+        );
+
+        let changes = previous.diff(&next);
+        let keys: Vec<&str> = changes.iter().map(|change| change.key.as_str()).collect();
+        assert_eq!(keys, vec!["tmux_command_mode", "socket_dir"]);
+        assert_eq!(changes[0].previous, Value::from("split"));
+        assert_eq!(changes[0].current, Value::from("hidden"));
+        assert!(previous.diff(&previous).is_empty());
+    }
+
+    #[test]
+    fn validation_reports_unknown_keys_and_invalid_values_with_lines() {
+        let toml = r#"# comment
+tmux_command_mode = "pane"
+agent_tmux_override_whitelist = ["split", "tab"]
+tmux_comand_mode = "split"
+"#;
+        let diagnostics = validate_config_contents(toml);
+        let summary: Vec<(Option<usize>, DiagnosticSeverity)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.severity))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Some(2), DiagnosticSeverity::Warning),
+                (Some(3), DiagnosticSeverity::Warning),
+                (Some(4), DiagnosticSeverity::Warning),
+            ]
+        );
+        assert!(diagnostics[0].message.contains("'pane'"));
+        assert!(diagnostics[1].message.contains("'tab'"));
+        assert!(diagnostics[2]
+            .message
+            .contains("Unknown key 'tmux_comand_mode'"));
+    }
+
+    #[test]
+    fn validation_reports_syntax_errors_as_errors() {
+        let diagnostics = validate_config_contents("socket_dir = \"/tmp\"\ninvalid = [\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+        assert!(diagnostics[0].line.is_some());
+        assert!(validate_config_contents(DEFAULT_CONFIG_TEMPLATE).is_empty());
+    }
+
+    #[test]
+    fn parses_agent_section() {
+        let toml = r#"
+tmux_command_mode = "split"
+
+[agent]
+path = " /opt/bin/codex-acp "
+args = ["--verbose"]
+model = " o3 "
+env = { RUST_LOG = "debug" }
+auto_start_on_launch = true
+"#;
+        let config = parse_config_contents(toml);
+        assert_eq!(config.agent.path.as_deref(), Some("/opt/bin/codex-acp"));
+        assert_eq!(config.agent.args, vec!["--verbose".to_string()]);
+        assert_eq!(config.agent.model.as_deref(), Some("o3"));
+        assert_eq!(
+            parse_config_contents("[agent]\nmodel = \"\"\n").agent.model,
+            None
+        );
+        assert_eq!(
+            config.agent.env.get("RUST_LOG").map(String::as_str),
+            Some("debug")
+        );
+        assert!(config.agent.auto_start_on_launch);
+        assert!(!config.agent.auto_restart);
+        assert_eq!(config.agent.event_batch_ms, 16);
+        assert!(!config.agent.allow_terminal_snapshot);
+        assert!(
+            parse_config_contents("[agent]\nallow_terminal_snapshot = true\n")
+                .agent
+                .allow_terminal_snapshot
+        );
+        assert!(config.agent.stream_thoughts);
+        assert!(
+            !parse_config_contents("[agent]\nstream_thoughts = false\n")
+                .agent
+                .stream_thoughts
+        );
+    }
+
+    #[test]
+    fn event_batching_can_be_tuned_or_disabled() {
+        let mut state = AppConfigState::default();
+        assert_eq!(
+            state.event_batch_interval(),
+            Some(Duration::from_millis(16))
+        );
+
+        state.config = parse_config_contents("[agent]\nevent_batch_ms = 0\n");
+        assert_eq!(state.event_batch_interval(), None);
+
+        let diagnostics = validate_config_contents("[agent]\nevent_batch_ms = -5\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].key.as_deref(), Some("agent.event_batch_ms"));
+    }
+
+    #[test]
+    fn limits_concurrent_commands_per_session() {
+        let config = parse_config_contents("");
+        assert_eq!(config.agent.max_concurrent_commands, 4);
+        assert!(config.agent.queue_excess_commands);
+
+        let config = parse_config_contents(
+            "[agent]\nmax_concurrent_commands = 0\nqueue_excess_commands = false\n",
+        );
+        assert_eq!(config.agent.max_concurrent_commands, 0);
+        assert!(!config.agent.queue_excess_commands);
+
+        let diagnostics = validate_config_contents("[agent]\nmax_concurrent_commands = \"4\"\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].key.as_deref(),
+            Some("agent.max_concurrent_commands")
+        );
+    }
+
+    #[test]
+    fn command_output_spill_threshold_can_be_disabled() {
+        let config = parse_config_contents("[agent]\npath = \"codex-acp\"\n");
+        assert_eq!(config.agent.command_output_spill_bytes, 256 * 1024);
+
+        let config = parse_config_contents("[agent]\ncommand_output_spill_bytes = 0\n");
+        assert_eq!(config.agent.command_output_spill_bytes, 0);
+    }
+
+    #[test]
+    fn context_window_can_be_resized_or_disabled() {
+        let mut state = AppConfigState::default();
+        assert_eq!(state.context_window_tokens(), Some(200_000));
+
+        state.config = parse_config_contents("[agent]\ncontext_window_tokens = 128000\n");
+        assert_eq!(state.context_window_tokens(), Some(128_000));
+
+        state.config = parse_config_contents("[agent]\ncontext_window_tokens = 0\n");
+        assert_eq!(state.context_window_tokens(), None);
+
+        let diagnostics = validate_config_contents("[agent]\ncontext_window_tokens = -1\n");
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn auto_approve_kinds_keep_known_tool_kinds() {
+        assert!(parse_config_contents("")
+            .agent
+            .auto_approve_kinds
+            .is_empty());
+        let contents = "[agent]\nauto_approve_kinds = [\"read\", \" Fetch \", \"sudo\"]\n";
+        assert_eq!(
+            parse_config_contents(contents).agent.auto_approve_kinds,
+            vec!["read".to_string(), "fetch".to_string()]
+        );
+        let diagnostics = validate_config_contents(contents);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Warning);
+        assert_eq!(diagnostics[0].line, Some(2));
+    }
+
+    #[test]
+    fn command_cwd_allow_keeps_absolute_paths() {
+        let mut state = AppConfigState::default();
+        assert!(state.command_cwd_allow().is_empty());
+
+        state.config =
+            parse_config_contents("[agent]\ncommand_cwd_allow = [\"/tmp\", \"relative\", \" \"]\n");
+        assert_eq!(state.command_cwd_allow(), vec![PathBuf::from("/tmp")]);
+    }
+
+    #[test]
+    fn command_env_rules_deny_loader_overrides_by_default() {
+        let mut state = AppConfigState::default();
+        let rules = state.command_env_rules();
+        assert!(!rules.permits("PATH"));
+        assert!(!rules.permits("Path"));
+        assert!(!rules.permits("LD_PRELOAD"));
+        assert!(!rules.permits("DYLD_INSERT_LIBRARIES"));
+        assert!(rules.permits("RUST_LOG"));
+        assert!(rules.permits("LD"));
+        assert!(EnvRules::default().permits("PATH"));
+
+        state.config = parse_config_contents(
+            "[agent]\ncommand_env_allow = [\"RUST_*\", \"CI\"]\ncommand_env_deny = [\"RUST_BACKTRACE\"]\n",
+        );
+        let rules = state.command_env_rules();
+        assert!(rules.permits("RUST_LOG"));
+        assert!(rules.permits("CI"));
+        assert!(!rules.permits("RUST_BACKTRACE"));
+        assert!(!rules.permits("HOME"));
+        assert!(!rules.permits("PATH"));
+
+        let diagnostics = validate_config_contents("[agent]\ncommand_env_deny = \"PATH\"\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].key.as_deref(),
+            Some("agent.command_env_deny")
+        );
+    }
+
+    #[test]
+    fn retry_backoff_doubles_up_to_the_cap() {
+        let mut state = AppConfigState::default();
+        let policy = RetryPolicy {
+            jitter: false,
+            ..state.retry_policy()
+        };
+        assert_eq!(policy.delay(1, 0), Duration::from_millis(1000));
+        assert_eq!(policy.delay(3, 0), Duration::from_millis(4000));
+        assert_eq!(policy.delay(40, 0), Duration::from_millis(30_000));
+
+        let jittered = state.retry_policy();
+        assert_eq!(jittered.delay(2, 0), Duration::from_millis(1000));
+        assert_eq!(jittered.delay(2, 1000), Duration::from_millis(2000));
+        assert!(jittered.delay(2, u64::MAX) <= Duration::from_millis(2000));
+
+        state.config = parse_config_contents("[agent]\nprompt_retries = 0\n");
+        assert_eq!(state.retry_policy().max_retries, 0);
+
+        let diagnostics = validate_config_contents("[agent]\nprompt_retry_max_ms = -1\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].key.as_deref(),
+            Some("agent.prompt_retry_max_ms")
+        );
+    }
+
+    #[test]
+    fn parses_terminal_section_and_drops_invalid_values() {
+        let toml = r#"
+[terminal]
+font_size = 14
+theme = "Builtin Dark"
+padding = 8.5
+corner_radius = -2
+scrollback_limit = 1000000
+prewarm = 2
+option_as_alt = true
+"#;
+        let config = parse_config_contents(toml);
+        assert_eq!(config.terminal.font_size, Some(14.0));
+        assert_eq!(config.terminal.theme.as_deref(), Some("Builtin Dark"));
+        assert_eq!(config.terminal.padding, Some(8.5));
+        assert_eq!(config.terminal.corner_radius, None);
+        assert_eq!(config.terminal.scrollback_limit, Some(1_000_000));
+        assert_eq!(config.terminal.prewarm, 2);
+        assert!(config.terminal.option_as_alt);
+
+        let diagnostics = validate_config_contents(toml);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].key.as_deref(),
+            Some("terminal.corner_radius")
+        );
+        assert_eq!(diagnostics[0].line, Some(6));
+    }
+
+    #[test]
+    fn validation_locates_keys_inside_agent_section() {
+        let toml = r#"path = "top-level typo"
+
+[agent]
+path = "codex-acp"
+auto_restart = "yes"
+"#;
+        let diagnostics = validate_config_contents(toml);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].line, Some(1));
+        assert_eq!(diagnostics[1].key.as_deref(), Some("agent.auto_restart"));
+        assert_eq!(diagnostics[1].line, Some(5));
+        assert_eq!(diagnostics[1].severity, DiagnosticSeverity::Error);
+    }
+
+    #[test]
+    fn validation_warns_about_plaintext_credentials_in_agent_env() {
+        let toml = r#"[agent]
+env = { RUST_LOG = "info", OPENAI_API_KEY = "sk-live", GH_TOKEN = "secret:gh" }
+"#;
+        let diagnostics = validate_config_contents(toml);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].key.as_deref(),
+            Some("agent.env.OPENAI_API_KEY")
+        );
+        assert_eq!(diagnostics[0].line, Some(2));
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Warning);
+        assert!(diagnostics[0].message.contains("secret:openai_api_key"));
+    }
+
+    #[test]
+    fn parses_log_level_case_insensitively() {
+        let config = parse_config_contents(r#"log_level = "DEBUG""#);
+        assert_eq!(config.log_level, LevelFilter::Debug);
+
+        let config = parse_config_contents(r#"log_level = "loud""#);
+        assert_eq!(config.log_level, LevelFilter::Info);
+        let diagnostics = validate_config_contents(r#"log_level = "loud""#);
+        assert_eq!(diagnostics[0].key.as_deref(), Some("log_level"));
+    }
+
+    #[test]
+    fn parses_notification_toggles_with_defaults() {
+        let config = parse_config_contents("");
+        assert_eq!(config.notifications, NotificationConfig::default());
+
+        let toml = r#"
+[notifications]
+permission_request = false
+command_min_duration_secs = 30
+"#;
+        let config = parse_config_contents(toml);
+        assert!(config.notifications.turn_finished);
+        assert!(!config.notifications.permission_request);
+        assert_eq!(
+            config.notifications.command_min_duration,
+            Duration::from_secs(30)
+        );
+        assert!(validate_config_contents(toml).is_empty());
+
+        let diagnostics =
+            validate_config_contents("[notifications]\ncommand_finished = \"sometimes\"\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].key.as_deref(),
+            Some("notifications.command_finished")
+        );
+    }
+
+    #[test]
+    fn parses_keymap_conflict_policy() {
+        let config = parse_config_contents("");
+        assert_eq!(config.keymaps, KeymapConfig::default());
+
+        let toml = "[keymaps]\non_conflict = \"Relocate\"\nrelocate_prefix = \"<leader>n\"\n";
+        let config = parse_config_contents(toml);
+        assert_eq!(config.keymaps.on_conflict, KeymapConflictPolicy::Relocate);
+        assert_eq!(config.keymaps.relocate_prefix, "<leader>n");
+        assert!(validate_config_contents(toml).is_empty());
+
+        let toml = "[keymaps]\non_conflict = \"clobber\"\nrelocate_prefix = \" \"\n";
+        let config = parse_config_contents(toml);
+        assert_eq!(config.keymaps, KeymapConfig::default());
+        let diagnostics = validate_config_contents(toml);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].key.as_deref(), Some("keymaps.on_conflict"));
+    }
+
+    #[test]
+    fn parses_context_section_and_ignores_zero_budget() {
+        let toml = "[context]\ntoken_budget = 4000\nrecent_files = 0\ncontext_reuse = false\n";
+        let config = parse_config_contents(toml);
+        assert_eq!(config.context.token_budget, 4000);
+        assert_eq!(config.context.recent_files, 0);
+        assert!(!config.context.context_reuse);
+        assert!(validate_config_contents(toml).is_empty());
+
+        let toml = "[context]\ntoken_budget = 0\n";
+        assert_eq!(
+            parse_config_contents(toml).context,
+            ContextConfig::default()
+        );
+        let diagnostics = validate_config_contents(toml);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Warning);
+    }
+
+    #[test]
+    fn command_shell_is_optional() {
+        assert_eq!(parse_config_contents("").command_shell, None);
+        let config = parse_config_contents(r#"command_shell = " /bin/sh ""#);
+        assert_eq!(config.command_shell.as_deref(), Some("/bin/sh"));
+        assert_eq!(validate_config_contents("command_shell = 1").len(), 1);
+    }
+
+    #[test]
+    fn global_shortcut_is_optional() {
+        assert_eq!(parse_config_contents("").global_shortcut, None);
+        assert_eq!(
+            parse_config_contents(r#"global_shortcut = "  ""#).global_shortcut,
+            None
+        );
+        let config = parse_config_contents(r#"global_shortcut = " Alt+Space ""#);
+        assert_eq!(config.global_shortcut.as_deref(), Some("Alt+Space"));
+    }
+
+    #[test]
+    fn update_checks_are_on_unless_disabled() {
+        assert!(parse_config_contents("").check_for_updates);
+        assert!(!parse_config_contents("check_for_updates = false").check_for_updates);
+        assert!(validate_config_contents("check_for_updates = false").is_empty());
+    }
+
+    #[test]
+    fn tmux_control_mode_is_on_unless_disabled() {
+        assert!(parse_config_contents("").tmux_control_mode);
+        assert!(!parse_config_contents("tmux_control_mode = false").tmux_control_mode);
+        assert_eq!(
+            validate_config_contents("tmux_control_mode = \"no\"").len(),
+            1
+        );
+    }
+
+    #[test]
+    fn agent_download_mirror_must_be_an_http_url() {
+        assert_eq!(parse_config_contents("").agent_download_base_url, None);
+        let config = parse_config_contents(
+            r#"agent_download_base_url = " https://mirror.corp/codex-acp/ ""#,
+        );
+        assert_eq!(
+            config.agent_download_base_url.as_deref(),
+            Some("https://mirror.corp/codex-acp")
+        );
+
+        let toml = r#"agent_download_base_url = "mirror.corp/codex-acp""#;
+        assert_eq!(parse_config_contents(toml).agent_download_base_url, None);
+        let diagnostics = validate_config_contents(toml);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Warning);
+    }
+
+    #[test]
+    fn accepts_only_absolute_socket_dir() {
+        let config = parse_config_contents(r#"socket_dir = "/run/user/1000/neoai""#);
+        assert_eq!(
+            config.socket_dir,
+            Some(PathBuf::from("/run/user/1000/neoai"))
+        );
+
+        let config = parse_config_contents(r#"socket_dir = "relative/dir""#);
+        assert_eq!(config.socket_dir, None);
+    }
+
+    #[test]
+    fn sets_single_keys_keeping_the_rest_of_the_file() {
+        let contents =
+            "# NeoAI\nlog_level = \"info\"\n\n[context]\n# budget\ntoken_budget = 8000\n";
+
+        let updated = set_config_value(contents, "log_level", &Value::from("debug")).unwrap();
+        assert_eq!(
+            updated,
+            "# NeoAI\nlog_level = \"debug\"\n\n[context]\n# budget\ntoken_budget = 8000\n"
+        );
+
+        let updated = set_config_value(contents, "context.recent_files", &Value::from(5)).unwrap();
+        assert!(updated.contains("[context]\nrecent_files = 5\n# budget\n"));
+        assert_eq!(parse_config_contents(&updated).context.recent_files, 5);
+
+        let updated = set_config_value(contents, "check_for_updates", &Value::from(false)).unwrap();
+        assert!(updated.contains("log_level = \"info\"\ncheck_for_updates = false\n\n[context]"));
+
+        let updated = set_config_value(contents, "agent.model", &Value::from("o3")).unwrap();
+        assert!(updated.ends_with("token_budget = 8000\n\n[agent]\nmodel = \"o3\"\n"));
+
+        let updated = set_config_value(contents, "context.token_budget", &Value::Null).unwrap();
+        assert!(!updated.contains("token_budget"));
+
+        assert!(set_config_value(contents, "no_such_key", &Value::from(1)).is_err());
+        assert!(set_config_value(contents, "log_level", &Value::from("loud")).is_err());
+    }
+
+    #[test]
+    fn replaces_every_line_of_a_multi_line_value() {
+        let contents = "[agent]\nargs = [\n  \"--verbose\",\n  \"--acp\",\n]\nmodel = \"o3\"\n";
+
+        let updated =
+            set_config_value(contents, "agent.args", &Value::from(vec!["--acp"])).unwrap();
+        assert_eq!(updated, "[agent]\nargs = [\"--acp\"]\nmodel = \"o3\"\n");
+        assert_eq!(parse_config_contents(&updated).agent.args, vec!["--acp"]);
+
+        let updated = set_config_value(contents, "agent.args", &Value::Null).unwrap();
+        assert_eq!(updated, "[agent]\nmodel = \"o3\"\n");
+    }
 }
@@ -0,0 +1,104 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use tauri::Manager;
+
+/// Environment variable that relocates everything NeoAI stores on disk.
+pub const DATA_DIR_ENV: &str = "NEOAI_DATA_DIR";
+
+/// Bundle identifier; names the app-local data directory off macOS.
+pub const APP_IDENTIFIER: &str = "com.fcoury.neoai";
+
+/// Root directory for config, per-folder state, registries and vendored agents.
+///
+/// `NEOAI_DATA_DIR` wins when set to an absolute path. Otherwise macOS keeps the
+/// historical `~/.neoai`, and other platforms use the app-local data directory
+/// (`$XDG_DATA_HOME/<identifier>` on Linux, `%LOCALAPPDATA%\<identifier>` on Windows).
+pub fn app_root_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    static OVERRIDE: OnceLock<Option<PathBuf>> = OnceLock::new();
+    if let Some(dir) = OVERRIDE.get_or_init(|| data_dir_override(std::env::var_os(DATA_DIR_ENV))) {
+        return Ok(dir.clone());
+    }
+
+    platform_root_dir(app_handle)
+}
+
+/// Same directory as [`app_root_dir`], resolved without a running app so
+/// `neoai-cli` shares config, vendored agents and snapshots with the GUI.
+pub fn standalone_root_dir() -> Result<PathBuf, String> {
+    if let Some(dir) = data_dir_override(std::env::var_os(DATA_DIR_ENV)) {
+        return Ok(dir);
+    }
+
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+    };
+    if cfg!(target_os = "macos") {
+        if let Some(home) = env_dir("HOME") {
+            return Ok(home.join(".neoai"));
+        }
+    }
+    let base = if cfg!(windows) {
+        env_dir("LOCALAPPDATA")
+    } else {
+        env_dir("XDG_DATA_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".local/share")))
+    };
+    base.map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or_else(|| "Failed to resolve the NeoAI data directory".to_string())
+}
+
+fn data_dir_override(value: Option<OsString>) -> Option<PathBuf> {
+    let path = PathBuf::from(value?);
+    if path.as_os_str().is_empty() {
+        return None;
+    }
+    if !path.is_absolute() {
+        log::warn!(
+            "Ignoring {DATA_DIR_ENV}='{}': path must be absolute",
+            path.display()
+        );
+        return None;
+    }
+    Some(path)
+}
+
+fn platform_root_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    #[cfg(target_os = "macos")]
+    {
+        match app_handle.path().home_dir() {
+            Ok(home) => return Ok(home.join(".neoai")),
+            Err(err) => {
+                log::warn!("Failed to resolve home directory, using app data directory: {err}");
+            }
+        }
+    }
+
+    app_handle
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Failed to resolve app local data directory: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_absolute_data_dir_override() {
+        assert_eq!(data_dir_override(None), None);
+        assert_eq!(data_dir_override(Some(OsString::new())), None);
+        assert_eq!(
+            data_dir_override(Some(OsString::from("relative/dir"))),
+            None
+        );
+
+        let absolute = std::env::temp_dir().join("neoai-data");
+        assert_eq!(
+            data_dir_override(Some(absolute.clone().into_os_string())),
+            Some(absolute)
+        );
+    }
+}
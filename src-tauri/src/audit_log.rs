@@ -0,0 +1,382 @@
+//! Record of everything run or typed in terminals on someone's behalf:
+//! commands the agent starts through ACP `terminal/create`, and text the app
+//! writes into a user's terminal. Entries are appended to `audit-log.jsonl`
+//! as they happen, so a crash loses nothing already recorded.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app_paths;
+use crate::folder_state::{self, FolderStateStore};
+
+pub const AUDIT_LOG_FILE: &str = "audit-log.jsonl";
+
+/// Entries kept; older ones are dropped when the log is loaded at startup.
+const MAX_ENTRIES: usize = 10_000;
+
+/// Entries returned by a query without a `limit`.
+const DEFAULT_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditOrigin {
+    /// A command the agent ran in a tmux pane.
+    Agent,
+    /// Text the app wrote into a terminal (`ghostty_write_text`,
+    /// `ghostty_run_command`).
+    App,
+    /// A permission request approved without asking under
+    /// `auto_approve_kinds`.
+    AutoApproval,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// Milliseconds since the Unix epoch.
+    pub at: u64,
+    pub origin: AuditOrigin,
+    pub terminal_id: String,
+    /// ACP session the command came from, or the folder's session for app
+    /// input.
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Command line, the text written for app input, or the approved tool
+    /// call's kind and command (or title).
+    pub command: String,
+    #[serde(default)]
+    pub cwd: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditFilter {
+    #[serde(default)]
+    pub origin: Option<AuditOrigin>,
+    #[serde(default)]
+    pub terminal_id: Option<String>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Only entries at or after this time, in milliseconds.
+    #[serde(default)]
+    pub since: Option<u64>,
+    /// Case-insensitive text the command must contain.
+    #[serde(default)]
+    pub contains: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl AuditFilter {
+    fn matches(&self, entry: &AuditEntry, contains: Option<&str>) -> bool {
+        if self.origin.is_some() && self.origin != Some(entry.origin) {
+            return false;
+        }
+        if self.terminal_id.is_some() && self.terminal_id.as_ref() != Some(&entry.terminal_id) {
+            return false;
+        }
+        if self.session_id.is_some() && self.session_id != entry.session_id {
+            return false;
+        }
+        if self.since.is_some_and(|since| entry.at < since) {
+            return false;
+        }
+        match contains {
+            Some(text) => entry.command.to_lowercase().contains(text),
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AuditLogStore {
+    path: Option<PathBuf>,
+    entries: VecDeque<AuditEntry>,
+}
+
+impl AuditLogStore {
+    pub fn initialize(&mut self, app_handle: &tauri::AppHandle) -> Result<(), String> {
+        let path = app_paths::app_root_dir(app_handle)?.join(AUDIT_LOG_FILE);
+        let mut trimmed = false;
+
+        if path.exists() {
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read audit log '{}': {e}", path.display()))?;
+            let (entries, skipped) = parse_entries(&contents);
+            if skipped > 0 {
+                log::warn!(
+                    "Skipped {skipped} unreadable line(s) in audit log '{}'",
+                    path.display()
+                );
+            }
+            self.entries = entries;
+            trimmed = self.trim();
+        }
+
+        self.path = Some(path);
+        if trimmed {
+            self.rewrite()?;
+        }
+        Ok(())
+    }
+
+    pub fn record(&mut self, entry: AuditEntry) -> Result<(), String> {
+        self.entries.push_back(entry.clone());
+        self.trim();
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                format!(
+                    "Failed to create audit log directory '{}': {e}",
+                    parent.display()
+                )
+            })?;
+        }
+
+        let mut line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+        line.push('\n');
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| format!("Failed to append to audit log '{}': {e}", path.display()))
+    }
+
+    /// Entries matching `filter`, newest first.
+    pub fn load(&self, filter: &AuditFilter) -> Vec<AuditEntry> {
+        let contains = filter
+            .contains
+            .as_deref()
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_lowercase);
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| filter.matches(entry, contains.as_deref()))
+            .take(filter.limit.unwrap_or(DEFAULT_LIMIT))
+            .cloned()
+            .collect()
+    }
+
+    /// Drops the oldest entries beyond [`MAX_ENTRIES`]; true if any were.
+    fn trim(&mut self) -> bool {
+        let excess = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..excess);
+        excess > 0
+    }
+
+    fn rewrite(&self) -> Result<(), String> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        let mut contents = String::new();
+        for entry in &self.entries {
+            contents.push_str(&serde_json::to_string(entry).map_err(|e| e.to_string())?);
+            contents.push('\n');
+        }
+        let temp_path = path.with_extension("jsonl.tmp");
+        std::fs::write(&temp_path, contents)
+            .map_err(|e| format!("Failed to write audit log '{}': {e}", temp_path.display()))?;
+        std::fs::rename(&temp_path, path)
+            .map_err(|e| format!("Failed to replace audit log '{}': {e}", path.display()))
+    }
+}
+
+/// Parses one entry per line, returning the entries and how many lines
+/// could not be read.
+fn parse_entries(contents: &str) -> (VecDeque<AuditEntry>, usize) {
+    let mut skipped = 0;
+    let entries = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            let entry = serde_json::from_str(line).ok();
+            if entry.is_none() {
+                skipped += 1;
+            }
+            entry
+        })
+        .collect();
+    (entries, skipped)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn record(app_handle: &tauri::AppHandle, entry: AuditEntry) {
+    use tauri::Manager;
+
+    let Some(store) = app_handle.try_state::<std::sync::Mutex<AuditLogStore>>() else {
+        return;
+    };
+    let Ok(mut store) = store.lock() else {
+        log::warn!("Failed to lock audit log");
+        return;
+    };
+    if let Err(err) = store.record(entry) {
+        log::warn!("{err}");
+    }
+}
+
+/// Records a command the agent started in `terminal_id`'s tmux session,
+/// logging instead of failing so a disk error never blocks the command.
+pub fn record_agent_command(
+    app_handle: &tauri::AppHandle,
+    terminal_id: &str,
+    session_id: &str,
+    command: String,
+    cwd: Option<String>,
+) {
+    record(
+        app_handle,
+        AuditEntry {
+            at: now_millis(),
+            origin: AuditOrigin::Agent,
+            terminal_id: terminal_id.to_string(),
+            session_id: Some(session_id.to_string()),
+            command,
+            cwd,
+        },
+    );
+}
+
+/// Records a permission request approved under `auto_approve_kinds`.
+pub fn record_auto_approval(
+    app_handle: &tauri::AppHandle,
+    terminal_id: &str,
+    session_id: &str,
+    description: String,
+    cwd: Option<String>,
+) {
+    record(
+        app_handle,
+        AuditEntry {
+            at: now_millis(),
+            origin: AuditOrigin::AutoApproval,
+            terminal_id: terminal_id.to_string(),
+            session_id: Some(session_id.to_string()),
+            command: description,
+            cwd,
+        },
+    );
+}
+
+/// Records text the app wrote into `terminal_id`, attributed to the ACP
+/// session of the terminal's folder, if any.
+pub fn record_app_input(app_handle: &tauri::AppHandle, terminal_id: &str, text: &str) {
+    use tauri::Manager;
+
+    let session_id = folder_state::folder_id_for_terminal(terminal_id).and_then(|folder_id| {
+        let store = app_handle.try_state::<std::sync::Mutex<FolderStateStore>>()?;
+        let store = store.lock().ok()?;
+        store.session(folder_id).acp_session_id
+    });
+    record(
+        app_handle,
+        AuditEntry {
+            at: now_millis(),
+            origin: AuditOrigin::App,
+            terminal_id: terminal_id.to_string(),
+            session_id,
+            command: text.to_string(),
+            cwd: None,
+        },
+    );
+}
+
+// -- Tauri IPC commands --
+
+/// Audit log entries matching `filter`, newest first; 200 at most unless the
+/// filter sets a `limit`.
+#[tauri::command]
+pub async fn audit_log_load(
+    state: tauri::State<'_, std::sync::Mutex<AuditLogStore>>,
+    filter: Option<AuditFilter>,
+) -> Result<Vec<AuditEntry>, String> {
+    let store = state.lock().map_err(|e| e.to_string())?;
+    Ok(store.load(&filter.unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(at: u64, origin: AuditOrigin, terminal_id: &str, command: &str) -> AuditEntry {
+        AuditEntry {
+            at,
+            origin,
+            terminal_id: terminal_id.to_string(),
+            session_id: None,
+            command: command.to_string(),
+            cwd: None,
+        }
+    }
+
+    #[test]
+    fn filters_entries_newest_first() {
+        let mut store = AuditLogStore::default();
+        store
+            .record(entry(1, AuditOrigin::Agent, "terminal-a", "cargo test"))
+            .unwrap();
+        store
+            .record(entry(2, AuditOrigin::App, "terminal-a", "ls\r"))
+            .unwrap();
+        store
+            .record(entry(3, AuditOrigin::Agent, "terminal-b", "rm -rf target"))
+            .unwrap();
+
+        let commands = |filter: AuditFilter| {
+            store
+                .load(&filter)
+                .into_iter()
+                .map(|entry| entry.command)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            commands(AuditFilter::default()),
+            ["rm -rf target", "ls\r", "cargo test"]
+        );
+        assert_eq!(
+            commands(AuditFilter {
+                origin: Some(AuditOrigin::Agent),
+                since: Some(2),
+                ..AuditFilter::default()
+            }),
+            ["rm -rf target"]
+        );
+        assert_eq!(
+            commands(AuditFilter {
+                terminal_id: Some("terminal-a".to_string()),
+                contains: Some(" CARGO ".to_string()),
+                ..AuditFilter::default()
+            }),
+            ["cargo test"]
+        );
+        assert_eq!(
+            commands(AuditFilter {
+                limit: Some(1),
+                ..AuditFilter::default()
+            }),
+            ["rm -rf target"]
+        );
+    }
+
+    #[test]
+    fn skips_unreadable_lines() {
+        let line = serde_json::to_string(&entry(1, AuditOrigin::App, "t", "echo")).unwrap();
+        let (entries, skipped) = parse_entries(&format!("{line}\n{{\"at\":\n\n{line}\n"));
+        assert_eq!((entries.len(), skipped), (2, 1));
+    }
+}
@@ -0,0 +1,250 @@
+//! Sends one prompt to the configured agent from a plain terminal, printing
+//! the reply to stdout and tool activity to stderr. File edits the agent makes
+//! are written to disk and snapshotted like in the app.
+
+use std::io::{BufRead, Read, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use agent_client_protocol as acp;
+use neoai_lib::acp_core::{self, HeadlessOptions, HeadlessOutput};
+
+const USAGE: &str = "\
+Usage: neoai-cli [OPTIONS] [PROMPT]
+
+Sends PROMPT to the agent configured in NeoAI's config.toml. With no PROMPT,
+or PROMPT `-`, the prompt is read from stdin.
+
+Options:
+  --cwd DIR          Working directory for the session (default: current)
+  --agent PATH       Agent program, overriding agent.path
+  --yes              Approve every permission request
+  --deny             Reject every permission request
+  --show-thoughts    Print the agent's reasoning to stderr
+  -h, --help         Show this help";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PermissionPolicy {
+    Ask,
+    Approve,
+    Reject,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct CliArgs {
+    cwd: Option<PathBuf>,
+    agent: Option<String>,
+    permissions: PermissionPolicy,
+    show_thoughts: bool,
+    /// `None` when the prompt comes from stdin.
+    prompt: Option<String>,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<CliArgs>, String> {
+    let mut parsed = CliArgs {
+        cwd: None,
+        agent: None,
+        permissions: PermissionPolicy::Ask,
+        show_thoughts: false,
+        prompt: None,
+    };
+    let mut words = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--cwd" => {
+                let dir = args.next().ok_or("--cwd needs a directory")?;
+                parsed.cwd = Some(PathBuf::from(dir));
+            }
+            "--agent" => parsed.agent = Some(args.next().ok_or("--agent needs a path")?),
+            "--yes" => parsed.permissions = PermissionPolicy::Approve,
+            "--deny" => parsed.permissions = PermissionPolicy::Reject,
+            "--show-thoughts" => parsed.show_thoughts = true,
+            "--" => {
+                words.extend(args.by_ref());
+            }
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {flag}")),
+            _ => words.push(arg),
+        }
+    }
+    let prompt = words.join(" ");
+    if !prompt.trim().is_empty() && prompt != "-" {
+        parsed.prompt = Some(prompt);
+    }
+    Ok(Some(parsed))
+}
+
+struct TerminalOutput {
+    permissions: PermissionPolicy,
+    show_thoughts: bool,
+}
+
+fn find_option(options: &[acp::PermissionOption], allow: bool) -> Option<String> {
+    let wanted = if allow {
+        [
+            acp::PermissionOptionKind::AllowOnce,
+            acp::PermissionOptionKind::AllowAlways,
+        ]
+    } else {
+        [
+            acp::PermissionOptionKind::RejectOnce,
+            acp::PermissionOptionKind::RejectAlways,
+        ]
+    };
+    wanted.iter().find_map(|kind| {
+        options
+            .iter()
+            .find(|option| option.kind == *kind)
+            .map(|option| option.option_id.to_string())
+    })
+}
+
+impl HeadlessOutput for TerminalOutput {
+    fn message_chunk(&self, text: &str) {
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(text.as_bytes());
+        let _ = stdout.flush();
+    }
+
+    fn thought_chunk(&self, text: &str) {
+        if self.show_thoughts {
+            eprint!("{text}");
+        }
+    }
+
+    fn tool_call(&self, _id: &str, title: &str, kind: &str) {
+        eprintln!("\n[tool] {title} ({kind})");
+    }
+
+    fn tool_call_updated(&self, _id: &str, status: &str) {
+        if status == "Completed" || status == "Failed" {
+            eprintln!("[tool] {}", status.to_lowercase());
+        }
+    }
+
+    fn diff(&self, _id: &str, path: &str, diff: &neoai_lib::acp_core::TextDiff) {
+        eprintln!("--- {path} (+{} -{})", diff.additions, diff.deletions);
+        eprint!("{}", diff.unified);
+    }
+
+    fn choose_permission(
+        &self,
+        title: Option<&str>,
+        options: &[acp::PermissionOption],
+    ) -> Option<String> {
+        let title = title.unwrap_or("The agent wants to run a tool");
+        match self.permissions {
+            PermissionPolicy::Approve => {
+                eprintln!("[permission] approved: {title}");
+                find_option(options, true)
+            }
+            PermissionPolicy::Reject => {
+                eprintln!("[permission] rejected: {title}");
+                find_option(options, false)
+            }
+            PermissionPolicy::Ask => {
+                eprint!("[permission] {title}. Allow? [y/N] ");
+                let mut answer = String::new();
+                let _ = std::io::stdin().lock().read_line(&mut answer);
+                let allow = matches!(answer.trim(), "y" | "Y" | "yes");
+                find_option(options, allow)
+            }
+        }
+    }
+}
+
+fn run(args: CliArgs) -> Result<(), String> {
+    let prompt = match args.prompt {
+        Some(prompt) => prompt,
+        None => {
+            let mut prompt = String::new();
+            std::io::stdin()
+                .read_to_string(&mut prompt)
+                .map_err(|e| format!("Failed to read prompt from stdin: {e}"))?;
+            if prompt.trim().is_empty() {
+                return Err("No prompt given".to_string());
+            }
+            prompt
+        }
+    };
+    let cwd = match args.cwd {
+        Some(dir) => std::fs::canonicalize(&dir)
+            .map_err(|e| format!("Invalid --cwd '{}': {e}", dir.display()))?,
+        None => std::env::current_dir().map_err(|e| e.to_string())?,
+    };
+    let output = Rc::new(TerminalOutput {
+        permissions: args.permissions,
+        show_thoughts: args.show_thoughts,
+    });
+    let options = HeadlessOptions {
+        cwd,
+        agent_path: args.agent,
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("Failed to start runtime: {e}"))?;
+    let local = tokio::task::LocalSet::new();
+    let result = local.block_on(&runtime, acp_core::run_prompt(options, prompt, output))?;
+
+    println!();
+    if !result.snapshot_ids.is_empty() {
+        eprintln!(
+            "[edits] {} write(s) snapshotted in session {}",
+            result.snapshot_ids.len(),
+            result.session_id
+        );
+    }
+    if result.stop_reason != acp_core::StopReason::EndTurn {
+        eprintln!("[stopped] {}", result.stop_reason);
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return;
+        }
+        Err(err) => {
+            eprintln!("neoai-cli: {err}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if let Err(err) = run(args) {
+        eprintln!("neoai-cli: {err}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Result<Option<CliArgs>, String> {
+        parse_args(list.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_flags_and_joins_the_prompt() {
+        let parsed = args(&["--cwd", "/tmp", "--yes", "fix", "the", "tests"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed.cwd, Some(PathBuf::from("/tmp")));
+        assert_eq!(parsed.permissions, PermissionPolicy::Approve);
+        assert_eq!(parsed.prompt.as_deref(), Some("fix the tests"));
+
+        assert_eq!(args(&["-"]).unwrap().unwrap().prompt, None);
+        assert_eq!(
+            args(&["--", "--yes"]).unwrap().unwrap().prompt.as_deref(),
+            Some("--yes")
+        );
+        assert!(args(&["--cwd"]).is_err());
+        assert!(args(&["--bogus"]).is_err());
+        assert_eq!(args(&["--help"]).unwrap(), None);
+    }
+}
@@ -0,0 +1,229 @@
+//! Latency of Neovim bridge calls, per terminal. Each call is timed from the
+//! moment it asks for the connection until it lets go of it, so time spent
+//! queued behind a blocked call counts too. Calls still running are listed
+//! with how long they have waited, which is what a frozen panel looks like.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+use serde::Serialize;
+
+/// Calls taking longer than this are logged and kept in `recent_slow`.
+pub const SLOW_CALL_MS: u64 = 500;
+/// Finished calls the percentiles are computed over.
+const WINDOW: usize = 200;
+const RECENT_SLOW: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowCall {
+    pub op: String,
+    pub duration_ms: u64,
+    /// When the call finished, in milliseconds since the Unix epoch.
+    pub finished_at_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingCall {
+    pub op: String,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalBridgeStats {
+    pub terminal_id: String,
+    pub calls: u64,
+    pub slow_calls: u64,
+    /// Over the last finished calls.
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+    /// Newest first.
+    pub recent_slow: Vec<SlowCall>,
+    /// Calls that haven't finished, longest waiting first.
+    pub pending: Vec<PendingCall>,
+}
+
+#[derive(Debug, Default)]
+struct TerminalCalls {
+    calls: u64,
+    slow_calls: u64,
+    durations: VecDeque<u64>,
+    recent_slow: VecDeque<SlowCall>,
+    pending: HashMap<u64, (&'static str, Instant)>,
+}
+
+#[derive(Debug, Default)]
+pub struct BridgeStats {
+    terminals: HashMap<String, TerminalCalls>,
+    next_call: u64,
+}
+
+impl BridgeStats {
+    /// Records that `op` started; pass the returned id to [`Self::finish`].
+    pub fn start(&mut self, terminal_id: &str, op: &'static str, now: Instant) -> u64 {
+        self.next_call += 1;
+        self.terminals
+            .entry(terminal_id.to_string())
+            .or_default()
+            .pending
+            .insert(self.next_call, (op, now));
+        self.next_call
+    }
+
+    /// Records that a call finished. Returns it when it was slow.
+    pub fn finish(
+        &mut self,
+        terminal_id: &str,
+        call: u64,
+        now: Instant,
+        now_ms: u64,
+    ) -> Option<SlowCall> {
+        let calls = self.terminals.get_mut(terminal_id)?;
+        let (op, started) = calls.pending.remove(&call)?;
+        let duration_ms = now.saturating_duration_since(started).as_millis() as u64;
+
+        calls.calls += 1;
+        if calls.durations.len() == WINDOW {
+            calls.durations.pop_front();
+        }
+        calls.durations.push_back(duration_ms);
+        if duration_ms < SLOW_CALL_MS {
+            return None;
+        }
+
+        let slow = SlowCall {
+            op: op.to_string(),
+            duration_ms,
+            finished_at_ms: now_ms,
+        };
+        calls.slow_calls += 1;
+        if calls.recent_slow.len() == RECENT_SLOW {
+            calls.recent_slow.pop_back();
+        }
+        calls.recent_slow.push_front(slow.clone());
+        Some(slow)
+    }
+
+    /// Forgets a terminal's calls, e.g. when its connection closes. Calls
+    /// still running are kept until they finish.
+    pub fn remove(&mut self, terminal_id: &str) {
+        let Some(calls) = self.terminals.get_mut(terminal_id) else {
+            return;
+        };
+        if calls.pending.is_empty() {
+            self.terminals.remove(terminal_id);
+        } else {
+            *calls = TerminalCalls {
+                pending: std::mem::take(&mut calls.pending),
+                ..TerminalCalls::default()
+            };
+        }
+    }
+
+    pub fn snapshot(&self, now: Instant) -> Vec<TerminalBridgeStats> {
+        let mut stats: Vec<TerminalBridgeStats> = self
+            .terminals
+            .iter()
+            .map(|(terminal_id, calls)| {
+                let mut sorted: Vec<u64> = calls.durations.iter().copied().collect();
+                sorted.sort_unstable();
+                let mut pending: Vec<PendingCall> = calls
+                    .pending
+                    .values()
+                    .map(|(op, started)| PendingCall {
+                        op: op.to_string(),
+                        elapsed_ms: now.saturating_duration_since(*started).as_millis() as u64,
+                    })
+                    .collect();
+                pending.sort_by(|a, b| b.elapsed_ms.cmp(&a.elapsed_ms));
+                TerminalBridgeStats {
+                    terminal_id: terminal_id.clone(),
+                    calls: calls.calls,
+                    slow_calls: calls.slow_calls,
+                    p50_ms: percentile(&sorted, 50),
+                    p95_ms: percentile(&sorted, 95),
+                    max_ms: sorted.last().copied().unwrap_or(0),
+                    recent_slow: calls.recent_slow.iter().cloned().collect(),
+                    pending,
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| a.terminal_id.cmp(&b.terminal_id));
+        stats
+    }
+}
+
+/// Nearest-rank percentile of ascending `sorted`.
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn tracks_percentiles_and_slow_calls() {
+        let mut stats = BridgeStats::default();
+        let t0 = Instant::now();
+        for ms in 1..=100u64 {
+            let call = stats.start("t1", "nvim_get_context", t0);
+            let slow = stats.finish("t1", call, t0 + Duration::from_millis(ms), 42);
+            assert!(slow.is_none());
+        }
+        let call = stats.start("t1", "nvim_lsp_rename", t0);
+        let slow = stats.finish("t1", call, t0 + Duration::from_millis(1_200), 99);
+        assert_eq!(
+            slow,
+            Some(SlowCall {
+                op: "nvim_lsp_rename".to_string(),
+                duration_ms: 1_200,
+                finished_at_ms: 99,
+            })
+        );
+
+        let snapshot = stats.snapshot(t0);
+        assert_eq!(snapshot.len(), 1);
+        let t1 = &snapshot[0];
+        assert_eq!((t1.calls, t1.slow_calls), (101, 1));
+        assert_eq!((t1.p50_ms, t1.p95_ms, t1.max_ms), (51, 96, 1_200));
+        assert_eq!(t1.recent_slow.len(), 1);
+        assert!(t1.pending.is_empty());
+    }
+
+    #[test]
+    fn lists_unfinished_calls_across_a_reset() {
+        let mut stats = BridgeStats::default();
+        let t0 = Instant::now();
+        let blocked = stats.start("t1", "nvim_get_context", t0);
+        let done = stats.start("t1", "nvim_get_diagnostics", t0);
+        stats.finish("t1", done, t0 + Duration::from_millis(5), 0);
+
+        stats.remove("t1");
+        let snapshot = stats.snapshot(t0 + Duration::from_secs(3));
+        assert_eq!(snapshot[0].calls, 0);
+        assert_eq!(
+            snapshot[0].pending,
+            vec![PendingCall {
+                op: "nvim_get_context".to_string(),
+                elapsed_ms: 3_000,
+            }]
+        );
+
+        assert!(stats
+            .finish("t1", blocked, t0 + Duration::from_secs(4), 0)
+            .is_some());
+        stats.remove("t1");
+        assert!(stats.snapshot(t0).is_empty());
+        assert!(stats.finish("t1", blocked, t0, 0).is_none());
+    }
+}
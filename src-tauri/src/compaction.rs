@@ -0,0 +1,276 @@
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::acp_client::{self, AcpClientState};
+use crate::app_config;
+use crate::context;
+use crate::conversation_export::{self, ExportFormat, ExportMessage, ExportOptions};
+use crate::error::{ErrorCode, NeoaiError};
+use crate::folder_state::{self, FolderSessionUpdate, FolderStateStore};
+
+/// Transcripts longer than this keep only their most recent part; the previous
+/// summary is expected to be among the messages and covers what came before.
+const MAX_TRANSCRIPT_CHARS: usize = 200_000;
+const MIN_MESSAGES_TO_COMPACT: usize = 2;
+/// Messages replayed into a new session when the caller sets no limit.
+const DEFAULT_SEED_MESSAGES: usize = 40;
+
+const SEED_PREAMBLE: &str = "\
+Earlier conversation in this project, from a previous session that is no \
+longer available. It is background for the requests that follow; don't \
+answer it again.";
+
+const SUMMARY_INSTRUCTIONS: &str = "\
+Summarize the conversation below so it can replace the original messages as \
+context for future requests in this project. Keep decisions made, open tasks, \
+files and symbols discussed, edits that were applied or rejected, and any user \
+preferences. Drop greetings, dead ends and verbatim code unless it is essential. \
+Reply with the summary only, as concise Markdown.";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeedResult {
+    /// Messages included in the replayed history.
+    pub seeded_count: usize,
+    /// Messages left out to stay within the limit and token budget.
+    pub omitted_count: usize,
+    pub estimated_tokens: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactionResult {
+    pub summary: String,
+    /// Messages the summary replaces.
+    pub compacted_count: usize,
+}
+
+fn build_summary_prompt(messages: &[ExportMessage]) -> String {
+    let transcript = conversation_export::render_conversation(
+        messages,
+        ExportFormat::Markdown,
+        &ExportOptions {
+            title: Some("Conversation".to_string()),
+        },
+    );
+    let transcript = match transcript.char_indices().rev().nth(MAX_TRANSCRIPT_CHARS) {
+        Some((cut, _)) => format!("[earlier messages omitted]\n\n{}", &transcript[cut..]),
+        None => transcript,
+    };
+    format!("{SUMMARY_INSTRUCTIONS}\n\n---\n\n{transcript}")
+}
+
+/// User and assistant messages with content; status notes and other system
+/// messages aren't replayed.
+fn replayable_messages(messages: Vec<ExportMessage>) -> Vec<ExportMessage> {
+    messages
+        .into_iter()
+        .filter(|message| matches!(message.role.as_str(), "user" | "assistant"))
+        .filter(|message| !message.content.trim().is_empty())
+        .collect()
+}
+
+/// Renders the last `max_messages` of `conversation` as one context block,
+/// dropping the oldest until it fits in `token_budget`. Returns the block
+/// and how many messages it holds.
+fn build_history_seed(
+    conversation: &[ExportMessage],
+    max_messages: usize,
+    token_budget: usize,
+) -> Option<(String, usize)> {
+    let start = conversation.len().saturating_sub(max_messages);
+    (start..conversation.len()).find_map(|first| {
+        let kept = &conversation[first..];
+        let transcript = conversation_export::render_conversation(
+            kept,
+            ExportFormat::Markdown,
+            &ExportOptions {
+                title: Some("Previous conversation".to_string()),
+            },
+        );
+        let block = format!("{SEED_PREAMBLE}\n\n{transcript}");
+        (context::estimate_tokens(&block) <= token_budget).then_some((block, kept.len()))
+    })
+}
+
+// -- Tauri IPC commands --
+
+/// Asks the agent to summarize `messages` (the folder's older chat history)
+/// and stores the summary in folder state, where `build_context` picks it up
+/// for later prompts. The frontend archives the originals.
+#[tauri::command]
+pub async fn compact_conversation(
+    app_handle: tauri::AppHandle,
+    acp_state: tauri::State<'_, Mutex<AcpClientState>>,
+    folder_id: String,
+    messages: Vec<ExportMessage>,
+) -> Result<CompactionResult, String> {
+    if messages.len() < MIN_MESSAGES_TO_COMPACT {
+        return Err("Not enough messages to compact".to_string());
+    }
+    let working_dir = folder_state::resolve_folder_path(&app_handle, &folder_id)?;
+
+    let prompt = build_summary_prompt(&messages);
+    let summary = acp_client::prompt_captured(&acp_state, working_dir, prompt)
+        .await?
+        .trim()
+        .to_string();
+    if summary.is_empty() {
+        return Err("The agent returned an empty summary".to_string());
+    }
+
+    {
+        use tauri::Manager;
+
+        let store = app_handle.state::<std::sync::Mutex<FolderStateStore>>();
+        let mut store = store.lock().map_err(|e| e.to_string())?;
+        store.update_session(
+            &folder_id,
+            FolderSessionUpdate {
+                conversation_summary: Some(summary.clone()),
+                ..Default::default()
+            },
+        )?;
+    }
+    log::info!(
+        "Compacted {} message(s) for folder {} into a {}-char summary",
+        messages.len(),
+        folder_id,
+        summary.len()
+    );
+
+    Ok(CompactionResult {
+        summary,
+        compacted_count: messages.len(),
+    })
+}
+
+/// Replays `messages` (the folder's stored chat) into `session_id`, a new
+/// session replacing one the agent no longer has. The history goes out as
+/// context with the session's next prompt, trimmed to `max_messages` and the
+/// `[context] token_budget`; the folder's compaction summary is already part
+/// of every prompt's context.
+#[tauri::command]
+pub async fn acp_seed_session_from_history(
+    app_handle: tauri::AppHandle,
+    acp_state: tauri::State<'_, Mutex<AcpClientState>>,
+    session_id: String,
+    folder_id: String,
+    messages: Vec<ExportMessage>,
+    max_messages: Option<usize>,
+) -> Result<SeedResult, NeoaiError> {
+    let terminal_id = acp_client::session_terminal(&acp_state, &session_id)
+        .await
+        .ok_or_else(|| {
+            NeoaiError::new(
+                ErrorCode::SessionFailed,
+                format!("Unknown session: {session_id}"),
+            )
+        })?;
+    if folder_state::folder_id_for_terminal(&terminal_id) != Some(folder_id.as_str()) {
+        return Err(NeoaiError::new(
+            ErrorCode::SessionFailed,
+            format!("Session {session_id} does not belong to folder {folder_id}"),
+        ));
+    }
+
+    let token_budget = {
+        use tauri::Manager;
+
+        app_handle
+            .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+            .and_then(|state| state.lock().ok().map(|state| state.context_config()))
+            .unwrap_or_default()
+            .token_budget
+    };
+    let max_messages = max_messages.unwrap_or(DEFAULT_SEED_MESSAGES);
+    let conversation = replayable_messages(messages);
+    let total = conversation.len();
+    let Some((block, seeded_count)) = build_history_seed(&conversation, max_messages, token_budget)
+    else {
+        return Ok(SeedResult {
+            seeded_count: 0,
+            omitted_count: total,
+            estimated_tokens: 0,
+        });
+    };
+    let estimated_tokens = context::estimate_tokens(&block);
+    acp_client::queue_session_context(&acp_state, &session_id, vec![block]).await;
+    log::info!(
+        "Seeded ACP session {} with {} of {} message(s) from folder {}",
+        session_id,
+        seeded_count,
+        total,
+        folder_id
+    );
+
+    Ok(SeedResult {
+        seeded_count,
+        omitted_count: total - seeded_count,
+        estimated_tokens,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_prompt_keeps_the_latest_messages() {
+        let message = |content: String| ExportMessage {
+            role: "user".to_string(),
+            content,
+            timestamp: 0,
+            system_kind: None,
+            proposed_edits: None,
+            edit_status: None,
+        };
+
+        let prompt = build_summary_prompt(&[message("hello".into()), message("world".into())]);
+        assert!(prompt.starts_with(SUMMARY_INSTRUCTIONS));
+        assert!(prompt.contains("hello") && prompt.contains("world"));
+
+        let long = vec![
+            message(format!("old {}", "x".repeat(MAX_TRANSCRIPT_CHARS))),
+            message("latest question".into()),
+        ];
+        let prompt = build_summary_prompt(&long);
+        assert!(prompt.contains("[earlier messages omitted]"));
+        assert!(prompt.contains("latest question"));
+        assert!(!prompt.contains("old x"));
+    }
+
+    #[test]
+    fn history_seed_keeps_recent_messages_within_budget() {
+        let message = |role: &str, content: &str| ExportMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: 0,
+            system_kind: None,
+            proposed_edits: None,
+            edit_status: None,
+        };
+        let messages = vec![
+            message("user", &"first question ".repeat(200)),
+            message("assistant", "first answer"),
+            message("system", "Agent connected"),
+            message("user", "second question"),
+            message("assistant", "second answer"),
+        ];
+
+        let messages = replayable_messages(messages);
+        let (block, count) = build_history_seed(&messages, 10, 10_000).unwrap();
+        assert_eq!(count, 4);
+        assert!(block.starts_with(SEED_PREAMBLE));
+        assert!(!block.contains("Agent connected"));
+
+        let (block, count) = build_history_seed(&messages, 10, 200).unwrap();
+        assert_eq!(count, 3);
+        assert!(!block.contains("first question"));
+        assert!(block.contains("second answer"));
+
+        assert_eq!(build_history_seed(&messages, 2, 10_000).unwrap().1, 2);
+        assert!(build_history_seed(&messages, 10, 10).is_none());
+        assert!(build_history_seed(&messages, 0, 10_000).is_none());
+    }
+}
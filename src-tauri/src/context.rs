@@ -0,0 +1,756 @@
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer, Serialize};
+use tauri::Manager;
+
+use crate::app_config::AppConfigState;
+use crate::diff;
+use crate::folder_state::{self, FolderStateStore};
+use crate::git;
+use crate::nvim_bridge::{self, Diagnostic, TerminalBufferInfo, WindowPlacement};
+
+/// Lines captured above and below the cursor.
+const CURSOR_RADIUS: i64 = 50;
+/// A section is truncated into the remaining budget only if at least this many
+/// tokens are left; otherwise it is omitted.
+const MIN_SECTION_TOKENS: usize = 64;
+const TRUNCATION_MARKER: &str = "... (truncated)";
+
+/// Which sources `build_context` draws from; every source is on by default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextOptions {
+    #[serde(default)]
+    pub diagnostics: Option<bool>,
+    #[serde(default)]
+    pub visible_buffers: Option<bool>,
+    #[serde(default)]
+    pub recent_files: Option<bool>,
+    #[serde(default)]
+    pub git_diff: Option<bool>,
+    /// The folder's compacted conversation summary, if any.
+    #[serde(default)]
+    pub conversation_summary: Option<bool>,
+    /// Overrides `[context] token_budget` for this call.
+    #[serde(default)]
+    pub token_budget: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContextSectionKind {
+    /// The folder's project template preamble.
+    ProjectInstructions,
+    Cursor,
+    /// Latest output of the current buffer when it is a `:terminal`.
+    TerminalOutput,
+    ConversationSummary,
+    Diagnostics,
+    VisibleBuffer,
+    GitDiff,
+    RecentFiles,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextSection {
+    pub kind: ContextSectionKind,
+    pub label: String,
+    pub tokens: usize,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuiltContext {
+    pub text: String,
+    /// Each section's rendered text; `text` is these joined by blank lines.
+    pub blocks: Vec<String>,
+    pub sections: Vec<ContextSection>,
+    /// Labels of sections dropped because the budget ran out.
+    pub omitted: Vec<String>,
+    pub estimated_tokens: usize,
+    pub token_budget: usize,
+}
+
+/// Lines `start..start + lines.len()` (1-based) of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct FileSlice {
+    path: String,
+    #[serde(default)]
+    filetype: String,
+    start: usize,
+    #[serde(deserialize_with = "lenient_vec")]
+    lines: Vec<String>,
+    /// Side of the current window the slice's split is on.
+    #[serde(default)]
+    placement: Option<WindowPlacement>,
+}
+
+impl FileSlice {
+    fn end(&self) -> usize {
+        self.start + self.lines.len()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurrentBuffer {
+    #[serde(flatten)]
+    slice: FileSlice,
+    cursor_line: usize,
+    cursor_col: usize,
+    /// Set for a `:terminal` buffer, whose `slice` is then its last output.
+    #[serde(default)]
+    terminal: Option<TerminalBufferInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawDiagnostic {
+    lnum: i64,
+    col: i64,
+    severity: i64,
+    message: String,
+    #[serde(default)]
+    source: String,
+}
+
+/// Payload of `nvim_context_sources_for_terminal`.
+#[derive(Debug, Deserialize)]
+struct ContextSources {
+    cwd: String,
+    current: CurrentBuffer,
+    #[serde(default, deserialize_with = "lenient_vec")]
+    windows: Vec<FileSlice>,
+    #[serde(default, deserialize_with = "lenient_vec")]
+    recent: Vec<String>,
+    #[serde(default, deserialize_with = "lenient_vec")]
+    diagnostics: Vec<RawDiagnostic>,
+}
+
+/// `vim.json` encodes empty Lua tables as `{}`; read those as empty lists.
+fn lenient_vec<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: serde::de::DeserializeOwned,
+{
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Array(items) => items
+            .into_iter()
+            .map(|item| serde_json::from_value(item).map_err(serde::de::Error::custom))
+            .collect(),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Rough token count for English text and code (about four bytes per token).
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Merges overlapping or adjacent slices of the same file, keeping files in
+/// the order they first appear.
+fn merge_slices(slices: Vec<FileSlice>) -> Vec<FileSlice> {
+    let mut paths: Vec<String> = Vec::new();
+    for slice in &slices {
+        if !paths.contains(&slice.path) {
+            paths.push(slice.path.clone());
+        }
+    }
+
+    let mut merged = Vec::new();
+    for path in paths {
+        let mut group: Vec<FileSlice> = slices
+            .iter()
+            .filter(|slice| slice.path == path && !slice.lines.is_empty())
+            .cloned()
+            .collect();
+        group.sort_by_key(|slice| slice.start);
+
+        let mut current: Option<FileSlice> = None;
+        for slice in group {
+            match current.as_mut() {
+                Some(open) if slice.start <= open.end() => {
+                    let skip = open.end() - slice.start;
+                    open.lines.extend(slice.lines.into_iter().skip(skip));
+                    open.placement = open.placement.or(slice.placement);
+                }
+                _ => merged.extend(current.replace(slice)),
+            }
+        }
+        merged.extend(current);
+    }
+    merged
+}
+
+/// One prospective section of the prompt; `lines` is what gets truncated.
+#[derive(Debug)]
+struct Candidate {
+    kind: ContextSectionKind,
+    label: String,
+    header: String,
+    lines: Vec<String>,
+    /// Fence language when `lines` is a code block.
+    fence: Option<String>,
+}
+
+impl Candidate {
+    fn render(&self, lines: &[String], truncated: bool) -> String {
+        let mut out = self.header.clone();
+        if let Some(fence) = &self.fence {
+            out.push_str(&format!("\n```{fence}"));
+        }
+        for line in lines {
+            out.push('\n');
+            out.push_str(line);
+        }
+        if truncated {
+            out.push('\n');
+            out.push_str(TRUNCATION_MARKER);
+        }
+        if self.fence.is_some() {
+            out.push_str("\n```");
+        }
+        out
+    }
+}
+
+/// Adds candidates in priority order until the budget runs out, truncating
+/// the first one that doesn't fit and omitting the rest that don't.
+fn assemble(candidates: Vec<Candidate>, token_budget: usize) -> BuiltContext {
+    let mut parts: Vec<String> = Vec::new();
+    let mut sections = Vec::new();
+    let mut omitted = Vec::new();
+    let mut used = 0;
+
+    for candidate in candidates {
+        // Sections are joined by a blank line.
+        let separator = if parts.is_empty() { 0 } else { 1 };
+        let remaining = token_budget.saturating_sub(used + separator);
+
+        let full = candidate.render(&candidate.lines, false);
+        let (text, truncated) = if estimate_tokens(&full) <= remaining {
+            (full, false)
+        } else if remaining >= MIN_SECTION_TOKENS {
+            let mut keep = candidate.lines.len();
+            let mut text = candidate.render(&candidate.lines[..keep], true);
+            while keep > 0 && estimate_tokens(&text) > remaining {
+                keep = keep.saturating_sub(keep.div_ceil(8).max(1));
+                text = candidate.render(&candidate.lines[..keep], true);
+            }
+            if keep == 0 {
+                omitted.push(candidate.label);
+                continue;
+            }
+            (text, true)
+        } else {
+            omitted.push(candidate.label);
+            continue;
+        };
+
+        let tokens = estimate_tokens(&text);
+        used += tokens + separator;
+        sections.push(ContextSection {
+            kind: candidate.kind,
+            label: candidate.label,
+            tokens,
+            truncated,
+        });
+        parts.push(text);
+    }
+
+    BuiltContext {
+        text: parts.join("\n\n"),
+        blocks: parts,
+        sections,
+        omitted,
+        estimated_tokens: used,
+        token_budget,
+    }
+}
+
+fn display_path<'a>(path: &'a str, cwd: &str) -> &'a str {
+    Path::new(path)
+        .strip_prefix(cwd)
+        .ok()
+        .and_then(|relative| relative.to_str())
+        .filter(|relative| !relative.is_empty())
+        .unwrap_or(path)
+}
+
+pub fn severity_label(severity: i64) -> &'static str {
+    match severity {
+        1 => "Error",
+        2 => "Warning",
+        3 => "Info",
+        4 => "Hint",
+        _ => "Unknown",
+    }
+}
+
+/// Says where a visible buffer is open, e.g. "Open in the right split".
+fn placement_header(placement: Option<WindowPlacement>) -> String {
+    match placement {
+        Some(WindowPlacement::Left) => "Open in the left split\n",
+        Some(WindowPlacement::Right) => "Open in the right split\n",
+        Some(WindowPlacement::Above) => "Open in the split above\n",
+        Some(WindowPlacement::Below) => "Open in the split below\n",
+        None => "",
+    }
+    .to_string()
+}
+
+fn slice_candidate(
+    kind: ContextSectionKind,
+    slice: FileSlice,
+    cwd: &str,
+    header: String,
+) -> Candidate {
+    let path = display_path(&slice.path, cwd).to_string();
+    let header = format!(
+        "{header}Lines {}-{} of {path}:",
+        slice.start,
+        slice.end().saturating_sub(1)
+    );
+    Candidate {
+        kind,
+        label: format!("{path}:{}", slice.start),
+        header,
+        fence: Some(slice.filetype),
+        lines: slice.lines,
+    }
+}
+
+/// A `:terminal` buffer's output, introduced by what the terminal runs so the
+/// agent doesn't mistake it for file contents.
+fn terminal_candidate(terminal: TerminalBufferInfo, slice: FileSlice) -> Candidate {
+    let command = terminal.command.trim();
+    let title = terminal.title.trim();
+    let mut header = if command.is_empty() {
+        "Output of a terminal".to_string()
+    } else {
+        format!("Output of the terminal running `{command}`")
+    };
+    if !title.is_empty() && title != command {
+        header.push_str(&format!(" (titled \"{title}\")"));
+    }
+    let state = if terminal.running {
+        "still running"
+    } else {
+        "exited"
+    };
+    header.push_str(&format!(", {state}; last {} lines:", slice.lines.len()));
+    let label = if command.is_empty() { title } else { command };
+    Candidate {
+        kind: ContextSectionKind::TerminalOutput,
+        label: format!("terminal {label}").trim_end().to_string(),
+        header,
+        lines: slice.lines,
+        fence: Some(String::new()),
+    }
+}
+
+fn build_candidates(
+    sources: ContextSources,
+    preamble: Option<String>,
+    diagnostics: Vec<Diagnostic>,
+    git_diff: Option<git::GitDiff>,
+    summary: Option<String>,
+    options: &ContextOptions,
+) -> Vec<Candidate> {
+    let cwd = sources.cwd.as_str();
+    let current = sources.current;
+    let current_path = current.slice.path.clone();
+    let cursor_line = current.cursor_line;
+
+    let mut candidates = Vec::new();
+    if let Some(preamble) = preamble.filter(|preamble| !preamble.trim().is_empty()) {
+        candidates.push(Candidate {
+            kind: ContextSectionKind::ProjectInstructions,
+            label: "project instructions".to_string(),
+            header: "Project instructions:".to_string(),
+            lines: preamble.lines().map(str::to_string).collect(),
+            fence: None,
+        });
+    }
+    let mut slices = Vec::new();
+    let in_terminal = current.terminal.is_some();
+    match current.terminal {
+        Some(terminal) if !current.slice.lines.is_empty() => {
+            candidates.push(terminal_candidate(terminal, current.slice));
+        }
+        Some(_) => {}
+        None => slices.push(current.slice),
+    }
+    if options.visible_buffers.unwrap_or(true) {
+        slices.extend(sources.windows);
+    }
+    let mut slices = merge_slices(slices);
+    let cursor_index = slices
+        .iter()
+        .position(|slice| {
+            slice.path == current_path && slice.start <= cursor_line && cursor_line < slice.end()
+        })
+        .unwrap_or(0);
+
+    if !in_terminal && !slices.is_empty() {
+        let slice = slices.remove(cursor_index);
+        let header = format!(
+            "File: {} ({})\nCursor: line {}, col {}\n",
+            display_path(&slice.path, cwd),
+            slice.filetype,
+            cursor_line,
+            current.cursor_col
+        );
+        candidates.push(slice_candidate(
+            ContextSectionKind::Cursor,
+            slice,
+            cwd,
+            header,
+        ));
+    }
+
+    if let Some(summary) = summary.filter(|summary| !summary.trim().is_empty()) {
+        candidates.push(Candidate {
+            kind: ContextSectionKind::ConversationSummary,
+            label: "conversation summary".to_string(),
+            header: "Summary of earlier conversation:".to_string(),
+            lines: summary.lines().map(str::to_string).collect(),
+            fence: None,
+        });
+    }
+
+    if options.diagnostics.unwrap_or(true) && !diagnostics.is_empty() {
+        candidates.push(Candidate {
+            kind: ContextSectionKind::Diagnostics,
+            label: "diagnostics".to_string(),
+            header: "Diagnostics:".to_string(),
+            lines: diagnostics
+                .iter()
+                .map(|d| {
+                    let source = if d.source.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" ({})", d.source)
+                    };
+                    format!(
+                        "  Line {}: [{}] {}{source}",
+                        d.line + 1,
+                        severity_label(d.severity),
+                        d.message
+                    )
+                })
+                .collect(),
+            fence: None,
+        });
+    }
+
+    for slice in slices {
+        let header = placement_header(slice.placement);
+        candidates.push(slice_candidate(
+            ContextSectionKind::VisibleBuffer,
+            slice,
+            cwd,
+            header,
+        ));
+    }
+
+    if let Some(git_diff) = git_diff.filter(|git_diff| !git_diff.files.is_empty()) {
+        let mut lines = Vec::new();
+        for file in &git_diff.files {
+            lines.push(format!(
+                "--- a/{}",
+                file.old_path.as_deref().unwrap_or(&file.path)
+            ));
+            lines.push(format!("+++ b/{}", file.path));
+            if file.binary {
+                lines.push("Binary file changed".to_string());
+            }
+            lines.extend(
+                diff::render_unified(&file.hunks)
+                    .lines()
+                    .map(str::to_string),
+            );
+        }
+        candidates.push(Candidate {
+            kind: ContextSectionKind::GitDiff,
+            label: "git diff".to_string(),
+            header: format!(
+                "Uncommitted changes (+{} -{}):",
+                git_diff.additions, git_diff.deletions
+            ),
+            lines,
+            fence: Some("diff".to_string()),
+        });
+    }
+
+    if options.recent_files.unwrap_or(true) && !sources.recent.is_empty() {
+        candidates.push(Candidate {
+            kind: ContextSectionKind::RecentFiles,
+            label: "recent files".to_string(),
+            header: "Recently opened files:".to_string(),
+            lines: sources
+                .recent
+                .iter()
+                .map(|path| format!("- {}", display_path(path, cwd)))
+                .collect(),
+            fence: None,
+        });
+    }
+
+    candidates
+}
+
+// -- Tauri IPC commands --
+
+/// Assembles prompt context for the terminal's Neovim, trimmed to the token
+/// budget from `[context]` (or `options.token_budget`).
+#[tauri::command]
+pub async fn build_context(
+    app_handle: tauri::AppHandle,
+    config: tauri::State<'_, std::sync::Mutex<AppConfigState>>,
+    terminal_id: String,
+    options: Option<ContextOptions>,
+) -> Result<BuiltContext, String> {
+    let options = options.unwrap_or_default();
+    let config = config.lock().map_err(|e| e.to_string())?.context_config();
+    let max_recent = if options.recent_files.unwrap_or(true) {
+        config.recent_files
+    } else {
+        0
+    };
+
+    let payload = nvim_bridge::nvim_context_sources_for_terminal(
+        &app_handle,
+        &terminal_id,
+        CURSOR_RADIUS,
+        max_recent,
+    )
+    .await?;
+    if !payload["ok"].as_bool().unwrap_or(false) {
+        return Err(payload["error"]
+            .as_str()
+            .unwrap_or("failed to collect context from neovim")
+            .to_string());
+    }
+    let mut sources: ContextSources = serde_json::from_value(payload)
+        .map_err(|e| format!("Failed to parse neovim context: {e}"))?;
+
+    let diagnostics = std::mem::take(&mut sources.diagnostics)
+        .into_iter()
+        .map(|d| Diagnostic {
+            line: d.lnum,
+            col: d.col,
+            severity: d.severity,
+            message: d.message,
+            source: d.source,
+        })
+        .collect();
+
+    let git_diff = if options.git_diff.unwrap_or(true) {
+        let cwd = folder_state::folder_id_for_terminal(&terminal_id)
+            .and_then(|folder_id| folder_state::resolve_folder_path(&app_handle, folder_id).ok())
+            .unwrap_or_else(|| sources.cwd.clone().into());
+        match git::diff(&cwd, false).await {
+            Ok(git_diff) => Some(git_diff),
+            Err(err) => {
+                log::debug!("No git diff for context in {}: {}", cwd.display(), err);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let session = folder_state::folder_id_for_terminal(&terminal_id).and_then(|folder_id| {
+        let store = app_handle.state::<std::sync::Mutex<FolderStateStore>>();
+        let store = store.lock().ok()?;
+        Some(store.session(folder_id))
+    });
+    let (summary, preamble) = match session {
+        Some(session) => (
+            session
+                .conversation_summary
+                .filter(|_| options.conversation_summary.unwrap_or(true)),
+            session.preamble,
+        ),
+        None => (None, None),
+    };
+
+    let token_budget = options
+        .token_budget
+        .filter(|budget| *budget > 0)
+        .unwrap_or(config.token_budget);
+    let candidates = build_candidates(sources, preamble, diagnostics, git_diff, summary, &options);
+    Ok(assemble(candidates, token_budget))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slice(path: &str, start: usize, count: usize) -> FileSlice {
+        FileSlice {
+            path: path.to_string(),
+            filetype: "rust".to_string(),
+            start,
+            lines: (start..start + count)
+                .map(|n| format!("line {n}"))
+                .collect(),
+            placement: None,
+        }
+    }
+
+    #[test]
+    fn merges_overlapping_slices_of_the_same_file() {
+        let merged = merge_slices(vec![
+            slice("/a.rs", 10, 20),
+            slice("/b.rs", 1, 5),
+            slice("/a.rs", 25, 10),
+            slice("/a.rs", 100, 3),
+        ]);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0].path, "/a.rs");
+        assert_eq!((merged[0].start, merged[0].end()), (10, 35));
+        assert_eq!(merged[0].lines[24], "line 34");
+        assert_eq!(merged[1].start, 100);
+        assert_eq!(merged[2].path, "/b.rs");
+    }
+
+    #[test]
+    fn trims_to_the_token_budget_in_priority_order() {
+        let candidate = |label: &str, lines: usize| Candidate {
+            kind: ContextSectionKind::VisibleBuffer,
+            label: label.to_string(),
+            header: format!("{label}:"),
+            lines: (0..lines).map(|n| format!("{n:>40}")).collect(),
+            fence: Some("rust".to_string()),
+        };
+
+        let built = assemble(
+            vec![
+                candidate("first", 10),
+                candidate("second", 100),
+                candidate("third", 100),
+            ],
+            400,
+        );
+        assert_eq!(built.sections.len(), 2);
+        assert!(!built.sections[0].truncated);
+        assert!(built.sections[1].truncated);
+        assert_eq!(built.omitted, vec!["third".to_string()]);
+        assert!(built.estimated_tokens <= 400);
+        assert!(built.text.contains(TRUNCATION_MARKER));
+        assert!(built.text.ends_with("```"));
+    }
+
+    #[test]
+    fn reads_empty_lua_tables_as_empty_lists() {
+        let sources: ContextSources = serde_json::from_value(serde_json::json!({
+            "ok": true,
+            "cwd": "/project",
+            "current": {
+                "path": "/project/src/main.rs",
+                "filetype": "rust",
+                "cursorLine": 1,
+                "cursorCol": 0,
+                "start": 1,
+                "lines": {}
+            },
+            "windows": {},
+            "recent": ["/project/README.md"],
+            "diagnostics": {}
+        }))
+        .unwrap();
+        assert!(sources.current.slice.lines.is_empty());
+        assert!(sources.windows.is_empty());
+
+        let candidates = build_candidates(
+            sources,
+            None,
+            Vec::new(),
+            None,
+            Some("Renamed the parser.".to_string()),
+            &ContextOptions::default(),
+        );
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].kind, ContextSectionKind::ConversationSummary);
+        assert_eq!(candidates[1].lines, vec!["- README.md".to_string()]);
+    }
+
+    #[test]
+    fn puts_project_instructions_first() {
+        let sources: ContextSources = serde_json::from_value(serde_json::json!({
+            "cwd": "/project",
+            "current": {
+                "path": "/project/src/main.rs",
+                "filetype": "rust",
+                "cursorLine": 1,
+                "cursorCol": 0,
+                "start": 1,
+                "lines": ["fn main() {}"]
+            }
+        }))
+        .unwrap();
+
+        let candidates = build_candidates(
+            sources,
+            Some("Use the 2021 edition.\nRun clippy before committing.".to_string()),
+            Vec::new(),
+            None,
+            None,
+            &ContextOptions::default(),
+        );
+        assert_eq!(candidates[0].kind, ContextSectionKind::ProjectInstructions);
+        assert_eq!(candidates[0].lines.len(), 2);
+        assert_eq!(candidates[1].kind, ContextSectionKind::Cursor);
+    }
+
+    #[test]
+    fn describes_terminal_buffers_by_their_command() {
+        let sources: ContextSources = serde_json::from_value(serde_json::json!({
+            "ok": true,
+            "cwd": "/project",
+            "current": {
+                "path": "term:///project//4242:cargo test",
+                "filetype": "",
+                "cursorLine": 1,
+                "cursorCol": 0,
+                "start": 120,
+                "lines": ["test parser::empty ... FAILED", "test result: FAILED"],
+                "terminal": { "title": "cargo test", "command": "cargo test", "running": false }
+            },
+            "windows": [{
+                "path": "/project/src/parser.rs",
+                "filetype": "rust",
+                "start": 1,
+                "lines": ["fn parse() {}"],
+                "placement": "right"
+            }],
+            "recent": {},
+            "diagnostics": {}
+        }))
+        .unwrap();
+
+        let candidates = build_candidates(
+            sources,
+            None,
+            Vec::new(),
+            None,
+            None,
+            &ContextOptions::default(),
+        );
+        assert_eq!(candidates[0].kind, ContextSectionKind::TerminalOutput);
+        assert_eq!(
+            candidates[0].header,
+            "Output of the terminal running `cargo test`, exited; last 2 lines:"
+        );
+        assert_eq!(candidates[0].label, "terminal cargo test");
+        // The window beside the terminal is context, not the cursor location.
+        assert_eq!(candidates[1].kind, ContextSectionKind::VisibleBuffer);
+        assert_eq!(
+            candidates[1].header,
+            "Open in the right split\nLines 1-1 of src/parser.rs:"
+        );
+    }
+}
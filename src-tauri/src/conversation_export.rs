@@ -0,0 +1,325 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Some(Self::Markdown),
+            "html" | "htm" => Some(Self::Html),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportEdit {
+    #[serde(default)]
+    pub file_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportMessage {
+    pub role: String,
+    pub content: String,
+    pub timestamp: i64,
+    #[serde(default)]
+    pub system_kind: Option<String>,
+    #[serde(default)]
+    pub proposed_edits: Option<Vec<ExportEdit>>,
+    #[serde(default)]
+    pub edit_status: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOptions {
+    pub title: Option<String>,
+}
+
+pub fn render_conversation(
+    messages: &[ExportMessage],
+    format: ExportFormat,
+    options: &ExportOptions,
+) -> String {
+    let title = options
+        .title
+        .clone()
+        .unwrap_or_else(|| "NeoAI conversation".to_string());
+    match format {
+        ExportFormat::Markdown => render_markdown(&title, messages),
+        ExportFormat::Html => render_html(&title, messages),
+    }
+}
+
+fn render_markdown(title: &str, messages: &[ExportMessage]) -> String {
+    let mut out = format!("# {title}\n\n");
+    for message in messages {
+        out.push_str(&format!(
+            "## {} — {}\n\n",
+            role_label(message),
+            format_timestamp(message.timestamp)
+        ));
+        let content = message.content.trim_end();
+        if !content.is_empty() {
+            out.push_str(content);
+            out.push_str("\n\n");
+        }
+        if let Some(summary) = edit_summary(message) {
+            out.push_str(&format!("> {summary}\n\n"));
+        }
+    }
+    out
+}
+
+fn render_html(title: &str, messages: &[ExportMessage]) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>{}</title>\n", escape_html(title)));
+    out.push_str(
+        "<style>body{font-family:-apple-system,sans-serif;max-width:860px;margin:2rem auto;padding:0 1rem}\
+         pre{background:#f4f4f4;padding:.75rem;overflow-x:auto}\
+         .meta{color:#666;font-size:.85rem}.summary{border-left:3px solid #999;padding-left:.5rem;color:#444}</style>\n",
+    );
+    out.push_str("</head>\n<body>\n");
+    out.push_str(&format!("<h1>{}</h1>\n", escape_html(title)));
+    for message in messages {
+        out.push_str(&format!(
+            "<section class=\"message {}\">\n<h2>{} <span class=\"meta\">{}</span></h2>\n",
+            escape_html(&message.role),
+            escape_html(role_label(message)),
+            escape_html(&format_timestamp(message.timestamp))
+        ));
+        out.push_str(&content_to_html(&message.content));
+        if let Some(summary) = edit_summary(message) {
+            out.push_str(&format!(
+                "<p class=\"summary\">{}</p>\n",
+                escape_html(&summary)
+            ));
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn role_label(message: &ExportMessage) -> &'static str {
+    match message.role.as_str() {
+        "user" => "User",
+        "assistant" => "Assistant",
+        _ => match message.system_kind.as_deref() {
+            Some("action-summary") => "Action",
+            Some("status-note") => "Status",
+            _ => "System",
+        },
+    }
+}
+
+fn edit_summary(message: &ExportMessage) -> Option<String> {
+    let edits = message.proposed_edits.as_ref()?;
+    if edits.is_empty() {
+        return None;
+    }
+
+    let mut files: Vec<&str> = Vec::new();
+    for edit in edits {
+        let file = edit.file_path.as_deref().unwrap_or("current buffer");
+        if !files.contains(&file) {
+            files.push(file);
+        }
+    }
+    let status = message.edit_status.as_deref().unwrap_or("pending");
+    Some(format!(
+        "Proposed {} edit(s) to {} ({status})",
+        edits.len(),
+        files.join(", ")
+    ))
+}
+
+/// Converts message text into HTML, keeping fenced code blocks verbatim.
+fn content_to_html(content: &str) -> String {
+    let mut out = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut code: Option<(String, Vec<&str>)> = None;
+
+    let flush_paragraph = |out: &mut String, paragraph: &mut Vec<&str>| {
+        if paragraph.is_empty() {
+            return;
+        }
+        let escaped: Vec<String> = paragraph.iter().map(|line| escape_html(line)).collect();
+        out.push_str(&format!("<p>{}</p>\n", escaped.join("<br>\n")));
+        paragraph.clear();
+    };
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if let Some((language, lines)) = code.as_mut() {
+            if trimmed.starts_with("```") {
+                let class = if language.is_empty() {
+                    String::new()
+                } else {
+                    format!(" class=\"language-{}\"", escape_html(language))
+                };
+                out.push_str(&format!(
+                    "<pre><code{class}>{}</code></pre>\n",
+                    escape_html(&lines.join("\n"))
+                ));
+                code = None;
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("```") {
+            flush_paragraph(&mut out, &mut paragraph);
+            code = Some((rest.trim().to_string(), Vec::new()));
+        } else if trimmed.is_empty() {
+            flush_paragraph(&mut out, &mut paragraph);
+        } else {
+            paragraph.push(line);
+        }
+    }
+
+    // Unterminated fences are still rendered as code.
+    if let Some((_, lines)) = code {
+        out.push_str(&format!(
+            "<pre><code>{}</code></pre>\n",
+            escape_html(&lines.join("\n"))
+        ));
+    }
+    flush_paragraph(&mut out, &mut paragraph);
+    out
+}
+
+fn escape_html(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Formats a millisecond Unix timestamp as `YYYY-MM-DD HH:MM UTC`.
+fn format_timestamp(timestamp_ms: i64) -> String {
+    let secs = timestamp_ms.div_euclid(1000);
+    let days = secs.div_euclid(86_400);
+    let secs_of_day = secs.rem_euclid(86_400);
+
+    // Civil-from-days (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        (secs_of_day % 3600) / 60
+    )
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn export_conversation(
+    messages: Vec<ExportMessage>,
+    format: String,
+    path: String,
+    options: Option<ExportOptions>,
+) -> Result<String, String> {
+    let format = ExportFormat::parse(&format)
+        .ok_or_else(|| format!("Unsupported export format '{format}' (use markdown or html)"))?;
+    let options = options.unwrap_or_default();
+    let rendered = render_conversation(&messages, format, &options);
+
+    let path = PathBuf::from(path);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            format!(
+                "Failed to create export directory '{}': {e}",
+                parent.display()
+            )
+        })?;
+    }
+    std::fs::write(&path, rendered)
+        .map_err(|e| format!("Failed to write export file '{}': {e}", path.display()))?;
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> ExportMessage {
+        ExportMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: 1_700_000_000_000,
+            system_kind: None,
+            proposed_edits: None,
+            edit_status: None,
+        }
+    }
+
+    #[test]
+    fn formats_timestamps_in_utc() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_timestamp(1_700_000_000_000), "2023-11-14 22:13 UTC");
+    }
+
+    #[test]
+    fn renders_markdown_with_role_headers_and_edit_summary() {
+        let mut assistant = message("assistant", "Done.\n```rust\nfn main() {}\n```");
+        assistant.proposed_edits = Some(vec![ExportEdit {
+            file_path: Some("/tmp/main.rs".to_string()),
+        }]);
+        assistant.edit_status = Some("applied".to_string());
+
+        let out = render_conversation(
+            &[message("user", "Fix it"), assistant],
+            ExportFormat::Markdown,
+            &ExportOptions::default(),
+        );
+        assert!(out.contains("## User — 2023-11-14 22:13 UTC"));
+        assert!(out.contains("```rust\nfn main() {}\n```"));
+        assert!(out.contains("> Proposed 1 edit(s) to /tmp/main.rs (applied)"));
+    }
+
+    #[test]
+    fn renders_html_code_blocks_escaped() {
+        let out = render_conversation(
+            &[message("assistant", "Use this:\n```html\n<b>hi</b>\n```")],
+            ExportFormat::Html,
+            &ExportOptions::default(),
+        );
+        assert!(out.contains("<p>Use this:</p>"));
+        assert!(
+            out.contains("<pre><code class=\"language-html\">&lt;b&gt;hi&lt;/b&gt;</code></pre>")
+        );
+    }
+}
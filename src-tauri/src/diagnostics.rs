@@ -0,0 +1,740 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use tokio::process::Command;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::acp_client::{self, AcpTraceEntry};
+use crate::app_config::{self, DiagnosticSeverity};
+use crate::app_paths;
+use crate::folder_state;
+use crate::logging;
+
+pub const CRASH_DIR_NAME: &str = "crashes";
+const BUNDLE_DIR_NAME: &str = "diagnostics";
+const VERSION_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Most recent crash reports copied into a bundle.
+const MAX_BUNDLED_CRASH_REPORTS: usize = 5;
+const REDACTED: &str = "<redacted>";
+/// `_`/`-` separated key segments treated as secrets when redacting config.
+const SECRET_KEY_SEGMENTS: &[&str] = &[
+    "key",
+    "apikey",
+    "token",
+    "secret",
+    "password",
+    "passwd",
+    "credential",
+    "credentials",
+    "auth",
+];
+/// Prefixes of well-known API tokens, redacted wherever they appear as a
+/// value, whatever the key or flag around them.
+const TOKEN_PREFIXES: &[&str] = &[
+    "sk-",
+    "ghp_",
+    "gho_",
+    "ghs_",
+    "ghu_",
+    "ghr_",
+    "github_pat_",
+    "glpat-",
+    "xoxb-",
+    "xoxp-",
+    "AKIA",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentVersion {
+    pub name: String,
+    pub command: String,
+    pub version: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorCheck {
+    pub id: String,
+    pub label: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What the user can do about a warning or failure.
+    pub remediation: Option<String>,
+}
+
+impl DoctorCheck {
+    fn new(id: &str, label: &str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            id: id.to_string(),
+            label: label.to_string(),
+            status,
+            detail: detail.into(),
+            remediation: None,
+        }
+    }
+
+    fn remediation(mut self, hint: impl Into<String>) -> Self {
+        self.remediation = Some(hint.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorReport {
+    pub checks: Vec<DoctorCheck>,
+    /// No check failed; warnings are allowed.
+    pub healthy: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleEnvironment {
+    app_version: String,
+    os: String,
+    arch: String,
+    generated_at: String,
+    components: Vec<ComponentVersion>,
+}
+
+pub fn crash_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_paths::app_root_dir(app_handle)?.join(CRASH_DIR_NAME))
+}
+
+/// Logs panics and writes a crash report with a backtrace to `crash_dir`
+/// before handing off to the default hook.
+pub fn install_panic_hook(crash_dir: PathBuf) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let now = SystemTime::now();
+        let thread = std::thread::current();
+        let thread = thread.name().unwrap_or("<unnamed>");
+        let backtrace = std::backtrace::Backtrace::force_capture();
+        let report = format!(
+            "NeoAI {} panicked on thread '{thread}' at {}\n\n{info}\n\nBacktrace:\n{backtrace}\n",
+            env!("CARGO_PKG_VERSION"),
+            logging::format_timestamp(now),
+        );
+
+        log::error!("Panic on thread '{thread}': {info}");
+        if std::fs::create_dir_all(&crash_dir).is_ok() {
+            let path = crash_dir.join(format!("panic-{}.txt", file_stamp(now)));
+            let _ = std::fs::write(path, report);
+        }
+        log::logger().flush();
+        previous(info);
+    }));
+}
+
+fn file_stamp(time: SystemTime) -> String {
+    logging::format_timestamp(time).replace([':', '.'], "-")
+}
+
+/// Runs `program args` and returns the first non-empty line of its output.
+pub async fn probe_version(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = tokio::time::timeout(
+        VERSION_PROBE_TIMEOUT,
+        Command::new(program).args(args).kill_on_drop(true).output(),
+    )
+    .await
+    .map_err(|_| {
+        format!(
+            "{program} did not respond within {}s",
+            VERSION_PROBE_TIMEOUT.as_secs()
+        )
+    })?
+    .map_err(|e| format!("Failed to execute {program}: {e}"))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let first_line = stdout
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    if output.status.success() && !first_line.is_empty() {
+        return Ok(first_line.to_string());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(if stderr.is_empty() {
+        format!("{program} exited with {}", output.status)
+    } else {
+        stderr
+    })
+}
+
+async fn component_version(name: &str, candidates: &[String], args: &[&str]) -> ComponentVersion {
+    let mut last_error = None;
+    for program in candidates {
+        match probe_version(program, args).await {
+            Ok(version) => {
+                return ComponentVersion {
+                    name: name.to_string(),
+                    command: program.clone(),
+                    version: Some(version),
+                    error: None,
+                };
+            }
+            Err(err) => last_error = Some(err),
+        }
+    }
+    ComponentVersion {
+        name: name.to_string(),
+        command: candidates.first().cloned().unwrap_or_default(),
+        version: None,
+        error: last_error,
+    }
+}
+
+pub async fn collect_versions(app_handle: &tauri::AppHandle) -> Vec<ComponentVersion> {
+    vec![
+        component_version("tmux", &["tmux".to_string()], &["-V"]).await,
+        component_version("nvim", &["nvim".to_string()], &["--version"]).await,
+        component_version(
+            "agent",
+            &acp_client::agent_binary_candidates(app_handle),
+            &["--version"],
+        )
+        .await,
+    ]
+}
+
+// -- Doctor checks --
+
+fn nvim_check(version: &ComponentVersion) -> DoctorCheck {
+    match (&version.version, &version.error) {
+        (Some(found), _) => DoctorCheck::new("nvim", "Neovim", CheckStatus::Pass, found.clone()),
+        (None, error) => DoctorCheck::new(
+            "nvim",
+            "Neovim",
+            CheckStatus::Fail,
+            error
+                .clone()
+                .unwrap_or_else(|| "nvim not found".to_string()),
+        )
+        .remediation(
+            "Install Neovim (for example `brew install neovim`) and make sure `nvim` is on PATH.",
+        ),
+    }
+}
+
+fn tmux_check(version: &ComponentVersion) -> DoctorCheck {
+    match (&version.version, &version.error) {
+        (Some(found), _) => DoctorCheck::new("tmux", "tmux", CheckStatus::Pass, found.clone()),
+        (None, error) => DoctorCheck::new(
+            "tmux",
+            "tmux",
+            CheckStatus::Warn,
+            error.clone().unwrap_or_else(|| "tmux not found".to_string()),
+        )
+        .remediation("Install tmux (for example `brew install tmux`) to run agent commands in tmux panes and keep Neovim sessions across restarts."),
+    }
+}
+
+fn agent_check(version: &ComponentVersion, uses_default_agent: bool) -> DoctorCheck {
+    match (&version.version, &version.error) {
+        (Some(found), _) => DoctorCheck::new(
+            "agent",
+            "ACP agent",
+            CheckStatus::Pass,
+            format!("{found} ({})", version.command),
+        ),
+        (None, _) if uses_default_agent => DoctorCheck::new(
+            "agent",
+            "ACP agent",
+            CheckStatus::Warn,
+            "codex-acp is not installed yet",
+        )
+        .remediation("NeoAI downloads codex-acp the first time the agent starts; this needs network access to GitHub."),
+        (None, error) => DoctorCheck::new(
+            "agent",
+            "ACP agent",
+            CheckStatus::Fail,
+            format!(
+                "{}: {}",
+                version.command,
+                error.as_deref().unwrap_or("not runnable")
+            ),
+        )
+        .remediation("Fix `[agent] path` in config.toml or remove it to use the managed codex-acp."),
+    }
+}
+
+fn ghostty_check() -> DoctorCheck {
+    match crate::ghostty_embed::init_status() {
+        Some(Ok(())) => DoctorCheck::new(
+            "libghostty",
+            "libghostty",
+            CheckStatus::Pass,
+            "Initialized",
+        ),
+        None => DoctorCheck::new(
+            "libghostty",
+            "libghostty",
+            CheckStatus::Pass,
+            "Linked; initializes when the first terminal opens",
+        ),
+        Some(Err(err)) => DoctorCheck::new("libghostty", "libghostty", CheckStatus::Fail, err)
+            .remediation("Run `just setup-libghostty` and rebuild, or check the Ghostty runtime libs in `.tools/libghostty`."),
+    }
+}
+
+fn socket_dir_check(app_handle: &tauri::AppHandle) -> DoctorCheck {
+    use tauri::Manager;
+
+    if cfg!(windows) {
+        return DoctorCheck::new(
+            "socket_dir",
+            "Socket directory",
+            CheckStatus::Pass,
+            "Neovim uses named pipes on Windows",
+        );
+    }
+    let dir = {
+        let sockets = app_handle.state::<std::sync::Mutex<crate::socket_manager::SocketManager>>();
+        let sockets = match sockets.lock() {
+            Ok(sockets) => sockets,
+            Err(_) => {
+                return DoctorCheck::new(
+                    "socket_dir",
+                    "Socket directory",
+                    CheckStatus::Fail,
+                    "Socket manager is unavailable",
+                )
+            }
+        };
+        if let Err(err) = sockets.ensure_socket_dir() {
+            return DoctorCheck::new("socket_dir", "Socket directory", CheckStatus::Fail, err)
+                .remediation("Set `socket_dir` in config.toml to a writable absolute path.");
+        }
+        sockets.socket_dir().to_path_buf()
+    };
+
+    let probe = dir.join(format!(".neoai-doctor-{}", std::process::id()));
+    match std::fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            DoctorCheck::new(
+                "socket_dir",
+                "Socket directory",
+                CheckStatus::Pass,
+                dir.display().to_string(),
+            )
+        }
+        Err(err) => DoctorCheck::new(
+            "socket_dir",
+            "Socket directory",
+            CheckStatus::Fail,
+            format!("'{}' is not writable: {err}", dir.display()),
+        )
+        .remediation("Set `socket_dir` in config.toml to a writable absolute path."),
+    }
+}
+
+/// NeoAI keeps no database; its persistent store is `folders.json`.
+fn state_store_check(root: &Path) -> DoctorCheck {
+    let path = root.join(folder_state::FOLDER_STATE_FILE);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return DoctorCheck::new(
+                "state_store",
+                "Folder state",
+                CheckStatus::Pass,
+                format!("'{}' will be created on first use", path.display()),
+            )
+        }
+        Err(err) => {
+            return DoctorCheck::new(
+                "state_store",
+                "Folder state",
+                CheckStatus::Fail,
+                format!("Cannot read '{}': {err}", path.display()),
+            )
+            .remediation("Check the permissions of the NeoAI app folder.")
+        }
+    };
+    match folder_state::count_sessions(&contents) {
+        Ok(count) => DoctorCheck::new(
+            "state_store",
+            "Folder state",
+            CheckStatus::Pass,
+            format!("{count} folder session(s) in '{}'", path.display()),
+        ),
+        Err(err) => DoctorCheck::new(
+            "state_store",
+            "Folder state",
+            CheckStatus::Fail,
+            format!("'{}' is corrupt: {err}", path.display()),
+        )
+        .remediation("Move the file aside; NeoAI starts with empty folder state and recreates it."),
+    }
+}
+
+fn config_check(root: &Path) -> DoctorCheck {
+    let path = root.join("config.toml");
+    let Ok(contents) = std::fs::read_to_string(&path) else {
+        return DoctorCheck::new(
+            "config",
+            "config.toml",
+            CheckStatus::Warn,
+            format!(
+                "'{}' could not be read; defaults are in use",
+                path.display()
+            ),
+        )
+        .remediation("Restart NeoAI to regenerate the default config.toml.");
+    };
+
+    let diagnostics = app_config::validate_config_contents(&contents);
+    let status = if diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == DiagnosticSeverity::Error)
+    {
+        CheckStatus::Fail
+    } else if diagnostics.is_empty() {
+        CheckStatus::Pass
+    } else {
+        CheckStatus::Warn
+    };
+    if status == CheckStatus::Pass {
+        return DoctorCheck::new("config", "config.toml", status, "Valid");
+    }
+    let detail = diagnostics
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ");
+    DoctorCheck::new("config", "config.toml", status, detail).remediation(format!(
+        "Edit '{}' to fix the reported lines.",
+        path.display()
+    ))
+}
+
+fn is_secret_key(key: &str) -> bool {
+    key.trim_start_matches('-')
+        .split(['_', '-', '.'])
+        .any(|segment| SECRET_KEY_SEGMENTS.contains(&segment.to_ascii_lowercase().as_str()))
+}
+
+/// Whether `value` looks like an API token by its prefix (`sk-...`,
+/// `ghp_...`).
+fn looks_like_token(value: &str) -> bool {
+    let value = value.trim();
+    TOKEN_PREFIXES.iter().any(|prefix| {
+        value
+            .strip_prefix(prefix)
+            .is_some_and(|rest| !rest.is_empty() && !rest.contains(char::is_whitespace))
+    })
+}
+
+/// Whether a command-line argument is a secret: `--api-key=...`, or a known
+/// token on its own.
+fn is_secret_arg(arg: &str) -> bool {
+    looks_like_token(arg)
+        || arg
+            .split_once('=')
+            .is_some_and(|(flag, _)| is_secret_key(flag))
+}
+
+/// A flag such as `--api-key` or `-token` whose value is the next argument.
+fn is_secret_flag(arg: &str) -> bool {
+    arg.starts_with('-') && !arg.contains('=') && is_secret_key(arg)
+}
+
+/// Replaces secret-looking values in `config.toml` contents. Every value in an
+/// `env` table is redacted, since that is where agent API keys usually live.
+/// In argument lists the value after a secret flag (`["--api-key", "..."]`)
+/// and known tokens are redacted too.
+pub fn redact_config(contents: &str) -> String {
+    match contents.parse::<toml::Table>() {
+        Ok(mut table) => {
+            redact_table(&mut table, false);
+            toml::to_string_pretty(&table).unwrap_or_else(|_| REDACTED.to_string())
+        }
+        // Unparseable files are still worth seeing; redact line by line.
+        Err(_) => contents
+            .lines()
+            .map(|line| match line.split_once('=') {
+                Some((key, value))
+                    if is_secret_key(key.trim())
+                        || value
+                            .split(['"', '\'', ',', '[', ']'])
+                            .any(looks_like_token) =>
+                {
+                    format!("{key}= \"{REDACTED}\"")
+                }
+                _ => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+fn redact_table(table: &mut toml::Table, redact_all: bool) {
+    for (key, value) in table.iter_mut() {
+        let secret = redact_all || is_secret_key(key);
+        match value {
+            toml::Value::Table(inner) => redact_table(inner, secret || key == "env"),
+            toml::Value::Array(items) if !secret => {
+                let mut after_secret_flag = false;
+                for item in items.iter_mut() {
+                    let arg = item.as_str().unwrap_or_default();
+                    let looks_secret = after_secret_flag || is_secret_arg(arg);
+                    after_secret_flag = is_secret_flag(arg);
+                    if looks_secret {
+                        *item = toml::Value::from(REDACTED);
+                    }
+                }
+            }
+            _ if secret => *value = toml::Value::from(REDACTED),
+            toml::Value::String(text) if looks_like_token(text) => {
+                *value = toml::Value::from(REDACTED)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn add_file(
+    zip: &mut ZipWriter<File>,
+    name: &str,
+    bytes: &[u8],
+    options: FileOptions,
+) -> Result<(), String> {
+    zip.start_file(name, options)
+        .and_then(|_| zip.write_all(bytes).map_err(Into::into))
+        .map_err(|e| format!("Failed to add '{name}' to diagnostic bundle: {e}"))
+}
+
+/// Files in `dir`, sorted newest first.
+fn files_newest_first(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .map(|entry| {
+            let modified = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            (modified, entry.path())
+        })
+        .collect();
+    files.sort_by(|a, b| b.0.cmp(&a.0));
+    files.into_iter().map(|(_, path)| path).collect()
+}
+
+fn write_bundle(
+    root: &Path,
+    environment: &BundleEnvironment,
+    trace: &[AcpTraceEntry],
+) -> Result<PathBuf, String> {
+    let dir = root.join(BUNDLE_DIR_NAME);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create '{}': {e}", dir.display()))?;
+    let path = dir.join(format!(
+        "neoai-diagnostics-{}.zip",
+        file_stamp(SystemTime::now())
+    ));
+    let file =
+        File::create(&path).map_err(|e| format!("Failed to create '{}': {e}", path.display()))?;
+
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let environment = serde_json::to_vec_pretty(environment).map_err(|e| e.to_string())?;
+    add_file(&mut zip, "environment.json", &environment, options)?;
+
+    if let Ok(contents) = std::fs::read_to_string(root.join("config.toml")) {
+        add_file(
+            &mut zip,
+            "config.toml",
+            redact_config(&contents).as_bytes(),
+            options,
+        )?;
+    }
+
+    let trace = serde_json::to_vec_pretty(trace).map_err(|e| e.to_string())?;
+    add_file(&mut zip, "acp-trace.json", &trace, options)?;
+
+    let logs = files_newest_first(&root.join(logging::LOG_DIR_NAME));
+    let crashes = files_newest_first(&root.join(CRASH_DIR_NAME));
+    let bundled = logs.iter().map(|path| (logging::LOG_DIR_NAME, path)).chain(
+        crashes
+            .iter()
+            .take(MAX_BUNDLED_CRASH_REPORTS)
+            .map(|path| (CRASH_DIR_NAME, path)),
+    );
+    for (folder, path) in bundled {
+        let (Some(name), Ok(bytes)) = (path.file_name(), std::fs::read(path)) else {
+            continue;
+        };
+        let name = format!("{folder}/{}", name.to_string_lossy());
+        add_file(&mut zip, &name, &bytes, options)?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to finish diagnostic bundle: {e}"))?;
+    Ok(path)
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn doctor(app_handle: tauri::AppHandle) -> Result<DoctorReport, String> {
+    let root = app_paths::app_root_dir(&app_handle)?;
+    let uses_default_agent = acp_client::agent_binary_candidates(&app_handle).len() > 1;
+    let versions = collect_versions(&app_handle).await;
+
+    let mut checks = Vec::new();
+    for version in &versions {
+        checks.push(match version.name.as_str() {
+            "nvim" => nvim_check(version),
+            "tmux" => tmux_check(version),
+            _ => agent_check(version, uses_default_agent),
+        });
+    }
+    checks.push(ghostty_check());
+    checks.push(socket_dir_check(&app_handle));
+    checks.push(state_store_check(&root));
+    checks.push(config_check(&root));
+
+    let healthy = checks.iter().all(|check| check.status != CheckStatus::Fail);
+    Ok(DoctorReport { checks, healthy })
+}
+
+/// Zips logs, crash reports, redacted config, component versions and the
+/// recent ACP trace into `<app folder>/diagnostics`. Returns the zip path.
+#[tauri::command]
+pub async fn create_diagnostic_bundle(app_handle: tauri::AppHandle) -> Result<String, String> {
+    let root = app_paths::app_root_dir(&app_handle)?;
+    let environment = BundleEnvironment {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        generated_at: logging::format_timestamp(SystemTime::now()),
+        components: collect_versions(&app_handle).await,
+    };
+    let trace = acp_client::recent_trace();
+    log::logger().flush();
+
+    let path =
+        tauri::async_runtime::spawn_blocking(move || write_bundle(&root, &environment, &trace))
+            .await
+            .map_err(|e| format!("Diagnostic bundle task failed: {e}"))??;
+    log::info!("Wrote diagnostic bundle to '{}'", path.display());
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_secrets_and_agent_env_values() {
+        let redacted = redact_config(
+            r#"
+tmux_command_mode = "split"
+github_token = "ghp_abc"
+
+[agent]
+path = "codex-acp"
+args = ["--verbose", "--api-key=sk-123"]
+env = { OPENAI_API_KEY = "sk-456", RUST_LOG = "info" }
+"#,
+        );
+        let table: toml::Table = redacted.parse().unwrap();
+        assert_eq!(table["tmux_command_mode"].as_str(), Some("split"));
+        assert_eq!(table["github_token"].as_str(), Some(REDACTED));
+        let agent = table["agent"].as_table().unwrap();
+        assert_eq!(agent["path"].as_str(), Some("codex-acp"));
+        assert_eq!(agent["args"][0].as_str(), Some("--verbose"));
+        assert_eq!(agent["args"][1].as_str(), Some(REDACTED));
+        assert_eq!(agent["env"]["OPENAI_API_KEY"].as_str(), Some(REDACTED));
+        assert_eq!(agent["env"]["RUST_LOG"].as_str(), Some(REDACTED));
+        assert!(!redacted.contains("sk-"));
+    }
+
+    #[test]
+    fn redacts_the_argument_after_a_secret_flag() {
+        let redacted = redact_config(
+            r#"
+[agent]
+args = ["--api-key", "hunter2", "--model", "o3", "--token"]
+"#,
+        );
+        let table: toml::Table = redacted.parse().unwrap();
+        let args = table["agent"]["args"].as_array().unwrap();
+        let args: Vec<&str> = args.iter().filter_map(|arg| arg.as_str()).collect();
+        assert_eq!(args, ["--api-key", REDACTED, "--model", "o3", "--token"]);
+    }
+
+    #[test]
+    fn redacts_bare_tokens_by_prefix() {
+        let redacted = redact_config(
+            r#"
+note = "ghp_0123456789abcdef"
+name = "sk-learn is not a token"
+
+[agent]
+args = ["--verbose", "sk-proj-abc123", "skip"]
+"#,
+        );
+        let table: toml::Table = redacted.parse().unwrap();
+        assert_eq!(table["note"].as_str(), Some(REDACTED));
+        assert_eq!(table["name"].as_str(), Some("sk-learn is not a token"));
+        let args = table["agent"]["args"].as_array().unwrap();
+        assert_eq!(args[1].as_str(), Some(REDACTED));
+        assert_eq!(args[2].as_str(), Some("skip"));
+
+        let unparseable = redact_config("args = [\"github_pat_xyz\"]\nbroken = [\n");
+        assert!(!unparseable.contains("github_pat_"));
+    }
+
+    #[test]
+    fn doctor_flags_corrupt_state_and_invalid_config() {
+        let root = std::env::temp_dir().join(format!("neoai-doctor-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+
+        assert_eq!(state_store_check(&root).status, CheckStatus::Pass);
+        std::fs::write(root.join(folder_state::FOLDER_STATE_FILE), "{ nope").unwrap();
+        let check = state_store_check(&root);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.remediation.is_some());
+
+        std::fs::write(
+            root.join("config.toml"),
+            "tmux_command_mode = \"sideways\"\n",
+        )
+        .unwrap();
+        assert_eq!(config_check(&root).status, CheckStatus::Warn);
+        std::fs::write(root.join("config.toml"), "broken = [\n").unwrap();
+        assert_eq!(config_check(&root).status, CheckStatus::Fail);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn redacts_unparseable_config_line_by_line() {
+        let redacted = redact_config("api_key = \"sk-1\"\nbroken = [\n");
+        assert!(redacted.starts_with("api_key = \"<redacted>\""));
+        assert!(redacted.contains("broken = ["));
+    }
+}
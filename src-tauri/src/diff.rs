@@ -0,0 +1,362 @@
+use std::fmt::Write as _;
+
+use serde::{Deserialize, Serialize};
+
+use crate::git::{GitDiffHunk, GitDiffLine, GitDiffLineKind};
+
+/// Unchanged lines kept around each change, as in `git diff`.
+pub const CONTEXT_LINES: usize = 3;
+/// Beyond this many edits the changed region is reported as one replacement
+/// instead of searching for the minimal diff.
+const MAX_EDIT_DISTANCE: usize = 2_000;
+
+/// Line diff of two texts, in the same hunk shape `git_diff` returns.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextDiff {
+    pub hunks: Vec<GitDiffHunk>,
+    pub additions: u32,
+    pub deletions: u32,
+    /// The hunks rendered as unified diff text, without file headers.
+    pub unified: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+pub fn compute_diff(original: &str, modified: &str) -> TextDiff {
+    let old: Vec<&str> = original.lines().collect();
+    let new: Vec<&str> = modified.lines().collect();
+    let ops = diff_lines(&old, &new);
+
+    let mut diff = TextDiff {
+        hunks: build_hunks(&old, &new, &ops, CONTEXT_LINES),
+        ..Default::default()
+    };
+    for op in &ops {
+        match op {
+            Op::Insert => diff.additions += 1,
+            Op::Delete => diff.deletions += 1,
+            Op::Equal => {}
+        }
+    }
+    diff.unified = render_unified(&diff.hunks);
+    diff
+}
+
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let prefix = common_prefix(old, new);
+    let suffix = common_suffix(&old[prefix..], &new[prefix..]);
+
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+    let middle = myers(old_middle, new_middle).unwrap_or_else(|| {
+        std::iter::repeat_n(Op::Delete, old_middle.len())
+            .chain(std::iter::repeat_n(Op::Insert, new_middle.len()))
+            .collect()
+    });
+
+    let mut ops = vec![Op::Equal; prefix];
+    ops.extend(middle);
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+    ops
+}
+
+/// Myers' shortest edit script in linear space: each step finds the middle
+/// snake of the edit graph and recurses on both sides of it. `None` when the
+/// edit distance passes [`MAX_EDIT_DISTANCE`].
+fn myers(old: &[&str], new: &[&str]) -> Option<Vec<Op>> {
+    let offset = (old.len() + new.len()).div_ceil(2) + 1;
+    let mut forward = vec![0usize; 2 * offset + 2];
+    let mut backward = vec![0usize; 2 * offset + 2];
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    // Only the outermost split needs the bound: its halves can't need more
+    // edits than the whole.
+    conquer(
+        old,
+        new,
+        &mut Diagonals {
+            offset: offset as isize,
+            forward: &mut forward,
+            backward: &mut backward,
+        },
+        &mut ops,
+        MAX_EDIT_DISTANCE / 2 + 1,
+    )?;
+    deletions_first(&mut ops);
+    Some(ops)
+}
+
+/// Puts the deletions of each run of changed lines ahead of its insertions,
+/// as `git diff` does; the halves of a split can otherwise leave a changed
+/// line rendered as `+new` followed by `-old`.
+fn deletions_first(ops: &mut [Op]) {
+    for run in ops.split_mut(|op| *op == Op::Equal) {
+        let deletes = run.iter().filter(|op| **op == Op::Delete).count();
+        let (head, tail) = run.split_at_mut(deletes);
+        head.fill(Op::Delete);
+        tail.fill(Op::Insert);
+    }
+}
+
+/// Furthest x reached on each diagonal `k = x - y`, searching from the start
+/// and from the end, shared by every step of [`conquer`].
+struct Diagonals<'a> {
+    offset: isize,
+    forward: &'a mut [usize],
+    backward: &'a mut [usize],
+}
+
+fn conquer(
+    old: &[&str],
+    new: &[&str],
+    diagonals: &mut Diagonals,
+    ops: &mut Vec<Op>,
+    max_d: usize,
+) -> Option<()> {
+    let prefix = common_prefix(old, new);
+    ops.extend(std::iter::repeat_n(Op::Equal, prefix));
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = common_suffix(old, new);
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    if old.is_empty() {
+        ops.extend(std::iter::repeat_n(Op::Insert, new.len()));
+    } else if new.is_empty() {
+        ops.extend(std::iter::repeat_n(Op::Delete, old.len()));
+    } else {
+        let (x, y) = middle_snake(old, new, diagonals, max_d)?;
+        conquer(&old[..x], &new[..y], diagonals, ops, usize::MAX)?;
+        conquer(&old[x..], &new[y..], diagonals, ops, usize::MAX)?;
+    }
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+    Some(())
+}
+
+/// A point on a shortest edit path of two non-empty slices, found by
+/// searching from both ends until the paths meet; `None` if they haven't
+/// after `max_d` steps each.
+fn middle_snake(
+    old: &[&str],
+    new: &[&str],
+    diagonals: &mut Diagonals,
+    max_d: usize,
+) -> Option<(usize, usize)> {
+    let (n, m) = (old.len(), new.len());
+    let delta = n as isize - m as isize;
+    let odd = delta & 1 == 1;
+    let offset = diagonals.offset;
+    let at = |k: isize| (k + offset) as usize;
+    let Diagonals {
+        forward, backward, ..
+    } = diagonals;
+    forward[at(1)] = 0;
+    backward[at(1)] = 0;
+
+    let d_max = (n + m).div_ceil(2) + 1;
+    for d in 0..d_max.min(max_d) as isize {
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let y = (x as isize - k) as usize;
+            let (x0, y0) = (x, y);
+            if x < n && y < m {
+                x += common_prefix(&old[x..], &new[y..]);
+            }
+            forward[at(k)] = x;
+            if odd && (k - delta).abs() < d && x + backward[at(delta - k)] >= n {
+                return Some((x0, y0));
+            }
+        }
+
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let mut y = (x as isize - k) as usize;
+            if x < n && y < m {
+                let advance = common_suffix(&old[..n - x], &new[..m - y]);
+                x += advance;
+                y += advance;
+            }
+            backward[at(k)] = x;
+            if !odd && (k - delta).abs() <= d && x + forward[at(delta - k)] >= n {
+                return Some((n - x, m - y));
+            }
+        }
+    }
+    None
+}
+
+fn common_prefix(old: &[&str], new: &[&str]) -> usize {
+    old.iter().zip(new).take_while(|(a, b)| a == b).count()
+}
+
+fn common_suffix(old: &[&str], new: &[&str]) -> usize {
+    old.iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+fn build_hunks(old: &[&str], new: &[&str], ops: &[Op], context: usize) -> Vec<GitDiffHunk> {
+    // Line positions (old, new) before each op.
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut o, mut n) = (0usize, 0usize);
+    for op in ops {
+        positions.push((o, n));
+        match op {
+            Op::Equal => {
+                o += 1;
+                n += 1;
+            }
+            Op::Delete => o += 1,
+            Op::Insert => n += 1,
+        }
+    }
+    positions.push((o, n));
+
+    let changes: Vec<usize> = (0..ops.len()).filter(|&i| ops[i] != Op::Equal).collect();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &i in &changes {
+        let start = i.saturating_sub(context);
+        let end = (i + 1 + context).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let (old_from, new_from) = positions[start];
+            let (old_to, new_to) = positions[end];
+            let old_lines = (old_to - old_from) as u32;
+            let new_lines = (new_to - new_from) as u32;
+            let lines = ops[start..end]
+                .iter()
+                .zip(&positions[start..end])
+                .map(|(op, &(o, n))| match op {
+                    Op::Equal => GitDiffLine {
+                        kind: GitDiffLineKind::Context,
+                        content: old[o].to_string(),
+                    },
+                    Op::Delete => GitDiffLine {
+                        kind: GitDiffLineKind::Removed,
+                        content: old[o].to_string(),
+                    },
+                    Op::Insert => GitDiffLine {
+                        kind: GitDiffLineKind::Added,
+                        content: new[n].to_string(),
+                    },
+                })
+                .collect();
+            GitDiffHunk {
+                // An empty side starts at the line before it, as in git.
+                old_start: old_from as u32 + u32::from(old_lines > 0),
+                old_lines,
+                new_start: new_from as u32 + u32::from(new_lines > 0),
+                new_lines,
+                header: String::new(),
+                lines,
+            }
+        })
+        .collect()
+}
+
+/// Renders hunks as unified diff text, without file headers.
+pub fn render_unified(hunks: &[GitDiffHunk]) -> String {
+    let range = |start: u32, lines: u32| {
+        if lines == 1 {
+            start.to_string()
+        } else {
+            format!("{start},{lines}")
+        }
+    };
+
+    let mut out = String::new();
+    for hunk in hunks {
+        let _ = writeln!(
+            out,
+            "@@ -{} +{} @@",
+            range(hunk.old_start, hunk.old_lines),
+            range(hunk.new_start, hunk.new_lines)
+        );
+        for line in &hunk.lines {
+            let marker = match line.kind {
+                GitDiffLineKind::Context => ' ',
+                GitDiffLineKind::Added => '+',
+                GitDiffLineKind::Removed => '-',
+            };
+            let _ = writeln!(out, "{marker}{}", line.content);
+        }
+    }
+    out
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn diff_text(original: String, modified: String) -> Result<TextDiff, String> {
+    tauri::async_runtime::spawn_blocking(move || compute_diff(&original, &modified))
+        .await
+        .map_err(|e| format!("Diff task failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_unified_hunks_with_context() {
+        let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+        let modified = "a\nb\nc\nD\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        let diff = compute_diff(original, modified);
+
+        assert_eq!(diff.additions, 2);
+        assert_eq!(diff.deletions, 1);
+        assert_eq!(diff.hunks.len(), 2);
+        assert_eq!(
+            diff.unified,
+            "@@ -1,7 +1,7 @@\n a\n b\n c\n-d\n+D\n e\n f\n g\n@@ -10,3 +10,4 @@\n j\n k\n l\n+m\n"
+        );
+    }
+
+    #[test]
+    fn finds_minimal_edits_and_handles_empty_sides() {
+        let diff = compute_diff("x\na\nb\nc\ny\n", "x\nb\nc\nq\ny\n");
+        assert_eq!((diff.additions, diff.deletions), (1, 1));
+
+        let created = compute_diff("", "one\ntwo\n");
+        assert_eq!(created.hunks[0].old_start, 0);
+        assert_eq!(created.hunks[0].old_lines, 0);
+        assert!(created.unified.starts_with("@@ -0,0 +1,2 @@"));
+
+        assert!(compute_diff("same\n", "same\n").hunks.is_empty());
+    }
+
+    #[test]
+    fn keeps_scattered_edits_minimal_in_large_files() {
+        let original: String = (0..20_000).map(|i| format!("line {i}\n")).collect();
+        let modified: String = (0..20_000)
+            .map(|i| match i % 50 {
+                0 => format!("changed {i}\n"),
+                _ => format!("line {i}\n"),
+            })
+            .collect();
+        let diff = compute_diff(&original, &modified);
+        assert_eq!((diff.additions, diff.deletions), (400, 400));
+        assert_eq!(diff.hunks.len(), 400);
+    }
+}
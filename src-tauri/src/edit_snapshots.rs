@@ -0,0 +1,368 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+use crate::app_paths;
+use crate::nvim_bridge::{self, NvimBridgeState};
+
+const SNAPSHOTS_DIR: &str = "snapshots";
+const MANIFEST_FILE: &str = "manifest.json";
+/// Oldest snapshots beyond this count are pruned when a session takes a new one.
+const MAX_SNAPSHOTS_PER_SESSION: usize = 200;
+
+static SNAPSHOT_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A file as it was on disk right before an agent-driven write.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotFile {
+    pub path: String,
+    /// `false` when the write created the file; rolling back deletes it.
+    pub existed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditSnapshot {
+    pub id: String,
+    pub session_id: String,
+    pub created_at: u64,
+    /// What triggered the write, e.g. `write_text_file` or `apply_edits`.
+    pub source: String,
+    pub files: Vec<SnapshotFile>,
+    #[serde(default)]
+    pub rolled_back: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RollbackResult {
+    /// Snapshots restored, newest first.
+    pub snapshot_ids: Vec<String>,
+    pub restored_files: Vec<String>,
+    pub deleted_files: Vec<String>,
+}
+
+fn snapshots_root(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(snapshots_dir(&app_paths::app_root_dir(app_handle)?))
+}
+
+/// Snapshot storage under the app root directory.
+pub fn snapshots_dir(app_root: &Path) -> PathBuf {
+    app_root.join(SNAPSHOTS_DIR)
+}
+
+/// Session ids come from agents; keep them to a safe directory name. A short
+/// hash of the raw id keeps ids that sanitize alike (`sess/1`, `sess_1`) in
+/// separate directories.
+fn session_dir_name(session_id: &str) -> String {
+    let name: String = session_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = if name.is_empty() { "default" } else { &name };
+    let hash = hex::encode(Sha256::digest(session_id.as_bytes()));
+    format!("{name}-{}", &hash[..8])
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Copies `paths` into a new snapshot under `root/{session}/{id}/`.
+pub fn take_snapshot(
+    root: &Path,
+    session_id: &str,
+    source: &str,
+    paths: &[PathBuf],
+) -> Result<EditSnapshot, String> {
+    let created_at = now_millis();
+    let id = format!(
+        "{created_at:013}-{:06}",
+        SNAPSHOT_COUNTER.fetch_add(1, Ordering::Relaxed) % 1_000_000
+    );
+    let session_dir = root.join(session_dir_name(session_id));
+    let dir = session_dir.join(&id);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create snapshot '{}': {e}", dir.display()))?;
+
+    let mut files = Vec::with_capacity(paths.len());
+    for (index, path) in paths.iter().enumerate() {
+        let existed = match std::fs::read(path) {
+            Ok(contents) => {
+                let blob = dir.join(index.to_string());
+                std::fs::write(&blob, contents)
+                    .map_err(|e| format!("Failed to write snapshot '{}': {e}", blob.display()))?;
+                true
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => false,
+            Err(err) => return Err(format!("Failed to read '{}': {err}", path.display())),
+        };
+        files.push(SnapshotFile {
+            path: path.to_string_lossy().into_owned(),
+            existed,
+        });
+    }
+
+    let snapshot = EditSnapshot {
+        id,
+        session_id: session_id.to_string(),
+        created_at,
+        source: source.to_string(),
+        files,
+        rolled_back: false,
+    };
+    write_manifest(&dir, &snapshot)?;
+    prune_session(&session_dir);
+    Ok(snapshot)
+}
+
+fn write_manifest(dir: &Path, snapshot: &EditSnapshot) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(snapshot).map_err(|e| e.to_string())?;
+    let path = dir.join(MANIFEST_FILE);
+    std::fs::write(&path, contents).map_err(|e| {
+        format!(
+            "Failed to write snapshot manifest '{}': {e}",
+            path.display()
+        )
+    })
+}
+
+fn read_manifest(dir: &Path) -> Option<EditSnapshot> {
+    let contents = std::fs::read_to_string(dir.join(MANIFEST_FILE)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Snapshot directories of a session, oldest first (ids sort by time).
+fn snapshot_dirs(session_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(session_dir) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.join(MANIFEST_FILE).is_file())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+fn prune_session(session_dir: &Path) {
+    let dirs = snapshot_dirs(session_dir);
+    let excess = dirs.len().saturating_sub(MAX_SNAPSHOTS_PER_SESSION);
+    for dir in &dirs[..excess] {
+        if let Err(err) = std::fs::remove_dir_all(dir) {
+            log::warn!("Failed to prune snapshot '{}': {}", dir.display(), err);
+        }
+    }
+}
+
+pub fn list_snapshots(root: &Path, session_id: &str) -> Vec<EditSnapshot> {
+    let mut snapshots: Vec<EditSnapshot> = snapshot_dirs(&root.join(session_dir_name(session_id)))
+        .iter()
+        .filter_map(|dir| read_manifest(dir))
+        .collect();
+    snapshots.reverse();
+    snapshots
+}
+
+/// Restores the files of `snapshot_id` and of every later snapshot in the same
+/// session, newest first, so the tree ends up as it was before that write.
+pub fn rollback(root: &Path, snapshot_id: &str) -> Result<RollbackResult, String> {
+    let session_dir = std::fs::read_dir(root)
+        .map_err(|e| format!("Failed to read snapshots '{}': {e}", root.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|dir| dir.join(snapshot_id).join(MANIFEST_FILE).is_file())
+        .ok_or_else(|| format!("Unknown edit snapshot: {snapshot_id}"))?;
+
+    let target = session_dir.join(snapshot_id);
+    let mut result = RollbackResult {
+        snapshot_ids: Vec::new(),
+        restored_files: Vec::new(),
+        deleted_files: Vec::new(),
+    };
+    for dir in snapshot_dirs(&session_dir).into_iter().rev() {
+        if dir < target {
+            break;
+        }
+        let Some(mut snapshot) = read_manifest(&dir) else {
+            continue;
+        };
+        if snapshot.rolled_back {
+            continue;
+        }
+
+        for (index, file) in snapshot.files.iter().enumerate() {
+            let path = Path::new(&file.path);
+            if file.existed {
+                let blob = dir.join(index.to_string());
+                std::fs::copy(&blob, path)
+                    .map_err(|e| format!("Failed to restore '{}': {e}", path.display()))?;
+                result.restored_files.push(file.path.clone());
+            } else {
+                match std::fs::remove_file(path) {
+                    Ok(()) => result.deleted_files.push(file.path.clone()),
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(err) => {
+                        return Err(format!("Failed to remove '{}': {err}", path.display()))
+                    }
+                }
+            }
+        }
+
+        snapshot.rolled_back = true;
+        write_manifest(&dir, &snapshot)?;
+        result.snapshot_ids.push(snapshot.id);
+    }
+
+    result.restored_files.sort();
+    result.restored_files.dedup();
+    result.deleted_files.sort();
+    result.deleted_files.dedup();
+    Ok(result)
+}
+
+/// Snapshots `paths` before an agent-driven write. Failures are logged rather
+/// than returned so a full or unwritable data directory never blocks edits.
+pub async fn snapshot_before_write(
+    app_handle: &tauri::AppHandle,
+    session_id: &str,
+    source: &str,
+    paths: Vec<PathBuf>,
+) {
+    if paths.is_empty() {
+        return;
+    }
+    let root = match snapshots_root(app_handle) {
+        Ok(root) => root,
+        Err(err) => {
+            log::warn!("Skipping edit snapshot: {}", err);
+            return;
+        }
+    };
+    let session_id = session_id.to_string();
+    let source = source.to_string();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        take_snapshot(&root, &session_id, &source, &paths)
+    })
+    .await;
+    match result {
+        Ok(Ok(snapshot)) => log::debug!(
+            "Snapshotted {} file(s) as {} before {}",
+            snapshot.files.len(),
+            snapshot.id,
+            snapshot.source
+        ),
+        Ok(Err(err)) => log::warn!("Failed to snapshot files before edit: {}", err),
+        Err(err) => log::warn!("Snapshot task failed: {}", err),
+    }
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn list_edit_snapshots(
+    app_handle: tauri::AppHandle,
+    session_id: String,
+) -> Result<Vec<EditSnapshot>, String> {
+    let root = snapshots_root(&app_handle)?;
+    tauri::async_runtime::spawn_blocking(move || list_snapshots(&root, &session_id))
+        .await
+        .map_err(|e| format!("Snapshot task failed: {e}"))
+}
+
+#[tauri::command]
+pub async fn rollback_edit(
+    app_handle: tauri::AppHandle,
+    bridge: tauri::State<'_, Mutex<NvimBridgeState>>,
+    snapshot_id: String,
+) -> Result<RollbackResult, String> {
+    let root = snapshots_root(&app_handle)?;
+    let result = tauri::async_runtime::spawn_blocking(move || rollback(&root, &snapshot_id))
+        .await
+        .map_err(|e| format!("Snapshot task failed: {e}"))??;
+
+    // Let open buffers pick up the restored files.
+    nvim_bridge::checktime_all(&bridge).await;
+    log::info!(
+        "Rolled back {} snapshot(s): {} restored, {} deleted",
+        result.snapshot_ids.len(),
+        result.restored_files.len(),
+        result.deleted_files.len()
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rollback_restores_later_snapshots_too() {
+        let root = std::env::temp_dir().join(format!("neoai-snapshots-{}", std::process::id()));
+        let work = root.join("work");
+        std::fs::create_dir_all(&work).unwrap();
+        let edited = work.join("main.rs");
+        let created = work.join("new.rs");
+        std::fs::write(&edited, "v1").unwrap();
+
+        let first = take_snapshot(
+            &root,
+            "sess/1",
+            "write_text_file",
+            std::slice::from_ref(&edited),
+        )
+        .unwrap();
+        std::fs::write(&edited, "v2").unwrap();
+        let second = take_snapshot(
+            &root,
+            "sess/1",
+            "write_text_file",
+            &[edited.clone(), created.clone()],
+        )
+        .unwrap();
+        std::fs::write(&edited, "v3").unwrap();
+        std::fs::write(&created, "new").unwrap();
+
+        let listed = list_snapshots(&root, "sess/1");
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].id, second.id);
+        assert!(!listed[0].files[1].existed);
+
+        let result = rollback(&root, &first.id).unwrap();
+        assert_eq!(
+            result.snapshot_ids,
+            vec![second.id.clone(), first.id.clone()]
+        );
+        assert_eq!(std::fs::read_to_string(&edited).unwrap(), "v1");
+        assert!(!created.exists());
+        assert!(list_snapshots(&root, "sess/1")
+            .iter()
+            .all(|s| s.rolled_back));
+        assert!(rollback(&root, "missing").is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn keeps_session_ids_that_sanitize_alike_apart() {
+        assert_ne!(session_dir_name("sess/1"), session_dir_name("sess_1"));
+        assert_eq!(session_dir_name("sess/1"), session_dir_name("sess/1"));
+        assert!(session_dir_name("sess/1").starts_with("sess_1-"));
+        assert!(session_dir_name("").starts_with("default-"));
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// Stable identifiers the frontend can branch on instead of matching
+/// message text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The terminal has no Neovim bridge; reconnecting fixes it.
+    NvimNotConnected,
+    NvimConnectFailed,
+    /// Neovim answered an RPC call with an error or the call failed.
+    NvimRequestFailed,
+    /// Neovim is waiting for input (a prompt, the command line or a pending
+    /// operator) and would not answer until the user acts; retry later.
+    NvimBusy,
+    AgentNotRunning,
+    AgentAlreadyRunning,
+    AgentStartFailed,
+    /// The agent's worker thread is gone, usually because the agent exited.
+    AgentWorkerDied,
+    SessionFailed,
+    /// A session's working directory is missing, not a directory, or outside
+    /// every registered folder.
+    InvalidWorkingDir,
+    PromptFailed,
+    /// A prompt is larger than the agent's whole context window.
+    ContextWindowExceeded,
+    PermissionRequestNotFound,
+    TmuxFailed,
+    GhosttyFailed,
+    Internal,
+}
+
+/// Error returned by Tauri commands, serialized as
+/// `{ code, message, detail }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NeoaiError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Underlying cause, when `message` summarizes it.
+    pub detail: Option<String>,
+}
+
+impl NeoaiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            detail: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl fmt::Display) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+
+    pub fn nvim_not_connected(terminal_id: &str) -> Self {
+        Self::new(
+            ErrorCode::NvimNotConnected,
+            format!("No neovim connection for terminal: {terminal_id}"),
+        )
+    }
+
+    pub fn nvim_request(err: impl fmt::Display) -> Self {
+        Self::new(ErrorCode::NvimRequestFailed, err.to_string())
+    }
+
+    pub fn agent_not_running() -> Self {
+        Self::new(ErrorCode::AgentNotRunning, "No agent running")
+    }
+
+    pub fn agent_worker_died() -> Self {
+        Self::new(ErrorCode::AgentWorkerDied, "Agent worker died")
+    }
+}
+
+impl fmt::Display for NeoaiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.detail {
+            Some(detail) => write!(f, "{}: {}", self.message, detail),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for NeoaiError {}
+
+/// Errors from helpers that still return `String` carry no specific code.
+impl From<String> for NeoaiError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+}
+
+impl From<NeoaiError> for String {
+    fn from(err: NeoaiError) -> Self {
+        err.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_code_message_and_detail() {
+        let err = NeoaiError::new(ErrorCode::NvimConnectFailed, "Failed to connect to neovim")
+            .with_detail("connection refused");
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["code"], "NVIM_CONNECT_FAILED");
+        assert_eq!(json["message"], "Failed to connect to neovim");
+        assert_eq!(json["detail"], "connection refused");
+        assert_eq!(
+            String::from(err),
+            "Failed to connect to neovim: connection refused"
+        );
+
+        let json = serde_json::to_value(NeoaiError::nvim_not_connected("t1")).unwrap();
+        assert_eq!(json["code"], "NVIM_NOT_CONNECTED");
+        assert!(json["detail"].is_null());
+    }
+}
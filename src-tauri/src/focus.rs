@@ -0,0 +1,170 @@
+//! Tracks which side of the window owns keyboard input: the webview (the
+//! chat panel and the rest of the UI) or an embedded terminal. Ghostty's
+//! first-responder changes, the window's own focus and the `focus_chat` /
+//! `focus_terminal` commands all report here, and every change is emitted
+//! once as `focus-changed`.
+
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FocusOwner {
+    Chat,
+    Terminal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusChangedEvent {
+    pub owner: FocusOwner,
+    /// Terminal holding focus when `owner` is `terminal`.
+    pub terminal_id: Option<String>,
+    /// Split pane within that terminal.
+    pub pane_id: Option<String>,
+    /// Whether the app window itself is the key window. `owner` is kept
+    /// while it is unfocused so focus returns to the same place.
+    pub window_focused: bool,
+}
+
+#[derive(Debug)]
+pub struct FocusCoordinator {
+    state: FocusChangedEvent,
+}
+
+impl Default for FocusCoordinator {
+    fn default() -> Self {
+        Self {
+            state: FocusChangedEvent {
+                owner: FocusOwner::Chat,
+                terminal_id: None,
+                pane_id: None,
+                window_focused: true,
+            },
+        }
+    }
+}
+
+impl FocusCoordinator {
+    pub fn current(&self) -> &FocusChangedEvent {
+        &self.state
+    }
+
+    /// A terminal pane became first responder.
+    pub fn terminal_focused(&mut self, terminal_id: &str, pane_id: &str) -> bool {
+        self.set(FocusChangedEvent {
+            owner: FocusOwner::Terminal,
+            terminal_id: Some(terminal_id.to_string()),
+            pane_id: Some(pane_id.to_string()),
+            ..self.state.clone()
+        })
+    }
+
+    /// A terminal pane lost focus or was closed. Input falls back to the
+    /// webview, unless another pane has taken focus in the meantime.
+    pub fn terminal_blurred(&mut self, pane_id: &str) -> bool {
+        if self.state.pane_id.as_deref() != Some(pane_id) {
+            return false;
+        }
+        self.chat_focused()
+    }
+
+    pub fn chat_focused(&mut self) -> bool {
+        self.set(FocusChangedEvent {
+            owner: FocusOwner::Chat,
+            terminal_id: None,
+            pane_id: None,
+            ..self.state.clone()
+        })
+    }
+
+    pub fn window_focused(&mut self, focused: bool) -> bool {
+        self.set(FocusChangedEvent {
+            window_focused: focused,
+            ..self.state.clone()
+        })
+    }
+
+    /// Returns whether anything changed.
+    fn set(&mut self, state: FocusChangedEvent) -> bool {
+        if self.state == state {
+            return false;
+        }
+        self.state = state;
+        true
+    }
+}
+
+/// Applies `change` to the app's coordinator and emits `focus-changed` when
+/// it reports a change.
+pub fn update(app_handle: &tauri::AppHandle, change: impl FnOnce(&mut FocusCoordinator) -> bool) {
+    let Some(coordinator) = app_handle.try_state::<std::sync::Mutex<FocusCoordinator>>() else {
+        return;
+    };
+    let event = {
+        let Ok(mut coordinator) = coordinator.lock() else {
+            log::warn!("Failed to lock focus coordinator");
+            return;
+        };
+        if !change(&mut coordinator) {
+            return;
+        }
+        coordinator.current().clone()
+    };
+    let _ = app_handle.emit("focus-changed", &event);
+}
+
+// -- Tauri IPC commands --
+
+/// Current keyboard focus owner, for views mounted after the last
+/// `focus-changed` event.
+#[tauri::command]
+pub fn focus_state(
+    state: tauri::State<'_, std::sync::Mutex<FocusCoordinator>>,
+) -> Result<FocusChangedEvent, String> {
+    let coordinator = state.lock().map_err(|e| e.to_string())?;
+    Ok(coordinator.current().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_each_change_once() {
+        let mut focus = FocusCoordinator::default();
+        assert!(!focus.chat_focused());
+
+        assert!(focus.terminal_focused("t1", "t1"));
+        assert!(!focus.terminal_focused("t1", "t1"));
+        assert_eq!(focus.current().owner, FocusOwner::Terminal);
+
+        assert!(focus.terminal_focused("t1", "t1:split-1"));
+        assert_eq!(focus.current().pane_id.as_deref(), Some("t1:split-1"));
+
+        assert!(focus.window_focused(false));
+        assert_eq!(focus.current().owner, FocusOwner::Terminal);
+        assert!(focus.window_focused(true));
+    }
+
+    #[test]
+    fn blur_only_hands_focus_back_from_the_owning_pane() {
+        let mut focus = FocusCoordinator::default();
+        focus.terminal_focused("t1", "t1:split-1");
+
+        // The pane that had focus before resigns after the new one took it.
+        assert!(!focus.terminal_blurred("t1"));
+        assert_eq!(focus.current().pane_id.as_deref(), Some("t1:split-1"));
+
+        assert!(focus.terminal_blurred("t1:split-1"));
+        assert_eq!(
+            focus.current(),
+            &FocusChangedEvent {
+                owner: FocusOwner::Chat,
+                terminal_id: None,
+                pane_id: None,
+                window_focused: true,
+            }
+        );
+    }
+}
@@ -0,0 +1,890 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app_paths;
+use crate::tmux_runtime::TmuxWindowSpec;
+
+pub const FOLDER_STATE_FILE: &str = "folders.json";
+
+/// Activity is written to disk at most this often; keystrokes only update
+/// memory in between.
+const ACTIVITY_FLUSH_DELAY: Duration = Duration::from_secs(30);
+
+/// Session associations remembered for a project folder across restarts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSession {
+    #[serde(default)]
+    pub acp_session_id: Option<String>,
+    #[serde(default)]
+    pub agent_name: Option<String>,
+    /// Agent program new sessions in this folder should run on, overriding
+    /// `[agent] path`.
+    #[serde(default)]
+    pub preferred_agent: Option<String>,
+    /// Model new sessions in this folder ask for, overriding `[agent] model`.
+    #[serde(default)]
+    pub preferred_model: Option<String>,
+    #[serde(default)]
+    pub tmux_session_name: Option<String>,
+    /// Folder location on disk, registered by the frontend.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Last git branch seen for the folder.
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// Summary of compacted chat history, fed to later prompts as context.
+    #[serde(default)]
+    pub conversation_summary: Option<String>,
+    /// Terminals open for the folder, restored by `restore_workspace`.
+    #[serde(default)]
+    pub terminals: Vec<String>,
+    /// Socket the folder's Neovim last listened on; it outlives the app when
+    /// Neovim runs inside tmux.
+    #[serde(default)]
+    pub nvim_socket_path: Option<String>,
+    /// Unsent chat prompt, kept until it is sent to the folder's agent.
+    #[serde(default)]
+    pub draft: Option<String>,
+    /// Instructions from the folder's project template, sent as context with
+    /// every prompt.
+    #[serde(default)]
+    pub preamble: Option<String>,
+    /// Windows opened next to Neovim whenever it starts in tmux.
+    #[serde(default)]
+    pub tmux_windows: Vec<TmuxWindowSpec>,
+    /// Last time one of the folder's terminals got input or a prompt was
+    /// sent to its agent.
+    #[serde(default)]
+    pub last_used_at: Option<u64>,
+    /// The folder's path was gone at the last check. Cleared once it is back
+    /// or the folder is registered at another path.
+    #[serde(default)]
+    pub missing: bool,
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+}
+
+/// Partial update for a [`FolderSession`]. `None` leaves a field untouched and
+/// an empty string clears it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSessionUpdate {
+    #[serde(default)]
+    pub acp_session_id: Option<String>,
+    #[serde(default)]
+    pub agent_name: Option<String>,
+    #[serde(default)]
+    pub preferred_agent: Option<String>,
+    #[serde(default)]
+    pub preferred_model: Option<String>,
+    #[serde(default)]
+    pub tmux_session_name: Option<String>,
+    #[serde(default)]
+    pub conversation_summary: Option<String>,
+    #[serde(default)]
+    pub nvim_socket_path: Option<String>,
+    #[serde(default)]
+    pub preamble: Option<String>,
+    /// Replaces the folder's tmux windows; an empty list removes them.
+    #[serde(default)]
+    pub tmux_windows: Option<Vec<TmuxWindowSpec>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FolderIssueKind {
+    /// Nothing exists at the folder's path.
+    Missing,
+    /// The path exists but is a file.
+    NotADirectory,
+    /// The path couldn't be checked, e.g. for lack of permission.
+    Unreadable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderIssue {
+    pub folder_id: String,
+    pub path: String,
+    pub kind: FolderIssueKind,
+}
+
+/// Payload of the `workspace-issues` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceIssuesEvent {
+    /// Ordered by folder id.
+    pub issues: Vec<FolderIssue>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderPathEntry {
+    pub folder_id: String,
+    pub path: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FolderStateFile {
+    #[serde(default)]
+    sessions: HashMap<String, FolderSession>,
+}
+
+#[derive(Debug, Default)]
+pub struct FolderStateStore {
+    path: Option<PathBuf>,
+    state: FolderStateFile,
+    /// `last_used_at` changed since it was last written.
+    activity_dirty: bool,
+}
+
+impl FolderStateStore {
+    pub fn initialize(&mut self, app_handle: &tauri::AppHandle) -> Result<(), String> {
+        let root = app_paths::app_root_dir(app_handle)?;
+        let path = root.join(FOLDER_STATE_FILE);
+
+        if path.exists() {
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read folder state '{}': {e}", path.display()))?;
+            self.state = serde_json::from_str(&contents).unwrap_or_else(|err| {
+                log::warn!(
+                    "Failed to parse folder state '{}'. Starting fresh: {err}",
+                    path.display()
+                );
+                FolderStateFile::default()
+            });
+        }
+
+        self.path = Some(path);
+        Ok(())
+    }
+
+    pub fn session(&self, folder_id: &str) -> FolderSession {
+        self.state
+            .sessions
+            .get(folder_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Whether any folder expects to reattach to `session_name`.
+    pub fn remembers_tmux_session(&self, session_name: &str) -> bool {
+        self.state
+            .sessions
+            .values()
+            .any(|session| session.tmux_session_name.as_deref() == Some(session_name))
+    }
+
+    pub fn update_session(
+        &mut self,
+        folder_id: &str,
+        update: FolderSessionUpdate,
+    ) -> Result<FolderSession, String> {
+        let entry = self
+            .state
+            .sessions
+            .entry(folder_id.to_string())
+            .or_default();
+        apply_field(&mut entry.acp_session_id, update.acp_session_id);
+        apply_field(&mut entry.agent_name, update.agent_name);
+        apply_field(&mut entry.preferred_agent, update.preferred_agent);
+        apply_field(&mut entry.preferred_model, update.preferred_model);
+        apply_field(&mut entry.tmux_session_name, update.tmux_session_name);
+        apply_field(&mut entry.conversation_summary, update.conversation_summary);
+        apply_field(&mut entry.nvim_socket_path, update.nvim_socket_path);
+        apply_field(&mut entry.preamble, update.preamble);
+        if let Some(windows) = update.tmux_windows {
+            entry.tmux_windows = windows;
+        }
+        entry.updated_at = Some(now_millis());
+        let session = entry.clone();
+
+        self.persist()?;
+        Ok(session)
+    }
+
+    /// Records where each folder lives, persisting only when something changed.
+    pub fn register_paths(&mut self, entries: Vec<FolderPathEntry>) -> Result<(), String> {
+        let mut changed = false;
+        for entry in entries {
+            let session = self.state.sessions.entry(entry.folder_id).or_default();
+            if session.path.as_deref() != Some(entry.path.as_str()) {
+                session.path = Some(entry.path);
+                session.missing = false;
+                session.updated_at = Some(now_millis());
+                changed = true;
+            }
+        }
+        if changed {
+            self.persist()?;
+        }
+        Ok(())
+    }
+
+    /// The registered folder that contains `path`, which should already be
+    /// canonical. The innermost folder wins when folders nest.
+    pub fn folder_containing(&self, path: &Path) -> Option<String> {
+        self.state
+            .sessions
+            .iter()
+            .filter_map(|(folder_id, session)| {
+                let root = PathBuf::from(session.path.as_deref()?);
+                let root = std::fs::canonicalize(&root).unwrap_or(root);
+                path.starts_with(&root)
+                    .then(|| (root.components().count(), folder_id))
+            })
+            .max_by_key(|(depth, _)| *depth)
+            .map(|(_, folder_id)| folder_id.clone())
+    }
+
+    pub fn folder_path(&self, folder_id: &str) -> Option<PathBuf> {
+        self.state
+            .sessions
+            .get(folder_id)
+            .and_then(|session| session.path.as_deref())
+            .map(PathBuf::from)
+    }
+
+    /// Stores the folder's current branch. Returns `true` when it changed.
+    pub fn set_branch(&mut self, folder_id: &str, branch: Option<String>) -> Result<bool, String> {
+        let session = self
+            .state
+            .sessions
+            .entry(folder_id.to_string())
+            .or_default();
+        if session.branch == branch {
+            return Ok(false);
+        }
+        session.branch = branch;
+        session.updated_at = Some(now_millis());
+        self.persist()?;
+        Ok(true)
+    }
+
+    /// Adds or removes an open terminal. Returns `true` when the list changed.
+    pub fn set_terminal_open(
+        &mut self,
+        folder_id: &str,
+        terminal_id: &str,
+        open: bool,
+    ) -> Result<bool, String> {
+        let session = self
+            .state
+            .sessions
+            .entry(folder_id.to_string())
+            .or_default();
+        let known = session.terminals.iter().any(|id| id == terminal_id);
+        if known == open {
+            return Ok(false);
+        }
+        if open {
+            session.terminals.push(terminal_id.to_string());
+        } else {
+            session.terminals.retain(|id| id != terminal_id);
+        }
+        session.updated_at = Some(now_millis());
+        self.persist()?;
+        Ok(true)
+    }
+
+    /// Stores the folder's unsent prompt; blank content clears it. Returns
+    /// `true` when it changed.
+    pub fn set_draft(&mut self, folder_id: &str, content: &str) -> Result<bool, String> {
+        let draft = (!content.trim().is_empty()).then(|| content.to_string());
+        let current = self
+            .state
+            .sessions
+            .get(folder_id)
+            .and_then(|session| session.draft.as_ref());
+        if current == draft.as_ref() {
+            return Ok(false);
+        }
+        let session = self
+            .state
+            .sessions
+            .entry(folder_id.to_string())
+            .or_default();
+        session.draft = draft;
+        session.updated_at = Some(now_millis());
+        self.persist()?;
+        Ok(true)
+    }
+
+    /// Checks that every registered folder's path is still a directory,
+    /// updating each folder's `missing` flag. Returns the folders with
+    /// problems.
+    pub fn check_paths(&mut self) -> Result<Vec<FolderIssue>, String> {
+        let mut issues = Vec::new();
+        let mut changed = false;
+        for (folder_id, session) in &mut self.state.sessions {
+            let Some(path) = session.path.clone() else {
+                continue;
+            };
+            let kind = match std::fs::metadata(&path) {
+                Ok(metadata) if metadata.is_dir() => None,
+                Ok(_) => Some(FolderIssueKind::NotADirectory),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    Some(FolderIssueKind::Missing)
+                }
+                Err(_) => Some(FolderIssueKind::Unreadable),
+            };
+            if session.missing != kind.is_some() {
+                session.missing = kind.is_some();
+                session.updated_at = Some(now_millis());
+                changed = true;
+            }
+            if let Some(kind) = kind {
+                issues.push(FolderIssue {
+                    folder_id: folder_id.clone(),
+                    path,
+                    kind,
+                });
+            }
+        }
+        if changed {
+            self.persist()?;
+        }
+        issues.sort_by(|a, b| a.folder_id.cmp(&b.folder_id));
+        Ok(issues)
+    }
+
+    /// Marks the folder as used at `now_ms` without writing to disk. Returns
+    /// `true` when this is the first change since the last
+    /// [`Self::flush_activity`], so the caller should schedule one.
+    pub fn touch(&mut self, folder_id: &str, now_ms: u64) -> bool {
+        let Some(session) = self.state.sessions.get_mut(folder_id) else {
+            return false;
+        };
+        session.last_used_at = Some(now_ms);
+        !std::mem::replace(&mut self.activity_dirty, true)
+    }
+
+    /// Writes activity recorded by [`Self::touch`]. Returns `true` when there
+    /// was any.
+    pub fn flush_activity(&mut self) -> Result<bool, String> {
+        if !std::mem::take(&mut self.activity_dirty) {
+            return Ok(false);
+        }
+        self.persist()?;
+        Ok(true)
+    }
+
+    /// When each folder was last used, for folders that have been.
+    pub fn activity(&self) -> HashMap<String, u64> {
+        self.state
+            .sessions
+            .iter()
+            .filter_map(|(folder_id, session)| Some((folder_id.clone(), session.last_used_at?)))
+            .collect()
+    }
+
+    pub fn clear_session(&mut self, folder_id: &str) -> Result<(), String> {
+        if self.state.sessions.remove(folder_id).is_some() {
+            self.persist()?;
+        }
+        Ok(())
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                format!(
+                    "Failed to create folder state directory '{}': {e}",
+                    parent.display()
+                )
+            })?;
+        }
+
+        let contents = serde_json::to_string_pretty(&self.state).map_err(|e| e.to_string())?;
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, contents).map_err(|e| {
+            format!(
+                "Failed to write folder state '{}': {e}",
+                temp_path.display()
+            )
+        })?;
+        std::fs::rename(&temp_path, path)
+            .map_err(|e| format!("Failed to replace folder state '{}': {e}", path.display()))
+    }
+}
+
+fn apply_field(field: &mut Option<String>, update: Option<String>) {
+    if let Some(value) = update {
+        *field = if value.is_empty() { None } else { Some(value) };
+    }
+}
+
+/// Number of folder sessions in a `folders.json` payload, or why it can't be loaded.
+pub fn count_sessions(contents: &str) -> Result<usize, String> {
+    serde_json::from_str::<FolderStateFile>(contents)
+        .map(|state| state.sessions.len())
+        .map_err(|e| e.to_string())
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Terminals are created by the frontend as `terminal-{folderId}`.
+pub fn folder_id_for_terminal(terminal_id: &str) -> Option<&str> {
+    terminal_id
+        .strip_prefix("terminal-")
+        .filter(|folder_id| !folder_id.is_empty())
+}
+
+/// Records session associations for the folder owning `terminal_id`, logging
+/// instead of failing so callers on the hot path are never blocked by disk errors.
+pub fn remember_for_terminal(
+    app_handle: &tauri::AppHandle,
+    terminal_id: &str,
+    update: FolderSessionUpdate,
+) {
+    use tauri::Manager;
+
+    let Some(folder_id) = folder_id_for_terminal(terminal_id) else {
+        return;
+    };
+    let Some(store) = app_handle.try_state::<std::sync::Mutex<FolderStateStore>>() else {
+        return;
+    };
+    let Ok(mut store) = store.lock() else {
+        log::warn!("Failed to lock folder state store");
+        return;
+    };
+    if let Err(err) = store.update_session(folder_id, update) {
+        log::warn!("Failed to persist session state for folder '{folder_id}': {err}");
+    }
+}
+
+/// Records that `terminal_id` was opened or closed, logging failures like
+/// [`remember_for_terminal`].
+pub fn record_terminal_open(app_handle: &tauri::AppHandle, terminal_id: &str, open: bool) {
+    use tauri::Manager;
+
+    let Some(folder_id) = folder_id_for_terminal(terminal_id) else {
+        return;
+    };
+    let Some(store) = app_handle.try_state::<std::sync::Mutex<FolderStateStore>>() else {
+        return;
+    };
+    let Ok(mut store) = store.lock() else {
+        log::warn!("Failed to lock folder state store");
+        return;
+    };
+    if let Err(err) = store.set_terminal_open(folder_id, terminal_id, open) {
+        log::warn!("Failed to persist open terminals for folder '{folder_id}': {err}");
+    }
+}
+
+/// Drops the draft of the folder owning `terminal_id` once its prompt has
+/// been sent, logging failures like [`remember_for_terminal`].
+pub fn clear_draft_for_terminal(app_handle: &tauri::AppHandle, terminal_id: &str) {
+    use tauri::Manager;
+
+    let Some(folder_id) = folder_id_for_terminal(terminal_id) else {
+        return;
+    };
+    let Some(store) = app_handle.try_state::<std::sync::Mutex<FolderStateStore>>() else {
+        return;
+    };
+    let Ok(mut store) = store.lock() else {
+        log::warn!("Failed to lock folder state store");
+        return;
+    };
+    if let Err(err) = store.set_draft(folder_id, "") {
+        log::warn!("Failed to clear draft for folder '{folder_id}': {err}");
+    }
+}
+
+/// Payload of the `folder-activity` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderActivityEvent {
+    /// Last use of every folder, in milliseconds since the Unix epoch.
+    pub last_used_at: HashMap<String, u64>,
+}
+
+/// Records that the folder owning `terminal_id` is in use. Called for every
+/// keystroke, so it only updates memory; the first call after a write
+/// schedules the next one [`ACTIVITY_FLUSH_DELAY`] later.
+pub fn note_terminal_activity(app_handle: &tauri::AppHandle, terminal_id: &str) {
+    use tauri::Manager;
+
+    let Some(folder_id) = folder_id_for_terminal(terminal_id) else {
+        return;
+    };
+    let Some(store) = app_handle.try_state::<std::sync::Mutex<FolderStateStore>>() else {
+        return;
+    };
+    let schedule = match store.lock() {
+        Ok(mut store) => store.touch(folder_id, now_millis()),
+        Err(_) => {
+            log::warn!("Failed to lock folder state store");
+            return;
+        }
+    };
+    if schedule {
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(ACTIVITY_FLUSH_DELAY).await;
+            flush_activity(&app_handle);
+        });
+    }
+}
+
+/// Writes pending folder activity and emits `folder-activity` so the sidebar
+/// can reorder. Also called on exit.
+pub fn flush_activity(app_handle: &tauri::AppHandle) {
+    use tauri::{Emitter, Manager};
+
+    let Some(store) = app_handle.try_state::<std::sync::Mutex<FolderStateStore>>() else {
+        return;
+    };
+    let Ok(mut store) = store.lock() else {
+        log::warn!("Failed to lock folder state store");
+        return;
+    };
+    match store.flush_activity() {
+        Ok(true) => {
+            let _ = app_handle.emit(
+                "folder-activity",
+                &FolderActivityEvent {
+                    last_used_at: store.activity(),
+                },
+            );
+        }
+        Ok(false) => {}
+        Err(err) => log::warn!("Failed to persist folder activity: {err}"),
+    }
+}
+
+/// Checks registered folder paths on launch and emits `workspace-issues`
+/// when any is gone, so the UI can offer to relocate or remove those folders.
+pub fn check_workspace(app_handle: &tauri::AppHandle) {
+    use tauri::{Emitter, Manager};
+
+    let Some(store) = app_handle.try_state::<std::sync::Mutex<FolderStateStore>>() else {
+        return;
+    };
+    let issues = match store.lock() {
+        Ok(mut store) => store.check_paths(),
+        Err(_) => {
+            log::warn!("Failed to lock folder state store");
+            return;
+        }
+    };
+    match issues {
+        Ok(issues) if issues.is_empty() => {}
+        Ok(issues) => {
+            for issue in &issues {
+                log::warn!(
+                    "Folder '{}' is unavailable ({:?}): {}",
+                    issue.folder_id,
+                    issue.kind,
+                    issue.path
+                );
+            }
+            let _ = app_handle.emit("workspace-issues", &WorkspaceIssuesEvent { issues });
+        }
+        Err(err) => log::warn!("Failed to persist folder availability: {err}"),
+    }
+}
+
+/// Agent and model preferred by the folder owning `terminal_id`, if any.
+pub fn preferences_for_terminal(
+    app_handle: &tauri::AppHandle,
+    terminal_id: &str,
+) -> (Option<String>, Option<String>) {
+    use tauri::Manager;
+
+    let Some(folder_id) = folder_id_for_terminal(terminal_id) else {
+        return (None, None);
+    };
+    let Some(store) = app_handle.try_state::<std::sync::Mutex<FolderStateStore>>() else {
+        return (None, None);
+    };
+    let Ok(store) = store.lock() else {
+        log::warn!("Failed to lock folder state store");
+        return (None, None);
+    };
+    let session = store.session(folder_id);
+    (session.preferred_agent, session.preferred_model)
+}
+
+/// Resolves a folder id to the directory registered for it by the frontend.
+pub fn resolve_folder_path(
+    app_handle: &tauri::AppHandle,
+    folder_id: &str,
+) -> Result<PathBuf, String> {
+    use tauri::Manager;
+
+    let store = app_handle.state::<std::sync::Mutex<FolderStateStore>>();
+    let store = store.lock().map_err(|e| e.to_string())?;
+    let path = store
+        .folder_path(folder_id)
+        .ok_or_else(|| format!("No path registered for folder: {folder_id}"))?;
+    Ok(path)
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn folder_session_get(
+    state: tauri::State<'_, std::sync::Mutex<FolderStateStore>>,
+    folder_id: String,
+) -> Result<FolderSession, String> {
+    let store = state.lock().map_err(|e| e.to_string())?;
+    Ok(store.session(&folder_id))
+}
+
+#[tauri::command]
+pub async fn folder_session_update(
+    state: tauri::State<'_, std::sync::Mutex<FolderStateStore>>,
+    folder_id: String,
+    update: FolderSessionUpdate,
+) -> Result<FolderSession, String> {
+    let mut store = state.lock().map_err(|e| e.to_string())?;
+    store.update_session(&folder_id, update)
+}
+
+#[tauri::command]
+pub async fn folder_register_paths(
+    state: tauri::State<'_, std::sync::Mutex<FolderStateStore>>,
+    folders: Vec<FolderPathEntry>,
+) -> Result<(), String> {
+    let mut store = state.lock().map_err(|e| e.to_string())?;
+    store.register_paths(folders)
+}
+
+/// Saves the chat prompt being written in a folder so it survives folder
+/// switches and restarts. Blank content clears it.
+#[tauri::command]
+pub async fn folder_draft_save(
+    state: tauri::State<'_, std::sync::Mutex<FolderStateStore>>,
+    folder_id: String,
+    content: String,
+) -> Result<(), String> {
+    let mut store = state.lock().map_err(|e| e.to_string())?;
+    store.set_draft(&folder_id, &content).map(|_| ())
+}
+
+#[tauri::command]
+pub async fn folder_draft_load(
+    state: tauri::State<'_, std::sync::Mutex<FolderStateStore>>,
+    folder_id: String,
+) -> Result<Option<String>, String> {
+    let store = state.lock().map_err(|e| e.to_string())?;
+    Ok(store.session(&folder_id).draft)
+}
+
+/// Re-checks registered folder paths, e.g. after the user moved a folder
+/// back, and returns the folders still unavailable.
+#[tauri::command]
+pub async fn folder_check_paths(
+    state: tauri::State<'_, std::sync::Mutex<FolderStateStore>>,
+) -> Result<Vec<FolderIssue>, String> {
+    let mut store = state.lock().map_err(|e| e.to_string())?;
+    store.check_paths()
+}
+
+/// When each folder was last used, keyed by folder id.
+#[tauri::command]
+pub async fn folder_activity(
+    state: tauri::State<'_, std::sync::Mutex<FolderStateStore>>,
+) -> Result<HashMap<String, u64>, String> {
+    let store = state.lock().map_err(|e| e.to_string())?;
+    Ok(store.activity())
+}
+
+#[tauri::command]
+pub async fn folder_session_clear(
+    state: tauri::State<'_, std::sync::Mutex<FolderStateStore>>,
+    folder_id: String,
+) -> Result<(), String> {
+    let mut store = state.lock().map_err(|e| e.to_string())?;
+    store.clear_session(&folder_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_folder_id_from_terminal_id() {
+        assert_eq!(folder_id_for_terminal("terminal-f-abc"), Some("f-abc"));
+        assert_eq!(folder_id_for_terminal("terminal-"), None);
+        assert_eq!(folder_id_for_terminal("other"), None);
+    }
+
+    #[test]
+    fn partial_updates_keep_and_clear_fields() {
+        let mut store = FolderStateStore::default();
+        store
+            .update_session(
+                "f1",
+                FolderSessionUpdate {
+                    acp_session_id: Some("sess-1".to_string()),
+                    tmux_session_name: Some("neoai-app".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let session = store
+            .update_session(
+                "f1",
+                FolderSessionUpdate {
+                    acp_session_id: Some(String::new()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(session.acp_session_id, None);
+        assert_eq!(session.tmux_session_name.as_deref(), Some("neoai-app"));
+
+        let session = store
+            .update_session(
+                "f1",
+                FolderSessionUpdate {
+                    preferred_model: Some("o3".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(session.preferred_model.as_deref(), Some("o3"));
+        assert_eq!(session.preferred_agent, None);
+    }
+
+    #[test]
+    fn batches_activity_until_flushed() {
+        let mut store = FolderStateStore::default();
+        store
+            .register_paths(vec![FolderPathEntry {
+                folder_id: "f1".to_string(),
+                path: "/nonexistent/f1".to_string(),
+            }])
+            .unwrap();
+
+        assert!(!store.touch("unknown", 1));
+        assert!(store.touch("f1", 10));
+        assert!(!store.touch("f1", 20));
+        assert_eq!(store.activity().get("f1"), Some(&20));
+
+        assert!(store.flush_activity().unwrap());
+        assert!(!store.flush_activity().unwrap());
+        assert!(store.touch("f1", 30));
+    }
+
+    #[test]
+    fn flags_folders_whose_path_is_gone() {
+        let root =
+            std::env::temp_dir().join(format!("neoai-folder-check-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("app")).unwrap();
+        std::fs::write(root.join("notes.txt"), "").unwrap();
+
+        let mut store = FolderStateStore::default();
+        let entry = |folder_id: &str, path: PathBuf| FolderPathEntry {
+            folder_id: folder_id.to_string(),
+            path: path.to_string_lossy().to_string(),
+        };
+        store
+            .register_paths(vec![
+                entry("app", root.join("app")),
+                entry("gone", root.join("gone")),
+                entry("file", root.join("notes.txt")),
+            ])
+            .unwrap();
+
+        let issues = store.check_paths().unwrap();
+        let kinds: Vec<(&str, FolderIssueKind)> = issues
+            .iter()
+            .map(|issue| (issue.folder_id.as_str(), issue.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("file", FolderIssueKind::NotADirectory),
+                ("gone", FolderIssueKind::Missing),
+            ]
+        );
+        assert!(store.session("gone").missing);
+        assert!(!store.session("app").missing);
+
+        // Relocating the folder clears the flag.
+        store
+            .register_paths(vec![entry("gone", root.join("app"))])
+            .unwrap();
+        assert!(!store.session("gone").missing);
+        assert_eq!(store.check_paths().unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn finds_the_innermost_folder_containing_a_path() {
+        let mut store = FolderStateStore::default();
+        store
+            .register_paths(vec![
+                FolderPathEntry {
+                    folder_id: "app".to_string(),
+                    path: "/nonexistent/work/app".to_string(),
+                },
+                FolderPathEntry {
+                    folder_id: "api".to_string(),
+                    path: "/nonexistent/work/app/api".to_string(),
+                },
+            ])
+            .unwrap();
+
+        let found = |path: &str| store.folder_containing(Path::new(path));
+        assert_eq!(
+            found("/nonexistent/work/app/api/src").as_deref(),
+            Some("api")
+        );
+        assert_eq!(found("/nonexistent/work/app/web").as_deref(), Some("app"));
+        assert_eq!(found("/nonexistent/work/application"), None);
+    }
+
+    #[test]
+    fn keeps_drafts_until_cleared_with_blank_content() {
+        let mut store = FolderStateStore::default();
+        assert!(!store.set_draft("f1", "  \n").unwrap());
+        assert!(store.state.sessions.is_empty());
+
+        assert!(store.set_draft("f1", "Explain the parser ").unwrap());
+        assert!(!store.set_draft("f1", "Explain the parser ").unwrap());
+        assert_eq!(
+            store.session("f1").draft.as_deref(),
+            Some("Explain the parser ")
+        );
+
+        assert!(store.set_draft("f1", "").unwrap());
+        assert_eq!(store.session("f1").draft, None);
+    }
+
+    #[test]
+    fn tracks_open_terminals_once() {
+        let mut store = FolderStateStore::default();
+        assert!(store.set_terminal_open("f1", "terminal-f1", true).unwrap());
+        assert!(!store.set_terminal_open("f1", "terminal-f1", true).unwrap());
+        assert_eq!(
+            store.session("f1").terminals,
+            vec!["terminal-f1".to_string()]
+        );
+
+        assert!(store.set_terminal_open("f1", "terminal-f1", false).unwrap());
+        assert!(store.session("f1").terminals.is_empty());
+        assert!(!store.set_terminal_open("f1", "terminal-f1", false).unwrap());
+    }
+}
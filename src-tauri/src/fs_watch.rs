@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use tauri::Emitter;
+
+use crate::{folder_state, fuzzy_finder, symbol_index};
+
+/// Quiet period after the last filesystem event before changes are emitted.
+const FILE_CHANGE_DEBOUNCE: Duration = Duration::from_millis(200);
+/// Upper bound on how long a steady stream of events can delay emission.
+const FILE_CHANGE_MAX_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChangedEvent {
+    pub folder_id: String,
+    /// Path relative to the folder root, `/`-separated.
+    pub path: String,
+    pub kind: FileChangeKind,
+}
+
+struct FolderWatch {
+    root: PathBuf,
+    // Dropping the watcher closes the channel, which ends the watch thread.
+    _watcher: RecommendedWatcher,
+}
+
+/// Active recursive watchers, keyed by folder id.
+#[derive(Default)]
+pub struct FsWatchState {
+    watchers: HashMap<String, FolderWatch>,
+}
+
+impl FsWatchState {
+    pub fn watched_root(&self, folder_id: &str) -> Option<&Path> {
+        self.watchers
+            .get(folder_id)
+            .map(|watch| watch.root.as_path())
+    }
+}
+
+fn spawn_folder_watch(
+    app_handle: tauri::AppHandle,
+    folder_id: String,
+    root: PathBuf,
+) -> Result<FolderWatch, String> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| format!("Failed to create file watcher: {e}"))?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch folder '{}': {e}", root.display()))?;
+
+    let thread_root = root.clone();
+    std::thread::Builder::new()
+        .name(format!("neoai-fs-watch-{folder_id}"))
+        .spawn(move || run_watch_loop(&app_handle, &folder_id, &thread_root, rx))
+        .map_err(|e| format!("Failed to spawn file watcher thread: {e}"))?;
+
+    Ok(FolderWatch {
+        root,
+        _watcher: watcher,
+    })
+}
+
+fn run_watch_loop(
+    app_handle: &tauri::AppHandle,
+    folder_id: &str,
+    root: &Path,
+    rx: Receiver<notify::Result<notify::Event>>,
+) {
+    let mut ignore = build_ignore(root);
+
+    while let Ok(first) = rx.recv() {
+        let mut pending: HashMap<PathBuf, FileChangeKind> = HashMap::new();
+        collect_event(&mut pending, first);
+
+        let deadline = Instant::now() + FILE_CHANGE_MAX_DELAY;
+        loop {
+            let wait = FILE_CHANGE_DEBOUNCE.min(deadline.saturating_duration_since(Instant::now()));
+            match rx.recv_timeout(wait) {
+                Ok(event) => collect_event(&mut pending, event),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        if pending
+            .keys()
+            .any(|path| path.file_name().is_some_and(|name| name == ".gitignore"))
+        {
+            ignore = build_ignore(root);
+        }
+
+        let mut changes: Vec<FileChangedEvent> = pending
+            .into_iter()
+            .filter_map(|(path, kind)| {
+                let relative = path.strip_prefix(root).ok()?;
+                if relative.as_os_str().is_empty() || is_ignored(&ignore, relative, path.is_dir()) {
+                    return None;
+                }
+                Some(FileChangedEvent {
+                    folder_id: folder_id.to_string(),
+                    path: relative.to_string_lossy().replace('\\', "/"),
+                    kind,
+                })
+            })
+            .collect();
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        fuzzy_finder::apply_file_changes(app_handle, folder_id, &changes);
+        symbol_index::apply_file_changes(app_handle, folder_id, &changes);
+        for change in &changes {
+            let _ = app_handle.emit("file-changed", change);
+        }
+    }
+}
+
+fn collect_event(
+    pending: &mut HashMap<PathBuf, FileChangeKind>,
+    event: notify::Result<notify::Event>,
+) {
+    let event = match event {
+        Ok(event) => event,
+        Err(err) => {
+            log::warn!("File watcher error: {err}");
+            return;
+        }
+    };
+
+    for path in event.paths {
+        let kind = match event.kind {
+            EventKind::Create(_) => FileChangeKind::Created,
+            EventKind::Remove(_) => FileChangeKind::Removed,
+            // Renames arrive as separate from/to paths on most platforms.
+            EventKind::Modify(ModifyKind::Name(_)) if path.exists() => FileChangeKind::Created,
+            EventKind::Modify(ModifyKind::Name(_)) => FileChangeKind::Removed,
+            EventKind::Modify(ModifyKind::Metadata(_)) => continue,
+            EventKind::Modify(_) => FileChangeKind::Modified,
+            _ => continue,
+        };
+        match pending.get(&path).copied() {
+            Some(previous) => match merge_change(previous, kind) {
+                Some(merged) => {
+                    pending.insert(path, merged);
+                }
+                None => {
+                    pending.remove(&path);
+                }
+            },
+            None => {
+                pending.insert(path, kind);
+            }
+        }
+    }
+}
+
+/// Folds two changes to the same path within one debounce window.
+/// `None` means the path ended up where it started (created then removed).
+fn merge_change(previous: FileChangeKind, next: FileChangeKind) -> Option<FileChangeKind> {
+    use FileChangeKind::*;
+
+    match (previous, next) {
+        (Created, Removed) => None,
+        (Created, _) => Some(Created),
+        (Removed, Created) | (Removed, Modified) => Some(Modified),
+        (_, next) => Some(next),
+    }
+}
+
+/// Builds the ignore matcher from the folder's `.gitignore` and `.git/info/exclude`.
+fn build_ignore(root: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for file in [root.join(".gitignore"), root.join(".git/info/exclude")] {
+        if file.is_file() {
+            if let Some(err) = builder.add(&file) {
+                log::warn!("Failed to read ignore file '{}': {err}", file.display());
+            }
+        }
+    }
+    builder.build().unwrap_or_else(|err| {
+        log::warn!(
+            "Failed to build ignore rules for '{}': {err}",
+            root.display()
+        );
+        Gitignore::empty()
+    })
+}
+
+fn is_ignored(ignore: &Gitignore, relative: &Path, is_dir: bool) -> bool {
+    relative
+        .components()
+        .next()
+        .is_some_and(|component| component.as_os_str() == ".git")
+        || ignore
+            .matched_path_or_any_parents(relative, is_dir)
+            .is_ignore()
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn watch_folder(
+    state: tauri::State<'_, std::sync::Mutex<FsWatchState>>,
+    app_handle: tauri::AppHandle,
+    folder_id: String,
+) -> Result<(), String> {
+    let root = folder_state::resolve_folder_path(&app_handle, &folder_id)?;
+    // Watchers report canonical paths (e.g. /private/var on macOS).
+    let root = std::fs::canonicalize(&root).unwrap_or(root);
+
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    if state.watched_root(&folder_id) == Some(root.as_path()) {
+        return Ok(());
+    }
+    let watch = spawn_folder_watch(app_handle.clone(), folder_id.clone(), root)?;
+    state.watchers.insert(folder_id, watch);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unwatch_folder(
+    state: tauri::State<'_, std::sync::Mutex<FsWatchState>>,
+    folder_id: String,
+) -> Result<bool, String> {
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    Ok(state.watchers.remove(&folder_id).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_changes_within_a_window() {
+        use FileChangeKind::*;
+
+        assert_eq!(merge_change(Created, Modified), Some(Created));
+        assert_eq!(merge_change(Created, Removed), None);
+        assert_eq!(merge_change(Removed, Created), Some(Modified));
+        assert_eq!(merge_change(Modified, Removed), Some(Removed));
+    }
+
+    #[test]
+    fn filters_gitignored_paths() {
+        let root = std::env::temp_dir().join(format!("neoai-fs-watch-test-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+
+        let ignore = build_ignore(&root);
+        assert!(is_ignored(&ignore, Path::new("target/debug/app"), false));
+        assert!(is_ignored(&ignore, Path::new("logs/run.log"), false));
+        assert!(is_ignored(&ignore, Path::new(".git/index"), false));
+        assert!(!is_ignored(&ignore, Path::new("src/main.rs"), false));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::folder_state;
+use crate::fs_watch::{FileChangeKind, FileChangedEvent, FsWatchState};
+use crate::project_files;
+
+/// Files indexed per folder.
+const INDEX_FILE_LIMIT: usize = 50_000;
+/// Unwatched folders are re-walked once their index is older than this.
+const UNWATCHED_INDEX_MAX_AGE: Duration = Duration::from_secs(30);
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL_CASE: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+const BONUS_BASENAME: i64 = 16;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FuzzyMatch {
+    pub path: String,
+    pub score: i64,
+    /// Character positions in `path` that matched the query, for highlighting.
+    pub indices: Vec<usize>,
+}
+
+struct FileIndex {
+    root: PathBuf,
+    files: Arc<Vec<String>>,
+    built_at: Instant,
+}
+
+/// In-memory file lists per folder, kept current by the file watcher.
+#[derive(Default)]
+pub struct FileIndexState {
+    indexes: HashMap<String, FileIndex>,
+}
+
+impl FileIndexState {
+    fn snapshot(&self, folder_id: &str, root: &Path, watched: bool) -> Option<Arc<Vec<String>>> {
+        let index = self.indexes.get(folder_id)?;
+        let fresh = watched || index.built_at.elapsed() < UNWATCHED_INDEX_MAX_AGE;
+        (index.root == *root && fresh).then(|| Arc::clone(&index.files))
+    }
+
+    fn apply_changes(&mut self, folder_id: &str, changes: &[FileChangedEvent]) {
+        if changes
+            .iter()
+            .any(|change| change.path.ends_with(".gitignore"))
+        {
+            // Ignore rules moved; the next query re-walks the folder.
+            self.indexes.remove(folder_id);
+            return;
+        }
+        let Some(index) = self.indexes.get_mut(folder_id) else {
+            return;
+        };
+
+        let files = Arc::make_mut(&mut index.files);
+        for change in changes {
+            match change.kind {
+                FileChangeKind::Created => {
+                    if index.root.join(&change.path).is_file() {
+                        if let Err(pos) = files.binary_search(&change.path) {
+                            files.insert(pos, change.path.clone());
+                        }
+                    }
+                }
+                FileChangeKind::Removed => {
+                    // The path may have been a directory.
+                    let prefix = format!("{}/", change.path);
+                    files.retain(|file| *file != change.path && !file.starts_with(&prefix));
+                }
+                FileChangeKind::Modified => {}
+            }
+        }
+    }
+}
+
+/// Called by the file watcher with each debounced batch of changes.
+pub fn apply_file_changes(
+    app_handle: &tauri::AppHandle,
+    folder_id: &str,
+    changes: &[FileChangedEvent],
+) {
+    if changes.is_empty() {
+        return;
+    }
+    if let Some(state) = app_handle.try_state::<std::sync::Mutex<FileIndexState>>() {
+        if let Ok(mut state) = state.lock() {
+            state.apply_changes(folder_id, changes);
+        }
+    }
+}
+
+/// Ranks `files` against `query`, best first.
+pub fn rank(files: &[String], query: &str, limit: usize) -> Vec<FuzzyMatch> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+
+    if query.is_empty() {
+        return files
+            .iter()
+            .take(limit)
+            .map(|path| FuzzyMatch {
+                path: path.clone(),
+                score: 0,
+                indices: Vec::new(),
+            })
+            .collect();
+    }
+
+    let mut matches: Vec<FuzzyMatch> = files
+        .iter()
+        .filter_map(|path| {
+            let (score, indices) = score(path, &query)?;
+            Some(FuzzyMatch {
+                path: path.clone(),
+                score,
+                indices,
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.path.len().cmp(&b.path.len()))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    matches.truncate(limit);
+    matches
+}
+
+/// Scores `candidate` against a lowercased query.
+///
+/// Finds the first complete subsequence match, then walks back from its end
+/// to the latest possible start so the matched window is as tight as
+/// possible, and scores that window.
+fn score(candidate: &str, query: &[char]) -> Option<(i64, Vec<usize>)> {
+    let chars: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    let mut end = None;
+    let mut qi = 0;
+    for (i, c) in lower.iter().enumerate() {
+        if *c == query[qi] {
+            qi += 1;
+            if qi == query.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    let mut start = end;
+    let mut qi = query.len();
+    for i in (0..=end).rev() {
+        if lower[i] == query[qi - 1] {
+            qi -= 1;
+            if qi == 0 {
+                start = i;
+                break;
+            }
+        }
+    }
+
+    let mut indices = Vec::with_capacity(query.len());
+    let mut qi = 0;
+    for (i, c) in lower.iter().enumerate().take(end + 1).skip(start) {
+        if qi < query.len() && *c == query[qi] {
+            indices.push(i);
+            qi += 1;
+        }
+    }
+
+    let basename_start = chars.iter().rposition(|c| *c == '/').map_or(0, |i| i + 1);
+    let mut total = 0;
+    let mut previous: Option<usize> = None;
+    for &pos in &indices {
+        total += SCORE_MATCH;
+        let before = pos.checked_sub(1).map(|i| chars[i]);
+        match before {
+            None | Some('/' | '_' | '-' | '.' | ' ') => total += BONUS_BOUNDARY,
+            Some(before) if before.is_lowercase() && chars[pos].is_uppercase() => {
+                total += BONUS_CAMEL_CASE
+            }
+            _ => {}
+        }
+        if let Some(previous) = previous {
+            let gap = pos - previous - 1;
+            if gap == 0 {
+                total += BONUS_CONSECUTIVE;
+            } else {
+                total -= PENALTY_GAP_START + (gap as i64 - 1) * PENALTY_GAP_EXTENSION;
+            }
+        }
+        previous = Some(pos);
+    }
+    if indices[0] >= basename_start {
+        total += BONUS_BASENAME;
+    }
+
+    Some((total, indices))
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn fuzzy_find_files(
+    state: tauri::State<'_, std::sync::Mutex<FileIndexState>>,
+    watch_state: tauri::State<'_, std::sync::Mutex<FsWatchState>>,
+    app_handle: tauri::AppHandle,
+    folder_id: String,
+    query: String,
+    limit: usize,
+) -> Result<Vec<FuzzyMatch>, String> {
+    let root = folder_state::resolve_folder_path(&app_handle, &folder_id)?;
+    let root = std::fs::canonicalize(&root).unwrap_or(root);
+    let watched = {
+        let watch_state = watch_state.lock().map_err(|e| e.to_string())?;
+        watch_state.watched_root(&folder_id) == Some(root.as_path())
+    };
+
+    let cached = {
+        let state = state.lock().map_err(|e| e.to_string())?;
+        state.snapshot(&folder_id, &root, watched)
+    };
+    let files = match cached {
+        Some(files) => files,
+        None => {
+            let walk_root = root.clone();
+            let listing = tauri::async_runtime::spawn_blocking(move || {
+                project_files::list_files(&walk_root, None, INDEX_FILE_LIMIT)
+            })
+            .await
+            .map_err(|e| format!("File index task failed: {e}"))??;
+            let mut files: Vec<String> = listing.files.into_iter().map(|file| file.path).collect();
+            // Watcher updates rely on binary search.
+            files.sort();
+            let files = Arc::new(files);
+            let mut state = state.lock().map_err(|e| e.to_string())?;
+            state.indexes.insert(
+                folder_id.clone(),
+                FileIndex {
+                    root,
+                    files: Arc::clone(&files),
+                    built_at: Instant::now(),
+                },
+            );
+            files
+        }
+    };
+
+    tauri::async_runtime::spawn_blocking(move || rank(&files, &query, limit))
+        .await
+        .map_err(|e| format!("Fuzzy search task failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn prefers_basename_and_boundary_matches() {
+        let files = paths(&["docs/maintenance/notes.md", "src/main.rs", "src/domain.rs"]);
+        let ranked = rank(&files, "main", 10);
+        assert_eq!(ranked[0].path, "src/main.rs");
+        assert_eq!(ranked[0].indices, vec![4, 5, 6, 7]);
+
+        let files = paths(&["src/fabric.rs", "src/foo_bar.rs"]);
+        assert_eq!(rank(&files, "fb", 10)[0].path, "src/foo_bar.rs");
+    }
+
+    #[test]
+    fn skips_non_matches_and_honors_limit() {
+        let files = paths(&["a.rs", "ab.rs", "abc.rs", "xyz.rs"]);
+        let ranked = rank(&files, "A B", 2);
+        assert_eq!(ranked.len(), 2);
+        assert!(ranked.iter().all(|m| m.path != "xyz.rs"));
+        assert_eq!(rank(&files, "", 3).len(), 3);
+    }
+
+    #[test]
+    fn watcher_changes_update_the_index() {
+        let mut state = FileIndexState::default();
+        state.indexes.insert(
+            "folder".to_string(),
+            FileIndex {
+                root: std::env::temp_dir(),
+                files: Arc::new(paths(&["a.rs", "old/x.rs", "old/y.rs"])),
+                built_at: Instant::now(),
+            },
+        );
+        state.apply_changes(
+            "folder",
+            &[FileChangedEvent {
+                folder_id: "folder".to_string(),
+                path: "old".to_string(),
+                kind: FileChangeKind::Removed,
+            }],
+        );
+        assert_eq!(*state.indexes["folder"].files, paths(&["a.rs"]));
+
+        state.apply_changes(
+            "folder",
+            &[FileChangedEvent {
+                folder_id: "folder".to_string(),
+                path: ".gitignore".to_string(),
+                kind: FileChangeKind::Modified,
+            }],
+        );
+        assert!(state.indexes.is_empty());
+    }
+}
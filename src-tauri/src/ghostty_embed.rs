@@ -3,15 +3,34 @@ use std::{
     collections::HashMap,
     ffi::CString,
     os::raw::{c_char, c_void},
+    path::{Path, PathBuf},
     ptr::{self, NonNull},
     sync::{
         atomic::{AtomicBool, Ordering},
         OnceLock,
     },
+    time::Duration,
 };
 
 use serde::{Deserialize, Serialize};
 
+use crate::app_config::TerminalConfig;
+use crate::app_paths;
+#[cfg(target_os = "macos")]
+use crate::ghostty_splits::PaneRect;
+use crate::ghostty_splits::{SplitDirection, SplitTree};
+#[cfg(target_os = "macos")]
+use crate::tmux_runtime::ShellKind;
+
+/// Directory under the app folder with named Ghostty configs.
+pub const GHOSTTY_PROFILES_DIR: &str = "ghostty-profiles";
+
+/// Longest frame animation `update_rect` runs; longer requests are capped.
+pub const MAX_FRAME_ANIMATION: Duration = Duration::from_secs(1);
+
+/// Most hidden terminals `[terminal] prewarm` may keep ready.
+pub const PREWARM_LIMIT: usize = 4;
+
 #[cfg(target_os = "macos")]
 use {
     block2::RcBlock,
@@ -19,10 +38,13 @@ use {
     objc2::rc::Retained,
     objc2::{declare_class, msg_send_id, mutability, ClassType, DeclaredClass},
     objc2_app_kit::{
-        NSEvent, NSEventModifierFlags, NSTrackingArea, NSTrackingAreaOptions, NSView,
+        NSEvent, NSEventModifierFlags, NSEventPhase, NSTrackingArea, NSTrackingAreaOptions, NSView,
         NSWindowOrderingMode,
     },
     objc2_foundation::{MainThreadMarker, NSPoint, NSRect, NSSize, NSTimer},
+    objc2_quartz_core::{
+        kCAMediaTimingFunctionEaseInEaseOut, CAMediaTimingFunction, CATransaction,
+    },
     raw_window_handle::{HasWindowHandle, RawWindowHandle},
     tauri::{Emitter, Manager, Window},
 };
@@ -64,7 +86,31 @@ pub struct GhosttyRect {
     #[serde(rename = "viewportHeight", default)]
     pub viewport_height: Option<f64>,
     #[serde(default)]
-    pub style: GhosttyStyle,
+    pub style: Option<GhosttyStyle>,
+}
+
+impl GhosttyRect {
+    /// Uses the `[terminal]` padding when no insets were set (none sent, or
+    /// all zero) and its corner radius when the sent radius is zero. The
+    /// frontend always reads a style from CSS, which is zero unless styled.
+    pub fn with_default_style(mut self, defaults: &TerminalConfig) -> Self {
+        let mut style = self.style.unwrap_or_default();
+        let insets = style.insets;
+        if insets.top == 0.0 && insets.right == 0.0 && insets.bottom == 0.0 && insets.left == 0.0 {
+            let padding = defaults.padding.unwrap_or(0.0);
+            style.insets = GhosttyInsets {
+                top: padding,
+                right: padding,
+                bottom: padding,
+                left: padding,
+            };
+        }
+        if style.corner_radius == 0.0 {
+            style.corner_radius = defaults.corner_radius.unwrap_or(0.0);
+        }
+        self.style = Some(style);
+        self
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -73,6 +119,20 @@ pub struct GhosttyOptions {
     pub font_size: Option<f32>,
     pub working_directory: Option<String>,
     pub command: Option<String>,
+    #[serde(default)]
+    pub theme: Option<String>,
+    #[serde(default)]
+    pub scrollback_limit: Option<u64>,
+    /// Treat Option as Alt instead of letting it compose characters.
+    #[serde(default)]
+    pub option_as_alt: Option<bool>,
+    /// Named Ghostty config from `ghostty-profiles/` in the app folder,
+    /// loaded on top of the user's own Ghostty config.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Load only the profile, skipping the user's own Ghostty config.
+    #[serde(default)]
+    pub profile_only: Option<bool>,
 }
 
 impl Default for GhosttyOptions {
@@ -81,20 +141,149 @@ impl Default for GhosttyOptions {
             font_size: None,
             working_directory: None,
             command: None,
+            theme: None,
+            scrollback_limit: None,
+            option_as_alt: None,
+            profile: None,
+            profile_only: None,
         }
     }
 }
 
+impl GhosttyOptions {
+    /// Fills unset options from the `[terminal]` section of config.toml.
+    pub fn with_defaults(mut self, defaults: &TerminalConfig) -> Self {
+        self.font_size = self.font_size.or(defaults.font_size);
+        self.theme = self.theme.or_else(|| defaults.theme.clone());
+        self.scrollback_limit = self.scrollback_limit.or(defaults.scrollback_limit);
+        self.option_as_alt = self.option_as_alt.or(Some(defaults.option_as_alt));
+        self
+    }
+
+    /// Config file of the selected profile, if any.
+    fn profile_file(&self, app_handle: &tauri::AppHandle) -> Result<Option<PathBuf>, String> {
+        let Some(name) = self
+            .profile
+            .as_deref()
+            .filter(|name| !name.trim().is_empty())
+        else {
+            return Ok(None);
+        };
+        profile_path(&profiles_dir(app_handle)?, name).map(Some)
+    }
+
+    /// Ghostty config lines layered on top of the user's own Ghostty config.
+    fn config_overrides(&self) -> String {
+        let mut overrides = String::new();
+        if let Some(theme) = self.theme.as_deref().map(str::trim) {
+            // Values are line-oriented; never let a theme name inject other keys.
+            if !theme.is_empty() && !theme.contains(['\n', '\r']) {
+                overrides.push_str(&format!("theme = {theme}\n"));
+            }
+        }
+        if let Some(limit) = self.scrollback_limit {
+            overrides.push_str(&format!("scrollback-limit = {limit}\n"));
+        }
+        overrides
+    }
+}
+
+/// Directory holding the named Ghostty configs terminals can select.
+pub fn profiles_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_paths::app_root_dir(app_handle)?.join(GHOSTTY_PROFILES_DIR))
+}
+
+/// Config file of the profile `name`: `<name>` or `<name>.conf` in `dir`.
+fn profile_path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(format!("Invalid Ghostty profile name '{name}'"));
+    }
+    [dir.join(name), dir.join(format!("{name}.conf"))]
+        .into_iter()
+        .find(|path| path.is_file())
+        .ok_or_else(|| format!("Ghostty profile '{name}' not found in '{}'", dir.display()))
+}
+
+/// Names of the profiles in `dir`, sorted, without the `.conf` extension.
+pub fn list_profiles(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| !name.starts_with('.'))
+        .map(|name| name.strip_suffix(".conf").unwrap_or(&name).to_string())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Options for `ghostty_run_command`.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GhosttyRunOptions {
+    /// Type the command without pressing Enter, leaving it on the prompt
+    /// for the user to review and run.
+    #[serde(default)]
+    pub confirm: bool,
+}
+
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct GhosttyFocusEvent {
     pub terminal_id: String,
+    /// Split pane within the terminal; the terminal's own id when unsplit.
+    pub pane_id: String,
+    pub focused: bool,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GhosttyFrame {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Where one terminal view is, for debugging the frontend layout.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GhosttyLayoutEntry {
+    pub id: String,
+    /// Terminal this is a split pane of; `id` itself when unsplit.
+    pub terminal_id: String,
+    /// Rect last sent by the frontend, in CSS pixels before insets.
+    pub requested: GhosttyFrame,
+    /// The view's frame in the window's content view, in points with a
+    /// bottom-left origin. While an animation runs this is where it ends.
+    pub frame: GhosttyFrame,
+    pub visible: bool,
     pub focused: bool,
+    /// A hidden terminal waiting in the prewarm pool.
+    pub prewarmed: bool,
+}
+
+/// A terminal's region divided into split panes. `rect` is the region as
+/// last placed by the frontend.
+struct SplitRegion {
+    tree: SplitTree,
+    rect: GhosttyRect,
 }
 
 #[derive(Default)]
 pub struct GhosttyManager {
     instances: HashMap<String, Box<GhosttyInstance>>,
+    /// Hidden terminals with a shell already started, handed out by `create`.
+    prewarmed: Vec<Box<GhosttyInstance>>,
+    next_prewarm_id: u64,
+    /// Split layouts, keyed by the id of the terminal whose region they divide.
+    splits: HashMap<String, SplitRegion>,
+    next_split_id: u64,
 }
 
 impl GhosttyManager {
@@ -113,8 +302,15 @@ impl GhosttyManager {
 
         #[cfg(target_os = "macos")]
         {
+            // `restore_workspace` may have created it before the view mounted.
             if self.instances.contains_key(&id) {
-                return Err(format!("Ghostty instance already exists: {id}"));
+                return self.update_rect(window, &id, rect, None);
+            }
+
+            if let Some(mut instance) = self.take_prewarmed(&options) {
+                instance.adopt(window, id.clone(), rect, options);
+                self.instances.insert(id, instance);
+                return Ok(());
             }
 
             let app_handle = window.app_handle().clone();
@@ -124,34 +320,268 @@ impl GhosttyManager {
         }
     }
 
+    /// Tops the pool of hidden terminals up to `count` (at most
+    /// [`PREWARM_LIMIT`]), or trims it. They are sized like `rect` and built
+    /// from `options`; ones built from other options are dropped.
+    pub fn prewarm(
+        &mut self,
+        window: &Window,
+        count: usize,
+        rect: GhosttyRect,
+        options: GhosttyOptions,
+    ) -> Result<(), String> {
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = (window, count, rect, options);
+            return Err("Ghostty embedding is only supported on macOS".to_string());
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let key = prewarm_key(&options);
+            self.prewarmed
+                .retain(|instance| instance.prewarm_key == key);
+            self.prewarmed.truncate(count.min(PREWARM_LIMIT));
+            while self.prewarmed.len() < count.min(PREWARM_LIMIT) {
+                self.next_prewarm_id += 1;
+                let id = format!("prewarm-{}", self.next_prewarm_id);
+                let app_handle = window.app_handle().clone();
+                let instance = GhosttyInstance::new(window, id, app_handle, rect, options.clone())?;
+                instance.view.setHidden(true);
+                self.prewarmed.push(instance);
+            }
+            Ok(())
+        }
+    }
+
+    /// A prewarmed terminal that can stand in for one created with `options`:
+    /// same settings and no custom command.
+    #[cfg(target_os = "macos")]
+    fn take_prewarmed(&mut self, options: &GhosttyOptions) -> Option<Box<GhosttyInstance>> {
+        if options.command.is_some() {
+            return None;
+        }
+        let key = prewarm_key(options);
+        let index = self
+            .prewarmed
+            .iter()
+            .position(|instance| instance.prewarm_key == key)?;
+        Some(self.prewarmed.swap_remove(index))
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.instances.contains_key(id)
+    }
+
+    /// Splits a terminal, opening a new pane on the `direction` side of the
+    /// pane `id` names, or of the terminal's focused pane when `id` is the
+    /// terminal. The new pane runs a shell like the one it was split from
+    /// and takes focus. Returns its id.
+    pub fn split(
+        &mut self,
+        window: &Window,
+        id: &str,
+        direction: SplitDirection,
+    ) -> Result<String, String> {
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = (window, id, direction);
+            return Err("Ghostty embedding is only supported on macOS".to_string());
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let region = self.region_of(id);
+            let target = self.pane_for(id);
+            let root = self
+                .instances
+                .get(&region)
+                .ok_or_else(|| format!("Ghostty instance not found: {region}"))?;
+            let rect = self
+                .splits
+                .get(&region)
+                .map_or(root.rect, |split| split.rect);
+            let hidden = root.view.isHidden();
+            let options = self
+                .instances
+                .get(&target)
+                .ok_or_else(|| format!("Ghostty instance not found: {target}"))?
+                .options
+                .clone();
+
+            self.next_split_id += 1;
+            let pane_id = format!("{region}:split-{}", self.next_split_id);
+            let app_handle = window.app_handle().clone();
+            let mut instance =
+                GhosttyInstance::new(window, pane_id.clone(), app_handle, rect, options)?;
+            instance.region = region.clone();
+            instance.view.setHidden(hidden);
+            self.instances.insert(pane_id.clone(), instance);
+
+            self.splits
+                .entry(region.clone())
+                .or_insert_with(|| SplitRegion {
+                    tree: SplitTree::new(&region),
+                    rect,
+                })
+                .tree
+                .split(&target, &pane_id, direction);
+            self.layout_region(window, &region, None);
+            if let Some(instance) = self.instances.get_mut(&pane_id) {
+                instance.set_focus(true);
+            }
+            Ok(pane_id)
+        }
+    }
+
+    /// Moves focus to the split pane on the `direction` side of the pane `id`
+    /// names (or of the terminal's focused pane). Returns the newly focused
+    /// pane, or `None` when there is no pane on that side.
+    pub fn focus_split(
+        &mut self,
+        id: &str,
+        direction: SplitDirection,
+    ) -> Result<Option<String>, String> {
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = (id, direction);
+            return Err("Ghostty embedding is only supported on macOS".to_string());
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let region = self.region_of(id);
+            let current = self.pane_for(id);
+            let Some(next) = self
+                .splits
+                .get(&region)
+                .and_then(|split| split.tree.neighbor(&current, direction))
+            else {
+                return Ok(None);
+            };
+            let instance = self
+                .instances
+                .get_mut(&next)
+                .ok_or_else(|| format!("Ghostty instance not found: {next}"))?;
+            instance.set_focus(true);
+            Ok(Some(next))
+        }
+    }
+
+    /// Terminal whose region contains pane `id`; `id` itself when unsplit.
+    #[cfg(target_os = "macos")]
+    fn region_of(&self, id: &str) -> String {
+        self.splits
+            .iter()
+            .find(|(_, split)| split.tree.contains(id))
+            .map_or_else(|| id.to_string(), |(region, _)| region.clone())
+    }
+
+    /// Pane meant by `id`: for a split terminal its most recently focused
+    /// pane, otherwise `id` itself.
+    #[cfg(target_os = "macos")]
+    fn pane_for(&self, id: &str) -> String {
+        let Some(split) = self.splits.get(id) else {
+            return id.to_string();
+        };
+        LAST_FOCUSED_TERMINAL
+            .with(|last| last.borrow().clone())
+            .filter(|last| split.tree.contains(last))
+            .unwrap_or_else(|| id.to_string())
+    }
+
+    /// Places every pane of a split terminal within its region.
+    #[cfg(target_os = "macos")]
+    fn layout_region(&mut self, window: &Window, region: &str, animation: Option<Duration>) {
+        let Some(split) = self.splits.get(region) else {
+            return;
+        };
+        let area = PaneRect {
+            x: split.rect.x,
+            y: split.rect.y,
+            width: split.rect.width,
+            height: split.rect.height,
+        };
+        for (pane, pane_rect) in split.tree.layout(area) {
+            if let Some(instance) = self.instances.get_mut(&pane) {
+                let rect = GhosttyRect {
+                    x: pane_rect.x,
+                    y: pane_rect.y,
+                    width: pane_rect.width,
+                    height: pane_rect.height,
+                    ..split.rect
+                };
+                instance.update_rect(window, rect, animation);
+            }
+        }
+    }
+
+    /// Moves a terminal, with all its split panes, to `rect`. With
+    /// `animation` the views slide there over that long instead of jumping.
     pub fn update_rect(
         &mut self,
         window: &Window,
         id: &str,
         rect: GhosttyRect,
+        animation: Option<Duration>,
     ) -> Result<(), String> {
         #[cfg(not(target_os = "macos"))]
         {
-            let _ = (window, id, rect);
+            let _ = (window, id, rect, animation);
             return Err("Ghostty embedding is only supported on macOS".to_string());
         }
 
         #[cfg(target_os = "macos")]
         {
+            if let Some(split) = self.splits.get_mut(id) {
+                split.rect = rect;
+                self.layout_region(window, id, animation);
+                return Ok(());
+            }
             let instance = self
                 .instances
                 .get_mut(id)
                 .ok_or_else(|| format!("Ghostty instance not found: {id}"))?;
-            instance.update_rect(window, rect);
+            instance.update_rect(window, rect, animation);
             Ok(())
         }
     }
 
-    pub fn destroy(&mut self, id: &str) -> Result<(), String> {
+    /// Destroys a terminal with all its split panes, or closes a single split
+    /// pane and gives its space to its sibling.
+    pub fn destroy(&mut self, window: &Window, id: &str) -> Result<(), String> {
+        if let Some(split) = self.splits.remove(id) {
+            for pane in split.tree.panes() {
+                self.instances.remove(&pane);
+            }
+            return Ok(());
+        }
         if self.instances.remove(id).is_none() {
             return Err(format!("Ghostty instance not found: {id}"));
         }
-        Ok(())
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = window;
+            Ok(())
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let region = self.region_of(id);
+            let Some(split) = self.splits.get_mut(&region) else {
+                return Ok(());
+            };
+            split.tree.remove(id);
+            if split.tree.panes().len() > 1 {
+                self.layout_region(window, &region, None);
+            } else if let Some(split) = self.splits.remove(&region) {
+                if let Some(root) = self.instances.get_mut(&region) {
+                    root.update_rect(window, split.rect, None);
+                }
+            }
+            Ok(())
+        }
     }
 
     pub fn set_visible(&mut self, id: &str, visible: bool) -> Result<(), String> {
@@ -163,11 +593,17 @@ impl GhosttyManager {
 
         #[cfg(target_os = "macos")]
         {
-            let instance = self
-                .instances
-                .get_mut(id)
-                .ok_or_else(|| format!("Ghostty instance not found: {id}"))?;
-            instance.view.setHidden(!visible);
+            let panes = match self.splits.get(id) {
+                Some(split) => split.tree.panes(),
+                None => vec![id.to_string()],
+            };
+            for pane in panes {
+                let instance = self
+                    .instances
+                    .get_mut(&pane)
+                    .ok_or_else(|| format!("Ghostty instance not found: {pane}"))?;
+                instance.view.setHidden(!visible);
+            }
             Ok(())
         }
     }
@@ -181,15 +617,81 @@ impl GhosttyManager {
 
         #[cfg(target_os = "macos")]
         {
+            let pane = self.pane_for(id);
             let instance = self
                 .instances
-                .get_mut(id)
-                .ok_or_else(|| format!("Ghostty instance not found: {id}"))?;
+                .get_mut(&pane)
+                .ok_or_else(|| format!("Ghostty instance not found: {pane}"))?;
             instance.set_focus(focused);
             Ok(())
         }
     }
 
+    /// Takes keyboard focus away from every terminal and gives it to the
+    /// webview.
+    pub fn focus_chat(&mut self) -> Result<(), String> {
+        #[cfg(not(target_os = "macos"))]
+        {
+            return Err("Ghostty embedding is only supported on macOS".to_string());
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            for instance in self.instances.values_mut() {
+                instance.set_focus(false);
+            }
+            Ok(())
+        }
+    }
+
+    /// Focuses the terminal that last had keyboard focus. Returns its id, or
+    /// `None` when no terminal has been focused or it has since been destroyed.
+    pub fn focus_last_used(&mut self) -> Result<Option<String>, String> {
+        #[cfg(not(target_os = "macos"))]
+        {
+            return Err("Ghostty embedding is only supported on macOS".to_string());
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let Some(id) = LAST_FOCUSED_TERMINAL.with(|last| last.borrow().clone()) else {
+                return Ok(None);
+            };
+            match self.instances.get_mut(&id) {
+                Some(instance) => {
+                    instance.set_focus(true);
+                    Ok(Some(id))
+                }
+                None => Ok(None),
+            }
+        }
+    }
+
+    /// Focuses the terminal and types `command` into it, followed by Enter
+    /// unless `options.confirm` asks the user to run it. A command waiting
+    /// for confirmation must fit on one line, or its first lines would run.
+    pub fn run_command(
+        &mut self,
+        id: &str,
+        command: &str,
+        options: GhosttyRunOptions,
+    ) -> Result<(), String> {
+        let command = command.trim_end_matches(['\n', '\r']);
+        if command.trim().is_empty() {
+            return Err("No command to run".to_string());
+        }
+        if options.confirm && command.contains(['\n', '\r']) {
+            return Err("Multi-line commands can't wait for confirmation".to_string());
+        }
+
+        self.focus(id, true)?;
+        if options.confirm {
+            self.write_text(id, command)
+        } else {
+            self.write_text(id, &format!("{command}\n"))
+        }
+    }
+
     pub fn write_text(&mut self, id: &str, text: &str) -> Result<(), String> {
         #[cfg(not(target_os = "macos"))]
         {
@@ -199,61 +701,75 @@ impl GhosttyManager {
 
         #[cfg(target_os = "macos")]
         {
+            let pane = self.pane_for(id);
             let instance = self
                 .instances
-                .get_mut(id)
-                .ok_or_else(|| format!("Ghostty instance not found: {id}"))?;
+                .get_mut(&pane)
+                .ok_or_else(|| format!("Ghostty instance not found: {pane}"))?;
+            instance.write_text(text);
+            Ok(())
+        }
+    }
 
-            // Split on \n and \r — send text segments via ghostty_surface_text
-            // and newlines as Enter keypresses via ghostty_surface_key.
-            let mut segment_start = 0;
-            for (i, ch) in text.char_indices() {
-                if ch == '\n' || ch == '\r' {
-                    if i > segment_start {
-                        let segment = &text[segment_start..i];
-                        unsafe {
-                            ghostty_surface_text(
-                                instance.ghostty_surface,
-                                segment.as_ptr() as *const _,
-                                segment.len(),
-                            );
-                        }
-                    }
-                    // macOS virtual keycode for Return = 0x24
-                    const VK_RETURN: u32 = 0x24;
-                    let key_event = ghostty_input_key_s {
-                        action: ghostty_input_action_e_GHOSTTY_ACTION_PRESS,
-                        mods: ghostty_input_mods_e_GHOSTTY_MODS_NONE,
-                        keycode: VK_RETURN,
-                        text: ptr::null(),
-                        composing: false,
-                    };
-                    unsafe {
-                        ghostty_surface_key(instance.ghostty_surface, key_event);
-                    }
-                    segment_start = i + ch.len_utf8();
-                }
-            }
-            // Send any remaining text after the last newline
-            if segment_start < text.len() {
-                let segment = &text[segment_start..];
-                unsafe {
-                    ghostty_surface_text(
-                        instance.ghostty_surface,
-                        segment.as_ptr() as *const _,
-                        segment.len(),
-                    );
-                }
-            }
+    /// Frames of every terminal view, prewarmed ones included, ordered by id.
+    pub fn layout_snapshot(&self) -> Result<Vec<GhosttyLayoutEntry>, String> {
+        #[cfg(not(target_os = "macos"))]
+        {
+            return Err("Ghostty embedding is only supported on macOS".to_string());
+        }
 
-            Ok(())
+        #[cfg(target_os = "macos")]
+        {
+            let mut entries: Vec<GhosttyLayoutEntry> = self
+                .instances
+                .values()
+                .map(|instance| instance.layout_entry(false))
+                .chain(
+                    self.prewarmed
+                        .iter()
+                        .map(|instance| instance.layout_entry(true)),
+                )
+                .collect();
+            entries.sort_by(|a, b| a.id.cmp(&b.id));
+            Ok(entries)
         }
     }
 }
 
+/// Options that must match for a prewarmed terminal to be adopted. The
+/// working directory is left out: it is applied on adoption.
+#[cfg(target_os = "macos")]
+fn prewarm_key(options: &GhosttyOptions) -> (Option<f32>, String, Option<String>, bool) {
+    (
+        options.font_size,
+        options.config_overrides(),
+        options.profile.clone(),
+        options.profile_only.unwrap_or(false),
+    )
+}
+
+#[cfg(target_os = "macos")]
+static GHOSTTY_INIT: OnceLock<Result<(), String>> = OnceLock::new();
+
+/// Outcome of the one-time `ghostty_init`, or `None` until the first
+/// terminal has been created.
+#[cfg(target_os = "macos")]
+pub fn init_status() -> Option<Result<(), String>> {
+    GHOSTTY_INIT.get().cloned()
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn init_status() -> Option<Result<(), String>> {
+    Some(Err(
+        "Embedded Ghostty terminals are only supported on macOS".to_string(),
+    ))
+}
+
 #[cfg(target_os = "macos")]
 thread_local! {
     static GHOSTTY_MANAGER: RefCell<GhosttyManager> = RefCell::new(GhosttyManager::default());
+    /// Terminal that most recently gained keyboard focus.
+    static LAST_FOCUSED_TERMINAL: RefCell<Option<String>> = const { RefCell::new(None) };
 }
 
 #[cfg(target_os = "macos")]
@@ -286,11 +802,20 @@ impl RuntimeFlags {
 #[cfg(target_os = "macos")]
 struct GhosttyInstance {
     id: String,
+    /// Terminal this is a split pane of; `id` itself for a terminal's first pane.
+    region: String,
+    /// [`prewarm_key`] of the options it was built from.
+    prewarm_key: (Option<f32>, String, Option<String>, bool),
+    /// Options it was built from, reused for panes split off it.
+    options: GhosttyOptions,
+    rect: GhosttyRect,
     app_handle: tauri::AppHandle,
     ghostty_app: ghostty_app_t,
     ghostty_surface: ghostty_surface_t,
     focused: bool,
     view: Retained<GhosttyView>,
+    /// Webview the view sits on, which takes keyboard input back on blur.
+    webview: Retained<NSView>,
     timer: Option<Retained<NSTimer>>,
     flags: RuntimeFlags,
 }
@@ -304,6 +829,7 @@ impl GhosttyInstance {
         rect: GhosttyRect,
         options: GhosttyOptions,
     ) -> Result<Box<Self>, String> {
+        let profile = options.profile_file(&app_handle)?;
         let (content_view, webview_view) = content_and_webview(window)?;
         let mtm = MainThreadMarker::new().ok_or("not on main thread")?;
 
@@ -324,19 +850,23 @@ impl GhosttyInstance {
         }
 
         let mut instance = Box::new(Self {
+            region: id.clone(),
             id,
+            prewarm_key: prewarm_key(&options),
+            options: options.clone(),
+            rect,
             app_handle,
             ghostty_app: ptr::null_mut(),
             ghostty_surface: ptr::null_mut(),
             focused: false,
             view,
+            webview: webview_view.clone(),
             timer: None,
             flags: RuntimeFlags::new(),
         });
 
         let instance_ptr = &mut *instance as *mut GhosttyInstance;
 
-        static GHOSTTY_INIT: OnceLock<Result<(), String>> = OnceLock::new();
         let init_result = GHOSTTY_INIT.get_or_init(|| {
             let res = unsafe { ghostty_init() };
             if res != GHOSTTY_SUCCESS as i32 {
@@ -355,9 +885,19 @@ impl GhosttyInstance {
         }
 
         unsafe {
-            ghostty_config_load_default_files(config);
-            ghostty_config_load_cli_args(config);
+            if !options.profile_only.unwrap_or(false) {
+                ghostty_config_load_default_files(config);
+                ghostty_config_load_cli_args(config);
+            }
+        }
+        if let Some(profile) = profile.as_ref() {
+            load_config_file(config, profile);
+        }
+        unsafe {
             ghostty_config_load_recursive_files(config);
+        }
+        load_config_overrides(config, &instance.id, &options.config_overrides());
+        unsafe {
             ghostty_config_finalize(config);
         }
 
@@ -418,7 +958,7 @@ impl GhosttyInstance {
         }
 
         instance.view.set_state_ptr(instance_ptr);
-        instance.update_rect(window, rect);
+        instance.update_rect(window, rect, None);
 
         let instance_ptr_for_timer = instance_ptr as usize;
         let tick_block: RcBlock<dyn Fn(NonNull<NSTimer>)> = RcBlock::new(move |_timer| {
@@ -434,6 +974,71 @@ impl GhosttyInstance {
         Ok(instance)
     }
 
+    /// Turns a prewarmed terminal into terminal `id`: shows it at `rect` and
+    /// moves its shell to `working_directory`. The command starts with a
+    /// space so shells ignoring such lines keep it out of history.
+    fn adopt(&mut self, window: &Window, id: String, rect: GhosttyRect, options: GhosttyOptions) {
+        self.region = id.clone();
+        self.id = id;
+        self.options = options;
+        self.update_rect(window, rect, None);
+        self.view.setHidden(false);
+        if let Some(dir) = self
+            .options
+            .working_directory
+            .clone()
+            .filter(|dir| !dir.is_empty())
+        {
+            let shell = ShellKind::from_program(&std::env::var("SHELL").unwrap_or_default());
+            self.write_text(&format!(" cd {} && clear\n", shell.quote(&dir)));
+        }
+    }
+
+    /// Sends text to the shell, with each \n or \r as an Enter keypress.
+    fn write_text(&mut self, text: &str) {
+        // Split on \n and \r — send text segments via ghostty_surface_text
+        // and newlines as Enter keypresses via ghostty_surface_key.
+        let mut segment_start = 0;
+        for (i, ch) in text.char_indices() {
+            if ch == '\n' || ch == '\r' {
+                if i > segment_start {
+                    let segment = &text[segment_start..i];
+                    unsafe {
+                        ghostty_surface_text(
+                            self.ghostty_surface,
+                            segment.as_ptr() as *const _,
+                            segment.len(),
+                        );
+                    }
+                }
+                // macOS virtual keycode for Return = 0x24
+                const VK_RETURN: u32 = 0x24;
+                let key_event = ghostty_input_key_s {
+                    action: ghostty_input_action_e_GHOSTTY_ACTION_PRESS,
+                    mods: ghostty_input_mods_e_GHOSTTY_MODS_NONE,
+                    keycode: VK_RETURN,
+                    text: ptr::null(),
+                    composing: false,
+                };
+                unsafe {
+                    ghostty_surface_key(self.ghostty_surface, key_event);
+                }
+                segment_start = i + ch.len_utf8();
+            }
+        }
+        // Send any remaining text after the last newline
+        if segment_start < text.len() {
+            let segment = &text[segment_start..];
+            unsafe {
+                ghostty_surface_text(
+                    self.ghostty_surface,
+                    segment.as_ptr() as *const _,
+                    segment.len(),
+                );
+            }
+        }
+    }
+
     fn tick(&mut self) {
         if self.flags.close_requested.swap(false, Ordering::AcqRel) {
             if let Some(window) = self.view.window() {
@@ -449,18 +1054,22 @@ impl GhosttyInstance {
         }
     }
 
-    fn update_rect(&mut self, window: &Window, rect: GhosttyRect) {
+    fn update_rect(&mut self, window: &Window, rect: GhosttyRect, animation: Option<Duration>) {
+        self.rect = rect;
         let (content_view, webview_view) = match content_and_webview(window) {
             Ok(tuple) => tuple,
             Err(_) => return,
         };
 
         let frame = rect_to_frame(&content_view, &webview_view, rect);
-        unsafe {
-            self.view.setFrame(frame);
+        match animation.filter(|duration| !duration.is_zero()) {
+            Some(duration) => self.animate_frame(frame, duration.min(MAX_FRAME_ANIMATION)),
+            None => unsafe {
+                self.view.setFrame(frame);
+            },
         }
 
-        self.apply_style(rect.style);
+        self.apply_style(rect.style.unwrap_or_default());
 
         // Use the window's backing scale factor directly (matches the working
         // standalone implementation). Avoids potential issues with
@@ -476,6 +1085,49 @@ impl GhosttyInstance {
         }
     }
 
+    /// Slides the view to `frame` in one Core Animation transaction. AppKit
+    /// suppresses implicit animations of a view's backing layer, so they are
+    /// allowed for the duration of the change. The surface is resized to the
+    /// final size right away.
+    fn animate_frame(&self, frame: NSRect, duration: Duration) {
+        unsafe {
+            CATransaction::begin();
+            CATransaction::setAnimationDuration(duration.as_secs_f64());
+            let timing =
+                CAMediaTimingFunction::functionWithName(kCAMediaTimingFunctionEaseInEaseOut);
+            CATransaction::setAnimationTimingFunction(Some(&timing));
+            let context: Retained<objc2::runtime::AnyObject> =
+                msg_send_id![objc2::class!(NSAnimationContext), currentContext];
+            let _: () = objc2::msg_send![&context, setAllowsImplicitAnimation: true];
+            self.view.setFrame(frame);
+            let _: () = objc2::msg_send![&context, setAllowsImplicitAnimation: false];
+            CATransaction::commit();
+        }
+    }
+
+    fn layout_entry(&self, prewarmed: bool) -> GhosttyLayoutEntry {
+        let frame = self.view.frame();
+        GhosttyLayoutEntry {
+            id: self.id.clone(),
+            terminal_id: self.region.clone(),
+            requested: GhosttyFrame {
+                x: self.rect.x,
+                y: self.rect.y,
+                width: self.rect.width,
+                height: self.rect.height,
+            },
+            frame: GhosttyFrame {
+                x: frame.origin.x,
+                y: frame.origin.y,
+                width: frame.size.width,
+                height: frame.size.height,
+            },
+            visible: !self.view.isHidden(),
+            focused: self.focused,
+            prewarmed,
+        }
+    }
+
     fn apply_style(&self, style: GhosttyStyle) {
         let corner = style.corner_radius.max(0.0);
         self.view.setWantsLayer(true);
@@ -491,6 +1143,7 @@ impl GhosttyInstance {
             // Re-assert focus on every explicit focus request so we can recover
             // from stale focus state between AppKit and our tracked flag.
             self.focused = true;
+            LAST_FOCUSED_TERMINAL.with(|last| *last.borrow_mut() = Some(self.id.clone()));
             unsafe {
                 ghostty_surface_set_focus(self.ghostty_surface, true);
                 ghostty_app_set_focus(self.ghostty_app, true);
@@ -502,10 +1155,14 @@ impl GhosttyInstance {
             let _ = self.app_handle.emit(
                 "ghostty-focus",
                 &GhosttyFocusEvent {
-                    terminal_id: self.id.clone(),
+                    terminal_id: self.region.clone(),
+                    pane_id: self.id.clone(),
                     focused: true,
                 },
             );
+            crate::focus::update(&self.app_handle, |focus| {
+                focus.terminal_focused(&self.region, &self.id)
+            });
             return;
         }
 
@@ -517,30 +1174,60 @@ impl GhosttyInstance {
             ghostty_surface_set_focus(self.ghostty_surface, false);
             ghostty_app_set_focus(self.ghostty_app, false);
         }
+        // An explicit blur must also hand AppKit's first responder back to
+        // the webview, or key events keep arriving here.
+        if let Some(window) = self.view.window() {
+            let responder = self.view.as_super().as_super();
+            if window
+                .firstResponder()
+                .is_some_and(|current| ptr::eq(&*current, responder))
+            {
+                window.makeFirstResponder(Some(self.webview.as_super()));
+            }
+        }
         let _ = self.app_handle.emit(
             "ghostty-focus",
             &GhosttyFocusEvent {
-                terminal_id: self.id.clone(),
+                terminal_id: self.region.clone(),
+                pane_id: self.id.clone(),
                 focused: false,
             },
         );
+        crate::focus::update(&self.app_handle, |focus| focus.terminal_blurred(&self.id));
     }
 
+    /// Sends a key with the text the current keyboard layout produces for
+    /// it. Ghostty maps the physical keycode itself, but bindings and
+    /// control sequences follow the layout's character, so on AZERTY ctrl+A
+    /// is sent as ctrl+a even though the key sits where US has Q.
     fn handle_key(&mut self, event: &NSEvent, action: ghostty_input_action_e) {
-        let mods = mods_from_event(event);
+        let mut mods = mods_from_event(event);
         let keycode = unsafe { event.keyCode() } as u32;
+        let flags = unsafe { event.modifierFlags() };
+        let option_as_alt = self.options.option_as_alt.unwrap_or(false);
+
+        let chars = if flags.contains(NSEventModifierFlags::NSEventModifierFlagCommand) {
+            // Command shortcuts carry no text.
+            None
+        } else if flags.contains(NSEventModifierFlags::NSEventModifierFlagControl)
+            || (option_as_alt && flags.contains(NSEventModifierFlags::NSEventModifierFlagOption))
+        {
+            // The layout's base character; Ghostty applies ctrl/alt to it.
+            unsafe { event.charactersIgnoringModifiers() }
+        } else {
+            if flags.contains(NSEventModifierFlags::NSEventModifierFlagOption) {
+                // Option already composed the text (e.g. @ on many European
+                // layouts); passing alt too would prefix it with ESC.
+                mods &= !ghostty_input_mods_e_GHOSTTY_MODS_ALT;
+            }
+            unsafe { event.characters() }
+        };
 
         let mut text_ptr: *const c_char = ptr::null();
-        let flags = unsafe { event.modifierFlags() };
-        let allow_text = !flags.contains(NSEventModifierFlags::NSEventModifierFlagCommand)
-            && !flags.contains(NSEventModifierFlags::NSEventModifierFlagControl);
-
-        if allow_text {
-            if let Some(chars) = unsafe { event.characters() } {
-                let utf8 = chars.UTF8String();
-                if !utf8.is_null() {
-                    text_ptr = utf8;
-                }
+        if let Some(chars) = chars.filter(|chars| key_text_is_printable(&chars.to_string())) {
+            let utf8 = chars.UTF8String();
+            if !utf8.is_null() {
+                text_ptr = utf8;
             }
         }
 
@@ -555,6 +1242,9 @@ impl GhosttyInstance {
         unsafe {
             ghostty_surface_key(self.ghostty_surface, key_event);
         }
+        if action != ghostty_input_action_e_GHOSTTY_ACTION_RELEASE {
+            crate::folder_state::note_terminal_activity(&self.app_handle, &self.region);
+        }
     }
 
     fn handle_mouse_button(
@@ -580,11 +1270,22 @@ impl GhosttyInstance {
     }
 
     fn handle_scroll(&mut self, event: &NSEvent) {
-        let mods = mods_from_event(event);
-        let dx = unsafe { event.scrollingDeltaX() } as f64;
-        let dy = unsafe { event.scrollingDeltaY() } as f64;
+        let mut dx = unsafe { event.scrollingDeltaX() } as f64;
+        let mut dy = unsafe { event.scrollingDeltaY() } as f64;
+        let precise = unsafe { event.hasPreciseScrollingDeltas() };
+        if precise {
+            // Trackpad deltas are in points and feel sluggish as-is; native
+            // Ghostty doubles them too.
+            dx *= 2.0;
+            dy *= 2.0;
+        }
         unsafe {
-            ghostty_surface_mouse_scroll(self.ghostty_surface, dx, dy, mods as i32);
+            ghostty_surface_mouse_scroll(
+                self.ghostty_surface,
+                dx,
+                dy,
+                scroll_mods(precise, event.momentumPhase()),
+            );
         }
     }
 
@@ -610,6 +1311,9 @@ impl Drop for GhosttyInstance {
                 timer.invalidate();
             }
             self.view.removeFromSuperview();
+        }
+        crate::focus::update(&self.app_handle, |focus| focus.terminal_blurred(&self.id));
+        unsafe {
             ghostty_surface_free(self.ghostty_surface);
             ghostty_app_free(self.ghostty_app);
         }
@@ -819,9 +1523,37 @@ fn content_and_webview(window: &Window) -> Result<(Retained<NSView>, Retained<NS
     Ok((content_view, webview_retained))
 }
 
+/// libghostty only reads config from files, so overrides go through a
+/// short-lived file that is removed as soon as it has been loaded.
+#[cfg(target_os = "macos")]
+fn load_config_file(config: ghostty_config_t, path: &Path) {
+    if let Ok(c_path) = CString::new(path.to_string_lossy().as_bytes()) {
+        unsafe {
+            ghostty_config_load_file(config, c_path.as_ptr());
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn load_config_overrides(config: ghostty_config_t, id: &str, overrides: &str) {
+    if overrides.is_empty() {
+        return;
+    }
+    let path = std::env::temp_dir().join(format!("neoai-ghostty-{}-{id}.conf", std::process::id()));
+    if let Err(err) = std::fs::write(&path, overrides) {
+        log::warn!(
+            "Failed to write Ghostty overrides '{}': {err}",
+            path.display()
+        );
+        return;
+    }
+    load_config_file(config, &path);
+    let _ = std::fs::remove_file(&path);
+}
+
 #[cfg(target_os = "macos")]
 fn rect_to_frame(content_view: &NSView, webview_view: &NSView, rect: GhosttyRect) -> NSRect {
-    let insets = rect.style.insets;
+    let insets = rect.style.unwrap_or_default().insets;
     let mut width = rect.width - (insets.left + insets.right);
     let mut height = rect.height - (insets.top + insets.bottom);
     width = width.max(1.0);
@@ -872,6 +1604,40 @@ fn backing_scale_factor(view: &NSView) -> f64 {
     }
 }
 
+/// Whether AppKit's text for a key is something to type. Control
+/// characters are left for Ghostty to encode from the keycode and mods, and
+/// function keys arrive as private-use characters (U+F700..U+F8FF).
+#[cfg(target_os = "macos")]
+fn key_text_is_printable(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| !c.is_control() && !('\u{F700}'..='\u{F8FF}').contains(&c))
+}
+
+/// Packs scroll mods the way libghostty reads them: bit 0 flags precise
+/// (trackpad) deltas and the bits above it hold the momentum phase, which
+/// lets Ghostty tell a flick's coasting apart from the finger still moving.
+#[cfg(target_os = "macos")]
+fn scroll_mods(precise: bool, momentum: NSEventPhase) -> ghostty_input_scroll_mods_t {
+    let momentum = if momentum == NSEventPhase::NSEventPhaseBegan {
+        ghostty_input_mouse_momentum_e_GHOSTTY_MOUSE_MOMENTUM_BEGAN
+    } else if momentum == NSEventPhase::NSEventPhaseStationary {
+        ghostty_input_mouse_momentum_e_GHOSTTY_MOUSE_MOMENTUM_STATIONARY
+    } else if momentum == NSEventPhase::NSEventPhaseChanged {
+        ghostty_input_mouse_momentum_e_GHOSTTY_MOUSE_MOMENTUM_CHANGED
+    } else if momentum == NSEventPhase::NSEventPhaseEnded {
+        ghostty_input_mouse_momentum_e_GHOSTTY_MOUSE_MOMENTUM_ENDED
+    } else if momentum == NSEventPhase::NSEventPhaseCancelled {
+        ghostty_input_mouse_momentum_e_GHOSTTY_MOUSE_MOMENTUM_CANCELLED
+    } else if momentum == NSEventPhase::NSEventPhaseMayBegin {
+        ghostty_input_mouse_momentum_e_GHOSTTY_MOUSE_MOMENTUM_MAY_BEGIN
+    } else {
+        ghostty_input_mouse_momentum_e_GHOSTTY_MOUSE_MOMENTUM_NONE
+    };
+    (precise as ghostty_input_scroll_mods_t) | ((momentum as ghostty_input_scroll_mods_t) << 1)
+}
+
 #[cfg(target_os = "macos")]
 fn mods_from_event(event: &NSEvent) -> ghostty_input_mods_e {
     let flags = unsafe { event.modifierFlags() };
@@ -0,0 +1,332 @@
+//! Layout of embedded terminals split inside one terminal's region. The
+//! region is a binary tree: every split halves its area between the pane
+//! that was split and the new one.
+
+use serde::Deserialize;
+
+/// Side of the current pane a split opens on or focus moves to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SplitDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl SplitDirection {
+    fn horizontal(self) -> bool {
+        matches!(self, Self::Left | Self::Right)
+    }
+}
+
+/// Area in webview coordinates, with `y` growing downwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaneRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl PaneRect {
+    fn right(&self) -> f64 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> f64 {
+        self.y + self.height
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Pane(String),
+    Split {
+        /// Children side by side rather than stacked.
+        horizontal: bool,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    fn is_pane(&self, pane: &str) -> bool {
+        matches!(self, Node::Pane(id) if id == pane)
+    }
+
+    fn split(&mut self, pane: &str, new_pane: &str, direction: SplitDirection) -> bool {
+        match self {
+            Node::Pane(id) if id == pane => {
+                let existing = Node::Pane(id.clone());
+                let added = Node::Pane(new_pane.to_string());
+                let (first, second) = match direction {
+                    SplitDirection::Left | SplitDirection::Up => (added, existing),
+                    SplitDirection::Right | SplitDirection::Down => (existing, added),
+                };
+                *self = Node::Split {
+                    horizontal: direction.horizontal(),
+                    first: Box::new(first),
+                    second: Box::new(second),
+                };
+                true
+            }
+            Node::Pane(_) => false,
+            Node::Split { first, second, .. } => {
+                first.split(pane, new_pane, direction) || second.split(pane, new_pane, direction)
+            }
+        }
+    }
+
+    /// Removes `pane`, giving its area to its sibling.
+    fn remove(&mut self, pane: &str) -> bool {
+        let Node::Split { first, second, .. } = self else {
+            return false;
+        };
+        let sibling = if first.is_pane(pane) {
+            Some(std::mem::replace(
+                second.as_mut(),
+                Node::Pane(String::new()),
+            ))
+        } else if second.is_pane(pane) {
+            Some(std::mem::replace(first.as_mut(), Node::Pane(String::new())))
+        } else {
+            None
+        };
+        if let Some(sibling) = sibling {
+            *self = sibling;
+            return true;
+        }
+        first.remove(pane) || second.remove(pane)
+    }
+
+    fn layout(&self, area: PaneRect, out: &mut Vec<(String, PaneRect)>) {
+        match self {
+            Node::Pane(id) => out.push((id.clone(), area)),
+            Node::Split {
+                horizontal,
+                first,
+                second,
+            } => {
+                let (a, b) = if *horizontal {
+                    let half = (area.width / 2.0).floor();
+                    (
+                        PaneRect {
+                            width: half,
+                            ..area
+                        },
+                        PaneRect {
+                            x: area.x + half,
+                            width: area.width - half,
+                            ..area
+                        },
+                    )
+                } else {
+                    let half = (area.height / 2.0).floor();
+                    (
+                        PaneRect {
+                            height: half,
+                            ..area
+                        },
+                        PaneRect {
+                            y: area.y + half,
+                            height: area.height - half,
+                            ..area
+                        },
+                    )
+                };
+                first.layout(a, out);
+                second.layout(b, out);
+            }
+        }
+    }
+}
+
+/// Panes sharing one terminal's region.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitTree {
+    root: Node,
+}
+
+impl SplitTree {
+    /// A region holding only `pane`.
+    pub fn new(pane: &str) -> Self {
+        Self {
+            root: Node::Pane(pane.to_string()),
+        }
+    }
+
+    pub fn contains(&self, pane: &str) -> bool {
+        self.panes().iter().any(|id| id == pane)
+    }
+
+    /// Pane ids, left to right and top to bottom.
+    pub fn panes(&self) -> Vec<String> {
+        let unit = PaneRect {
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+        };
+        self.layout(unit).into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Splits `pane`, placing `new_pane` on its `direction` side. Returns
+    /// `false` when `pane` isn't in the region.
+    pub fn split(&mut self, pane: &str, new_pane: &str, direction: SplitDirection) -> bool {
+        self.root.split(pane, new_pane, direction)
+    }
+
+    /// Removes `pane` and lets its sibling take its place. The last pane
+    /// can't be removed.
+    pub fn remove(&mut self, pane: &str) -> bool {
+        self.root.remove(pane)
+    }
+
+    /// Area of each pane when the region covers `area`.
+    pub fn layout(&self, area: PaneRect) -> Vec<(String, PaneRect)> {
+        let mut panes = Vec::new();
+        self.root.layout(area, &mut panes);
+        panes
+    }
+
+    /// The pane next to `pane` on its `direction` side: the nearest one that
+    /// overlaps it along the other axis, preferring the one lined up with
+    /// its center.
+    pub fn neighbor(&self, pane: &str, direction: SplitDirection) -> Option<String> {
+        let area = PaneRect {
+            x: 0.0,
+            y: 0.0,
+            width: 1000.0,
+            height: 1000.0,
+        };
+        let panes = self.layout(area);
+        let (_, current) = panes.iter().find(|(id, _)| id == pane)?;
+        let center_x = current.x + current.width / 2.0;
+        let center_y = current.y + current.height / 2.0;
+
+        panes
+            .iter()
+            .filter(|(id, _)| id != pane)
+            .filter_map(|(id, rect)| {
+                let overlaps_rows = rect.y < current.bottom() && rect.bottom() > current.y;
+                let overlaps_columns = rect.x < current.right() && rect.right() > current.x;
+                let (gap, offset) = match direction {
+                    SplitDirection::Left if overlaps_rows => (
+                        current.x - rect.right(),
+                        (rect.y + rect.height / 2.0 - center_y).abs(),
+                    ),
+                    SplitDirection::Right if overlaps_rows => (
+                        rect.x - current.right(),
+                        (rect.y + rect.height / 2.0 - center_y).abs(),
+                    ),
+                    SplitDirection::Up if overlaps_columns => (
+                        current.y - rect.bottom(),
+                        (rect.x + rect.width / 2.0 - center_x).abs(),
+                    ),
+                    SplitDirection::Down if overlaps_columns => (
+                        rect.y - current.bottom(),
+                        (rect.x + rect.width / 2.0 - center_x).abs(),
+                    ),
+                    _ => return None,
+                };
+                (gap >= 0.0).then_some((gap, offset, id))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)))
+            .map(|(_, _, id)| id.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area() -> PaneRect {
+        PaneRect {
+            x: 10.0,
+            y: 20.0,
+            width: 800.0,
+            height: 600.0,
+        }
+    }
+
+    #[test]
+    fn splits_halve_the_pane_that_was_split() {
+        let mut tree = SplitTree::new("t1");
+        assert!(tree.split("t1", "t1:2", SplitDirection::Right));
+        assert!(tree.split("t1:2", "t1:3", SplitDirection::Up));
+        assert!(!tree.split("missing", "t1:4", SplitDirection::Down));
+
+        let layout = tree.layout(area());
+        assert_eq!(
+            layout,
+            vec![
+                (
+                    "t1".to_string(),
+                    PaneRect {
+                        x: 10.0,
+                        y: 20.0,
+                        width: 400.0,
+                        height: 600.0
+                    }
+                ),
+                (
+                    "t1:3".to_string(),
+                    PaneRect {
+                        x: 410.0,
+                        y: 20.0,
+                        width: 400.0,
+                        height: 300.0
+                    }
+                ),
+                (
+                    "t1:2".to_string(),
+                    PaneRect {
+                        x: 410.0,
+                        y: 320.0,
+                        width: 400.0,
+                        height: 300.0
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_neighbors_in_each_direction() {
+        let mut tree = SplitTree::new("a");
+        tree.split("a", "b", SplitDirection::Right);
+        tree.split("b", "c", SplitDirection::Down);
+
+        assert_eq!(
+            tree.neighbor("a", SplitDirection::Right).as_deref(),
+            Some("b")
+        );
+        assert_eq!(
+            tree.neighbor("c", SplitDirection::Left).as_deref(),
+            Some("a")
+        );
+        assert_eq!(
+            tree.neighbor("b", SplitDirection::Down).as_deref(),
+            Some("c")
+        );
+        assert_eq!(tree.neighbor("c", SplitDirection::Up).as_deref(), Some("b"));
+        assert_eq!(tree.neighbor("a", SplitDirection::Left), None);
+        assert_eq!(tree.neighbor("b", SplitDirection::Up), None);
+    }
+
+    #[test]
+    fn removing_a_pane_gives_its_area_to_the_sibling() {
+        let mut tree = SplitTree::new("a");
+        tree.split("a", "b", SplitDirection::Down);
+        tree.split("b", "c", SplitDirection::Right);
+
+        assert!(tree.remove("b"));
+        assert_eq!(tree.panes(), vec!["a", "c"]);
+        assert_eq!(tree.layout(area())[1].1.width, 800.0);
+
+        assert!(tree.remove("c"));
+        assert_eq!(tree, SplitTree::new("a"));
+        assert!(!tree.remove("a"));
+    }
+}
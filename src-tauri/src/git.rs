@@ -0,0 +1,670 @@
+use std::path::{Component, Path};
+use std::process::Stdio;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::folder_state::{self, FolderStateStore};
+
+// -- Serializable types for IPC --
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitFileStatus {
+    pub path: String,
+    /// Source path of a rename or copy.
+    pub orig_path: Option<String>,
+    /// Porcelain status letter for the index (`.` = unmodified, `?` = untracked).
+    pub index_status: String,
+    /// Porcelain status letter for the worktree.
+    pub worktree_status: String,
+    pub conflicted: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitStatus {
+    /// `None` when HEAD is detached.
+    pub branch: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    pub files: Vec<GitFileStatus>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GitDiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiffLine {
+    pub kind: GitDiffLineKind,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiffHunk {
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    /// Text after the closing `@@`, usually the enclosing function.
+    pub header: String,
+    pub lines: Vec<GitDiffLine>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitFileDiff {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub binary: bool,
+    pub hunks: Vec<GitDiffHunk>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiff {
+    pub files: Vec<GitFileDiff>,
+    pub additions: u32,
+    pub deletions: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommitResult {
+    pub sha: String,
+    pub branch: Option<String>,
+    /// First line of the commit message.
+    pub summary: String,
+    /// Whether `commit.gpgsign` was in effect for this commit.
+    pub signed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderBranchChangedEvent {
+    pub folder_id: String,
+    pub branch: Option<String>,
+}
+
+// -- git invocation --
+
+async fn run_git(cwd: &Path, args: &[&str]) -> Result<String, String> {
+    run_git_with_input(cwd, args, None).await
+}
+
+async fn run_git_with_input(
+    cwd: &Path,
+    args: &[&str],
+    input: Option<&str>,
+) -> Result<String, String> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(cwd)
+        .args(["-c", "core.quotePath=false"])
+        .args(args)
+        // Read-only queries must not take index.lock away from the user's own git.
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute git {}: {e}", args.join(" ")))?;
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin
+            .write_all(input.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write to git {}: {e}", args.join(" ")))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("Failed to execute git {}: {e}", args.join(" ")))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let reason = if stderr.is_empty() {
+            format!("exit status {}", output.status)
+        } else {
+            stderr
+        };
+        Err(format!("git {} failed: {reason}", args.join(" ")))
+    }
+}
+
+pub async fn status(cwd: &Path) -> Result<GitStatus, String> {
+    let output = run_git(cwd, &["status", "--porcelain=v2", "--branch", "-z"]).await?;
+    Ok(parse_status(&output))
+}
+
+pub async fn diff(cwd: &Path, staged: bool) -> Result<GitDiff, String> {
+    let mut args = vec!["diff", "--no-color", "--no-ext-diff", "-M"];
+    if staged {
+        args.push("--cached");
+    }
+    let output = run_git(cwd, &args).await?;
+    Ok(parse_diff(&output))
+}
+
+/// Unstaged changes to a single file. Runs from the file's directory, so the
+/// file doesn't need to be in a registered folder.
+pub async fn file_diff(path: &Path) -> Result<Vec<GitDiffHunk>, String> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| format!("{} has no parent directory", path.display()))?;
+    let path = path.to_string_lossy();
+    let output = run_git(
+        dir,
+        &["diff", "--no-color", "--no-ext-diff", "--", path.as_ref()],
+    )
+    .await?;
+    Ok(parse_diff(&output)
+        .files
+        .pop()
+        .map(|file| file.hunks)
+        .unwrap_or_default())
+}
+
+/// Current branch name, or `None` for a detached HEAD.
+pub async fn current_branch(cwd: &Path) -> Result<Option<String>, String> {
+    match run_git(cwd, &["symbolic-ref", "--quiet", "--short", "HEAD"]).await {
+        Ok(output) => Ok(Some(output.trim().to_string())),
+        Err(err) => {
+            // symbolic-ref also fails outside a repository; only a detached
+            // HEAD inside one should read as "no branch".
+            run_git(cwd, &["rev-parse", "--git-dir"])
+                .await
+                .map_err(|_| err)?;
+            Ok(None)
+        }
+    }
+}
+
+pub async fn stage(cwd: &Path, paths: &[String]) -> Result<GitStatus, String> {
+    validate_paths(paths)?;
+    let mut args = vec!["--literal-pathspecs", "add", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    run_git(cwd, &args).await?;
+    status(cwd).await
+}
+
+pub async fn unstage(cwd: &Path, paths: &[String]) -> Result<GitStatus, String> {
+    validate_paths(paths)?;
+    let mut args = vec!["--literal-pathspecs", "restore", "--staged", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    run_git(cwd, &args).await?;
+    status(cwd).await
+}
+
+/// Commits the current index. Hooks and signing follow the user's git config;
+/// nothing is overridden on the command line.
+pub async fn commit(cwd: &Path, message: &str) -> Result<GitCommitResult, String> {
+    let message = message.trim();
+    if message.is_empty() {
+        return Err("Commit message is empty".to_string());
+    }
+
+    let status = status(cwd).await?;
+    if let Some(reason) = commit_blocker(&status) {
+        return Err(reason);
+    }
+
+    run_git_with_input(cwd, &["commit", "--file=-"], Some(message)).await?;
+
+    let sha = run_git(cwd, &["rev-parse", "HEAD"])
+        .await?
+        .trim()
+        .to_string();
+    let signed = run_git(cwd, &["config", "--bool", "commit.gpgsign"])
+        .await
+        .map(|value| value.trim() == "true")
+        .unwrap_or(false);
+
+    Ok(GitCommitResult {
+        sha,
+        branch: status.branch,
+        summary: message.lines().next().unwrap_or_default().to_string(),
+        signed,
+    })
+}
+
+/// Rejects absolute paths and paths escaping the folder.
+fn validate_paths(paths: &[String]) -> Result<(), String> {
+    if paths.is_empty() {
+        return Err("No paths given".to_string());
+    }
+    for path in paths {
+        let escapes = Path::new(path)
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+        if path.trim().is_empty() || escapes {
+            return Err(format!("Path must be relative to the folder: {path}"));
+        }
+    }
+    Ok(())
+}
+
+/// Why the index can't be committed as-is, if anything.
+fn commit_blocker(status: &GitStatus) -> Option<String> {
+    if let Some(file) = status.files.iter().find(|file| file.conflicted) {
+        return Some(format!(
+            "Resolve merge conflicts before committing: {}",
+            file.path
+        ));
+    }
+    let staged = status
+        .files
+        .iter()
+        .any(|file| file.index_status != "." && file.index_status != "?");
+    if !staged {
+        return Some("Nothing staged to commit".to_string());
+    }
+    None
+}
+
+// -- Parsing --
+
+/// Parses `git status --porcelain=v2 --branch -z`.
+fn parse_status(output: &str) -> GitStatus {
+    let mut status = GitStatus::default();
+    let mut entries = output.split('\0').filter(|entry| !entry.is_empty());
+
+    while let Some(entry) = entries.next() {
+        if let Some(header) = entry.strip_prefix("# ") {
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+            match key {
+                "branch.head" => {
+                    status.branch = (value != "(detached)").then(|| value.to_string());
+                }
+                "branch.upstream" => status.upstream = Some(value.to_string()),
+                "branch.ab" => {
+                    for part in value.split_whitespace() {
+                        if let Some(count) = part.strip_prefix('+') {
+                            status.ahead = count.parse().unwrap_or(0);
+                        } else if let Some(count) = part.strip_prefix('-') {
+                            status.behind = count.parse().unwrap_or(0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        // Field counts per entry type, the path always being the last field.
+        let (field_count, conflicted) = match entry.as_bytes()[0] {
+            b'1' => (9, false),
+            b'2' => (10, false),
+            b'u' => (11, true),
+            b'?' => {
+                status.files.push(GitFileStatus {
+                    path: entry[2..].to_string(),
+                    orig_path: None,
+                    index_status: "?".to_string(),
+                    worktree_status: "?".to_string(),
+                    conflicted: false,
+                });
+                continue;
+            }
+            _ => continue,
+        };
+
+        let fields: Vec<&str> = entry.splitn(field_count, ' ').collect();
+        if fields.len() != field_count {
+            continue;
+        }
+        let xy = fields[1];
+        let orig_path = if entry.starts_with('2') {
+            entries.next().map(str::to_string)
+        } else {
+            None
+        };
+        status.files.push(GitFileStatus {
+            path: fields[field_count - 1].to_string(),
+            orig_path,
+            index_status: xy.get(0..1).unwrap_or(".").to_string(),
+            worktree_status: xy.get(1..2).unwrap_or(".").to_string(),
+            conflicted,
+        });
+    }
+
+    status
+}
+
+/// Parses unified diff output from `git diff` into files and hunks.
+fn parse_diff(output: &str) -> GitDiff {
+    let mut diff = GitDiff::default();
+    let mut file: Option<GitFileDiff> = None;
+    // Lines still expected in the current hunk as (old, new).
+    let mut remaining = (0u32, 0u32);
+
+    for line in output.lines() {
+        if remaining.0 > 0 || remaining.1 > 0 {
+            let Some(hunk) = file.as_mut().and_then(|file| file.hunks.last_mut()) else {
+                remaining = (0, 0);
+                continue;
+            };
+            let (kind, content) = match line.split_at_checked(1) {
+                Some(("+", content)) => (GitDiffLineKind::Added, content),
+                Some(("-", content)) => (GitDiffLineKind::Removed, content),
+                Some((" ", content)) => (GitDiffLineKind::Context, content),
+                // "\ No newline at end of file"
+                Some(("\\", _)) => continue,
+                // Some tools strip the space from empty context lines.
+                _ => (GitDiffLineKind::Context, ""),
+            };
+            match kind {
+                GitDiffLineKind::Added => {
+                    remaining.1 = remaining.1.saturating_sub(1);
+                    diff.additions += 1;
+                }
+                GitDiffLineKind::Removed => {
+                    remaining.0 = remaining.0.saturating_sub(1);
+                    diff.deletions += 1;
+                }
+                GitDiffLineKind::Context => {
+                    remaining.0 = remaining.0.saturating_sub(1);
+                    remaining.1 = remaining.1.saturating_sub(1);
+                }
+            }
+            hunk.lines.push(GitDiffLine {
+                kind,
+                content: content.to_string(),
+            });
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            diff.files.extend(file.take());
+            let path = rest
+                .rsplit_once(" b/")
+                .map(|(_, path)| path)
+                .unwrap_or(rest);
+            file = Some(GitFileDiff {
+                path: path.to_string(),
+                ..GitFileDiff::default()
+            });
+            continue;
+        }
+        let Some(current) = file.as_mut() else {
+            continue;
+        };
+
+        if let Some(path) = line.strip_prefix("rename from ") {
+            current.old_path = Some(path.to_string());
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            current.path = path.to_string();
+        } else if let Some(path) = line.strip_prefix("--- a/") {
+            if current.old_path.is_none() && path != current.path {
+                current.old_path = Some(path.to_string());
+            }
+        } else if let Some(path) = line.strip_prefix("+++ b/") {
+            current.path = path.to_string();
+        } else if line.starts_with("Binary files ") {
+            current.binary = true;
+        } else if let Some(hunk) = parse_hunk_header(line) {
+            remaining = (hunk.old_lines, hunk.new_lines);
+            current.hunks.push(hunk);
+        }
+    }
+
+    diff.files.extend(file);
+    diff
+}
+
+/// Parses hunks that come without a file header, as gitsigns reports them.
+pub fn parse_hunks(patch: &str) -> Vec<GitDiffHunk> {
+    parse_diff(&format!("diff --git a/file b/file\n{patch}"))
+        .files
+        .pop()
+        .map(|file| file.hunks)
+        .unwrap_or_default()
+}
+
+/// Parses `@@ -old_start,old_lines +new_start,new_lines @@ header`.
+fn parse_hunk_header(line: &str) -> Option<GitDiffHunk> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (ranges, header) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let parse_range = |range: &str| -> Option<(u32, u32)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_lines) = parse_range(old)?;
+    let (new_start, new_lines) = parse_range(new)?;
+
+    Some(GitDiffHunk {
+        old_start,
+        old_lines,
+        new_start,
+        new_lines,
+        header: header.trim().to_string(),
+        lines: Vec::new(),
+    })
+}
+
+/// Stores the branch for the folder and emits `folder-branch-changed` when it moved.
+fn record_branch(app_handle: &tauri::AppHandle, folder_id: &str, branch: Option<String>) {
+    let store = app_handle.state::<std::sync::Mutex<FolderStateStore>>();
+    let changed = match store.lock() {
+        Ok(mut store) => store.set_branch(folder_id, branch.clone()),
+        Err(_) => {
+            log::warn!("Failed to lock folder state store");
+            return;
+        }
+    };
+    match changed {
+        Ok(true) => {
+            let _ = app_handle.emit(
+                "folder-branch-changed",
+                &FolderBranchChangedEvent {
+                    folder_id: folder_id.to_string(),
+                    branch,
+                },
+            );
+        }
+        Ok(false) => {}
+        Err(err) => log::warn!("Failed to persist branch for folder '{folder_id}': {err}"),
+    }
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn git_status(
+    app_handle: tauri::AppHandle,
+    folder_id: String,
+) -> Result<GitStatus, String> {
+    let cwd = folder_state::resolve_folder_path(&app_handle, &folder_id)?;
+    let status = status(&cwd).await?;
+    record_branch(&app_handle, &folder_id, status.branch.clone());
+    Ok(status)
+}
+
+#[tauri::command]
+pub async fn git_diff(
+    app_handle: tauri::AppHandle,
+    folder_id: String,
+    staged: Option<bool>,
+) -> Result<GitDiff, String> {
+    let cwd = folder_state::resolve_folder_path(&app_handle, &folder_id)?;
+    diff(&cwd, staged.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn git_current_branch(
+    app_handle: tauri::AppHandle,
+    folder_id: String,
+) -> Result<Option<String>, String> {
+    let cwd = folder_state::resolve_folder_path(&app_handle, &folder_id)?;
+    let branch = current_branch(&cwd).await?;
+    record_branch(&app_handle, &folder_id, branch.clone());
+    Ok(branch)
+}
+
+#[tauri::command]
+pub async fn git_stage_files(
+    app_handle: tauri::AppHandle,
+    folder_id: String,
+    paths: Vec<String>,
+) -> Result<GitStatus, String> {
+    let cwd = folder_state::resolve_folder_path(&app_handle, &folder_id)?;
+    stage(&cwd, &paths).await
+}
+
+#[tauri::command]
+pub async fn git_unstage(
+    app_handle: tauri::AppHandle,
+    folder_id: String,
+    paths: Vec<String>,
+) -> Result<GitStatus, String> {
+    let cwd = folder_state::resolve_folder_path(&app_handle, &folder_id)?;
+    unstage(&cwd, &paths).await
+}
+
+#[tauri::command]
+pub async fn git_commit(
+    app_handle: tauri::AppHandle,
+    folder_id: String,
+    message: String,
+) -> Result<GitCommitResult, String> {
+    let cwd = folder_state::resolve_folder_path(&app_handle, &folder_id)?;
+    let result = commit(&cwd, &message).await?;
+    record_branch(&app_handle, &folder_id, result.branch.clone());
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_porcelain_v2_status() {
+        let output = [
+            "# branch.oid 1234567890abcdef",
+            "# branch.head feature/git",
+            "# branch.upstream origin/feature/git",
+            "# branch.ab +2 -1",
+            "1 .M N... 100644 100644 100644 aaaa bbbb src/main.rs",
+            "2 R. N... 100644 100644 100644 aaaa bbbb R100 src/new name.rs",
+            "src/old.rs",
+            "u UU N... 100644 100644 100644 100644 aaaa bbbb cccc conflict.rs",
+            "? notes.txt",
+            "",
+        ]
+        .join("\0");
+
+        let status = parse_status(&output);
+        assert_eq!(status.branch.as_deref(), Some("feature/git"));
+        assert_eq!(status.upstream.as_deref(), Some("origin/feature/git"));
+        assert_eq!((status.ahead, status.behind), (2, 1));
+        assert_eq!(status.files.len(), 4);
+        assert_eq!(status.files[0].worktree_status, "M");
+        assert_eq!(status.files[1].path, "src/new name.rs");
+        assert_eq!(status.files[1].orig_path.as_deref(), Some("src/old.rs"));
+        assert!(status.files[2].conflicted);
+        assert_eq!(status.files[3].index_status, "?");
+    }
+
+    #[test]
+    fn detached_head_has_no_branch() {
+        let status = parse_status("# branch.oid abc\0# branch.head (detached)\0");
+        assert_eq!(status.branch, None);
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_folder() {
+        assert!(validate_paths(&["src/main.rs".to_string(), "./README.md".to_string()]).is_ok());
+        assert!(validate_paths(&[]).is_err());
+        assert!(validate_paths(&["../secret".to_string()]).is_err());
+        assert!(validate_paths(&["/etc/passwd".to_string()]).is_err());
+        assert!(validate_paths(&["src/../../x".to_string()]).is_err());
+    }
+
+    #[test]
+    fn commit_requires_staged_changes_without_conflicts() {
+        let unstaged = parse_status("1 .M N... 100644 100644 100644 a b src/main.rs\0? new.rs\0");
+        assert_eq!(
+            commit_blocker(&unstaged).as_deref(),
+            Some("Nothing staged to commit")
+        );
+
+        let staged = parse_status("1 M. N... 100644 100644 100644 a b src/main.rs\0");
+        assert_eq!(commit_blocker(&staged), None);
+
+        let conflicted = parse_status(
+            "1 M. N... 100644 100644 100644 a b src/main.rs\0u UU N... 100644 100644 100644 100644 a b c x.rs\0",
+        );
+        assert!(commit_blocker(&conflicted).unwrap().contains("x.rs"));
+    }
+
+    #[test]
+    fn parses_unified_diff_into_hunks() {
+        let output = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@ fn main() {
+ fn main() {
+-    old();
++    new();
++    more();
+ }
+\\ No newline at end of file
+diff --git a/logo.png b/logo.png
+index 3333333..4444444 100644
+Binary files a/logo.png and b/logo.png differ
+diff --git a/a.txt b/b.txt
+similarity index 90%
+rename from a.txt
+rename to b.txt
+";
+        let diff = parse_diff(output);
+        assert_eq!(diff.files.len(), 3);
+        assert_eq!((diff.additions, diff.deletions), (2, 1));
+
+        let hunk = &diff.files[0].hunks[0];
+        assert_eq!((hunk.old_start, hunk.old_lines), (1, 3));
+        assert_eq!((hunk.new_start, hunk.new_lines), (1, 4));
+        assert_eq!(hunk.header, "fn main() {");
+        assert_eq!(hunk.lines.len(), 5);
+        assert_eq!(hunk.lines[1].kind, GitDiffLineKind::Removed);
+        assert_eq!(hunk.lines[1].content, "    old();");
+
+        assert!(diff.files[1].binary);
+        assert_eq!(diff.files[2].path, "b.txt");
+        assert_eq!(diff.files[2].old_path.as_deref(), Some("a.txt"));
+    }
+
+    #[test]
+    fn parses_hunks_without_a_file_header() {
+        let hunks =
+            parse_hunks("@@ -3 +3,2 @@\n-old\n+new\n+added\n@@ -10,2 +11,0 @@\n-gone\n-also gone");
+        assert_eq!(hunks.len(), 2);
+        assert_eq!((hunks[0].old_start, hunks[0].old_lines), (3, 1));
+        assert_eq!(hunks[0].lines[2].kind, GitDiffLineKind::Added);
+        assert_eq!((hunks[1].new_start, hunks[1].new_lines), (11, 0));
+        assert_eq!(hunks[1].lines.len(), 2);
+        assert!(parse_hunks("").is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+use std::str::FromStr;
+
+use tauri::plugin::TauriPlugin;
+use tauri::Manager;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::ghostty_embed::with_manager;
+use crate::MAIN_WINDOW_LABEL;
+
+/// Global shortcut plugin whose handler toggles the main window.
+pub fn plugin() -> TauriPlugin<tauri::Wry> {
+    tauri_plugin_global_shortcut::Builder::new()
+        .with_handler(|app_handle, _shortcut, event| {
+            if event.state() == ShortcutState::Pressed {
+                toggle_main_window(app_handle);
+            }
+        })
+        .build()
+}
+
+pub fn parse_shortcut(shortcut: &str) -> Result<Shortcut, String> {
+    Shortcut::from_str(shortcut.trim())
+        .map_err(|e| format!("Invalid global_shortcut '{shortcut}': {e}"))
+}
+
+/// Replaces the registered summon shortcut; `None` leaves none registered.
+pub fn apply(app_handle: &tauri::AppHandle, shortcut: Option<&str>) -> Result<(), String> {
+    let shortcuts = app_handle.global_shortcut();
+    shortcuts
+        .unregister_all()
+        .map_err(|e| format!("Failed to unregister global shortcuts: {e}"))?;
+
+    let Some(shortcut) = shortcut else {
+        return Ok(());
+    };
+    let parsed = parse_shortcut(shortcut)?;
+    shortcuts
+        .register(parsed)
+        .map_err(|e| format!("Failed to register global shortcut '{shortcut}': {e}"))
+}
+
+/// Hides the window when it is in front, otherwise shows it and puts focus
+/// back into the terminal that was last used.
+fn toggle_main_window(app_handle: &tauri::AppHandle) {
+    let Some(window) = app_handle.get_webview_window(MAIN_WINDOW_LABEL) else {
+        return;
+    };
+    let visible = window.is_visible().unwrap_or(false);
+    let minimized = window.is_minimized().unwrap_or(false);
+    if visible && !minimized && window.is_focused().unwrap_or(false) {
+        let _ = window.hide();
+        return;
+    }
+
+    if minimized {
+        let _ = window.unminimize();
+    }
+    let _ = window.show();
+    let _ = window.set_focus();
+    let _ = app_handle.run_on_main_thread(|| {
+        if let Err(err) = with_manager(|manager| manager.focus_last_used()) {
+            log::debug!("No terminal to focus after summoning the window: {}", err);
+        }
+    });
+}
@@ -1,25 +1,100 @@
 mod acp_client;
+pub mod acp_core;
 mod app_config;
+mod app_paths;
+mod audit_log;
+mod bridge_stats;
+mod compaction;
+mod context;
+mod conversation_export;
+mod diagnostics;
+mod diff;
+mod edit_snapshots;
+mod error;
+mod focus;
+mod folder_state;
+mod fs_watch;
+mod fuzzy_finder;
 mod ghostty_embed;
+mod ghostty_splits;
+mod git;
+mod global_shortcut;
+mod logging;
+mod mentions;
+mod message_ratings;
+mod notifications;
 mod nvim_bridge;
+mod process;
+mod project_files;
+mod project_templates;
+mod prompt_templates;
+mod resource_monitor;
+mod secrets;
+mod shutdown;
+mod single_instance;
 mod socket_manager;
+mod symbol_index;
+mod terminal_registry;
+mod tmux_control;
 mod tmux_runtime;
-
-use ghostty_embed::{with_manager, GhosttyOptions, GhosttyRect};
-use socket_manager::SocketManager;
+mod updates;
+mod workspace;
+
+use error::{ErrorCode, NeoaiError};
+use ghostty_embed::{
+    with_manager, GhosttyLayoutEntry, GhosttyOptions, GhosttyRect, GhosttyRunOptions,
+};
+use ghostty_splits::SplitDirection;
+use socket_manager::{SocketAllocation, SocketManager};
 use tauri::Manager;
 use tokio::sync::Mutex;
 
+/// Label of the single app window declared in `tauri.conf.json`.
+pub(crate) const MAIN_WINDOW_LABEL: &str = "main";
+
+fn terminal_defaults(window: &tauri::Window) -> app_config::TerminalConfig {
+    window
+        .state::<std::sync::Mutex<app_config::AppConfigState>>()
+        .lock()
+        .map(|state| state.terminal_config())
+        .unwrap_or_default()
+}
+
+fn ghostty_error(message: String) -> NeoaiError {
+    NeoaiError::new(ErrorCode::GhosttyFailed, message)
+}
+
+fn tmux_error(message: String) -> NeoaiError {
+    NeoaiError::new(ErrorCode::TmuxFailed, message)
+}
+
+/// Whether a terminal view with `id` exists.
+pub(crate) fn ghostty_exists(window: &tauri::Window, id: &str) -> Result<bool, String> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let id = id.to_string();
+
+    window
+        .run_on_main_thread(move || {
+            let _ = tx.send(with_manager(|manager| manager.contains(&id)));
+        })
+        .map_err(|e| e.to_string())?;
+
+    rx.recv().map_err(|_| "ghostty_exists failed".to_string())
+}
+
 #[tauri::command]
-fn ghostty_create(
+pub(crate) fn ghostty_create(
     window: tauri::Window,
     id: String,
     rect: GhosttyRect,
     options: Option<GhosttyOptions>,
-) -> Result<(), String> {
-    let options = options.unwrap_or_default();
+) -> Result<(), NeoaiError> {
+    let defaults = terminal_defaults(&window);
+    let options = options.unwrap_or_default().with_defaults(&defaults);
+    let rect = rect.with_default_style(&defaults);
     let (tx, rx) = std::sync::mpsc::channel();
     let window_clone = window.clone();
+    let terminal_id = id.clone();
 
     window
         .run_on_main_thread(move || {
@@ -30,41 +105,127 @@ fn ghostty_create(
 
     rx.recv()
         .unwrap_or_else(|_| Err("ghostty_create failed".to_string()))
+        .map_err(ghostty_error)?;
+    folder_state::record_terminal_open(window.app_handle(), &terminal_id, true);
+
+    // Refill the pool after this call returns, so the new terminal shows first.
+    let prewarm_window = window.clone();
+    let prewarm_options = GhosttyOptions::default().with_defaults(&defaults);
+    let _ = window.run_on_main_thread(move || {
+        let res = with_manager(|manager| {
+            manager.prewarm(&prewarm_window, defaults.prewarm, rect, prewarm_options)
+        });
+        if let Err(err) = res {
+            log::warn!("Failed to prewarm terminals: {}", err);
+        }
+    });
+    Ok(())
 }
 
+/// Moves a terminal view to `rect`; with `animation_ms` it slides there
+/// instead of jumping (capped at one second).
 #[tauri::command]
-fn ghostty_update_rect(window: tauri::Window, id: String, rect: GhosttyRect) -> Result<(), String> {
+fn ghostty_update_rect(
+    window: tauri::Window,
+    id: String,
+    rect: GhosttyRect,
+    animation_ms: Option<u64>,
+) -> Result<(), NeoaiError> {
+    let rect = rect.with_default_style(&terminal_defaults(&window));
+    let animation = animation_ms.map(std::time::Duration::from_millis);
     let (tx, rx) = std::sync::mpsc::channel();
     let window_clone = window.clone();
 
     window
         .run_on_main_thread(move || {
-            let res = with_manager(|manager| manager.update_rect(&window_clone, &id, rect));
+            let res =
+                with_manager(|manager| manager.update_rect(&window_clone, &id, rect, animation));
             let _ = tx.send(res);
         })
         .map_err(|e| e.to_string())?;
 
     rx.recv()
         .unwrap_or_else(|_| Err("ghostty_update_rect failed".to_string()))
+        .map_err(ghostty_error)
 }
 
+/// Destroys the terminal view. A view that is already gone (e.g. torn down by
+/// `destroy_terminal` before the component unmounted) is not an error.
 #[tauri::command]
-fn ghostty_destroy(window: tauri::Window, id: String) -> Result<(), String> {
+pub(crate) fn ghostty_destroy(window: tauri::Window, id: String) -> Result<(), NeoaiError> {
     let (tx, rx) = std::sync::mpsc::channel();
+    let terminal_id = id.clone();
+    let window_clone = window.clone();
 
     window
         .run_on_main_thread(move || {
-            let res = with_manager(|manager| manager.destroy(&id));
+            let res = with_manager(|manager| {
+                if manager.contains(&id) {
+                    manager.destroy(&window_clone, &id)
+                } else {
+                    Ok(())
+                }
+            });
             let _ = tx.send(res);
         })
         .map_err(|e| e.to_string())?;
 
     rx.recv()
         .unwrap_or_else(|_| Err("ghostty_destroy failed".to_string()))
+        .map_err(ghostty_error)?;
+    folder_state::record_terminal_open(window.app_handle(), &terminal_id, false);
+    Ok(())
 }
 
+/// Splits a terminal, opening a shell in a new pane on the `direction` side
+/// of the pane `id` (or the terminal's focused pane). Returns the new pane's
+/// id, which `ghostty_destroy` closes.
 #[tauri::command]
-fn ghostty_set_visible(window: tauri::Window, id: String, visible: bool) -> Result<(), String> {
+fn ghostty_split(
+    window: tauri::Window,
+    id: String,
+    direction: SplitDirection,
+) -> Result<String, NeoaiError> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let window_clone = window.clone();
+
+    window
+        .run_on_main_thread(move || {
+            let res = with_manager(|manager| manager.split(&window_clone, &id, direction));
+            let _ = tx.send(res);
+        })
+        .map_err(|e| e.to_string())?;
+
+    rx.recv()
+        .unwrap_or_else(|_| Err("ghostty_split failed".to_string()))
+        .map_err(ghostty_error)
+}
+
+/// Focuses the split pane on the `direction` side of the pane `id` (or the
+/// terminal's focused pane). Returns the focused pane, or `null` when there
+/// is none on that side.
+#[tauri::command]
+fn ghostty_focus_split(
+    window: tauri::Window,
+    id: String,
+    direction: SplitDirection,
+) -> Result<Option<String>, NeoaiError> {
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    window
+        .run_on_main_thread(move || {
+            let res = with_manager(|manager| manager.focus_split(&id, direction));
+            let _ = tx.send(res);
+        })
+        .map_err(|e| e.to_string())?;
+
+    rx.recv()
+        .unwrap_or_else(|_| Err("ghostty_focus_split failed".to_string()))
+        .map_err(ghostty_error)
+}
+
+#[tauri::command]
+fn ghostty_set_visible(window: tauri::Window, id: String, visible: bool) -> Result<(), NeoaiError> {
     let (tx, rx) = std::sync::mpsc::channel();
 
     window
@@ -76,10 +237,11 @@ fn ghostty_set_visible(window: tauri::Window, id: String, visible: bool) -> Resu
 
     rx.recv()
         .unwrap_or_else(|_| Err("ghostty_set_visible failed".to_string()))
+        .map_err(ghostty_error)
 }
 
 #[tauri::command]
-fn ghostty_focus(window: tauri::Window, id: String, focused: bool) -> Result<(), String> {
+fn ghostty_focus(window: tauri::Window, id: String, focused: bool) -> Result<(), NeoaiError> {
     let (tx, rx) = std::sync::mpsc::channel();
 
     window
@@ -91,12 +253,55 @@ fn ghostty_focus(window: tauri::Window, id: String, focused: bool) -> Result<(),
 
     rx.recv()
         .unwrap_or_else(|_| Err("ghostty_focus failed".to_string()))
+        .map_err(ghostty_error)
 }
 
+/// Gives keyboard input to the webview, resigning first responder from
+/// whichever terminal holds it.
 #[tauri::command]
-fn ghostty_write_text(window: tauri::Window, id: String, text: String) -> Result<(), String> {
+fn focus_chat(window: tauri::Window) -> Result<(), NeoaiError> {
     let (tx, rx) = std::sync::mpsc::channel();
 
+    window
+        .run_on_main_thread(move || {
+            let res = with_manager(|manager| manager.focus_chat());
+            let _ = tx.send(res);
+        })
+        .map_err(|e| e.to_string())?;
+
+    rx.recv()
+        .unwrap_or_else(|_| Err("focus_chat failed".to_string()))
+        .map_err(ghostty_error)?;
+    focus::update(window.app_handle(), |focus| focus.chat_focused());
+    Ok(())
+}
+
+/// Gives keyboard input to terminal `id` (its focused pane when split).
+#[tauri::command]
+fn focus_terminal(window: tauri::Window, id: String) -> Result<(), NeoaiError> {
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    window
+        .run_on_main_thread(move || {
+            let res = with_manager(|manager| manager.focus(&id, true));
+            let _ = tx.send(res);
+        })
+        .map_err(|e| e.to_string())?;
+
+    rx.recv()
+        .unwrap_or_else(|_| Err("focus_terminal failed".to_string()))
+        .map_err(ghostty_error)
+}
+
+#[tauri::command]
+pub(crate) fn ghostty_write_text(
+    window: tauri::Window,
+    id: String,
+    text: String,
+) -> Result<(), NeoaiError> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let (terminal_id, written) = (id.clone(), text.clone());
+
     window
         .run_on_main_thread(move || {
             let res = with_manager(|manager| manager.write_text(&id, &text));
@@ -106,17 +311,70 @@ fn ghostty_write_text(window: tauri::Window, id: String, text: String) -> Result
 
     rx.recv()
         .unwrap_or_else(|_| Err("ghostty_write_text failed".to_string()))
+        .map_err(ghostty_error)?;
+    audit_log::record_app_input(window.app_handle(), &terminal_id, &written);
+    Ok(())
+}
+
+/// Focuses the terminal and runs `command` in it, or with `confirm` leaves
+/// it typed on the prompt for the user to run. Backs "Run suggested command"
+/// buttons; use `ghostty_write_text` for raw input.
+#[tauri::command]
+fn ghostty_run_command(
+    window: tauri::Window,
+    id: String,
+    command: String,
+    options: Option<GhosttyRunOptions>,
+) -> Result<(), NeoaiError> {
+    let options = options.unwrap_or_default();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let (terminal_id, run) = (id.clone(), command.clone());
+
+    window
+        .run_on_main_thread(move || {
+            let res = with_manager(|manager| manager.run_command(&id, &command, options));
+            let _ = tx.send(res);
+        })
+        .map_err(|e| e.to_string())?;
+
+    rx.recv()
+        .unwrap_or_else(|_| Err("ghostty_run_command failed".to_string()))
+        .map_err(ghostty_error)?;
+    audit_log::record_app_input(window.app_handle(), &terminal_id, &run);
+    Ok(())
+}
+
+/// Frames of every terminal view, for debugging the frontend layout.
+#[tauri::command]
+fn ghostty_layout_snapshot(window: tauri::Window) -> Result<Vec<GhosttyLayoutEntry>, NeoaiError> {
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    window
+        .run_on_main_thread(move || {
+            let _ = tx.send(with_manager(|manager| manager.layout_snapshot()));
+        })
+        .map_err(|e| e.to_string())?;
+
+    rx.recv()
+        .unwrap_or_else(|_| Err("ghostty_layout_snapshot failed".to_string()))
+        .map_err(ghostty_error)
+}
+
+/// Names of the Ghostty profiles terminals can select with
+/// `options.profile`.
+#[tauri::command]
+fn ghostty_list_profiles(app_handle: tauri::AppHandle) -> Result<Vec<String>, NeoaiError> {
+    let dir = ghostty_embed::profiles_dir(&app_handle).map_err(ghostty_error)?;
+    Ok(ghostty_embed::list_profiles(&dir))
 }
 
 #[tauri::command]
 async fn get_socket_path(
     state: tauri::State<'_, std::sync::Mutex<SocketManager>>,
     terminal_id: String,
-) -> Result<String, String> {
+) -> Result<SocketAllocation, NeoaiError> {
     let mut mgr = state.lock().map_err(|e| e.to_string())?;
-    let path = mgr.socket_path(&terminal_id);
-    mgr.register(path.clone());
-    Ok(path.to_string_lossy().into_owned())
+    mgr.allocate(&terminal_id).map_err(NeoaiError::from)
 }
 
 #[tauri::command]
@@ -124,24 +382,9 @@ async fn remove_socket_path(
     state: tauri::State<'_, std::sync::Mutex<SocketManager>>,
     tmux_state: tauri::State<'_, Mutex<tmux_runtime::TmuxRuntimeState>>,
     terminal_id: String,
-) -> Result<(), String> {
-    {
-        let mut mgr = state.lock().map_err(|e| e.to_string())?;
-        let path = mgr.socket_path(&terminal_id);
-        mgr.remove_socket(&path);
-    }
-
-    let (session_name, pane_ids) = {
-        let mut tmux = tmux_state.lock().await;
-        tmux.remove_terminal(&terminal_id)
-    };
-    for pane_id in pane_ids {
-        let _ = tmux_runtime::kill_pane(&pane_id).await;
-    }
-    if let Some(session_name) = session_name {
-        let _ = tmux_runtime::kill_session(&session_name).await;
-    }
-
+) -> Result<(), NeoaiError> {
+    terminal_registry::release_socket(&state, &terminal_id)?;
+    terminal_registry::release_tmux(&tmux_state, &terminal_id).await;
     Ok(())
 }
 
@@ -149,7 +392,7 @@ async fn remove_socket_path(
 async fn tmux_status(
     tmux_state: tauri::State<'_, Mutex<tmux_runtime::TmuxRuntimeState>>,
     terminal_id: String,
-) -> Result<tmux_runtime::TmuxStatus, String> {
+) -> Result<tmux_runtime::TmuxStatus, NeoaiError> {
     let availability = tmux_runtime::detect_tmux_available().await;
     let available = availability.is_ok();
     let error = availability.err();
@@ -162,7 +405,7 @@ async fn tmux_enable_for_terminal(
     tmux_state: tauri::State<'_, Mutex<tmux_runtime::TmuxRuntimeState>>,
     terminal_id: String,
     enabled: bool,
-) -> Result<tmux_runtime::TmuxStatus, String> {
+) -> Result<tmux_runtime::TmuxStatus, NeoaiError> {
     let availability = tmux_runtime::detect_tmux_available().await;
     let available = availability.is_ok();
     let error = availability.err();
@@ -171,15 +414,39 @@ async fn tmux_enable_for_terminal(
     Ok(tmux.snapshot_for_terminal(&terminal_id, available, error))
 }
 
+/// Commands the agent ran in `terminal_id`'s tmux session, running and
+/// finished, oldest first. Panes found dead are marked exited on the way.
 #[tauri::command]
-async fn nvim_start_in_tmux(
+async fn tmux_list_commands(
+    app_handle: tauri::AppHandle,
+    tmux_state: tauri::State<'_, Mutex<tmux_runtime::TmuxRuntimeState>>,
+    terminal_id: String,
+) -> Result<Vec<tmux_runtime::TmuxCommandInfo>, NeoaiError> {
+    let running = tmux_state
+        .lock()
+        .await
+        .running_commands_for_terminal(&terminal_id);
+    for (command_id, pane_id) in running {
+        match tmux_runtime::pane_state(&pane_id).await {
+            Ok(pane) if pane.dead => {
+                acp_client::notify_command_exit(&app_handle, &command_id, pane.exit_code).await
+            }
+            Ok(_) => {}
+            Err(err) => log::debug!("Failed to read state of pane {}: {}", pane_id, err),
+        }
+    }
+    Ok(tmux_state.lock().await.list_commands(&terminal_id))
+}
+
+#[tauri::command]
+pub(crate) async fn nvim_start_in_tmux(
     window: tauri::Window,
     tmux_state: tauri::State<'_, Mutex<tmux_runtime::TmuxRuntimeState>>,
     terminal_id: String,
     socket_path: String,
     cwd: Option<String>,
     allow_fallback: Option<bool>,
-) -> Result<tmux_runtime::StartNvimResult, String> {
+) -> Result<tmux_runtime::StartNvimResult, NeoaiError> {
     let allow_fallback = allow_fallback.unwrap_or(false);
 
     let (tmux_enabled, assigned_session_name, assigned_names) = {
@@ -225,15 +492,54 @@ async fn nvim_start_in_tmux(
         let session_name = if let Some(existing) = assigned_session_name {
             existing
         } else {
-            let base_name = tmux_runtime::session_base_name(cwd_path, &terminal_id);
-            let chosen =
-                tmux_runtime::find_available_session_name(&base_name, &assigned_names).await?;
+            let remembered = folder_state::folder_id_for_terminal(&terminal_id)
+                .and_then(|folder_id| {
+                    let store = window.state::<std::sync::Mutex<folder_state::FolderStateStore>>();
+                    let store = store.lock().ok()?;
+                    let session = store.session(folder_id);
+                    session.tmux_session_name
+                })
+                .filter(|name| !assigned_names.contains(name));
+            let chosen = match remembered {
+                // Reattach to the session this folder used before the restart.
+                Some(name) => name,
+                None => {
+                    let base_name = tmux_runtime::session_base_name(cwd_path, &terminal_id);
+                    tmux_runtime::find_available_session_name(&base_name, &assigned_names)
+                        .await
+                        .map_err(tmux_error)?
+                }
+            };
             let mut tmux = tmux_state.lock().await;
             tmux.set_session_name(&terminal_id, chosen.clone());
             chosen
         };
 
-        tmux_runtime::prepare_nvim_window(&session_name, &socket_path, cwd_path).await?;
+        tmux_runtime::prepare_nvim_window(&session_name, &socket_path, cwd_path)
+            .await
+            .map_err(tmux_error)?;
+        let layout = folder_state::folder_id_for_terminal(&terminal_id)
+            .and_then(|folder_id| {
+                let store = window.state::<std::sync::Mutex<folder_state::FolderStateStore>>();
+                let store = store.lock().ok()?;
+                Some(store.session(folder_id).tmux_windows)
+            })
+            .unwrap_or_default();
+        if let Err(err) = tmux_runtime::open_layout_windows(&session_name, cwd_path, &layout).await
+        {
+            log::warn!("Failed to open tmux layout windows for {terminal_id}: {err}");
+        }
+        if let Ok(mut sockets) = window.state::<std::sync::Mutex<SocketManager>>().lock() {
+            sockets.record_tmux_session(&terminal_id, &session_name);
+        }
+        folder_state::remember_for_terminal(
+            window.app_handle(),
+            &terminal_id,
+            folder_state::FolderSessionUpdate {
+                tmux_session_name: Some(session_name.clone()),
+                ..Default::default()
+            },
+        );
         ghostty_write_text(
             window,
             terminal_id,
@@ -262,17 +568,56 @@ async fn nvim_start_in_tmux(
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Clean up sockets left behind by crashed instances
-    SocketManager::cleanup_stale();
+    let socket_manager = SocketManager::new();
+    socket_manager.cleanup_stale();
 
     #[allow(unused_mut)]
     let mut builder = tauri::Builder::default()
+        .plugin(single_instance::plugin())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(global_shortcut::plugin())
         .manage(Mutex::new(nvim_bridge::NvimBridgeState::new()))
         .manage(Mutex::new(acp_client::AcpClientState::new()))
         .manage(std::sync::Mutex::new(app_config::AppConfigState::default()))
         .manage(Mutex::new(tmux_runtime::TmuxRuntimeState::new()))
-        .manage(std::sync::Mutex::new(SocketManager::new()))
+        .manage(std::sync::Mutex::new(socket_manager))
+        .manage(std::sync::Mutex::new(
+            terminal_registry::TerminalRegistry::default(),
+        ))
+        .manage(std::sync::Mutex::new(
+            folder_state::FolderStateStore::default(),
+        ))
+        .manage(std::sync::Mutex::new(
+            prompt_templates::PromptTemplateStore::default(),
+        ))
+        .manage(std::sync::Mutex::new(
+            message_ratings::MessageRatingStore::default(),
+        ))
+        .manage(std::sync::Mutex::new(audit_log::AuditLogStore::default()))
+        .manage(std::sync::Mutex::new(
+            project_templates::ProjectTemplateStore::default(),
+        ))
+        .manage(std::sync::Mutex::new(fs_watch::FsWatchState::default()))
+        .manage(std::sync::Mutex::new(
+            fuzzy_finder::FileIndexState::default(),
+        ))
+        .manage(std::sync::Mutex::new(
+            symbol_index::SymbolIndexState::default(),
+        ))
+        .manage(std::sync::Mutex::new(
+            resource_monitor::ResourceMonitorState::default(),
+        ))
+        .manage(std::sync::Mutex::new(focus::FocusCoordinator::default()))
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Focused(focused) = event {
+                if *focused {
+                    notifications::clear_attention(window);
+                }
+                focus::update(window.app_handle(), |focus| focus.window_focused(*focused));
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             // Ghostty
             ghostty_create,
@@ -281,32 +626,126 @@ pub fn run() {
             ghostty_set_visible,
             ghostty_focus,
             ghostty_write_text,
+            ghostty_run_command,
+            ghostty_split,
+            ghostty_focus_split,
+            ghostty_list_profiles,
+            ghostty_layout_snapshot,
+            // Keyboard focus
+            focus_chat,
+            focus_terminal,
+            focus::focus_state,
+            // Terminal lifecycle
+            terminal_registry::create_terminal,
+            terminal_registry::destroy_terminal,
             // Neovim bridge
             nvim_bridge::nvim_connect,
             nvim_bridge::nvim_disconnect,
             nvim_bridge::nvim_connection_status,
             nvim_bridge::nvim_probe_health,
+            nvim_bridge::nvim_bridge_stats,
             nvim_bridge::nvim_reinject_keymaps,
             nvim_bridge::nvim_get_context,
+            nvim_bridge::nvim_get_breadcrumbs,
             nvim_bridge::nvim_get_diagnostics,
             nvim_bridge::nvim_get_buffer_content,
             nvim_bridge::nvim_apply_edit,
             nvim_bridge::nvim_apply_edits,
+            nvim_bridge::nvim_exec_batch,
+            context::build_context,
             nvim_bridge::nvim_exec_command,
+            nvim_bridge::nvim_lsp_rename,
+            nvim_bridge::nvim_get_buffer_hunks,
+            nvim_bridge::nvim_set_progress,
+            nvim_bridge::nvim_insert_at_cursor,
+            nvim_bridge::nvim_get_workspace_roots,
+            nvim_bridge::nvim_get_layout,
             // ACP agent
             acp_client::acp_start_agent,
             acp_client::acp_stop_agent,
             acp_client::acp_agent_status,
+            acp_client::acp_subscribe_terminal_events,
+            acp_client::acp_unsubscribe_terminal_events,
             acp_client::acp_create_session,
+            acp_client::acp_set_session_cwd,
+            acp_client::acp_set_stream_options,
             acp_client::acp_send_prompt,
+            acp_client::acp_send_prompt_multi,
+            acp_client::acp_cancel_prompt,
+            // Prompt templates
+            prompt_templates::prompt_template_list,
+            prompt_templates::prompt_template_save,
+            prompt_templates::prompt_template_delete,
+            prompt_templates::acp_send_template,
+            project_templates::project_template_list,
+            project_templates::project_template_save,
+            project_templates::project_template_delete,
+            project_templates::create_project_from_template,
+            message_ratings::message_rate,
+            message_ratings::message_ratings_summary,
+            audit_log::audit_log_load,
             acp_client::acp_respond_permission_request,
             // tmux
             tmux_status,
             tmux_enable_for_terminal,
+            tmux_list_commands,
             nvim_start_in_tmux,
             // Socket management
             get_socket_path,
             remove_socket_path,
+            // Config
+            app_config::config_validate,
+            app_config::config_set,
+            // Logs and diagnostics
+            logging::get_recent_logs,
+            logging::open_log_dir,
+            diagnostics::create_diagnostic_bundle,
+            diagnostics::doctor,
+            // Conversation export
+            conversation_export::export_conversation,
+            compaction::compact_conversation,
+            compaction::acp_seed_session_from_history,
+            // Folder session state
+            folder_state::folder_session_get,
+            folder_state::folder_session_update,
+            folder_state::folder_session_clear,
+            folder_state::folder_activity,
+            folder_state::folder_check_paths,
+            folder_state::folder_draft_save,
+            folder_state::folder_draft_load,
+            folder_state::folder_register_paths,
+            workspace::restore_workspace,
+            // Git
+            git::git_status,
+            git::git_diff,
+            git::git_current_branch,
+            git::git_stage_files,
+            git::git_unstage,
+            git::git_commit,
+            diff::diff_text,
+            // Edit snapshots
+            edit_snapshots::list_edit_snapshots,
+            edit_snapshots::rollback_edit,
+            // Secrets
+            secrets::secret_set,
+            secrets::secret_get,
+            secrets::secret_delete,
+            // Resource monitor
+            resource_monitor::get_process_stats,
+            resource_monitor::start_resource_monitor,
+            resource_monitor::stop_resource_monitor,
+            // Updates
+            updates::check_for_updates,
+            // File watching
+            fs_watch::watch_folder,
+            fs_watch::unwatch_folder,
+            // Project files
+            project_files::list_project_files,
+            project_files::import_projects_from_dir,
+            fuzzy_finder::fuzzy_find_files,
+            // Symbols
+            symbol_index::search_symbols,
+            symbol_index::reindex_symbols,
         ]);
 
     #[cfg(all(debug_assertions, feature = "mcp-debug"))]
@@ -318,6 +757,14 @@ pub fn run() {
         .build(tauri::generate_context!())
         .expect("error while building tauri application");
 
+    if let Err(err) = logging::init(app.handle()) {
+        eprintln!("NeoAI file logging is unavailable: {err}");
+    }
+    match diagnostics::crash_dir(app.handle()) {
+        Ok(dir) => diagnostics::install_panic_hook(dir),
+        Err(err) => log::warn!("Crash reports will not be written: {}", err),
+    }
+
     if let Some(config_state) = app.try_state::<std::sync::Mutex<app_config::AppConfigState>>() {
         match config_state.lock() {
             Ok(mut state) => {
@@ -326,6 +773,21 @@ pub fn run() {
                 } else if let Some(path) = state.config_path() {
                     log::info!("Loaded NeoAI configuration from '{}'", path.display());
                 }
+                logging::set_level(state.log_level());
+                tmux_control::init(app.handle(), state.tmux_control_mode());
+                if let Err(err) =
+                    global_shortcut::apply(app.handle(), state.global_shortcut().as_deref())
+                {
+                    log::warn!("{}", err);
+                }
+                if let Some(dir) = state.socket_dir() {
+                    if let Some(sockets) = app.try_state::<std::sync::Mutex<SocketManager>>() {
+                        if let Ok(mut mgr) = sockets.lock() {
+                            mgr.set_socket_dir(dir);
+                            mgr.cleanup_stale();
+                        }
+                    }
+                }
             }
             Err(_) => {
                 log::warn!("Failed to lock NeoAI app config state");
@@ -333,13 +795,137 @@ pub fn run() {
         }
     }
 
-    app.run(|_handle, event| {
-        if let tauri::RunEvent::Exit = event {
-            if let Some(state) = _handle.try_state::<std::sync::Mutex<SocketManager>>() {
-                if let Ok(mut mgr) = state.inner().lock() {
-                    mgr.cleanup_all();
+    let agent_auto_start = app
+        .state::<std::sync::Mutex<app_config::AppConfigState>>()
+        .lock()
+        .map(|state| state.agent_config().auto_start_on_launch)
+        .unwrap_or(false);
+    if agent_auto_start {
+        let handle = app.handle().clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(err) = acp_client::start_agent(&handle, None).await {
+                log::warn!("Failed to auto-start ACP agent: {}", err);
+            }
+        });
+    }
+
+    if let Err(err) = app_config::watch_config(app.handle().clone()) {
+        log::warn!("NeoAI config.toml changes will require a restart: {}", err);
+    }
+    updates::schedule_startup_check(app.handle().clone());
+
+    if let Some(folder_state) = app.try_state::<std::sync::Mutex<folder_state::FolderStateStore>>()
+    {
+        match folder_state.lock() {
+            Ok(mut store) => {
+                if let Err(err) = store.initialize(&app.handle()) {
+                    log::warn!("Failed to load NeoAI folder state: {}", err);
                 }
             }
+            Err(_) => {
+                log::warn!("Failed to lock NeoAI folder state");
+            }
+        }
+        let handle = app.handle().clone();
+        tauri::async_runtime::spawn_blocking(move || folder_state::check_workspace(&handle));
+    }
+
+    if let Some(templates) =
+        app.try_state::<std::sync::Mutex<prompt_templates::PromptTemplateStore>>()
+    {
+        match templates.lock() {
+            Ok(mut store) => {
+                if let Err(err) = store.initialize(&app.handle()) {
+                    log::warn!("Failed to load NeoAI prompt templates: {}", err);
+                }
+            }
+            Err(_) => {
+                log::warn!("Failed to lock NeoAI prompt templates");
+            }
+        }
+    }
+
+    if let Some(templates) =
+        app.try_state::<std::sync::Mutex<project_templates::ProjectTemplateStore>>()
+    {
+        match templates.lock() {
+            Ok(mut store) => {
+                if let Err(err) = store.initialize(&app.handle()) {
+                    log::warn!("Failed to load NeoAI project templates: {}", err);
+                }
+            }
+            Err(_) => {
+                log::warn!("Failed to lock NeoAI project templates");
+            }
+        }
+    }
+
+    if let Some(ratings) = app.try_state::<std::sync::Mutex<message_ratings::MessageRatingStore>>()
+    {
+        match ratings.lock() {
+            Ok(mut store) => {
+                if let Err(err) = store.initialize(&app.handle()) {
+                    log::warn!("Failed to load NeoAI message ratings: {}", err);
+                }
+            }
+            Err(_) => {
+                log::warn!("Failed to lock NeoAI message ratings");
+            }
+        }
+    }
+
+    if let Some(audit) = app.try_state::<std::sync::Mutex<audit_log::AuditLogStore>>() {
+        match audit.lock() {
+            Ok(mut store) => {
+                if let Err(err) = store.initialize(&app.handle()) {
+                    log::warn!("Failed to load NeoAI audit log: {}", err);
+                }
+            }
+            Err(_) => {
+                log::warn!("Failed to lock NeoAI audit log");
+            }
+        }
+    }
+
+    // Reconcile sockets and tmux sessions left behind by crashed instances.
+    let stale_sockets = match app_paths::app_root_dir(app.handle()) {
+        Ok(root) => match app.state::<std::sync::Mutex<SocketManager>>().lock() {
+            Ok(mut mgr) => mgr.load_registry(root.join(socket_manager::SOCKET_REGISTRY_FILE)),
+            Err(_) => {
+                log::warn!("Failed to lock NeoAI socket manager");
+                Vec::new()
+            }
+        },
+        Err(err) => {
+            log::warn!("Failed to locate NeoAI socket registry: {}", err);
+            Vec::new()
+        }
+    };
+    let orphaned_sessions: Vec<String> = {
+        let folders = app.state::<std::sync::Mutex<folder_state::FolderStateStore>>();
+        let folders = folders.lock().ok();
+        stale_sockets
+            .into_iter()
+            .filter_map(|record| record.tmux_session)
+            // Sessions a folder will reattach to are kept alive.
+            .filter(|name| {
+                !folders
+                    .as_ref()
+                    .is_some_and(|store| store.remembers_tmux_session(name))
+            })
+            .collect()
+    };
+    if !orphaned_sessions.is_empty() {
+        tauri::async_runtime::spawn(async move {
+            for session_name in orphaned_sessions {
+                let _ = tmux_runtime::kill_session(&session_name).await;
+            }
+        });
+    }
+
+    app.run(|handle, event| {
+        if let tauri::RunEvent::Exit = event {
+            shutdown::run(handle);
         }
     });
 }
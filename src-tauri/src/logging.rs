@@ -0,0 +1,321 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+
+use crate::app_paths;
+
+pub const LOG_DIR_NAME: &str = "logs";
+const LOG_FILE_NAME: &str = "neoai.log";
+/// The active file is rotated once it would grow past this size.
+const MAX_LOG_FILE_BYTES: u64 = 5 * 1024 * 1024;
+/// Rotated files kept next to the active one (`neoai.log.1` is the newest).
+const MAX_ROTATED_FILES: usize = 5;
+/// Level applied to other crates' log records so dependency chatter stays out.
+const DEPENDENCY_MAX_LEVEL: LevelFilter = LevelFilter::Info;
+/// Upper bound on lines returned by `get_recent_logs`.
+const MAX_RECENT_LINES: usize = 5_000;
+
+static LOGGER: OnceLock<FileLogger> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+struct LogFile {
+    file: File,
+    size: u64,
+}
+
+struct FileLogger {
+    dir: PathBuf,
+    file: Mutex<Option<LogFile>>,
+}
+
+impl FileLogger {
+    fn active_path(&self) -> PathBuf {
+        self.dir.join(LOG_FILE_NAME)
+    }
+
+    fn open(&self) -> std::io::Result<LogFile> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.active_path())?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(LogFile { file, size })
+    }
+
+    fn rotate(&self) -> std::io::Result<()> {
+        let _ = std::fs::remove_file(rotated_path(&self.dir, MAX_ROTATED_FILES));
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let from = rotated_path(&self.dir, index);
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(&self.dir, index + 1))?;
+            }
+        }
+        std::fs::rename(self.active_path(), rotated_path(&self.dir, 1))
+    }
+
+    fn write_line(&self, line: &str) {
+        let Ok(mut guard) = self.file.lock() else {
+            return;
+        };
+        let len = line.len() as u64;
+        if guard
+            .as_ref()
+            .is_some_and(|log| log.size > 0 && log.size + len > MAX_LOG_FILE_BYTES)
+        {
+            *guard = None;
+            let _ = self.rotate();
+        }
+        if guard.is_none() {
+            *guard = self.open().ok();
+        }
+        if let Some(log) = guard.as_mut() {
+            if log.file.write_all(line.as_bytes()).is_ok() {
+                log.size += len;
+            }
+        }
+    }
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let own = metadata.target().starts_with(env!("CARGO_CRATE_NAME"));
+        own || metadata.level() <= DEPENDENCY_MAX_LEVEL
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{} {} [{}] {}\n",
+            format_timestamp(SystemTime::now()),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        if cfg!(debug_assertions) {
+            eprint!("{line}");
+        }
+        self.write_line(&line);
+    }
+
+    fn flush(&self) {
+        if let Ok(mut guard) = self.file.lock() {
+            if let Some(log) = guard.as_mut() {
+                let _ = log.file.flush();
+            }
+        }
+    }
+}
+
+fn rotated_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("{LOG_FILE_NAME}.{index}"))
+}
+
+/// Installs the file logger under `<app root>/logs`. Records below `Info` are
+/// dropped until the configured level is applied with [`set_level`].
+pub fn init(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let dir = log_dir(app_handle)?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create log directory '{}': {e}", dir.display()))?;
+
+    let logger = LOGGER.get_or_init(|| FileLogger {
+        dir,
+        file: Mutex::new(None),
+    });
+    log::set_logger(logger).map_err(|e| format!("Failed to install logger: {e}"))?;
+    log::set_max_level(LevelFilter::Info);
+    Ok(())
+}
+
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
+
+pub fn log_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_paths::app_root_dir(app_handle)?.join(LOG_DIR_NAME))
+}
+
+/// Formats a UTC timestamp as `YYYY-MM-DDTHH:MM:SS.mmmZ`.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs_of_day = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs_of_day / 3600,
+        (secs_of_day / 60) % 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Converts days since 1970-01-01 to a proleptic Gregorian date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Parses one line written by the logger. Lines that don't start with a
+/// record header are continuations of a multi-line message.
+fn parse_line(line: &str) -> Option<LogEntry> {
+    let (timestamp, rest) = line.split_once(' ')?;
+    if !timestamp.ends_with('Z') || !timestamp.contains('T') {
+        return None;
+    }
+    let (level, rest) = rest.split_once(' ')?;
+    Level::from_str(level).ok()?;
+    let (target, message) = rest.strip_prefix('[')?.split_once("] ")?;
+    Some(LogEntry {
+        timestamp: timestamp.to_string(),
+        level: level.to_string(),
+        target: target.to_string(),
+        message: message.to_string(),
+    })
+}
+
+fn parse_entries(lines: impl Iterator<Item = String>) -> Vec<LogEntry> {
+    let mut entries: Vec<LogEntry> = Vec::new();
+    for line in lines {
+        match parse_line(&line) {
+            Some(entry) => entries.push(entry),
+            None => {
+                if let Some(last) = entries.last_mut() {
+                    last.message.push('\n');
+                    last.message.push_str(&line);
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// Newest `count` entries at or above `min_level`, oldest first.
+fn recent_entries(dir: &Path, count: usize, min_level: LevelFilter) -> Vec<LogEntry> {
+    let mut collected: Vec<LogEntry> = Vec::new();
+    let files = std::iter::once(dir.join(LOG_FILE_NAME))
+        .chain((1..=MAX_ROTATED_FILES).map(|index| rotated_path(dir, index)));
+
+    for path in files {
+        if collected.len() >= count {
+            break;
+        }
+        let Ok(file) = File::open(&path) else {
+            continue;
+        };
+        let mut entries: Vec<LogEntry> =
+            parse_entries(BufReader::new(file).lines().map_while(Result::ok))
+                .into_iter()
+                .filter(|entry| Level::from_str(&entry.level).is_ok_and(|level| level <= min_level))
+                .collect();
+        // Older files go in front of what has been collected so far.
+        entries.append(&mut collected);
+        collected = entries;
+    }
+
+    let skip = collected.len().saturating_sub(count);
+    collected.split_off(skip)
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn get_recent_logs(
+    app_handle: tauri::AppHandle,
+    lines: usize,
+    level: Option<String>,
+) -> Result<Vec<LogEntry>, String> {
+    let min_level = match level.as_deref() {
+        Some(level) => {
+            LevelFilter::from_str(level).map_err(|_| format!("Unknown log level: {level}"))?
+        }
+        None => LevelFilter::Trace,
+    };
+    let dir = log_dir(&app_handle)?;
+    let count = lines.min(MAX_RECENT_LINES);
+    tauri::async_runtime::spawn_blocking(move || recent_entries(&dir, count, min_level))
+        .await
+        .map_err(|e| format!("Log read task failed: {e}"))
+}
+
+#[tauri::command]
+pub async fn open_log_dir(app_handle: tauri::AppHandle) -> Result<String, String> {
+    use tauri_plugin_opener::OpenerExt;
+
+    let dir = log_dir(&app_handle)?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create log directory '{}': {e}", dir.display()))?;
+    let dir = dir.to_string_lossy().into_owned();
+    app_handle
+        .opener()
+        .open_path(dir.clone(), None::<&str>)
+        .map_err(|e| format!("Failed to open log directory: {e}"))?;
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_utc_timestamps() {
+        let time = UNIX_EPOCH + std::time::Duration::from_millis(1_700_000_000_123);
+        assert_eq!(format_timestamp(time), "2023-11-14T22:13:20.123Z");
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn reads_recent_entries_across_rotated_files() {
+        let dir = std::env::temp_dir().join(format!("neoai-logging-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            rotated_path(&dir, 1),
+            "2024-01-01T00:00:00.000Z ERROR [neoai_lib::acp_client] old failure\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(LOG_FILE_NAME),
+            "2024-01-02T00:00:00.000Z INFO [neoai_lib] started\n\
+             2024-01-02T00:00:01.000Z WARN [neoai_lib::git] first line\n\
+             second line\n\
+             2024-01-02T00:00:02.000Z DEBUG [neoai_lib] noise\n",
+        )
+        .unwrap();
+
+        let warnings = recent_entries(&dir, 10, LevelFilter::Warn);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].message, "old failure");
+        assert_eq!(warnings[1].target, "neoai_lib::git");
+        assert_eq!(warnings[1].message, "first line\nsecond line");
+
+        let last_two = recent_entries(&dir, 2, LevelFilter::Trace);
+        assert_eq!(last_two[0].level, "WARN");
+        assert_eq!(last_two[1].level, "DEBUG");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,413 @@
+use std::path::{Path, PathBuf};
+
+use agent_client_protocol as acp;
+
+use crate::folder_state;
+use crate::nvim_bridge::{self, apply_line_window, SymbolLocation};
+use crate::symbol_index;
+
+/// Most lines embedded for one mention.
+const MAX_MENTION_LINES: u32 = 400;
+/// Lines shown for a symbol whose LSP range only covers its name.
+const SYMBOL_FALLBACK_LINES: u32 = 40;
+const SYMBOL_LOOKUP_TIMEOUT_MS: u64 = 2_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mention {
+    /// `@path/to/file`, optionally with `:start` or `:start-end` (1-based).
+    File {
+        path: String,
+        start: Option<u32>,
+        end: Option<u32>,
+    },
+    /// `#SymbolName`
+    Symbol(String),
+}
+
+/// A mention expanded to the file lines it refers to.
+#[derive(Debug, Clone)]
+pub struct ResolvedMention {
+    /// The mention as typed, e.g. `@src/main.rs`.
+    pub mention: String,
+    pub path: PathBuf,
+    /// 1-based, inclusive.
+    pub start_line: u32,
+    pub end_line: u32,
+    pub content: String,
+    /// The content is the entire file.
+    pub whole_file: bool,
+    /// Cut at `MAX_MENTION_LINES` before the end of what was asked for.
+    pub truncated: bool,
+}
+
+impl ResolvedMention {
+    pub fn uri(&self) -> String {
+        let uri = format!("file://{}", self.path.to_string_lossy());
+        if self.whole_file {
+            uri
+        } else {
+            format!("{uri}#L{}-{}", self.start_line, self.end_line)
+        }
+    }
+
+    /// An embedded resource when the agent accepts them, otherwise the same
+    /// content as a fenced text block.
+    pub fn to_content_block(&self, embedded_context: bool) -> acp::ContentBlock {
+        if embedded_context {
+            return acp::ContentBlock::Resource(acp::EmbeddedResource::new(
+                acp::EmbeddedResourceResource::TextResourceContents(
+                    acp::TextResourceContents::new(self.content.clone(), self.uri()),
+                ),
+            ));
+        }
+        let truncated = if self.truncated { " (truncated)" } else { "" };
+        format!(
+            "{} ({}:{}-{}){truncated}\n```\n{}\n```",
+            self.mention,
+            self.path.display(),
+            self.start_line,
+            self.end_line,
+            self.content
+        )
+        .into()
+    }
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | ':' | '.')
+}
+
+/// `:12` or `:12-40` at the end of a file mention.
+fn split_line_range(token: &str) -> (&str, Option<u32>, Option<u32>) {
+    if let Some((path, range)) = token.rsplit_once(':') {
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (start.parse().ok(), end.parse().ok().map(Some)),
+            None => (range.parse().ok(), Some(None)),
+        };
+        if let (Some(start), Some(end)) = (start, end) {
+            if start > 0 && !path.is_empty() {
+                return (path, Some(start), end);
+            }
+        }
+    }
+    (token, None, None)
+}
+
+/// Finds `@file` and `#Symbol` mentions. A mention must start a word, so
+/// e-mail addresses and `a#b` are left alone; trailing punctuation is dropped.
+pub fn parse_mentions(text: &str) -> Vec<(String, Mention)> {
+    let mut mentions = Vec::new();
+    for word in text.split_whitespace() {
+        let word = word.trim_end_matches(|c: char| matches!(c, ',' | ';' | '!' | '?' | ')' | '.'));
+        let word = word.trim_start_matches('(');
+        if let Some(token) = word.strip_prefix('@') {
+            if token.is_empty() {
+                continue;
+            }
+            let (path, start, end) = split_line_range(token);
+            mentions.push((
+                word.to_string(),
+                Mention::File {
+                    path: path.to_string(),
+                    start,
+                    end,
+                },
+            ));
+        } else if let Some(name) = word.strip_prefix('#') {
+            let starts_like_identifier = name
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_alphabetic() || c == '_');
+            if starts_like_identifier && name.chars().all(is_symbol_char) {
+                mentions.push((word.to_string(), Mention::Symbol(name.to_string())));
+            }
+        }
+    }
+    mentions.dedup_by(|a, b| a.1 == b.1);
+    mentions
+}
+
+/// Prefers an exact name match, then a case-insensitive one; `Type::method`
+/// and `Type.method` match on the last segment within that container.
+fn pick_symbol<'a>(query: &str, symbols: &'a [SymbolLocation]) -> Option<&'a SymbolLocation> {
+    let (container, name) = match query.rsplit_once("::").or_else(|| query.rsplit_once('.')) {
+        Some((container, name)) => (Some(container), name),
+        None => (None, query),
+    };
+    let in_container = |symbol: &&SymbolLocation| match container {
+        Some(container) => symbol
+            .container_name
+            .as_deref()
+            .is_some_and(|name| name.ends_with(container)),
+        None => true,
+    };
+    symbols
+        .iter()
+        .filter(in_container)
+        .find(|symbol| symbol.name == name)
+        .or_else(|| {
+            symbols
+                .iter()
+                .filter(in_container)
+                .find(|symbol| symbol.name.eq_ignore_ascii_case(name))
+        })
+}
+
+fn path_from_uri(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        if let Some(byte) = escaped {
+            decoded.push(byte);
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+/// Reads `path` through Neovim when connected, so unsaved buffer contents
+/// win, falling back to disk.
+async fn read_lines(
+    app_handle: &tauri::AppHandle,
+    terminal_id: Option<&str>,
+    path: &Path,
+    start: u32,
+    limit: u32,
+) -> Result<String, String> {
+    if let Some(terminal_id) = terminal_id {
+        if let Ok(content) = nvim_bridge::nvim_read_file_for_terminal(
+            app_handle,
+            terminal_id,
+            path,
+            Some(start),
+            Some(limit),
+        )
+        .await
+        {
+            return Ok(content);
+        }
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read '{}': {e}", path.display()))?;
+    Ok(apply_line_window(&content, Some(start), Some(limit)))
+}
+
+async fn resolve_window(
+    app_handle: &tauri::AppHandle,
+    terminal_id: Option<&str>,
+    mention: &str,
+    path: PathBuf,
+    start: u32,
+    wanted: u32,
+) -> Result<ResolvedMention, String> {
+    let lines = wanted.min(MAX_MENTION_LINES);
+    // One extra line tells whether the window cut the file short.
+    let content = read_lines(app_handle, terminal_id, &path, start, lines + 1).await?;
+    let mut kept: Vec<&str> = content.split('\n').collect();
+    let reached_end = kept.len() as u32 <= lines;
+    kept.truncate(lines as usize);
+    Ok(ResolvedMention {
+        mention: mention.to_string(),
+        start_line: start,
+        end_line: start + (kept.len() as u32).saturating_sub(1),
+        content: kept.join("\n"),
+        whole_file: start == 1 && reached_end,
+        truncated: !reached_end && wanted > lines,
+        path,
+    })
+}
+
+/// Path, 1-based start line and line span of `name` according to the LSP.
+async fn lsp_symbol(
+    app_handle: &tauri::AppHandle,
+    terminal_id: &str,
+    name: &str,
+) -> Result<(PathBuf, u32, u32), String> {
+    let query = name.rsplit([':', '.']).next().unwrap_or(name);
+    let symbols = nvim_bridge::nvim_workspace_symbols_for_terminal(
+        app_handle,
+        terminal_id,
+        query,
+        SYMBOL_LOOKUP_TIMEOUT_MS,
+    )
+    .await?;
+    let symbol = pick_symbol(name, &symbols).ok_or_else(|| format!("No symbol named '{name}'"))?;
+    let path = path_from_uri(&symbol.uri)
+        .ok_or_else(|| format!("Unsupported symbol location '{}'", symbol.uri))?;
+    let span = symbol.end_line.saturating_sub(symbol.start_line) + 1;
+    Ok((path, symbol.start_line + 1, span))
+}
+
+/// Same as [`lsp_symbol`] from the folder's ctags index, for when Neovim or
+/// its language server isn't available.
+fn indexed_symbol(
+    app_handle: &tauri::AppHandle,
+    terminal_id: &str,
+    name: &str,
+) -> Result<(PathBuf, u32, u32), String> {
+    let folder_id = folder_state::folder_id_for_terminal(terminal_id)
+        .ok_or_else(|| format!("No folder for terminal: {terminal_id}"))?;
+    let (path, symbol) = symbol_index::find_symbol(app_handle, folder_id, name)
+        .filter(|(_, symbol)| {
+            let wanted = name.rsplit([':', '.']).next().unwrap_or(name);
+            symbol.name.eq_ignore_ascii_case(wanted)
+        })
+        .ok_or_else(|| format!("No indexed symbol named '{name}'"))?;
+    let span = symbol
+        .end_line
+        .map_or(1, |end| end.saturating_sub(symbol.line) + 1);
+    Ok((path, symbol.line, span))
+}
+
+async fn resolve_mention(
+    app_handle: &tauri::AppHandle,
+    terminal_id: Option<&str>,
+    cwd: Option<&Path>,
+    text: &str,
+    mention: &Mention,
+) -> Result<ResolvedMention, String> {
+    match mention {
+        Mention::File { path, start, end } => {
+            let path = match cwd {
+                Some(cwd) => cwd.join(path),
+                None if Path::new(path).is_absolute() => PathBuf::from(path),
+                None => return Err("No folder to resolve a relative path against".to_string()),
+            };
+            if !path.is_file() {
+                return Err(format!("'{}' is not a file", path.display()));
+            }
+            let start = start.unwrap_or(1);
+            let wanted = match end {
+                Some(end) if *end >= start => end - start + 1,
+                _ => u32::MAX,
+            };
+            resolve_window(app_handle, terminal_id, text, path, start, wanted).await
+        }
+        Mention::Symbol(name) => {
+            let terminal_id = terminal_id.ok_or("Symbol mentions need a terminal")?;
+            let (path, start_line, span) = match lsp_symbol(app_handle, terminal_id, name).await {
+                Ok(found) => found,
+                Err(err) => {
+                    log::debug!("Falling back to the symbol index for #{}: {}", name, err);
+                    indexed_symbol(app_handle, terminal_id, name)?
+                }
+            };
+            let wanted = if span > 1 {
+                span
+            } else {
+                SYMBOL_FALLBACK_LINES
+            };
+            resolve_window(
+                app_handle,
+                Some(terminal_id),
+                text,
+                path,
+                start_line,
+                wanted,
+            )
+            .await
+        }
+    }
+}
+
+/// Expands the mentions in `messages`. Mentions that don't resolve (no such
+/// file, no LSP match) stay plain text and are only logged.
+pub async fn resolve_mentions(
+    app_handle: &tauri::AppHandle,
+    terminal_id: Option<&str>,
+    cwd: Option<&Path>,
+    messages: &[String],
+) -> Vec<ResolvedMention> {
+    let mut resolved = Vec::new();
+    for message in messages {
+        for (text, mention) in parse_mentions(message) {
+            match resolve_mention(app_handle, terminal_id, cwd, &text, &mention).await {
+                Ok(mention) => resolved.push(mention),
+                Err(err) => log::debug!("Leaving mention {} as text: {}", text, err),
+            }
+        }
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_file_and_symbol_mentions() {
+        let mentions = parse_mentions(
+            "Compare @src/lib.rs:10-20 with (@README.md), then fix #AppConfig::reload. Mail me@example.com, see issue #12 or # heading",
+        );
+        assert_eq!(
+            mentions,
+            vec![
+                (
+                    "@src/lib.rs:10-20".to_string(),
+                    Mention::File {
+                        path: "src/lib.rs".to_string(),
+                        start: Some(10),
+                        end: Some(20),
+                    }
+                ),
+                (
+                    "@README.md".to_string(),
+                    Mention::File {
+                        path: "README.md".to_string(),
+                        start: None,
+                        end: None,
+                    }
+                ),
+                (
+                    "#AppConfig::reload".to_string(),
+                    Mention::Symbol("AppConfig::reload".to_string())
+                ),
+            ]
+        );
+        assert_eq!(split_line_range("a.rs:7"), ("a.rs", Some(7), None));
+        assert_eq!(split_line_range("c:\\x"), ("c:\\x", None, None));
+    }
+
+    #[test]
+    fn picks_exact_symbols_within_their_container() {
+        let symbol = |name: &str, container: &str| SymbolLocation {
+            name: name.to_string(),
+            container_name: Some(container.to_string()),
+            uri: "file:///tmp/my%20project/lib.rs".to_string(),
+            start_line: 0,
+            end_line: 0,
+        };
+        let symbols = vec![
+            symbol("reload", "impl FolderStateStore"),
+            symbol("reload", "impl AppConfigState"),
+            symbol("Reload", "Command"),
+        ];
+        let picked = pick_symbol("AppConfigState::reload", &symbols).unwrap();
+        assert_eq!(
+            picked.container_name.as_deref(),
+            Some("impl AppConfigState")
+        );
+        assert_eq!(
+            pick_symbol("reload", &symbols)
+                .unwrap()
+                .container_name
+                .as_deref(),
+            Some("impl FolderStateStore")
+        );
+        assert!(pick_symbol("Missing", &symbols).is_none());
+        assert_eq!(
+            path_from_uri(&symbols[0].uri),
+            Some(PathBuf::from("/tmp/my project/lib.rs"))
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! Ratings users give assistant answers in the chat, kept for later review of
+//! which agents and prompts work. Chat messages themselves live in the
+//! webview; only the rating, an optional note and where the answer came from
+//! are stored here, keyed by message id.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app_paths;
+use crate::folder_state::FolderStateStore;
+
+pub const MESSAGE_RATINGS_FILE: &str = "message-ratings.json";
+
+/// Wrong answers listed with their notes in the summary.
+const RECENT_WRONG: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Rating {
+    Helpful,
+    Wrong,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageRating {
+    pub message_id: String,
+    pub rating: Rating,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub folder_id: Option<String>,
+    /// Agent the folder's session ran on when the answer was rated.
+    #[serde(default)]
+    pub agent_name: Option<String>,
+    pub rated_at: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRatings {
+    /// `None` for answers rated without a known agent.
+    pub agent_name: Option<String>,
+    pub helpful: usize,
+    pub wrong: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingsSummary {
+    pub helpful: usize,
+    pub wrong: usize,
+    /// Ordered by agent name.
+    pub by_agent: Vec<AgentRatings>,
+    /// Newest first.
+    pub recent_wrong: Vec<MessageRating>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MessageRatingsFile {
+    #[serde(default)]
+    ratings: BTreeMap<String, MessageRating>,
+}
+
+#[derive(Debug, Default)]
+pub struct MessageRatingStore {
+    path: Option<PathBuf>,
+    state: MessageRatingsFile,
+}
+
+impl MessageRatingStore {
+    pub fn initialize(&mut self, app_handle: &tauri::AppHandle) -> Result<(), String> {
+        let path = app_paths::app_root_dir(app_handle)?.join(MESSAGE_RATINGS_FILE);
+
+        if path.exists() {
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read message ratings '{}': {e}", path.display()))?;
+            self.state = serde_json::from_str(&contents).unwrap_or_else(|err| {
+                log::warn!(
+                    "Failed to parse message ratings '{}'. Starting fresh: {err}",
+                    path.display()
+                );
+                MessageRatingsFile::default()
+            });
+        }
+
+        self.path = Some(path);
+        Ok(())
+    }
+
+    pub fn get(&self, message_id: &str) -> Option<MessageRating> {
+        self.state.ratings.get(message_id).cloned()
+    }
+
+    /// Rates a message, replacing any earlier rating. `None` removes it.
+    pub fn rate(
+        &mut self,
+        message_id: &str,
+        rating: Option<Rating>,
+        note: Option<String>,
+        folder_id: Option<String>,
+        agent_name: Option<String>,
+    ) -> Result<Option<MessageRating>, String> {
+        if message_id.trim().is_empty() {
+            return Err("A rating needs a message id".to_string());
+        }
+        let Some(rating) = rating else {
+            if self.state.ratings.remove(message_id).is_some() {
+                self.persist()?;
+            }
+            return Ok(None);
+        };
+        let rated = MessageRating {
+            message_id: message_id.to_string(),
+            rating,
+            note: note
+                .map(|note| note.trim().to_string())
+                .filter(|note| !note.is_empty()),
+            folder_id,
+            agent_name,
+            rated_at: now_millis(),
+        };
+        self.state
+            .ratings
+            .insert(message_id.to_string(), rated.clone());
+        self.persist()?;
+        Ok(Some(rated))
+    }
+
+    pub fn summary(&self) -> RatingsSummary {
+        let mut summary = RatingsSummary::default();
+        let mut by_agent: BTreeMap<Option<String>, AgentRatings> = BTreeMap::new();
+        for rated in self.state.ratings.values() {
+            let agent = by_agent
+                .entry(rated.agent_name.clone())
+                .or_insert_with(|| AgentRatings {
+                    agent_name: rated.agent_name.clone(),
+                    ..AgentRatings::default()
+                });
+            match rated.rating {
+                Rating::Helpful => {
+                    summary.helpful += 1;
+                    agent.helpful += 1;
+                }
+                Rating::Wrong => {
+                    summary.wrong += 1;
+                    agent.wrong += 1;
+                    summary.recent_wrong.push(rated.clone());
+                }
+            }
+        }
+        summary.by_agent = by_agent.into_values().collect();
+        summary
+            .recent_wrong
+            .sort_by(|a, b| b.rated_at.cmp(&a.rated_at));
+        summary.recent_wrong.truncate(RECENT_WRONG);
+        summary
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                format!(
+                    "Failed to create message ratings directory '{}': {e}",
+                    parent.display()
+                )
+            })?;
+        }
+
+        let contents = serde_json::to_string_pretty(&self.state).map_err(|e| e.to_string())?;
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, contents).map_err(|e| {
+            format!(
+                "Failed to write message ratings '{}': {e}",
+                temp_path.display()
+            )
+        })?;
+        std::fs::rename(&temp_path, path).map_err(|e| {
+            format!(
+                "Failed to replace message ratings '{}': {e}",
+                path.display()
+            )
+        })
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+// -- Tauri IPC commands --
+
+/// Marks an assistant answer as helpful or wrong, with an optional note;
+/// `rating: null` takes the rating back. The agent is taken from the
+/// folder's session.
+#[tauri::command]
+pub async fn message_rate(
+    state: tauri::State<'_, std::sync::Mutex<MessageRatingStore>>,
+    folders: tauri::State<'_, std::sync::Mutex<FolderStateStore>>,
+    message_id: String,
+    rating: Option<Rating>,
+    note: Option<String>,
+    folder_id: Option<String>,
+) -> Result<Option<MessageRating>, String> {
+    let agent_name = match folder_id.as_deref() {
+        Some(folder_id) => {
+            folders
+                .lock()
+                .map_err(|e| e.to_string())?
+                .session(folder_id)
+                .agent_name
+        }
+        None => None,
+    };
+    let mut store = state.lock().map_err(|e| e.to_string())?;
+    store.rate(&message_id, rating, note, folder_id, agent_name)
+}
+
+#[tauri::command]
+pub async fn message_ratings_summary(
+    state: tauri::State<'_, std::sync::Mutex<MessageRatingStore>>,
+) -> Result<RatingsSummary, String> {
+    let store = state.lock().map_err(|e| e.to_string())?;
+    Ok(store.summary())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_ratings_per_agent() {
+        let mut store = MessageRatingStore::default();
+        let agent = |name: &str| Some(name.to_string());
+        store
+            .rate("m1", Some(Rating::Helpful), None, None, agent("codex"))
+            .unwrap();
+        store
+            .rate(
+                "m2",
+                Some(Rating::Wrong),
+                Some("  made up an API ".to_string()),
+                None,
+                agent("codex"),
+            )
+            .unwrap();
+        store
+            .rate(
+                "m3",
+                Some(Rating::Helpful),
+                Some(" ".to_string()),
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(store.get("m3").unwrap().note, None);
+
+        // Rating again replaces; rating with `None` takes it back.
+        store
+            .rate("m1", Some(Rating::Wrong), None, None, agent("claude"))
+            .unwrap();
+        store.rate("m3", None, None, None, None).unwrap();
+        assert!(store.get("m3").is_none());
+
+        let summary = store.summary();
+        assert_eq!((summary.helpful, summary.wrong), (0, 2));
+        assert_eq!(
+            summary.by_agent,
+            vec![
+                AgentRatings {
+                    agent_name: agent("claude"),
+                    helpful: 0,
+                    wrong: 1,
+                },
+                AgentRatings {
+                    agent_name: agent("codex"),
+                    helpful: 0,
+                    wrong: 1,
+                },
+            ]
+        );
+        let note = summary
+            .recent_wrong
+            .iter()
+            .find(|rated| rated.message_id == "m2")
+            .and_then(|rated| rated.note.clone());
+        assert_eq!(note.as_deref(), Some("made up an API"));
+        assert!(store
+            .rate(" ", Some(Rating::Helpful), None, None, None)
+            .is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+use tauri::Manager;
+use tauri_plugin_notification::NotificationExt;
+
+use crate::acp_core::StopReason;
+use crate::app_config::{self, NotificationConfig};
+use crate::MAIN_WINDOW_LABEL;
+
+/// Notifications shown since the window last had focus; mirrored on the dock badge.
+static PENDING_ATTENTION: AtomicI64 = AtomicI64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    TurnFinished,
+    PermissionRequest,
+    CommandFinished { elapsed: Duration },
+}
+
+impl NotificationKind {
+    fn enabled(self, config: &NotificationConfig) -> bool {
+        match self {
+            Self::TurnFinished => config.turn_finished,
+            Self::PermissionRequest => config.permission_request,
+            Self::CommandFinished { elapsed } => {
+                config.command_finished && elapsed >= config.command_min_duration
+            }
+        }
+    }
+}
+
+/// Shows an OS notification and asks for attention, unless the event type is
+/// turned off in `[notifications]` or the NeoAI window already has focus.
+pub fn notify(app_handle: &tauri::AppHandle, kind: NotificationKind, title: &str, body: &str) {
+    let config = app_handle
+        .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+        .and_then(|state| state.lock().ok().map(|state| state.notification_config()))
+        .unwrap_or_default();
+    if !kind.enabled(&config) {
+        return;
+    }
+
+    let Some(window) = app_handle.get_webview_window(MAIN_WINDOW_LABEL) else {
+        return;
+    };
+    if window.is_focused().unwrap_or(false) {
+        return;
+    }
+
+    if let Err(err) = app_handle
+        .notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+    {
+        log::warn!("Failed to show notification '{}': {}", title, err);
+    }
+
+    PENDING_ATTENTION.fetch_add(1, Ordering::Relaxed);
+    let _ = window.request_user_attention(Some(tauri::UserAttentionType::Informational));
+    #[cfg(target_os = "macos")]
+    {
+        let _ = window.set_badge_count(Some(PENDING_ATTENTION.load(Ordering::Relaxed)));
+    }
+}
+
+/// Clears the dock badge and attention request once the window gains focus.
+pub fn clear_attention(window: &tauri::Window) {
+    if PENDING_ATTENTION.swap(0, Ordering::Relaxed) == 0 {
+        return;
+    }
+    let _ = window.request_user_attention(None);
+    #[cfg(target_os = "macos")]
+    {
+        let _ = window.set_badge_count(None);
+    }
+}
+
+/// Human-readable body for a finished prompt turn, or `None` when the user
+/// cancelled it and needs no reminder.
+pub fn turn_finished_body(stop_reason: StopReason) -> Option<String> {
+    let body = match stop_reason {
+        StopReason::Cancelled => return None,
+        StopReason::EndTurn => "The agent finished its turn.",
+        StopReason::MaxTokens => "The agent stopped at the token limit.",
+        StopReason::MaxTurnRequests => "The agent stopped after too many requests.",
+        StopReason::Refusal => "The agent refused to continue.",
+        StopReason::Error => "The agent's turn failed.",
+    };
+    Some(body.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_notifications_respect_the_minimum_duration() {
+        let config = NotificationConfig::default();
+        let quick = NotificationKind::CommandFinished {
+            elapsed: Duration::from_secs(2),
+        };
+        let slow = NotificationKind::CommandFinished {
+            elapsed: config.command_min_duration,
+        };
+        assert!(!quick.enabled(&config));
+        assert!(slow.enabled(&config));
+
+        let config = NotificationConfig {
+            command_finished: false,
+            ..NotificationConfig::default()
+        };
+        assert!(!slow.enabled(&config));
+        assert!(NotificationKind::TurnFinished.enabled(&config));
+    }
+
+    #[test]
+    fn skips_cancelled_turns() {
+        assert_eq!(turn_finished_body(StopReason::Cancelled), None);
+        assert!(turn_finished_body(StopReason::EndTurn).is_some());
+    }
+}
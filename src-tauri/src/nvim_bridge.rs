@@ -1,6 +1,7 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use async_trait::async_trait;
 use nvim_rs::compat::tokio::Compat;
@@ -10,13 +11,26 @@ use rmpv::Value;
 use serde::{Deserialize, Serialize};
 use tauri::{Emitter, Manager};
 use tokio::io::WriteHalf;
-use tokio::net::UnixStream;
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
 
+use crate::app_config::{self, KeymapConfig};
+use crate::bridge_stats::{BridgeStats, TerminalBridgeStats};
+use crate::edit_snapshots;
+use crate::error::{ErrorCode, NeoaiError};
+use crate::folder_state;
+use crate::git;
+
 // -- Types --
 
-type Writer = Compat<WriteHalf<UnixStream>>;
+/// Transport used by `nvim_create::new_path`: unix sockets, or named pipes on
+/// Windows (the address comes from `SocketManager::socket_path`).
+#[cfg(unix)]
+type Connection = tokio::net::UnixStream;
+#[cfg(windows)]
+type Connection = tokio::net::windows::named_pipe::NamedPipeClient;
+
+type Writer = Compat<WriteHalf<Connection>>;
 
 #[derive(Clone)]
 struct NvimHandler {
@@ -29,6 +43,12 @@ impl Handler for NvimHandler {
     type Writer = Writer;
 
     async fn handle_notify(&self, name: String, args: Vec<Value>, _neovim: Neovim<Self::Writer>) {
+        // Sent on cursor movement; too frequent for the debug trace.
+        if name == "libg_breadcrumbs" {
+            self.forward_breadcrumbs(args).await;
+            return;
+        }
+
         emit_bridge_debug(
             &self.app_handle,
             &self.terminal_id,
@@ -104,10 +124,29 @@ impl Handler for NvimHandler {
     }
 }
 
+impl NvimHandler {
+    async fn forward_breadcrumbs(&self, args: Vec<Value>) {
+        let Some(Value::String(payload)) = args.into_iter().next() else {
+            return;
+        };
+        let raw = match parse_breadcrumbs(payload.as_str().unwrap_or_default()) {
+            Ok(raw) => raw,
+            Err(err) => {
+                log::debug!("Ignoring breadcrumbs from {}: {}", self.terminal_id, err);
+                return;
+            }
+        };
+        let breadcrumbs = complete_breadcrumbs(&self.app_handle, &self.terminal_id, raw).await;
+        let _ = self.app_handle.emit("nvim-breadcrumbs", &breadcrumbs);
+    }
+}
+
 struct NvimConnection {
     nvim: Neovim<Writer>,
-    _io_handle: JoinHandle<Result<(), Box<nvim_rs::error::LoopError>>>,
+    io_handle: JoinHandle<Result<(), Box<nvim_rs::error::LoopError>>>,
     socket_path: String,
+    /// Process id reported by Neovim when the bridge connected.
+    pid: Option<u32>,
 }
 
 pub struct NvimBridgeState {
@@ -120,6 +159,51 @@ impl NvimBridgeState {
             connections: HashMap::new(),
         }
     }
+
+    /// Drops every connection, stopping its I/O task. Connections busy with a
+    /// request are left to close when their socket does.
+    pub fn disconnect_all(&mut self) -> usize {
+        let count = self.connections.len();
+        for (_, conn) in self.connections.drain() {
+            if let Ok(conn) = conn.try_lock() {
+                conn.io_handle.abort();
+            }
+        }
+        count
+    }
+}
+
+/// Asks every connected Neovim to reload buffers whose files changed on disk.
+pub async fn checktime_all(state: &Mutex<NvimBridgeState>) {
+    let connections: Vec<_> = state.lock().await.connections.values().cloned().collect();
+    for conn in connections {
+        let conn = conn.lock().await;
+        if let Err(err) = conn.nvim.command("silent! checktime").await {
+            log::debug!("checktime failed on {}: {}", conn.socket_path, err);
+        }
+    }
+}
+
+pub async fn is_connected(state: &Mutex<NvimBridgeState>, terminal_id: &str) -> bool {
+    state.lock().await.connections.contains_key(terminal_id)
+}
+
+/// Process ids of connected Neovim instances, keyed by terminal id.
+pub async fn connected_pids(state: &Mutex<NvimBridgeState>) -> Vec<(String, u32)> {
+    let connections: Vec<_> = state
+        .lock()
+        .await
+        .connections
+        .iter()
+        .map(|(terminal_id, conn)| (terminal_id.clone(), conn.clone()))
+        .collect();
+    let mut pids = Vec::new();
+    for (terminal_id, conn) in connections {
+        if let Some(pid) = conn.lock().await.pid {
+            pids.push((terminal_id, pid));
+        }
+    }
+    pids
 }
 
 // -- Serializable types for IPC --
@@ -131,6 +215,29 @@ pub struct CursorPosition {
     pub col: i64,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum BufferKind {
+    File,
+    /// A `:terminal` buffer; `visible_lines` hold its latest output.
+    Terminal,
+}
+
+/// What a `:terminal` buffer is running.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalBufferInfo {
+    /// Title the running program set, if any.
+    #[serde(default)]
+    pub title: String,
+    /// Command the terminal was started with.
+    #[serde(default)]
+    pub command: String,
+    /// Whether its job is still running.
+    #[serde(default)]
+    pub running: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct NvimContext {
@@ -140,8 +247,181 @@ pub struct NvimContext {
     pub buffer_id: i64,
     pub line_count: i64,
     pub modified: bool,
+    /// `b:changedtick`, to tell later whether the buffer changed.
+    #[serde(default)]
+    pub changedtick: i64,
     pub visible_lines: Vec<String>,
     pub visible_range: (i64, i64),
+    pub buffer_kind: BufferKind,
+    /// Set when `buffer_kind` is `terminal`.
+    pub terminal: Option<TerminalBufferInfo>,
+    /// Closed folds within `visible_range`, each standing in `visible_lines`
+    /// as its fold text. Only filled when folds were requested.
+    #[serde(default)]
+    pub folds: Vec<FoldSummary>,
+}
+
+/// A closed fold, summarized by `foldtextresult()`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FoldSummary {
+    pub start: i64,
+    pub end: i64,
+    pub text: String,
+}
+
+/// Lines of a file buffer sent around the cursor.
+const CONTEXT_RADIUS: i64 = 50;
+
+/// Lua returning the rows the current window shows within `radius` rows of
+/// the cursor: unfolded lines as they are and each closed fold as one row of
+/// its fold text.
+const FOLDED_CONTEXT_LUA: &str = r#"
+local radius = ...
+local cursor = vim.api.nvim_win_get_cursor(0)[1]
+local line_count = vim.api.nvim_buf_line_count(0)
+local function fold_start(lnum)
+    local start = vim.fn.foldclosed(lnum)
+    return start == -1 and lnum or start
+end
+local function fold_end(lnum)
+    local last = vim.fn.foldclosedend(lnum)
+    return last == -1 and lnum or last
+end
+
+local first, rows = fold_start(cursor), 0
+while first > 1 and rows < radius do
+    first = fold_start(first - 1)
+    rows = rows + 1
+end
+local last = fold_end(cursor)
+rows = 0
+while last < line_count and rows < radius do
+    last = fold_end(last + 1)
+    rows = rows + 1
+end
+
+local lines, folds = {}, {}
+local lnum = first
+while lnum <= last do
+    local fold_last = vim.fn.foldclosedend(lnum)
+    if fold_last ~= -1 then
+        local text = vim.fn.foldtextresult(lnum)
+        table.insert(lines, text)
+        table.insert(folds, { start = lnum, ["end"] = fold_last, text = text })
+        lnum = fold_last + 1
+    else
+        table.insert(lines, vim.fn.getline(lnum))
+        lnum = lnum + 1
+    end
+end
+return vim.json.encode({ first = first, last = last, lines = lines, folds = folds })
+"#;
+
+/// Output lines sent for a `:terminal` buffer.
+const TERMINAL_OUTPUT_LINES: i64 = 100;
+
+/// Lua defining `terminal_tail(buf, max_lines)`: for a `:terminal` buffer, its
+/// title, command, job state and last `max_lines` lines of output; `nil` for
+/// any other buffer. Terminal buffers end in blank rows, which are skipped.
+const TERMINAL_TAIL_LUA: &str = r#"
+local function terminal_tail(buf, max_lines)
+    if vim.bo[buf].buftype ~= "terminal" then
+        return nil
+    end
+    local last = vim.api.nvim_buf_line_count(buf)
+    while last > 1 and vim.api.nvim_buf_get_lines(buf, last - 1, last, false)[1] == "" do
+        last = last - 1
+    end
+    local first = math.max(last - max_lines + 1, 1)
+    local chan = vim.bo[buf].channel
+    return {
+        title = vim.b[buf].term_title or "",
+        command = vim.api.nvim_buf_get_name(buf):match("^term://.-//%d+:(.*)$") or "",
+        running = chan > 0 and vim.fn.jobwait({ chan }, 0)[1] == -1,
+        start = first,
+        lines = vim.api.nvim_buf_get_lines(buf, first - 1, last, false),
+    }
+end
+"#;
+
+/// Which side of the anchor window a split sits on.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum WindowPlacement {
+    Left,
+    Right,
+    Above,
+    Below,
+}
+
+/// Lua defining `window_placement(win, anchor)`: `"left"`, `"right"`,
+/// `"above"` or `"below"` for a split beside `anchor`, `nil` for `anchor`
+/// itself and windows overlapping it. Left and right win over above and
+/// below for diagonal neighbours.
+const WINDOW_PLACEMENT_LUA: &str = r#"
+local function window_placement(win, anchor)
+    if win == anchor then
+        return nil
+    end
+    local pos = vim.api.nvim_win_get_position(win)
+    local anchor_pos = vim.api.nvim_win_get_position(anchor)
+    if pos[2] >= anchor_pos[2] + vim.api.nvim_win_get_width(anchor) then
+        return "right"
+    elseif pos[2] + vim.api.nvim_win_get_width(win) <= anchor_pos[2] then
+        return "left"
+    elseif pos[1] >= anchor_pos[1] + vim.api.nvim_win_get_height(anchor) then
+        return "below"
+    elseif pos[1] + vim.api.nvim_win_get_height(win) <= anchor_pos[1] then
+        return "above"
+    end
+    return nil
+end
+"#;
+
+/// A window of a tabpage, with its buffer and where it sits on screen.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowLayout {
+    pub id: i64,
+    /// Window number within the tabpage, as in `<N>wincmd w`.
+    pub number: i64,
+    /// The tabpage's active window.
+    pub current: bool,
+    pub floating: bool,
+    pub buffer_id: i64,
+    pub file_path: String,
+    pub file_type: String,
+    /// `buftype`, empty for a file buffer.
+    pub buf_type: String,
+    pub modified: bool,
+    pub cursor: CursorPosition,
+    /// Screen position (0-based) and size in cells.
+    pub row: i64,
+    pub col: i64,
+    pub width: i64,
+    pub height: i64,
+    /// Side of the tabpage's active window this split sits on; `None` for
+    /// the active window itself and floating windows.
+    #[serde(default)]
+    pub placement: Option<WindowPlacement>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TabpageLayout {
+    pub number: i64,
+    pub current: bool,
+    pub windows: Vec<WindowLayout>,
+}
+
+/// Every tabpage and window of a Neovim, plus the screen size they fill.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NvimLayout {
+    pub columns: i64,
+    pub lines: i64,
+    pub tabpages: Vec<TabpageLayout>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -154,6 +434,84 @@ pub struct Diagnostic {
     pub source: String,
 }
 
+/// Project roots Neovim knows for the current buffer.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceRoots {
+    pub file_path: String,
+    /// Workspace folders of the LSP clients attached to the buffer.
+    #[serde(default)]
+    pub lsp_folders: Vec<String>,
+    /// Nearest directory above the file holding one of [`ROOT_MARKERS`].
+    #[serde(default)]
+    pub marker_root: Option<String>,
+    /// The marker found there, e.g. `Cargo.toml`.
+    #[serde(default)]
+    pub marker: Option<String>,
+    /// Neovim's working directory.
+    pub cwd: String,
+    /// Best working directory for an agent session: the innermost LSP folder
+    /// holding the file, else the marker root, else `cwd`.
+    #[serde(default)]
+    pub root: String,
+}
+
+/// Files marking a project root, closest match winning.
+const ROOT_MARKERS: &[&str] = &[
+    ".git",
+    "Cargo.toml",
+    "package.json",
+    "go.mod",
+    "pyproject.toml",
+    "setup.py",
+    "Gemfile",
+    "mix.exs",
+    "pom.xml",
+    "build.gradle",
+    "Makefile",
+];
+
+/// Options for `nvim_insert_at_cursor`.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InsertOptions {
+    /// Put the text in the unnamed register instead of the buffer.
+    #[serde(default)]
+    pub register: bool,
+}
+
+/// Where buffer hunks came from.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum HunkSource {
+    /// gitsigns, which includes unsaved changes.
+    Gitsigns,
+    /// `git diff` of the file on disk.
+    Git,
+}
+
+/// Changes to the current buffer against the git index.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BufferHunks {
+    pub file_path: String,
+    pub source: HunkSource,
+    pub hunks: Vec<git::GitDiffHunk>,
+}
+
+/// A `workspace/symbol` match reported by one of Neovim's LSP clients.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolLocation {
+    pub name: String,
+    #[serde(default)]
+    pub container_name: Option<String>,
+    pub uri: String,
+    /// 0-based, as in LSP.
+    pub start_line: u32,
+    pub end_line: u32,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct BufferContent {
@@ -172,6 +530,65 @@ pub struct BufferEdit {
     pub file_path: Option<String>,
     #[serde(default)]
     pub target_line: Option<i64>,
+    /// Lines the range held when the edit was proposed. The edit conflicts
+    /// when the buffer no longer holds them.
+    #[serde(default)]
+    pub expected_lines: Option<Vec<String>>,
+    /// The buffer's `changedtick` when the edit was proposed, for edits
+    /// without `expected_lines`: any change since then is a conflict.
+    #[serde(default)]
+    pub changedtick: Option<i64>,
+}
+
+/// An edit whose range changed after it was proposed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EditConflict {
+    pub file_path: Option<String>,
+    pub start_line: i64,
+    pub end_line: i64,
+    /// What the range holds now.
+    pub current_lines: Vec<String>,
+    pub changedtick: i64,
+}
+
+/// Outcome of `nvim_apply_edits`. When any edit conflicts, none are applied.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyEditsResult {
+    pub applied: bool,
+    pub conflicts: Vec<EditConflict>,
+}
+
+/// One step of [`nvim_exec_batch`], tagged by `op`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "op")]
+pub enum BridgeOp {
+    /// Shows the file in the current window, loading it if needed.
+    OpenFile { file_path: String },
+    /// 1-based line (clamped to the buffer) and 0-based column in the
+    /// current window.
+    SetCursor {
+        line: i64,
+        #[serde(default)]
+        col: i64,
+    },
+    /// Targets the current buffer when the edit has no `filePath`.
+    ApplyEdit(BufferEdit),
+    /// Writes the file's buffer, or the current one, to disk.
+    Write {
+        #[serde(default)]
+        file_path: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResult {
+    pub applied: usize,
+    /// Current buffer and 1-based cursor line after the last step.
+    pub file_path: String,
+    pub line: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -192,6 +609,34 @@ pub struct NvimHealth {
     pub last_error: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum KeymapBindingStatus {
+    /// The key was free.
+    Mapped,
+    /// The key was taken and has been replaced.
+    Overridden,
+    /// The key was taken, so the action was bound under the relocate prefix.
+    Relocated,
+    /// The key (and its relocated form, if any) was taken and left alone.
+    Skipped,
+}
+
+/// Outcome of binding one NeoAI keymap.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct KeymapBinding {
+    pub mode: String,
+    /// The key NeoAI wanted, e.g. `<leader>mf`.
+    pub lhs: String,
+    pub action: String,
+    pub status: KeymapBindingStatus,
+    /// Where the action ended up, `None` when skipped.
+    pub bound_to: Option<String>,
+    /// The user's or a plugin's mapping found on `lhs`.
+    pub existing: Option<String>,
+}
+
 // -- Neovim action types (sent from Neovim → Tauri via rpcnotify) --
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -270,6 +715,48 @@ pub struct NvimCursorFollowEvent {
     pub source: String,
 }
 
+/// One scope enclosing the cursor, e.g. `impl AppConfigState` or `fn reload`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BreadcrumbSymbol {
+    pub name: String,
+    /// `function`, `method`, `class`, `struct`, `impl`, `module`, ...
+    pub kind: String,
+}
+
+/// Where the cursor is, for the header: `project ▸ src/foo.rs ▸ fn bar()`.
+/// Also the payload of `nvim-breadcrumbs`.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Breadcrumbs {
+    pub terminal_id: String,
+    /// Name of the folder the terminal belongs to, or of Neovim's cwd.
+    pub project: Option<String>,
+    pub file_path: String,
+    /// `file_path` relative to the project, when it is inside it.
+    pub relative_path: Option<String>,
+    /// 1-based cursor line.
+    pub line: i64,
+    /// Outermost scope first.
+    pub symbols: Vec<BreadcrumbSymbol>,
+    /// `lsp` or `treesitter`; `None` when no scope was found.
+    pub symbol_source: Option<String>,
+    pub branch: Option<String>,
+}
+
+/// What `_G.libg.breadcrumbs()` reports from Neovim.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawBreadcrumbs {
+    file_path: String,
+    cwd: String,
+    line: i64,
+    #[serde(default)]
+    symbols: Vec<BreadcrumbSymbol>,
+    #[serde(default)]
+    source: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct LuaBufferEdit<'a> {
@@ -278,6 +765,17 @@ struct LuaBufferEdit<'a> {
     new_lines: &'a [String],
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LuaEditCheck<'a> {
+    start_line: i64,
+    end_line: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected_lines: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changedtick: Option<i64>,
+}
+
 // -- Helpers --
 
 fn emit_bridge_debug(
@@ -323,7 +821,48 @@ fn parse_lua_json(result: Value) -> Result<serde_json::Value, String> {
     serde_json::from_str(&json_str).map_err(|e| format!("Failed to parse lua JSON: {}", e))
 }
 
-fn apply_line_window(content: &str, line: Option<u32>, limit: Option<u32>) -> String {
+fn parse_breadcrumbs(json: &str) -> Result<RawBreadcrumbs, String> {
+    let mut value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| format!("Failed to parse breadcrumbs: {}", e))?;
+    // An empty Lua table encodes as `{}` rather than `[]`.
+    if !value["symbols"].is_array() {
+        value["symbols"] = serde_json::Value::Array(Vec::new());
+    }
+    serde_json::from_value(value).map_err(|e| format!("Failed to parse breadcrumbs: {}", e))
+}
+
+/// Adds the project, relative path and git branch to what Neovim reported.
+/// The project root is the terminal's folder, falling back to Neovim's cwd.
+async fn complete_breadcrumbs(
+    app_handle: &tauri::AppHandle,
+    terminal_id: &str,
+    raw: RawBreadcrumbs,
+) -> Breadcrumbs {
+    let root = folder_state::folder_id_for_terminal(terminal_id)
+        .and_then(|folder_id| folder_state::resolve_folder_path(app_handle, folder_id).ok())
+        .unwrap_or_else(|| PathBuf::from(&raw.cwd));
+    let relative_path = Path::new(&raw.file_path)
+        .strip_prefix(&root)
+        .ok()
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .map(|relative| relative.to_string_lossy().replace('\\', "/"));
+    let branch = git::current_branch(&root).await.ok().flatten();
+
+    Breadcrumbs {
+        terminal_id: terminal_id.to_string(),
+        project: root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned()),
+        file_path: raw.file_path,
+        relative_path,
+        line: raw.line,
+        symbol_source: raw.source.filter(|_| !raw.symbols.is_empty()),
+        symbols: raw.symbols,
+        branch,
+    }
+}
+
+pub(crate) fn apply_line_window(content: &str, line: Option<u32>, limit: Option<u32>) -> String {
     if line.is_none() && limit.is_none() {
         return content.to_string();
     }
@@ -380,6 +919,92 @@ fn group_edits_by_file(mut edits: Vec<BufferEdit>) -> Vec<(Option<String>, Vec<B
     grouped
 }
 
+/// Absolute paths of the files `edits` touch; edits without a path target the
+/// current buffer. Unnamed buffers are skipped.
+async fn edit_target_paths(nvim: &Neovim<Writer>, edits: &[BufferEdit]) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for (file_path, _) in group_edits_by_file(edits.to_vec()) {
+        let resolved = match file_path {
+            Some(path) => {
+                nvim.call_function("fnamemodify", vec![Value::from(path), Value::from(":p")])
+                    .await
+            }
+            None => nvim.call_function("expand", vec![Value::from("%:p")]).await,
+        };
+        let Some(path) = resolved
+            .ok()
+            .and_then(|value| value.as_str().map(PathBuf::from))
+        else {
+            continue;
+        };
+        if !path.as_os_str().is_empty() && !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Edits whose ranges changed since they were proposed. Edits carrying
+/// neither `expected_lines` nor `changedtick` are never stale.
+async fn find_edit_conflicts(
+    nvim: &Neovim<Writer>,
+    edits: &[BufferEdit],
+) -> Result<Vec<EditConflict>, String> {
+    let mut conflicts = Vec::new();
+    for (file_path, group) in group_edits_by_file(edits.to_vec()) {
+        if group
+            .iter()
+            .all(|edit| edit.expected_lines.is_none() && edit.changedtick.is_none())
+        {
+            continue;
+        }
+        let checks: Vec<LuaEditCheck<'_>> = group
+            .iter()
+            .map(|edit| LuaEditCheck {
+                start_line: edit.start_line,
+                end_line: edit.end_line,
+                expected_lines: edit.expected_lines.as_deref(),
+                changedtick: edit.changedtick,
+            })
+            .collect();
+        let checks_json = serde_json::to_string(&checks).map_err(|e| e.to_string())?;
+        let path_value = match &file_path {
+            Some(path) => Value::from(path.clone()),
+            None => Value::Nil,
+        };
+        let result = nvim
+            .exec_lua(
+                build_check_edits_lua(),
+                vec![path_value, Value::from(checks_json)],
+            )
+            .await
+            .map_err(|e| format!("Neovim edit check lua failed: {}", e))?;
+        let payload = parse_lua_json(result)?;
+        let changedtick = payload["changedtick"].as_i64().unwrap_or_default();
+        // An empty Lua table encodes as `{}` rather than `[]`.
+        let Some(stale) = payload["stale"].as_array() else {
+            continue;
+        };
+        for entry in stale {
+            let Some(edit) = entry["index"]
+                .as_u64()
+                .and_then(|index| group.get(index as usize))
+            else {
+                continue;
+            };
+            conflicts.push(EditConflict {
+                file_path: file_path.clone(),
+                start_line: edit.start_line,
+                end_line: edit.end_line,
+                current_lines: serde_json::from_value(entry["currentLines"].clone())
+                    .unwrap_or_default(),
+                changedtick,
+            });
+        }
+    }
+    Ok(conflicts)
+}
+
 fn target_line_for_group(edits: &[BufferEdit]) -> Option<i64> {
     edits
         .iter()
@@ -487,13 +1112,12 @@ local function has_map(lhs, mode)
     local rhs = vim.fn.maparg(lhs, mode)
     return type(rhs) == "string" and rhs ~= ""
 end
-local maps_ok =
-    has_map("<leader>mf", "n")
-    and has_map("<leader>mi", "n")
-    and has_map("<leader>me", "n")
-    and has_map("<leader>ma", "n")
-    and has_map("<leader>me", "v")
-    and has_map("<leader>ma", "v")
+local maps_ok = has_libg and type(_G.libg.bound_keymaps) == "table"
+if maps_ok then
+    for _, bound in ipairs(_G.libg.bound_keymaps) do
+        maps_ok = maps_ok and has_map(bound.lhs, bound.mode)
+    end
+end
 local result = {{
     hasLibg = has_libg,
     hasChannel = has_channel,
@@ -513,13 +1137,59 @@ async fn current_channel_id(nvim: &Neovim<Writer>) -> Result<i64, String> {
     extract_channel_id(&api_info)
 }
 
-async fn inject_keymaps(nvim: &Neovim<Writer>) -> Result<i64, String> {
+async fn inject_keymaps(
+    nvim: &Neovim<Writer>,
+    config: &KeymapConfig,
+) -> Result<Vec<KeymapBinding>, String> {
     let channel_id = current_channel_id(nvim).await?;
     let lua_setup = build_lua_setup(channel_id);
     nvim.exec_lua(&lua_setup, vec![])
         .await
         .map_err(|e| format!("Failed to inject lua keybindings: {}", e))?;
-    Ok(channel_id)
+    let result = nvim
+        .exec_lua(
+            BIND_KEYMAPS_LUA,
+            vec![
+                Value::from(config.on_conflict.as_str()),
+                Value::from(config.relocate_prefix.as_str()),
+            ],
+        )
+        .await
+        .map_err(|e| format!("Failed to bind keymaps: {}", e))?;
+    nvim.exec_lua(build_breadcrumbs_lua(), vec![Value::from(channel_id)])
+        .await
+        .map_err(|e| format!("Failed to inject breadcrumb tracking: {}", e))?;
+    serde_json::from_value(parse_lua_json(result)?)
+        .map_err(|e| format!("Failed to parse keymap report: {}", e))
+}
+
+fn keymap_config(app_handle: &tauri::AppHandle) -> KeymapConfig {
+    app_handle
+        .try_state::<std::sync::Mutex<app_config::AppConfigState>>()
+        .and_then(|state| state.lock().ok().map(|state| state.keymap_config()))
+        .unwrap_or_default()
+}
+
+fn log_keymap_collisions(terminal_id: &str, bindings: &[KeymapBinding]) {
+    for binding in bindings {
+        let Some(existing) = &binding.existing else {
+            continue;
+        };
+        log::info!(
+            "Keymap {} ({}) for terminal {} is already mapped to {}: {:?}{}",
+            binding.lhs,
+            binding.mode,
+            terminal_id,
+            existing,
+            binding.status,
+            binding
+                .bound_to
+                .as_deref()
+                .filter(|lhs| *lhs != binding.lhs)
+                .map(|lhs| format!(" to {lhs}"))
+                .unwrap_or_default(),
+        );
+    }
 }
 
 async fn probe_keymaps(nvim: &Neovim<Writer>, channel_id: i64) -> Result<bool, String> {
@@ -576,7 +1246,12 @@ local function get_visual_selection()
 end
 
 -- Helper: send action to host and surface rpc channel errors in Neovim.
+-- Remembers where it was sent from so progress can be shown there.
 local function send_action(action_name, payload)
+    _G.libg.action_origin = {{
+        buf = vim.api.nvim_get_current_buf(),
+        line = vim.api.nvim_win_get_cursor(0)[1],
+    }}
     local ok, err = pcall(vim.rpcnotify, {channel_id}, "libg_action", payload)
     if not ok then
         vim.notify("[libg] Failed to send " .. action_name .. " to agent: " .. tostring(err), vim.log.levels.ERROR)
@@ -689,14 +1364,6 @@ function _G.libg.ask(use_selection)
     }})
 end
 
--- Keybindings (<leader>m prefix = "model")
-vim.keymap.set("n", "<leader>mf", function() _G.libg.fix_diagnostic() end, {{ desc = "[libg] Fix diagnostic" }})
-vim.keymap.set("n", "<leader>mi", function() _G.libg.implement() end, {{ desc = "[libg] Implement" }})
-vim.keymap.set("n", "<leader>me", function() _G.libg.explain(false) end, {{ desc = "[libg] Explain" }})
-vim.keymap.set("v", "<leader>me", function() _G.libg.explain(true) end, {{ desc = "[libg] Explain selection" }})
-vim.keymap.set("n", "<leader>ma", function() _G.libg.ask(false) end, {{ desc = "[libg] Ask" }})
-vim.keymap.set("v", "<leader>ma", function() _G.libg.ask(true) end, {{ desc = "[libg] Ask with selection" }})
-
 -- User commands
 vim.api.nvim_create_user_command("LibgFixDiagnostic", function() _G.libg.fix_diagnostic() end, {{}})
 vim.api.nvim_create_user_command("LibgImplement", function() _G.libg.implement() end, {{}})
@@ -753,43 +1420,645 @@ return vim.json.encode({
 "#
 }
 
-fn build_write_file_lua() -> &'static str {
+fn build_workspace_symbols_lua() -> &'static str {
     r#"
-local input_path, content = ...
-if type(input_path) ~= "string" or input_path == "" then
-    return vim.json.encode({ ok = false, error = "missing file path" })
+local query, timeout_ms = ...
+local get_clients = vim.lsp.get_clients or vim.lsp.get_active_clients
+local bufnr = vim.api.nvim_get_current_buf()
+if #get_clients({ bufnr = bufnr }) == 0 then
+    bufnr = nil
+    for _, client in ipairs(get_clients()) do
+        local attached = vim.lsp.get_buffers_by_client_id(client.id)
+        if attached[1] then
+            bufnr = attached[1]
+            break
+        end
+    end
 end
-if type(content) ~= "string" then
-    return vim.json.encode({ ok = false, error = "missing file content" })
+if not bufnr then
+    return vim.json.encode({ ok = false, error = "no LSP client is attached" })
 end
 
-local path = vim.fn.fnamemodify(input_path, ":p")
-local current_buf = vim.api.nvim_get_current_buf()
-local current_path = vim.fn.fnamemodify(vim.api.nvim_buf_get_name(current_buf), ":p")
-local current_cursor = vim.api.nvim_win_get_cursor(0)
-local same_file = current_path == path
-local bufnr = vim.fn.bufnr(path)
-if bufnr == -1 then
-    bufnr = vim.fn.bufadd(path)
+local responses = vim.lsp.buf_request_sync(bufnr, "workspace/symbol", { query = query }, timeout_ms) or {}
+local symbols = {}
+for _, response in pairs(responses) do
+    for _, symbol in ipairs(response.result or {}) do
+        local location = symbol.location or {}
+        if location.uri and location.range then
+            table.insert(symbols, {
+                name = symbol.name,
+                containerName = symbol.containerName,
+                uri = location.uri,
+                startLine = location.range.start.line,
+                endLine = location.range["end"].line,
+            })
+        end
+    end
 end
-if bufnr == -1 then
-    return vim.json.encode({ ok = false, error = "failed to create buffer for file" })
+
+return vim.json.encode({ ok = true, symbols = symbols })
+"#
+}
+
+/// Current buffer's path and buftype, plus its hunks as a patch when gitsigns
+/// is attached to it.
+fn build_buffer_hunks_lua() -> &'static str {
+    r#"
+local bufnr = vim.api.nvim_get_current_buf()
+local result = {
+    path = vim.api.nvim_buf_get_name(bufnr),
+    buftype = vim.bo[bufnr].buftype,
+}
+local ok, gitsigns = pcall(require, "gitsigns")
+local hunks = ok and type(gitsigns.get_hunks) == "function" and gitsigns.get_hunks(bufnr) or nil
+if hunks then
+    local patch = {}
+    for _, hunk in ipairs(hunks) do
+        table.insert(patch, hunk.head)
+        vim.list_extend(patch, hunk.lines)
+    end
+    result.patch = table.concat(patch, "\n")
 end
+return vim.json.encode(result)
+"#
+}
 
-if vim.fn.bufloaded(bufnr) == 0 then
-    vim.fn.bufload(bufnr)
+/// Replaces the progress indicator with a spinner and `message` as virtual
+/// text at the end of the line the last action was sent from (the cursor
+/// line when there is none), or only removes it when `message` is nil.
+const PROGRESS_LUA: &str = r#"
+local message = ...
+_G.libg = _G.libg or {}
+local libg = _G.libg
+local ns = vim.api.nvim_create_namespace("libg_progress")
+if libg.progress then
+    libg.progress.timer:stop()
+    libg.progress.timer:close()
+    if vim.api.nvim_buf_is_valid(libg.progress.buf) then
+        vim.api.nvim_buf_clear_namespace(libg.progress.buf, ns, 0, -1)
+    end
+    libg.progress = nil
 end
-if not vim.api.nvim_buf_is_valid(bufnr) then
-    return vim.json.encode({ ok = false, error = "invalid buffer for file" })
+if message == nil then
+    return
 end
 
-local old_lines = vim.api.nvim_buf_get_lines(bufnr, 0, -1, false)
-local lines = vim.split(content, "\n", { plain = true })
-if #lines > 0 and lines[#lines] == "" then
-    table.remove(lines, #lines)
+local origin = libg.action_origin or {}
+local buf, line = origin.buf, origin.line
+if not buf or not vim.api.nvim_buf_is_valid(buf) then
+    buf = vim.api.nvim_get_current_buf()
+    line = vim.api.nvim_win_get_cursor(0)[1]
 end
-if #lines == 0 then
-    lines = { "" }
+line = math.min(line, vim.api.nvim_buf_line_count(buf)) - 1
+
+local frames = { "⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏" }
+local frame, mark = 1, nil
+local function draw()
+    if not vim.api.nvim_buf_is_valid(buf) then
+        return
+    end
+    if mark then
+        -- Follow the line when edits above it move it.
+        local pos = vim.api.nvim_buf_get_extmark_by_id(buf, ns, mark, {})
+        line = pos[1] or line
+    end
+    line = math.min(line, vim.api.nvim_buf_line_count(buf) - 1)
+    mark = vim.api.nvim_buf_set_extmark(buf, ns, line, 0, {
+        id = mark,
+        virt_text = { { frames[frame] .. " " .. message, "Comment" } },
+        virt_text_pos = "eol",
+    })
+    frame = frame % #frames + 1
+end
+draw()
+local timer = (vim.uv or vim.loop).new_timer()
+timer:start(100, 100, vim.schedule_wrap(draw))
+libg.progress = { buf = buf, timer = timer }
+"#;
+
+/// Binds the `<leader>m` keymaps, leaving alone, replacing or relocating
+/// keys the user or a plugin already mapped depending on the policy. Maps
+/// from an earlier injection are removed first so re-injecting starts clean.
+/// Returns one report entry per binding.
+const BIND_KEYMAPS_LUA: &str = r#"
+local policy, prefix = ...
+local libg = _G.libg
+local bindings = {
+    { mode = "n", key = "f", action = "fixDiagnostic", desc = "[libg] Fix diagnostic", fn = function() libg.fix_diagnostic() end },
+    { mode = "n", key = "i", action = "implement", desc = "[libg] Implement", fn = function() libg.implement() end },
+    { mode = "n", key = "e", action = "explain", desc = "[libg] Explain", fn = function() libg.explain(false) end },
+    { mode = "v", key = "e", action = "explain", desc = "[libg] Explain selection", fn = function() libg.explain(true) end },
+    { mode = "n", key = "a", action = "ask", desc = "[libg] Ask", fn = function() libg.ask(false) end },
+    { mode = "v", key = "a", action = "ask", desc = "[libg] Ask with selection", fn = function() libg.ask(true) end },
+}
+
+local function is_libg(map)
+    return type(map.desc) == "string" and vim.startswith(map.desc, "[libg]")
+end
+
+-- Describes a mapping on lhs that NeoAI didn't make, or returns nil.
+local function foreign_map(lhs, mode)
+    local map = vim.fn.maparg(lhs, mode, false, true)
+    if vim.tbl_isempty(map) or is_libg(map) then
+        return nil
+    end
+    if type(map.desc) == "string" and map.desc ~= "" then
+        return map.desc
+    end
+    if type(map.rhs) == "string" and map.rhs ~= "" then
+        return map.rhs
+    end
+    return "<Lua function>"
+end
+
+for _, bound in ipairs(libg.bound_keymaps or {}) do
+    if is_libg(vim.fn.maparg(bound.lhs, bound.mode, false, true)) then
+        pcall(vim.keymap.del, bound.mode, bound.lhs)
+    end
+end
+
+local report, bound = {}, {}
+for _, b in ipairs(bindings) do
+    local lhs = "<leader>m" .. b.key
+    local existing = foreign_map(lhs, b.mode)
+    local entry = { mode = b.mode, lhs = lhs, action = b.action, existing = existing, status = "mapped" }
+    local target = lhs
+    if existing then
+        if policy == "override" then
+            entry.status = "overridden"
+        elseif policy == "relocate" and not foreign_map(prefix .. b.key, b.mode) then
+            entry.status = "relocated"
+            target = prefix .. b.key
+        else
+            entry.status = "skipped"
+            target = nil
+        end
+    end
+    if target then
+        vim.keymap.set(b.mode, target, b.fn, { desc = b.desc })
+        entry.boundTo = target
+        table.insert(bound, { mode = b.mode, lhs = target })
+    end
+    table.insert(report, entry)
+end
+libg.bound_keymaps = bound
+return vim.json.encode(report)
+"#;
+
+/// Puts text into the current buffer at the cursor, or into the unnamed
+/// register (linewise when it ends in a newline) for the user to paste.
+const INSERT_AT_CURSOR_LUA: &str = r#"
+local text, to_register = ...
+if to_register then
+    vim.fn.setreg('"', text, text:sub(-1) == "\n" and "l" or "c")
+    return
+end
+if not vim.bo.modifiable then
+    error("buffer is not modifiable")
+end
+vim.api.nvim_put(vim.split(text, "\n", { plain = true }), "c", false, true)
+"#;
+
+/// Current buffer's path, the workspace folders of its LSP clients, the
+/// nearest directory holding one of the given markers and Neovim's cwd.
+fn build_workspace_roots_lua() -> &'static str {
+    r#"
+local markers = ...
+local bufnr = vim.api.nvim_get_current_buf()
+local file = vim.api.nvim_buf_get_name(bufnr)
+local get_clients = vim.lsp.get_clients or vim.lsp.get_active_clients
+
+local folders, seen = {}, {}
+local function add_folder(path)
+    if path and path ~= "" and not seen[path] then
+        seen[path] = true
+        table.insert(folders, path)
+    end
+end
+for _, client in ipairs(get_clients({ bufnr = bufnr })) do
+    for _, folder in ipairs(client.workspace_folders or {}) do
+        add_folder(vim.uri_to_fname(folder.uri))
+    end
+    add_folder(client.root_dir or (client.config and client.config.root_dir))
+end
+
+local result = { filePath = file, lspFolders = folders, cwd = vim.fn.getcwd() }
+if file ~= "" then
+    local found = vim.fs.find(markers, { path = vim.fs.dirname(file), upward = true })[1]
+    if found then
+        result.markerRoot = vim.fs.dirname(found)
+        result.marker = vim.fs.basename(found)
+    end
+end
+return vim.json.encode(result)
+"#
+}
+
+/// How long the LSP gets to compute a rename.
+const LSP_RENAME_TIMEOUT_MS: u64 = 5_000;
+
+/// Renames the symbol under the cursor through the first attached LSP client
+/// with a rename provider, applies the resulting workspace edit and returns
+/// the files it touched.
+fn build_lsp_rename_lua() -> &'static str {
+    r#"
+local new_name, timeout_ms = ...
+local get_clients = vim.lsp.get_clients or vim.lsp.get_active_clients
+local bufnr = vim.api.nvim_get_current_buf()
+local client
+for _, candidate in ipairs(get_clients({ bufnr = bufnr })) do
+    if candidate.server_capabilities.renameProvider then
+        client = candidate
+        break
+    end
+end
+if not client then
+    return vim.json.encode({ ok = false, error = "no attached LSP client supports rename" })
+end
+
+local params = vim.lsp.util.make_position_params(0, client.offset_encoding)
+params.newName = new_name
+local responses = vim.lsp.buf_request_sync(bufnr, "textDocument/rename", params, timeout_ms)
+local response = responses and responses[client.id]
+if not response then
+    return vim.json.encode({ ok = false, error = "LSP rename timed out" })
+end
+if response.error then
+    return vim.json.encode({ ok = false, error = response.error.message or "LSP rename failed" })
+end
+local edit = response.result
+if not edit then
+    return vim.json.encode({ ok = false, error = "nothing to rename at the cursor" })
+end
+
+local files, seen = {}, {}
+local function touched(uri)
+    local path = vim.uri_to_fname(uri)
+    if not seen[path] then
+        seen[path] = true
+        table.insert(files, path)
+    end
+end
+for uri in pairs(edit.changes or {}) do
+    touched(uri)
+end
+for _, change in ipairs(edit.documentChanges or {}) do
+    if change.textDocument then
+        touched(change.textDocument.uri)
+    elseif change.kind == "rename" then
+        touched(change.newUri)
+    elseif change.uri then
+        touched(change.uri)
+    end
+end
+
+vim.lsp.util.apply_workspace_edit(edit, client.offset_encoding)
+table.sort(files)
+return vim.json.encode({ ok = true, files = files })
+"#
+}
+
+/// Defines `_G.libg.breadcrumbs()` and notifies the host with
+/// `libg_breadcrumbs` when the file or the scopes around the cursor change,
+/// at most every 150ms. Scopes come from the LSP's document symbols (fetched
+/// asynchronously and cached per changedtick) or, until those arrive or
+/// without a language server, from treesitter.
+fn build_breadcrumbs_lua() -> &'static str {
+    r#"
+local channel = ...
+_G.libg = _G.libg or {}
+
+local lsp_kinds = {
+    [2] = "module", [3] = "namespace", [5] = "class", [6] = "method", [9] = "constructor",
+    [10] = "enum", [11] = "interface", [12] = "function", [19] = "object", [23] = "struct",
+}
+local scope_patterns = {
+    "function", "method", "class", "struct", "impl", "trait", "interface", "enum", "module", "namespace",
+}
+local document_symbols = {}
+
+local function contains(range, row, col)
+    local first, last = range.start, range["end"]
+    if row < first.line or row > last.line then return false end
+    if row == first.line and col < first.character then return false end
+    if row == last.line and col > last.character then return false end
+    return true
+end
+
+local function refresh_document_symbols(bufnr)
+    local tick = vim.api.nvim_buf_get_changedtick(bufnr)
+    local cached = document_symbols[bufnr]
+    if cached and (cached.tick == tick or cached.pending) then return end
+    local get_clients = vim.lsp.get_clients or vim.lsp.get_active_clients
+    if #get_clients({ bufnr = bufnr }) == 0 then return end
+    document_symbols[bufnr] = { tick = tick, symbols = cached and cached.symbols or {}, pending = true }
+    local params = { textDocument = vim.lsp.util.make_text_document_params(bufnr) }
+    vim.lsp.buf_request_all(bufnr, "textDocument/documentSymbol", params, function(responses)
+        local symbols = {}
+        for _, response in pairs(responses or {}) do
+            for _, symbol in ipairs(response.result or {}) do
+                table.insert(symbols, symbol)
+            end
+        end
+        document_symbols[bufnr] = { tick = tick, symbols = symbols }
+        vim.schedule(function()
+            if _G.libg.schedule_breadcrumbs then _G.libg.schedule_breadcrumbs() end
+        end)
+    end)
+end
+
+local function lsp_chain(bufnr, row, col)
+    refresh_document_symbols(bufnr)
+    local cached = document_symbols[bufnr]
+    if not cached then return {} end
+    local chain = {}
+    local level = cached.symbols
+    while level do
+        local inner = nil
+        for _, symbol in ipairs(level) do
+            local range = symbol.range or (symbol.location and symbol.location.range)
+            if range and contains(range, row, col) then
+                if lsp_kinds[symbol.kind] then
+                    table.insert(chain, { name = symbol.name, kind = lsp_kinds[symbol.kind] })
+                end
+                inner = symbol.children
+                break
+            end
+        end
+        level = inner
+    end
+    return chain
+end
+
+local function scope_kind(node_type)
+    if node_type:find("call", 1, true) or node_type:find("expression", 1, true) then return nil end
+    for _, pattern in ipairs(scope_patterns) do
+        if node_type:find(pattern, 1, true) then return pattern end
+    end
+end
+
+local function treesitter_chain(bufnr, row, col)
+    local ok, node = pcall(vim.treesitter.get_node, { bufnr = bufnr, pos = { row, col } })
+    if not ok or not node then return {} end
+    local chain = {}
+    while node do
+        local kind = scope_kind(node:type())
+        local name_node = kind and (node:field("name")[1] or node:field("type")[1])
+        if name_node then
+            local name = vim.treesitter.get_node_text(name_node, bufnr):gsub("%s+", " ")
+            table.insert(chain, 1, { name = name, kind = kind })
+        end
+        node = node:parent()
+    end
+    return chain
+end
+
+function _G.libg.breadcrumbs()
+    local bufnr = vim.api.nvim_get_current_buf()
+    local cursor = vim.api.nvim_win_get_cursor(0)
+    local row, col = cursor[1] - 1, cursor[2]
+    local symbols, source = lsp_chain(bufnr, row, col), "lsp"
+    if #symbols == 0 then
+        symbols, source = treesitter_chain(bufnr, row, col), "treesitter"
+    end
+    return {
+        filePath = vim.api.nvim_buf_get_name(bufnr),
+        cwd = vim.fn.getcwd(),
+        line = cursor[1],
+        symbols = symbols,
+        source = source,
+    }
+end
+
+local pending = false
+local last_key = nil
+local function send_breadcrumbs()
+    pending = false
+    if vim.bo.buftype ~= "" then return end
+    local crumbs = _G.libg.breadcrumbs()
+    local key = crumbs.filePath .. "\0" .. vim.json.encode(crumbs.symbols)
+    if key == last_key then return end
+    last_key = key
+    pcall(vim.rpcnotify, channel, "libg_breadcrumbs", vim.json.encode(crumbs))
+end
+
+function _G.libg.schedule_breadcrumbs()
+    if pending then return end
+    pending = true
+    vim.defer_fn(send_breadcrumbs, 150)
+end
+
+vim.api.nvim_create_autocmd({ "CursorMoved", "CursorMovedI", "BufEnter", "LspAttach" }, {
+    group = vim.api.nvim_create_augroup("LibgBreadcrumbs", { clear = true }),
+    callback = function() _G.libg.schedule_breadcrumbs() end,
+})
+_G.libg.schedule_breadcrumbs()
+"#
+}
+
+fn build_context_sources_lua() -> String {
+    let sources = r#"
+local radius, max_recent = ...
+local cur_buf = vim.api.nvim_get_current_buf()
+local cursor = vim.api.nvim_win_get_cursor(0)
+local line_count = vim.api.nvim_buf_line_count(cur_buf)
+local first = math.max(cursor[1] - radius, 1)
+local last = math.min(cursor[1] + radius, line_count)
+local current_lines = vim.api.nvim_buf_get_lines(cur_buf, first - 1, last, false)
+local terminal = terminal_tail(cur_buf, 2 * radius)
+if terminal then
+    first = terminal.start
+    current_lines = terminal.lines
+    terminal.start = nil
+    terminal.lines = nil
+end
+
+local function is_file_buf(buf)
+    return vim.bo[buf].buftype == "" and vim.api.nvim_buf_get_name(buf) ~= ""
+end
+
+local windows = {}
+local cur_win = vim.api.nvim_get_current_win()
+for _, win in ipairs(vim.api.nvim_tabpage_list_wins(0)) do
+    local buf = vim.api.nvim_win_get_buf(win)
+    if is_file_buf(buf) and vim.api.nvim_win_get_config(win).relative == "" then
+        local top = vim.fn.line("w0", win)
+        local bottom = vim.fn.line("w$", win)
+        table.insert(windows, {
+            path = vim.api.nvim_buf_get_name(buf),
+            filetype = vim.bo[buf].filetype,
+            start = top,
+            lines = vim.api.nvim_buf_get_lines(buf, top - 1, bottom, false),
+            placement = window_placement(win, cur_win),
+        })
+    end
+end
+
+local recent = {}
+local infos = vim.fn.getbufinfo({ buflisted = 1 })
+table.sort(infos, function(a, b) return a.lastused > b.lastused end)
+for _, info in ipairs(infos) do
+    if #recent >= max_recent then
+        break
+    end
+    if info.bufnr ~= cur_buf and is_file_buf(info.bufnr) then
+        table.insert(recent, info.name)
+    end
+end
+
+local diagnostics = {}
+for _, d in ipairs(vim.diagnostic.get(cur_buf)) do
+    table.insert(diagnostics, {
+        lnum = d.lnum,
+        col = d.col,
+        severity = d.severity,
+        message = d.message,
+        source = d.source or "",
+    })
+end
+
+return vim.json.encode({
+    ok = true,
+    cwd = vim.fn.getcwd(),
+    current = {
+        path = vim.api.nvim_buf_get_name(cur_buf),
+        filetype = vim.bo[cur_buf].filetype,
+        cursorLine = cursor[1],
+        cursorCol = cursor[2],
+        start = first,
+        lines = current_lines,
+        terminal = terminal,
+    },
+    windows = windows,
+    recent = recent,
+    diagnostics = diagnostics,
+})
+"#;
+    [TERMINAL_TAIL_LUA, WINDOW_PLACEMENT_LUA, sources].concat()
+}
+
+/// Tabpages and their windows in one call, for `nvim_get_layout`.
+fn build_layout_lua() -> String {
+    let layout = r#"
+local current_tab = vim.api.nvim_get_current_tabpage()
+local tabpages = {}
+for _, tab in ipairs(vim.api.nvim_list_tabpages()) do
+    local anchor = vim.api.nvim_tabpage_get_win(tab)
+    local windows = {}
+    for _, win in ipairs(vim.api.nvim_tabpage_list_wins(tab)) do
+        local buf = vim.api.nvim_win_get_buf(win)
+        local cursor = vim.api.nvim_win_get_cursor(win)
+        local pos = vim.api.nvim_win_get_position(win)
+        local floating = vim.api.nvim_win_get_config(win).relative ~= ""
+        table.insert(windows, {
+            id = win,
+            number = vim.api.nvim_win_get_number(win),
+            current = win == anchor,
+            floating = floating,
+            bufferId = buf,
+            filePath = vim.api.nvim_buf_get_name(buf),
+            fileType = vim.bo[buf].filetype,
+            bufType = vim.bo[buf].buftype,
+            modified = vim.bo[buf].modified,
+            cursor = { line = cursor[1], col = cursor[2] },
+            row = pos[1],
+            col = pos[2],
+            width = vim.api.nvim_win_get_width(win),
+            height = vim.api.nvim_win_get_height(win),
+            placement = not floating and window_placement(win, anchor) or nil,
+        })
+    end
+    table.insert(tabpages, {
+        number = vim.api.nvim_tabpage_get_number(tab),
+        current = tab == current_tab,
+        windows = windows,
+    })
+end
+return vim.json.encode({ columns = vim.o.columns, lines = vim.o.lines, tabpages = tabpages })
+"#;
+    [WINDOW_PLACEMENT_LUA, layout].concat()
+}
+
+fn build_template_sources_lua() -> &'static str {
+    r#"
+local buf = vim.api.nvim_get_current_buf()
+
+local selection = ""
+local start_pos = vim.fn.getpos("'<")
+local end_pos = vim.fn.getpos("'>")
+if start_pos[2] > 0 and end_pos[2] >= start_pos[2] then
+    local lines = vim.api.nvim_buf_get_lines(buf, start_pos[2] - 1, end_pos[2], false)
+    if #lines > 0 then
+        if #lines == 1 then
+            lines[1] = string.sub(lines[1], start_pos[3], end_pos[3])
+        else
+            lines[1] = string.sub(lines[1], start_pos[3])
+            lines[#lines] = string.sub(lines[#lines], 1, end_pos[3])
+        end
+        selection = table.concat(lines, "\n")
+    end
+end
+
+local diagnostics = {}
+for _, d in ipairs(vim.diagnostic.get(buf)) do
+    table.insert(diagnostics, {
+        lnum = d.lnum,
+        col = d.col,
+        severity = d.severity,
+        message = d.message,
+        source = d.source or "",
+    })
+end
+
+return vim.json.encode({
+    ok = true,
+    cwd = vim.fn.getcwd(),
+    path = vim.api.nvim_buf_get_name(buf),
+    filetype = vim.bo[buf].filetype,
+    line = vim.api.nvim_win_get_cursor(0)[1],
+    selection = selection,
+    diagnostics = diagnostics,
+})
+"#
+}
+
+fn build_write_file_lua() -> &'static str {
+    r#"
+local input_path, content = ...
+if type(input_path) ~= "string" or input_path == "" then
+    return vim.json.encode({ ok = false, error = "missing file path" })
+end
+if type(content) ~= "string" then
+    return vim.json.encode({ ok = false, error = "missing file content" })
+end
+
+local path = vim.fn.fnamemodify(input_path, ":p")
+local current_buf = vim.api.nvim_get_current_buf()
+local current_path = vim.fn.fnamemodify(vim.api.nvim_buf_get_name(current_buf), ":p")
+local current_cursor = vim.api.nvim_win_get_cursor(0)
+local same_file = current_path == path
+local bufnr = vim.fn.bufnr(path)
+if bufnr == -1 then
+    bufnr = vim.fn.bufadd(path)
+end
+if bufnr == -1 then
+    return vim.json.encode({ ok = false, error = "failed to create buffer for file" })
+end
+
+if vim.fn.bufloaded(bufnr) == 0 then
+    vim.fn.bufload(bufnr)
+end
+if not vim.api.nvim_buf_is_valid(bufnr) then
+    return vim.json.encode({ ok = false, error = "invalid buffer for file" })
+end
+
+local old_lines = vim.api.nvim_buf_get_lines(bufnr, 0, -1, false)
+local lines = vim.split(content, "\n", { plain = true })
+if #lines > 0 and lines[#lines] == "" then
+    table.remove(lines, #lines)
+end
+if #lines == 0 then
+    lines = { "" }
 end
 
 local changed_line = nil
@@ -867,6 +2136,44 @@ return vim.json.encode({
 "#
 }
 
+/// Compares each edit's range with what it held when proposed. Returns the
+/// 0-based indices of stale edits with the range's current lines.
+fn build_check_edits_lua() -> &'static str {
+    r#"
+local input_path, edits_json = ...
+local edits = vim.json.decode(edits_json)
+local bufnr
+if type(input_path) == "string" and input_path ~= "" then
+    local path = vim.fn.fnamemodify(input_path, ":p")
+    bufnr = vim.fn.bufnr(path)
+    if bufnr == -1 then
+        bufnr = vim.fn.bufadd(path)
+    end
+    if vim.fn.bufloaded(bufnr) == 0 then
+        vim.fn.bufload(bufnr)
+    end
+else
+    bufnr = vim.api.nvim_get_current_buf()
+end
+
+local tick = vim.api.nvim_buf_get_changedtick(bufnr)
+local stale = {}
+for i, edit in ipairs(edits) do
+    local current = vim.api.nvim_buf_get_lines(bufnr, edit.startLine, edit.endLine, false)
+    local conflict = false
+    if type(edit.expectedLines) == "table" then
+        conflict = not vim.deep_equal(edit.expectedLines, current)
+    elseif type(edit.changedtick) == "number" then
+        conflict = edit.changedtick ~= tick
+    end
+    if conflict then
+        table.insert(stale, { index = i - 1, currentLines = current })
+    end
+end
+return vim.json.encode({ changedtick = tick, stale = stale })
+"#
+}
+
 fn build_apply_edits_lua() -> &'static str {
     r#"
 local input_path, edits_json, target_line_hint = ...
@@ -974,6 +2281,282 @@ return vim.json.encode({
 "#
 }
 
+/// Runs a JSON array of [`BridgeOp`]s in order. When a step fails, every
+/// buffer touched so far gets its lines and modified flag back, files
+/// written by the batch get their previous contents (or are removed if the
+/// batch created them), and the original window, buffer and cursor are
+/// restored.
+fn build_exec_batch_lua() -> &'static str {
+    r#"
+local ops_json = ...
+local ok_decode, ops = pcall(vim.json.decode, ops_json)
+if not ok_decode or type(ops) ~= "table" then
+    return vim.json.encode({ ok = false, failedStep = 0, error = "invalid batch payload" })
+end
+if #ops == 0 then
+    return vim.json.encode({ ok = false, failedStep = 0, error = "empty batch" })
+end
+
+local original_win = vim.api.nvim_get_current_win()
+local original_buf = vim.api.nvim_get_current_buf()
+local original_cursor = vim.api.nvim_win_get_cursor(0)
+local saved = {}
+local saved_order = {}
+
+local function resolve_buf(file_path)
+    if type(file_path) ~= "string" or file_path == "" then
+        return vim.api.nvim_get_current_buf()
+    end
+    local path = vim.fn.fnamemodify(file_path, ":p")
+    local bufnr = vim.fn.bufnr(path)
+    if bufnr == -1 then
+        bufnr = vim.fn.bufadd(path)
+    end
+    if bufnr == -1 then
+        error("failed to create buffer for " .. path)
+    end
+    if vim.fn.bufloaded(bufnr) == 0 then
+        vim.fn.bufload(bufnr)
+    end
+    return bufnr
+end
+
+local function save(bufnr)
+    if saved[bufnr] then
+        return saved[bufnr]
+    end
+    saved[bufnr] = {
+        lines = vim.api.nvim_buf_get_lines(bufnr, 0, -1, false),
+        modified = vim.bo[bufnr].modified,
+    }
+    table.insert(saved_order, bufnr)
+    return saved[bufnr]
+end
+
+local function write(bufnr)
+    vim.api.nvim_buf_call(bufnr, function()
+        vim.cmd("silent keepalt noautocmd write")
+    end)
+end
+
+local handlers = {
+    openFile = function(op)
+        if type(op.filePath) ~= "string" or op.filePath == "" then
+            error("openFile needs a filePath")
+        end
+        vim.api.nvim_win_set_buf(0, resolve_buf(op.filePath))
+    end,
+    setCursor = function(op)
+        local line_count = math.max(vim.api.nvim_buf_line_count(0), 1)
+        local line = math.min(math.max(tonumber(op.line) or 1, 1), line_count)
+        vim.api.nvim_win_set_cursor(0, { line, tonumber(op.col) or 0 })
+    end,
+    applyEdit = function(op)
+        local start_line = tonumber(op.startLine)
+        local end_line = tonumber(op.endLine)
+        if not start_line or not end_line then
+            error("invalid edit line range")
+        end
+        local bufnr = resolve_buf(op.filePath)
+        save(bufnr)
+        local new_lines = type(op.newLines) == "table" and op.newLines or { "" }
+        vim.api.nvim_buf_set_lines(bufnr, start_line, end_line, true, new_lines)
+    end,
+    write = function(op)
+        local bufnr = resolve_buf(op.filePath)
+        local snapshot = save(bufnr)
+        if snapshot.disk == nil then
+            snapshot.path = vim.api.nvim_buf_get_name(bufnr)
+            snapshot.disk = vim.fn.filereadable(snapshot.path) == 1
+                and vim.fn.readfile(snapshot.path, "b")
+                or false
+        end
+        write(bufnr)
+        snapshot.written = true
+    end,
+}
+
+local function rollback()
+    local failed = {}
+    for i = #saved_order, 1, -1 do
+        local bufnr = saved_order[i]
+        local snapshot = saved[bufnr]
+        local restored = pcall(function()
+            if snapshot.written then
+                if snapshot.disk then
+                    vim.fn.writefile(snapshot.disk, snapshot.path, "b")
+                else
+                    os.remove(snapshot.path)
+                end
+            end
+            vim.api.nvim_buf_set_lines(bufnr, 0, -1, false, snapshot.lines)
+            vim.bo[bufnr].modified = snapshot.modified
+        end)
+        if not restored then
+            table.insert(failed, vim.api.nvim_buf_get_name(bufnr))
+        end
+    end
+    pcall(vim.api.nvim_set_current_win, original_win)
+    pcall(vim.api.nvim_win_set_buf, original_win, original_buf)
+    pcall(vim.api.nvim_win_set_cursor, original_win, original_cursor)
+    return failed
+end
+
+for index, op in ipairs(ops) do
+    local handler = handlers[op.op]
+    local ok, err
+    if handler then
+        ok, err = pcall(handler, op)
+    else
+        ok, err = false, "unknown op " .. tostring(op.op)
+    end
+    if not ok then
+        return vim.json.encode({
+            ok = false,
+            failedStep = index - 1,
+            error = tostring(err),
+            rollbackFailed = rollback(),
+        })
+    end
+end
+
+return vim.json.encode({
+    ok = true,
+    applied = #ops,
+    filePath = vim.api.nvim_buf_get_name(0),
+    line = vim.api.nvim_win_get_cursor(0)[1],
+})
+"#
+}
+
+fn bridge_stats() -> &'static std::sync::Mutex<BridgeStats> {
+    static BRIDGE_STATS: std::sync::OnceLock<std::sync::Mutex<BridgeStats>> =
+        std::sync::OnceLock::new();
+    BRIDGE_STATS.get_or_init(|| std::sync::Mutex::new(BridgeStats::default()))
+}
+
+/// Records a bridge call in [`bridge_stats`] from creation until dropped,
+/// logging it when it was slow.
+struct CallTimer {
+    terminal_id: String,
+    call: u64,
+}
+
+impl CallTimer {
+    fn start(terminal_id: &str, op: &'static str) -> Self {
+        let call = bridge_stats()
+            .lock()
+            .map(|mut stats| stats.start(terminal_id, op, Instant::now()))
+            .unwrap_or_default();
+        Self {
+            terminal_id: terminal_id.to_string(),
+            call,
+        }
+    }
+}
+
+impl Drop for CallTimer {
+    fn drop(&mut self) {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let slow = bridge_stats().lock().ok().and_then(|mut stats| {
+            stats.finish(&self.terminal_id, self.call, Instant::now(), now_ms)
+        });
+        if let Some(slow) = slow {
+            log::warn!(
+                "Slow neovim bridge call for terminal {}: {} took {}ms",
+                self.terminal_id,
+                slow.op,
+                slow.duration_ms
+            );
+        }
+    }
+}
+
+/// A locked connection whose use, including the wait for the lock, is timed
+/// as `op`.
+struct TimedConnection<'a> {
+    conn: tokio::sync::MutexGuard<'a, NvimConnection>,
+    // Dropped after `conn`, so the time covers the whole hold.
+    _timer: CallTimer,
+}
+
+impl std::ops::Deref for TimedConnection<'_> {
+    type Target = NvimConnection;
+
+    fn deref(&self) -> &NvimConnection {
+        &self.conn
+    }
+}
+
+async fn lock_timed<'a>(
+    conn: &'a Mutex<NvimConnection>,
+    terminal_id: &str,
+    op: &'static str,
+) -> TimedConnection<'a> {
+    let timer = CallTimer::start(terminal_id, op);
+    TimedConnection {
+        conn: conn.lock().await,
+        _timer: timer,
+    }
+}
+
+/// How long `nvim_get_mode` may take before Neovim counts as busy.
+const MODE_CHECK_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Why Neovim in `mode` can't run Lua right now, if it can't. `blocking` is
+/// `nvim_get_mode`'s flag for a pending `getchar()` and similar.
+fn busy_reason(mode: &str, blocking: bool) -> Option<&'static str> {
+    if mode.starts_with("no") {
+        Some("an operator is pending")
+    } else if mode.starts_with('c') {
+        Some("the command line is open")
+    } else if matches!(mode, "r" | "rm" | "r?") {
+        Some("a prompt is waiting for an answer")
+    } else if blocking {
+        Some("it is waiting for input")
+    } else {
+        None
+    }
+}
+
+/// Fails with `NVIM_BUSY` when Neovim is in a state where Lua calls would
+/// block until the user acts. `nvim_get_mode` is answered even then.
+async fn ensure_not_blocked(nvim: &Neovim<Writer>) -> Result<(), NeoaiError> {
+    let busy = |message: &str| NeoaiError::new(ErrorCode::NvimBusy, message);
+    let mode = tokio::time::timeout(MODE_CHECK_TIMEOUT, nvim.get_mode())
+        .await
+        .map_err(|_| busy("Neovim did not answer a mode check"))?
+        .map_err(NeoaiError::nvim_request)?;
+    let field = |name: &str| {
+        mode.iter()
+            .find(|(key, _)| key.as_str() == Some(name))
+            .map(|(_, value)| value)
+    };
+    let mode_name = field("mode").and_then(Value::as_str).unwrap_or_default();
+    let blocking = field("blocking").and_then(Value::as_bool).unwrap_or(false);
+    match busy_reason(mode_name, blocking) {
+        Some(reason) => {
+            Err(busy(&format!("Neovim is busy: {reason}")).with_detail(format!("mode={mode_name}")))
+        }
+        None => Ok(()),
+    }
+}
+
+/// [`lock_timed`] for calls that run Lua or edit buffers: fails with
+/// `NVIM_BUSY` instead of hanging while Neovim waits for input.
+async fn lock_ready<'a>(
+    conn: &'a Mutex<NvimConnection>,
+    terminal_id: &str,
+    op: &'static str,
+) -> Result<TimedConnection<'a>, NeoaiError> {
+    let conn = lock_timed(conn, terminal_id, op).await;
+    ensure_not_blocked(&conn.nvim).await?;
+    Ok(conn)
+}
+
 async fn resolve_connection_for_terminal(
     app_handle: &tauri::AppHandle,
     terminal_id: &str,
@@ -995,7 +2578,7 @@ pub async fn nvim_read_file_for_terminal(
     limit: Option<u32>,
 ) -> Result<String, String> {
     let conn = resolve_connection_for_terminal(app_handle, terminal_id).await?;
-    let conn = conn.lock().await;
+    let conn = lock_ready(&conn, terminal_id, "nvim_read_file_for_terminal").await?;
     let nvim = &conn.nvim;
 
     let result = nvim
@@ -1018,6 +2601,76 @@ pub async fn nvim_read_file_for_terminal(
     Ok(apply_line_window(content, line, limit))
 }
 
+/// Raw material for prompt context in one round trip: the lines around the
+/// cursor, every visible window, recently used buffers and the current
+/// buffer's diagnostics. Parsed by the `context` module.
+pub async fn nvim_context_sources_for_terminal(
+    app_handle: &tauri::AppHandle,
+    terminal_id: &str,
+    cursor_radius: i64,
+    max_recent: usize,
+) -> Result<serde_json::Value, String> {
+    let conn = resolve_connection_for_terminal(app_handle, terminal_id).await?;
+    let conn = lock_ready(&conn, terminal_id, "nvim_context_sources_for_terminal").await?;
+    let result = conn
+        .nvim
+        .exec_lua(
+            &build_context_sources_lua(),
+            vec![Value::from(cursor_radius), Value::from(max_recent as u64)],
+        )
+        .await
+        .map_err(|e| format!("Neovim context lua failed: {}", e))?;
+    parse_lua_json(result)
+}
+
+/// Current buffer, its last visual selection and its diagnostics, for
+/// filling prompt template variables.
+pub async fn nvim_template_sources_for_terminal(
+    app_handle: &tauri::AppHandle,
+    terminal_id: &str,
+) -> Result<serde_json::Value, String> {
+    let conn = resolve_connection_for_terminal(app_handle, terminal_id).await?;
+    let conn = lock_ready(&conn, terminal_id, "nvim_template_sources_for_terminal").await?;
+    let result = conn
+        .nvim
+        .exec_lua(build_template_sources_lua(), vec![])
+        .await
+        .map_err(|e| format!("Neovim template lua failed: {}", e))?;
+    parse_lua_json(result)
+}
+
+/// Asks the terminal's LSP clients for `workspace/symbol` matches of `query`.
+pub async fn nvim_workspace_symbols_for_terminal(
+    app_handle: &tauri::AppHandle,
+    terminal_id: &str,
+    query: &str,
+    timeout_ms: u64,
+) -> Result<Vec<SymbolLocation>, String> {
+    let conn = resolve_connection_for_terminal(app_handle, terminal_id).await?;
+    let conn = lock_ready(&conn, terminal_id, "nvim_workspace_symbols_for_terminal").await?;
+    let result = conn
+        .nvim
+        .exec_lua(
+            build_workspace_symbols_lua(),
+            vec![Value::from(query), Value::from(timeout_ms)],
+        )
+        .await
+        .map_err(|e| format!("Neovim workspace symbol lua failed: {}", e))?;
+    let payload = parse_lua_json(result)?;
+    if !payload["ok"].as_bool().unwrap_or(false) {
+        return Err(payload["error"]
+            .as_str()
+            .unwrap_or("workspace symbol lookup failed")
+            .to_string());
+    }
+    // An empty Lua table encodes as `{}` rather than `[]`.
+    if !payload["symbols"].is_array() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_value(payload["symbols"].clone())
+        .map_err(|e| format!("Failed to parse workspace symbols: {}", e))
+}
+
 pub async fn nvim_write_file_for_terminal(
     app_handle: &tauri::AppHandle,
     terminal_id: &str,
@@ -1035,7 +2688,7 @@ pub async fn nvim_write_file_for_terminal(
     );
 
     let conn = resolve_connection_for_terminal(app_handle, terminal_id).await?;
-    let conn = conn.lock().await;
+    let conn = lock_ready(&conn, terminal_id, "nvim_write_file_for_terminal").await?;
     let nvim = &conn.nvim;
 
     let result = nvim
@@ -1113,7 +2766,7 @@ pub async fn nvim_connect(
     state: tauri::State<'_, Mutex<NvimBridgeState>>,
     terminal_id: String,
     socket_path: String,
-) -> Result<(), String> {
+) -> Result<(), NeoaiError> {
     log::info!(
         "Connecting neovim bridge for terminal {} to socket {}",
         terminal_id,
@@ -1121,23 +2774,51 @@ pub async fn nvim_connect(
     );
 
     let handler = NvimHandler {
-        app_handle,
+        app_handle: app_handle.clone(),
         terminal_id: terminal_id.clone(),
     };
 
     let (nvim, io_handle) = nvim_create::new_path(&socket_path, handler)
         .await
-        .map_err(|e| format!("Failed to connect to neovim at {}: {}", socket_path, e))?;
+        .map_err(|e| {
+            NeoaiError::new(
+                ErrorCode::NvimConnectFailed,
+                format!("Failed to connect to neovim at {socket_path}"),
+            )
+            .with_detail(e)
+        })?;
 
     // Inject keybindings into neovim
-    inject_keymaps(&nvim).await?;
+    let bindings = inject_keymaps(&nvim, &keymap_config(&app_handle))
+        .await
+        .map_err(|e| {
+            NeoaiError::new(ErrorCode::NvimConnectFailed, "Failed to inject keymaps").with_detail(e)
+        })?;
+    log_keymap_collisions(&terminal_id, &bindings);
+
+    let pid = nvim
+        .call_function("getpid", vec![])
+        .await
+        .ok()
+        .and_then(|pid| pid.as_u64())
+        .map(|pid| pid as u32);
 
     let conn = NvimConnection {
         nvim,
-        _io_handle: io_handle,
+        io_handle,
         socket_path: socket_path.clone(),
+        pid,
     };
 
+    folder_state::remember_for_terminal(
+        &app_handle,
+        &terminal_id,
+        folder_state::FolderSessionUpdate {
+            nvim_socket_path: Some(socket_path),
+            ..Default::default()
+        },
+    );
+
     let mut bridge = state.lock().await;
     bridge
         .connections
@@ -1146,14 +2827,27 @@ pub async fn nvim_connect(
     Ok(())
 }
 
+/// Call counts and latencies of each terminal's bridge, with the recent slow
+/// calls and any still running, to diagnose an unresponsive panel.
+#[tauri::command]
+pub fn nvim_bridge_stats() -> Vec<TerminalBridgeStats> {
+    bridge_stats()
+        .lock()
+        .map(|stats| stats.snapshot(Instant::now()))
+        .unwrap_or_default()
+}
+
 #[tauri::command]
 pub async fn nvim_disconnect(
     state: tauri::State<'_, Mutex<NvimBridgeState>>,
     terminal_id: String,
-) -> Result<(), String> {
+) -> Result<(), NeoaiError> {
     log::info!("Disconnecting neovim bridge for terminal {}", terminal_id);
     let mut bridge = state.lock().await;
     bridge.connections.remove(&terminal_id);
+    if let Ok(mut stats) = bridge_stats().lock() {
+        stats.remove(&terminal_id);
+    }
     Ok(())
 }
 
@@ -1161,7 +2855,7 @@ pub async fn nvim_disconnect(
 pub async fn nvim_connection_status(
     state: tauri::State<'_, Mutex<NvimBridgeState>>,
     terminal_id: String,
-) -> Result<ConnectionStatus, String> {
+) -> Result<ConnectionStatus, NeoaiError> {
     let bridge = state.lock().await;
     match bridge.connections.get(&terminal_id) {
         Some(conn) => {
@@ -1174,31 +2868,37 @@ pub async fn nvim_connection_status(
     }
 }
 
+/// Re-runs keymap injection and reports how each `<leader>m` binding was
+/// placed, including the existing mappings it collided with.
 #[tauri::command]
 pub async fn nvim_reinject_keymaps(
+    app_handle: tauri::AppHandle,
     state: tauri::State<'_, Mutex<NvimBridgeState>>,
     terminal_id: String,
-) -> Result<(), String> {
+) -> Result<Vec<KeymapBinding>, NeoaiError> {
     log::info!("Re-injecting neovim keymaps for terminal {}", terminal_id);
     let bridge = state.lock().await;
     let conn = bridge
         .connections
         .get(&terminal_id)
-        .ok_or_else(|| format!("No neovim connection for terminal: {}", terminal_id))?
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
         .clone();
     drop(bridge);
 
-    let conn = conn.lock().await;
-    inject_keymaps(&conn.nvim).await?;
+    let conn = lock_ready(&conn, &terminal_id, "nvim_reinject_keymaps").await?;
+    let bindings = inject_keymaps(&conn.nvim, &keymap_config(&app_handle))
+        .await
+        .map_err(NeoaiError::nvim_request)?;
+    log_keymap_collisions(&terminal_id, &bindings);
     log::info!("Neovim keymap re-injection complete");
-    Ok(())
+    Ok(bindings)
 }
 
 #[tauri::command]
 pub async fn nvim_probe_health(
     state: tauri::State<'_, Mutex<NvimBridgeState>>,
     terminal_id: String,
-) -> Result<NvimHealth, String> {
+) -> Result<NvimHealth, NeoaiError> {
     let conn = {
         let bridge = state.lock().await;
         match bridge.connections.get(&terminal_id) {
@@ -1215,7 +2915,7 @@ pub async fn nvim_probe_health(
         }
     };
 
-    let conn = conn.lock().await;
+    let conn = lock_timed(&conn, &terminal_id, "nvim_probe_health").await;
     let socket_path = conn.socket_path.clone();
 
     let channel_id = match current_channel_id(&conn.nvim).await {
@@ -1269,28 +2969,38 @@ pub async fn nvim_probe_health(
     })
 }
 
+/// Cursor, buffer details and the lines around the cursor. With `folds`,
+/// only what the window shows is sent: closed folds collapse to their fold
+/// text and the range widens to keep the same number of rows.
 #[tauri::command]
 pub async fn nvim_get_context(
     state: tauri::State<'_, Mutex<NvimBridgeState>>,
     terminal_id: String,
-) -> Result<NvimContext, String> {
+    folds: Option<bool>,
+) -> Result<NvimContext, NeoaiError> {
     let bridge = state.lock().await;
     let conn = bridge
         .connections
         .get(&terminal_id)
-        .ok_or_else(|| format!("No neovim connection for terminal: {}", terminal_id))?
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
         .clone();
     drop(bridge);
 
-    let conn = conn.lock().await;
+    let conn = lock_ready(&conn, &terminal_id, "nvim_get_context").await?;
     let nvim = &conn.nvim;
 
-    let win = nvim.get_current_win().await.map_err(|e| e.to_string())?;
-    let buf = nvim.get_current_buf().await.map_err(|e| e.to_string())?;
+    let win = nvim
+        .get_current_win()
+        .await
+        .map_err(NeoaiError::nvim_request)?;
+    let buf = nvim
+        .get_current_buf()
+        .await
+        .map_err(NeoaiError::nvim_request)?;
 
-    let (cursor_line, cursor_col) = win.get_cursor().await.map_err(|e| e.to_string())?;
-    let file_path = buf.get_name().await.map_err(|e| e.to_string())?;
-    let line_count = buf.line_count().await.map_err(|e| e.to_string())?;
+    let (cursor_line, cursor_col) = win.get_cursor().await.map_err(NeoaiError::nvim_request)?;
+    let file_path = buf.get_name().await.map_err(NeoaiError::nvim_request)?;
+    let line_count = buf.line_count().await.map_err(NeoaiError::nvim_request)?;
 
     let file_type = nvim
         .exec_lua(
@@ -1298,7 +3008,7 @@ pub async fn nvim_get_context(
             vec![],
         )
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(NeoaiError::nvim_request)?;
     let file_type = match file_type {
         Value::String(s) => s.into_str().unwrap_or_default(),
         _ => String::new(),
@@ -1310,18 +3020,88 @@ pub async fn nvim_get_context(
             vec![],
         )
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(NeoaiError::nvim_request)?;
     let modified = matches!(modified, Value::Boolean(true));
 
-    let buffer_id = buf.get_number().await.map_err(|e| e.to_string())?;
+    let buffer_id = buf.get_number().await.map_err(NeoaiError::nvim_request)?;
+    let changedtick = nvim
+        .exec_lua("return vim.api.nvim_buf_get_changedtick(0)", vec![])
+        .await
+        .map_err(NeoaiError::nvim_request)?
+        .as_i64()
+        .unwrap_or_default();
+
+    let terminal_lua = format!(
+        "{TERMINAL_TAIL_LUA}\nlocal tail = terminal_tail(vim.api.nvim_get_current_buf(), ...)\n\
+         return tail and vim.json.encode(tail) or nil"
+    );
+    let terminal = nvim
+        .exec_lua(&terminal_lua, vec![Value::from(TERMINAL_OUTPUT_LINES)])
+        .await
+        .map_err(NeoaiError::nvim_request)?;
+    if !terminal.is_nil() {
+        let tail = parse_lua_json(terminal).map_err(NeoaiError::nvim_request)?;
+        let start = tail["start"].as_i64().unwrap_or(1);
+        let visible_lines: Vec<String> =
+            serde_json::from_value(tail["lines"].clone()).unwrap_or_default();
+        let end = start + visible_lines.len() as i64 - 1;
+        let info = serde_json::from_value(tail).map_err(NeoaiError::nvim_request)?;
+        return Ok(NvimContext {
+            cursor: CursorPosition {
+                line: cursor_line,
+                col: cursor_col,
+            },
+            file_path,
+            file_type,
+            buffer_id,
+            line_count,
+            modified,
+            changedtick,
+            visible_lines,
+            visible_range: (start, end),
+            buffer_kind: BufferKind::Terminal,
+            terminal: Some(info),
+            folds: Vec::new(),
+        });
+    }
+
+    if folds.unwrap_or(false) {
+        let folded = nvim
+            .exec_lua(FOLDED_CONTEXT_LUA, vec![Value::from(CONTEXT_RADIUS)])
+            .await
+            .map_err(NeoaiError::nvim_request)?;
+        let folded = parse_lua_json(folded).map_err(NeoaiError::nvim_request)?;
+        let visible_lines = serde_json::from_value(folded["lines"].clone()).unwrap_or_default();
+        let folds = serde_json::from_value(folded["folds"].clone()).unwrap_or_default();
+        return Ok(NvimContext {
+            cursor: CursorPosition {
+                line: cursor_line,
+                col: cursor_col,
+            },
+            file_path,
+            file_type,
+            buffer_id,
+            line_count,
+            modified,
+            changedtick,
+            visible_lines,
+            visible_range: (
+                folded["first"].as_i64().unwrap_or(1),
+                folded["last"].as_i64().unwrap_or(line_count),
+            ),
+            buffer_kind: BufferKind::File,
+            terminal: None,
+            folds,
+        });
+    }
 
-    // Get visible lines: cursor_line +/- 50
-    let start = (cursor_line - 50).max(1) - 1; // 0-indexed for get_lines
-    let end = (cursor_line + 50).min(line_count);
+    // Get visible lines: cursor_line +/- CONTEXT_RADIUS
+    let start = (cursor_line - CONTEXT_RADIUS).max(1) - 1; // 0-indexed for get_lines
+    let end = (cursor_line + CONTEXT_RADIUS).min(line_count);
     let visible_lines = buf
         .get_lines(start, end, false)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(NeoaiError::nvim_request)?;
 
     Ok(NvimContext {
         cursor: CursorPosition {
@@ -1333,25 +3113,59 @@ pub async fn nvim_get_context(
         buffer_id,
         line_count,
         modified,
+        changedtick,
         visible_lines,
         visible_range: (start + 1, end), // 1-indexed for display
+        buffer_kind: BufferKind::File,
+        terminal: None,
+        folds: Vec::new(),
     })
 }
 
+/// File, enclosing scopes and git branch at the cursor. The same data is
+/// pushed as `nvim-breadcrumbs` whenever the file or scopes change.
+#[tauri::command]
+pub async fn nvim_get_breadcrumbs(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<NvimBridgeState>>,
+    terminal_id: String,
+) -> Result<Breadcrumbs, NeoaiError> {
+    let bridge = state.lock().await;
+    let conn = bridge
+        .connections
+        .get(&terminal_id)
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
+        .clone();
+    drop(bridge);
+
+    let result = lock_ready(&conn, &terminal_id, "nvim_get_breadcrumbs")
+        .await?
+        .nvim
+        .exec_lua("return vim.json.encode(_G.libg.breadcrumbs())", vec![])
+        .await
+        .map_err(NeoaiError::nvim_request)?;
+    let json = match result {
+        Value::String(s) => s.into_str().unwrap_or_default(),
+        _ => String::new(),
+    };
+    let raw = parse_breadcrumbs(&json).map_err(NeoaiError::nvim_request)?;
+    Ok(complete_breadcrumbs(&app_handle, &terminal_id, raw).await)
+}
+
 #[tauri::command]
 pub async fn nvim_get_diagnostics(
     state: tauri::State<'_, Mutex<NvimBridgeState>>,
     terminal_id: String,
-) -> Result<Vec<Diagnostic>, String> {
+) -> Result<Vec<Diagnostic>, NeoaiError> {
     let bridge = state.lock().await;
     let conn = bridge
         .connections
         .get(&terminal_id)
-        .ok_or_else(|| format!("No neovim connection for terminal: {}", terminal_id))?
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
         .clone();
     drop(bridge);
 
-    let conn = conn.lock().await;
+    let conn = lock_ready(&conn, &terminal_id, "nvim_get_diagnostics").await?;
     let nvim = &conn.nvim;
 
     let result = nvim
@@ -1374,14 +3188,15 @@ pub async fn nvim_get_diagnostics(
             vec![],
         )
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(NeoaiError::nvim_request)?;
 
     let json_str = match result {
         Value::String(s) => s.as_str().unwrap_or("[]").to_string(),
         _ => "[]".to_string(),
     };
 
-    let raw: Vec<serde_json::Value> = serde_json::from_str(&json_str).map_err(|e| e.to_string())?;
+    let raw: Vec<serde_json::Value> =
+        serde_json::from_str(&json_str).map_err(NeoaiError::nvim_request)?;
 
     let diagnostics = raw
         .into_iter()
@@ -1401,25 +3216,28 @@ pub async fn nvim_get_diagnostics(
 pub async fn nvim_get_buffer_content(
     state: tauri::State<'_, Mutex<NvimBridgeState>>,
     terminal_id: String,
-) -> Result<BufferContent, String> {
+) -> Result<BufferContent, NeoaiError> {
     let bridge = state.lock().await;
     let conn = bridge
         .connections
         .get(&terminal_id)
-        .ok_or_else(|| format!("No neovim connection for terminal: {}", terminal_id))?
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
         .clone();
     drop(bridge);
 
-    let conn = conn.lock().await;
+    let conn = lock_ready(&conn, &terminal_id, "nvim_get_buffer_content").await?;
     let nvim = &conn.nvim;
 
-    let buf = nvim.get_current_buf().await.map_err(|e| e.to_string())?;
-    let file_path = buf.get_name().await.map_err(|e| e.to_string())?;
-    let line_count = buf.line_count().await.map_err(|e| e.to_string())?;
+    let buf = nvim
+        .get_current_buf()
+        .await
+        .map_err(NeoaiError::nvim_request)?;
+    let file_path = buf.get_name().await.map_err(NeoaiError::nvim_request)?;
+    let line_count = buf.line_count().await.map_err(NeoaiError::nvim_request)?;
     let lines = buf
         .get_lines(0, line_count, false)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(NeoaiError::nvim_request)?;
 
     Ok(BufferContent {
         file_path,
@@ -1434,7 +3252,7 @@ pub async fn nvim_apply_edit(
     state: tauri::State<'_, Mutex<NvimBridgeState>>,
     terminal_id: String,
     edit: BufferEdit,
-) -> Result<(), String> {
+) -> Result<(), NeoaiError> {
     emit_bridge_debug(
         &app_handle,
         &terminal_id,
@@ -1446,23 +3264,30 @@ pub async fn nvim_apply_edit(
     let conn = bridge
         .connections
         .get(&terminal_id)
-        .ok_or_else(|| format!("No neovim connection for terminal: {}", terminal_id))?
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
         .clone();
     drop(bridge);
 
-    let conn = conn.lock().await;
+    let conn = lock_ready(&conn, &terminal_id, "nvim_apply_edit").await?;
     let nvim = &conn.nvim;
     apply_buffer_edits_with_cursor_follow(&app_handle, &terminal_id, nvim, vec![edit], "apply_edit")
         .await
+        .map_err(NeoaiError::nvim_request)
 }
 
+/// Applies a batch of proposed edits after snapshotting the files they touch.
+/// Snapshots are grouped under `session_id`, or the terminal id when omitted.
+/// Edits carrying `expected_lines` or `changedtick` are checked first; if
+/// any range changed since it was proposed, nothing is applied and the
+/// stale ranges are returned so the edits can be proposed again.
 #[tauri::command]
 pub async fn nvim_apply_edits(
     app_handle: tauri::AppHandle,
     state: tauri::State<'_, Mutex<NvimBridgeState>>,
     terminal_id: String,
     edits: Vec<BufferEdit>,
-) -> Result<(), String> {
+    session_id: Option<String>,
+) -> Result<ApplyEditsResult, NeoaiError> {
     emit_bridge_debug(
         &app_handle,
         &terminal_id,
@@ -1474,14 +3299,143 @@ pub async fn nvim_apply_edits(
     let conn = bridge
         .connections
         .get(&terminal_id)
-        .ok_or_else(|| format!("No neovim connection for terminal: {}", terminal_id))?
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
         .clone();
     drop(bridge);
 
-    let conn = conn.lock().await;
+    let conn = lock_ready(&conn, &terminal_id, "nvim_apply_edits").await?;
     let nvim = &conn.nvim;
+    let conflicts = find_edit_conflicts(nvim, &edits)
+        .await
+        .map_err(NeoaiError::nvim_request)?;
+    if !conflicts.is_empty() {
+        emit_bridge_debug(
+            &app_handle,
+            &terminal_id,
+            "apply_edits.conflict",
+            Some(format!("stale={}", conflicts.len())),
+        );
+        return Ok(ApplyEditsResult {
+            applied: false,
+            conflicts,
+        });
+    }
+
+    let paths = edit_target_paths(nvim, &edits).await;
+    edit_snapshots::snapshot_before_write(
+        &app_handle,
+        session_id.as_deref().unwrap_or(&terminal_id),
+        "apply_edits",
+        paths,
+    )
+    .await;
     apply_buffer_edits_with_cursor_follow(&app_handle, &terminal_id, nvim, edits, "apply_edits")
         .await
+        .map_err(NeoaiError::nvim_request)?;
+    Ok(ApplyEditsResult {
+        applied: true,
+        conflicts: Vec::new(),
+    })
+}
+
+/// Runs `ops` in one Lua call, so a multi-step edit costs a single round
+/// trip and never leaves Neovim half-edited: if any step fails, the earlier
+/// ones are rolled back and the error names the failing step.
+#[tauri::command]
+pub async fn nvim_exec_batch(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<NvimBridgeState>>,
+    terminal_id: String,
+    ops: Vec<BridgeOp>,
+    session_id: Option<String>,
+) -> Result<BatchResult, NeoaiError> {
+    emit_bridge_debug(
+        &app_handle,
+        &terminal_id,
+        "exec_batch.requested",
+        Some(format!("count={}", ops.len())),
+    );
+
+    let bridge = state.lock().await;
+    let conn = bridge
+        .connections
+        .get(&terminal_id)
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
+        .clone();
+    drop(bridge);
+
+    let conn = lock_ready(&conn, &terminal_id, "nvim_exec_batch").await?;
+    let nvim = &conn.nvim;
+
+    let touched: Vec<BufferEdit> = ops
+        .iter()
+        .filter_map(|op| match op {
+            BridgeOp::ApplyEdit(edit) => Some(edit.clone()),
+            BridgeOp::Write { file_path } => Some(BufferEdit {
+                start_line: 0,
+                end_line: 0,
+                new_lines: Vec::new(),
+                file_path: file_path.clone(),
+                target_line: None,
+                expected_lines: None,
+                changedtick: None,
+            }),
+            _ => None,
+        })
+        .collect();
+    if !touched.is_empty() {
+        let paths = edit_target_paths(nvim, &touched).await;
+        edit_snapshots::snapshot_before_write(
+            &app_handle,
+            session_id.as_deref().unwrap_or(&terminal_id),
+            "exec_batch",
+            paths,
+        )
+        .await;
+    }
+
+    let ops_json = serde_json::to_string(&ops).map_err(|e| NeoaiError::from(e.to_string()))?;
+    let result = nvim
+        .exec_lua(build_exec_batch_lua(), vec![Value::from(ops_json)])
+        .await
+        .map_err(NeoaiError::nvim_request)?;
+    let payload = parse_lua_json(result).map_err(NeoaiError::nvim_request)?;
+
+    if !payload["ok"].as_bool().unwrap_or(false) {
+        let step = payload["failedStep"].as_u64().unwrap_or(0) + 1;
+        let mut detail = payload["error"]
+            .as_str()
+            .unwrap_or("batch failed")
+            .to_string();
+        if let Some(failed) = payload["rollbackFailed"]
+            .as_array()
+            .filter(|failed| !failed.is_empty())
+        {
+            let files: Vec<&str> = failed.iter().filter_map(|file| file.as_str()).collect();
+            detail.push_str(&format!(" (could not roll back: {})", files.join(", ")));
+        }
+        emit_bridge_debug(
+            &app_handle,
+            &terminal_id,
+            "exec_batch.failed",
+            Some(format!("step={} {}", step, detail)),
+        );
+        return Err(NeoaiError::new(
+            ErrorCode::NvimRequestFailed,
+            format!("Batch step {step} failed; earlier steps were rolled back"),
+        )
+        .with_detail(detail));
+    }
+
+    let batch: BatchResult = serde_json::from_value(payload).map_err(NeoaiError::nvim_request)?;
+    emit_cursor_follow(
+        &app_handle,
+        &terminal_id,
+        &batch.file_path,
+        batch.line,
+        "exec_batch",
+    );
+    Ok(batch)
 }
 
 #[tauri::command]
@@ -1489,22 +3443,237 @@ pub async fn nvim_exec_command(
     state: tauri::State<'_, Mutex<NvimBridgeState>>,
     terminal_id: String,
     command: String,
-) -> Result<String, String> {
+) -> Result<String, NeoaiError> {
     let bridge = state.lock().await;
     let conn = bridge
         .connections
         .get(&terminal_id)
-        .ok_or_else(|| format!("No neovim connection for terminal: {}", terminal_id))?
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
         .clone();
     drop(bridge);
 
-    let conn = conn.lock().await;
+    let conn = lock_ready(&conn, &terminal_id, "nvim_exec_command").await?;
     let nvim = &conn.nvim;
 
     let output = nvim
         .command_output(&command)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(NeoaiError::nvim_request)?;
 
     Ok(output)
 }
+
+/// Project roots for the current buffer, so an agent session can start in
+/// the directory the language server and build tools treat as the project
+/// rather than one guessed from the file path.
+#[tauri::command]
+pub async fn nvim_get_workspace_roots(
+    state: tauri::State<'_, Mutex<NvimBridgeState>>,
+    terminal_id: String,
+) -> Result<WorkspaceRoots, NeoaiError> {
+    let bridge = state.lock().await;
+    let conn = bridge
+        .connections
+        .get(&terminal_id)
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
+        .clone();
+    drop(bridge);
+
+    let conn = lock_ready(&conn, &terminal_id, "nvim_get_workspace_roots").await?;
+    let markers = Value::Array(ROOT_MARKERS.iter().map(|&m| Value::from(m)).collect());
+    let result = conn
+        .nvim
+        .exec_lua(build_workspace_roots_lua(), vec![markers])
+        .await
+        .map_err(NeoaiError::nvim_request)?;
+    drop(conn);
+
+    let mut payload = parse_lua_json(result).map_err(NeoaiError::nvim_request)?;
+    // An empty Lua table encodes as `{}` rather than `[]`.
+    if !payload["lspFolders"].is_array() {
+        payload["lspFolders"] = serde_json::Value::Array(Vec::new());
+    }
+    let mut roots: WorkspaceRoots =
+        serde_json::from_value(payload).map_err(NeoaiError::nvim_request)?;
+    let file = Path::new(&roots.file_path);
+    roots.root = roots
+        .lsp_folders
+        .iter()
+        .filter(|folder| file.starts_with(folder))
+        .max_by_key(|folder| folder.len())
+        .or(roots.marker_root.as_ref())
+        .unwrap_or(&roots.cwd)
+        .clone();
+    Ok(roots)
+}
+
+/// Every tabpage with its windows, their buffers, cursors and on-screen
+/// geometry, so a view can draw the layout and context can say where a file
+/// is open.
+#[tauri::command]
+pub async fn nvim_get_layout(
+    state: tauri::State<'_, Mutex<NvimBridgeState>>,
+    terminal_id: String,
+) -> Result<NvimLayout, NeoaiError> {
+    let bridge = state.lock().await;
+    let conn = bridge
+        .connections
+        .get(&terminal_id)
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
+        .clone();
+    drop(bridge);
+
+    let conn = lock_ready(&conn, &terminal_id, "nvim_get_layout").await?;
+    let result = conn
+        .nvim
+        .exec_lua(&build_layout_lua(), vec![])
+        .await
+        .map_err(NeoaiError::nvim_request)?;
+    drop(conn);
+
+    let payload = parse_lua_json(result).map_err(NeoaiError::nvim_request)?;
+    serde_json::from_value(payload).map_err(NeoaiError::nvim_request)
+}
+
+/// Inserts `text` at the cursor, as if typed before it, leaving the cursor
+/// after the inserted text. With `register`, the text goes to the unnamed
+/// register instead, so the user chooses where to paste it.
+#[tauri::command]
+pub async fn nvim_insert_at_cursor(
+    state: tauri::State<'_, Mutex<NvimBridgeState>>,
+    terminal_id: String,
+    text: String,
+    options: Option<InsertOptions>,
+) -> Result<(), NeoaiError> {
+    let options = options.unwrap_or_default();
+    let bridge = state.lock().await;
+    let conn = bridge
+        .connections
+        .get(&terminal_id)
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
+        .clone();
+    drop(bridge);
+
+    let conn = lock_ready(&conn, &terminal_id, "nvim_insert_at_cursor").await?;
+    conn.nvim
+        .exec_lua(
+            INSERT_AT_CURSOR_LUA,
+            vec![Value::from(text), Value::from(options.register)],
+        )
+        .await
+        .map_err(NeoaiError::nvim_request)?;
+    Ok(())
+}
+
+/// Shows `message` with a spinner in the buffer an action was sent from
+/// while the agent works on it; `None` clears it. Only one indicator exists
+/// per Neovim, so setting a new one replaces the last.
+#[tauri::command]
+pub async fn nvim_set_progress(
+    state: tauri::State<'_, Mutex<NvimBridgeState>>,
+    terminal_id: String,
+    message: Option<String>,
+) -> Result<(), NeoaiError> {
+    let bridge = state.lock().await;
+    let conn = bridge
+        .connections
+        .get(&terminal_id)
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
+        .clone();
+    drop(bridge);
+
+    let conn = lock_ready(&conn, &terminal_id, "nvim_set_progress").await?;
+    conn.nvim
+        .exec_lua(PROGRESS_LUA, vec![message.map_or(Value::Nil, Value::from)])
+        .await
+        .map_err(NeoaiError::nvim_request)?;
+    Ok(())
+}
+
+/// Hunks changed in the current buffer, from gitsigns when it is attached
+/// and from `git diff` on the file otherwise. Lets "explain my changes" or
+/// commit message prompts carry the diff instead of the whole file.
+#[tauri::command]
+pub async fn nvim_get_buffer_hunks(
+    state: tauri::State<'_, Mutex<NvimBridgeState>>,
+    terminal_id: String,
+) -> Result<BufferHunks, NeoaiError> {
+    let bridge = state.lock().await;
+    let conn = bridge
+        .connections
+        .get(&terminal_id)
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
+        .clone();
+    drop(bridge);
+
+    let conn = lock_ready(&conn, &terminal_id, "nvim_get_buffer_hunks").await?;
+    let result = conn
+        .nvim
+        .exec_lua(build_buffer_hunks_lua(), vec![])
+        .await
+        .map_err(NeoaiError::nvim_request)?;
+    drop(conn);
+    let payload = parse_lua_json(result).map_err(NeoaiError::nvim_request)?;
+
+    let file_path = payload["path"].as_str().unwrap_or_default().to_string();
+    if file_path.is_empty() || payload["buftype"].as_str().is_some_and(|t| !t.is_empty()) {
+        return Err(NeoaiError::nvim_request("Current buffer is not a file"));
+    }
+    if let Some(patch) = payload["patch"].as_str() {
+        return Ok(BufferHunks {
+            file_path,
+            source: HunkSource::Gitsigns,
+            hunks: git::parse_hunks(patch),
+        });
+    }
+    let hunks = git::file_diff(Path::new(&file_path)).await?;
+    Ok(BufferHunks {
+        file_path,
+        source: HunkSource::Git,
+        hunks,
+    })
+}
+
+/// Renames the symbol under the cursor to `new_name` with the LSP's
+/// `textDocument/rename`, so references across the workspace change
+/// together. Edited buffers are left unsaved, as after `vim.lsp.buf.rename`.
+/// Returns the paths of the changed files.
+#[tauri::command]
+pub async fn nvim_lsp_rename(
+    state: tauri::State<'_, Mutex<NvimBridgeState>>,
+    terminal_id: String,
+    new_name: String,
+) -> Result<Vec<String>, NeoaiError> {
+    if new_name.trim().is_empty() {
+        return Err(NeoaiError::nvim_request("New name is empty"));
+    }
+
+    let bridge = state.lock().await;
+    let conn = bridge
+        .connections
+        .get(&terminal_id)
+        .ok_or_else(|| NeoaiError::nvim_not_connected(&terminal_id))?
+        .clone();
+    drop(bridge);
+
+    let conn = lock_ready(&conn, &terminal_id, "nvim_lsp_rename").await?;
+    let result = conn
+        .nvim
+        .exec_lua(
+            build_lsp_rename_lua(),
+            vec![
+                Value::from(new_name.trim()),
+                Value::from(LSP_RENAME_TIMEOUT_MS),
+            ],
+        )
+        .await
+        .map_err(NeoaiError::nvim_request)?;
+    let payload = parse_lua_json(result).map_err(NeoaiError::nvim_request)?;
+    if !payload["ok"].as_bool().unwrap_or(false) {
+        return Err(NeoaiError::nvim_request(
+            payload["error"].as_str().unwrap_or("LSP rename failed"),
+        ));
+    }
+    // An empty Lua table encodes as `{}` rather than `[]`.
+    Ok(serde_json::from_value(payload["files"].clone()).unwrap_or_default())
+}
@@ -0,0 +1,64 @@
+//! Process checks that differ per platform, kept here so callers such as the
+//! socket registry stay platform-neutral.
+
+/// Whether a process with `pid` is running. When that can't be determined
+/// the process is assumed alive, so nothing it owns is cleaned up early.
+pub fn process_alive(pid: u32) -> bool {
+    if pid == 0 {
+        return false;
+    }
+    platform_process_alive(pid)
+}
+
+#[cfg(unix)]
+fn platform_process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    // EPERM: the process exists but belongs to another user.
+    std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(windows)]
+fn platform_process_alive(pid: u32) -> bool {
+    use std::os::windows::process::CommandExt;
+
+    // CREATE_NO_WINDOW, so no console flashes up.
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let output = std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/NH", "/FO", "CSV"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output();
+    match output {
+        // Matches list the pid as a quoted CSV field; otherwise tasklist
+        // prints an informational line.
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).contains(&format!("\"{pid}\""))
+        }
+        _ => true,
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn platform_process_alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_running_from_exited_processes() {
+        assert!(process_alive(std::process::id()));
+        assert!(!process_alive(0));
+
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        assert!(!process_alive(pid));
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use serde::Serialize;
+
+use crate::folder_state;
+
+/// Hard cap on entries returned by a single listing.
+const MAX_FILE_LIMIT: usize = 50_000;
+/// Directory levels below the import root searched when none is given.
+const DEFAULT_IMPORT_DEPTH: usize = 3;
+const MAX_IMPORT_DEPTH: usize = 8;
+/// Repositories returned by one import scan.
+const MAX_IMPORTED_REPOS: usize = 500;
+/// Directories never searched for repositories.
+const SKIPPED_IMPORT_DIRS: &[&str] = &["node_modules", "target", "vendor", "dist", "build"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectFile {
+    /// Path relative to the folder root, `/`-separated.
+    pub path: String,
+    pub size: u64,
+    /// Last modification time in milliseconds since the Unix epoch.
+    pub modified_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectFileList {
+    pub files: Vec<ProjectFile>,
+    /// More files matched than `limit` allowed.
+    pub truncated: bool,
+}
+
+/// Walks `root` honoring `.gitignore`, `.ignore` and `.git/info/exclude`.
+/// Hidden files are included; the `.git` directory is not.
+pub fn list_files(
+    root: &Path,
+    glob: Option<&str>,
+    limit: usize,
+) -> Result<ProjectFileList, String> {
+    let limit = limit.min(MAX_FILE_LIMIT);
+    let mut walker = WalkBuilder::new(root);
+    walker
+        .hidden(false)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(|entry| entry.file_name() != ".git");
+
+    if let Some(glob) = glob.map(str::trim).filter(|glob| !glob.is_empty()) {
+        let mut overrides = OverrideBuilder::new(root);
+        overrides
+            .add(glob)
+            .map_err(|e| format!("Invalid glob '{glob}': {e}"))?;
+        let overrides = overrides
+            .build()
+            .map_err(|e| format!("Invalid glob '{glob}': {e}"))?;
+        walker.overrides(overrides);
+    }
+
+    let mut list = ProjectFileList::default();
+    for entry in walker.build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                log::debug!(
+                    "Skipping unreadable entry under '{}': {err}",
+                    root.display()
+                );
+                continue;
+            }
+        };
+        if !entry.file_type().is_some_and(|kind| kind.is_file()) {
+            continue;
+        }
+        if list.files.len() == limit {
+            list.truncated = true;
+            break;
+        }
+
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        let metadata = entry.metadata().ok();
+        list.files.push(ProjectFile {
+            path: relative.to_string_lossy().replace('\\', "/"),
+            size: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+            modified_ms: metadata
+                .and_then(|m| m.modified().ok())
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_millis() as u64),
+        });
+    }
+
+    Ok(list)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedRepo {
+    pub path: String,
+    /// Directory name, used as the project name.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectImport {
+    /// Repositories not yet added, ordered by path.
+    pub repos: Vec<ImportedRepo>,
+    /// Repositories found that were already added as projects.
+    pub already_added: usize,
+    /// More repositories were found than one import returns.
+    pub truncated: bool,
+}
+
+/// Finds git repositories at most `max_depth` levels below `root` (`root`
+/// itself is level 0). Repositories aren't searched for nested ones, and
+/// hidden and build output directories are skipped. Paths in `existing`
+/// are counted but not returned.
+pub fn find_git_repos(
+    root: &Path,
+    max_depth: usize,
+    existing: &[String],
+) -> Result<ProjectImport, String> {
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", root.display()));
+    }
+    let existing: HashSet<PathBuf> = existing
+        .iter()
+        .map(|path| normalize_dir(Path::new(path)))
+        .collect();
+
+    let mut found = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        if dir.join(".git").exists() {
+            found.push(dir);
+            continue;
+        }
+        if depth == max_depth {
+            continue;
+        }
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                log::debug!("Skipping unreadable directory '{}': {err}", dir.display());
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            // `file_type` doesn't follow symlinks, so linked trees aren't
+            // scanned twice.
+            if !entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                continue;
+            }
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || SKIPPED_IMPORT_DIRS.contains(&name.as_ref()) {
+                continue;
+            }
+            pending.push((entry.path(), depth + 1));
+        }
+    }
+
+    found.sort();
+    let mut import = ProjectImport::default();
+    for dir in found {
+        if existing.contains(&normalize_dir(&dir)) {
+            import.already_added += 1;
+            continue;
+        }
+        if import.repos.len() == MAX_IMPORTED_REPOS {
+            import.truncated = true;
+            break;
+        }
+        let name = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| dir.to_string_lossy().to_string());
+        import.repos.push(ImportedRepo {
+            path: dir.to_string_lossy().to_string(),
+            name,
+        });
+    }
+    Ok(import)
+}
+
+/// `path` without `.` components and trailing separators, resolved through
+/// symlinks when it exists.
+fn normalize_dir(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.components().collect())
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn list_project_files(
+    app_handle: tauri::AppHandle,
+    folder_id: String,
+    glob: Option<String>,
+    limit: usize,
+) -> Result<ProjectFileList, String> {
+    let root = folder_state::resolve_folder_path(&app_handle, &folder_id)?;
+    tauri::async_runtime::spawn_blocking(move || list_files(&root, glob.as_deref(), limit))
+        .await
+        .map_err(|e| format!("File listing task failed: {e}"))?
+}
+
+/// Scans `root` for git repositories to add as projects, leaving out the
+/// project roots in `existing`. The frontend adds the returned repositories
+/// in one update.
+#[tauri::command]
+pub async fn import_projects_from_dir(
+    root: String,
+    max_depth: Option<usize>,
+    existing: Vec<String>,
+) -> Result<ProjectImport, String> {
+    let max_depth = max_depth
+        .unwrap_or(DEFAULT_IMPORT_DEPTH)
+        .min(MAX_IMPORT_DEPTH);
+    tauri::async_runtime::spawn_blocking(move || {
+        find_git_repos(Path::new(&root), max_depth, &existing)
+    })
+    .await
+    .map_err(|e| format!("Project import task failed: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_files_respecting_gitignore_and_glob() {
+        let root =
+            std::env::temp_dir().join(format!("neoai-project-files-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("README.md"), "# test\n").unwrap();
+        std::fs::write(root.join("target/app"), "").unwrap();
+        std::fs::write(root.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+
+        let all = list_files(&root, None, 100).unwrap();
+        let paths: Vec<&str> = all.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec![".gitignore", "README.md", "src/main.rs"]);
+        assert_eq!(all.files[2].size, 13);
+        assert!(!all.truncated);
+
+        let rust = list_files(&root, Some("*.rs"), 100).unwrap();
+        assert_eq!(rust.files.len(), 1);
+        assert_eq!(rust.files[0].path, "src/main.rs");
+
+        let limited = list_files(&root, None, 1).unwrap();
+        assert_eq!(limited.files.len(), 1);
+        assert!(limited.truncated);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn finds_repos_skipping_existing_and_nested() {
+        let root =
+            std::env::temp_dir().join(format!("neoai-project-import-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in [
+            "app/.git",
+            "app/crates/inner/.git",
+            "work/api/.git",
+            "work/web/node_modules/dep/.git",
+            "work/deep/a/b/.git",
+            ".cache/tool/.git",
+        ] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        // Worktrees and submodules have a `.git` file instead of a directory.
+        std::fs::create_dir_all(root.join("work/worktree")).unwrap();
+        std::fs::write(root.join("work/worktree/.git"), "gitdir: ../api/.git\n").unwrap();
+
+        let existing = vec![format!("{}/", root.join("work/api").display())];
+        let import = find_git_repos(&root, 2, &existing).unwrap();
+        let names: Vec<&str> = import.repos.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["app", "worktree"]);
+        assert_eq!(import.already_added, 1);
+        assert!(!import.truncated);
+
+        let deeper = find_git_repos(&root, 4, &[]).unwrap();
+        assert_eq!(deeper.repos.len(), 4);
+        assert!(find_git_repos(&root.join("missing"), 2, &[]).is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,445 @@
+//! Templates for starting a new project: commands that scaffold it, tmux
+//! windows to open next to Neovim, the agent and model to use, and
+//! instructions sent with every prompt. `create_project_from_template`
+//! creates the directory, runs the commands and records the rest with the
+//! folder's session state.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::app_paths;
+use crate::folder_state::{FolderPathEntry, FolderSessionUpdate, FolderStateStore};
+use crate::prompt_templates;
+use crate::tmux_runtime::TmuxWindowSpec;
+
+pub const PROJECT_TEMPLATES_FILE: &str = "project-templates.json";
+
+/// An init command still running after this long is killed.
+const INIT_COMMAND_TIMEOUT: Duration = Duration::from_secs(300);
+/// Output kept per init command, from the end.
+const INIT_OUTPUT_CHARS: usize = 4_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectTemplate {
+    pub id: String,
+    pub name: String,
+    /// Shell commands run in order in the new directory, e.g. `cargo init`.
+    #[serde(default)]
+    pub init_commands: Vec<String>,
+    /// tmux windows opened next to Neovim.
+    #[serde(default)]
+    pub windows: Vec<TmuxWindowSpec>,
+    #[serde(default)]
+    pub agent: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Instructions sent as context with every prompt in the project.
+    #[serde(default)]
+    pub preamble: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+}
+
+/// Template to create (without `id`) or replace.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectTemplateInput {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub init_commands: Vec<String>,
+    #[serde(default)]
+    pub windows: Vec<TmuxWindowSpec>,
+    #[serde(default)]
+    pub agent: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub preamble: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitCommandResult {
+    pub command: String,
+    pub success: bool,
+    /// `None` when the command couldn't start, timed out or was killed.
+    pub exit_code: Option<i32>,
+    /// Last part of its combined stdout and stderr.
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedProject {
+    pub folder_id: String,
+    pub path: String,
+    /// Commands in the order they ran; they stop at the first failure.
+    pub init: Vec<InitCommandResult>,
+    pub preamble: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProjectTemplatesFile {
+    #[serde(default)]
+    templates: BTreeMap<String, ProjectTemplate>,
+}
+
+#[derive(Debug, Default)]
+pub struct ProjectTemplateStore {
+    path: Option<PathBuf>,
+    state: ProjectTemplatesFile,
+}
+
+impl ProjectTemplateStore {
+    pub fn initialize(&mut self, app_handle: &tauri::AppHandle) -> Result<(), String> {
+        let path = app_paths::app_root_dir(app_handle)?.join(PROJECT_TEMPLATES_FILE);
+
+        if path.exists() {
+            let contents = std::fs::read_to_string(&path).map_err(|e| {
+                format!("Failed to read project templates '{}': {e}", path.display())
+            })?;
+            self.state = serde_json::from_str(&contents).unwrap_or_else(|err| {
+                log::warn!(
+                    "Failed to parse project templates '{}'. Starting fresh: {err}",
+                    path.display()
+                );
+                ProjectTemplatesFile::default()
+            });
+        }
+
+        self.path = Some(path);
+        Ok(())
+    }
+
+    /// Templates ordered by name.
+    pub fn list(&self) -> Vec<ProjectTemplate> {
+        let mut templates: Vec<ProjectTemplate> = self.state.templates.values().cloned().collect();
+        templates.sort_by_key(|template| template.name.to_lowercase());
+        templates
+    }
+
+    pub fn get(&self, id: &str) -> Option<ProjectTemplate> {
+        self.state.templates.get(id).cloned()
+    }
+
+    /// Creates or replaces a template. New templates get an id derived from
+    /// their name.
+    pub fn save(&mut self, input: ProjectTemplateInput) -> Result<ProjectTemplate, String> {
+        let name = input.name.trim().to_string();
+        if name.is_empty() {
+            return Err("A project template needs a name".to_string());
+        }
+        if let Some(window) = input
+            .windows
+            .iter()
+            .find(|window| window.name.trim().is_empty())
+        {
+            return Err(format!(
+                "Every tmux window needs a name (command: '{}')",
+                window.command
+            ));
+        }
+        let id = match input.id.filter(|id| !id.trim().is_empty()) {
+            Some(id) => id,
+            None => self.unused_id(&name)?,
+        };
+        let non_blank = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+        let template = ProjectTemplate {
+            id: id.clone(),
+            name,
+            init_commands: input
+                .init_commands
+                .into_iter()
+                .filter(|command| !command.trim().is_empty())
+                .collect(),
+            windows: input.windows,
+            agent: non_blank(input.agent),
+            model: non_blank(input.model),
+            preamble: non_blank(input.preamble),
+            updated_at: Some(now_millis()),
+        };
+        self.state.templates.insert(id, template.clone());
+        self.persist()?;
+        Ok(template)
+    }
+
+    pub fn delete(&mut self, id: &str) -> Result<(), String> {
+        if self.state.templates.remove(id).is_none() {
+            return Err(format!("Unknown project template: {id}"));
+        }
+        self.persist()
+    }
+
+    fn unused_id(&self, name: &str) -> Result<String, String> {
+        let base = prompt_templates::slug(name);
+        if base.is_empty() {
+            return Err(format!("Can't derive an id from template name '{name}'"));
+        }
+        let mut id = base.clone();
+        let mut n = 2;
+        while self.state.templates.contains_key(&id) {
+            id = format!("{base}-{n}");
+            n += 1;
+        }
+        Ok(id)
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                format!(
+                    "Failed to create project template directory '{}': {e}",
+                    parent.display()
+                )
+            })?;
+        }
+
+        let contents = serde_json::to_string_pretty(&self.state).map_err(|e| e.to_string())?;
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, contents).map_err(|e| {
+            format!(
+                "Failed to write project templates '{}': {e}",
+                temp_path.display()
+            )
+        })?;
+        std::fs::rename(&temp_path, path).map_err(|e| {
+            format!(
+                "Failed to replace project templates '{}': {e}",
+                path.display()
+            )
+        })
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Creates `path`, refusing one that already holds files so init commands
+/// never run over an existing project.
+fn prepare_directory(path: &Path) -> Result<(), String> {
+    if !path.is_absolute() {
+        return Err(format!("Project path must be absolute: {}", path.display()));
+    }
+    if path.exists() {
+        let mut entries = std::fs::read_dir(path)
+            .map_err(|e| format!("Can't use '{}' for a new project: {e}", path.display()))?;
+        if entries.next().is_some() {
+            return Err(format!(
+                "'{}' already exists and isn't empty",
+                path.display()
+            ));
+        }
+        return Ok(());
+    }
+    std::fs::create_dir_all(path).map_err(|e| format!("Failed to create '{}': {e}", path.display()))
+}
+
+/// The last `max_chars` characters of `text`.
+fn tail(text: &str, max_chars: usize) -> String {
+    let skip = text.chars().count().saturating_sub(max_chars);
+    text.chars().skip(skip).collect()
+}
+
+async fn run_init_command(command: &str, cwd: &Path) -> InitCommandResult {
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+    let failed = |output: String| InitCommandResult {
+        command: command.to_string(),
+        success: false,
+        exit_code: None,
+        output,
+    };
+    let child = match child {
+        Ok(child) => child,
+        Err(err) => return failed(format!("Failed to start: {err}")),
+    };
+    match tokio::time::timeout(INIT_COMMAND_TIMEOUT, child.wait_with_output()).await {
+        Ok(Ok(output)) => {
+            let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+            InitCommandResult {
+                command: command.to_string(),
+                success: output.status.success(),
+                exit_code: output.status.code(),
+                output: tail(text.trim_end(), INIT_OUTPUT_CHARS),
+            }
+        }
+        Ok(Err(err)) => failed(format!("Failed to wait for command: {err}")),
+        Err(_) => failed(format!(
+            "Timed out after {}s",
+            INIT_COMMAND_TIMEOUT.as_secs()
+        )),
+    }
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn project_template_list(
+    state: tauri::State<'_, std::sync::Mutex<ProjectTemplateStore>>,
+) -> Result<Vec<ProjectTemplate>, String> {
+    let store = state.lock().map_err(|e| e.to_string())?;
+    Ok(store.list())
+}
+
+#[tauri::command]
+pub async fn project_template_save(
+    state: tauri::State<'_, std::sync::Mutex<ProjectTemplateStore>>,
+    template: ProjectTemplateInput,
+) -> Result<ProjectTemplate, String> {
+    let mut store = state.lock().map_err(|e| e.to_string())?;
+    store.save(template)
+}
+
+#[tauri::command]
+pub async fn project_template_delete(
+    state: tauri::State<'_, std::sync::Mutex<ProjectTemplateStore>>,
+    id: String,
+) -> Result<(), String> {
+    let mut store = state.lock().map_err(|e| e.to_string())?;
+    store.delete(&id)
+}
+
+/// Creates `path` as folder `folder_id` from a template: runs its init
+/// commands there (stopping at the first failure), registers the folder and
+/// stores the template's agent, model, tmux windows and preamble with the
+/// folder. The windows open when the folder's Neovim starts in tmux.
+#[tauri::command]
+pub async fn create_project_from_template(
+    templates: tauri::State<'_, std::sync::Mutex<ProjectTemplateStore>>,
+    folders: tauri::State<'_, std::sync::Mutex<FolderStateStore>>,
+    template_id: String,
+    path: String,
+    folder_id: String,
+) -> Result<CreatedProject, String> {
+    let template = templates
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get(&template_id)
+        .ok_or_else(|| format!("Unknown project template: {template_id}"))?;
+    let dir = PathBuf::from(&path);
+    prepare_directory(&dir)?;
+
+    let mut init = Vec::new();
+    for command in &template.init_commands {
+        let result = run_init_command(command, &dir).await;
+        let success = result.success;
+        init.push(result);
+        if !success {
+            log::warn!("Init command '{command}' for {path} failed; skipping the rest");
+            break;
+        }
+    }
+
+    let mut store = folders.lock().map_err(|e| e.to_string())?;
+    store.register_paths(vec![FolderPathEntry {
+        folder_id: folder_id.clone(),
+        path: path.clone(),
+    }])?;
+    store.update_session(
+        &folder_id,
+        FolderSessionUpdate {
+            preferred_agent: template.agent.clone(),
+            preferred_model: template.model.clone(),
+            preamble: template.preamble.clone(),
+            tmux_windows: Some(template.windows.clone()),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(CreatedProject {
+        folder_id,
+        path,
+        init,
+        preamble: template.preamble,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_templates_with_blank_fields_dropped() {
+        let mut store = ProjectTemplateStore::default();
+        let template = store
+            .save(ProjectTemplateInput {
+                id: None,
+                name: "Rust CLI".to_string(),
+                init_commands: vec!["cargo init".to_string(), " ".to_string()],
+                windows: vec![TmuxWindowSpec {
+                    name: "tests".to_string(),
+                    command: "cargo watch -x test".to_string(),
+                }],
+                agent: Some(String::new()),
+                model: Some("o3".to_string()),
+                preamble: None,
+            })
+            .unwrap();
+        assert_eq!(template.id, "rust-cli");
+        assert_eq!(template.init_commands, vec!["cargo init".to_string()]);
+        assert_eq!(template.agent, None);
+        assert_eq!(template.model.as_deref(), Some("o3"));
+
+        let unnamed_window = ProjectTemplateInput {
+            id: None,
+            name: "Web".to_string(),
+            init_commands: Vec::new(),
+            windows: vec![TmuxWindowSpec {
+                name: String::new(),
+                command: "npm run dev".to_string(),
+            }],
+            agent: None,
+            model: None,
+            preamble: None,
+        };
+        assert!(store.save(unnamed_window).is_err());
+        assert_eq!(store.list().len(), 1);
+    }
+
+    #[test]
+    fn refuses_directories_with_files() {
+        let root = std::env::temp_dir().join(format!("neoai-project-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let fresh = root.join("fresh");
+        assert!(prepare_directory(&fresh).is_ok());
+        assert!(fresh.is_dir());
+        // Empty directories are fine.
+        assert!(prepare_directory(&fresh).is_ok());
+
+        std::fs::write(fresh.join("README.md"), "hi").unwrap();
+        assert!(prepare_directory(&fresh).is_err());
+        assert!(prepare_directory(Path::new("relative/app")).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn keeps_the_end_of_long_output() {
+        assert_eq!(tail("abcdef", 3), "def");
+        assert_eq!(tail("ab", 3), "ab");
+    }
+}
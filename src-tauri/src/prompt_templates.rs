@@ -0,0 +1,470 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::acp_client::{self, AcpClientState};
+use crate::app_paths;
+use crate::context;
+use crate::error::{ErrorCode, NeoaiError};
+use crate::nvim_bridge;
+
+pub const PROMPT_TEMPLATES_FILE: &str = "prompt-templates.json";
+
+/// Variables filled from the session's Neovim when a template uses them and
+/// the caller didn't pass them.
+const NVIM_VARIABLES: &[&str] = &["file", "filetype", "line", "selection", "diagnostics"];
+
+/// A saved prompt. `body` may reference variables as `{{name}}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    pub body: String,
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+}
+
+/// Template to create (without `id`) or replace.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplateInput {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    pub body: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PromptTemplatesFile {
+    #[serde(default)]
+    templates: BTreeMap<String, PromptTemplate>,
+}
+
+#[derive(Debug, Default)]
+pub struct PromptTemplateStore {
+    path: Option<PathBuf>,
+    state: PromptTemplatesFile,
+}
+
+impl PromptTemplateStore {
+    pub fn initialize(&mut self, app_handle: &tauri::AppHandle) -> Result<(), String> {
+        let path = app_paths::app_root_dir(app_handle)?.join(PROMPT_TEMPLATES_FILE);
+
+        if path.exists() {
+            let contents = std::fs::read_to_string(&path).map_err(|e| {
+                format!("Failed to read prompt templates '{}': {e}", path.display())
+            })?;
+            self.state = serde_json::from_str(&contents).unwrap_or_else(|err| {
+                log::warn!(
+                    "Failed to parse prompt templates '{}'. Starting fresh: {err}",
+                    path.display()
+                );
+                PromptTemplatesFile::default()
+            });
+        }
+
+        self.path = Some(path);
+        Ok(())
+    }
+
+    /// Templates ordered by name.
+    pub fn list(&self) -> Vec<PromptTemplate> {
+        let mut templates: Vec<PromptTemplate> = self.state.templates.values().cloned().collect();
+        templates.sort_by_key(|template| template.name.to_lowercase());
+        templates
+    }
+
+    pub fn get(&self, id: &str) -> Option<PromptTemplate> {
+        self.state.templates.get(id).cloned()
+    }
+
+    /// Creates or replaces a template. New templates get an id derived from
+    /// their name.
+    pub fn save(&mut self, input: PromptTemplateInput) -> Result<PromptTemplate, String> {
+        let name = input.name.trim().to_string();
+        if name.is_empty() {
+            return Err("A prompt template needs a name".to_string());
+        }
+        let id = match input.id.filter(|id| !id.trim().is_empty()) {
+            Some(id) => id,
+            None => self.unused_id(&name)?,
+        };
+        let template = PromptTemplate {
+            id: id.clone(),
+            name,
+            body: input.body,
+            updated_at: Some(now_millis()),
+        };
+        self.state.templates.insert(id, template.clone());
+        self.persist()?;
+        Ok(template)
+    }
+
+    pub fn delete(&mut self, id: &str) -> Result<(), String> {
+        if self.state.templates.remove(id).is_none() {
+            return Err(format!("Unknown prompt template: {id}"));
+        }
+        self.persist()
+    }
+
+    fn unused_id(&self, name: &str) -> Result<String, String> {
+        let base = slug(name);
+        if base.is_empty() {
+            return Err(format!("Can't derive an id from template name '{name}'"));
+        }
+        let mut id = base.clone();
+        let mut n = 2;
+        while self.state.templates.contains_key(&id) {
+            id = format!("{base}-{n}");
+            n += 1;
+        }
+        Ok(id)
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                format!(
+                    "Failed to create prompt template directory '{}': {e}",
+                    parent.display()
+                )
+            })?;
+        }
+
+        let contents = serde_json::to_string_pretty(&self.state).map_err(|e| e.to_string())?;
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, contents).map_err(|e| {
+            format!(
+                "Failed to write prompt templates '{}': {e}",
+                temp_path.display()
+            )
+        })?;
+        std::fs::rename(&temp_path, path).map_err(|e| {
+            format!(
+                "Failed to replace prompt templates '{}': {e}",
+                path.display()
+            )
+        })
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Lowercase ASCII words of `name` joined by dashes.
+pub fn slug(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// Splits `body` into literal text and `{{name}}` references. Braces around
+/// anything that isn't a variable name (e.g. code) stay literal.
+fn parse(body: &str) -> Vec<(&str, Option<&str>)> {
+    let mut parts = Vec::new();
+    let mut rest = body;
+    let mut literal_start = 0;
+    let mut offset = 0;
+    while let Some(open) = rest.find("{{") {
+        let Some(len) = rest[open + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[open + 2..open + 2 + len].trim();
+        let end = open + 2 + len + 2;
+        if is_variable_name(name) {
+            parts.push((&body[literal_start..offset + open], Some(name)));
+            literal_start = offset + end;
+            offset += end;
+            rest = &body[offset..];
+        } else {
+            offset += open + 2;
+            rest = &body[offset..];
+        }
+    }
+    parts.push((&body[literal_start..], None));
+    parts
+}
+
+/// Variables `body` references, in first-use order.
+pub fn variables(body: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (_, name) in parse(body) {
+        if let Some(name) = name {
+            if !names.iter().any(|known| known == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Substitutes every `{{name}}` in `body`, failing with the names that have
+/// no value.
+pub fn render(body: &str, vars: &HashMap<String, String>) -> Result<String, String> {
+    let mut prompt = String::with_capacity(body.len());
+    let mut missing: Vec<&str> = Vec::new();
+    for (literal, name) in parse(body) {
+        prompt.push_str(literal);
+        let Some(name) = name else {
+            continue;
+        };
+        match vars.get(name) {
+            Some(value) => prompt.push_str(value),
+            None if !missing.contains(&name) => missing.push(name),
+            None => {}
+        }
+    }
+    if !missing.is_empty() {
+        return Err(format!(
+            "No value for template variable(s): {}",
+            missing.join(", ")
+        ));
+    }
+    Ok(prompt)
+}
+
+/// Payload of `nvim_template_sources_for_terminal`.
+#[derive(Debug, Deserialize)]
+struct TemplateSources {
+    cwd: String,
+    path: String,
+    #[serde(default)]
+    filetype: String,
+    #[serde(default)]
+    line: u64,
+    #[serde(default)]
+    selection: String,
+    #[serde(default)]
+    diagnostics: Vec<TemplateDiagnostic>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TemplateDiagnostic {
+    lnum: i64,
+    severity: i64,
+    message: String,
+    #[serde(default)]
+    source: String,
+}
+
+fn nvim_variables(sources: TemplateSources) -> HashMap<String, String> {
+    let file = Path::new(&sources.path)
+        .strip_prefix(&sources.cwd)
+        .ok()
+        .and_then(|relative| relative.to_str())
+        .filter(|relative| !relative.is_empty())
+        .unwrap_or(&sources.path)
+        .to_string();
+    let diagnostics = sources
+        .diagnostics
+        .iter()
+        .map(|d| {
+            let source = if d.source.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", d.source)
+            };
+            format!(
+                "Line {}: [{}] {}{source}",
+                d.lnum + 1,
+                context::severity_label(d.severity),
+                d.message
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    HashMap::from([
+        ("file".to_string(), file),
+        ("filetype".to_string(), sources.filetype),
+        ("line".to_string(), sources.line.to_string()),
+        ("selection".to_string(), sources.selection),
+        ("diagnostics".to_string(), diagnostics),
+    ])
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn prompt_template_list(
+    state: tauri::State<'_, std::sync::Mutex<PromptTemplateStore>>,
+) -> Result<Vec<PromptTemplate>, String> {
+    let store = state.lock().map_err(|e| e.to_string())?;
+    Ok(store.list())
+}
+
+#[tauri::command]
+pub async fn prompt_template_save(
+    state: tauri::State<'_, std::sync::Mutex<PromptTemplateStore>>,
+    template: PromptTemplateInput,
+) -> Result<PromptTemplate, String> {
+    let mut store = state.lock().map_err(|e| e.to_string())?;
+    store.save(template)
+}
+
+#[tauri::command]
+pub async fn prompt_template_delete(
+    state: tauri::State<'_, std::sync::Mutex<PromptTemplateStore>>,
+    id: String,
+) -> Result<(), String> {
+    let mut store = state.lock().map_err(|e| e.to_string())?;
+    store.delete(&id)
+}
+
+/// Renders template `template_id` and sends it to `session_id`. `vars` take
+/// precedence; `file`, `filetype`, `line`, `selection` and `diagnostics` are
+/// otherwise read from the session's Neovim, only when the template uses
+/// them.
+#[tauri::command]
+pub async fn acp_send_template(
+    state: tauri::State<'_, Mutex<AcpClientState>>,
+    templates: tauri::State<'_, std::sync::Mutex<PromptTemplateStore>>,
+    app_handle: tauri::AppHandle,
+    session_id: String,
+    template_id: String,
+    vars: Option<HashMap<String, String>>,
+) -> Result<String, NeoaiError> {
+    let template = templates
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get(&template_id)
+        .ok_or_else(|| {
+            NeoaiError::new(
+                ErrorCode::PromptFailed,
+                format!("Unknown prompt template: {template_id}"),
+            )
+        })?;
+
+    let mut vars = vars.unwrap_or_default();
+    let needs_nvim = variables(&template.body)
+        .iter()
+        .any(|name| NVIM_VARIABLES.contains(&name.as_str()) && !vars.contains_key(name));
+    if needs_nvim {
+        let terminal_id = acp_client::session_terminal(&state, &session_id)
+            .await
+            .ok_or_else(|| {
+                NeoaiError::new(
+                    ErrorCode::SessionFailed,
+                    format!("Unknown session: {session_id}"),
+                )
+            })?;
+        let payload = nvim_bridge::nvim_template_sources_for_terminal(&app_handle, &terminal_id)
+            .await
+            .map_err(NeoaiError::nvim_request)?;
+        let sources: TemplateSources = serde_json::from_value(payload).map_err(|e| {
+            NeoaiError::nvim_request(format!("Failed to parse neovim template sources: {e}"))
+        })?;
+        for (name, value) in nvim_variables(sources) {
+            vars.entry(name).or_insert(value);
+        }
+    }
+
+    let prompt = render(&template.body, &vars).map_err(|e| {
+        NeoaiError::new(
+            ErrorCode::PromptFailed,
+            format!("Failed to render prompt template '{}'", template.name),
+        )
+        .with_detail(e)
+    })?;
+    acp_client::acp_send_prompt(state, app_handle, session_id, vec![prompt], None, None).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn renders_variables_and_leaves_other_braces_alone() {
+        let body =
+            "Explain {{ selection }} in {{file}}.\nKeep `{{ }}` and {{a b}}; {{file}} again.";
+        assert_eq!(variables(body), vec!["selection", "file"]);
+        assert_eq!(
+            render(
+                body,
+                &vars(&[("selection", "fn main"), ("file", "src/main.rs")])
+            )
+            .unwrap(),
+            "Explain fn main in src/main.rs.\nKeep `{{ }}` and {{a b}}; src/main.rs again."
+        );
+
+        let err = render("{{a}} {{b}} {{a}}", &vars(&[])).unwrap_err();
+        assert!(err.ends_with("a, b"), "{err}");
+        assert_eq!(render("no vars {{", &vars(&[])).unwrap(), "no vars {{");
+    }
+
+    #[test]
+    fn derives_unique_ids_from_names() {
+        let mut store = PromptTemplateStore::default();
+        let input = |name: &str| PromptTemplateInput {
+            id: None,
+            name: name.to_string(),
+            body: "Review {{selection}}".to_string(),
+        };
+        assert_eq!(store.save(input("Code Review")).unwrap().id, "code-review");
+        assert_eq!(
+            store.save(input("code review")).unwrap().id,
+            "code-review-2"
+        );
+        assert!(store.save(input("  ")).is_err());
+        assert!(store.save(input("???")).is_err());
+
+        assert_eq!(
+            store
+                .list()
+                .iter()
+                .map(|t| t.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["code-review", "code-review-2"]
+        );
+        store.delete("code-review").unwrap();
+        assert!(store.get("code-review").is_none());
+        assert!(store.delete("code-review").is_err());
+    }
+
+    #[test]
+    fn fills_nvim_variables_relative_to_cwd() {
+        let sources: TemplateSources = serde_json::from_value(serde_json::json!({
+            "ok": true,
+            "cwd": "/work/app",
+            "path": "/work/app/src/lib.rs",
+            "filetype": "rust",
+            "line": 12,
+            "selection": "let x = 1;",
+            "diagnostics": [
+                { "lnum": 11, "col": 4, "severity": 1, "message": "unused", "source": "rustc" }
+            ]
+        }))
+        .unwrap();
+        let vars = nvim_variables(sources);
+        assert_eq!(vars["file"], "src/lib.rs");
+        assert_eq!(vars["line"], "12");
+        assert_eq!(vars["diagnostics"], "Line 12: [Error] unused (rustc)");
+    }
+}
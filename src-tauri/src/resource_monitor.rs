@@ -0,0 +1,412 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+use tokio::sync::Mutex;
+
+use crate::acp_client::AcpClientState;
+use crate::nvim_bridge::{self, NvimBridgeState};
+use crate::tmux_runtime::{self, TmuxRuntimeState};
+
+const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+const MIN_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProcessKind {
+    App,
+    Agent,
+    Neovim,
+    TmuxPane,
+}
+
+/// Usage of a managed process plus its descendants (other than processes
+/// reported on their own, such as a Neovim running inside a tmux pane).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessStats {
+    pub kind: ProcessKind,
+    pub label: String,
+    pub pid: u32,
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+    pub process_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessStatsSnapshot {
+    pub processes: Vec<ProcessStats>,
+    /// Totals count each process once.
+    pub total_cpu_percent: f64,
+    pub total_rss_bytes: u64,
+    pub sampled_at: u64,
+}
+
+#[derive(Default)]
+pub struct ResourceMonitorState {
+    /// Cumulative CPU seconds per pid at the previous sample.
+    previous_cpu: HashMap<u32, f64>,
+    previous_at: Option<Instant>,
+    sampler: Option<tauri::async_runtime::JoinHandle<()>>,
+}
+
+#[derive(Debug, Clone)]
+struct Target {
+    kind: ProcessKind,
+    label: String,
+    pid: u32,
+    include_children: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PsRow {
+    ppid: u32,
+    rss_kb: u64,
+    /// `ps`'s own estimate, used when there is no previous sample.
+    pcpu: f64,
+    cpu_seconds: f64,
+}
+
+/// Parses `ps -o time=` output: `[[dd-]hh:]mm:ss[.cc]`.
+fn parse_cpu_time(value: &str) -> Option<f64> {
+    let (days, clock) = match value.trim().split_once('-') {
+        Some((days, clock)) => (days.parse::<f64>().ok()?, clock),
+        None => (0.0, value.trim()),
+    };
+    let mut seconds = 0.0;
+    let mut unit = 1.0;
+    for part in clock.rsplit(':') {
+        seconds += part.parse::<f64>().ok()? * unit;
+        unit *= 60.0;
+    }
+    Some(days * 86_400.0 + seconds)
+}
+
+/// Parses `ps -A -o pid=,ppid=,rss=,pcpu=,time=` output, keyed by pid.
+fn parse_ps_output(output: &str) -> HashMap<u32, PsRow> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let row = PsRow {
+                ppid: fields.next()?.parse().ok()?,
+                rss_kb: fields.next()?.parse().ok()?,
+                pcpu: fields.next()?.parse().ok()?,
+                cpu_seconds: parse_cpu_time(fields.next()?)?,
+            };
+            Some((pid, row))
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+async fn read_process_table() -> Result<HashMap<u32, PsRow>, String> {
+    let output = tokio::process::Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid=,rss=,pcpu=,time="])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to execute ps: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "ps failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_ps_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(not(unix))]
+async fn read_process_table() -> Result<HashMap<u32, PsRow>, String> {
+    Err("Process stats are not supported on this platform".to_string())
+}
+
+/// `root` and, if requested, its descendants, stopping at pids in `stop_at`.
+fn process_tree(
+    root: u32,
+    include_children: bool,
+    children: &HashMap<u32, Vec<u32>>,
+    stop_at: &HashSet<u32>,
+) -> Vec<u32> {
+    let mut pids = vec![root];
+    if !include_children {
+        return pids;
+    }
+    let mut index = 0;
+    while index < pids.len() {
+        let pid = pids[index];
+        index += 1;
+        for &child in children.get(&pid).into_iter().flatten() {
+            if !stop_at.contains(&child) && !pids.contains(&child) {
+                pids.push(child);
+            }
+        }
+    }
+    pids
+}
+
+fn summarize(
+    targets: &[Target],
+    table: &HashMap<u32, PsRow>,
+    previous_cpu: &HashMap<u32, f64>,
+    elapsed: Option<Duration>,
+    sampled_at: u64,
+) -> ProcessStatsSnapshot {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (&pid, row) in table {
+        if row.ppid != pid {
+            children.entry(row.ppid).or_default().push(pid);
+        }
+    }
+    let roots: HashSet<u32> = targets.iter().map(|target| target.pid).collect();
+
+    let cpu_percent = |pid: u32, row: &PsRow| match (previous_cpu.get(&pid), elapsed) {
+        (Some(previous), Some(elapsed)) if !elapsed.is_zero() => {
+            (row.cpu_seconds - previous).max(0.0) / elapsed.as_secs_f64() * 100.0
+        }
+        _ => row.pcpu,
+    };
+
+    let mut counted = HashSet::new();
+    let mut snapshot = ProcessStatsSnapshot {
+        processes: Vec::new(),
+        total_cpu_percent: 0.0,
+        total_rss_bytes: 0,
+        sampled_at,
+    };
+    for target in targets {
+        if !table.contains_key(&target.pid) {
+            continue;
+        }
+        let mut stats = ProcessStats {
+            kind: target.kind,
+            label: target.label.clone(),
+            pid: target.pid,
+            cpu_percent: 0.0,
+            rss_bytes: 0,
+            process_count: 0,
+        };
+        for pid in process_tree(target.pid, target.include_children, &children, &roots) {
+            let Some(row) = table.get(&pid) else {
+                continue;
+            };
+            let cpu = cpu_percent(pid, row);
+            let rss = row.rss_kb * 1024;
+            stats.cpu_percent += cpu;
+            stats.rss_bytes += rss;
+            stats.process_count += 1;
+            if counted.insert(pid) {
+                snapshot.total_cpu_percent += cpu;
+                snapshot.total_rss_bytes += rss;
+            }
+        }
+        snapshot.processes.push(stats);
+    }
+    snapshot
+}
+
+async fn collect_targets(app_handle: &tauri::AppHandle) -> Vec<Target> {
+    let mut targets = vec![Target {
+        kind: ProcessKind::App,
+        label: "NeoAI".to_string(),
+        pid: std::process::id(),
+        include_children: false,
+    }];
+
+    let agent_pid = app_handle
+        .state::<Mutex<AcpClientState>>()
+        .lock()
+        .await
+        .agent_pid();
+    if let Some(pid) = agent_pid {
+        targets.push(Target {
+            kind: ProcessKind::Agent,
+            label: "ACP agent".to_string(),
+            pid,
+            include_children: true,
+        });
+    }
+
+    let bridge = app_handle.state::<Mutex<NvimBridgeState>>();
+    for (terminal_id, pid) in nvim_bridge::connected_pids(&bridge).await {
+        targets.push(Target {
+            kind: ProcessKind::Neovim,
+            label: terminal_id,
+            pid,
+            include_children: true,
+        });
+    }
+
+    let (sessions, command_panes) = {
+        let tmux = app_handle.state::<Mutex<TmuxRuntimeState>>();
+        let tmux = tmux.lock().await;
+        (tmux.assigned_session_names(), tmux.running_command_panes())
+    };
+    if sessions.is_empty() && command_panes.is_empty() {
+        return targets;
+    }
+    match tmux_runtime::list_pane_processes().await {
+        Ok(panes) => {
+            for pane in panes {
+                let label = match command_panes
+                    .iter()
+                    .find(|(pane_id, _)| *pane_id == pane.pane_id)
+                {
+                    Some((_, label)) => label.clone(),
+                    None if sessions.contains(&pane.session_name) => {
+                        format!("{} {}", pane.session_name, pane.pane_id)
+                    }
+                    None => continue,
+                };
+                targets.push(Target {
+                    kind: ProcessKind::TmuxPane,
+                    label,
+                    pid: pane.pid,
+                    include_children: true,
+                });
+            }
+        }
+        Err(err) => log::debug!("Skipping tmux panes in process stats: {}", err),
+    }
+    targets
+}
+
+async fn sample(app_handle: &tauri::AppHandle) -> Result<ProcessStatsSnapshot, String> {
+    let targets = collect_targets(app_handle).await;
+    let table = read_process_table().await?;
+    let now = Instant::now();
+    let sampled_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+
+    let state = app_handle.state::<std::sync::Mutex<ResourceMonitorState>>();
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    let elapsed = state.previous_at.map(|at| now.duration_since(at));
+    let snapshot = summarize(&targets, &table, &state.previous_cpu, elapsed, sampled_at);
+    state.previous_cpu = table
+        .iter()
+        .map(|(&pid, row)| (pid, row.cpu_seconds))
+        .collect();
+    state.previous_at = Some(now);
+    Ok(snapshot)
+}
+
+// -- Tauri IPC commands --
+
+/// CPU and memory use of the agent, connected Neovims, managed tmux panes and
+/// the app itself. CPU is averaged since the previous sample when there is one.
+#[tauri::command]
+pub async fn get_process_stats(
+    app_handle: tauri::AppHandle,
+) -> Result<ProcessStatsSnapshot, String> {
+    sample(&app_handle).await
+}
+
+/// Samples every `interval_ms` (default 5s) and emits `process-stats` events
+/// until `stop_resource_monitor` is called.
+#[tauri::command]
+pub fn start_resource_monitor(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, std::sync::Mutex<ResourceMonitorState>>,
+    interval_ms: Option<u64>,
+) -> Result<(), String> {
+    let interval = interval_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_SAMPLE_INTERVAL)
+        .max(MIN_SAMPLE_INTERVAL);
+
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    if let Some(sampler) = state.sampler.take() {
+        sampler.abort();
+    }
+    state.sampler = Some(tauri::async_runtime::spawn(async move {
+        loop {
+            match sample(&app_handle).await {
+                Ok(snapshot) => {
+                    if let Err(err) = app_handle.emit("process-stats", &snapshot) {
+                        log::warn!("Failed to emit process-stats event: {}", err);
+                    }
+                }
+                Err(err) => log::debug!("Process stats sample failed: {}", err),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }));
+    log::info!("Resource monitor sampling every {:?}", interval);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_resource_monitor(
+    state: tauri::State<'_, std::sync::Mutex<ResourceMonitorState>>,
+) -> Result<(), String> {
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    if let Some(sampler) = state.sampler.take() {
+        sampler.abort();
+        log::info!("Resource monitor stopped");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ps_cpu_times_from_linux_and_macos() {
+        assert_eq!(parse_cpu_time("00:01:02"), Some(62.0));
+        assert_eq!(parse_cpu_time("1-02:00:00"), Some(93_600.0));
+        assert_eq!(parse_cpu_time("0:01.50"), Some(1.5));
+        assert_eq!(parse_cpu_time("soon"), None);
+
+        let table = parse_ps_output(
+            "  10     1  2048  1.5 00:00:10\nbad line\n  11    10 1024  0.0 0:00.50\n",
+        );
+        assert_eq!(table.len(), 2);
+        assert_eq!(table[&11].ppid, 10);
+        assert_eq!(table[&11].cpu_seconds, 0.5);
+    }
+
+    #[test]
+    fn attributes_descendants_without_double_counting() {
+        let row = |ppid, rss_kb, cpu_seconds| PsRow {
+            ppid,
+            rss_kb,
+            pcpu: 50.0,
+            cpu_seconds,
+        };
+        // 100: pane shell -> 101: nvim -> 102: language server; 103: pane child.
+        let table = HashMap::from([
+            (100, row(1, 100, 1.0)),
+            (101, row(100, 200, 4.0)),
+            (102, row(101, 300, 10.0)),
+            (103, row(100, 50, 0.0)),
+        ]);
+        let target = |kind, pid: u32| Target {
+            kind,
+            label: pid.to_string(),
+            pid,
+            include_children: true,
+        };
+        let targets = [
+            target(ProcessKind::TmuxPane, 100),
+            target(ProcessKind::Neovim, 101),
+            target(ProcessKind::Agent, 999),
+        ];
+        let previous = HashMap::from([(101, 3.0), (102, 8.0)]);
+
+        let snapshot = summarize(&targets, &table, &previous, Some(Duration::from_secs(2)), 0);
+        assert_eq!(snapshot.processes.len(), 2);
+        let pane = &snapshot.processes[0];
+        assert_eq!((pane.process_count, pane.rss_bytes), (2, 150 * 1024));
+        let nvim = &snapshot.processes[1];
+        assert_eq!(nvim.process_count, 2);
+        // 1s and 2s of CPU over 2s, plus `ps` estimates for unseen pids.
+        assert_eq!(nvim.cpu_percent, 150.0);
+        assert_eq!(snapshot.total_rss_bytes, 650 * 1024);
+        assert_eq!(snapshot.total_cpu_percent, 250.0);
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+
+/// Keychain service name; secrets are stored as accounts under it.
+const SERVICE: &str = "com.fcoury.neoai";
+/// `[agent] env` values starting with this are looked up in the keychain.
+pub const SECRET_REF_PREFIX: &str = "secret:";
+const MAX_NAME_LEN: usize = 128;
+
+fn validate_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!(
+            "Secret name must be 1-{MAX_NAME_LEN} characters long"
+        ));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return Err(format!(
+            "Invalid secret name '{name}': use letters, digits, '_', '-' or '.'"
+        ));
+    }
+    Ok(name)
+}
+
+fn entry(name: &str) -> Result<keyring::Entry, String> {
+    let name = validate_name(name)?;
+    keyring::Entry::new(SERVICE, name)
+        .map_err(|e| format!("Failed to open keychain entry '{name}': {e}"))
+}
+
+pub fn set(name: &str, value: &str) -> Result<(), String> {
+    entry(name)?
+        .set_password(value)
+        .map_err(|e| format!("Failed to store secret '{name}': {e}"))
+}
+
+pub fn get(name: &str) -> Result<Option<String>, String> {
+    match entry(name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read secret '{name}': {e}")),
+    }
+}
+
+/// Returns `false` when there was nothing to delete.
+pub fn delete(name: &str) -> Result<bool, String> {
+    match entry(name)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(format!("Failed to delete secret '{name}': {e}")),
+    }
+}
+
+/// The secret name referenced by an env value such as `secret:openai`.
+pub fn secret_ref(value: &str) -> Option<&str> {
+    value
+        .trim()
+        .strip_prefix(SECRET_REF_PREFIX)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// Replaces `secret:<name>` values with the stored secret. Variables whose
+/// secret is missing or unreadable are left out rather than passed verbatim.
+pub fn resolve_env(env: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    resolve_env_with(env, get)
+}
+
+fn resolve_env_with(
+    env: &BTreeMap<String, String>,
+    lookup: impl Fn(&str) -> Result<Option<String>, String>,
+) -> BTreeMap<String, String> {
+    let mut resolved = BTreeMap::new();
+    for (key, value) in env {
+        let Some(name) = secret_ref(value) else {
+            resolved.insert(key.clone(), value.clone());
+            continue;
+        };
+        match lookup(name) {
+            Ok(Some(secret)) => {
+                resolved.insert(key.clone(), secret);
+            }
+            Ok(None) => log::warn!(
+                "Agent env {} references secret '{}', which is not stored; leaving it unset",
+                key,
+                name
+            ),
+            Err(err) => log::warn!("Agent env {} left unset: {}", key, err),
+        }
+    }
+    resolved
+}
+
+/// Heuristic for env vars that hold credentials and belong in the keychain.
+pub fn looks_sensitive(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    ["KEY", "TOKEN", "SECRET", "PASSWORD"]
+        .iter()
+        .any(|marker| key.contains(marker))
+}
+
+// -- Tauri IPC commands --
+
+#[tauri::command]
+pub async fn secret_set(name: String, value: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || set(&name, &value))
+        .await
+        .map_err(|e| format!("Keychain task failed: {e}"))?
+}
+
+#[tauri::command]
+pub async fn secret_get(name: String) -> Result<Option<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || get(&name))
+        .await
+        .map_err(|e| format!("Keychain task failed: {e}"))?
+}
+
+#[tauri::command]
+pub async fn secret_delete(name: String) -> Result<bool, String> {
+    tauri::async_runtime::spawn_blocking(move || delete(&name))
+        .await
+        .map_err(|e| format!("Keychain task failed: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_secret_references_in_env() {
+        let env = BTreeMap::from([
+            ("RUST_LOG".to_string(), "info".to_string()),
+            ("OPENAI_API_KEY".to_string(), "secret:openai".to_string()),
+            ("OTHER_TOKEN".to_string(), "secret: missing ".to_string()),
+        ]);
+        let resolved = resolve_env_with(&env, |name| {
+            Ok((name == "openai").then(|| "sk-test".to_string()))
+        });
+
+        assert_eq!(resolved.get("RUST_LOG").map(String::as_str), Some("info"));
+        assert_eq!(
+            resolved.get("OPENAI_API_KEY").map(String::as_str),
+            Some("sk-test")
+        );
+        assert!(!resolved.contains_key("OTHER_TOKEN"));
+    }
+
+    #[test]
+    fn rejects_unsafe_secret_names() {
+        assert!(validate_name("openai_api-key.v2").is_ok());
+        assert!(validate_name("  ").is_err());
+        assert!(validate_name("../etc").is_err());
+        assert_eq!(secret_ref("secret:"), None);
+        assert!(looks_sensitive("anthropic_api_key"));
+        assert!(!looks_sensitive("RUST_LOG"));
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tauri::Manager;
+use tokio::sync::Mutex;
+
+use crate::acp_client;
+use crate::app_config::AppConfigState;
+use crate::folder_state;
+use crate::nvim_bridge::NvimBridgeState;
+use crate::socket_manager::SocketManager;
+use crate::tmux_control;
+use crate::tmux_runtime::{self, TmuxRuntimeState};
+
+/// How long exit waits for the agent to stop before killing it.
+const AGENT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+/// Upper bound on the whole async part of shutdown.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+static SHUT_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Stops the agent, closes Neovim connections and agent command panes, then
+/// removes this instance's sockets, writes pending folder activity and
+/// flushes the log. Runs once; blocks the calling thread for at most
+/// [`SHUTDOWN_TIMEOUT`] plus socket cleanup.
+pub fn run(app_handle: &tauri::AppHandle) {
+    if SHUT_DOWN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let keep_sessions = app_handle
+        .try_state::<std::sync::Mutex<AppConfigState>>()
+        .and_then(|state| {
+            state
+                .lock()
+                .ok()
+                .map(|state| state.keep_nvim_sessions_on_exit())
+        })
+        .unwrap_or(true);
+
+    let result = tauri::async_runtime::block_on(tokio::time::timeout(
+        SHUTDOWN_TIMEOUT,
+        shutdown_async(app_handle, keep_sessions),
+    ));
+    if result.is_err() {
+        log::warn!("Shutdown cleanup timed out; exiting anyway");
+    }
+
+    if let Some(state) = app_handle.try_state::<std::sync::Mutex<SocketManager>>() {
+        if let Ok(mut mgr) = state.inner().lock() {
+            mgr.cleanup_all();
+        }
+    }
+    folder_state::flush_activity(app_handle);
+    log::info!("NeoAI shut down");
+    log::logger().flush();
+}
+
+async fn shutdown_async(app_handle: &tauri::AppHandle, keep_sessions: bool) {
+    acp_client::shutdown_agent(app_handle, AGENT_SHUTDOWN_TIMEOUT).await;
+
+    if let Some(bridge) = app_handle.try_state::<Mutex<NvimBridgeState>>() {
+        let closed = bridge.lock().await.disconnect_all();
+        log::debug!("Closed {} Neovim connection(s)", closed);
+    }
+
+    if let Some(tmux_state) = app_handle.try_state::<Mutex<TmuxRuntimeState>>() {
+        let (pane_ids, sessions) = tmux_state.lock().await.drain_for_shutdown(keep_sessions);
+        for pane_id in pane_ids {
+            let _ = tmux_runtime::kill_pane(&pane_id).await;
+        }
+        for session_name in sessions {
+            let _ = tmux_runtime::kill_session(&session_name).await;
+        }
+    }
+    tmux_control::disconnect().await;
+}
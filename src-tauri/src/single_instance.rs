@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use serde::Serialize;
+use tauri::plugin::TauriPlugin;
+use tauri::{Emitter, Manager};
+
+use crate::MAIN_WINDOW_LABEL;
+
+/// Payload of the `instance-args` event, sent when NeoAI is launched again
+/// while already running.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceArgsEvent {
+    /// Arguments of the second launch, without the program name.
+    pub args: Vec<String>,
+    pub cwd: String,
+    /// Arguments naming existing directories, made absolute against `cwd`.
+    pub folders: Vec<String>,
+    /// Arguments that look like URLs (deep links).
+    pub urls: Vec<String>,
+}
+
+/// Must be the first plugin registered: a second launch exits during plugin
+/// setup, before it touches sockets, tmux sessions or folder state.
+pub fn plugin() -> TauriPlugin<tauri::Wry> {
+    tauri_plugin_single_instance::init(|app_handle, argv, cwd| {
+        let event = parse_args(&argv, Path::new(&cwd));
+        log::info!(
+            "Second launch forwarded {} folder(s) and {} URL(s)",
+            event.folders.len(),
+            event.urls.len()
+        );
+        let _ = app_handle.emit("instance-args", &event);
+
+        if let Some(window) = app_handle.get_webview_window(MAIN_WINDOW_LABEL) {
+            let _ = window.unminimize();
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+    })
+}
+
+fn parse_args(argv: &[String], cwd: &Path) -> InstanceArgsEvent {
+    let args: Vec<String> = argv.iter().skip(1).cloned().collect();
+    let mut event = InstanceArgsEvent {
+        cwd: cwd.to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+
+    for arg in &args {
+        if arg.starts_with('-') {
+            continue;
+        }
+        if arg.contains("://") {
+            event.urls.push(arg.clone());
+            continue;
+        }
+        let path = cwd.join(arg);
+        if path.is_dir() {
+            let path = std::fs::canonicalize(&path).unwrap_or(path);
+            event.folders.push(path.to_string_lossy().into_owned());
+        }
+    }
+    event.args = args;
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_folders_and_urls_from_second_launch() {
+        let cwd = std::env::temp_dir().join(format!("neoai-instance-test-{}", std::process::id()));
+        std::fs::create_dir_all(cwd.join("project")).unwrap();
+        std::fs::write(cwd.join("notes.txt"), "").unwrap();
+
+        let argv: Vec<String> = ["neoai", "project", "notes.txt", "--verbose", "neoai://open"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let event = parse_args(&argv, &cwd);
+        assert_eq!(event.args.len(), 4);
+        assert_eq!(event.folders.len(), 1);
+        assert!(event.folders[0].ends_with("project"));
+        assert_eq!(event.urls, vec!["neoai://open".to_string()]);
+
+        let _ = std::fs::remove_dir_all(&cwd);
+    }
+}
@@ -1,25 +1,182 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+use serde::{Deserialize, Serialize};
+
+use crate::process::process_alive;
+
+pub const SOCKET_REGISTRY_FILE: &str = "sockets.json";
+
+/// Upper bound on suffixed candidates tried before giving up on a terminal.
+const MAX_SOCKET_CANDIDATES: usize = 32;
+
+/// Result of [`SocketManager::allocate`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SocketAllocation {
+    pub path: String,
+    /// `true` when this terminal's Neovim is already listening on `path`.
+    pub reused: bool,
+}
+
+/// A socket handed out by some NeoAI instance, persisted so a later instance
+/// can reconcile what a crashed one left behind.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SocketRecord {
+    pub path: PathBuf,
+    pub pid: u32,
+    pub terminal_id: String,
+    #[serde(default)]
+    pub tmux_session: Option<String>,
+}
+
 pub struct SocketManager {
     instance_id: u32,
+    socket_dir: PathBuf,
     created_sockets: Vec<PathBuf>,
+    assigned: HashMap<String, PathBuf>,
+    registry_path: Option<PathBuf>,
+    records: Vec<SocketRecord>,
 }
 
 impl SocketManager {
     pub fn new() -> Self {
         Self {
             instance_id: std::process::id(),
+            socket_dir: default_socket_dir(),
             created_sockets: Vec::new(),
+            assigned: HashMap::new(),
+            registry_path: None,
+            records: Vec::new(),
         }
     }
 
+    pub fn socket_dir(&self) -> &Path {
+        &self.socket_dir
+    }
+
+    /// Override the socket directory (e.g. from the `socket_dir` config option).
+    /// Sockets already handed out keep their original paths.
+    pub fn set_socket_dir(&mut self, dir: PathBuf) {
+        self.socket_dir = dir;
+    }
+
+    /// Create the socket directory if needed, restricted to the current user.
+    /// Named pipes on Windows live in their own namespace, so this is a no-op there.
+    pub fn ensure_socket_dir(&self) -> Result<(), String> {
+        if cfg!(windows) {
+            return Ok(());
+        }
+
+        std::fs::create_dir_all(&self.socket_dir).map_err(|e| {
+            format!(
+                "Failed to create socket directory '{}': {e}",
+                self.socket_dir.display()
+            )
+        })?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            std::fs::set_permissions(&self.socket_dir, std::fs::Permissions::from_mode(0o700))
+                .map_err(|e| {
+                    format!(
+                        "Failed to restrict socket directory '{}': {e}",
+                        self.socket_dir.display()
+                    )
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Address Neovim should `--listen` on for a terminal: a unix socket path,
+    /// or a named pipe (`\\.\pipe\neoai-nvim-{pid}-{id}`) on Windows.
     pub fn socket_path(&self, terminal_id: &str) -> PathBuf {
-        PathBuf::from(format!(
-            "/tmp/libg-nvim-{}-{}.sock",
-            self.instance_id, terminal_id
+        #[cfg(windows)]
+        {
+            PathBuf::from(format!(
+                r"\\.\pipe\neoai-nvim-{}-{}",
+                self.instance_id, terminal_id
+            ))
+        }
+
+        #[cfg(not(windows))]
+        {
+            self.socket_dir.join(format!(
+                "libg-nvim-{}-{}.sock",
+                self.instance_id, terminal_id
+            ))
+        }
+    }
+
+    /// Hands out a socket for `terminal_id` that Neovim can `--listen` on.
+    /// Stale files are unlinked, and addresses held by another live process get a
+    /// numeric suffix so launches never fail with "address already in use".
+    pub fn allocate(&mut self, terminal_id: &str) -> Result<SocketAllocation, String> {
+        self.ensure_socket_dir()?;
+
+        if let Some(path) = self.assigned.get(terminal_id).cloned() {
+            if socket_is_live(&path) {
+                return Ok(SocketAllocation {
+                    path: path.to_string_lossy().into_owned(),
+                    reused: true,
+                });
+            }
+        }
+
+        for attempt in 0..MAX_SOCKET_CANDIDATES {
+            let path = if attempt == 0 {
+                self.socket_path(terminal_id)
+            } else {
+                self.socket_path(&format!("{terminal_id}-{attempt}"))
+            };
+            if socket_is_live(&path) {
+                continue;
+            }
+            remove_socket_file(&path);
+            self.register(path.clone());
+            self.assigned.insert(terminal_id.to_string(), path.clone());
+            self.records
+                .retain(|record| record.terminal_id != terminal_id);
+            self.records.push(SocketRecord {
+                path: path.clone(),
+                pid: self.instance_id,
+                terminal_id: terminal_id.to_string(),
+                tmux_session: None,
+            });
+            self.persist_registry();
+            return Ok(SocketAllocation {
+                path: path.to_string_lossy().into_owned(),
+                reused: false,
+            });
+        }
+
+        Err(format!(
+            "No free socket address for terminal '{terminal_id}' after {MAX_SOCKET_CANDIDATES} attempts"
         ))
     }
 
+    /// Assigns an existing socket (e.g. one a Neovim from an earlier run still
+    /// listens on) to `terminal_id`. Returns `false` if nothing is listening.
+    pub fn adopt(&mut self, terminal_id: &str, path: PathBuf) -> bool {
+        if !socket_is_live(&path) {
+            return false;
+        }
+        self.assigned.insert(terminal_id.to_string(), path);
+        true
+    }
+
+    /// Socket handed out for `terminal_id`, falling back to its default address.
+    pub fn assigned_path(&self, terminal_id: &str) -> PathBuf {
+        self.assigned
+            .get(terminal_id)
+            .cloned()
+            .unwrap_or_else(|| self.socket_path(terminal_id))
+    }
+
     pub fn register(&mut self, path: PathBuf) {
         if !self.created_sockets.contains(&path) {
             self.created_sockets.push(path);
@@ -27,41 +184,102 @@ impl SocketManager {
     }
 
     pub fn remove_socket(&mut self, path: &Path) {
-        let _ = std::fs::remove_file(path);
+        remove_socket_file(path);
         self.created_sockets.retain(|p| p != path);
+        self.assigned.retain(|_, p| p != path);
+        self.records.retain(|record| record.path != path);
+        self.persist_registry();
     }
 
     pub fn cleanup_all(&mut self) {
         for path in self.created_sockets.drain(..) {
-            let _ = std::fs::remove_file(&path);
+            remove_socket_file(&path);
         }
+        let instance_id = self.instance_id;
+        self.records.retain(|record| record.pid != instance_id);
+        self.persist_registry();
     }
 
-    /// Remove sockets left behind by dead processes.
-    /// Scans /tmp for `libg-nvim-{pid}-*.sock` and removes any whose PID is no longer alive.
-    pub fn cleanup_stale() {
-        let Ok(entries) = std::fs::read_dir("/tmp") else {
+    /// Notes the tmux session hosting a terminal's Neovim so it can be
+    /// reconciled if this instance dies without cleaning up.
+    pub fn record_tmux_session(&mut self, terminal_id: &str, session_name: &str) {
+        let mut changed = false;
+        for record in self
+            .records
+            .iter_mut()
+            .filter(|record| record.terminal_id == terminal_id)
+        {
+            if record.tmux_session.as_deref() != Some(session_name) {
+                record.tmux_session = Some(session_name.to_string());
+                changed = true;
+            }
+        }
+        if changed {
+            self.persist_registry();
+        }
+    }
+
+    /// Loads the registry at `path` and drops entries owned by processes that
+    /// are gone, removing their socket files. Returns the dropped records so the
+    /// caller can reconcile related resources such as tmux sessions.
+    pub fn load_registry(&mut self, path: PathBuf) -> Vec<SocketRecord> {
+        let stored: Vec<SocketRecord> = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                log::warn!(
+                    "Failed to parse socket registry '{}'. Starting fresh: {err}",
+                    path.display()
+                );
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+
+        let instance_id = self.instance_id;
+        // Anything already carrying our pid was written by an earlier process
+        // that happened to get the same id.
+        let (stale, live): (Vec<_>, Vec<_>) = stored
+            .into_iter()
+            .partition(|record| record.pid == instance_id || !process_alive(record.pid));
+        for record in &stale {
+            remove_socket_file(&record.path);
+        }
+
+        self.registry_path = Some(path);
+        // Keep entries for this instance that were recorded before the path was known.
+        self.records.extend(live);
+        self.persist_registry();
+        stale
+    }
+
+    fn persist_registry(&self) {
+        let Some(path) = self.registry_path.as_ref() else {
             return;
         };
-        for entry in entries.flatten() {
-            let name = entry.file_name();
-            let Some(name) = name.to_str() else { continue };
-            if !name.starts_with("libg-nvim-") || !name.ends_with(".sock") {
-                continue;
-            }
-            // Extract PID: "libg-nvim-{pid}-{terminalId}.sock"
-            let inner = &name["libg-nvim-".len()..name.len() - ".sock".len()];
-            let Some(pid_str) = inner.split('-').next() else {
-                continue;
-            };
-            let Ok(pid) = pid_str.parse::<i32>() else {
-                continue;
-            };
-            // Check if the process is still alive
-            let alive = unsafe { libc::kill(pid, 0) } == 0;
-            if !alive {
-                let _ = std::fs::remove_file(entry.path());
-            }
+        let result = serde_json::to_string_pretty(&self.records)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                std::fs::write(path, contents).map_err(|e| e.to_string())
+            });
+        if let Err(err) = result {
+            log::warn!(
+                "Failed to write socket registry '{}': {err}",
+                path.display()
+            );
+        }
+    }
+
+    /// Remove sockets left behind by dead processes.
+    /// Scans the socket directory for `libg-nvim-{pid}-*.sock` and removes any whose PID is no longer alive.
+    /// Named pipes are released by the OS when their server exits, so Windows has nothing to scan.
+    pub fn cleanup_stale(&self) {
+        if cfg!(windows) {
+            return;
+        }
+        for path in stale_socket_files(&self.socket_dir, process_alive) {
+            remove_socket_file(&path);
         }
     }
 }
@@ -71,3 +289,183 @@ impl Drop for SocketManager {
         self.cleanup_all();
     }
 }
+
+/// Socket files in `dir` whose owning process `alive` reports as gone.
+fn stale_socket_files(dir: &Path, alive: impl Fn(u32) -> bool) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(socket_owner_pid)
+                .is_some_and(|pid| !alive(pid))
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+/// The pid in a `libg-nvim-{pid}-{terminalId}.sock` file name.
+fn socket_owner_pid(file_name: &str) -> Option<u32> {
+    file_name
+        .strip_prefix("libg-nvim-")?
+        .strip_suffix(".sock")?
+        .split('-')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Whether something is accepting connections on `path`. A successful probe
+/// connection is dropped immediately; Neovim tolerates idle clients going away.
+fn socket_is_live(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        std::os::unix::net::UnixStream::connect(path).is_ok()
+    }
+
+    #[cfg(windows)]
+    {
+        std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .is_ok()
+    }
+}
+
+fn remove_socket_file(path: &Path) {
+    if cfg!(not(windows)) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/neoai` when available, otherwise a per-user directory
+/// under the system temp dir so users on shared machines don't collide.
+pub fn default_socket_dir() -> PathBuf {
+    if let Some(runtime_dir) = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute() && dir.is_dir())
+    {
+        return runtime_dir.join("neoai");
+    }
+
+    #[cfg(unix)]
+    {
+        let uid = unsafe { libc::getuid() };
+        std::env::temp_dir().join(format!("neoai-{uid}"))
+    }
+
+    #[cfg(not(unix))]
+    {
+        std::env::temp_dir().join("neoai")
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_sockets_of_exited_instances() {
+        let dir = std::env::temp_dir().join(format!("neoai-stale-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "libg-nvim-100-terminal-a.sock",
+            "libg-nvim-200-terminal-b.sock",
+            "libg-nvim-x-terminal-c.sock",
+            "other.sock",
+        ] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let stale = stale_socket_files(&dir, |pid| pid == 200);
+        assert_eq!(stale, vec![dir.join("libg-nvim-100-terminal-a.sock")]);
+        assert_eq!(socket_owner_pid("libg-nvim-42-t1-1.sock"), Some(42));
+        assert_eq!(socket_owner_pid("libg-nvim-42-t1.pipe"), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn manager_in(dir: &Path) -> SocketManager {
+        let mut mgr = SocketManager::new();
+        mgr.set_socket_dir(dir.to_path_buf());
+        mgr
+    }
+
+    #[test]
+    fn allocate_unlinks_stale_files_and_skips_live_listeners() {
+        let dir = std::env::temp_dir().join(format!("neoai-socket-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut mgr = manager_in(&dir);
+        mgr.ensure_socket_dir().unwrap();
+
+        // A leftover file with nobody listening is replaced in place.
+        let default_path = mgr.socket_path("t1");
+        std::fs::write(&default_path, b"").unwrap();
+        let fresh = mgr.allocate("t1").unwrap();
+        assert_eq!(Path::new(&fresh.path), default_path);
+        assert!(!fresh.reused);
+        assert!(!default_path.exists());
+
+        // Another process listening on the default address forces a suffix.
+        let _foreign = std::os::unix::net::UnixListener::bind(mgr.socket_path("t2")).unwrap();
+        let suffixed = mgr.allocate("t2").unwrap();
+        assert_eq!(Path::new(&suffixed.path), mgr.socket_path("t2-1"));
+        assert!(!suffixed.reused);
+
+        // Once this terminal's Neovim is listening, the address is reused.
+        let _ours = std::os::unix::net::UnixListener::bind(&suffixed.path).unwrap();
+        let reused = mgr.allocate("t2").unwrap();
+        assert_eq!(reused.path, suffixed.path);
+        assert!(reused.reused);
+        assert_eq!(mgr.assigned_path("t2"), mgr.socket_path("t2-1"));
+
+        drop(mgr);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn load_registry_drops_records_from_dead_processes() {
+        let dir = std::env::temp_dir().join(format!("neoai-registry-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let dead_socket = dir.join("libg-nvim-dead.sock");
+        std::fs::write(&dead_socket, b"").unwrap();
+        let records = vec![
+            SocketRecord {
+                path: dead_socket.clone(),
+                pid: std::process::id(),
+                terminal_id: "terminal-a".to_string(),
+                tmux_session: Some("neoai-a".to_string()),
+            },
+            SocketRecord {
+                path: dir.join("libg-nvim-parent.sock"),
+                pid: std::os::unix::process::parent_id(),
+                terminal_id: "terminal-b".to_string(),
+                tmux_session: None,
+            },
+        ];
+        let registry = dir.join(SOCKET_REGISTRY_FILE);
+        std::fs::write(&registry, serde_json::to_string(&records).unwrap()).unwrap();
+
+        let mut mgr = manager_in(&dir);
+        let stale = mgr.load_registry(registry.clone());
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].tmux_session.as_deref(), Some("neoai-a"));
+        assert!(!dead_socket.exists());
+
+        let kept: Vec<SocketRecord> =
+            serde_json::from_str(&std::fs::read_to_string(&registry).unwrap()).unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].terminal_id, "terminal-b");
+
+        drop(mgr);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,484 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+
+use crate::app_paths;
+use crate::folder_state;
+use crate::fs_watch::FileChangedEvent;
+use crate::project_files;
+
+const SYMBOLS_DIR: &str = "symbols";
+/// Files handed to ctags per folder.
+const INDEX_FILE_LIMIT: usize = 50_000;
+/// Larger files are usually generated or vendored; ctags skips nothing on its
+/// own, so they are left out here.
+const MAX_INDEXED_FILE_BYTES: u64 = 1024 * 1024;
+
+/// A definition found by ctags.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedSymbol {
+    pub name: String,
+    pub kind: String,
+    /// Relative to the folder root.
+    pub path: String,
+    /// 1-based.
+    pub line: u32,
+    pub end_line: Option<u32>,
+    /// Enclosing type or module, e.g. `AppConfigState`.
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolSearchResult {
+    pub symbols: Vec<IndexedSymbol>,
+    /// The index is being (re)built; results may be missing or out of date.
+    pub indexing: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SymbolIndexEvent {
+    folder_id: String,
+    symbol_count: usize,
+    error: Option<String>,
+}
+
+/// What gets written to `symbols/<folder>.json`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredIndex {
+    root: PathBuf,
+    built_at_ms: u64,
+    symbols: Vec<IndexedSymbol>,
+}
+
+struct FolderSymbols {
+    root: PathBuf,
+    symbols: Arc<Vec<IndexedSymbol>>,
+    /// Files changed since the index was built.
+    stale: bool,
+}
+
+/// Per-folder symbol indexes built by ctags in the background.
+#[derive(Default)]
+pub struct SymbolIndexState {
+    indexes: HashMap<String, FolderSymbols>,
+    building: HashSet<String>,
+}
+
+impl SymbolIndexState {
+    fn mark_stale(&mut self, folder_id: &str) {
+        if let Some(index) = self.indexes.get_mut(folder_id) {
+            index.stale = true;
+        }
+    }
+}
+
+/// Parses `ctags --output-format=json` output, skipping pseudo-tags and
+/// lines it can't read.
+fn parse_ctags_output(output: &str) -> Vec<IndexedSymbol> {
+    #[derive(Deserialize)]
+    struct CtagsLine {
+        #[serde(rename = "_type")]
+        kind_of_line: String,
+        name: String,
+        path: String,
+        line: Option<u32>,
+        #[serde(default)]
+        kind: String,
+        scope: Option<String>,
+        end: Option<u32>,
+    }
+
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CtagsLine>(line).ok())
+        .filter(|tag| tag.kind_of_line == "tag")
+        .filter_map(|tag| {
+            Some(IndexedSymbol {
+                line: tag.line?,
+                name: tag.name,
+                kind: tag.kind,
+                path: tag.path.replace('\\', "/"),
+                end_line: tag.end,
+                scope: tag.scope,
+            })
+        })
+        .collect()
+}
+
+/// Best matches for `query`: exact names first, then case-insensitive,
+/// prefix and substring matches. `Type::name` and `Type.name` narrow the
+/// search to symbols scoped under `Type`.
+fn search(symbols: &[IndexedSymbol], query: &str, limit: usize) -> Vec<IndexedSymbol> {
+    let query = query.trim();
+    let (scope, name) = match query.rsplit_once("::").or_else(|| query.rsplit_once('.')) {
+        Some((scope, name)) => (Some(scope), name),
+        None => (None, query),
+    };
+    if name.is_empty() {
+        return Vec::new();
+    }
+    let lower = name.to_lowercase();
+
+    let mut ranked: Vec<(u8, &IndexedSymbol)> = symbols
+        .iter()
+        .filter(|symbol| match scope {
+            Some(scope) => symbol
+                .scope
+                .as_deref()
+                .is_some_and(|symbol_scope| symbol_scope.ends_with(scope)),
+            None => true,
+        })
+        .filter_map(|symbol| {
+            let candidate = symbol.name.to_lowercase();
+            let rank = if symbol.name == name {
+                0
+            } else if candidate == lower {
+                1
+            } else if candidate.starts_with(&lower) {
+                2
+            } else if candidate.contains(&lower) {
+                3
+            } else {
+                return None;
+            };
+            Some((rank, symbol))
+        })
+        .collect();
+    ranked.sort_by(|(a_rank, a), (b_rank, b)| {
+        a_rank
+            .cmp(b_rank)
+            .then_with(|| a.name.len().cmp(&b.name.len()))
+            .then_with(|| a.path.cmp(&b.path))
+            .then_with(|| a.line.cmp(&b.line))
+    });
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(_, symbol)| symbol.clone())
+        .collect()
+}
+
+/// Runs ctags over the folder's non-ignored files.
+fn build_index(root: &Path) -> Result<Vec<IndexedSymbol>, String> {
+    let listing = project_files::list_files(root, None, INDEX_FILE_LIMIT)?;
+    let files: Vec<String> = listing
+        .files
+        .into_iter()
+        .filter(|file| file.size <= MAX_INDEXED_FILE_BYTES)
+        .map(|file| file.path)
+        .collect();
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut child = Command::new("ctags")
+        .args([
+            "--output-format=json",
+            "--fields=+neK",
+            "--extras=-F",
+            "-f",
+            "-",
+            "-L",
+            "-",
+        ])
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| {
+            format!("Failed to run ctags ({e}). Install Universal Ctags to index symbols")
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Writing on this thread while ctags fills stdout could deadlock, so
+        // hand the list over from a helper thread.
+        let list = files.join("\n");
+        std::thread::spawn(move || {
+            let _ = stdin.write_all(list.as_bytes());
+        });
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("ctags failed: {e}"))?;
+    if !output.status.success() {
+        return Err(format!("ctags exited with {}", output.status));
+    }
+    Ok(parse_ctags_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Folder ids come from the frontend; keep them to a safe file name.
+fn index_file(app_root: &Path, folder_id: &str) -> PathBuf {
+    let name: String = folder_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    app_root.join(SYMBOLS_DIR).join(format!("{name}.json"))
+}
+
+fn load_stored(path: &Path, root: &Path) -> Option<Vec<IndexedSymbol>> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let stored: StoredIndex = serde_json::from_str(&contents).ok()?;
+    (stored.root == root).then_some(stored.symbols)
+}
+
+fn store(path: &Path, root: &Path, symbols: &[IndexedSymbol]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            format!(
+                "Failed to create symbol index directory '{}': {e}",
+                parent.display()
+            )
+        })?;
+    }
+    let built_at_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0);
+    let stored = StoredIndex {
+        root: root.to_path_buf(),
+        built_at_ms,
+        symbols: symbols.to_vec(),
+    };
+    let contents = serde_json::to_string(&stored).map_err(|e| e.to_string())?;
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, contents).map_err(|e| {
+        format!(
+            "Failed to write symbol index '{}': {e}",
+            temp_path.display()
+        )
+    })?;
+    std::fs::rename(&temp_path, path)
+        .map_err(|e| format!("Failed to replace symbol index '{}': {e}", path.display()))
+}
+
+/// Starts a background rebuild of `folder_id`'s index unless one is already
+/// running. Emits `symbol-index-updated` when done.
+fn spawn_build(app_handle: &tauri::AppHandle, folder_id: &str, root: PathBuf) {
+    let Some(state) = app_handle.try_state::<std::sync::Mutex<SymbolIndexState>>() else {
+        return;
+    };
+    match state.lock() {
+        Ok(mut state) => {
+            if !state.building.insert(folder_id.to_string()) {
+                return;
+            }
+        }
+        Err(_) => return,
+    }
+
+    let app_handle = app_handle.clone();
+    let folder_id = folder_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        let build_root = root.clone();
+        let result = tauri::async_runtime::spawn_blocking(move || build_index(&build_root))
+            .await
+            .map_err(|e| format!("Symbol index task failed: {e}"))
+            .and_then(|result| result);
+
+        let state = app_handle.state::<std::sync::Mutex<SymbolIndexState>>();
+        let event = match result {
+            Ok(symbols) => {
+                if let Ok(app_root) = app_paths::app_root_dir(&app_handle) {
+                    if let Err(err) = store(&index_file(&app_root, &folder_id), &root, &symbols) {
+                        log::warn!("{}", err);
+                    }
+                }
+                log::info!(
+                    "Indexed {} symbol(s) for folder {}",
+                    symbols.len(),
+                    folder_id
+                );
+                let symbol_count = symbols.len();
+                if let Ok(mut state) = state.lock() {
+                    state.indexes.insert(
+                        folder_id.clone(),
+                        FolderSymbols {
+                            root,
+                            symbols: Arc::new(symbols),
+                            stale: false,
+                        },
+                    );
+                }
+                SymbolIndexEvent {
+                    folder_id: folder_id.clone(),
+                    symbol_count,
+                    error: None,
+                }
+            }
+            Err(err) => {
+                log::warn!("Failed to index symbols for folder {}: {}", folder_id, err);
+                SymbolIndexEvent {
+                    folder_id: folder_id.clone(),
+                    symbol_count: 0,
+                    error: Some(err),
+                }
+            }
+        };
+        if let Ok(mut state) = state.lock() {
+            state.building.remove(&folder_id);
+        }
+        let _ = app_handle.emit("symbol-index-updated", &event);
+    });
+}
+
+/// The folder's current symbols, loading the stored index or starting a
+/// build when there is none. Never waits for a build.
+fn current_symbols(
+    app_handle: &tauri::AppHandle,
+    folder_id: &str,
+) -> Result<(Arc<Vec<IndexedSymbol>>, bool), String> {
+    let root = folder_state::resolve_folder_path(app_handle, folder_id)?;
+    let root = std::fs::canonicalize(&root).unwrap_or(root);
+    let state = app_handle.state::<std::sync::Mutex<SymbolIndexState>>();
+
+    let cached = {
+        let state = state.lock().map_err(|e| e.to_string())?;
+        state
+            .indexes
+            .get(folder_id)
+            .filter(|index| index.root == root)
+            .map(|index| (Arc::clone(&index.symbols), index.stale))
+    };
+    let (symbols, needs_build) = match cached {
+        Some((symbols, stale)) => (symbols, stale),
+        None => {
+            let stored = app_paths::app_root_dir(app_handle)
+                .ok()
+                .and_then(|app_root| load_stored(&index_file(&app_root, folder_id), &root));
+            match stored {
+                Some(symbols) => {
+                    let symbols = Arc::new(symbols);
+                    // Files may have changed while the app was closed.
+                    let mut state = state.lock().map_err(|e| e.to_string())?;
+                    state.indexes.insert(
+                        folder_id.to_string(),
+                        FolderSymbols {
+                            root: root.clone(),
+                            symbols: Arc::clone(&symbols),
+                            stale: true,
+                        },
+                    );
+                    (symbols, true)
+                }
+                None => (Arc::new(Vec::new()), true),
+            }
+        }
+    };
+
+    if needs_build {
+        spawn_build(app_handle, folder_id, root);
+    }
+    let building = state
+        .lock()
+        .map(|state| state.building.contains(folder_id))
+        .unwrap_or(false);
+    Ok((symbols, building))
+}
+
+/// Looks `name` up in the folder's index without a live LSP. Returns the
+/// absolute path with the symbol.
+pub fn find_symbol(
+    app_handle: &tauri::AppHandle,
+    folder_id: &str,
+    name: &str,
+) -> Option<(PathBuf, IndexedSymbol)> {
+    let (symbols, _) = current_symbols(app_handle, folder_id).ok()?;
+    let symbol = search(&symbols, name, 1).into_iter().next()?;
+    let root = folder_state::resolve_folder_path(app_handle, folder_id).ok()?;
+    Some((root.join(&symbol.path), symbol))
+}
+
+/// Called by the file watcher; the next lookup rebuilds the index.
+pub fn apply_file_changes(
+    app_handle: &tauri::AppHandle,
+    folder_id: &str,
+    changes: &[FileChangedEvent],
+) {
+    if changes.is_empty() {
+        return;
+    }
+    if let Some(state) = app_handle.try_state::<std::sync::Mutex<SymbolIndexState>>() {
+        if let Ok(mut state) = state.lock() {
+            state.mark_stale(folder_id);
+        }
+    }
+}
+
+// -- Tauri IPC commands --
+
+/// Searches the folder's symbol index. The first search (or one after files
+/// changed) starts a background rebuild and answers from what is indexed so
+/// far; `symbol-index-updated` fires when the rebuild finishes.
+#[tauri::command]
+pub async fn search_symbols(
+    app_handle: tauri::AppHandle,
+    folder_id: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<SymbolSearchResult, String> {
+    let (symbols, indexing) = current_symbols(&app_handle, &folder_id)?;
+    let limit = limit.unwrap_or(50);
+    let symbols = tauri::async_runtime::spawn_blocking(move || search(&symbols, &query, limit))
+        .await
+        .map_err(|e| format!("Symbol search task failed: {e}"))?;
+    Ok(SymbolSearchResult { symbols, indexing })
+}
+
+/// Rebuilds the folder's symbol index in the background.
+#[tauri::command]
+pub async fn reindex_symbols(
+    app_handle: tauri::AppHandle,
+    folder_id: String,
+) -> Result<(), String> {
+    let root = folder_state::resolve_folder_path(&app_handle, &folder_id)?;
+    let root = std::fs::canonicalize(&root).unwrap_or(root);
+    spawn_build(&app_handle, &folder_id, root);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ctags_json_and_ranks_matches() {
+        let output = r#"{"_type": "ptag", "name": "JSON_OUTPUT_VERSION", "path": "0.0"}
+{"_type": "tag", "name": "AppConfigState", "path": "src/app_config.rs", "line": 210, "kind": "struct", "end": 220}
+{"_type": "tag", "name": "reload", "path": "src/app_config.rs", "line": 300, "kind": "method", "scope": "AppConfigState", "end": 320}
+{"_type": "tag", "name": "reload", "path": "src/folder_state.rs", "line": 40, "kind": "method", "scope": "FolderStateStore"}
+{"_type": "tag", "name": "reload_all", "path": "src/lib.rs", "line": 12, "kind": "function"}
+not json"#;
+        let symbols = parse_ctags_output(output);
+        assert_eq!(symbols.len(), 4);
+        assert_eq!(symbols[1].end_line, Some(320));
+        assert_eq!(symbols[1].scope.as_deref(), Some("AppConfigState"));
+
+        let found = search(&symbols, "AppConfigState::reload", 10);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, "src/app_config.rs");
+
+        let names: Vec<_> = search(&symbols, "RELOAD", 10)
+            .into_iter()
+            .map(|symbol| symbol.name)
+            .collect();
+        assert_eq!(names, vec!["reload", "reload", "reload_all"]);
+        assert_eq!(search(&symbols, "config", 10)[0].name, "AppConfigState");
+        assert!(search(&symbols, "", 10).is_empty());
+    }
+}
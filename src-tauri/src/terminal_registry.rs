@@ -0,0 +1,306 @@
+//! Owns a terminal's whole lifecycle: the Ghostty view, its Neovim socket,
+//! the bridge connection and the tmux session and panes behind it. Creating
+//! or destroying a terminal goes through one command that touches every
+//! subsystem and reports progress on `terminal-lifecycle`.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+use tokio::sync::Mutex;
+
+use crate::acp_client::{self, AcpClientState};
+use crate::error::NeoaiError;
+use crate::ghostty_embed::{GhosttyOptions, GhosttyRect};
+use crate::nvim_bridge::{self, NvimBridgeState};
+use crate::socket_manager::SocketManager;
+use crate::tmux_runtime::{self, TmuxRuntimeState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TerminalPhase {
+    Creating,
+    Created,
+    Destroying,
+    Destroyed,
+    /// Creation failed; everything it had set up was torn down again.
+    Failed,
+}
+
+/// Payload of `terminal-lifecycle`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalLifecycleEvent {
+    pub terminal_id: String,
+    pub phase: TerminalPhase,
+    pub socket_path: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedTerminal {
+    pub terminal_id: String,
+    pub socket_path: String,
+    /// `true` when a Neovim from an earlier run is still listening on it.
+    pub socket_reused: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalTeardown {
+    pub terminal_id: String,
+    /// Steps that failed, as `subsystem: error`. Later steps still run.
+    pub errors: Vec<String>,
+}
+
+/// Terminals with a create or destroy in progress, so two calls for the same
+/// id can't interleave.
+#[derive(Default)]
+pub struct TerminalRegistry {
+    in_flight: HashSet<String>,
+}
+
+impl TerminalRegistry {
+    fn begin(&mut self, terminal_id: &str) -> Result<(), String> {
+        if !self.in_flight.insert(terminal_id.to_string()) {
+            return Err(format!(
+                "Terminal {terminal_id} is already being created or destroyed"
+            ));
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, terminal_id: &str) {
+        self.in_flight.remove(terminal_id);
+    }
+}
+
+/// Releases the terminal's slot in [`TerminalRegistry`] when dropped.
+struct LifecycleGuard {
+    app_handle: tauri::AppHandle,
+    terminal_id: String,
+}
+
+impl LifecycleGuard {
+    fn acquire(app_handle: &tauri::AppHandle, terminal_id: &str) -> Result<Self, String> {
+        app_handle
+            .state::<std::sync::Mutex<TerminalRegistry>>()
+            .lock()
+            .map_err(|e| e.to_string())?
+            .begin(terminal_id)?;
+        Ok(Self {
+            app_handle: app_handle.clone(),
+            terminal_id: terminal_id.to_string(),
+        })
+    }
+}
+
+impl Drop for LifecycleGuard {
+    fn drop(&mut self) {
+        if let Ok(mut registry) = self
+            .app_handle
+            .state::<std::sync::Mutex<TerminalRegistry>>()
+            .lock()
+        {
+            registry.finish(&self.terminal_id);
+        }
+    }
+}
+
+fn emit_lifecycle(
+    app_handle: &tauri::AppHandle,
+    terminal_id: &str,
+    phase: TerminalPhase,
+    socket_path: Option<String>,
+    error: Option<String>,
+) {
+    let event = TerminalLifecycleEvent {
+        terminal_id: terminal_id.to_string(),
+        phase,
+        socket_path,
+        error,
+    };
+    if let Err(err) = app_handle.emit("terminal-lifecycle", &event) {
+        log::debug!("Failed to emit terminal-lifecycle: {}", err);
+    }
+}
+
+/// Kills the tmux session and agent command panes hosted by `terminal_id`.
+pub async fn release_tmux(tmux_state: &Mutex<TmuxRuntimeState>, terminal_id: &str) {
+    let (session_name, pane_ids) = tmux_state.lock().await.remove_terminal(terminal_id);
+    for pane_id in pane_ids {
+        let _ = tmux_runtime::kill_pane(&pane_id).await;
+    }
+    if let Some(session_name) = session_name {
+        let _ = tmux_runtime::kill_session(&session_name).await;
+    }
+}
+
+/// Unlinks the socket handed out to `terminal_id` and forgets it.
+pub fn release_socket(
+    sockets: &std::sync::Mutex<SocketManager>,
+    terminal_id: &str,
+) -> Result<(), String> {
+    let mut mgr = sockets.lock().map_err(|e| e.to_string())?;
+    let path = mgr.assigned_path(terminal_id);
+    mgr.remove_socket(&path);
+    Ok(())
+}
+
+async fn teardown(window: &tauri::Window, terminal_id: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if let Err(err) = acp_client::acp_unsubscribe_terminal_events(
+        window.state::<Mutex<AcpClientState>>(),
+        terminal_id.to_string(),
+    )
+    .await
+    {
+        errors.push(format!("agent: {err}"));
+    }
+    if let Err(err) = nvim_bridge::nvim_disconnect(
+        window.state::<Mutex<NvimBridgeState>>(),
+        terminal_id.to_string(),
+    )
+    .await
+    {
+        errors.push(format!("nvim: {err}"));
+    }
+    release_tmux(&window.state::<Mutex<TmuxRuntimeState>>(), terminal_id).await;
+    if let Err(err) = release_socket(
+        &window.state::<std::sync::Mutex<SocketManager>>(),
+        terminal_id,
+    ) {
+        errors.push(format!("socket: {err}"));
+    }
+    if let Err(err) = crate::ghostty_destroy(window.clone(), terminal_id.to_string()) {
+        errors.push(format!("ghostty: {err}"));
+    }
+
+    errors
+}
+
+// -- Tauri IPC commands --
+
+/// Creates the terminal view and allocates its Neovim socket. If the socket
+/// can't be allocated a view created by this call is destroyed again, so a
+/// failed call leaves nothing behind.
+#[tauri::command]
+pub async fn create_terminal(
+    window: tauri::Window,
+    terminal_id: String,
+    rect: GhosttyRect,
+    options: Option<GhosttyOptions>,
+) -> Result<CreatedTerminal, NeoaiError> {
+    let app_handle = window.app_handle().clone();
+    let _guard = LifecycleGuard::acquire(&app_handle, &terminal_id)?;
+    emit_lifecycle(
+        &app_handle,
+        &terminal_id,
+        TerminalPhase::Creating,
+        None,
+        None,
+    );
+
+    let view_existed = crate::ghostty_exists(&window, &terminal_id)?;
+    let created = crate::ghostty_create(window.clone(), terminal_id.clone(), rect, options)
+        .and_then(|()| {
+            let sockets = window.state::<std::sync::Mutex<SocketManager>>();
+            let mut sockets = sockets.lock().map_err(|e| e.to_string())?;
+            Ok(sockets.allocate(&terminal_id)?)
+        });
+    let allocation = match created {
+        Ok(allocation) => allocation,
+        Err(err) => {
+            if !view_existed {
+                let _ = crate::ghostty_destroy(window.clone(), terminal_id.clone());
+            }
+            log::warn!("Failed to create terminal {}: {}", terminal_id, err);
+            emit_lifecycle(
+                &app_handle,
+                &terminal_id,
+                TerminalPhase::Failed,
+                None,
+                Some(err.to_string()),
+            );
+            return Err(err);
+        }
+    };
+
+    log::info!(
+        "Created terminal {} with socket {}",
+        terminal_id,
+        allocation.path
+    );
+    emit_lifecycle(
+        &app_handle,
+        &terminal_id,
+        TerminalPhase::Created,
+        Some(allocation.path.clone()),
+        None,
+    );
+    Ok(CreatedTerminal {
+        terminal_id,
+        socket_path: allocation.path,
+        socket_reused: allocation.reused,
+    })
+}
+
+/// Tears a terminal down across every subsystem: agent event subscription,
+/// Neovim bridge, tmux session and panes, socket, then the view. Every step
+/// runs even if an earlier one fails; failures are listed in the result and
+/// in the `destroyed` event. Destroying an unknown terminal is a no-op.
+#[tauri::command]
+pub async fn destroy_terminal(
+    window: tauri::Window,
+    terminal_id: String,
+) -> Result<TerminalTeardown, NeoaiError> {
+    let app_handle = window.app_handle().clone();
+    let _guard = LifecycleGuard::acquire(&app_handle, &terminal_id)?;
+    emit_lifecycle(
+        &app_handle,
+        &terminal_id,
+        TerminalPhase::Destroying,
+        None,
+        None,
+    );
+
+    let errors = teardown(&window, &terminal_id).await;
+    if errors.is_empty() {
+        log::info!("Destroyed terminal {}", terminal_id);
+    } else {
+        log::warn!(
+            "Destroyed terminal {} with errors: {:?}",
+            terminal_id,
+            errors
+        );
+    }
+    emit_lifecycle(
+        &app_handle,
+        &terminal_id,
+        TerminalPhase::Destroyed,
+        None,
+        (!errors.is_empty()).then(|| errors.join("; ")),
+    );
+    Ok(TerminalTeardown {
+        terminal_id,
+        errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_overlapping_lifecycle_calls_for_one_terminal() {
+        let mut registry = TerminalRegistry::default();
+        registry.begin("terminal-a").unwrap();
+        assert!(registry.begin("terminal-a").is_err());
+        registry.begin("terminal-b").unwrap();
+
+        registry.finish("terminal-a");
+        assert!(registry.begin("terminal-a").is_ok());
+    }
+}
@@ -0,0 +1,531 @@
+//! One persistent tmux control-mode (`tmux -C`) connection shared by the
+//! runtime. Commands go over the connection's stdin instead of spawning a
+//! `tmux` process each, and tmux pushes notifications for pane exits, window
+//! renames and closed sessions. Everything here is best effort: while the
+//! connection is down, `run` returns `None` and callers spawn `tmux` as before.
+
+use std::collections::VecDeque;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::Emitter;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{oneshot, Notify};
+
+use crate::tmux_runtime;
+
+/// Session the control client attaches to. It only hosts an idle shell.
+pub const CONTROL_SESSION: &str = "neoai-control";
+
+/// Global user option the `pane-died` hook of watched sessions sets to the
+/// dead pane's id; the control client subscribes to it.
+const PANE_DIED_OPTION: &str = "@neoai-pane-died";
+
+const PANE_DIED_SUBSCRIPTION: &str = "neoai-pane-died";
+
+/// How long a command waits for its reply before the connection is dropped.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest wait for a pane change while connected. tmux pushes pane deaths,
+/// so this only bounds how long a missed notification can stall a waiter.
+const CONNECTED_PANE_POLL: Duration = Duration::from_secs(2);
+
+const DISCONNECTED_PANE_POLL: Duration = Duration::from_millis(200);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static APP_HANDLE: OnceLock<tauri::AppHandle> = OnceLock::new();
+static CLIENT: Mutex<Option<Arc<ControlClient>>> = Mutex::new(None);
+static CONNECTING: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
+static PANE_CHANGED: OnceLock<Notify> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TmuxPaneDiedEvent {
+    pub pane_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TmuxWindowRenamedEvent {
+    pub window_id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TmuxWindowClosedEvent {
+    pub window_id: String,
+}
+
+/// `%begin`, `%end` and `%error` lines that frame a command's output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Guard {
+    Begin,
+    End,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GuardLine {
+    guard: Guard,
+    number: u64,
+    /// Set for replies to commands this client sent, as opposed to the
+    /// command the client was started with.
+    from_client: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Notification {
+    WindowRenamed { window_id: String, name: String },
+    WindowClosed { window_id: String },
+    SessionsChanged,
+    SubscriptionChanged { name: String, value: String },
+    Exit,
+}
+
+struct ControlClient {
+    stdin: tokio::sync::Mutex<ChildStdin>,
+    /// Reply senders in the order their commands were written.
+    pending: Mutex<VecDeque<oneshot::Sender<Result<String, String>>>>,
+    closed: AtomicBool,
+    /// Held so the process is killed when the client is dropped.
+    child: Mutex<Option<Child>>,
+}
+
+impl ControlClient {
+    /// Sends one command line. `None` if it could not be written; once it is
+    /// written the command may have run, so failures are reported as errors
+    /// instead of letting the caller run it a second time.
+    async fn request(self: &Arc<Self>, line: &str) -> Option<Result<String, String>> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        {
+            let mut stdin = self.stdin.lock().await;
+            if self.closed.load(Ordering::SeqCst) {
+                return None;
+            }
+            self.pending.lock().ok()?.push_back(reply_tx);
+            let written = async {
+                stdin.write_all(line.as_bytes()).await?;
+                stdin.write_all(b"\n").await?;
+                stdin.flush().await
+            }
+            .await;
+            if let Err(err) = written {
+                log::debug!("tmux control connection closed while writing: {err}");
+                self.close();
+                return None;
+            }
+        }
+
+        match tokio::time::timeout(REPLY_TIMEOUT, reply_rx).await {
+            Ok(Ok(reply)) => Some(reply),
+            Ok(Err(_)) => Some(Err("tmux control connection closed".to_string())),
+            Err(_) => {
+                // Replies would no longer line up with their commands.
+                log::warn!("tmux control mode stopped answering; reconnecting on next use");
+                self.close();
+                Some(Err("tmux control connection timed out".to_string()))
+            }
+        }
+    }
+
+    fn reply(&self, reply: Result<String, String>) {
+        let sender = self
+            .pending
+            .lock()
+            .ok()
+            .and_then(|mut pending| pending.pop_front());
+        if let Some(sender) = sender {
+            let _ = sender.send(reply);
+        }
+    }
+
+    fn close(self: &Arc<Self>) {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.clear();
+        }
+        if let Ok(mut child) = self.child.lock() {
+            if let Some(child) = child.as_mut() {
+                let _ = child.start_kill();
+            }
+        }
+        if let Ok(mut client) = CLIENT.lock() {
+            if client
+                .as_ref()
+                .is_some_and(|current| Arc::ptr_eq(current, self))
+            {
+                *client = None;
+            }
+        }
+        pane_changed().notify_waiters();
+    }
+}
+
+fn pane_changed() -> &'static Notify {
+    PANE_CHANGED.get_or_init(Notify::new)
+}
+
+fn current() -> Option<Arc<ControlClient>> {
+    CLIENT
+        .lock()
+        .ok()?
+        .clone()
+        .filter(|client| !client.closed.load(Ordering::SeqCst))
+}
+
+/// Records the app handle used for notifications and whether control mode is
+/// on. The connection itself is opened lazily by `connect`.
+pub fn init(app_handle: &tauri::AppHandle, enabled: bool) {
+    let _ = APP_HANDLE.set(app_handle.clone());
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Applies a `tmux_control_mode` change. Turning it off drops the connection,
+/// so later calls spawn `tmux` again.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+    if !enabled {
+        tauri::async_runtime::spawn(disconnect());
+    }
+}
+
+pub fn is_connected() -> bool {
+    current().is_some()
+}
+
+/// Opens the control connection unless it is up, disabled, or tmux can't
+/// start one. Failures are logged and leave callers on one-shot processes.
+pub async fn connect() {
+    if !ENABLED.load(Ordering::SeqCst) || is_connected() {
+        return;
+    }
+    let _connecting = CONNECTING
+        .get_or_init(|| tokio::sync::Mutex::new(()))
+        .lock()
+        .await;
+    if is_connected() {
+        return;
+    }
+
+    let client = match spawn_client() {
+        Ok(client) => client,
+        Err(err) => {
+            log::debug!("tmux control mode unavailable: {err}");
+            return;
+        }
+    };
+    if let Ok(mut current) = CLIENT.lock() {
+        *current = Some(client.clone());
+    }
+
+    // No pane output is wanted, only replies and notifications. Both
+    // commands need tmux 3.2; older servers just miss out on pane events.
+    let setup = [
+        "refresh-client -f no-output".to_string(),
+        format!(
+            "refresh-client -B {}",
+            tmux_runtime::shell_quote(&format!(
+                "{PANE_DIED_SUBSCRIPTION}::#{{{PANE_DIED_OPTION}}}"
+            ))
+        ),
+    ];
+    for line in setup {
+        match client.request(&line).await {
+            Some(Ok(_)) => {}
+            Some(Err(err)) => log::debug!("tmux control setup '{line}' failed: {err}"),
+            None => return,
+        }
+    }
+    log::info!("Connected to tmux in control mode");
+}
+
+fn spawn_client() -> Result<Arc<ControlClient>, String> {
+    let mut child = Command::new("tmux")
+        .args(["-C", "new-session", "-A", "-s", CONTROL_SESSION])
+        // tmux refuses to attach from inside another tmux client.
+        .env_remove("TMUX")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to execute tmux -C: {e}"))?;
+    let stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| "tmux -C has no stdin".to_string())?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| "tmux -C has no stdout".to_string())?;
+
+    let client = Arc::new(ControlClient {
+        stdin: tokio::sync::Mutex::new(stdin),
+        pending: Mutex::new(VecDeque::new()),
+        closed: AtomicBool::new(false),
+        child: Mutex::new(Some(child)),
+    });
+    tauri::async_runtime::spawn(read_control_output(client.clone(), stdout));
+    Ok(client)
+}
+
+async fn read_control_output(client: Arc<ControlClient>, stdout: ChildStdout) {
+    let mut reader = BufReader::new(stdout);
+    let mut raw = Vec::new();
+    let mut block: Option<(GuardLine, Vec<String>)> = None;
+    loop {
+        raw.clear();
+        match reader.read_until(b'\n', &mut raw).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = String::from_utf8_lossy(&raw);
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        if let Some((begin, body)) = block.as_mut() {
+            match parse_guard(line) {
+                Some(end) if end.guard != Guard::Begin && end.number == begin.number => {
+                    if begin.from_client {
+                        let output = reply_output(std::mem::take(body));
+                        client.reply(if end.guard == Guard::End {
+                            Ok(output)
+                        } else {
+                            Err(output.trim().to_string())
+                        });
+                    }
+                    block = None;
+                }
+                _ => body.push(line.to_string()),
+            }
+            continue;
+        }
+
+        if let Some(guard) = parse_guard(line) {
+            if guard.guard == Guard::Begin {
+                block = Some((guard, Vec::new()));
+            }
+            continue;
+        }
+        if let Some(notification) = parse_notification(line) {
+            handle_notification(notification);
+        }
+    }
+    log::debug!("tmux control connection ended");
+    client.close();
+}
+
+/// Joins a reply's lines the way `tmux` would print them to stdout.
+fn reply_output(body: Vec<String>) -> String {
+    if body.is_empty() {
+        return String::new();
+    }
+    let mut output = body.join("\n");
+    output.push('\n');
+    output
+}
+
+fn handle_notification(notification: Notification) {
+    match notification {
+        Notification::SubscriptionChanged { name, value } if name == PANE_DIED_SUBSCRIPTION => {
+            pane_changed().notify_waiters();
+            if !value.is_empty() {
+                emit("tmux-pane-died", TmuxPaneDiedEvent { pane_id: value });
+            }
+        }
+        Notification::SubscriptionChanged { .. } => {}
+        Notification::WindowRenamed { window_id, name } => {
+            emit(
+                "tmux-window-renamed",
+                TmuxWindowRenamedEvent { window_id, name },
+            );
+        }
+        Notification::WindowClosed { window_id } => {
+            pane_changed().notify_waiters();
+            emit("tmux-window-closed", TmuxWindowClosedEvent { window_id });
+        }
+        Notification::SessionsChanged => {
+            pane_changed().notify_waiters();
+            emit("tmux-sessions-changed", ());
+        }
+        // The reader sees end of output right after.
+        Notification::Exit => {}
+    }
+}
+
+fn emit<S: Serialize + Clone>(event: &str, payload: S) {
+    if let Some(app_handle) = APP_HANDLE.get() {
+        let _ = app_handle.emit(event, payload);
+    }
+}
+
+/// Runs a tmux command over the control connection. `None` when there is no
+/// connection or the command can't be sent on one line; the caller then runs
+/// `tmux` itself.
+pub async fn run(args: &[String]) -> Option<Result<String, String>> {
+    if args.iter().any(|arg| arg.contains(['\n', '\r'])) {
+        return None;
+    }
+    let client = current()?;
+    client.request(&command_line(args)).await
+}
+
+fn command_line(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| tmux_runtime::shell_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Makes pane exits in `session_name` reach the control client. A no-op while
+/// disconnected, where waiters poll instead.
+pub async fn watch_session(session_name: &str) {
+    if !is_connected() {
+        return;
+    }
+    let hook = format!("set-option -gF {PANE_DIED_OPTION} '#{{pane_id}}'");
+    let args = [
+        "set-hook".to_string(),
+        "-t".to_string(),
+        session_name.to_string(),
+        "pane-died".to_string(),
+        hook,
+    ];
+    if let Some(Err(err)) = run(&args).await {
+        log::debug!("Failed to watch tmux session '{session_name}' for pane exits: {err}");
+    }
+}
+
+/// Waits until a pane may have died: until tmux reports one while connected
+/// (or a safety timeout), otherwise for a short poll interval.
+pub async fn wait_for_pane_change() {
+    if is_connected() {
+        let _ = tokio::time::timeout(CONNECTED_PANE_POLL, pane_changed().notified()).await;
+    } else {
+        tokio::time::sleep(DISCONNECTED_PANE_POLL).await;
+    }
+}
+
+/// Closes the control connection and its session.
+pub async fn disconnect() {
+    let Some(client) = current() else {
+        return;
+    };
+    let kill = command_line(&[
+        "kill-session".to_string(),
+        "-t".to_string(),
+        CONTROL_SESSION.to_string(),
+    ]);
+    let _ = client.request(&kill).await;
+    client.close();
+}
+
+fn parse_guard(line: &str) -> Option<GuardLine> {
+    let mut fields = line.split(' ');
+    let guard = match fields.next()? {
+        "%begin" => Guard::Begin,
+        "%end" => Guard::End,
+        "%error" => Guard::Error,
+        _ => return None,
+    };
+    let _time = fields.next()?;
+    let number = fields.next()?.parse().ok()?;
+    let flags: u32 = fields.next()?.parse().ok()?;
+    Some(GuardLine {
+        guard,
+        number,
+        from_client: flags & 1 == 1,
+    })
+}
+
+fn parse_notification(line: &str) -> Option<Notification> {
+    let (name, rest) = line.split_once(' ').unwrap_or((line, ""));
+    match name {
+        "%window-renamed" | "%unlinked-window-renamed" => {
+            let (window_id, name) = rest.split_once(' ').unwrap_or((rest, ""));
+            Some(Notification::WindowRenamed {
+                window_id: window_id.to_string(),
+                name: name.to_string(),
+            })
+        }
+        "%window-close" | "%unlinked-window-close" => Some(Notification::WindowClosed {
+            window_id: rest.trim().to_string(),
+        }),
+        "%sessions-changed" => Some(Notification::SessionsChanged),
+        // `%subscription-changed name $session @window index %pane ... : value`
+        "%subscription-changed" => {
+            let (fields, value) = rest.split_once(" : ")?;
+            let name = fields.split(' ').next()?;
+            Some(Notification::SubscriptionChanged {
+                name: name.to_string(),
+                value: value.to_string(),
+            })
+        }
+        "%exit" => Some(Notification::Exit),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_guards_and_notifications() {
+        assert_eq!(
+            parse_guard("%begin 1363006971 2 1"),
+            Some(GuardLine {
+                guard: Guard::Begin,
+                number: 2,
+                from_client: true,
+            })
+        );
+        assert_eq!(
+            parse_guard("%error 1363006971 7 0").map(|guard| (guard.guard, guard.from_client)),
+            Some((Guard::Error, false))
+        );
+        assert_eq!(parse_guard("%begin oops"), None);
+        assert_eq!(parse_guard("%output %1 hi"), None);
+
+        assert_eq!(
+            parse_notification("%window-renamed @3 cargo test"),
+            Some(Notification::WindowRenamed {
+                window_id: "@3".to_string(),
+                name: "cargo test".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_notification("%unlinked-window-close @4"),
+            Some(Notification::WindowClosed {
+                window_id: "@4".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_notification("%subscription-changed neoai-pane-died $1 - - - : %12"),
+            Some(Notification::SubscriptionChanged {
+                name: "neoai-pane-died".to_string(),
+                value: "%12".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_notification("%sessions-changed"),
+            Some(Notification::SessionsChanged)
+        );
+        assert_eq!(parse_notification("%layout-change @1 abc"), None);
+    }
+
+    #[test]
+    fn quotes_each_argument() {
+        let args = ["send-keys", "-t", "%1", "echo 'hi' $HOME", ""].map(String::from);
+        assert_eq!(
+            command_line(&args),
+            r#"'send-keys' '-t' '%1' 'echo '"'"'hi'"'"' $HOME' ''"#
+        );
+    }
+}
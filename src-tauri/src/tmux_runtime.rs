@@ -1,11 +1,25 @@
-use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use agent_client_protocol as acp;
 use serde::{Deserialize, Serialize};
 use tokio::process::Command;
+use tokio::sync::Notify;
+
+use crate::app_config::EnvRules;
+use crate::tmux_control;
 
 const DEFAULT_OUTPUT_LIMIT: u64 = 64 * 1024;
+/// Directory under the app folder holding spilled command output.
+pub const COMMAND_LOG_DIR_NAME: &str = "command-logs";
+/// Spilled output files kept; older ones are deleted as new ones are written.
+const MAX_COMMAND_LOGS: usize = 50;
+/// Released commands remembered for `tmux_list_commands`, across terminals.
+const RELEASED_COMMAND_HISTORY: usize = 100;
+/// Longest window or pane title derived from a command.
+const MAX_COMMAND_TITLE_CHARS: usize = 32;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -34,6 +48,58 @@ impl TmuxCommandMode {
     }
 }
 
+/// Quoting and environment syntax of the shell a command is typed into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    Posix,
+    Fish,
+    PowerShell,
+}
+
+impl ShellKind {
+    /// Classifies a shell by program name or path (`/usr/bin/fish`,
+    /// `pwsh.exe`, `-zsh` as login shells show up). Unknown shells are
+    /// treated as POSIX.
+    pub fn from_program(program: &str) -> Self {
+        // Split by hand so Windows paths classify on any host.
+        let name = program
+            .trim()
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches('-')
+            .to_ascii_lowercase();
+        let name = name.strip_suffix(".exe").unwrap_or(&name);
+        match name {
+            "fish" => Self::Fish,
+            "pwsh" | "powershell" => Self::PowerShell,
+            _ => Self::Posix,
+        }
+    }
+
+    /// Quotes `value` as a single word for this shell.
+    pub fn quote(self, value: &str) -> String {
+        match self {
+            Self::Posix => shell_quote(value),
+            // Inside fish single quotes only \' and \\ are escapes.
+            Self::Fish => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'")),
+            Self::PowerShell => format!("'{}'", value.replace('\'', "''")),
+        }
+    }
+}
+
+/// What to run in a command pane.
+pub struct PaneCommand<'a> {
+    pub command: &'a str,
+    pub args: &'a [String],
+    pub env: &'a [acp::EnvVariable],
+    /// Variables in `env` these rules don't permit are left out.
+    pub env_rules: &'a EnvRules,
+    /// Shell `exec`ed in the pane first (`command_shell`); otherwise the
+    /// command goes to the pane's own shell.
+    pub shell: Option<&'a str>,
+}
+
 #[derive(Debug, Clone)]
 struct TerminalTmuxConfig {
     enabled: bool,
@@ -43,14 +109,93 @@ struct TerminalTmuxConfig {
 #[derive(Debug, Clone)]
 pub struct ManagedTmuxCommand {
     pub host_terminal_id: String,
+    /// ACP session that created it; the concurrency limit counts per session.
+    pub session_id: String,
+    pub pane_id: String,
+    pub output_byte_limit: Option<u64>,
+    /// Command and arguments as requested by the agent, for notifications.
+    pub label: String,
+    /// Short name given to the tmux window or pane, from [`command_title`].
+    pub title: String,
+    pub started_at: Instant,
+    /// Milliseconds since the Unix epoch when the command started.
+    pub started_at_ms: u64,
+    /// Set once the command has been seen exiting.
+    pub ended_at: Option<Instant>,
+    pub exit_code: Option<u32>,
+    /// Directory the command was started in, after confinement checks.
+    pub cwd: Option<PathBuf>,
+}
+
+impl ManagedTmuxCommand {
+    pub fn exited(&self) -> bool {
+        self.ended_at.is_some()
+    }
+
+    fn info(&self, command_id: &str, released: bool) -> TmuxCommandInfo {
+        let elapsed = match self.ended_at {
+            Some(ended_at) => ended_at.duration_since(self.started_at),
+            None => self.started_at.elapsed(),
+        };
+        TmuxCommandInfo {
+            command_id: command_id.to_string(),
+            terminal_id: self.host_terminal_id.clone(),
+            pane_id: self.pane_id.clone(),
+            title: self.title.clone(),
+            command: self.label.clone(),
+            cwd: self
+                .cwd
+                .as_ref()
+                .map(|cwd| cwd.to_string_lossy().into_owned()),
+            running: !self.exited(),
+            released,
+            exit_code: self.exit_code,
+            started_at_ms: self.started_at_ms,
+            duration_ms: elapsed.as_millis() as u64,
+        }
+    }
+}
+
+/// A command the agent ran in a tmux pane, as listed by `tmux_list_commands`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TmuxCommandInfo {
+    pub command_id: String,
+    pub terminal_id: String,
     pub pane_id: String,
+    pub title: String,
+    pub command: String,
+    pub cwd: Option<String>,
+    pub running: bool,
+    /// The agent released the terminal, so its pane is gone.
+    pub released: bool,
+    pub exit_code: Option<u32>,
+    pub started_at_ms: u64,
+    /// Run time so far, or until the command exited.
+    pub duration_ms: u64,
+}
+
+/// What [`TmuxRuntimeState::register_command`] records about a new command.
+pub struct CommandRegistration {
     pub output_byte_limit: Option<u64>,
+    /// Command and arguments as requested by the agent.
+    pub label: String,
+    pub title: String,
+    pub cwd: Option<PathBuf>,
 }
 
 #[derive(Debug, Default)]
 pub struct TmuxRuntimeState {
     terminals: HashMap<String, TerminalTmuxConfig>,
     commands: HashMap<String, ManagedTmuxCommand>,
+    /// Commands the agent released, newest last.
+    released: VecDeque<(String, ManagedTmuxCommand)>,
+    /// Per session: commands holding a slot while their pane is being set up.
+    starting: HashMap<String, usize>,
+    /// Per session: commands waiting for a slot.
+    queued: HashMap<String, usize>,
+    /// Woken whenever a command slot may have become free.
+    slot_freed: Arc<Notify>,
     next_command_id: u64,
 }
 
@@ -73,6 +218,14 @@ pub struct StartNvimResult {
     pub message: String,
 }
 
+/// A pane and the pid of the process tmux started in it.
+#[derive(Debug, Clone)]
+pub struct TmuxPaneProcess {
+    pub session_name: String,
+    pub pane_id: String,
+    pub pid: u32,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct TmuxPaneState {
     pub dead: bool,
@@ -84,6 +237,10 @@ impl TmuxRuntimeState {
         Self {
             terminals: HashMap::new(),
             commands: HashMap::new(),
+            released: VecDeque::new(),
+            starting: HashMap::new(),
+            queued: HashMap::new(),
+            slot_freed: Arc::new(Notify::new()),
             next_command_id: 1,
         }
     }
@@ -145,11 +302,123 @@ impl TmuxRuntimeState {
             .collect()
     }
 
+    /// Panes of commands that are still running, with their labels.
+    pub fn running_command_panes(&self) -> Vec<(String, String)> {
+        self.commands
+            .values()
+            .filter(|command| !command.exited())
+            .map(|command| (command.pane_id.clone(), command.label.clone()))
+            .collect()
+    }
+
+    /// Ids and panes of `terminal_id`'s commands not yet seen exiting.
+    pub fn running_commands_for_terminal(&self, terminal_id: &str) -> Vec<(String, String)> {
+        self.commands
+            .iter()
+            .filter(|(_, command)| command.host_terminal_id == terminal_id && !command.exited())
+            .map(|(command_id, command)| (command_id.clone(), command.pane_id.clone()))
+            .collect()
+    }
+
+    /// Commands of `session_id` not yet seen exiting, or of every session
+    /// when `None`.
+    pub fn running_commands_for_session(
+        &self,
+        session_id: Option<&str>,
+    ) -> Vec<(String, ManagedTmuxCommand)> {
+        self.commands
+            .iter()
+            .filter(|(_, command)| {
+                !command.exited()
+                    && (session_id.is_none() || session_id == Some(command.session_id.as_str()))
+            })
+            .map(|(command_id, command)| (command_id.clone(), command.clone()))
+            .collect()
+    }
+
+    /// Live and released commands hosted by `terminal_id`, oldest first.
+    pub fn list_commands(&self, terminal_id: &str) -> Vec<TmuxCommandInfo> {
+        let released = self
+            .released
+            .iter()
+            .map(|(command_id, command)| (command_id, command, true));
+        let live = self
+            .commands
+            .iter()
+            .map(|(command_id, command)| (command_id, command, false));
+        let mut commands: Vec<(Instant, TmuxCommandInfo)> = released
+            .chain(live)
+            .filter(|(_, command, _)| command.host_terminal_id == terminal_id)
+            .map(|(command_id, command, released)| {
+                (command.started_at, command.info(command_id, released))
+            })
+            .collect();
+        commands.sort_by_key(|(started_at, _)| *started_at);
+        commands.into_iter().map(|(_, info)| info).collect()
+    }
+
+    /// Commands of `session_id` running or being started.
+    pub fn active_commands(&self, session_id: &str) -> usize {
+        let running = self
+            .commands
+            .values()
+            .filter(|command| command.session_id == session_id && !command.exited())
+            .count();
+        running + self.starting.get(session_id).copied().unwrap_or(0)
+    }
+
+    /// Takes a command slot for `session_id` if fewer than `limit` commands
+    /// are active (0 = unlimited). Give it back with
+    /// [`Self::end_command_start`] once the command is registered or failed.
+    pub fn try_reserve_command_slot(&mut self, session_id: &str, limit: usize) -> bool {
+        if limit > 0 && self.active_commands(session_id) >= limit {
+            return false;
+        }
+        *self.starting.entry(session_id.to_string()).or_default() += 1;
+        true
+    }
+
+    pub fn end_command_start(&mut self, session_id: &str) {
+        if let Some(count) = self.starting.get_mut(session_id) {
+            *count -= 1;
+            if *count == 0 {
+                self.starting.remove(session_id);
+            }
+        }
+        self.slot_freed.notify_waiters();
+    }
+
+    /// Counts a command as waiting for a slot; returns how many now wait.
+    pub fn enqueue_command(&mut self, session_id: &str) -> usize {
+        let queued = self.queued.entry(session_id.to_string()).or_default();
+        *queued += 1;
+        *queued
+    }
+
+    /// Takes a command off the wait count; returns how many still wait.
+    pub fn dequeue_command(&mut self, session_id: &str) -> usize {
+        let Some(queued) = self.queued.get_mut(session_id) else {
+            return 0;
+        };
+        *queued = queued.saturating_sub(1);
+        let remaining = *queued;
+        if remaining == 0 {
+            self.queued.remove(session_id);
+        }
+        remaining
+    }
+
+    /// Notified when a command exits, is released or fails to start.
+    pub fn command_slot_freed(&self) -> Arc<Notify> {
+        self.slot_freed.clone()
+    }
+
     pub fn register_command(
         &mut self,
         host_terminal_id: &str,
+        session_id: &str,
         pane_id: String,
-        output_byte_limit: Option<u64>,
+        registration: CommandRegistration,
     ) -> String {
         let command_id = format!("tmux-{}", self.next_command_id);
         self.next_command_id += 1;
@@ -158,8 +427,19 @@ impl TmuxRuntimeState {
             command_id.clone(),
             ManagedTmuxCommand {
                 host_terminal_id: host_terminal_id.to_string(),
+                session_id: session_id.to_string(),
                 pane_id,
-                output_byte_limit,
+                output_byte_limit: registration.output_byte_limit,
+                label: registration.label,
+                title: registration.title,
+                started_at: Instant::now(),
+                started_at_ms: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64,
+                ended_at: None,
+                exit_code: None,
+                cwd: registration.cwd,
             },
         );
 
@@ -170,8 +450,56 @@ impl TmuxRuntimeState {
         self.commands.get(command_id).cloned()
     }
 
+    /// Records that a command's pane died. Returns the command only the first
+    /// time, so its exit is reported once however often the agent polls.
+    pub fn mark_exited(
+        &mut self,
+        command_id: &str,
+        exit_code: Option<u32>,
+    ) -> Option<ManagedTmuxCommand> {
+        let command = self.commands.get_mut(command_id)?;
+        if command.exited() {
+            return None;
+        }
+        command.ended_at = Some(Instant::now());
+        command.exit_code = exit_code;
+        let command = command.clone();
+        self.slot_freed.notify_waiters();
+        Some(command)
+    }
+
+    /// Forgets a command's pane; the command stays in the history listed by
+    /// [`Self::list_commands`], ended now if it was still running.
     pub fn remove_command(&mut self, command_id: &str) -> Option<ManagedTmuxCommand> {
-        self.commands.remove(command_id)
+        let command = self.commands.remove(command_id)?;
+        let mut released = command.clone();
+        released.ended_at.get_or_insert_with(Instant::now);
+        if self.released.len() == RELEASED_COMMAND_HISTORY {
+            self.released.pop_front();
+        }
+        self.released.push_back((command_id.to_string(), released));
+        self.slot_freed.notify_waiters();
+        Some(command)
+    }
+
+    /// Forgets all managed commands and returns their panes, plus the Neovim
+    /// sessions assigned to terminals unless `keep_sessions` is set.
+    pub fn drain_for_shutdown(&mut self, keep_sessions: bool) -> (Vec<String>, Vec<String>) {
+        self.released.clear();
+        let pane_ids = self
+            .commands
+            .drain()
+            .map(|(_, command)| command.pane_id)
+            .collect();
+        let sessions = if keep_sessions {
+            Vec::new()
+        } else {
+            self.terminals
+                .drain()
+                .filter_map(|(_, config)| config.session_name)
+                .collect()
+        };
+        (pane_ids, sessions)
     }
 
     pub fn remove_terminal(&mut self, terminal_id: &str) -> (Option<String>, Vec<String>) {
@@ -180,6 +508,8 @@ impl TmuxRuntimeState {
             .remove(terminal_id)
             .and_then(|config| config.session_name);
 
+        self.released
+            .retain(|(_, command)| command.host_terminal_id != terminal_id);
         let mut pane_ids = Vec::new();
         self.commands.retain(|_, command| {
             if command.host_terminal_id == terminal_id {
@@ -190,6 +520,7 @@ impl TmuxRuntimeState {
             }
         });
 
+        self.slot_freed.notify_waiters();
         (session, pane_ids)
     }
 }
@@ -215,7 +546,9 @@ pub async fn detect_tmux_available() -> Result<(), String> {
 }
 
 pub async fn ensure_session_exists(session_name: &str, cwd: Option<&Path>) -> Result<(), String> {
+    tmux_control::connect().await;
     if tmux_has_session(session_name).await? {
+        tmux_control::watch_session(session_name).await;
         return Ok(());
     }
 
@@ -230,6 +563,7 @@ pub async fn ensure_session_exists(session_name: &str, cwd: Option<&Path>) -> Re
         args.push(cwd.to_string_lossy().to_string());
     }
     run_tmux_checked(args).await?;
+    tmux_control::watch_session(session_name).await;
     Ok(())
 }
 
@@ -238,6 +572,7 @@ pub async fn prepare_nvim_window(
     socket_path: &str,
     cwd: Option<&Path>,
 ) -> Result<(), String> {
+    tmux_control::connect().await;
     let command = format!("nvim --listen {}", shell_quote(socket_path));
     if !tmux_has_session(session_name).await? {
         let mut args = vec![
@@ -297,24 +632,122 @@ pub async fn prepare_nvim_window(
 
     // Keep startup deterministic: only Neovim window exists until ACP opens command panes/splits.
     prune_non_nvim_windows(session_name).await?;
+    tmux_control::watch_session(session_name).await;
 
     Ok(())
 }
 
+/// A window a project template opens next to Neovim, e.g. a test watcher.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TmuxWindowSpec {
+    pub name: String,
+    /// Typed into the window's shell; empty leaves just the shell.
+    #[serde(default)]
+    pub command: String,
+}
+
+/// Opens `windows` in the background after the Neovim window, in order. The
+/// commands are typed into each window's shell, so the window outlives them.
+pub async fn open_layout_windows(
+    session_name: &str,
+    cwd: Option<&Path>,
+    windows: &[TmuxWindowSpec],
+) -> Result<(), String> {
+    for window in windows {
+        let mut args = vec![
+            "new-window".to_string(),
+            "-d".to_string(),
+            "-P".to_string(),
+            "-F".to_string(),
+            "#{window_id}".to_string(),
+            "-t".to_string(),
+            session_name.to_string(),
+            "-n".to_string(),
+            window.name.clone(),
+        ];
+        if let Some(cwd) = cwd {
+            args.push("-c".to_string());
+            args.push(cwd.to_string_lossy().to_string());
+        }
+        let window_id = run_tmux_checked(args).await?.trim().to_string();
+        if window.command.trim().is_empty() {
+            continue;
+        }
+        run_tmux_checked(vec![
+            "send-keys".to_string(),
+            "-t".to_string(),
+            window_id,
+            window.command.clone(),
+            "Enter".to_string(),
+        ])
+        .await?;
+    }
+    Ok(())
+}
+
+/// Readable window/pane name for a command: the program's file name and its
+/// first argument that isn't a flag, e.g. `cargo test` for
+/// `/usr/bin/cargo test --workspace`. `sh -c` scripts are named after the
+/// script's first words.
+pub fn command_title(command: &str, args: &[String]) -> String {
+    let program = Path::new(command)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(command);
+    let is_shell = matches!(program, "sh" | "bash" | "zsh" | "fish" | "dash");
+    let title = match args {
+        [flag, script, ..] if is_shell && flag == "-c" => script
+            .split_whitespace()
+            .take(2)
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => std::iter::once(program)
+            .chain(
+                args.iter()
+                    .map(String::as_str)
+                    .find(|arg| !arg.starts_with('-')),
+            )
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    let title: String = title
+        .chars()
+        .filter(|ch| !ch.is_control())
+        .take(MAX_COMMAND_TITLE_CHARS)
+        .collect();
+    if title.trim().is_empty() {
+        "neoai-cmd".to_string()
+    } else {
+        title
+    }
+}
+
+/// Opens a pane for the command and types it in, quoted for the shell that
+/// will read it. `title` names the window, or the pane when it is split next
+/// to Neovim.
 pub async fn create_command_pane(
     session_name: &str,
     mode: TmuxCommandMode,
-    command: &str,
-    args: &[String],
-    env: &[acp::EnvVariable],
+    title: &str,
+    command: &PaneCommand<'_>,
     cwd: Option<&Path>,
 ) -> Result<String, String> {
-    let pane_id = create_pane_target(session_name, mode, cwd).await?;
+    let pane_id = create_pane_target(session_name, mode, title, cwd).await?;
     let pane_id = pane_id.trim().to_string();
     if pane_id.is_empty() {
         return Err("tmux did not return a pane id".to_string());
     }
 
+    run_tmux_checked(vec![
+        "select-pane".to_string(),
+        "-t".to_string(),
+        pane_id.clone(),
+        "-T".to_string(),
+        title.to_string(),
+    ])
+    .await?;
+
     run_tmux_checked(vec![
         "set-option".to_string(),
         "-t".to_string(),
@@ -324,34 +757,72 @@ pub async fn create_command_pane(
     ])
     .await?;
 
-    let shell_command = build_shell_command(command, args, env);
+    let mut shell = pane_shell(&pane_id).await;
+    if let Some(forced) = command.shell {
+        send_line(&pane_id, exec_shell_command(shell, forced)).await?;
+        shell = ShellKind::from_program(forced);
+    }
+    let shell_command = build_shell_command(
+        shell,
+        command.command,
+        command.args,
+        command.env,
+        command.env_rules,
+    );
+    send_line(&pane_id, shell_command).await?;
+
+    Ok(pane_id)
+}
+
+/// Kind of the shell running in a fresh pane, POSIX when tmux can't tell.
+async fn pane_shell(pane_id: &str) -> ShellKind {
+    let current = run_tmux_checked(vec![
+        "display-message".to_string(),
+        "-p".to_string(),
+        "-t".to_string(),
+        pane_id.to_string(),
+        "#{pane_current_command}".to_string(),
+    ])
+    .await;
+    match current {
+        Ok(program) => ShellKind::from_program(&program),
+        Err(err) => {
+            log::debug!("Could not detect the shell of pane {}: {}", pane_id, err);
+            ShellKind::Posix
+        }
+    }
+}
+
+async fn send_line(pane_id: &str, line: String) -> Result<(), String> {
     run_tmux_checked(vec![
         "send-keys".to_string(),
         "-t".to_string(),
-        pane_id.clone(),
+        pane_id.to_string(),
         "-l".to_string(),
-        shell_command,
+        line,
     ])
     .await?;
     run_tmux_checked(vec![
         "send-keys".to_string(),
         "-t".to_string(),
-        pane_id.clone(),
+        pane_id.to_string(),
         "Enter".to_string(),
     ])
     .await?;
-
-    Ok(pane_id)
+    Ok(())
 }
 
 async fn create_pane_target(
     session_name: &str,
     mode: TmuxCommandMode,
+    title: &str,
     cwd: Option<&Path>,
 ) -> Result<String, String> {
     match mode {
-        TmuxCommandMode::Window => new_window_pane(session_name, "neoai-cmd", cwd).await,
-        TmuxCommandMode::Hidden => new_window_pane(session_name, "neoai-cmd-bg", cwd).await,
+        TmuxCommandMode::Window => new_window_pane(session_name, title, cwd).await,
+        TmuxCommandMode::Hidden => {
+            new_window_pane(session_name, &format!("{title} (bg)"), cwd).await
+        }
         TmuxCommandMode::Split => split_window_pane(session_name, cwd).await,
     }
 }
@@ -429,6 +900,21 @@ pub async fn pane_output(pane_id: &str) -> Result<String, String> {
     .await
 }
 
+/// Everything the pane has printed that tmux still holds, scrollback
+/// included, unlike [`pane_output`] which only sees the screen.
+pub async fn pane_history(pane_id: &str) -> Result<String, String> {
+    run_tmux_checked(vec![
+        "capture-pane".to_string(),
+        "-p".to_string(),
+        "-J".to_string(),
+        "-S".to_string(),
+        "-".to_string(),
+        "-t".to_string(),
+        pane_id.to_string(),
+    ])
+    .await
+}
+
 pub async fn pane_state(pane_id: &str) -> Result<TmuxPaneState, String> {
     let status = run_tmux_checked(vec![
         "display-message".to_string(),
@@ -442,6 +928,28 @@ pub async fn pane_state(pane_id: &str) -> Result<TmuxPaneState, String> {
     Ok(TmuxPaneState { dead, exit_code })
 }
 
+/// Every pane on the tmux server with its process id.
+pub async fn list_pane_processes() -> Result<Vec<TmuxPaneProcess>, String> {
+    let output = run_tmux_checked(vec![
+        "list-panes".to_string(),
+        "-a".to_string(),
+        "-F".to_string(),
+        "#{session_name}\t#{pane_id}\t#{pane_pid}".to_string(),
+    ])
+    .await?;
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            Some(TmuxPaneProcess {
+                session_name: fields.next()?.to_string(),
+                pane_id: fields.next()?.to_string(),
+                pid: fields.next()?.trim().parse().ok()?,
+            })
+        })
+        .collect())
+}
+
 pub async fn interrupt_pane(pane_id: &str) -> Result<(), String> {
     run_tmux_checked(vec![
         "send-keys".to_string(),
@@ -487,6 +995,36 @@ pub fn truncate_output(output: String, output_byte_limit: Option<u64>) -> (Strin
     (output[start..].to_string(), true)
 }
 
+/// File in `dir` holding the full output of a command, named so files sort
+/// by start time.
+pub fn command_log_path(dir: &Path, command_id: &str, started_at_ms: u64) -> PathBuf {
+    dir.join(format!("{started_at_ms}-{command_id}.log"))
+}
+
+/// Writes a command's full output to `path`, replacing what an earlier
+/// capture saved, and deletes the oldest logs beyond [`MAX_COMMAND_LOGS`].
+pub fn spill_output(path: &Path, output: &str) -> Result<(), String> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| format!("'{}' has no parent directory", path.display()))?;
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    std::fs::write(path, output).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+
+    let mut logs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {e}", dir.display()))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .collect();
+    if logs.len() > MAX_COMMAND_LOGS {
+        logs.sort();
+        for old in &logs[..logs.len() - MAX_COMMAND_LOGS] {
+            let _ = std::fs::remove_file(old);
+        }
+    }
+    Ok(())
+}
+
 fn parse_pane_state(raw: &str) -> (bool, Option<u32>) {
     let value = raw.trim();
     let mut parts = value.splitn(2, ':');
@@ -560,18 +1098,56 @@ fn sanitize_identifier(input: &str) -> String {
         .to_string()
 }
 
-fn build_shell_command(command: &str, args: &[String], env: &[acp::EnvVariable]) -> String {
-    let mut parts = Vec::new();
-    for var in env {
-        if valid_env_name(&var.name) {
-            parts.push(format!("{}={}", var.name, shell_quote(&var.value)));
-        }
+/// Replaces the pane's shell with `shell`. PowerShell has no `exec`, so
+/// there the new shell runs as a child.
+fn exec_shell_command(current: ShellKind, shell: &str) -> String {
+    match current {
+        ShellKind::Posix | ShellKind::Fish => format!("exec {}", current.quote(shell)),
+        ShellKind::PowerShell => format!("& {}", current.quote(shell)),
     }
-    parts.push(shell_quote(command));
-    for arg in args {
-        parts.push(shell_quote(arg));
+}
+
+/// Command line for `shell`: POSIX shells get `NAME=value cmd`, fish goes
+/// through `env`, and PowerShell sets `$env:NAME` before calling the command
+/// with `&`.
+fn build_shell_command(
+    shell: ShellKind,
+    command: &str,
+    args: &[String],
+    env: &[acp::EnvVariable],
+    rules: &EnvRules,
+) -> String {
+    let env = env
+        .iter()
+        .filter(|var| valid_env_name(&var.name) && rules.permits(&var.name));
+    let assignments: Vec<String> = env
+        .map(|var| match shell {
+            ShellKind::PowerShell => format!("$env:{}={}", var.name, shell.quote(&var.value)),
+            ShellKind::Posix | ShellKind::Fish => {
+                format!("{}={}", var.name, shell.quote(&var.value))
+            }
+        })
+        .collect();
+    let invocation = std::iter::once(command)
+        .chain(args.iter().map(String::as_str))
+        .map(|part| shell.quote(part))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    match shell {
+        ShellKind::Posix => assignments
+            .into_iter()
+            .chain(std::iter::once(invocation))
+            .collect::<Vec<_>>()
+            .join(" "),
+        ShellKind::Fish if assignments.is_empty() => invocation,
+        ShellKind::Fish => format!("env {} {}", assignments.join(" "), invocation),
+        ShellKind::PowerShell => assignments
+            .into_iter()
+            .chain(std::iter::once(format!("& {invocation}")))
+            .collect::<Vec<_>>()
+            .join("; "),
     }
-    parts.join(" ")
 }
 
 fn valid_env_name(name: &str) -> bool {
@@ -583,7 +1159,16 @@ fn valid_env_name(name: &str) -> bool {
     chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
 }
 
-fn shell_quote(value: &str) -> String {
+/// Names in `env` that [`build_shell_command`] leaves out: malformed ones and
+/// those `rules` don't permit.
+pub fn rejected_env_names(env: &[acp::EnvVariable], rules: &EnvRules) -> Vec<String> {
+    env.iter()
+        .filter(|var| !valid_env_name(&var.name) || !rules.permits(&var.name))
+        .map(|var| var.name.clone())
+        .collect()
+}
+
+pub(crate) fn shell_quote(value: &str) -> String {
     if value.is_empty() {
         "''".to_string()
     } else {
@@ -591,7 +1176,20 @@ fn shell_quote(value: &str) -> String {
     }
 }
 
-async fn tmux_has_session(session_name: &str) -> Result<bool, String> {
+pub async fn tmux_has_session(session_name: &str) -> Result<bool, String> {
+    // Over control mode a missing session is just an error reply, so check
+    // the session list instead.
+    let list = [
+        "list-sessions".to_string(),
+        "-F".to_string(),
+        "#{session_name}".to_string(),
+    ];
+    if let Some(sessions) = tmux_control::run(&list).await {
+        return sessions
+            .map(|sessions| sessions.lines().any(|name| name == session_name))
+            .map_err(|err| format!("tmux list-sessions failed: {err}"));
+    }
+
     let output = Command::new("tmux")
         .args(["has-session", "-t", session_name])
         .output()
@@ -610,6 +1208,10 @@ async fn tmux_has_session(session_name: &str) -> Result<bool, String> {
 }
 
 async fn run_tmux_checked(args: Vec<String>) -> Result<String, String> {
+    if let Some(result) = tmux_control::run(&args).await {
+        return result.map_err(|err| format!("tmux {} failed: {err}", args.join(" ")));
+    }
+
     let output = Command::new("tmux")
         .args(&args)
         .output()
@@ -668,3 +1270,142 @@ async fn prune_non_nvim_windows(session_name: &str) -> Result<(), String> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_var(name: &str, value: &str) -> acp::EnvVariable {
+        serde_json::from_value(serde_json::json!({ "name": name, "value": value })).unwrap()
+    }
+
+    #[test]
+    fn lists_running_commands_per_session() {
+        let mut state = TmuxRuntimeState::new();
+        let mut register = |session_id: &str, pane_id: &str| {
+            state.register_command(
+                "terminal-a",
+                session_id,
+                pane_id.to_string(),
+                CommandRegistration {
+                    output_byte_limit: None,
+                    label: "cargo test".to_string(),
+                    title: "cargo test".to_string(),
+                    cwd: None,
+                },
+            )
+        };
+        let first = register("s1", "%1");
+        let second = register("s1", "%2");
+        register("s2", "%3");
+        state.mark_exited(&second, Some(0));
+
+        let running = state.running_commands_for_session(Some("s1"));
+        assert_eq!(running.len(), 1);
+        assert_eq!(running[0].0, first);
+        assert_eq!(running[0].1.pane_id, "%1");
+        assert_eq!(state.running_commands_for_session(None).len(), 2);
+        assert!(state.running_commands_for_session(Some("s3")).is_empty());
+    }
+
+    #[test]
+    fn classifies_shell_programs() {
+        assert_eq!(ShellKind::from_program("/usr/bin/fish"), ShellKind::Fish);
+        assert_eq!(ShellKind::from_program("-fish"), ShellKind::Fish);
+        assert_eq!(ShellKind::from_program("pwsh"), ShellKind::PowerShell);
+        assert_eq!(
+            ShellKind::from_program("C:\\Windows\\powershell.exe"),
+            ShellKind::PowerShell
+        );
+        assert_eq!(ShellKind::from_program("/bin/zsh"), ShellKind::Posix);
+        assert_eq!(ShellKind::from_program(""), ShellKind::Posix);
+    }
+
+    #[test]
+    fn builds_posix_command_with_env_prefix() {
+        let line = build_shell_command(
+            ShellKind::Posix,
+            "echo",
+            &["it's".to_string()],
+            &[env_var("FOO", "a b"), env_var("bad name", "x")],
+            &EnvRules::default(),
+        );
+        assert_eq!(line, "FOO='a b' 'echo' 'it'\"'\"'s'");
+    }
+
+    #[test]
+    fn builds_fish_command_through_env() {
+        let line = build_shell_command(
+            ShellKind::Fish,
+            "echo",
+            &["it's".to_string(), "C:\\dir".to_string()],
+            &[env_var("FOO", "bar")],
+            &EnvRules::default(),
+        );
+        assert_eq!(line, "env FOO='bar' 'echo' 'it\\'s' 'C:\\\\dir'");
+
+        let line = build_shell_command(ShellKind::Fish, "ls", &[], &[], &EnvRules::default());
+        assert_eq!(line, "'ls'");
+    }
+
+    #[test]
+    fn builds_powershell_command_with_env_statements() {
+        let line = build_shell_command(
+            ShellKind::PowerShell,
+            "cargo",
+            &["it's".to_string(), String::new()],
+            &[env_var("FOO", "a'b"), env_var("BAR", "1")],
+            &EnvRules::default(),
+        );
+        assert_eq!(line, "$env:FOO='a''b'; $env:BAR='1'; & 'cargo' 'it''s' ''");
+    }
+
+    #[test]
+    fn leaves_out_denied_env_vars() {
+        let rules = EnvRules {
+            allow: Vec::new(),
+            deny: vec!["PATH".to_string(), "LD_*".to_string()],
+        };
+        let env = [
+            env_var("PATH", "/tmp/evil"),
+            env_var("LD_PRELOAD", "/tmp/evil.so"),
+            env_var("RUST_LOG", "debug"),
+            env_var("1BAD", "x"),
+        ];
+        let line = build_shell_command(ShellKind::Posix, "make", &[], &env, &rules);
+        assert_eq!(line, "RUST_LOG='debug' 'make'");
+        assert_eq!(
+            rejected_env_names(&env, &rules),
+            vec!["PATH", "LD_PRELOAD", "1BAD"]
+        );
+    }
+
+    #[test]
+    fn spills_output_and_keeps_the_newest_logs() {
+        let dir = std::env::temp_dir().join(format!("neoai-spill-test-{}", std::process::id()));
+        let first = command_log_path(&dir, "tmux-1", 1_000);
+        spill_output(&first, "old").unwrap();
+        spill_output(&first, "full output").unwrap();
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "full output");
+
+        for ms in 1..=MAX_COMMAND_LOGS as u64 {
+            spill_output(&command_log_path(&dir, "tmux-2", 1_000 + ms), "x").unwrap();
+        }
+        assert!(!first.exists());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), MAX_COMMAND_LOGS);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn execs_configured_shell() {
+        assert_eq!(
+            exec_shell_command(ShellKind::Posix, "/usr/bin/fish"),
+            "exec '/usr/bin/fish'"
+        );
+        assert_eq!(
+            exec_shell_command(ShellKind::PowerShell, "pwsh"),
+            "& 'pwsh'"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::cmp::Ordering;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+
+use crate::app_config::AppConfigState;
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/fcoury/neoai/releases/latest";
+/// Delay before the launch check, so it doesn't compete with startup work.
+const STARTUP_CHECK_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub current_version: String,
+    pub latest_version: String,
+    pub update_available: bool,
+    pub release_notes: Option<String>,
+    pub release_url: String,
+    pub published_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    html_url: String,
+    body: Option<String>,
+    published_at: Option<String>,
+}
+
+/// Numeric parts of a version like `v0.2.1` or `0.3.0-beta.1`; the pre-release
+/// suffix is returned separately.
+fn parse_version(version: &str) -> Option<(Vec<u64>, Option<&str>)> {
+    let version = version.trim().trim_start_matches('v');
+    let (core, pre) = match version.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (version, None),
+    };
+    let parts = core
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    (!parts.is_empty()).then_some((parts, pre))
+}
+
+/// Compares two release versions; a pre-release sorts before its release.
+/// `None` when either isn't a version.
+fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    let (a_parts, a_pre) = parse_version(a)?;
+    let (b_parts, b_pre) = parse_version(b)?;
+    let len = a_parts.len().max(b_parts.len());
+    let part = |parts: &[u64], i: usize| parts.get(i).copied().unwrap_or(0);
+    let ordering = (0..len)
+        .map(|i| part(&a_parts, i).cmp(&part(&b_parts, i)))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal);
+    Some(ordering.then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => a.cmp(b),
+    }))
+}
+
+async fn fetch_latest_release() -> Result<GithubRelease, String> {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("neoai/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(15))
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {e}"))?;
+    let response = client
+        .get(LATEST_RELEASE_URL)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .map_err(|e| format!("Update check failed: {e}"))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Update check failed with HTTP status {status}"));
+    }
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read release info: {e}"))?;
+    serde_json::from_str(&body).map_err(|e| format!("Unexpected release info: {e}"))
+}
+
+fn update_info(current_version: &str, release: GithubRelease) -> UpdateInfo {
+    let latest_version = release.tag_name.trim_start_matches('v').to_string();
+    UpdateInfo {
+        update_available: compare_versions(&latest_version, current_version)
+            == Some(Ordering::Greater),
+        current_version: current_version.to_string(),
+        latest_version,
+        release_notes: release.body.filter(|body| !body.trim().is_empty()),
+        release_url: release.html_url,
+        published_at: release.published_at,
+    }
+}
+
+/// Checks once after launch when `check_for_updates` is enabled, emitting
+/// `update-available` if a newer release exists.
+pub fn schedule_startup_check(app_handle: tauri::AppHandle) {
+    let enabled = app_handle
+        .try_state::<std::sync::Mutex<AppConfigState>>()
+        .and_then(|state| state.lock().ok().map(|state| state.check_for_updates()))
+        .unwrap_or(false);
+    if !enabled {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(STARTUP_CHECK_DELAY).await;
+        if let Err(err) = check_for_updates(app_handle).await {
+            log::info!("{}", err);
+        }
+    });
+}
+
+// -- Tauri IPC commands --
+
+/// Queries the latest GitHub release and emits `update-available` when it is
+/// newer than the running build. Works regardless of `check_for_updates`,
+/// which only controls the automatic check at launch.
+#[tauri::command]
+pub async fn check_for_updates(app_handle: tauri::AppHandle) -> Result<UpdateInfo, String> {
+    let current_version = app_handle.package_info().version.to_string();
+    let info = update_info(&current_version, fetch_latest_release().await?);
+    if info.update_available {
+        log::info!(
+            "NeoAI {} is available (running {})",
+            info.latest_version,
+            info.current_version
+        );
+        let _ = app_handle.emit("update-available", &info);
+    }
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_release_versions() {
+        assert_eq!(compare_versions("v0.2.0", "0.1.9"), Some(Ordering::Greater));
+        assert_eq!(compare_versions("0.1.0", "0.1"), Some(Ordering::Equal));
+        assert_eq!(compare_versions("0.10.0", "0.9.3"), Some(Ordering::Greater));
+        assert_eq!(
+            compare_versions("0.2.0-beta.1", "0.2.0"),
+            Some(Ordering::Less)
+        );
+        assert_eq!(compare_versions("nightly", "0.1.0"), None);
+
+        let release = GithubRelease {
+            tag_name: "v0.2.0".to_string(),
+            html_url: "https://example.com/release".to_string(),
+            body: Some("  ".to_string()),
+            published_at: None,
+        };
+        let info = update_info("0.1.0", release);
+        assert!(info.update_available);
+        assert_eq!(info.latest_version, "0.2.0");
+        assert_eq!(info.release_notes, None);
+    }
+}
@@ -0,0 +1,259 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::Manager;
+use tokio::sync::Mutex;
+
+use crate::acp_client;
+use crate::folder_state::{self, FolderSession, FolderStateStore};
+use crate::ghostty_embed::{GhosttyOptions, GhosttyRect};
+use crate::nvim_bridge::{self, NvimBridgeState};
+use crate::socket_manager::SocketManager;
+use crate::tmux_runtime::{self, TmuxRuntimeState};
+
+const NVIM_CONNECT_POLL: Duration = Duration::from_millis(250);
+const NVIM_CONNECT_TIMEOUT: Duration = Duration::from_secs(8);
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoredTerminal {
+    pub terminal_id: String,
+    /// `false` when the terminal view already existed.
+    pub created: bool,
+    pub socket_path: Option<String>,
+    /// `connected` when the bridge was already up, `reattached` when Neovim
+    /// was still running, otherwise the launch mode from `nvim_start_in_tmux`.
+    pub launch_mode: Option<String>,
+    pub tmux_session_name: Option<String>,
+    pub nvim_connected: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceRestore {
+    pub folder_id: String,
+    pub terminals: Vec<RestoredTerminal>,
+    pub acp_session_id: Option<String>,
+    /// Whether the agent reopened the folder's previous session rather than
+    /// starting a new one.
+    pub session_resumed: bool,
+    pub agent_error: Option<String>,
+}
+
+/// Terminals to bring back: the ones open last time, or the folder's main
+/// terminal when none were recorded.
+fn terminals_to_restore(folder_id: &str, session: &FolderSession) -> Vec<String> {
+    if session.terminals.is_empty() {
+        vec![format!("terminal-{folder_id}")]
+    } else {
+        session.terminals.clone()
+    }
+}
+
+async fn connect_nvim(
+    window: &tauri::Window,
+    terminal_id: &str,
+    socket_path: &str,
+) -> Result<(), String> {
+    let deadline = Instant::now() + NVIM_CONNECT_TIMEOUT;
+    loop {
+        let result = nvim_bridge::nvim_connect(
+            window.app_handle().clone(),
+            window.state::<Mutex<NvimBridgeState>>(),
+            terminal_id.to_string(),
+            socket_path.to_string(),
+        )
+        .await;
+        match result {
+            Ok(()) => return Ok(()),
+            Err(err) if Instant::now() >= deadline => return Err(err.into()),
+            Err(_) => tokio::time::sleep(NVIM_CONNECT_POLL).await,
+        }
+    }
+}
+
+async fn restore_terminal(
+    window: &tauri::Window,
+    terminal_id: &str,
+    session: &FolderSession,
+    cwd: &Path,
+    rect: Option<GhosttyRect>,
+    options: Option<GhosttyOptions>,
+    restored: &mut RestoredTerminal,
+) -> Result<(), String> {
+    if !crate::ghostty_exists(window, terminal_id)? {
+        let rect = rect.ok_or_else(|| {
+            format!("Terminal {terminal_id} is not open and no rect was given to create it")
+        })?;
+        let options = GhosttyOptions {
+            working_directory: Some(cwd.to_string_lossy().into_owned()),
+            ..options.unwrap_or_default()
+        };
+        crate::ghostty_create(window.clone(), terminal_id.to_string(), rect, Some(options))?;
+        restored.created = true;
+    }
+
+    if nvim_bridge::is_connected(&window.state::<Mutex<NvimBridgeState>>(), terminal_id).await {
+        restored.nvim_connected = true;
+        restored.launch_mode = Some("connected".to_string());
+        return Ok(());
+    }
+
+    let allocation = window
+        .state::<std::sync::Mutex<SocketManager>>()
+        .lock()
+        .map_err(|e| e.to_string())?
+        .allocate(terminal_id)?;
+    restored.socket_path = Some(allocation.path.clone());
+
+    if allocation.reused {
+        restored.launch_mode = Some("reattached".to_string());
+        // A fresh terminal shows a shell; put it back on the tmux session that
+        // still hosts this Neovim.
+        if let Some(session_name) = session.tmux_session_name.clone() {
+            if restored.created && tmux_runtime::tmux_has_session(&session_name).await? {
+                window
+                    .state::<Mutex<TmuxRuntimeState>>()
+                    .lock()
+                    .await
+                    .set_session_name(terminal_id, session_name.clone());
+                crate::ghostty_write_text(
+                    window.clone(),
+                    terminal_id.to_string(),
+                    format!("tmux new-session -A -s {session_name}\n"),
+                )?;
+                restored.tmux_session_name = Some(session_name);
+            }
+        }
+    } else {
+        let launch = crate::nvim_start_in_tmux(
+            window.clone(),
+            window.state::<Mutex<TmuxRuntimeState>>(),
+            terminal_id.to_string(),
+            allocation.path.clone(),
+            Some(cwd.to_string_lossy().into_owned()),
+            Some(true),
+        )
+        .await?;
+        restored.launch_mode = Some(launch.launch_mode);
+        restored.tmux_session_name = launch.session_name;
+    }
+
+    connect_nvim(window, terminal_id, &allocation.path).await?;
+    restored.nvim_connected = true;
+    Ok(())
+}
+
+// -- Tauri IPC commands --
+
+/// Brings a folder back to where it was: recreates its terminals (when
+/// `rect` is given), reattaches tmux or starts Neovim, connects the bridge,
+/// and resumes the folder's ACP session. Each step's failure is reported in
+/// the result instead of aborting the rest.
+#[tauri::command]
+pub async fn restore_workspace(
+    window: tauri::Window,
+    folder_id: String,
+    rect: Option<GhosttyRect>,
+    options: Option<GhosttyOptions>,
+) -> Result<WorkspaceRestore, String> {
+    let cwd = folder_state::resolve_folder_path(window.app_handle(), &folder_id)?;
+    let session = window
+        .state::<std::sync::Mutex<FolderStateStore>>()
+        .lock()
+        .map_err(|e| e.to_string())?
+        .session(&folder_id);
+
+    let terminal_ids = terminals_to_restore(&folder_id, &session);
+    // Only the folder's first terminal can take over the socket of a Neovim
+    // that outlived the last run.
+    if let Some(path) = session.nvim_socket_path.as_deref() {
+        let sockets = window.state::<std::sync::Mutex<SocketManager>>();
+        let mut sockets = sockets.lock().map_err(|e| e.to_string())?;
+        if sockets.adopt(&terminal_ids[0], PathBuf::from(path)) {
+            log::info!(
+                "Adopted live Neovim socket {} for {}",
+                path,
+                terminal_ids[0]
+            );
+        }
+    }
+    let mut terminals = Vec::with_capacity(terminal_ids.len());
+    for terminal_id in &terminal_ids {
+        let mut restored = RestoredTerminal {
+            terminal_id: terminal_id.clone(),
+            ..Default::default()
+        };
+        if let Err(err) = restore_terminal(
+            &window,
+            terminal_id,
+            &session,
+            &cwd,
+            rect,
+            options.clone(),
+            &mut restored,
+        )
+        .await
+        {
+            log::warn!("Failed to restore terminal {}: {}", terminal_id, err);
+            restored.error = Some(err);
+        }
+        terminals.push(restored);
+    }
+
+    let mut result = WorkspaceRestore {
+        folder_id: folder_id.clone(),
+        terminals,
+        acp_session_id: None,
+        session_resumed: false,
+        agent_error: None,
+    };
+    match acp_client::resume_session(
+        window.app_handle(),
+        cwd,
+        &terminal_ids[0],
+        session.acp_session_id.clone(),
+    )
+    .await
+    {
+        Ok((session_id, resumed)) => {
+            result.acp_session_id = Some(session_id);
+            result.session_resumed = resumed;
+        }
+        Err(err) => {
+            log::warn!("Failed to resume agent session for {}: {}", folder_id, err);
+            result.agent_error = Some(err);
+        }
+    }
+
+    log::info!(
+        "Restored workspace {}: {} terminal(s), session {}",
+        folder_id,
+        result.terminals.len(),
+        if result.session_resumed {
+            "resumed"
+        } else {
+            "new"
+        }
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restores_the_main_terminal_when_none_were_recorded() {
+        let mut session = FolderSession::default();
+        assert_eq!(
+            terminals_to_restore("f1", &session),
+            vec!["terminal-f1".to_string()]
+        );
+
+        session.terminals = vec!["terminal-f1".to_string(), "terminal-f1-2".to_string()];
+        assert_eq!(terminals_to_restore("f1", &session), session.terminals);
+    }
+}
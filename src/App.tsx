@@ -9,6 +9,7 @@ import { useAiChat } from "./hooks/useAiChat";
 import { useTerminalManager } from "./hooks/useTerminalManager";
 import { useLocalStorage } from "./hooks/useLocalStorage";
 import type { NvimActionEvent, NvimBridgeDebugEvent } from "./types/nvim";
+import type { FocusChangedEvent } from "./types/focus";
 import "./App.css";
 
 type SidePanel = "explorer" | "ai";
@@ -22,29 +23,26 @@ function App() {
   const nvim = useNvimBridge(activeTerminalId);
   const aiChat = useAiChat(activeTerminalId, nvim);
 
-  const blurActiveTerminal = useCallback(() => {
-    if (!activeTerminalId) return;
-    invoke("ghostty_focus", { id: activeTerminalId, focused: false }).catch(console.error);
-  }, [activeTerminalId]);
+  const focusChat = useCallback(() => {
+    invoke("focus_chat").catch(console.error);
+  }, []);
 
   const handleAppMouseDownCapture = useCallback((event: React.MouseEvent<HTMLElement>) => {
     const target = event.target as HTMLElement | null;
     if (!target) return;
     if (target.closest(".terminal-panel")) return;
-    blurActiveTerminal();
-  }, [blurActiveTerminal]);
+    focusChat();
+  }, [focusChat]);
 
-  // Track native Ghostty focus via becomeFirstResponder / resignFirstResponder
+  // Track which side owns keyboard input, as reported by the backend's
+  // focus coordinator
   useEffect(() => {
+    const apply = (focus: FocusChangedEvent) => {
+      setTerminalFocused(focus.owner === "terminal" && focus.terminalId === activeTerminalId);
+    };
     setTerminalFocused(false);
-    const unlisten = listen<{ terminalId: string; focused: boolean }>(
-      "ghostty-focus",
-      (event) => {
-        if (event.payload.terminalId === activeTerminalId) {
-          setTerminalFocused(event.payload.focused);
-        }
-      }
-    );
+    invoke<FocusChangedEvent>("focus_state").then(apply).catch(console.error);
+    const unlisten = listen<FocusChangedEvent>("focus-changed", (event) => apply(event.payload));
     return () => {
       unlisten.then((fn) => fn());
     };
@@ -127,6 +125,24 @@ function App() {
     >
       <header className="toolbar">
         <div className="toolbar-title">NeoAI</div>
+        {nvim.breadcrumbs && (
+          <nav className="toolbar-breadcrumbs" aria-label="Cursor location">
+            {[
+              nvim.breadcrumbs.project,
+              nvim.breadcrumbs.relativePath ?? nvim.breadcrumbs.filePath,
+              ...nvim.breadcrumbs.symbols.map((symbol) =>
+                symbol.kind === "function" || symbol.kind === "method"
+                  ? `${symbol.name}()`
+                  : symbol.name
+              ),
+            ]
+              .filter((crumb): crumb is string => !!crumb)
+              .join(" \u25b8 ")}
+            {nvim.breadcrumbs.branch && (
+              <span className="toolbar-breadcrumbs-branch">{nvim.breadcrumbs.branch}</span>
+            )}
+          </nav>
+        )}
         <div className="toolbar-actions">
           <button
             type="button"
@@ -1,10 +1,11 @@
 import { useRef, useEffect, useState, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import type { AiChatController } from "../../hooks/useAiChat";
-import type { NvimStartLaunchResult } from "../../types/nvim";
+import type { NvimStartLaunchResult, SocketAllocation } from "../../types/nvim";
 import { ContextBadge } from "./ContextBadge";
 import { ChatMessage } from "./ChatMessage";
 import { ChatInput } from "./ChatInput";
+import { errorMessage } from "../../types/errors";
 import "./AiChat.css";
 
 const NVIM_CONNECT_POLL_MS = 250;
@@ -24,8 +25,11 @@ export function AiChat({ terminalId, terminalWorkingDirectory, ai }: Props) {
     setAutoApply,
     appendSystemMessage,
     sendMessage,
+    compactConversation,
+    isCompacting,
     applyProposedEdits,
     rejectProposedEdits,
+    rateMessage,
     nvim,
     acp,
     traceEvents,
@@ -68,7 +72,7 @@ export function AiChat({ terminalId, terminalWorkingDirectory, ai }: Props) {
       setShowManualConnect(false);
       appendSystemMessage("Connected to existing Neovim socket.");
     } catch (e) {
-      appendSystemMessage(`Failed to connect Neovim socket: ${String(e)}`, "status-note");
+      appendSystemMessage(`Failed to connect Neovim socket: ${errorMessage(e)}`, "status-note");
     }
   }, [connectInput, nvim, appendSystemMessage]);
 
@@ -92,7 +96,7 @@ export function AiChat({ terminalId, terminalWorkingDirectory, ai }: Props) {
         }
       }
 
-      const reason = lastError ? String(lastError) : "Timed out waiting for Neovim to be ready";
+      const reason = lastError ? errorMessage(lastError) : "Timed out waiting for Neovim to be ready";
       throw new Error(reason);
     },
     [nvim, appendSystemMessage]
@@ -105,9 +109,18 @@ export function AiChat({ terminalId, terminalWorkingDirectory, ai }: Props) {
       startAttemptRef.current = attemptId;
       setIsStartingNvim(true);
       try {
-        const socketPath = await invoke<string>("get_socket_path", {
+        const socket = await invoke<SocketAllocation>("get_socket_path", {
           terminalId,
         });
+        const socketPath = socket.path;
+        if (socket.reused) {
+          await waitForNvimConnection(
+            attemptId,
+            socketPath,
+            "Reconnected to running Neovim."
+          );
+          return;
+        }
         const launch = await invoke<NvimStartLaunchResult>("nvim_start_in_tmux", {
           terminalId,
           socketPath,
@@ -138,7 +151,7 @@ export function AiChat({ terminalId, terminalWorkingDirectory, ai }: Props) {
           return;
         }
         console.error("Failed to start neovim:", e);
-        appendSystemMessage(`Failed to start Neovim: ${String(e)}`, "status-note");
+        appendSystemMessage(`Failed to start Neovim: ${errorMessage(e)}`, "status-note");
       } finally {
         if (startAttemptRef.current === attemptId) {
           setIsStartingNvim(false);
@@ -182,14 +195,18 @@ export function AiChat({ terminalId, terminalWorkingDirectory, ai }: Props) {
     setAgentError(null);
     try {
       let startedAgent = false;
-      if (acp.status !== "Running") {
+      if (acp.status !== "Running" && acp.status !== "Unresponsive") {
         if (acp.status === "Starting") return;
         await acp.startAgent();
         startedAgent = true;
       }
 
-      // Also create a session with the terminal's working directory
-      if (nvim.context?.filePath) {
+      // Also create a session in the project Neovim's LSP and root markers
+      // point at, falling back to the current file's directory.
+      const roots = await nvim.getWorkspaceRoots().catch(() => null);
+      if (roots?.root) {
+        await acp.createSession(roots.root, terminalId);
+      } else if (nvim.context?.filePath) {
         const dir = nvim.context.filePath.replace(/\/[^/]+$/, "") || "/";
         await acp.createSession(dir, terminalId);
       } else {
@@ -206,10 +223,10 @@ export function AiChat({ terminalId, terminalWorkingDirectory, ai }: Props) {
         appendSystemMessage("Auto-started codex-acp agent and created session.");
       }
     } catch (e) {
-      setAgentError(String(e));
-      appendSystemMessage(`Failed to ensure agent session: ${String(e)}`, "status-note");
+      setAgentError(errorMessage(e));
+      appendSystemMessage(`Failed to ensure agent session: ${errorMessage(e)}`, "status-note");
     }
-  }, [acp, nvim.context, terminalId, appendSystemMessage]);
+  }, [acp, nvim.context, nvim.getWorkspaceRoots, terminalId, appendSystemMessage]);
 
   const handleStartAgent = useCallback(async () => {
     await ensureAgentSession("manual");
@@ -219,18 +236,33 @@ export function AiChat({ terminalId, terminalWorkingDirectory, ai }: Props) {
     setKeymapError(null);
     setIsReinjectingKeymaps(true);
     try {
-      await nvim.reinjectKeymaps();
-      appendSystemMessage("Re-injected neoai keymaps.");
+      const bindings = await nvim.reinjectKeymaps();
+      const collisions = bindings
+        .filter((binding) => binding.existing)
+        .map((binding) => {
+          const outcome =
+            binding.status === "relocated"
+              ? `moved to ${binding.boundTo}`
+              : binding.status === "overridden"
+                ? "replaced it"
+                : "left it alone";
+          return `${binding.lhs} (${binding.mode}) was mapped to ${binding.existing}; ${outcome}`;
+        });
+      appendSystemMessage(
+        collisions.length > 0
+          ? `Re-injected neoai keymaps. ${collisions.join(". ")}.`
+          : "Re-injected neoai keymaps.",
+      );
     } catch (e) {
-      setKeymapError(String(e));
-      appendSystemMessage(`Failed to re-inject keymaps: ${String(e)}`, "status-note");
+      setKeymapError(errorMessage(e));
+      appendSystemMessage(`Failed to re-inject keymaps: ${errorMessage(e)}`, "status-note");
     } finally {
       setIsReinjectingKeymaps(false);
     }
   }, [nvim, appendSystemMessage]);
 
   const isConnected = nvim.status === "Connected";
-  const isAgentRunning = acp.status === "Running";
+  const isAgentRunning = acp.status === "Running" || acp.status === "Unresponsive";
   const isAgentStarting = acp.status === "Starting";
   const hasKeymapIssue =
     isConnected && (nvim.keymapStatus === "missing" || nvim.keymapStatus === "error");
@@ -320,8 +352,8 @@ export function AiChat({ terminalId, terminalWorkingDirectory, ai }: Props) {
           appendSystemMessage("Submitted permission response.");
         }
       } catch (e) {
-        setPermissionError(String(e));
-        appendSystemMessage(`Failed to submit permission response: ${String(e)}`, "status-note");
+        setPermissionError(errorMessage(e));
+        appendSystemMessage(`Failed to submit permission response: ${errorMessage(e)}`, "status-note");
       } finally {
         setIsRespondingPermission(false);
       }
@@ -344,6 +376,19 @@ export function AiChat({ terminalId, terminalWorkingDirectory, ai }: Props) {
               <span>Auto-apply</span>
             </label>
           )}
+          {isAgentRunning && messages.some((m) => !m.archived) && (
+            <button
+              type="button"
+              className="ai-chat__connect-link"
+              onClick={() => {
+                void compactConversation();
+              }}
+              disabled={isStreaming || isCompacting}
+              title="Summarize older messages to keep the conversation manageable"
+            >
+              {isCompacting ? "Compacting..." : "Compact"}
+            </button>
+          )}
         </div>
         <ContextBadge
           nvimStatus={nvim.status}
@@ -377,12 +422,13 @@ export function AiChat({ terminalId, terminalWorkingDirectory, ai }: Props) {
       </div>
 
       <div className="ai-chat__messages">
-        {messages.map((msg) => (
+        {messages.filter((msg) => !msg.archived).map((msg) => (
           <ChatMessage
             key={msg.id}
             message={msg}
             onApplyEdits={applyProposedEdits}
             onRejectEdits={rejectProposedEdits}
+            onRate={rateMessage}
           />
         ))}
 
@@ -395,6 +441,29 @@ export function AiChat({ terminalId, terminalWorkingDirectory, ai }: Props) {
                 {permissionRequest.kind ? ` (${permissionRequest.kind})` : ""}
               </p>
             )}
+            {permissionRequest.command && (
+              <p className="ai-chat__permission-command">
+                Run <code>{permissionRequest.command}</code>
+                {permissionRequest.cwd ? <> in <code>{permissionRequest.cwd}</code></> : null}
+              </p>
+            )}
+            {permissionRequest.paths.length > 0 && (
+              <ul className="ai-chat__permission-paths">
+                {permissionRequest.paths.map((path) => (
+                  <li key={path}>
+                    <code>{path}</code>
+                  </li>
+                ))}
+              </ul>
+            )}
+            {permissionRequest.diffs.map((item) => (
+              <details key={item.path} className="ai-chat__permission-diff">
+                <summary>
+                  {item.path} (+{item.diff.additions} -{item.diff.deletions})
+                </summary>
+                <pre>{item.diff.unified}</pre>
+              </details>
+            ))}
             <div className="ai-chat__permission-options">
               {permissionRequest.options.map((option) => (
                 <button
@@ -554,6 +623,7 @@ export function AiChat({ terminalId, terminalWorkingDirectory, ai }: Props) {
       <div className="ai-chat__footer">
         <ChatInput
           onSend={sendMessage}
+          folderId={terminalId?.replace(/^terminal-/, "") ?? null}
           disabled={!isConnected || !isAgentRunning || isStreaming}
           placeholder={
             !isConnected
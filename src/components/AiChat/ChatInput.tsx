@@ -1,28 +1,84 @@
-import { useState, useRef, useCallback, type KeyboardEvent } from "react";
+import { useState, useRef, useCallback, useEffect, type KeyboardEvent } from "react";
+import { invoke } from "@tauri-apps/api/core";
+
+/** Pause in typing after which the draft is saved. */
+const DRAFT_SAVE_DELAY_MS = 500;
 
 type Props = {
   onSend: (content: string) => void;
+  /** Folder whose unsent prompt is saved and restored; the backend drops it once sent. */
+  folderId?: string | null;
   disabled?: boolean;
   placeholder?: string;
 };
 
 export function ChatInput({
   onSend,
+  folderId = null,
   disabled = false,
   placeholder = "Ask about your code...",
 }: Props) {
   const [value, setValue] = useState("");
   const textareaRef = useRef<HTMLTextAreaElement>(null);
+  const draftTimerRef = useRef<number | null>(null);
+  const pendingDraftRef = useRef<{ folderId: string; content: string } | null>(null);
+
+  const cancelDraftSave = useCallback(() => {
+    if (draftTimerRef.current !== null) {
+      window.clearTimeout(draftTimerRef.current);
+      draftTimerRef.current = null;
+    }
+    pendingDraftRef.current = null;
+  }, []);
+
+  const flushDraft = useCallback(() => {
+    const pending = pendingDraftRef.current;
+    cancelDraftSave();
+    if (!pending) return;
+    invoke("folder_draft_save", pending).catch((e) => {
+      console.error("folder_draft_save error:", e);
+    });
+  }, [cancelDraftSave]);
+
+  const scheduleDraftSave = useCallback(
+    (content: string) => {
+      if (!folderId) return;
+      if (draftTimerRef.current !== null) window.clearTimeout(draftTimerRef.current);
+      pendingDraftRef.current = { folderId, content };
+      draftTimerRef.current = window.setTimeout(flushDraft, DRAFT_SAVE_DELAY_MS);
+    },
+    [folderId, flushDraft]
+  );
+
+  // Restore the folder's draft, and save the last keystrokes before leaving it.
+  useEffect(() => {
+    setValue("");
+    if (!folderId) return;
+    let cancelled = false;
+    invoke<string | null>("folder_draft_load", { folderId })
+      .then((draft) => {
+        if (!cancelled && draft) setValue((current) => current || draft);
+      })
+      .catch((e) => {
+        console.error("folder_draft_load error:", e);
+      });
+    return () => {
+      cancelled = true;
+      flushDraft();
+    };
+  }, [folderId, flushDraft]);
 
   const handleSend = useCallback(() => {
     const trimmed = value.trim();
     if (!trimmed || disabled) return;
+    // The backend clears the saved draft once the prompt reaches the agent.
+    cancelDraftSave();
     onSend(trimmed);
     setValue("");
     if (textareaRef.current) {
       textareaRef.current.style.height = "auto";
     }
-  }, [value, disabled, onSend]);
+  }, [value, disabled, onSend, cancelDraftSave]);
 
   const handleKeyDown = useCallback(
     (e: KeyboardEvent<HTMLTextAreaElement>) => {
@@ -41,6 +97,11 @@ export function ChatInput({
     el.style.height = Math.min(el.scrollHeight, 200) + "px";
   }, []);
 
+  // A restored draft may span several lines.
+  useEffect(() => {
+    handleInput();
+  }, [value, handleInput]);
+
   return (
     <div className="chat-input">
       <textarea
@@ -49,7 +110,7 @@ export function ChatInput({
         value={value}
         onChange={(e) => {
           setValue(e.target.value);
-          handleInput();
+          scheduleDraftSave(e.target.value);
         }}
         onKeyDown={handleKeyDown}
         placeholder={placeholder}
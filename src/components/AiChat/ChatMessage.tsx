@@ -1,16 +1,24 @@
-import type { ChatMessage as ChatMessageType } from "../../types/ai-chat";
+import type { TurnMetadata } from "../../types/acp";
+import type { ChatMessage as ChatMessageType, MessageRating } from "../../types/ai-chat";
 import { DiffPreview } from "./DiffPreview";
 
 type Props = {
   message: ChatMessageType;
   onApplyEdits?: (messageId: string) => void;
   onRejectEdits?: (messageId: string) => void;
+  onRate?: (messageId: string, rating: MessageRating | null) => void;
 };
 
-export function ChatMessage({ message, onApplyEdits, onRejectEdits }: Props) {
+export function ChatMessage({ message, onApplyEdits, onRejectEdits, onRate }: Props) {
   const isUser = message.role === "user";
   const isSystem = message.role === "system";
-  const roleLabel = isUser ? "You" : isSystem ? "System" : "Assistant";
+  const roleLabel = isUser
+    ? "You"
+    : message.systemKind === "summary"
+      ? "Summary of earlier messages"
+      : isSystem
+        ? "System"
+        : "Assistant";
 
   return (
     <div className={`chat-message chat-message--${message.role}`}>
@@ -18,6 +26,24 @@ export function ChatMessage({ message, onApplyEdits, onRejectEdits }: Props) {
         <span className="chat-message__role">
           {roleLabel}
         </span>
+        {message.turn && (
+          <span className="chat-message__turn">{turnSummary(message.turn)}</span>
+        )}
+        {message.role === "assistant" && onRate && (
+          <span className="chat-message__rating">
+            {(["helpful", "wrong"] as const).map((rating) => (
+              <button
+                key={rating}
+                type="button"
+                className={`chat-message__rate${message.rating === rating ? " chat-message__rate--active" : ""}`}
+                title={rating === "helpful" ? "Helpful" : "Wrong"}
+                onClick={() => onRate(message.id, message.rating === rating ? null : rating)}
+              >
+                {rating === "helpful" ? "👍" : "👎"}
+              </button>
+            ))}
+          </span>
+        )}
         <span className="chat-message__time">
           {new Date(message.timestamp).toLocaleTimeString()}
         </span>
@@ -60,12 +86,40 @@ export function ChatMessage({ message, onApplyEdits, onRejectEdits }: Props) {
               Rejected
             </span>
           )}
+          {message.editStatus === "conflict" && (
+            <span className="chat-message__edit-status chat-message__edit-status--conflict">
+              Not applied: {message.editConflicts?.length ?? 0} range(s) changed since these edits
+              were proposed
+            </span>
+          )}
         </div>
       )}
     </div>
   );
 }
 
+const STOP_REASON_LABELS: Record<TurnMetadata["stopReason"], string | null> = {
+  EndTurn: null,
+  MaxTokens: "token limit",
+  MaxTurnRequests: "request limit",
+  Refusal: "refused",
+  Cancelled: "cancelled",
+  Error: "failed",
+};
+
+function turnSummary(turn: TurnMetadata): string {
+  const parts = [`${(turn.durationMs / 1000).toFixed(1)}s`];
+  if (turn.toolCalls > 0) {
+    parts.push(`${turn.toolCalls} tool call${turn.toolCalls === 1 ? "" : "s"}`);
+  }
+  if (turn.inputTokens !== null || turn.outputTokens !== null) {
+    parts.push(`${turn.inputTokens ?? "?"} in / ${turn.outputTokens ?? "?"} out`);
+  }
+  const reason = STOP_REASON_LABELS[turn.stopReason];
+  if (reason) parts.push(reason);
+  return parts.join(" · ");
+}
+
 function renderContent(content: string) {
   // Simple markdown-like rendering for code blocks
   const parts = content.split(/(```[\s\S]*?```)/g);
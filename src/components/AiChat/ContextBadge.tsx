@@ -17,6 +17,7 @@ function statusDot(status: ConnectionStatus): { color: string; label: string } {
 function agentDot(status: AgentStatus): { color: string; label: string } {
   if (status === "Running") return { color: "#22c55e", label: "agent" };
   if (status === "Starting") return { color: "#eab308", label: "agent" };
+  if (status === "Unresponsive") return { color: "#f97316", label: "agent (unresponsive)" };
   if (status === "Stopped") return { color: "#6b7280", label: "agent" };
   return { color: "#ef4444", label: "agent" };
 }
@@ -8,7 +8,8 @@ export type GhosttyRect = {
   height: number;
   viewportWidth: number;
   viewportHeight: number;
-  style: GhosttyStyle;
+  /** Zero (or omitted) insets and radius fall back to the `[terminal]` config defaults. */
+  style?: GhosttyStyle;
 };
 
 export type GhosttyInsets = {
@@ -27,6 +28,11 @@ export type GhosttyOptions = {
   fontSize?: number;
   workingDirectory?: string;
   command?: string;
+  theme?: string;
+  scrollbackLimit?: number;
+  optionAsAlt?: boolean;
+  profile?: string;
+  profileOnly?: boolean;
 };
 
 type GhosttyProps = {
@@ -35,6 +41,8 @@ type GhosttyProps = {
   className?: string;
   styleSource?: "self" | "parent";
   visible?: boolean;
+  /** Slide the terminal to its new place over this many ms on resize. */
+  animationMs?: number;
 };
 
 function readStyle(el: HTMLElement): GhosttyStyle {
@@ -91,9 +99,11 @@ export function Ghostty({
   className,
   styleSource = "parent",
   visible = true,
+  animationMs,
 }: GhosttyProps) {
   const ref = useRef<HTMLDivElement | null>(null);
   const optionsRef = useRef<GhosttyOptions | undefined>(options);
+  const animationMsRef = useRef(animationMs);
   const visibleRef = useRef(visible);
   const createdRef = useRef(false);
 
@@ -101,6 +111,10 @@ export function Ghostty({
     optionsRef.current = options;
   }, [options]);
 
+  useEffect(() => {
+    animationMsRef.current = animationMs;
+  }, [animationMs]);
+
   // Keep visibleRef in sync
   useEffect(() => {
     visibleRef.current = visible;
@@ -156,6 +170,7 @@ export function Ghostty({
       await invoke("ghostty_update_rect", {
         id,
         rect: readRect(sourceEl),
+        animationMs: animationMsRef.current,
       });
     };
 
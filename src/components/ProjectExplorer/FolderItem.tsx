@@ -35,6 +35,7 @@ export function FolderItem({
     'folder-item',
     folder.isActive ? 'folder-item--active' : '',
     isFocused ? 'folder-item--focused' : '',
+    folder.missing ? 'folder-item--missing' : '',
   ].filter(Boolean).join(' ');
 
   return (
@@ -46,6 +47,7 @@ export function FolderItem({
       tabIndex={0}
       aria-selected={folder.isActive}
       data-folder-id={folder.id}
+      title={folder.missing ? `${folder.path} no longer exists; remove the folder or add it again from its new location` : undefined}
     >
       {/* Status dot */}
       <div className="folder-status-dot" />
@@ -26,6 +26,7 @@ export function ProjectExplorer({ onSelectFolder, onRemoveProject, onRemoveFolde
     addFolder,
     removeProject,
     removeFolder,
+    importProjects,
   } = useProjectExplorer();
 
   // Wrap selectFolder to also call onSelectFolder
@@ -42,6 +43,20 @@ export function ProjectExplorer({ onSelectFolder, onRemoveProject, onRemoveFolde
     }
   };
 
+  const handleImportProjects = async () => {
+    const selected = await open({ directory: true, multiple: false, title: 'Import Repositories From Folder' });
+    if (!selected) return;
+    try {
+      const result = await importProjects(selected);
+      console.info(
+        `Imported ${result.repos.length} projects (${result.alreadyAdded} already added)` +
+          (result.truncated ? '; more were found, import again to add them' : '')
+      );
+    } catch (e) {
+      console.error('import_projects_from_dir error:', e);
+    }
+  };
+
   const handleRemoveProject = (projectId: string) => {
     const project = projects.find((p) => p.id === projectId);
     const folderIds = project ? project.folders.map((f) => f.id) : [];
@@ -97,6 +112,13 @@ export function ProjectExplorer({ onSelectFolder, onRemoveProject, onRemoveFolde
         >
           +
         </button>
+        <button
+          className="add-project-btn"
+          onClick={handleImportProjects}
+          title="Import all git repositories in a folder"
+        >
+          ⇣
+        </button>
       </div>
 
       {projects.length === 0 ? (
@@ -10,7 +10,6 @@ interface ProjectItemProps {
   onAddFolder: (projectId: string) => void;
   onRemoveProject?: (projectId: string) => void;
   onRemoveFolder?: (folderId: string) => void;
-  globalFolderStartIndex: number;
   focusedFolderId?: string | null;
 }
 
@@ -22,7 +21,6 @@ export function ProjectItem({
   onAddFolder,
   onRemoveProject,
   onRemoveFolder,
-  globalFolderStartIndex,
   focusedFolderId,
 }: ProjectItemProps) {
   const handleHeaderClick = () => {
@@ -86,14 +84,14 @@ export function ProjectItem({
       </div>
       {project.isExpanded && (
         <div className="project-folders" role="group">
-          {project.folders.map((folder, index) => (
+          {project.folders.map((folder) => (
             <FolderItem
               key={folder.id}
               folder={folder}
               onSelect={onSelectFolder}
               onPRClick={onPRClick}
               onRemove={onRemoveFolder}
-              globalIndex={globalFolderStartIndex + index}
+              globalIndex={folder.hotkeyIndex}
               isFocused={focusedFolderId === folder.id}
             />
           ))}
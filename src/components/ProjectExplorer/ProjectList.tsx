@@ -22,31 +22,21 @@ export function ProjectList({
   onRemoveFolder,
   focusedFolderId,
 }: ProjectListProps) {
-  // Calculate global folder start index for each project
-  // This is used for cmd+1-9 hotkey badges
-  let globalFolderIndex = 0;
-
   return (
     <div className="project-list" role="tree" aria-label="Projects">
-      {projects.map((project) => {
-        const startIndex = globalFolderIndex;
-        globalFolderIndex += project.folders.length;
-
-        return (
-          <ProjectItem
-            key={project.id}
-            project={project}
-            onToggle={onToggleProject}
-            onSelectFolder={onSelectFolder}
-            onPRClick={onPRClick}
-            onAddFolder={onAddFolder}
-            onRemoveProject={onRemoveProject}
-            onRemoveFolder={onRemoveFolder}
-            globalFolderStartIndex={startIndex}
-            focusedFolderId={focusedFolderId}
-          />
-        );
-      })}
+      {projects.map((project) => (
+        <ProjectItem
+          key={project.id}
+          project={project}
+          onToggle={onToggleProject}
+          onSelectFolder={onSelectFolder}
+          onPRClick={onPRClick}
+          onAddFolder={onAddFolder}
+          onRemoveProject={onRemoveProject}
+          onRemoveFolder={onRemoveFolder}
+          focusedFolderId={focusedFolderId}
+        />
+      ))}
     </div>
   );
 }
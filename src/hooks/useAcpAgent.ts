@@ -4,11 +4,11 @@ import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import type {
   AcpEvent,
   AcpInstallStatus,
+  AcpMultiPromptResult,
   AcpPermissionRequest,
   AgentStatus,
 } from "../types/acp";
-
-const DEFAULT_AGENT_PATH = "codex-acp";
+import { errorMessage } from "../types/errors";
 
 export function useAcpAgent() {
   const [status, setStatus] = useState<AgentStatus>("Stopped");
@@ -28,6 +28,13 @@ export function useAcpAgent() {
           eventCallbackRef.current(event.payload);
         }
       });
+      // Streamed chunks arrive coalesced, one batch per frame.
+      const unlistenBatch = await listen<AcpEvent[]>("acp-event-batch", (event) => {
+        if (cancelled || !eventCallbackRef.current) return;
+        for (const item of event.payload) {
+          eventCallbackRef.current(item);
+        }
+      });
       const unlistenInstall = await listen<AcpInstallStatus>("acp-install-status", (event) => {
         if (cancelled) return;
         const next = event.payload;
@@ -44,14 +51,31 @@ export function useAcpAgent() {
           setPermissionQueue((prev) => [...prev, event.payload]);
         }
       );
+      // The backend also starts/restarts the agent on its own ([agent] config).
+      const unlistenStatus = await listen<AgentStatus>("acp-agent-status", (event) => {
+        if (cancelled) return;
+        setStatus(event.payload);
+        if (event.payload === "Stopped" || typeof event.payload === "object") {
+          setSessionId(null);
+        }
+      });
       if (!cancelled) {
         listenersRef.current.push(unlisten);
+        listenersRef.current.push(unlistenBatch);
         listenersRef.current.push(unlistenInstall);
         listenersRef.current.push(unlistenPermission);
+        listenersRef.current.push(unlistenStatus);
+        invoke<AgentStatus>("acp_agent_status")
+          .then((current) => {
+            if (!cancelled) setStatus(current);
+          })
+          .catch((e) => console.error("acp_agent_status error:", e));
       } else {
         unlisten();
+        unlistenBatch();
         unlistenInstall();
         unlistenPermission();
+        unlistenStatus();
       }
     };
 
@@ -71,7 +95,7 @@ export function useAcpAgent() {
   }, []);
 
   const startAgent = useCallback(
-    async (agentPath: string = DEFAULT_AGENT_PATH) => {
+    async (agentPath?: string) => {
       try {
         setStatus("Starting");
         setPermissionQueue([]);
@@ -79,11 +103,11 @@ export function useAcpAgent() {
           phase: "starting",
           message: "Starting AI agent...",
         });
-        await invoke("acp_start_agent", { agentPath });
+        await invoke("acp_start_agent", { agentPath: agentPath ?? null });
         setStatus("Running");
         setInstallState(null);
       } catch (e) {
-        setStatus({ Error: String(e) });
+        setStatus({ Error: errorMessage(e) });
         setInstallState((prev) => (prev?.phase === "error" ? prev : null));
         throw e;
       }
@@ -112,18 +136,66 @@ export function useAcpAgent() {
     return sid;
   }, []);
 
+  /** Points the active session at `dir` (e.g. another worktree); resolves to the canonical path. */
+  const setSessionCwd = useCallback(
+    async (dir: string) => {
+      if (!sessionId) throw new Error("No active session");
+      return invoke<string>("acp_set_session_cwd", { sessionId, dir });
+    },
+    [sessionId]
+  );
+
+  /** Turns streaming of the agent's reasoning on or off for the active session. */
+  const setStreamOptions = useCallback(
+    async (options: { thoughts: boolean }) => {
+      if (!sessionId) throw new Error("No active session");
+      await invoke("acp_set_stream_options", { sessionId, options });
+    },
+    [sessionId]
+  );
+
+  /** Sends one prompt to several sessions at once to compare their answers. */
+  const sendPromptMulti = useCallback(
+    async (sessionIds: string[], messages: string[], context?: string, contextBlocks?: string[]) =>
+      invoke<AcpMultiPromptResult[]>("acp_send_prompt_multi", {
+        sessionIds,
+        messages,
+        context: context ?? null,
+        contextBlocks: contextBlocks ?? null,
+      }),
+    []
+  );
+
+  /** Renders a stored prompt template in the backend and sends it. */
+  const sendTemplate = useCallback(
+    async (templateId: string, vars?: Record<string, string>) => {
+      if (!sessionId) throw new Error("No active session");
+      return invoke<string>("acp_send_template", { sessionId, templateId, vars });
+    },
+    [sessionId]
+  );
+
   const sendPrompt = useCallback(
-    async (messages: string[], context?: string) => {
+    async (messages: string[], context?: string, contextBlocks?: string[]) => {
       if (!sessionId) throw new Error("No active session");
       return invoke<string>("acp_send_prompt", {
         sessionId,
         messages,
         context: context ?? null,
+        contextBlocks: contextBlocks ?? null,
       });
     },
     [sessionId]
   );
 
+  /** Cancels the active session's turn and stops the commands it started. */
+  const cancelPrompt = useCallback(async () => {
+    if (!sessionId) throw new Error("No active session");
+    await invoke("acp_cancel_prompt", { sessionId });
+    // The backend answers the session's open permission requests as cancelled.
+    setPermissionQueue((prev) => prev.filter((req) => req.sessionId !== sessionId));
+  }, [sessionId]);
+
   const refreshStatus = useCallback(async () => {
     const s = await invoke<AgentStatus>("acp_agent_status");
     setStatus(s);
@@ -153,7 +225,12 @@ export function useAcpAgent() {
     startAgent,
     stopAgent,
     createSession,
+    setSessionCwd,
+    setStreamOptions,
     sendPrompt,
+    sendPromptMulti,
+    sendTemplate,
+    cancelPrompt,
     respondPermission,
     onEvent,
     refreshStatus,
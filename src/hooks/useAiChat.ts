@@ -1,22 +1,31 @@
 import { useState, useCallback, useRef, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import type { NvimBridgeApi } from "./useNvimBridge";
 import { useAcpAgent } from "./useAcpAgent";
 import { useLocalStorage } from "./useLocalStorage";
-import type { ChatMessage } from "../types/ai-chat";
-import type { AcpEvent } from "../types/acp";
+import type { BuiltContext, ChatMessage, MessageRating } from "../types/ai-chat";
+import {
+  errorRecoveryHint,
+  type AcpContextUsageEvent,
+  type AcpEvent,
+  type AcpRetryingEvent,
+} from "../types/acp";
 import type {
   NvimAction,
   NvimActionEvent,
   NvimBridgeDebugEvent,
   NvimCursorFollowEvent,
 } from "../types/nvim";
+import { errorMessage } from "../types/errors";
 
 function nextMessageId(): string {
   return `msg-${Date.now()}-${Math.random().toString(36).slice(2, 8)}`;
 }
 
 const MAX_PERSISTED_MESSAGES = 200;
+/** Messages left untouched when older history is compacted into a summary. */
+const KEEP_RECENT_ON_COMPACT = 10;
 const MAX_TRACE_EVENTS = 80;
 
 export interface AiTraceEvent {
@@ -36,8 +45,12 @@ export interface AiChatController {
     kind?: "action-summary" | "status-note"
   ) => void;
   sendMessage: (content: string) => Promise<void>;
+  compactConversation: () => Promise<void>;
+  isCompacting: boolean;
   applyProposedEdits: (messageId: string) => Promise<void>;
   rejectProposedEdits: (messageId: string) => void;
+  /** Rates an assistant answer; `null` takes the rating back. */
+  rateMessage: (messageId: string, rating: MessageRating | null, note?: string) => Promise<void>;
   clearMessages: () => void;
   traceEvents: AiTraceEvent[];
   clearTraceEvents: () => void;
@@ -51,6 +64,7 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
   const [isStreaming, setIsStreaming] = useState(false);
   const [autoApply, setAutoApply] = useLocalStorage<boolean>('libg:autoApply', false);
   const [traceEvents, setTraceEvents] = useState<AiTraceEvent[]>([]);
+  const [isCompacting, setIsCompacting] = useState(false);
 
   // Trim messages on mount to prevent unbounded growth
   useEffect(() => {
@@ -120,6 +134,8 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
   // Wire up ACP streaming events to chat messages
   useEffect(() => {
     acp.onEvent((event: AcpEvent) => {
+      // Another terminal's chat may be streaming at the same time.
+      if (event.terminalId && terminalId && event.terminalId !== terminalId) return;
       switch (event.type) {
         case "contentChunk": {
           const assistantId = currentAssistantIdRef.current;
@@ -133,12 +149,42 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
           );
           break;
         }
+        case "toolCallDiff": {
+          const assistantId = currentAssistantIdRef.current;
+          if (!assistantId) return;
+          trace(
+            "agent.toolCallDiff",
+            `${event.data.path} +${event.data.diff.additions} -${event.data.diff.deletions}`
+          );
+          setMessages((prev) =>
+            prev.map((m) => {
+              if (m.id !== assistantId) return m;
+              // A later update for the same tool call and file replaces the earlier diff.
+              const diffs = (m.diffs ?? []).filter(
+                (d) => d.id !== event.data.id || d.path !== event.data.path
+              );
+              return { ...m, diffs: [...diffs, event.data] };
+            })
+          );
+          break;
+        }
         case "done": {
-          trace("agent.done");
+          const { turn, context } = event.data;
+          trace(
+            "agent.done",
+            `${turn.stopReason} ${turn.durationMs}ms tools=${turn.toolCalls} context sent=${context.sentBlocks} reused=${context.reusedBlocks} savedTokens=${context.reusedTokens}`
+          );
           setIsStreaming(false);
           const wasActionTriggered = actionTriggeredRef.current;
           actionTriggeredRef.current = false;
+          if (wasActionTriggered) void nvim.setProgress(null).catch(() => {});
+          const assistantId = currentAssistantIdRef.current;
           currentAssistantIdRef.current = null;
+          if (assistantId) {
+            setMessages((prev) =>
+              prev.map((m) => (m.id === assistantId ? { ...m, turn } : m))
+            );
+          }
 
           // Auto-apply edits if enabled and this was a nvim-triggered action
           if (wasActionTriggered && autoApplyRef.current) {
@@ -147,17 +193,23 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
                 .reverse()
                 .find((m) => m.role === "assistant" && m.proposedEdits);
               if (lastAssistant?.proposedEdits && lastAssistant.editStatus !== "applied") {
-                nvim.applyEdits(lastAssistant.proposedEdits).then(() => {
-                  trace("edits.autoApply.success");
+                nvim.applyEdits(lastAssistant.proposedEdits).then((result) => {
+                  const conflicts = result?.conflicts ?? [];
+                  trace(
+                    conflicts.length > 0 ? "edits.autoApply.conflict" : "edits.autoApply.success",
+                    conflicts.length > 0 ? `stale=${conflicts.length}` : undefined
+                  );
                   setMessages((curr) =>
                     curr.map((m) =>
                       m.id === lastAssistant.id
-                        ? { ...m, editStatus: "applied" }
+                        ? conflicts.length > 0
+                          ? { ...m, editStatus: "conflict", editConflicts: conflicts }
+                          : { ...m, editStatus: "applied" }
                         : m
                     )
                   );
                 }).catch((e) => {
-                  trace("edits.autoApply.error", String(e));
+                  trace("edits.autoApply.error", errorMessage(e));
                 });
               }
               return prev;
@@ -166,15 +218,27 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
           break;
         }
         case "error": {
-          trace("agent.error", event.data);
+          trace(
+            "agent.error",
+            `${event.data.kind.code}: ${event.data.message} (retryable=${event.data.retryable} attempts=${event.data.attempts})`
+          );
           setIsStreaming(false);
+          if (actionTriggeredRef.current) void nvim.setProgress(null).catch(() => {});
           actionTriggeredRef.current = false;
+          const hint = errorRecoveryHint(event.data);
           const assistantId = currentAssistantIdRef.current;
           if (assistantId) {
             setMessages((prev) =>
               prev.map((m) =>
                 m.id === assistantId
-                  ? { ...m, content: m.content + `\n\n**Error:** ${event.data}` }
+                  ? {
+                      ...m,
+                      content:
+                        m.content +
+                        `\n\n**Error:** ${event.data.message}` +
+                        (hint ? `\n\n${hint}` : ""),
+                      turn: event.data.turn,
+                    }
                   : m
               )
             );
@@ -182,9 +246,14 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
           currentAssistantIdRef.current = null;
           break;
         }
+        case "permissionAutoApproved": {
+          trace("agent.permissionAutoApproved", `${event.data.kind}: ${event.data.title}`);
+          appendSystemMessage(`Auto-approved ${event.data.kind}: ${event.data.title}`, "status-note");
+          break;
+        }
       }
     });
-  }, [acp, nvim, trace]);
+  }, [acp, nvim, trace, terminalId, appendSystemMessage]);
 
   const sendMessage = useCallback(
     async (content: string) => {
@@ -215,18 +284,44 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
       setMessages((prev) => [...prev, assistantMsg]);
       setIsStreaming(true);
 
-      // Build context string from nvim state
+      // Build context on the backend, which dedupes sources and keeps them
+      // within the configured token budget.
       let contextStr: string | undefined;
-      if (nvim.context) {
+      let contextBlocks: string[] | undefined;
+      if (terminalId && nvim.context) {
+        try {
+          const built = await invoke<BuiltContext>("build_context", { terminalId });
+          contextStr = built.text || undefined;
+          contextBlocks = built.blocks.length > 0 ? built.blocks : undefined;
+          trace(
+            "context.built",
+            `tokens=${built.estimatedTokens}/${built.tokenBudget} omitted=${built.omitted.length}`
+          );
+        } catch (e) {
+          trace("context.build.error", errorMessage(e));
+        }
+      }
+
+      // Fall back to the editor state the frontend already has
+      if (contextStr === undefined && nvim.context) {
         const ctx = nvim.context;
-        const parts = [
-          `File: ${ctx.filePath} (${ctx.fileType})`,
-          `Cursor: line ${ctx.cursor.line}, col ${ctx.cursor.col}`,
-          `Buffer lines ${ctx.visibleRange[0]}-${ctx.visibleRange[1]}:`,
-          "```",
-          ...ctx.visibleLines,
-          "```",
-        ];
+        const parts = ctx.terminal
+          ? [
+              `Output of the terminal running \`${ctx.terminal.command || ctx.terminal.title}\`, ${
+                ctx.terminal.running ? "still running" : "exited"
+              }; last ${ctx.visibleLines.length} lines:`,
+              "```",
+              ...ctx.visibleLines,
+              "```",
+            ]
+          : [
+              `File: ${ctx.filePath} (${ctx.fileType})`,
+              `Cursor: line ${ctx.cursor.line}, col ${ctx.cursor.col}`,
+              `Buffer lines ${ctx.visibleRange[0]}-${ctx.visibleRange[1]}:`,
+              "```",
+              ...ctx.visibleLines,
+              "```",
+            ];
         if (nvim.diagnostics.length > 0) {
           parts.push(
             "\nDiagnostics:",
@@ -241,22 +336,24 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
 
       try {
         trace("agent.prompt.start");
-        await acp.sendPrompt([content], contextStr);
+        await acp.sendPrompt([content], contextStr, contextBlocks);
         trace("agent.prompt.sent");
       } catch (e) {
-        trace("agent.prompt.error", String(e));
+        trace("agent.prompt.error", errorMessage(e));
         setIsStreaming(false);
+        if (actionTriggeredRef.current) void nvim.setProgress(null).catch(() => {});
+        actionTriggeredRef.current = false;
         setMessages((prev) =>
           prev.map((m) =>
             m.id === assistantId
-              ? { ...m, content: `**Error:** ${String(e)}` }
+              ? { ...m, content: `**Error:** ${errorMessage(e)}` }
               : m
           )
         );
         currentAssistantIdRef.current = null;
       }
     },
-    [isStreaming, nvim.context, nvim.diagnostics, acp, trace]
+    [isStreaming, terminalId, nvim.context, nvim.diagnostics, nvim.setProgress, acp, trace]
   );
 
   // Listen for nvim-action events from Neovim keybindings
@@ -276,6 +373,9 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
       }
 
       actionTriggeredRef.current = true;
+      void nvim.setProgress(`agent: ${action.action}…`).catch((e) => {
+        trace("nvim.progress.error", errorMessage(e));
+      });
       const prompt = buildActionPrompt(action);
       trace("nvim.action.forwarded", action.action);
       void sendMessage(prompt);
@@ -284,7 +384,7 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
     return () => {
       unlisten.then((fn) => fn());
     };
-  }, [terminalId, isStreaming, sendMessage, trace]);
+  }, [terminalId, isStreaming, sendMessage, nvim.setProgress, trace]);
 
   // Listen for backend bridge debug events so failures are visible in UI.
   useEffect(() => {
@@ -300,6 +400,39 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
     };
   }, [terminalId, trace]);
 
+  useEffect(() => {
+    if (!terminalId) return;
+
+    const unlisten = listen<AcpRetryingEvent>("acp-retrying", (event) => {
+      if (event.payload.terminalId !== terminalId) return;
+      const { attempt, maxRetries, delayMs, error } = event.payload;
+      trace("agent.prompt.retrying", `${attempt}/${maxRetries} in ${delayMs}ms: ${error}`);
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [terminalId, trace]);
+
+  useEffect(() => {
+    if (!terminalId) return;
+
+    const unlisten = listen<AcpContextUsageEvent>("acp-context-usage", (event) => {
+      if (event.payload.terminalId !== terminalId) return;
+      const { tokens, limit, threshold } = event.payload;
+      trace("agent.context.usage", `${tokens}/${limit} tokens (${threshold}%)`);
+      appendSystemMessage(
+        `The conversation fills about ${threshold}% of the agent's context window ` +
+          `(~${tokens} of ${limit} tokens). Compact it to keep room for new context.`,
+        "status-note"
+      );
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [terminalId, trace, appendSystemMessage]);
+
   useEffect(() => {
     if (!terminalId) return;
 
@@ -323,7 +456,18 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
 
       try {
         trace("edits.apply.start", `message=${messageId}`);
-        await nvim.applyEdits(msg.proposedEdits);
+        const result = await nvim.applyEdits(msg.proposedEdits);
+        if (result && !result.applied) {
+          trace("edits.apply.conflict", `message=${messageId} stale=${result.conflicts.length}`);
+          setMessages((prev) =>
+            prev.map((m) =>
+              m.id === messageId
+                ? { ...m, editStatus: "conflict", editConflicts: result.conflicts }
+                : m
+            )
+          );
+          return;
+        }
         trace("edits.apply.success", `message=${messageId}`);
         setMessages((prev) =>
           prev.map((m) =>
@@ -331,7 +475,7 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
           )
         );
       } catch (e) {
-        trace("edits.apply.error", String(e));
+        trace("edits.apply.error", errorMessage(e));
         console.error("Failed to apply edits:", e);
       }
     },
@@ -350,9 +494,73 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
     [trace]
   );
 
+  const rateMessage = useCallback(
+    async (messageId: string, rating: MessageRating | null, note?: string) => {
+      const folderId = terminalId?.replace(/^terminal-/, "") || null;
+      try {
+        await invoke("message_rate", { messageId, rating, note: note ?? null, folderId });
+        setMessages((prev) =>
+          prev.map((m) => (m.id === messageId ? { ...m, rating: rating ?? undefined } : m))
+        );
+      } catch (e) {
+        appendSystemMessage(`Failed to save rating: ${errorMessage(e)}`, "status-note");
+      }
+    },
+    [terminalId, setMessages, appendSystemMessage]
+  );
+
+  const compactConversation = useCallback(async () => {
+    const folderId = terminalId?.replace(/^terminal-/, "");
+    if (!folderId || isStreaming || isCompacting) return;
+
+    const active = messages.filter((m) => !m.archived);
+    const older = active.slice(0, Math.max(0, active.length - KEEP_RECENT_ON_COMPACT));
+    if (older.length < 2) {
+      appendSystemMessage("Not enough history to compact yet.", "status-note");
+      return;
+    }
+
+    setIsCompacting(true);
+    trace("chat.compact.start", `messages=${older.length}`);
+    try {
+      const result = await invoke<{ summary: string; compactedCount: number }>(
+        "compact_conversation",
+        { folderId, messages: older }
+      );
+      const archivedIds = new Set(older.map((m) => m.id));
+      const summaryMsg: ChatMessage = {
+        id: nextMessageId(),
+        role: "system",
+        systemKind: "summary",
+        content: result.summary,
+        timestamp: older[older.length - 1].timestamp,
+      };
+      setMessages((prev) => {
+        const next = prev.map((m) => (archivedIds.has(m.id) ? { ...m, archived: true } : m));
+        const firstKept = next.findIndex((m) => !m.archived);
+        const at = firstKept === -1 ? next.length : firstKept;
+        return [...next.slice(0, at), summaryMsg, ...next.slice(at)];
+      });
+      trace("chat.compact.done", `compacted=${result.compactedCount}`);
+    } catch (e) {
+      trace("chat.compact.error", errorMessage(e));
+      appendSystemMessage(`Failed to compact conversation: ${errorMessage(e)}`, "status-note");
+    } finally {
+      setIsCompacting(false);
+    }
+  }, [terminalId, isStreaming, isCompacting, messages, setMessages, appendSystemMessage, trace]);
+
   const clearMessages = useCallback(() => {
     setMessages([]);
-  }, []);
+    const folderId = terminalId?.replace(/^terminal-/, "");
+    if (folderId) {
+      // Drop the stored summary too so it stops being sent as context.
+      void invoke("folder_session_update", {
+        folderId,
+        update: { conversationSummary: "" },
+      }).catch(() => {});
+    }
+  }, [terminalId]);
 
   return {
     messages,
@@ -361,8 +569,11 @@ export function useAiChat(terminalId: string | null, nvim: NvimBridgeApi): AiCha
     setAutoApply,
     appendSystemMessage,
     sendMessage,
+    compactConversation,
+    isCompacting,
     applyProposedEdits,
     rejectProposedEdits,
+    rateMessage,
     clearMessages,
     traceEvents,
     clearTraceEvents,
@@ -1,13 +1,23 @@
 import { useState, useCallback, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import type {
+  ApplyEditsResult,
+  BatchResult,
+  Breadcrumbs,
+  BufferHunks,
+  BridgeOp,
   NvimContext,
   Diagnostic,
   BufferEdit,
   ConnectionStatus,
   NvimHealth,
+  NvimLayout,
+  KeymapBinding,
   KeymapStatus,
+  WorkspaceRoots,
 } from "../types/nvim";
+import { errorMessage, isNeoaiError } from "../types/errors";
 
 const POLL_INTERVAL_MS = 2000;
 const HEALTH_FAILURE_THRESHOLD = 2;
@@ -16,6 +26,7 @@ export interface NvimBridgeApi {
   status: ConnectionStatus;
   context: NvimContext | null;
   diagnostics: Diagnostic[];
+  breadcrumbs: Breadcrumbs | null;
   health: NvimHealth | null;
   keymapStatus: KeymapStatus;
   lastError: string | null;
@@ -23,16 +34,39 @@ export interface NvimBridgeApi {
   disconnect: () => Promise<void>;
   refreshContext: () => Promise<void>;
   probeHealth: () => Promise<NvimHealth | null>;
-  reinjectKeymaps: () => Promise<void>;
+  /** Lists each binding, including the existing mappings it collided with. */
+  reinjectKeymaps: () => Promise<KeymapBinding[]>;
   applyEdit: (edit: BufferEdit) => Promise<void>;
-  applyEdits: (edits: BufferEdit[]) => Promise<void>;
+  /** Applies nothing and lists the stale ranges when the buffer changed since the edits were proposed. */
+  applyEdits: (edits: BufferEdit[]) => Promise<ApplyEditsResult | null>;
+  /** Runs `ops` atomically; a failing step rolls the earlier ones back. */
+  execBatch: (ops: BridgeOp[]) => Promise<BatchResult | null>;
   execCommand: (command: string) => Promise<string>;
+  /** Renames the symbol under the cursor through the LSP; resolves to the changed files. */
+  lspRename: (newName: string) => Promise<string[]>;
+  getBufferHunks: () => Promise<BufferHunks | null>;
+  /** Shows `message` with a spinner where the last action was sent from; `null` clears it. */
+  setProgress: (message: string | null) => Promise<void>;
+  /** Inserts `text` at the cursor, or puts it in the unnamed register with `register`. */
+  insertAtCursor: (text: string, options?: { register?: boolean }) => Promise<void>;
+  getWorkspaceRoots: () => Promise<WorkspaceRoots | null>;
+  /** Tabpages and their windows with buffers, cursors and screen geometry. */
+  getLayout: () => Promise<NvimLayout | null>;
 }
 
-export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
+export interface NvimBridgeOptions {
+  /** Fetch only the lines the window shows, with closed folds summarized. */
+  folds?: boolean;
+}
+
+export function useNvimBridge(
+  terminalId: string | null,
+  { folds = false }: NvimBridgeOptions = {}
+): NvimBridgeApi {
   const [status, setStatus] = useState<ConnectionStatus>("Disconnected");
   const [context, setContext] = useState<NvimContext | null>(null);
   const [diagnostics, setDiagnostics] = useState<Diagnostic[]>([]);
+  const [breadcrumbs, setBreadcrumbs] = useState<Breadcrumbs | null>(null);
   const [health, setHealth] = useState<NvimHealth | null>(null);
   const [keymapStatus, setKeymapStatus] = useState<KeymapStatus>("unknown");
   const [lastError, setLastError] = useState<string | null>(null);
@@ -48,6 +82,7 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
       setKeymapStatus("unknown");
       setContext(null);
       setDiagnostics([]);
+      setBreadcrumbs(null);
       setLastError(snapshot.lastError ?? null);
       return;
     }
@@ -71,7 +106,7 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
       setStatus("Error");
       setHealth(null);
       setKeymapStatus("error");
-      setLastError(String(e));
+      setLastError(errorMessage(e));
       return null;
     }
   }, [terminalId, applyHealthSnapshot]);
@@ -81,6 +116,7 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
 
     const ctx = await invoke<NvimContext>("nvim_get_context", {
       terminalId,
+      folds,
     });
     const diags = await invoke<Diagnostic[]>("nvim_get_diagnostics", {
       terminalId,
@@ -88,6 +124,15 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
 
     setContext(ctx);
     setDiagnostics(diags);
+  }, [terminalId, folds]);
+
+  const fetchBreadcrumbs = useCallback(async () => {
+    if (!terminalId) return;
+    try {
+      setBreadcrumbs(await invoke<Breadcrumbs>("nvim_get_breadcrumbs", { terminalId }));
+    } catch (e) {
+      console.error("nvim_get_breadcrumbs error:", e);
+    }
   }, [terminalId]);
 
   const connect = useCallback(
@@ -111,7 +156,7 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
         setStatus("Error");
         setHealth(null);
         setKeymapStatus("error");
-        setLastError(String(e));
+        setLastError(errorMessage(e));
         throw e;
       }
     },
@@ -129,6 +174,7 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
       setLastError(null);
       setContext(null);
       setDiagnostics([]);
+      setBreadcrumbs(null);
     } catch (e) {
       console.error("nvim_disconnect error:", e);
     }
@@ -147,8 +193,22 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
       await fetchContextAndDiagnostics();
       failedRefreshesRef.current = 0;
     } catch (e) {
+      // Neovim is showing a prompt or the command line; the next poll retries.
+      if (isNeoaiError(e, "NVIM_BUSY")) return;
       console.error("nvim context refresh error:", e);
-      setLastError(String(e));
+      setLastError(errorMessage(e));
+
+      // The bridge is gone; show the reconnect controls right away.
+      if (isNeoaiError(e, "NVIM_NOT_CONNECTED")) {
+        failedRefreshesRef.current = 0;
+        setStatus("Disconnected");
+        setHealth(null);
+        setKeymapStatus("unknown");
+        setContext(null);
+        setDiagnostics([]);
+        setBreadcrumbs(null);
+        return;
+      }
 
       failedRefreshesRef.current += 1;
       if (failedRefreshesRef.current >= HEALTH_FAILURE_THRESHOLD) {
@@ -159,18 +219,19 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
   }, [terminalId, status, probeHealth, fetchContextAndDiagnostics]);
 
   const reinjectKeymaps = useCallback(async () => {
-    if (!terminalId) return;
+    if (!terminalId) return [];
 
     try {
-      await invoke("nvim_reinject_keymaps", { terminalId });
+      const bindings = await invoke<KeymapBinding[]>("nvim_reinject_keymaps", { terminalId });
       const snapshot = await probeHealth();
       if (snapshot?.connected) {
         setKeymapStatus(snapshot.keymapsInjected ? "present" : "missing");
       }
+      return bindings;
     } catch (e) {
       console.error("nvim_reinject_keymaps error:", e);
       setKeymapStatus("error");
-      setLastError(String(e));
+      setLastError(errorMessage(e));
       throw e;
     }
   }, [terminalId, probeHealth]);
@@ -184,9 +245,17 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
   );
 
   const applyEdits = useCallback(
-    async (edits: BufferEdit[]) => {
-      if (!terminalId) return;
-      await invoke("nvim_apply_edits", { terminalId, edits });
+    async (edits: BufferEdit[]): Promise<ApplyEditsResult | null> => {
+      if (!terminalId) return null;
+      return invoke<ApplyEditsResult>("nvim_apply_edits", { terminalId, edits });
+    },
+    [terminalId]
+  );
+
+  const execBatch = useCallback(
+    async (ops: BridgeOp[]): Promise<BatchResult | null> => {
+      if (!terminalId) return null;
+      return invoke<BatchResult>("nvim_exec_batch", { terminalId, ops });
     },
     [terminalId]
   );
@@ -199,6 +268,45 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
     [terminalId]
   );
 
+  const lspRename = useCallback(
+    async (newName: string): Promise<string[]> => {
+      if (!terminalId) return [];
+      return invoke<string[]>("nvim_lsp_rename", { terminalId, newName });
+    },
+    [terminalId]
+  );
+
+  const getBufferHunks = useCallback(async (): Promise<BufferHunks | null> => {
+    if (!terminalId) return null;
+    return invoke<BufferHunks>("nvim_get_buffer_hunks", { terminalId });
+  }, [terminalId]);
+
+  const setProgress = useCallback(
+    async (message: string | null) => {
+      if (!terminalId) return;
+      await invoke("nvim_set_progress", { terminalId, message });
+    },
+    [terminalId]
+  );
+
+  const insertAtCursor = useCallback(
+    async (text: string, options?: { register?: boolean }) => {
+      if (!terminalId) return;
+      await invoke("nvim_insert_at_cursor", { terminalId, text, options });
+    },
+    [terminalId]
+  );
+
+  const getWorkspaceRoots = useCallback(async (): Promise<WorkspaceRoots | null> => {
+    if (!terminalId) return null;
+    return invoke<WorkspaceRoots>("nvim_get_workspace_roots", { terminalId });
+  }, [terminalId]);
+
+  const getLayout = useCallback(async (): Promise<NvimLayout | null> => {
+    if (!terminalId) return null;
+    return invoke<NvimLayout>("nvim_get_layout", { terminalId });
+  }, [terminalId]);
+
   useEffect(() => {
     failedRefreshesRef.current = 0;
 
@@ -209,6 +317,7 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
       setLastError(null);
       setContext(null);
       setDiagnostics([]);
+      setBreadcrumbs(null);
       return;
     }
 
@@ -220,12 +329,27 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
           failedRefreshesRef.current = 0;
         } catch (e) {
           console.error("nvim initial refresh error:", e);
-          setLastError(String(e));
+          setLastError(errorMessage(e));
         }
       }
     })();
   }, [terminalId, probeHealth, fetchContextAndDiagnostics]);
 
+  // Breadcrumbs are pushed by Neovim as the cursor moves; fetch once so the
+  // header isn't empty until the first move.
+  useEffect(() => {
+    if (status !== "Connected") return;
+    void fetchBreadcrumbs();
+    const unlisten = listen<Breadcrumbs>("nvim-breadcrumbs", (event) => {
+      if (event.payload.terminalId === terminalId) {
+        setBreadcrumbs(event.payload);
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [status, terminalId, fetchBreadcrumbs]);
+
   // Poll health + context every 2s when connected
   useEffect(() => {
     if (status !== "Connected") {
@@ -253,6 +377,7 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
     status,
     context,
     diagnostics,
+    breadcrumbs,
     health,
     keymapStatus,
     lastError,
@@ -263,6 +388,13 @@ export function useNvimBridge(terminalId: string | null): NvimBridgeApi {
     reinjectKeymaps,
     applyEdit,
     applyEdits,
+    execBatch,
     execCommand,
+    lspRename,
+    getBufferHunks,
+    setProgress,
+    insertAtCursor,
+    getWorkspaceRoots,
+    getLayout,
   };
 }
@@ -1,5 +1,7 @@
 import { useState, useCallback, useEffect, useMemo } from 'react';
-import type { Project, ProjectFolder } from '../types/project-explorer';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import type { CreatedProject, FolderIssue, Project, ProjectFolder, ProjectImport } from '../types/project-explorer';
 import { useLocalStorage } from './useLocalStorage';
 
 function stableId(prefix: string, path: string): string {
@@ -25,6 +27,8 @@ export interface UseProjectExplorerReturn {
   addFolder: (projectId: string, path: string, name: string) => void;
   removeProject: (projectId: string) => void;
   removeFolder: (folderId: string) => void;
+  createProjectFromTemplate: (templateId: string, path: string) => Promise<CreatedProject>;
+  importProjects: (root: string, maxDepth?: number) => Promise<ProjectImport>;
 }
 
 export function useProjectExplorer(): UseProjectExplorerReturn {
@@ -32,6 +36,26 @@ export function useProjectExplorer(): UseProjectExplorerReturn {
   const [activeFolderId, setActiveFolderId] = useLocalStorage<string | null>('libg:activeFolderId', null);
   const [focusedFolderId, setFocusedFolderId] = useState<string | null>(null);
   const [showHotkeys, setShowHotkeys] = useState(false);
+  const [lastUsedAt, setLastUsedAt] = useState<Record<string, number>>({});
+  const [missingFolderIds, setMissingFolderIds] = useState<Set<string>>(new Set());
+
+  // Most recently used projects first; a project counts as used when any of
+  // its folders' terminals got input or sent a prompt. The list and arrow keys
+  // follow this order; cmd+1-9 keep the stored one (`hotkeyIndex`), so
+  // activity never reassigns them.
+  const sortedProjects = useMemo(() => {
+    const recency = (p: Project) => Math.max(0, ...p.folders.map((f) => lastUsedAt[f.id] ?? 0));
+    let hotkeyIndex = 0;
+    const numbered = projects.map((p) => ({
+      ...p,
+      folders: p.folders.map((f) => ({
+        ...f,
+        missing: missingFolderIds.has(f.id),
+        hotkeyIndex: hotkeyIndex++,
+      })),
+    }));
+    return numbered.sort((a, b) => recency(b) - recency(a));
+  }, [projects, lastUsedAt, missingFolderIds]);
 
   const activeFolder = useMemo(() => {
     if (!activeFolderId) return null;
@@ -42,12 +66,13 @@ export function useProjectExplorer(): UseProjectExplorerReturn {
     return null;
   }, [activeFolderId, projects]);
 
-  // Get all folders from expanded projects (for keyboard navigation)
+  // Get all folders from expanded projects, in the order the sidebar shows
+  // them (for keyboard navigation)
   const getAllFolders = useCallback((): ProjectFolder[] => {
-    return projects
+    return sortedProjects
       .filter((p) => p.isExpanded)
       .flatMap((p) => p.folders);
-  }, [projects]);
+  }, [sortedProjects]);
 
   // Get all folders regardless of expansion state (for cmd+1-9 shortcuts)
   const allFolders = useMemo(() => {
@@ -109,6 +134,43 @@ export function useProjectExplorer(): UseProjectExplorerReturn {
     });
   }, [setProjects]);
 
+  // Adds every git repository found under `root` as a project, in one update.
+  const importProjects = useCallback(async (root: string, maxDepth?: number) => {
+    const result = await invoke<ProjectImport>('import_projects_from_dir', {
+      root,
+      maxDepth: maxDepth ?? null,
+      existing: projects.map((p) => p.rootPath),
+    });
+    if (result.repos.length > 0) {
+      setProjects((prev) => {
+        const known = new Set(prev.map((p) => p.id));
+        const added: Project[] = [];
+        for (const repo of result.repos) {
+          const id = stableId('proj', repo.path);
+          if (known.has(id)) continue;
+          known.add(id);
+          added.push({
+            id,
+            name: repo.name,
+            rootPath: repo.path,
+            folders: [{
+              id: stableId('folder', repo.path),
+              name: repo.name,
+              path: repo.path,
+              branch: '',
+              diffStats: null,
+              pullRequest: null,
+              isActive: false,
+            }],
+            isExpanded: false,
+          });
+        }
+        return [...prev, ...added];
+      });
+    }
+    return result;
+  }, [projects, setProjects]);
+
   const addFolder = useCallback((projectId: string, path: string, name: string) => {
     const folderId = stableId('folder', path);
     const folder: ProjectFolder = {
@@ -215,6 +277,121 @@ export function useProjectExplorer(): UseProjectExplorerReturn {
     }
   }, []);
 
+  const setFolderBranch = useCallback((folderId: string, branch: string | null) => {
+    setProjects((prev) =>
+      prev.map((p) => ({
+        ...p,
+        folders: p.folders.map((f) =>
+          f.id === folderId && f.branch !== (branch ?? '') ? { ...f, branch: branch ?? '' } : f
+        ),
+      }))
+    );
+  }, [setProjects]);
+
+  // Register folder paths with the backend so git commands can resolve them,
+  // then pick up each folder's current branch.
+  const folderPathsKey = allFolders.map((f) => `${f.id}=${f.path}`).join('\n');
+  useEffect(() => {
+    const folders = allFolders.map((f) => ({ folderId: f.id, path: f.path }));
+    if (folders.length === 0) return;
+    invoke('folder_register_paths', { folders })
+      .then(() =>
+        Promise.all(
+          folders.map(({ folderId }) =>
+            invoke<string | null>('git_current_branch', { folderId })
+              .then((branch) => setFolderBranch(folderId, branch))
+              .catch(() => {
+                // Not a git repository.
+              })
+          )
+        )
+      )
+      .catch((e) => console.error('folder_register_paths error:', e));
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [folderPathsKey, setFolderBranch]);
+
+  useEffect(() => {
+    const unlisten = listen<{ folderId: string; branch: string | null }>(
+      'folder-branch-changed',
+      (event) => setFolderBranch(event.payload.folderId, event.payload.branch)
+    );
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [setFolderBranch]);
+
+  useEffect(() => {
+    invoke<Record<string, number>>('folder_activity')
+      .then(setLastUsedAt)
+      .catch((e) => console.error('folder_activity error:', e));
+    const unlisten = listen<{ lastUsedAt: Record<string, number> }>(
+      'folder-activity',
+      (event) => setLastUsedAt(event.payload.lastUsedAt)
+    );
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // Folders whose directory disappeared are flagged instead of failing later.
+  useEffect(() => {
+    const applyIssues = (issues: FolderIssue[]) =>
+      setMissingFolderIds(new Set(issues.map((issue) => issue.folderId)));
+    invoke<FolderIssue[]>('folder_check_paths')
+      .then(applyIssues)
+      .catch((e) => console.error('folder_check_paths error:', e));
+    const unlisten = listen<{ issues: FolderIssue[] }>(
+      'workspace-issues',
+      (event) => applyIssues(event.payload.issues)
+    );
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // Folders passed to a second launch (e.g. `neoai ~/code/app`) are opened here.
+  const openFolderPath = useCallback((path: string) => {
+    const name = path.split(/[\\/]/).filter(Boolean).pop() ?? path;
+    const folderId = stableId('folder', path);
+    if (!projects.some((p) => p.folders.some((f) => f.id === folderId))) {
+      addProject(path, name);
+    }
+    selectFolder({ id: folderId, name, path, branch: '', diffStats: null, pullRequest: null });
+  }, [projects, addProject, selectFolder]);
+
+  // Creates the directory from a template, then opens it like any other folder.
+  const createProjectFromTemplate = useCallback(async (templateId: string, path: string) => {
+    const created = await invoke<CreatedProject>('create_project_from_template', {
+      templateId,
+      path,
+      folderId: stableId('folder', path),
+    });
+    openFolderPath(path);
+    return created;
+  }, [openFolderPath]);
+
+  useEffect(() => {
+    const unlisten = listen<{ folders: string[] }>('instance-args', (event) => {
+      event.payload.folders.forEach(openFolderPath);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [openFolderPath]);
+
+  // Watch the active folder so file-changed events reach the UI.
+  const activeFolderPath = activeFolder?.path;
+  useEffect(() => {
+    if (!activeFolderId || !activeFolderPath) return;
+    const folderId = activeFolderId;
+    invoke('folder_register_paths', { folders: [{ folderId, path: activeFolderPath }] })
+      .then(() => invoke('watch_folder', { folderId }))
+      .catch((e) => console.error('watch_folder error:', e));
+    return () => {
+      invoke('unwatch_folder', { folderId }).catch(() => {});
+    };
+  }, [activeFolderId, activeFolderPath]);
+
   // Set initial focus to active folder
   useEffect(() => {
     if (activeFolder && !focusedFolderId) {
@@ -223,7 +400,7 @@ export function useProjectExplorer(): UseProjectExplorerReturn {
   }, [activeFolder, focusedFolderId]);
 
   return {
-    projects,
+    projects: sortedProjects,
     activeFolder,
     focusedFolderId,
     showHotkeys,
@@ -237,5 +414,7 @@ export function useProjectExplorer(): UseProjectExplorerReturn {
     addFolder,
     removeProject,
     removeFolder,
+    createProjectFromTemplate,
+    importProjects,
   };
 }
@@ -24,9 +24,9 @@ export function useTerminalManager() {
   }, []);
 
   const destroyTerminal = useCallback((termId: string) => {
-    // Clean up the Neovim socket file on the Rust side
-    invoke("remove_socket_path", { terminalId: termId }).catch((e) =>
-      console.error("remove_socket_path error:", e)
+    // Tear down the view, Neovim bridge, tmux session and socket on the Rust side
+    invoke("destroy_terminal", { terminalId: termId }).catch((e) =>
+      console.error("destroy_terminal error:", e)
     );
     setTerminals(prev => {
       const next = new Map(prev);
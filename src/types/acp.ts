@@ -1,12 +1,153 @@
-export type AcpEvent =
-  | { type: "contentChunk"; data: string }
-  | { type: "thoughtChunk"; data: string }
-  | { type: "toolCallStarted"; data: { id: string; title: string; kind: string } }
-  | { type: "toolCallUpdated"; data: { id: string; status: string } }
-  | { type: "done"; data: { stopReason: string } }
-  | { type: "error"; data: string };
+import type { NeoaiError } from "./errors";
 
-export type AgentStatus = "Stopped" | "Starting" | "Running" | { Error: string };
+/** The conversation an ACP event belongs to. */
+export type AcpEventScope = {
+  sessionId: string | null;
+  terminalId: string | null;
+};
+
+export type AcpEvent = AcpEventScope &
+  (
+    | { type: "contentChunk"; data: string }
+    | { type: "thoughtChunk"; data: string }
+    | { type: "toolCallStarted"; data: { id: string; title: string; kind: string } }
+    | { type: "toolCallUpdated"; data: { id: string; status: string } }
+    | { type: "toolCallDiff"; data: ToolCallDiff }
+    | { type: "done"; data: { turn: TurnMetadata; context: ContextReuseStats } }
+    | { type: "error"; data: AcpErrorEventData }
+    | { type: "permissionAutoApproved"; data: { id: string; title: string; kind: string } }
+  );
+
+/** Why a prompt failed, for choosing a recovery action. */
+export type AcpErrorKind =
+  | { code: "AGENT_UNAVAILABLE" }
+  | { code: "PROTOCOL_ERROR" }
+  | { code: "TOOL_CALL_FAILED"; id: string }
+  | { code: "PERMISSION_DENIED" }
+  | { code: "TIMEOUT" }
+  | { code: "OTHER" };
+
+/** A prompt that failed for good; retryable errors were retried first. */
+export type AcpErrorEventData = {
+  kind: AcpErrorKind;
+  message: string;
+  retryable: boolean;
+  attempts: number;
+  turn: TurnMetadata;
+};
+
+/** Suggested next step for a failed prompt, or null when there is none. */
+export function errorRecoveryHint(error: AcpErrorEventData): string | null {
+  switch (error.kind.code) {
+    case "AGENT_UNAVAILABLE":
+      return "Restart the agent and send the message again.";
+    case "PERMISSION_DENIED":
+      return "Sign in to the agent again or check its API key.";
+    case "TOOL_CALL_FAILED":
+      return `Tool call ${error.kind.id} failed; check its output before retrying.`;
+    case "TIMEOUT":
+      return "The agent took too long; try again.";
+    default:
+      return error.retryable ? "Try again in a moment." : null;
+  }
+}
+
+export type StopReason =
+  | "EndTurn"
+  | "MaxTokens"
+  | "MaxTurnRequests"
+  | "Refusal"
+  | "Cancelled"
+  | "Error";
+
+/** How a prompt turn ended; token counts are null when the agent doesn't report them. */
+export type TurnMetadata = {
+  stopReason: StopReason;
+  durationMs: number;
+  inputTokens: number | null;
+  outputTokens: number | null;
+  toolCalls: number;
+};
+
+/** Payload of `acp-command-queue`: agent commands waiting for a free slot. */
+export type AcpCommandQueueEvent = {
+  sessionId: string;
+  terminalId: string;
+  queued: number;
+  running: number;
+  limit: number;
+  /** No commands are left waiting. */
+  drained: boolean;
+};
+
+/** Payload of `acp-retrying`: a prompt is sent again after `delayMs`. */
+export type AcpRetryingEvent = {
+  sessionId: string;
+  terminalId: string | null;
+  attempt: number;
+  maxRetries: number;
+  delayMs: number;
+  error: string;
+};
+
+/** Payload of `acp-context-usage`: a session's estimated size crossed `threshold` percent of the context window. */
+export type AcpContextUsageEvent = {
+  sessionId: string;
+  terminalId: string | null;
+  tokens: number;
+  limit: number;
+  threshold: number;
+};
+
+/** One session's outcome of `acp_send_prompt_multi`: `stopReason` when the turn finished, `error` when it failed. */
+export type AcpMultiPromptResult = {
+  sessionId: string;
+  stopReason: StopReason | null;
+  error: NeoaiError | null;
+};
+
+/** How much of a turn's context was sent versus skipped as already sent. */
+export type ContextReuseStats = {
+  sentBlocks: number;
+  reusedBlocks: number;
+  sentTokens: number;
+  reusedTokens: number;
+};
+
+export type DiffLine = {
+  kind: "context" | "added" | "removed";
+  content: string;
+};
+
+export type DiffHunk = {
+  oldStart: number;
+  oldLines: number;
+  newStart: number;
+  newLines: number;
+  header: string;
+  lines: DiffLine[];
+};
+
+export type TextDiff = {
+  hunks: DiffHunk[];
+  additions: number;
+  deletions: number;
+  unified: string;
+};
+
+export type ToolCallDiff = {
+  id: string;
+  path: string;
+  diff: TextDiff;
+};
+
+export type AgentStatus =
+  | "Stopped"
+  | "Starting"
+  | "Running"
+  /** Alive but not answering health pings; may recover on its own. */
+  | "Unresponsive"
+  | { Error: string };
 
 export type AcpInstallPhase =
   | "resolving"
@@ -22,6 +163,8 @@ export type AcpInstallStatus = {
   phase: AcpInstallPhase;
   message: string;
   version?: string | null;
+  /** Archive URL while downloading and verifying; a mirror when one is configured. */
+  downloadUrl?: string | null;
 };
 
 export type AcpPermissionOptionKind =
@@ -44,4 +187,10 @@ export type AcpPermissionRequest = {
   title: string | null;
   kind: string | null;
   options: AcpPermissionOption[];
+  /** Shell command the tool call would run, as one line. */
+  command: string | null;
+  cwd: string | null;
+  /** Files the tool call reads or changes. */
+  paths: string[];
+  diffs: { path: string; diff: TextDiff }[];
 };
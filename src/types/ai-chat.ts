@@ -1,18 +1,67 @@
-import type { NvimContext, Diagnostic, BufferEdit } from "./nvim";
+import type { NvimContext, Diagnostic, BufferEdit, EditConflict } from "./nvim";
+import type { ToolCallDiff, TurnMetadata } from "./acp";
 
 export interface ChatMessage {
   id: string;
   role: "user" | "assistant" | "system";
   content: string;
   timestamp: number;
-  systemKind?: "action-summary" | "status-note";
+  systemKind?: "action-summary" | "status-note" | "summary";
   context?: NvimContext;
   diagnostics?: Diagnostic[];
   proposedEdits?: BufferEdit[];
-  editStatus?: "pending" | "applied" | "rejected";
+  /** File diffs reported by the agent's tool calls, computed by the backend. */
+  diffs?: ToolCallDiff[];
+  editStatus?: "pending" | "applied" | "rejected" | "conflict";
+  /** Ranges that changed since `proposedEdits` were made, when `editStatus` is `conflict`. */
+  editConflicts?: EditConflict[];
+  /** How the agent's turn ended, on assistant messages. */
+  turn?: TurnMetadata;
+  /** Replaced by a conversation summary; kept in storage but not shown. */
+  archived?: boolean;
+  /** The user's verdict on an assistant answer, also recorded by the backend. */
+  rating?: MessageRating;
+}
+
+export type MessageRating = "helpful" | "wrong";
+
+export interface RatedMessage {
+  messageId: string;
+  rating: MessageRating;
+  note: string | null;
+  folderId: string | null;
+  agentName: string | null;
+  ratedAt: number;
+}
+
+/** Result of `message_ratings_summary`. */
+export interface RatingsSummary {
+  helpful: number;
+  wrong: number;
+  byAgent: { agentName: string | null; helpful: number; wrong: number }[];
+  /** Newest first. */
+  recentWrong: RatedMessage[];
 }
 
 export interface AgentConfig {
   path: string;
   name?: string;
 }
+
+export interface ContextSection {
+  kind: "cursor" | "diagnostics" | "visibleBuffer" | "gitDiff" | "recentFiles";
+  label: string;
+  tokens: number;
+  truncated: boolean;
+}
+
+/** Prompt context assembled by the backend `build_context` command. */
+export interface BuiltContext {
+  text: string;
+  /** Rendered sections; the backend skips ones the agent already has. */
+  blocks: string[];
+  sections: ContextSection[];
+  omitted: string[];
+  estimatedTokens: number;
+  tokenBudget: number;
+}
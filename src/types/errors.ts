@@ -0,0 +1,37 @@
+/** Codes of errors returned by backend commands (`NeoaiError`). */
+export type NeoaiErrorCode =
+  | "NVIM_NOT_CONNECTED"
+  | "NVIM_CONNECT_FAILED"
+  | "NVIM_REQUEST_FAILED"
+  | "NVIM_BUSY"
+  | "AGENT_NOT_RUNNING"
+  | "AGENT_ALREADY_RUNNING"
+  | "AGENT_START_FAILED"
+  | "AGENT_WORKER_DIED"
+  | "SESSION_FAILED"
+  | "INVALID_WORKING_DIR"
+  | "PROMPT_FAILED"
+  | "CONTEXT_WINDOW_EXCEEDED"
+  | "PERMISSION_REQUEST_NOT_FOUND"
+  | "TMUX_FAILED"
+  | "GHOSTTY_FAILED"
+  | "INTERNAL";
+
+export interface NeoaiError {
+  code: NeoaiErrorCode;
+  message: string;
+  detail: string | null;
+}
+
+export function isNeoaiError(e: unknown, code?: NeoaiErrorCode): e is NeoaiError {
+  if (typeof e !== "object" || e === null) return false;
+  const err = e as Partial<NeoaiError>;
+  if (typeof err.code !== "string" || typeof err.message !== "string") return false;
+  return code === undefined || err.code === code;
+}
+
+/** Readable text for anything a command rejected with. */
+export function errorMessage(e: unknown): string {
+  if (isNeoaiError(e)) return e.detail ? `${e.message}: ${e.detail}` : e.message;
+  return String(e);
+}
@@ -0,0 +1,8 @@
+/** Payload of `focus-changed`: which side of the window owns keyboard input. */
+export interface FocusChangedEvent {
+  owner: "chat" | "terminal";
+  terminalId: string | null;
+  paneId: string | null;
+  /** `owner` is kept while the window is in the background. */
+  windowFocused: boolean;
+}
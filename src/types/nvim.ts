@@ -10,8 +10,28 @@ export interface NvimContext {
   bufferId: number;
   lineCount: number;
   modified: boolean;
+  changedtick: number;
   visibleLines: string[];
   visibleRange: [number, number];
+  /** `terminal` when the buffer is a `:terminal`; `visibleLines` are then its latest output. */
+  bufferKind: "file" | "terminal";
+  terminal: TerminalBufferInfo | null;
+  /** Closed folds in `visibleRange`, each shown in `visibleLines` as its fold text. */
+  folds: FoldSummary[];
+}
+
+/** A closed fold and its `foldtextresult()`. */
+export interface FoldSummary {
+  start: number;
+  end: number;
+  text: string;
+}
+
+/** What a `:terminal` buffer runs. */
+export interface TerminalBufferInfo {
+  title: string;
+  command: string;
+  running: boolean;
 }
 
 export interface Diagnostic {
@@ -22,6 +42,98 @@ export interface Diagnostic {
   source: string;
 }
 
+export interface DiffLine {
+  kind: "context" | "added" | "removed";
+  content: string;
+}
+
+export interface DiffHunk {
+  oldStart: number;
+  oldLines: number;
+  newStart: number;
+  newLines: number;
+  /** Text after the closing `@@`, usually the enclosing function. */
+  header: string;
+  lines: DiffLine[];
+}
+
+/** Changes to the current buffer against the git index. */
+export interface BufferHunks {
+  filePath: string;
+  /** `gitsigns` includes unsaved changes; `git` diffs the file on disk. */
+  source: "gitsigns" | "git";
+  hunks: DiffHunk[];
+}
+
+/** Project roots Neovim knows for the current buffer. */
+export interface WorkspaceRoots {
+  filePath: string;
+  /** Workspace folders of the buffer's LSP clients. */
+  lspFolders: string[];
+  /** Nearest directory above the file with a root marker such as `.git` or `Cargo.toml`. */
+  markerRoot: string | null;
+  marker: string | null;
+  cwd: string;
+  /** Suggested session directory: innermost LSP folder holding the file, else `markerRoot`, else `cwd`. */
+  root: string;
+}
+
+export type WindowPlacement = "left" | "right" | "above" | "below";
+
+export interface WindowLayout {
+  id: number;
+  number: number;
+  /** The tabpage's active window. */
+  current: boolean;
+  floating: boolean;
+  bufferId: number;
+  filePath: string;
+  fileType: string;
+  /** Empty for a file buffer. */
+  bufType: string;
+  modified: boolean;
+  cursor: CursorPosition;
+  /** Screen position (0-based) and size in cells. */
+  row: number;
+  col: number;
+  width: number;
+  height: number;
+  /** Side of the tabpage's active window; null for it and for floating windows. */
+  placement: WindowPlacement | null;
+}
+
+export interface TabpageLayout {
+  number: number;
+  current: boolean;
+  windows: WindowLayout[];
+}
+
+export interface NvimLayout {
+  columns: number;
+  lines: number;
+  tabpages: TabpageLayout[];
+}
+
+export interface SlowBridgeCall {
+  op: string;
+  durationMs: number;
+  finishedAtMs: number;
+}
+
+/** Latency of one terminal's bridge calls, from `nvim_bridge_stats`. */
+export interface TerminalBridgeStats {
+  terminalId: string;
+  calls: number;
+  slowCalls: number;
+  p50Ms: number;
+  p95Ms: number;
+  maxMs: number;
+  /** Newest first. */
+  recentSlow: SlowBridgeCall[];
+  /** Calls that haven't finished yet, longest waiting first. */
+  pending: { op: string; elapsedMs: number }[];
+}
+
 export interface BufferContent {
   filePath: string;
   lines: string[];
@@ -34,6 +146,38 @@ export interface BufferEdit {
   newLines: string[];
   filePath?: string;
   targetLine?: number;
+  /** What the range held when the edit was proposed; applying fails if it changed. */
+  expectedLines?: string[];
+  /** The buffer's `changedtick` when proposed, for edits without `expectedLines`. */
+  changedtick?: number;
+}
+
+/** An edit whose range changed after it was proposed. */
+export interface EditConflict {
+  filePath: string | null;
+  startLine: number;
+  endLine: number;
+  currentLines: string[];
+  changedtick: number;
+}
+
+/** When any edit conflicts, none are applied. */
+export interface ApplyEditsResult {
+  applied: boolean;
+  conflicts: EditConflict[];
+}
+
+/** One step of `nvim_exec_batch`. */
+export type BridgeOp =
+  | { op: "openFile"; filePath: string }
+  | { op: "setCursor"; line: number; col?: number }
+  | ({ op: "applyEdit" } & BufferEdit)
+  | { op: "write"; filePath?: string };
+
+export interface BatchResult {
+  applied: number;
+  filePath: string;
+  line: number;
 }
 
 /** Simple status for UI display. The backend ConnectionStatus enum is richer
@@ -42,6 +186,20 @@ export type ConnectionStatus = "Connected" | "Disconnected" | "Error";
 
 export type KeymapStatus = "unknown" | "present" | "missing" | "error";
 
+export type KeymapBindingStatus = "mapped" | "overridden" | "relocated" | "skipped";
+
+/** How one `<leader>m` keymap was bound, per `[keymaps] on_conflict`. */
+export interface KeymapBinding {
+  mode: string;
+  lhs: string;
+  action: string;
+  status: KeymapBindingStatus;
+  /** Null when skipped. */
+  boundTo: string | null;
+  /** The mapping that was already on `lhs`. */
+  existing: string | null;
+}
+
 export interface NvimHealth {
   connected: boolean;
   channelId: number | null;
@@ -117,6 +275,25 @@ export interface NvimCursorFollowEvent {
   source: string;
 }
 
+export interface BreadcrumbSymbol {
+  name: string;
+  /** `function`, `method`, `class`, `struct`, `impl`, `module`, ... */
+  kind: string;
+}
+
+/** Cursor location for the header; also the `nvim-breadcrumbs` payload. */
+export interface Breadcrumbs {
+  terminalId: string;
+  project: string | null;
+  filePath: string;
+  relativePath: string | null;
+  line: number;
+  /** Outermost scope first. */
+  symbols: BreadcrumbSymbol[];
+  symbolSource: "lsp" | "treesitter" | null;
+  branch: string | null;
+}
+
 export interface TmuxStatus {
   terminalId: string;
   available: boolean;
@@ -126,6 +303,30 @@ export interface TmuxStatus {
   error?: string;
 }
 
+/** A command the agent ran in a tmux pane, from `tmux_list_commands`. */
+export interface TmuxCommandInfo {
+  commandId: string;
+  terminalId: string;
+  paneId: string;
+  /** Window/pane name, e.g. "cargo test". */
+  title: string;
+  command: string;
+  /** Directory it was started in. */
+  cwd: string | null;
+  running: boolean;
+  /** The agent released it and its pane is gone. */
+  released: boolean;
+  exitCode: number | null;
+  startedAtMs: number;
+  durationMs: number;
+}
+
+export interface SocketAllocation {
+  path: string;
+  /** True when this terminal's Neovim is already listening on `path`. */
+  reused: boolean;
+}
+
 export interface NvimStartLaunchResult {
   launchMode: "tmux" | "direct" | "tmuxUnavailable";
   sessionName?: string;
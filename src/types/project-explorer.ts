@@ -19,6 +19,10 @@ export interface ProjectFolder {
   diffStats: GitDiffStats | null;
   pullRequest: PullRequest | null;
   isActive?: boolean;
+  /** The folder's path was gone at the last check (see `workspace-issues`). */
+  missing?: boolean;
+  /** Position in the stored project order, which cmd+1–9 follow. */
+  hotkeyIndex?: number;
 }
 
 export interface Project {
@@ -28,3 +32,52 @@ export interface Project {
   folders: ProjectFolder[];
   isExpanded?: boolean;
 }
+
+export type FolderIssueKind = 'missing' | 'notADirectory' | 'unreadable';
+
+export interface FolderIssue {
+  folderId: string;
+  path: string;
+  kind: FolderIssueKind;
+}
+
+export interface ImportedRepo {
+  path: string;
+  name: string;
+}
+
+export interface ProjectImport {
+  repos: ImportedRepo[];
+  alreadyAdded: number;
+  truncated: boolean;
+}
+
+export interface TmuxWindowSpec {
+  name: string;
+  command: string;
+}
+
+export interface ProjectTemplate {
+  id: string;
+  name: string;
+  initCommands: string[];
+  windows: TmuxWindowSpec[];
+  agent: string | null;
+  model: string | null;
+  preamble: string | null;
+  updatedAt: number | null;
+}
+
+export interface InitCommandResult {
+  command: string;
+  success: boolean;
+  exitCode: number | null;
+  output: string;
+}
+
+export interface CreatedProject {
+  folderId: string;
+  path: string;
+  init: InitCommandResult[];
+  preamble: string | null;
+}